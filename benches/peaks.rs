@@ -0,0 +1,76 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rs_algo_shared::scanner::peak::Peaks;
+
+fn set_env() {
+    for (key, val) in [
+        ("LOGARITHMIC_SCANNER", "false"),
+        ("LOCAL_MIN_PROMINENCE", "0.01"),
+        ("LOCAL_PROMINENCE_MIN_DISTANCE", "5"),
+        ("EXTREMA_MIN_PROMINENCE", "0.1"),
+        ("EXTREMA_PROMINENCE_MIN_DISTANCE", "5"),
+        ("KERNEL_PRICE_SMOOTHING", "true"),
+        ("KERNEL_REGRESSION_BANDWIDTH", "0.05"),
+        ("PRICE_SOURCE", "close"),
+        ("PEAKS_INCREMENTAL_WINDOW", "200"),
+    ] {
+        std::env::set_var(key, val);
+    }
+}
+
+fn price(idx: usize) -> f64 {
+    100. + (idx as f64 / 7.).sin() * 5. + (idx as f64 / 31.).cos() * 10.
+}
+
+fn push(peaks: &mut Peaks, idx: usize) {
+    let close = price(idx);
+    peaks.highs.push(close + 0.5);
+    peaks.lows.push(close - 0.5);
+    peaks.close.push(close);
+}
+
+/// Peaks of `len` bars, already calculated in incremental mode so the kernel
+/// sums are warm.
+fn warm_peaks(len: usize) -> Peaks {
+    let mut peaks = Peaks::new().logarithmic(false);
+    for idx in 0..len {
+        push(&mut peaks, idx);
+    }
+    peaks.calculate_peaks_incremental(&115., &85.).unwrap();
+    peaks
+}
+
+fn next_candle(c: &mut Criterion) {
+    set_env();
+    let mut group = c.benchmark_group("peaks_next_candle");
+
+    for len in [500, 2000] {
+        let peaks = warm_peaks(len);
+
+        group.bench_with_input(BenchmarkId::new("full", len), &peaks, |b, peaks| {
+            b.iter_batched(
+                || peaks.clone(),
+                |mut peaks| {
+                    push(&mut peaks, len);
+                    peaks.calculate_peaks(&115., &85., &0).unwrap();
+                },
+                BatchSize::SmallInput,
+            )
+        });
+
+        group.bench_with_input(BenchmarkId::new("incremental", len), &peaks, |b, peaks| {
+            b.iter_batched(
+                || peaks.clone(),
+                |mut peaks| {
+                    push(&mut peaks, len);
+                    peaks.calculate_peaks_incremental(&115., &85.).unwrap();
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, next_candle);
+criterion_main!(benches);
@@ -0,0 +1,58 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rs_algo_shared::broker::VEC_DOHLC;
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::ws::compression::Compression;
+use rs_algo_shared::ws::encoding::Encoding;
+use rs_algo_shared::ws::message::*;
+
+const START: i64 = 1_672_653_600;
+
+/// GetInstrumentData response of `len` one minute candles.
+fn instrument_data(len: usize) -> Message {
+    let data: VEC_DOHLC = (0..len)
+        .map(|idx| {
+            let close = 1.1 + (idx as f64 / 7.).sin() * 0.005;
+            (
+                Local.timestamp_opt(START + idx as i64 * 60, 0).unwrap(),
+                close - 0.0002,
+                close + 0.0004,
+                close - 0.0005,
+                close,
+                1000. + idx as f64,
+            )
+        })
+        .collect();
+
+    let msg = ResponseBody {
+        response: ResponseType::GetInstrumentData,
+        payload: Some(InstrumentData {
+            symbol: "EURUSD".to_owned(),
+            time_frame: TimeFrameType::M1,
+            data,
+            gaps: vec![],
+        }),
+    };
+    Encoding::Json.encode(&msg).unwrap()
+}
+
+fn compress(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ws_compression");
+
+    for len in [1000, 5000] {
+        let msg = instrument_data(len);
+        let compressed = Compression::Deflate.compress(msg.clone()).unwrap();
+        group.throughput(Throughput::Bytes(msg.len() as u64));
+        group.bench_with_input(BenchmarkId::new("deflate", len), &msg, |b, msg| {
+            b.iter(|| Compression::Deflate.compress(msg.clone()).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("inflate", len), &compressed, |b, msg| {
+            b.iter(|| Compression::Deflate.decompress(msg.clone()).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, compress);
+criterion_main!(benches);
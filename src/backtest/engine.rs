@@ -0,0 +1,556 @@
+use crate::helpers::date::*;
+use crate::helpers::uuid::IdGenerator;
+use crate::models::backtest_instrument::{
+    resolve_open_positions, OpenPosition, OpenPositionPolicy,
+};
+use crate::models::commission::CommissionModel;
+use crate::models::currency::CurrencyConverter;
+use crate::models::equity::Equity;
+use crate::models::exposure::{margin_rate, Exposure};
+use crate::models::order::*;
+use crate::models::position_sizer::{position_sizer_from_env, BoxedSizer, FixedSize, Sizing};
+use crate::models::pricing::Pricing;
+use crate::models::risk::{account_equity, RiskEvent, RiskLimits, RiskManager};
+use crate::models::session::session_open_gap;
+use crate::models::strategy::{Signal, Strategy};
+use crate::models::trade::*;
+use crate::scanner::instrument::{HTFInstrument, Instrument};
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackTestConfig {
+    pub equity: f64,
+    /// Size of the entries of engines without a position sizer
+    pub order_size: f64,
+    /// Bars skipped before the strategy is evaluated
+    pub warm_up: usize,
+    pub commission: CommissionModel,
+    pub open_positions: OpenPositionPolicy,
+    #[serde(default)]
+    pub risk: RiskLimits,
+}
+
+impl BackTestConfig {
+    /// Reads ACCOUNT_EQUITY, ORDER_SIZE, BACKTEST_WARM_UP, the commission
+    /// model, BACKTEST_OPEN_POSITIONS and the risk limits
+    pub fn from_env() -> Self {
+        Self {
+            equity: account_equity(),
+            order_size: env::var("ORDER_SIZE")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(0.),
+            warm_up: env::var("BACKTEST_WARM_UP")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(0),
+            commission: CommissionModel::from_env(),
+            open_positions: OpenPositionPolicy::from_env(),
+            risk: RiskLimits::from_env(),
+        }
+    }
+
+    pub fn equity(mut self, val: f64) -> Self {
+        self.equity = val;
+        self
+    }
+
+    pub fn order_size(mut self, val: f64) -> Self {
+        self.order_size = val;
+        self
+    }
+
+    pub fn warm_up(mut self, val: usize) -> Self {
+        self.warm_up = val;
+        self
+    }
+
+    pub fn commission(mut self, val: CommissionModel) -> Self {
+        self.commission = val;
+        self
+    }
+
+    pub fn open_positions(mut self, val: OpenPositionPolicy) -> Self {
+        self.open_positions = val;
+        self
+    }
+
+    pub fn risk(mut self, val: RiskLimits) -> Self {
+        self.risk = val;
+        self
+    }
+}
+
+impl Default for BackTestConfig {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// Account equity at the close of the bar, open trades marked to market.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EquityPoint {
+    pub index: usize,
+    pub date: DbDateTime,
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackTestResult {
+    pub symbol: String,
+    pub strategy: String,
+    pub initial_equity: f64,
+    pub trades_in: Vec<TradeIn>,
+    pub trades_out: Vec<TradeOut>,
+    pub orders: Vec<Order>,
+    pub open_positions: Vec<OpenPosition>,
+    pub commissions: f64,
+    pub equity_curve: Vec<EquityPoint>,
+    /// Entry signals and orders that were not allowed to open a trade
+    #[serde(default)]
+    pub rejected_entries: usize,
+    #[serde(default)]
+    pub risk_events: Vec<RiskEvent>,
+}
+
+impl BackTestResult {
+    pub fn final_equity(&self) -> f64 {
+        match self.equity_curve.last() {
+            Some(point) => point.equity,
+            None => self.initial_equity,
+        }
+    }
+
+    pub fn net_profit(&self) -> f64 {
+        self.final_equity() - self.initial_equity
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct State {
+    pub(crate) trades_in: Vec<TradeIn>,
+    pub(crate) trades_out: Vec<TradeOut>,
+    pub(crate) orders: Vec<Order>,
+    pub(crate) open_trade: Option<TradeIn>,
+    pub(crate) equity: Equity,
+    pub(crate) rejected_entries: usize,
+    pub(crate) ids: IdGenerator,
+}
+
+impl State {
+    pub(crate) fn new(equity: f64) -> Self {
+        Self {
+            trades_in: vec![],
+            trades_out: vec![],
+            orders: vec![],
+            open_trade: None,
+            equity: Equity::new(equity),
+            rejected_entries: 0,
+            ids: IdGenerator::from_env(),
+        }
+    }
+
+    /// Marks the open trade at the close price
+    pub(crate) fn mark(&mut self, close: f64, pricing: &Pricing, conversion_rate: f64) {
+        self.equity
+            .mark(self.open_trade.as_ref(), close, pricing, conversion_rate);
+    }
+}
+
+/// Runs a strategy over the instrument bars with the same semantics as the
+/// bot: signals and orders of a bar are executed on the open of the next
+/// one. Needs EXECUTION_MODE=BackTest, slippage and spread come from their
+/// env models. One position is open at a time. The strategy gets the
+/// account equity before the signals of every bar and entries are sized
+/// from it by the position sizer.
+#[derive(Debug, Clone)]
+pub struct BackTestEngine {
+    config: BackTestConfig,
+    sizer: BoxedSizer,
+}
+
+impl BackTestEngine {
+    /// Entries are sized with the order size of the config
+    pub fn new(config: BackTestConfig) -> Self {
+        let sizer = Box::new(FixedSize(config.order_size));
+        Self { config, sizer }
+    }
+
+    pub fn sizer(mut self, val: BoxedSizer) -> Self {
+        self.sizer = val;
+        self
+    }
+
+    pub fn config(&self) -> &BackTestConfig {
+        &self.config
+    }
+
+    /// Size of an entry on the close of the bar without a stop
+    pub(crate) fn entry_size(&self, index: usize, instrument: &Instrument, equity: &Equity) -> f64 {
+        Sizing::new(self.sizer.as_ref(), equity.value())
+            .size(index, instrument, instrument.data()[index].close(), None)
+            .unwrap_or(self.config.order_size)
+    }
+
+    pub fn run(
+        &self,
+        strategy: &dyn Strategy,
+        instrument: &Instrument,
+        htf_instrument: &HTFInstrument,
+        pricing: &Pricing,
+    ) -> BackTestResult {
+        let data = instrument.data();
+        let conversion_rate = CurrencyConverter::from_env().rate(instrument.symbol());
+        let mut strategy = dyn_clone::clone_box(strategy);
+        let mut state = State::new(self.config.equity);
+        let mut risk = RiskManager::new(self.config.risk.clone());
+        let mut equity_curve = vec![];
+
+        for (index, candle) in data.iter().enumerate().skip(self.config.warm_up) {
+            state.mark(candle.close(), pricing, conversion_rate);
+            risk.update(candle.date(), &state.equity);
+            equity_curve.push(EquityPoint {
+                index,
+                date: to_dbtime(candle.date()),
+                equity: state.equity.value(),
+            });
+
+            if index + 1 < data.len() {
+                strategy.update_equity(&state.equity);
+                self.next(
+                    index,
+                    strategy.as_ref(),
+                    instrument,
+                    htf_instrument,
+                    pricing,
+                    &mut |index: usize, state: &State| {
+                        let exposure = self.exposure(instrument, state);
+                        self.risk_allows(&mut risk, index, instrument, &state.equity, &[exposure])
+                    },
+                    &mut state,
+                );
+            }
+        }
+
+        self.finish(
+            strategy.as_ref(),
+            instrument,
+            state,
+            equity_curve,
+            risk.take_events(),
+        )
+    }
+
+    /// Open trades and pending entries of the instrument
+    pub(crate) fn exposure(&self, instrument: &Instrument, state: &State) -> Exposure {
+        Exposure::new(
+            instrument.symbol(),
+            &state.trades_in,
+            &state.trades_out,
+            &state.orders,
+            margin_rate(),
+        )
+    }
+
+    /// Whether the risk limits allow an entry on the bar
+    pub(crate) fn risk_allows(
+        &self,
+        risk: &mut RiskManager,
+        index: usize,
+        instrument: &Instrument,
+        equity: &Equity,
+        exposures: &[Exposure],
+    ) -> bool {
+        risk.check_entry(
+            instrument.data()[index].date(),
+            instrument.symbol(),
+            equity,
+            exposures,
+            self.entry_size(index, instrument, equity),
+        )
+    }
+
+    /// Resolves the positions left open and fixes up the last equity point
+    pub(crate) fn finish(
+        &self,
+        strategy: &dyn Strategy,
+        instrument: &Instrument,
+        mut state: State,
+        mut equity_curve: Vec<EquityPoint>,
+        risk_events: Vec<RiskEvent>,
+    ) -> BackTestResult {
+        let open_positions = match instrument.data().is_empty() {
+            true => vec![],
+            false => {
+                let num_trades_out = state.trades_out.len();
+                let open_positions = resolve_open_positions(
+                    instrument,
+                    &mut state.trades_in,
+                    &mut state.trades_out,
+                    &self.config.open_positions,
+                    &mut state.ids,
+                );
+
+                for trade_out in state.trades_out.iter().skip(num_trades_out) {
+                    state.equity.realize(trade_out.profit);
+                    if let Some(trade_in) = &state.open_trade {
+                        state.equity.charge(
+                            self.config
+                                .commission
+                                .commission(trade_in.quantity, trade_out.price_out),
+                        );
+                    }
+                }
+
+                let unrealized: f64 = open_positions
+                    .iter()
+                    .filter(|x| x.policy == OpenPositionPolicy::MarkOpen)
+                    .map(|x| x.unrealized_profit)
+                    .sum();
+
+                if let Some(point) = equity_curve.last_mut() {
+                    point.equity = state.equity.balance() + unrealized;
+                }
+                open_positions
+            }
+        };
+
+        BackTestResult {
+            symbol: instrument.symbol().to_owned(),
+            strategy: strategy.name().to_owned(),
+            initial_equity: self.config.equity,
+            trades_in: state.trades_in,
+            trades_out: state.trades_out,
+            orders: state.orders,
+            open_positions,
+            commissions: state.equity.commissions(),
+            equity_curve,
+            rejected_entries: state.rejected_entries,
+            risk_events,
+        }
+    }
+
+    /// Runs the bar. New trades and entry orders the strategy asks for go
+    /// through `entries` first, exits always run.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn next(
+        &self,
+        index: usize,
+        strategy: &dyn Strategy,
+        instrument: &Instrument,
+        htf_instrument: &HTFInstrument,
+        pricing: &Pricing,
+        entries: &mut dyn FnMut(usize, &State) -> bool,
+        state: &mut State,
+    ) {
+        cancel_pending_expired_orders(index, instrument, None, &mut state.orders);
+        if let Some(gap) = session_open_gap(index, instrument) {
+            cancel_gapped_orders(instrument, &gap, &mut state.orders);
+        }
+
+        match resolve_active_orders(index, instrument, &state.orders, pricing) {
+            Position::MarketInOrder(order) if state.open_trade.is_none() => {
+                if self.allow_entry(index, entries, state) {
+                    self.trade_in(
+                        index,
+                        instrument,
+                        pricing,
+                        &order.to_trade_type(),
+                        Some(&order),
+                        state,
+                    );
+                }
+            }
+            Position::MarketOutOrder(order) if state.open_trade.is_some() => {
+                self.trade_out(
+                    index,
+                    instrument,
+                    pricing,
+                    &order.to_trade_type(),
+                    Some(&order),
+                    state,
+                );
+            }
+            _ => (),
+        }
+
+        let signals = match &state.open_trade {
+            Some(trade_in) => match trade_in.trade_type.is_long() {
+                true => vec![Signal::ExitLong],
+                false => vec![Signal::ExitShort],
+            },
+            None => vec![Signal::EntryLong, Signal::EntryShort],
+        };
+
+        for signal in signals {
+            let position = strategy.signal(signal, index, instrument, htf_instrument, pricing);
+            let trade_type = match (signal, &position) {
+                (Signal::EntryLong, Position::Order(_)) => TradeType::OrderInLong,
+                (Signal::EntryShort, Position::Order(_)) => TradeType::OrderInShort,
+                (Signal::EntryLong, _) => TradeType::MarketInLong,
+                (Signal::EntryShort, _) => TradeType::MarketInShort,
+                (Signal::ExitLong, _) => TradeType::MarketOutLong,
+                (Signal::ExitShort, _) => TradeType::MarketOutShort,
+            };
+
+            let is_entry = matches!(position, Position::MarketIn(_) | Position::Order(_));
+            if signal.is_entry() && is_entry && !self.allow_entry(index, entries, state) {
+                break;
+            }
+
+            let executed = match position {
+                Position::MarketIn(order_types) if signal.is_entry() => {
+                    let executed =
+                        self.trade_in(index, instrument, pricing, &trade_type, None, state);
+                    if let (true, Some(order_types)) = (executed, order_types) {
+                        self.place_orders(
+                            index,
+                            instrument,
+                            pricing,
+                            &trade_type,
+                            &order_types,
+                            state,
+                        );
+                    }
+                    executed
+                }
+                Position::MarketOut(_) if !signal.is_entry() => {
+                    self.trade_out(index, instrument, pricing, &trade_type, None, state)
+                }
+                Position::Order(order_types) if signal.is_entry() => {
+                    self.place_orders(index, instrument, pricing, &trade_type, &order_types, state);
+                    true
+                }
+                _ => false,
+            };
+
+            if executed {
+                break;
+            }
+        }
+    }
+
+    fn allow_entry(
+        &self,
+        index: usize,
+        entries: &mut dyn FnMut(usize, &State) -> bool,
+        state: &mut State,
+    ) -> bool {
+        let allowed = entries(index, state);
+        if !allowed {
+            state.rejected_entries += 1;
+        }
+        allowed
+    }
+
+    fn trade_in(
+        &self,
+        index: usize,
+        instrument: &Instrument,
+        pricing: &Pricing,
+        trade_type: &TradeType,
+        order: Option<&Order>,
+        state: &mut State,
+    ) -> bool {
+        match resolve_trade_in(
+            index,
+            Sizing::new(self.sizer.as_ref(), state.equity.value()),
+            instrument,
+            pricing,
+            trade_type,
+            order,
+            &mut state.ids,
+        ) {
+            TradeResult::TradeIn(trade_in) => {
+                if let Some(order) = order {
+                    fulfill_trade_order(index, &trade_in, order, &mut state.orders);
+                }
+                state.equity.charge(
+                    self.config
+                        .commission
+                        .commission(trade_in.quantity, trade_in.price_in),
+                );
+                state.trades_in.push(trade_in.clone());
+                state.open_trade = Some(trade_in);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn trade_out(
+        &self,
+        index: usize,
+        instrument: &Instrument,
+        pricing: &Pricing,
+        trade_type: &TradeType,
+        order: Option<&Order>,
+        state: &mut State,
+    ) -> bool {
+        let trade_in = match &state.open_trade {
+            Some(trade_in) => trade_in.clone(),
+            None => return false,
+        };
+
+        match resolve_trade_out(
+            index,
+            instrument,
+            pricing,
+            &trade_in,
+            trade_type,
+            order,
+            &mut state.ids,
+        ) {
+            TradeResult::TradeOut(trade_out) => {
+                if let Some(order) = order {
+                    fulfill_trade_order(index, &trade_out, order, &mut state.orders);
+                }
+                cancel_trade_pending_orders(&trade_out, &mut state.orders);
+                state.equity.realize(trade_out.profit);
+                state.equity.charge(
+                    self.config
+                        .commission
+                        .commission(trade_in.quantity, trade_out.price_out),
+                );
+                state.trades_out.push(trade_out);
+                state.open_trade = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn place_orders(
+        &self,
+        index: usize,
+        instrument: &Instrument,
+        pricing: &Pricing,
+        trade_type: &TradeType,
+        order_types: &Vec<OrderType>,
+        state: &mut State,
+    ) {
+        let sizing = Sizing::new(self.sizer.as_ref(), state.equity.value());
+        match prepare_orders(
+            index,
+            sizing,
+            instrument,
+            pricing,
+            trade_type,
+            order_types,
+            &mut state.ids,
+        ) {
+            Ok(new_orders) => {
+                state.orders = add_pending(std::mem::take(&mut state.orders), new_orders);
+            }
+            Err(err) => log::error!("Can't place orders {}", err),
+        }
+    }
+}
+
+impl Default for BackTestEngine {
+    fn default() -> Self {
+        Self::new(BackTestConfig::default()).sizer(position_sizer_from_env())
+    }
+}
@@ -0,0 +1,6 @@
+pub mod engine;
+pub mod monte_carlo;
+#[cfg(feature = "optimize")]
+pub mod optimize;
+pub mod portfolio;
+pub mod stats;
@@ -0,0 +1,227 @@
+use super::engine::BackTestResult;
+use super::stats::drawdown;
+use crate::helpers::random::SeededRng;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Resampling {
+    /// Same trades in a random order
+    Shuffle,
+    /// Same number of trades drawn with replacement
+    Bootstrap,
+    /// Random order with N trades left out
+    Skip(usize),
+}
+
+impl Resampling {
+    /// MONTE_CARLO_METHOD is "shuffle" (default), "bootstrap" or "skip",
+    /// with MONTE_CARLO_SKIP trades left out
+    pub fn from_env() -> Self {
+        match env::var("MONTE_CARLO_METHOD").unwrap_or_default().as_ref() {
+            "bootstrap" => Resampling::Bootstrap,
+            "skip" => Resampling::Skip(
+                env::var("MONTE_CARLO_SKIP")
+                    .ok()
+                    .and_then(|val| val.parse::<usize>().ok())
+                    .unwrap_or(1),
+            ),
+            _ => Resampling::Shuffle,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MonteCarloConfig {
+    pub runs: usize,
+    pub resampling: Resampling,
+    pub seed: u64,
+}
+
+impl MonteCarloConfig {
+    /// Reads MONTE_CARLO_RUNS, the resampling method and MONTE_CARLO_SEED
+    pub fn from_env() -> Self {
+        Self {
+            runs: env::var("MONTE_CARLO_RUNS")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(1000),
+            resampling: Resampling::from_env(),
+            seed: env::var("MONTE_CARLO_SEED")
+                .ok()
+                .and_then(|val| val.parse::<u64>().ok())
+                .unwrap_or(0),
+        }
+    }
+
+    pub fn runs(mut self, val: usize) -> Self {
+        self.runs = val;
+        self
+    }
+
+    pub fn resampling(mut self, val: Resampling) -> Self {
+        self.resampling = val;
+        self
+    }
+
+    pub fn seed(mut self, val: u64) -> Self {
+        self.seed = val;
+        self
+    }
+}
+
+impl Default for MonteCarloConfig {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Distribution {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// Sorted run values
+    pub values: Vec<f64>,
+}
+
+impl Distribution {
+    pub fn new(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / values.len() as f64;
+
+        Self {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean,
+            std_dev: variance.sqrt(),
+            values,
+        }
+    }
+
+    /// Nearest rank percentile, `per` in [0, 100]
+    pub fn percentile(&self, per: f64) -> f64 {
+        match self.values.is_empty() {
+            true => 0.,
+            false => {
+                let rank = (per.clamp(0., 100.) / 100. * (self.values.len() - 1) as f64).round();
+                self.values[rank as usize]
+            }
+        }
+    }
+
+    pub fn median(&self) -> f64 {
+        self.percentile(50.)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct MonteCarloResult {
+    pub runs: usize,
+    pub initial_equity: f64,
+    pub final_equity: Distribution,
+    pub max_drawdown: Distribution,
+    pub max_drawdown_per: Distribution,
+    /// Percentage of runs ending below the initial equity
+    pub loss_probability: f64,
+}
+
+/// Resamples the trade sequence of a backtest to see how much of its result
+/// depends on the order and selection of the trades.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarlo {
+    config: MonteCarloConfig,
+}
+
+impl MonteCarlo {
+    pub fn new(config: MonteCarloConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &MonteCarloConfig {
+        &self.config
+    }
+
+    /// Trade profits are taken net of the average commission.
+    pub fn from_result(&self, result: &BackTestResult) -> MonteCarloResult {
+        let commission = match result.trades_out.is_empty() {
+            true => 0.,
+            false => result.commissions / result.trades_out.len() as f64,
+        };
+        let profits: Vec<f64> = result
+            .trades_out
+            .iter()
+            .map(|x| x.profit - commission)
+            .collect();
+
+        self.run(result.initial_equity, &profits)
+    }
+
+    pub fn run(&self, initial_equity: f64, profits: &[f64]) -> MonteCarloResult {
+        let mut rng = SeededRng::new(self.config.seed);
+        let mut final_equities = Vec::with_capacity(self.config.runs);
+        let mut drawdowns = Vec::with_capacity(self.config.runs);
+        let mut drawdowns_per = Vec::with_capacity(self.config.runs);
+
+        for _ in 0..self.config.runs {
+            let sequence = self.resample(profits, &mut rng);
+            let equities = sequence.iter().scan(initial_equity, |equity, profit| {
+                *equity += profit;
+                Some(*equity)
+            });
+            let (max_drawdown, max_drawdown_per) =
+                drawdown(std::iter::once(initial_equity).chain(equities));
+
+            final_equities.push(initial_equity + sequence.iter().sum::<f64>());
+            drawdowns.push(max_drawdown);
+            drawdowns_per.push(max_drawdown_per);
+        }
+
+        let loss_probability = match final_equities.is_empty() {
+            true => 0.,
+            false => {
+                final_equities
+                    .iter()
+                    .filter(|x| **x < initial_equity)
+                    .count() as f64
+                    / final_equities.len() as f64
+                    * 100.
+            }
+        };
+
+        MonteCarloResult {
+            runs: self.config.runs,
+            initial_equity,
+            final_equity: Distribution::new(final_equities),
+            max_drawdown: Distribution::new(drawdowns),
+            max_drawdown_per: Distribution::new(drawdowns_per),
+            loss_probability,
+        }
+    }
+
+    fn resample(&self, profits: &[f64], rng: &mut SeededRng) -> Vec<f64> {
+        match self.config.resampling {
+            Resampling::Shuffle => {
+                let mut sequence = profits.to_vec();
+                rng.shuffle(&mut sequence);
+                sequence
+            }
+            Resampling::Bootstrap => (0..profits.len())
+                .map(|_| profits[rng.below(profits.len())])
+                .collect(),
+            Resampling::Skip(num) => {
+                let mut sequence = profits.to_vec();
+                rng.shuffle(&mut sequence);
+                sequence.truncate(profits.len().saturating_sub(num));
+                sequence
+            }
+        }
+    }
+}
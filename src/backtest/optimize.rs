@@ -0,0 +1,279 @@
+use super::engine::BackTestEngine;
+use super::stats::BackTestStats;
+use crate::error::Result;
+use crate::helpers::random::SeededRng;
+use crate::models::pricing::Pricing;
+use crate::models::strategy::BoxedStrategy;
+use crate::scanner::instrument::{HTFInstrument, Instrument};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+
+/// Strategy parameters by name
+pub type ParamSet = BTreeMap<String, f64>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParamRange {
+    pub name: String,
+    pub from: f64,
+    pub to: f64,
+    pub step: f64,
+}
+
+impl ParamRange {
+    pub fn new(name: &str, from: f64, to: f64, step: f64) -> Self {
+        Self {
+            name: name.to_owned(),
+            from: from.min(to),
+            to: from.max(to),
+            step: step.abs(),
+        }
+    }
+
+    pub fn values(&self) -> Vec<f64> {
+        match self.step > 0. {
+            true => {
+                let steps = ((self.to - self.from) / self.step + 1e-9).floor() as usize;
+                (0..=steps)
+                    .map(|i| self.snap(self.from + i as f64 * self.step))
+                    .collect()
+            }
+            false => vec![self.from],
+        }
+    }
+
+    /// Value at `position` in [0, 1) of the range, on the step grid.
+    pub fn at(&self, position: f64) -> f64 {
+        self.snap(self.from + position * (self.to - self.from))
+    }
+
+    fn snap(&self, val: f64) -> f64 {
+        let val = match self.step > 0. {
+            true => self.from + ((val - self.from) / self.step).round() * self.step,
+            false => val,
+        };
+        // Drops the float noise of the step multiples
+        (val.clamp(self.from, self.to) * 1e9).round() / 1e9
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Search {
+    Grid,
+    Random(usize),
+    LatinHypercube(usize),
+}
+
+impl Search {
+    /// OPTIMIZE_SEARCH is "grid" (default), "random" or "lhs", with
+    /// OPTIMIZE_SAMPLES samples
+    pub fn from_env() -> Self {
+        let samples = env::var("OPTIMIZE_SAMPLES")
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok())
+            .unwrap_or(100);
+
+        match env::var("OPTIMIZE_SEARCH").unwrap_or_default().as_ref() {
+            "random" => Search::Random(samples),
+            "lhs" => Search::LatinHypercube(samples),
+            _ => Search::Grid,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Objective {
+    NetProfit,
+    Sharpe,
+    Sortino,
+    ProfitFactor,
+    /// Net profit over max drawdown
+    DrawdownAdjusted,
+}
+
+impl Objective {
+    /// OPTIMIZE_OBJECTIVE: "net_profit" (default), "sharpe", "sortino",
+    /// "profit_factor" or "drawdown_adjusted"
+    pub fn from_env() -> Self {
+        match env::var("OPTIMIZE_OBJECTIVE").unwrap_or_default().as_ref() {
+            "sharpe" => Objective::Sharpe,
+            "sortino" => Objective::Sortino,
+            "profit_factor" => Objective::ProfitFactor,
+            "drawdown_adjusted" => Objective::DrawdownAdjusted,
+            _ => Objective::NetProfit,
+        }
+    }
+
+    pub fn score(&self, stats: &BackTestStats) -> f64 {
+        match self {
+            Objective::NetProfit => stats.net_profit,
+            Objective::Sharpe => stats.sharpe_ratio,
+            Objective::Sortino => stats.sortino_ratio,
+            Objective::ProfitFactor => stats.profit_factor,
+            Objective::DrawdownAdjusted => match stats.max_drawdown > 0. {
+                true => stats.net_profit / stats.max_drawdown,
+                false => stats.net_profit,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OptimizationResult {
+    pub params: ParamSet,
+    pub score: f64,
+    pub stats: BackTestStats,
+}
+
+/// Runs the backtest engine over the parameter samples in parallel and
+/// ranks them by the objective, best first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Optimizer {
+    ranges: Vec<ParamRange>,
+    search: Search,
+    objective: Objective,
+    seed: u64,
+}
+
+impl Optimizer {
+    pub fn new(ranges: Vec<ParamRange>) -> Self {
+        Self {
+            ranges,
+            search: Search::Grid,
+            objective: Objective::NetProfit,
+            seed: 0,
+        }
+    }
+
+    /// Search, objective and OPTIMIZE_SEED from the env
+    pub fn from_env(ranges: Vec<ParamRange>) -> Self {
+        Self::new(ranges)
+            .search(Search::from_env())
+            .objective(Objective::from_env())
+            .seed(
+                env::var("OPTIMIZE_SEED")
+                    .ok()
+                    .and_then(|val| val.parse::<u64>().ok())
+                    .unwrap_or(0),
+            )
+    }
+
+    pub fn search(mut self, val: Search) -> Self {
+        self.search = val;
+        self
+    }
+
+    pub fn objective(mut self, val: Objective) -> Self {
+        self.objective = val;
+        self
+    }
+
+    pub fn seed(mut self, val: u64) -> Self {
+        self.seed = val;
+        self
+    }
+
+    /// Parameter sets to run, without duplicates. Samples snap to the range
+    /// steps, so random searches over coarse grids repeat values.
+    pub fn samples(&self) -> Vec<ParamSet> {
+        let mut rng = SeededRng::new(self.seed);
+        let samples = match self.search {
+            Search::Grid => self.grid(),
+            Search::Random(num) => (0..num)
+                .map(|_| {
+                    self.ranges
+                        .iter()
+                        .map(|range| (range.name.clone(), range.at(rng.next_f64())))
+                        .collect()
+                })
+                .collect(),
+            Search::LatinHypercube(num) => {
+                let strata: Vec<Vec<usize>> = self
+                    .ranges
+                    .iter()
+                    .map(|_| {
+                        let mut strata: Vec<usize> = (0..num).collect();
+                        rng.shuffle(&mut strata);
+                        strata
+                    })
+                    .collect();
+
+                (0..num)
+                    .map(|i| {
+                        self.ranges
+                            .iter()
+                            .zip(strata.iter())
+                            .map(|(range, strata)| {
+                                let position = (strata[i] as f64 + rng.next_f64()) / num as f64;
+                                (range.name.clone(), range.at(position))
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+        };
+
+        let mut seen = HashSet::new();
+        samples
+            .into_iter()
+            .filter(|params: &ParamSet| {
+                seen.insert(params.values().map(|x| x.to_bits()).collect::<Vec<u64>>())
+            })
+            .collect()
+    }
+
+    fn grid(&self) -> Vec<ParamSet> {
+        self.ranges
+            .iter()
+            .fold(vec![ParamSet::new()], |sets, range| {
+                sets.iter()
+                    .flat_map(|params| {
+                        range.values().into_iter().map(move |val| {
+                            let mut params = params.clone();
+                            params.insert(range.name.clone(), val);
+                            params
+                        })
+                    })
+                    .collect()
+            })
+    }
+
+    /// Sets the factory can't build a strategy for are skipped.
+    pub fn run<F>(
+        &self,
+        engine: &BackTestEngine,
+        instrument: &Instrument,
+        htf_instrument: &HTFInstrument,
+        pricing: &Pricing,
+        factory: F,
+    ) -> Vec<OptimizationResult>
+    where
+        F: Fn(&ParamSet) -> Result<BoxedStrategy> + Sync,
+    {
+        let mut results: Vec<OptimizationResult> = self
+            .samples()
+            .into_par_iter()
+            .filter_map(|params| match factory(&params) {
+                Ok(strategy) => {
+                    let result = engine.run(strategy.as_ref(), instrument, htf_instrument, pricing);
+                    let stats = BackTestStats::from_result(&result);
+                    Some(OptimizationResult {
+                        score: self.objective.score(&stats),
+                        params,
+                        stats,
+                    })
+                }
+                Err(err) => {
+                    log::error!("Can't build strategy for {:?} {}", params, err);
+                    None
+                }
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+        results
+    }
+}
@@ -0,0 +1,351 @@
+use super::engine::{BackTestEngine, BackTestResult, EquityPoint, State};
+use crate::helpers::date::*;
+use crate::models::currency::CurrencyConverter;
+use crate::models::equity::Equity;
+use crate::models::exposure::{margin_rate, total_margin, Exposure};
+use crate::models::pricing::Pricing;
+use crate::models::risk::{RiskEvent, RiskManager};
+use crate::models::strategy::{BoxedStrategy, Strategy};
+use crate::scanner::instrument::{HTFInstrument, Instrument};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::env;
+
+/// Aggregate limits of the portfolio. Exposures are notional percentages of
+/// the current equity, 0 disables a limit.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PortfolioLimits {
+    pub margin_rate: f64,
+    pub max_exposure_per: f64,
+    pub max_symbol_exposure_per: f64,
+    pub max_open_trades: usize,
+}
+
+impl PortfolioLimits {
+    /// Reads MARGIN_RATE, PORTFOLIO_MAX_EXPOSURE,
+    /// PORTFOLIO_MAX_SYMBOL_EXPOSURE and PORTFOLIO_MAX_OPEN_TRADES
+    pub fn from_env() -> Self {
+        Self {
+            margin_rate: margin_rate(),
+            max_exposure_per: env::var("PORTFOLIO_MAX_EXPOSURE")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(0.),
+            max_symbol_exposure_per: env::var("PORTFOLIO_MAX_SYMBOL_EXPOSURE")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(0.),
+            max_open_trades: env::var("PORTFOLIO_MAX_OPEN_TRADES")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(0),
+        }
+    }
+
+    pub fn margin_rate(mut self, val: f64) -> Self {
+        self.margin_rate = val;
+        self
+    }
+
+    pub fn max_exposure_per(mut self, val: f64) -> Self {
+        self.max_exposure_per = val;
+        self
+    }
+
+    pub fn max_symbol_exposure_per(mut self, val: f64) -> Self {
+        self.max_symbol_exposure_per = val;
+        self
+    }
+
+    pub fn max_open_trades(mut self, val: usize) -> Self {
+        self.max_open_trades = val;
+        self
+    }
+}
+
+impl Default for PortfolioLimits {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// A strategy trading one instrument of the portfolio
+#[derive(Clone, Copy)]
+pub struct PortfolioLeg<'a> {
+    pub strategy: &'a dyn Strategy,
+    pub instrument: &'a Instrument,
+    pub htf_instrument: &'a HTFInstrument,
+    pub pricing: &'a Pricing,
+}
+
+impl<'a> PortfolioLeg<'a> {
+    pub fn new(
+        strategy: &'a dyn Strategy,
+        instrument: &'a Instrument,
+        htf_instrument: &'a HTFInstrument,
+        pricing: &'a Pricing,
+    ) -> Self {
+        Self {
+            strategy,
+            instrument,
+            htf_instrument,
+            pricing,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SymbolAttribution {
+    pub symbol: String,
+    pub strategy: String,
+    pub trades: usize,
+    pub net_profit: f64,
+    pub commissions: f64,
+    /// Share of the portfolio net profit
+    pub contribution_per: f64,
+    pub rejected_entries: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PortfolioResult {
+    pub initial_equity: f64,
+    /// Combined curve over the union of the leg dates
+    pub equity_curve: Vec<EquityPoint>,
+    /// Per leg results. Their equity curves are the initial equity plus the
+    /// leg profit.
+    pub results: Vec<BackTestResult>,
+    pub attribution: Vec<SymbolAttribution>,
+    #[serde(default)]
+    pub risk_events: Vec<RiskEvent>,
+}
+
+impl PortfolioResult {
+    pub fn final_equity(&self) -> f64 {
+        match self.equity_curve.last() {
+            Some(point) => point.equity,
+            None => self.initial_equity,
+        }
+    }
+
+    pub fn net_profit(&self) -> f64 {
+        self.final_equity() - self.initial_equity
+    }
+}
+
+struct LegState {
+    strategy: BoxedStrategy,
+    state: State,
+    equity_curve: Vec<EquityPoint>,
+    conversion_rate: f64,
+    cursor: usize,
+}
+
+/// Runs several legs bar by bar on one timeline against the equity and
+/// margin of the engine config. Entries are rejected while they would go
+/// over the margin, the portfolio limits or the risk limits of the engine
+/// config, exits always run. Strategies get the portfolio equity.
+#[derive(Debug, Clone)]
+pub struct PortfolioBackTest {
+    engine: BackTestEngine,
+    limits: PortfolioLimits,
+}
+
+impl PortfolioBackTest {
+    pub fn new(engine: BackTestEngine, limits: PortfolioLimits) -> Self {
+        Self { engine, limits }
+    }
+
+    pub fn limits(&self) -> &PortfolioLimits {
+        &self.limits
+    }
+
+    pub fn run(&self, legs: &[PortfolioLeg]) -> PortfolioResult {
+        let config = self.engine.config();
+        let converter = CurrencyConverter::from_env();
+
+        let timeline: BTreeSet<_> = legs
+            .iter()
+            .flat_map(|leg| leg.instrument.data().iter().map(|candle| candle.date()))
+            .collect();
+
+        let mut states: Vec<LegState> = legs
+            .iter()
+            .map(|leg| LegState {
+                strategy: dyn_clone::clone_box(leg.strategy),
+                state: State::new(config.equity),
+                equity_curve: vec![],
+                conversion_rate: converter.rate(leg.instrument.symbol()),
+                cursor: 0,
+            })
+            .collect();
+
+        let mut equity = Equity::new(config.equity);
+        let mut risk = RiskManager::new(config.risk.clone());
+        let mut equity_curve = vec![];
+        for (position, date) in timeline.iter().enumerate() {
+            let mut bars = vec![None; legs.len()];
+            for (i, leg) in legs.iter().enumerate() {
+                let data = leg.instrument.data();
+                let leg_state = &mut states[i];
+                if let Some(candle) = data.get(leg_state.cursor) {
+                    if candle.date() == *date {
+                        let index = leg_state.cursor;
+                        leg_state.cursor += 1;
+                        leg_state.state.mark(
+                            candle.close(),
+                            leg.pricing,
+                            leg_state.conversion_rate,
+                        );
+
+                        if index >= config.warm_up {
+                            leg_state.equity_curve.push(EquityPoint {
+                                index,
+                                date: to_dbtime(*date),
+                                equity: leg_state.state.equity.value(),
+                            });
+                            bars[i] = Some(index);
+                        }
+                    }
+                }
+            }
+
+            equity.aggregate(states.iter().map(|x| &x.state.equity));
+            risk.update(*date, &equity);
+            equity_curve.push(EquityPoint {
+                index: position,
+                date: to_dbtime(*date),
+                equity: equity.value(),
+            });
+
+            for (i, leg) in legs.iter().enumerate() {
+                if let Some(index) = bars[i] {
+                    if index + 1 < leg.instrument.data().len() {
+                        let others: Vec<Exposure> = legs
+                            .iter()
+                            .zip(states.iter())
+                            .enumerate()
+                            .filter(|(j, _)| *j != i)
+                            .map(|(_, (leg, leg_state))| self.exposure(leg, &leg_state.state))
+                            .collect();
+
+                        let leg_state = &mut states[i];
+                        leg_state.strategy.update_equity(&equity);
+                        self.engine.next(
+                            index,
+                            leg_state.strategy.as_ref(),
+                            leg.instrument,
+                            leg.htf_instrument,
+                            leg.pricing,
+                            &mut |index: usize, state: &State| {
+                                let mut exposures = others.clone();
+                                exposures.push(self.exposure(leg, state));
+                                self.entries_allowed(index, leg, &equity, &exposures)
+                                    && self.engine.risk_allows(
+                                        &mut risk,
+                                        index,
+                                        leg.instrument,
+                                        &equity,
+                                        &exposures,
+                                    )
+                            },
+                            &mut leg_state.state,
+                        );
+                    }
+                }
+            }
+        }
+
+        let risk_events = risk.take_events();
+        let results: Vec<BackTestResult> = legs
+            .iter()
+            .zip(states.into_iter())
+            .map(|(leg, leg_state)| {
+                let symbol = leg.instrument.symbol();
+                self.engine.finish(
+                    leg_state.strategy.as_ref(),
+                    leg.instrument,
+                    leg_state.state,
+                    leg_state.equity_curve,
+                    risk_events
+                        .iter()
+                        .filter(|x| x.symbol == symbol)
+                        .cloned()
+                        .collect(),
+                )
+            })
+            .collect();
+
+        if let Some(point) = equity_curve.last_mut() {
+            point.equity = config.equity + results.iter().map(|x| x.net_profit()).sum::<f64>();
+        }
+
+        let net_profit: f64 = results.iter().map(|x| x.net_profit()).sum();
+        let attribution = results
+            .iter()
+            .map(|result| SymbolAttribution {
+                symbol: result.symbol.clone(),
+                strategy: result.strategy.clone(),
+                trades: result.trades_out.len(),
+                net_profit: result.net_profit(),
+                commissions: result.commissions,
+                contribution_per: match net_profit != 0. {
+                    true => result.net_profit() / net_profit.abs() * 100.,
+                    false => 0.,
+                },
+                rejected_entries: result.rejected_entries,
+            })
+            .collect();
+
+        PortfolioResult {
+            initial_equity: config.equity,
+            equity_curve,
+            results,
+            attribution,
+            risk_events,
+        }
+    }
+
+    fn exposure(&self, leg: &PortfolioLeg, state: &State) -> Exposure {
+        Exposure::new(
+            leg.instrument.symbol(),
+            &state.trades_in,
+            &state.trades_out,
+            &state.orders,
+            self.limits.margin_rate,
+        )
+    }
+
+    /// Whether a new sized trade of the leg fits in the free margin and the
+    /// exposure limits
+    fn entries_allowed(
+        &self,
+        index: usize,
+        leg: &PortfolioLeg,
+        equity: &Equity,
+        exposures: &Vec<Exposure>,
+    ) -> bool {
+        let symbol_notional: f64 = exposures
+            .iter()
+            .filter(|x| x.symbol == leg.instrument.symbol())
+            .map(|x| x.notional)
+            .sum();
+
+        let order_size = self.engine.entry_size(index, leg.instrument, equity);
+        let equity = equity.value();
+        let open_trades: usize = exposures.iter().map(|x| x.open_trades).sum();
+        let notional: f64 = exposures.iter().map(|x| x.notional).sum();
+        let within = |notional: f64, limit_per: f64| {
+            limit_per <= 0. || notional + order_size <= equity * limit_per / 100.
+        };
+
+        let margin = total_margin(exposures) + order_size * self.limits.margin_rate;
+        let open_trades_ok =
+            self.limits.max_open_trades == 0 || open_trades < self.limits.max_open_trades;
+
+        margin <= equity
+            && open_trades_ok
+            && within(notional, self.limits.max_exposure_per)
+            && within(symbol_notional, self.limits.max_symbol_exposure_per)
+    }
+}
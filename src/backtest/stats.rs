@@ -0,0 +1,282 @@
+use super::engine::{BackTestResult, EquityPoint};
+use crate::helpers::comp::average_f64;
+use crate::helpers::date::*;
+use crate::models::trade::TradeOut;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const SECONDS_PER_YEAR: f64 = 365. * 86_400.;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct MonthlyStats {
+    pub year: i32,
+    pub month: u32,
+    pub trades: usize,
+    pub net_profit: f64,
+    /// Equity change over the month
+    pub return_per: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct BackTestStats {
+    pub trades: usize,
+    pub winning_trades: usize,
+    pub losing_trades: usize,
+    pub win_rate: f64,
+    pub gross_profit: f64,
+    pub gross_loss: f64,
+    pub net_profit: f64,
+    pub net_profit_per: f64,
+    pub profit_factor: f64,
+    /// Average profit per trade
+    pub expectancy: f64,
+    pub avg_win: f64,
+    pub avg_loss: f64,
+    pub largest_win: f64,
+    pub largest_loss: f64,
+    pub max_consecutive_wins: usize,
+    pub max_consecutive_losses: usize,
+    pub max_drawdown: f64,
+    pub max_drawdown_per: f64,
+    /// Annualized from the bar returns of the equity curve
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    /// Percentage of bars with an open trade
+    pub exposure: f64,
+    pub avg_bars_in_trade: f64,
+    pub monthly: Vec<MonthlyStats>,
+}
+
+impl BackTestStats {
+    pub fn new(trades_out: &Vec<TradeOut>, equity_curve: &Vec<EquityPoint>) -> Self {
+        let profits: Vec<f64> = trades_out.iter().map(|x| x.profit).collect();
+        let wins: Vec<f64> = profits.iter().filter(|x| **x > 0.).cloned().collect();
+        let losses: Vec<f64> = profits.iter().filter(|x| **x <= 0.).cloned().collect();
+
+        let gross_profit: f64 = wins.iter().sum();
+        let gross_loss: f64 = losses.iter().sum::<f64>().abs();
+        let net_profit = gross_profit - gross_loss;
+
+        let initial_equity = equity_curve.first().map(|x| x.equity).unwrap_or(0.);
+        let net_profit_per = match initial_equity > 0. {
+            true => net_profit / initial_equity * 100.,
+            false => 0.,
+        };
+
+        let win_rate = match profits.is_empty() {
+            true => 0.,
+            false => wins.len() as f64 / profits.len() as f64 * 100.,
+        };
+
+        let profit_factor = match gross_loss > 0. {
+            true => gross_profit / gross_loss,
+            false => 0.,
+        };
+
+        let (max_consecutive_wins, max_consecutive_losses) = consecutive(&profits);
+        let (max_drawdown, max_drawdown_per) = max_drawdown(equity_curve);
+        let (sharpe_ratio, sortino_ratio) = risk_ratios(equity_curve);
+
+        let bars_in_trade: Vec<f64> = trades_out
+            .iter()
+            .map(|x| x.index_out.saturating_sub(x.index_in) as f64)
+            .collect();
+
+        Self {
+            trades: profits.len(),
+            winning_trades: wins.len(),
+            losing_trades: losses.len(),
+            win_rate,
+            gross_profit,
+            gross_loss,
+            net_profit,
+            net_profit_per,
+            profit_factor,
+            expectancy: average_f64(&profits),
+            avg_win: average_f64(&wins),
+            avg_loss: average_f64(&losses),
+            largest_win: wins.iter().cloned().fold(0., f64::max),
+            largest_loss: losses.iter().cloned().fold(0., f64::min),
+            max_consecutive_wins,
+            max_consecutive_losses,
+            max_drawdown,
+            max_drawdown_per,
+            sharpe_ratio,
+            sortino_ratio,
+            exposure: exposure(trades_out, equity_curve),
+            avg_bars_in_trade: average_f64(&bars_in_trade),
+            monthly: monthly(trades_out, equity_curve),
+        }
+    }
+
+    pub fn from_result(result: &BackTestResult) -> Self {
+        Self::new(&result.trades_out, &result.equity_curve)
+    }
+}
+
+fn consecutive(profits: &[f64]) -> (usize, usize) {
+    let (mut wins, mut losses) = (0, 0);
+    let (mut max_wins, mut max_losses) = (0, 0);
+
+    for profit in profits {
+        match *profit > 0. {
+            true => {
+                wins += 1;
+                losses = 0;
+            }
+            false => {
+                losses += 1;
+                wins = 0;
+            }
+        }
+        max_wins = max_wins.max(wins);
+        max_losses = max_losses.max(losses);
+    }
+
+    (max_wins, max_losses)
+}
+
+/// Largest peak to trough fall of the equity, in account currency and
+/// percentage of the peak.
+pub fn max_drawdown(equity_curve: &[EquityPoint]) -> (f64, f64) {
+    drawdown(equity_curve.iter().map(|x| x.equity))
+}
+
+/// Same as `max_drawdown` over a plain equity sequence
+pub fn drawdown<I: IntoIterator<Item = f64>>(equities: I) -> (f64, f64) {
+    let mut peak = f64::MIN;
+    let mut max_drawdown = 0.;
+    let mut max_drawdown_per = 0.;
+
+    for equity in equities {
+        peak = peak.max(equity);
+        let drawdown = peak - equity;
+        if drawdown > max_drawdown {
+            max_drawdown = drawdown;
+        }
+        if peak > 0. && drawdown / peak * 100. > max_drawdown_per {
+            max_drawdown_per = drawdown / peak * 100.;
+        }
+    }
+
+    (max_drawdown, max_drawdown_per)
+}
+
+fn returns(equity_curve: &[EquityPoint]) -> Vec<f64> {
+    equity_curve
+        .windows(2)
+        .filter(|x| x[0].equity > 0.)
+        .map(|x| (x[1].equity - x[0].equity) / x[0].equity)
+        .collect()
+}
+
+/// Bars per year from the average bar duration of the curve
+fn periods_per_year(equity_curve: &[EquityPoint]) -> f64 {
+    match (equity_curve.first(), equity_curve.last()) {
+        (Some(first), Some(last)) if equity_curve.len() > 1 => {
+            let seconds = (last.date.timestamp_millis() - first.date.timestamp_millis()) / 1000;
+            match seconds > 0 {
+                true => SECONDS_PER_YEAR / (seconds as f64 / (equity_curve.len() - 1) as f64),
+                false => 0.,
+            }
+        }
+        _ => 0.,
+    }
+}
+
+fn risk_ratios(equity_curve: &[EquityPoint]) -> (f64, f64) {
+    let returns = returns(equity_curve);
+    if returns.len() < 2 {
+        return (0., 0.);
+    }
+
+    let mean = average_f64(&returns);
+    let deviation = |values: Vec<f64>| {
+        let variance = values.iter().map(|x| x.powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+        variance.sqrt()
+    };
+
+    let annualize = periods_per_year(equity_curve).sqrt();
+    let std_dev = deviation(returns.iter().map(|x| x - mean).collect());
+    let downside_dev = deviation(returns.iter().map(|x| x.min(0.)).collect());
+
+    let sharpe = match std_dev > 0. {
+        true => mean / std_dev * annualize,
+        false => 0.,
+    };
+
+    let sortino = match downside_dev > 0. {
+        true => mean / downside_dev * annualize,
+        false => 0.,
+    };
+
+    (sharpe, sortino)
+}
+
+fn exposure(trades_out: &[TradeOut], equity_curve: &[EquityPoint]) -> f64 {
+    let (first, last) = match (equity_curve.first(), equity_curve.last()) {
+        (Some(first), Some(last)) => (first.index, last.index),
+        _ => return 0.,
+    };
+
+    let bars = last - first + 1;
+    let mut in_market = vec![false; bars];
+    for trade_out in trades_out {
+        let from = trade_out.index_in.max(first);
+        let to = trade_out.index_out.min(last);
+        for index in from..=to {
+            in_market[index - first] = true;
+        }
+    }
+
+    in_market.iter().filter(|x| **x).count() as f64 / bars as f64 * 100.
+}
+
+fn monthly(trades_out: &[TradeOut], equity_curve: &[EquityPoint]) -> Vec<MonthlyStats> {
+    let year_month = |date: &DbDateTime| {
+        let date = from_dbtime(date);
+        (date.year(), date.month())
+    };
+
+    // Start equity is the close of the previous month
+    let mut equities: BTreeMap<(i32, u32), (f64, f64)> = BTreeMap::new();
+    let mut prev_equity = equity_curve.first().map(|x| x.equity).unwrap_or(0.);
+    for point in equity_curve {
+        let (_, end) = equities
+            .entry(year_month(&point.date))
+            .or_insert((prev_equity, point.equity));
+        *end = point.equity;
+        prev_equity = point.equity;
+    }
+
+    let mut months: BTreeMap<(i32, u32), MonthlyStats> = equities
+        .into_iter()
+        .map(|((year, month), (start, end))| {
+            let return_per = match start > 0. {
+                true => (end - start) / start * 100.,
+                false => 0.,
+            };
+            let stats = MonthlyStats {
+                year,
+                month,
+                return_per,
+                ..Default::default()
+            };
+            ((year, month), stats)
+        })
+        .collect();
+
+    for trade_out in trades_out {
+        let (year, month) = year_month(&trade_out.date_out);
+        let stats = months.entry((year, month)).or_insert(MonthlyStats {
+            year,
+            month,
+            ..Default::default()
+        });
+        stats.trades += 1;
+        stats.net_profit += trade_out.profit;
+    }
+
+    months.into_values().collect()
+}
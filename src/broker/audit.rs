@@ -0,0 +1,71 @@
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::helpers::date::*;
+use crate::models::config::ConfigUpdate;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry<T> {
+    pub date: DbDateTime,
+    pub action: String,
+    pub symbol: String,
+    pub payload: T,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditJournal {
+    path: String,
+}
+
+impl AuditJournal {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let path = env::var("AUDIT_JOURNAL").unwrap_or_else(|_| "audit.jsonl".to_owned());
+        Self::new(&path)
+    }
+
+    pub fn is_dry_run() -> bool {
+        ConfigUpdate::current().dry_run.unwrap_or(false)
+    }
+
+    pub fn path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn record<T: Serialize>(&self, action: &str, symbol: &str, payload: &T) -> Result<()> {
+        let entry = AuditEntry {
+            date: now_dbtime(),
+            action: action.to_owned(),
+            symbol: symbol.to_owned(),
+            payload,
+        };
+
+        let line = serde_json::to_string(&entry).map_err(|_| RsAlgoError {
+            err: RsAlgoErrorKind::AuditError,
+        })?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|_| RsAlgoError {
+                err: RsAlgoErrorKind::AuditError,
+            })?;
+
+        writeln!(file, "{}", line).map_err(|_| RsAlgoError {
+            err: RsAlgoErrorKind::AuditError,
+        })?;
+
+        log::info!("[DRY RUN] {} {} recorded", action, symbol);
+
+        Ok(())
+    }
+}
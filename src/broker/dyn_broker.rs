@@ -0,0 +1,320 @@
+use super::xtb_stream::{BrokerStream, Xtb};
+use super::*;
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::models::derivatives::{FundingRate, OpenInterest};
+use crate::models::market::{MarketHours, MarketSymbol};
+use crate::models::mode;
+use crate::models::order::{Order, OrderEvent};
+use crate::models::pricing::Pricing;
+use crate::models::trade::{TradeIn, TradeOut};
+use crate::ws::message::{InstrumentData, ResponseBody, TradeData, TradeOptions, TradeResponse};
+
+use futures_util::future::BoxFuture;
+use futures_util::stream::BoxStream;
+use std::env;
+
+pub type ListenCallback = Box<dyn FnMut(Message) -> BoxFuture<'static, Result<()>> + Send>;
+
+#[async_trait::async_trait]
+pub trait DynBroker: Send {
+    async fn login(&mut self, username: &str, password: &str) -> Result<()>;
+    fn get_session_id(&mut self) -> &String;
+    async fn listen(&mut self, symbol: &str, session_id: String, callback: ListenCallback);
+    async fn get_instrument_data(
+        &mut self,
+        symbol: &str,
+        period: usize,
+        start: i64,
+    ) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>>;
+    async fn get_instrument_history(
+        &mut self,
+        symbol: &str,
+        period: usize,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>>;
+    async fn open_trade(
+        &mut self,
+        trade_in: TradeData<TradeIn>,
+    ) -> Result<ResponseBody<TradeResponse<TradeIn>>>;
+    async fn close_trade(
+        &mut self,
+        trade_out: TradeData<TradeOut>,
+    ) -> Result<ResponseBody<TradeResponse<TradeOut>>>;
+    async fn open_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<TradeIn>>>;
+    async fn close_order(
+        &mut self,
+        trade: TradeData<TradeOut>,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<TradeOut>>>;
+    async fn cancel_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<Order>>>;
+    async fn modify_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<Order>>>;
+    async fn get_market_hours(
+        &mut self,
+        symbol: &MarketSymbol,
+    ) -> Result<ResponseBody<MarketHours>>;
+    async fn is_market_open(&mut self, symbol: &MarketSymbol) -> bool;
+    async fn get_calendar(
+        &mut self,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<CalendarEvent>>>;
+    async fn get_instrument_pricing(&mut self, symbol: &str) -> Result<ResponseBody<Pricing>>;
+    async fn get_symbol_availability(
+        &mut self,
+        symbol: &str,
+    ) -> Result<ResponseBody<SymbolAvailability>>;
+    async fn events(&mut self) -> BoxStream<'_, BrokerEvent>;
+    async fn subscribe_stream(&mut self, symbol: &str) -> Result<()>;
+    async fn subscribe_tick_prices(&mut self, symbol: &str) -> Result<()>;
+    async fn subscribe_trades(&mut self) -> Result<()>;
+    async fn subscribe_news(&mut self) -> Result<()>;
+    async fn subscribe_funding_rates(&mut self, symbol: &str) -> Result<()>;
+    async fn subscribe_open_interest(&mut self, symbol: &str) -> Result<()>;
+    async fn get_funding_rates(
+        &mut self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<FundingRate>>>;
+    async fn get_open_interest(
+        &mut self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<OpenInterest>>>;
+    async fn keepalive_ping(&mut self) -> Result<String>;
+    async fn disconnect(&mut self) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl<B: BrokerStream + Send> DynBroker for B {
+    async fn login(&mut self, username: &str, password: &str) -> Result<()> {
+        BrokerStream::login(self, username, password).await?;
+        Ok(())
+    }
+
+    fn get_session_id(&mut self) -> &String {
+        BrokerStream::get_session_id(self)
+    }
+
+    async fn listen(&mut self, symbol: &str, session_id: String, callback: ListenCallback) {
+        BrokerStream::listen(self, symbol, session_id, callback).await
+    }
+
+    async fn get_instrument_data(
+        &mut self,
+        symbol: &str,
+        period: usize,
+        start: i64,
+    ) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>> {
+        BrokerStream::get_instrument_data(self, symbol, period, start).await
+    }
+
+    async fn get_instrument_history(
+        &mut self,
+        symbol: &str,
+        period: usize,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>> {
+        BrokerStream::get_instrument_history(self, symbol, period, from, to).await
+    }
+
+    async fn open_trade(
+        &mut self,
+        trade_in: TradeData<TradeIn>,
+    ) -> Result<ResponseBody<TradeResponse<TradeIn>>> {
+        BrokerStream::open_trade(self, trade_in).await
+    }
+
+    async fn close_trade(
+        &mut self,
+        trade_out: TradeData<TradeOut>,
+    ) -> Result<ResponseBody<TradeResponse<TradeOut>>> {
+        BrokerStream::close_trade(self, trade_out).await
+    }
+
+    async fn open_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<TradeIn>>> {
+        BrokerStream::open_order(self, order).await
+    }
+
+    async fn close_order(
+        &mut self,
+        trade: TradeData<TradeOut>,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<TradeOut>>> {
+        BrokerStream::close_order(self, trade, order).await
+    }
+
+    async fn cancel_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<Order>>> {
+        BrokerStream::cancel_order(self, order).await
+    }
+
+    async fn modify_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<Order>>> {
+        BrokerStream::modify_order(self, order).await
+    }
+
+    async fn get_market_hours(
+        &mut self,
+        symbol: &MarketSymbol,
+    ) -> Result<ResponseBody<MarketHours>> {
+        BrokerStream::get_market_hours(self, symbol).await
+    }
+
+    async fn is_market_open(&mut self, symbol: &MarketSymbol) -> bool {
+        BrokerStream::is_market_open(self, symbol).await
+    }
+
+    async fn get_calendar(
+        &mut self,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<CalendarEvent>>> {
+        BrokerStream::get_calendar(self, from, to).await
+    }
+
+    async fn get_instrument_pricing(&mut self, symbol: &str) -> Result<ResponseBody<Pricing>> {
+        BrokerStream::get_instrument_pricing(self, symbol).await
+    }
+
+    async fn get_symbol_availability(
+        &mut self,
+        symbol: &str,
+    ) -> Result<ResponseBody<SymbolAvailability>> {
+        BrokerStream::get_symbol_availability(self, symbol).await
+    }
+
+    async fn events(&mut self) -> BoxStream<'_, BrokerEvent> {
+        BrokerStream::events(self).await
+    }
+
+    async fn subscribe_stream(&mut self, symbol: &str) -> Result<()> {
+        BrokerStream::subscribe_stream(self, symbol).await
+    }
+
+    async fn subscribe_tick_prices(&mut self, symbol: &str) -> Result<()> {
+        BrokerStream::subscribe_tick_prices(self, symbol).await
+    }
+
+    async fn subscribe_trades(&mut self) -> Result<()> {
+        BrokerStream::subscribe_trades(self).await
+    }
+
+    async fn subscribe_news(&mut self) -> Result<()> {
+        BrokerStream::subscribe_news(self).await
+    }
+
+    async fn subscribe_funding_rates(&mut self, symbol: &str) -> Result<()> {
+        BrokerStream::subscribe_funding_rates(self, symbol).await
+    }
+
+    async fn subscribe_open_interest(&mut self, symbol: &str) -> Result<()> {
+        BrokerStream::subscribe_open_interest(self, symbol).await
+    }
+
+    async fn get_funding_rates(
+        &mut self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<FundingRate>>> {
+        BrokerStream::get_funding_rates(self, symbol, from, to).await
+    }
+
+    async fn get_open_interest(
+        &mut self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<OpenInterest>>> {
+        BrokerStream::get_open_interest(self, symbol, from, to).await
+    }
+
+    async fn keepalive_ping(&mut self) -> Result<String> {
+        BrokerStream::keepalive_ping(self).await
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        BrokerStream::disconnect(self).await
+    }
+}
+
+pub async fn from_config() -> Result<Box<dyn DynBroker>> {
+    let broker = env::var("BROKER").unwrap_or("xtb".to_string());
+
+    match broker.as_ref() {
+        "xtb" => Ok(Box::new(<Xtb as BrokerStream>::new().await)),
+        _ => {
+            log::error!("Broker {} not supported", broker);
+            Err(RsAlgoError {
+                err: RsAlgoErrorKind::NotSupported,
+            })
+        }
+    }
+}
+
+/// Sends the orders amended by `events` to the broker. Backtests only amend
+/// the local orders.
+pub async fn modify_amended_orders(
+    broker: &mut dyn DynBroker,
+    symbol: &str,
+    orders: &[Order],
+    events: &[OrderEvent],
+    options: &TradeOptions,
+) -> Result<Vec<ResponseBody<TradeResponse<Order>>>> {
+    let execution_mode = mode::from_str(&env::var("EXECUTION_MODE").unwrap());
+    let mut responses = vec![];
+
+    if execution_mode.is_back_test() {
+        return Ok(responses);
+    }
+
+    for event in events {
+        if let OrderEvent::Amended {
+            id,
+            trade_id,
+            order_type,
+            ..
+        } = event
+        {
+            let amended = orders.iter().find(|order| {
+                order.id == *id
+                    && order.trade_id == *trade_id
+                    && std::mem::discriminant(&order.order_type)
+                        == std::mem::discriminant(order_type)
+            });
+
+            if let Some(order) = amended {
+                let response = broker
+                    .modify_order(TradeData {
+                        symbol: symbol.to_owned(),
+                        data: order.clone(),
+                        options: options.clone(),
+                    })
+                    .await?;
+                responses.push(response);
+            }
+        }
+    }
+
+    Ok(responses)
+}
@@ -1,8 +1,17 @@
+pub mod audit;
+pub mod dyn_broker;
 pub mod models;
+pub mod rate_limiter;
+pub mod symbol_mapper;
+pub mod volume_normalizer;
 pub mod xtb;
 pub mod xtb_stream;
 
 pub use crate::ws::message::Message;
+pub use audit::AuditJournal;
+pub use dyn_broker::DynBroker;
 pub use models::*;
+pub use symbol_mapper::SymbolMapper;
+pub use volume_normalizer::VolumeNormalizer;
 pub use xtb::Broker;
 pub use xtb_stream::BrokerStream;
@@ -1,5 +1,10 @@
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
 use crate::helpers::date::{DateTime, Local};
+use crate::models::derivatives::{FundingRate, OpenInterest};
+use crate::models::market::MarketSymbol;
+use crate::models::pricing::Pricing;
 use crate::models::time_frame::*;
+use crate::models::trade::TradeType;
 use serde::{Deserialize, Serialize};
 
 pub type DOHLC = (DateTime<Local>, f64, f64, f64, f64, f64);
@@ -15,6 +20,126 @@ pub enum MessageType {
     Other,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BrokerEvent {
+    Candle(DOHLC),
+    Tick(Pricing),
+    TradeUpdate(TradeUpdate),
+    News(NewsItem),
+    FundingRate(FundingRate),
+    OpenInterest(OpenInterest),
+    Subscription(SubscriptionEvent),
+    KeepAlive,
+    Disconnect,
+}
+
+impl BrokerEvent {
+    pub fn channel(&self) -> Option<&str> {
+        match self {
+            BrokerEvent::Candle(_) => Some("getCandles"),
+            BrokerEvent::Tick(_) => Some("getTickPrices"),
+            BrokerEvent::TradeUpdate(_) => Some("getTrades"),
+            BrokerEvent::News(_) => Some("getNews"),
+            BrokerEvent::FundingRate(_) => Some("getFundingRates"),
+            BrokerEvent::OpenInterest(_) => Some("getOpenInterest"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum SubscriptionState {
+    Established,
+    Confirmed,
+    Dropped,
+    Resubscribed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionEvent {
+    pub channel: String,
+    pub symbol: String,
+    pub state: SubscriptionState,
+    pub date: DateTime<Local>,
+}
+
+impl SubscriptionEvent {
+    pub fn new(channel: &str, symbol: &str, state: SubscriptionState) -> Self {
+        Self {
+            channel: channel.to_owned(),
+            symbol: symbol.to_owned(),
+            state,
+            date: Local::now(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state != SubscriptionState::Dropped
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TradeStatus {
+    Error,
+    Pending,
+    Accepted,
+    Rejected,
+    Opened,
+    Closed,
+    Modified,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeUpdate {
+    pub order: isize,
+    pub symbol: String,
+    pub status: TradeStatus,
+    pub price: f64,
+    pub sl: f64,
+    pub tp: f64,
+    pub volume: f64,
+    pub profit: f64,
+    pub comment: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CalendarImpact {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    pub date: DateTime<Local>,
+    pub country: String,
+    pub title: String,
+    pub impact: CalendarImpact,
+    pub period: String,
+    pub current: String,
+    pub forecast: String,
+    pub previous: String,
+}
+
+impl CalendarEvent {
+    pub fn is_high_impact(&self) -> bool {
+        self.impact == CalendarImpact::High
+    }
+
+    pub fn affects(&self, symbol: &MarketSymbol) -> bool {
+        symbol.countries().contains(&self.country.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsItem {
+    pub key: String,
+    pub date: DateTime<Local>,
+    pub title: String,
+    pub body: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Symbol {
     pub symbol: String,
@@ -41,6 +166,40 @@ pub struct SymbolPricing {
     pub swapShort: f64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SymbolAvailability {
+    pub symbol: String,
+    pub trailing_enabled: bool,
+    pub short_selling: bool,
+    pub long_only: bool,
+    pub quote_id: i64,
+    pub trading_enabled: bool,
+}
+
+impl SymbolAvailability {
+    pub fn can_short(&self) -> bool {
+        self.short_selling && !self.long_only
+    }
+
+    pub fn check(&self, trade_type: &TradeType) -> Result<()> {
+        if !self.trading_enabled {
+            log::error!("{} trading is disabled", self.symbol);
+            return Err(RsAlgoError {
+                err: RsAlgoErrorKind::InstrumentHalted,
+            });
+        }
+
+        if trade_type.is_short() && !self.can_short() {
+            log::error!("{} short selling is disabled", self.symbol);
+            return Err(RsAlgoError {
+                err: RsAlgoErrorKind::ShortSellingDisabled,
+            });
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SymbolPricingResponse {
     pub status: bool,
@@ -122,6 +281,20 @@ pub struct InstrumentCandles {
     pub symbol: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstrumentRange {
+    pub info: InstrumentRangeCandles,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstrumentRangeCandles {
+    pub period: usize,
+    pub start: i64,
+    pub end: i64,
+    pub symbol: String,
+    pub ticks: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TickParams {
     pub level: usize,
@@ -152,9 +325,25 @@ pub struct CommandGetTickPrices {
     pub maxLevel: usize,
 }
 
+impl Transaction {
+    pub const BUY: isize = 0;
+    pub const SELL: isize = 1;
+    pub const MODIFY: isize = 3;
+    pub const DELETE: isize = 4;
+
+    pub fn custom_comment(tags: &Vec<String>) -> String {
+        tags.join(",")
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TradeTransactionInfo {
+    pub tradeTransInfo: Transaction,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Transaction {
-    pub cmd: String,
+    pub cmd: isize,
     pub customComment: String,
     pub symbol: String,
     pub expiration: isize,
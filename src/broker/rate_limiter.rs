@@ -0,0 +1,56 @@
+use std::env;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    tokens: f64,
+    refill_rate: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64, burst: usize) -> Self {
+        Self {
+            capacity: burst as f64,
+            tokens: burst as f64,
+            refill_rate: requests_per_second,
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let requests_per_second = env::var("BROKER_REQUESTS_PER_SECOND")
+            .unwrap_or_else(|_| "5".to_owned())
+            .parse::<f64>()
+            .unwrap();
+
+        let burst = env::var("BROKER_REQUESTS_BURST")
+            .unwrap_or_else(|_| "5".to_owned())
+            .parse::<usize>()
+            .unwrap();
+
+        Self::new(requests_per_second, burst)
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    pub async fn acquire(&mut self) {
+        loop {
+            self.refill();
+            if self.tokens >= 1. {
+                self.tokens -= 1.;
+                return;
+            }
+
+            let wait = (1. - self.tokens) / self.refill_rate;
+            log::debug!("Broker rate limit reached, waiting {:.3}s", wait);
+            tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+        }
+    }
+}
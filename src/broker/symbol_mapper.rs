@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SymbolMapper {
+    to_broker: HashMap<String, String>,
+    from_broker: HashMap<String, String>,
+    separator: char,
+}
+
+impl SymbolMapper {
+    pub fn new() -> Self {
+        Self {
+            to_broker: HashMap::new(),
+            from_broker: HashMap::new(),
+            separator: '_',
+        }
+    }
+
+    /// Reads `BROKER_SYMBOL_MAP` as a comma separated list of
+    /// `symbol:broker_symbol` pairs, e.g. `EURUSD:EURUSD_4,BTCUSD:BTC/USD`.
+    pub fn from_env() -> Self {
+        let mut mapper = Self::new();
+        let table = env::var("BROKER_SYMBOL_MAP").unwrap_or_default();
+
+        for pair in table.split(',').filter(|pair| !pair.trim().is_empty()) {
+            match pair.split_once(':') {
+                Some((symbol, broker_symbol)) => {
+                    mapper.add_alias(symbol.trim(), broker_symbol.trim());
+                }
+                None => log::error!("Invalid symbol mapping {}", pair),
+            }
+        }
+
+        mapper
+    }
+
+    pub fn add_alias(&mut self, symbol: &str, broker_symbol: &str) -> &mut Self {
+        self.to_broker
+            .insert(symbol.to_owned(), broker_symbol.to_owned());
+        self.from_broker
+            .insert(broker_symbol.to_owned(), symbol.to_owned());
+        self
+    }
+
+    pub fn to_broker(&self, symbol: &str) -> String {
+        match self.to_broker.get(symbol) {
+            Some(broker_symbol) => broker_symbol.to_owned(),
+            None => symbol.to_owned(),
+        }
+    }
+
+    pub fn from_broker(&self, broker_symbol: &str) -> String {
+        match self.from_broker.get(broker_symbol) {
+            Some(symbol) => symbol.to_owned(),
+            None => match broker_symbol.split_once(self.separator) {
+                Some((symbol, _suffix)) => symbol.to_owned(),
+                None => broker_symbol.to_owned(),
+            },
+        }
+    }
+}
+
+impl Default for SymbolMapper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
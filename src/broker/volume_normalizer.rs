@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+
+const DEFAULT_SCALE: f64 = 1000.;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VolumeNormalizer {
+    scale: f64,
+    rules: HashMap<String, f64>,
+}
+
+impl VolumeNormalizer {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            scale,
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Reads `BROKER_VOLUME_SCALE` as the default multiplier and
+    /// `BROKER_VOLUME_RULES` as per symbol overrides, e.g. `EURUSD:1000,US500:1`.
+    pub fn from_env() -> Self {
+        let scale = env::var("BROKER_VOLUME_SCALE")
+            .ok()
+            .and_then(|val| val.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_SCALE);
+
+        let mut normalizer = Self::new(scale);
+        let rules = env::var("BROKER_VOLUME_RULES").unwrap_or_default();
+
+        for rule in rules.split(',').filter(|rule| !rule.trim().is_empty()) {
+            match rule
+                .split_once(':')
+                .and_then(|(symbol, scale)| Some((symbol, scale.trim().parse::<f64>().ok()?)))
+            {
+                Some((symbol, scale)) => {
+                    normalizer.add_rule(symbol.trim(), scale);
+                }
+                None => log::error!("Invalid volume rule {}", rule),
+            }
+        }
+
+        normalizer
+    }
+
+    pub fn add_rule(&mut self, symbol: &str, scale: f64) -> &mut Self {
+        self.rules.insert(symbol.to_owned(), scale);
+        self
+    }
+
+    pub fn scale(&self, symbol: &str) -> f64 {
+        match self.rules.get(symbol) {
+            Some(scale) => *scale,
+            None => self.scale,
+        }
+    }
+
+    pub fn normalize(&self, symbol: &str, raw_volume: f64) -> f64 {
+        raw_volume * self.scale(symbol)
+    }
+
+    pub fn raw(&self, symbol: &str, volume: f64) -> f64 {
+        match self.scale(symbol) {
+            scale if scale != 0. => volume / scale,
+            _ => volume,
+        }
+    }
+}
+
+impl Default for VolumeNormalizer {
+    fn default() -> Self {
+        Self::new(DEFAULT_SCALE)
+    }
+}
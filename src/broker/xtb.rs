@@ -1,8 +1,9 @@
+use super::rate_limiter::RateLimiter;
 use super::*;
-use crate::error::Result;
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
 use crate::ws::ws_client::WebSocket;
 
-use crate::helpers::date::parse_time;
+use crate::helpers::date::from_ctm;
 use crate::models::time_frame::*;
 
 use futures_util::Future;
@@ -52,6 +53,9 @@ pub struct Xtb {
     streamSessionId: String,
     time_frame: usize,
     from_date: i64,
+    rate_limiter: RateLimiter,
+    symbol_mapper: SymbolMapper,
+    volume_normalizer: VolumeNormalizer,
 }
 
 #[async_trait::async_trait]
@@ -65,6 +69,9 @@ impl Broker for Xtb {
             symbol: "".to_owned(),
             time_frame: 0,
             from_date: 0,
+            rate_limiter: RateLimiter::from_env(),
+            symbol_mapper: SymbolMapper::from_env(),
+            volume_normalizer: VolumeNormalizer::from_env(),
         }
     }
 
@@ -89,11 +96,7 @@ impl Broker for Xtb {
     }
 
     async fn read(&mut self) -> Result<Response<VEC_DOHLC>> {
-        let msg = self.websocket.read().await.unwrap();
-        let txt_msg = match msg {
-            Message::Text(txt) => txt,
-            _ => panic!(),
-        };
+        let txt_msg = self.websocket.read_text().await?;
         let response = self.handle_response::<VEC_DOHLC>(&txt_msg).await.unwrap();
         Ok(response)
     }
@@ -116,19 +119,21 @@ impl Broker for Xtb {
     ) -> Result<Response<VEC_DOHLC>> {
         self.symbol = symbol.to_owned();
         self.time_frame = time_frame;
+        let custom_time_frame = TimeFrameType::from_number(time_frame);
         self.send(&Command {
             command: "getChartLastRequest".to_owned(),
             arguments: Instrument {
                 info: InstrumentCandles {
-                    symbol: symbol.to_owned(),
-                    period: time_frame,
+                    symbol: self.symbol_mapper.to_broker(symbol),
+                    period: custom_time_frame.broker_time_frame().to_number() as usize,
                     start: from_date * 1000,
                 },
             },
         })
         .await?;
 
-        let res = self.get_response().await?;
+        let mut res = self.get_response().await?;
+        res.data = from_broker_time_frame(&custom_time_frame, res.data);
 
         Ok(res)
     }
@@ -140,12 +145,12 @@ impl Broker for Xtb {
         let symbol_command = Command {
             command: "getSymbol".to_owned(),
             arguments: SymbolArg {
-                symbol: symbol.to_owned(),
+                symbol: self.symbol_mapper.to_broker(symbol),
             },
         };
 
         self.send(&symbol_command).await.unwrap();
-        let msg = self.websocket.read().await.unwrap();
+        let msg = self.websocket.read().await?;
         let txt_msg = match msg {
             Message::Text(txt) => {
                 let parsed: SymbolPricingResponse = serde_json::from_str(&txt).unwrap();
@@ -155,7 +160,11 @@ impl Broker for Xtb {
                     payload: Some(symbol_detail),
                 }
             }
-            _ => panic!(),
+            _ => {
+                return Err(RsAlgoError {
+                    err: RsAlgoErrorKind::WebSocketError,
+                })
+            }
         };
 
         Ok(txt_msg)
@@ -171,20 +180,13 @@ impl Broker for Xtb {
             command: "getTickPrices".to_owned(),
             arguments: TickParams {
                 timestamp,
-                symbols: vec![symbol.to_string()],
+                symbols: vec![self.symbol_mapper.to_broker(symbol)],
                 level,
             },
         };
 
-        self.websocket
-            .send(&serde_json::to_string(&tick_command).unwrap())
-            .await
-            .unwrap();
-        let msg = self.websocket.read().await.unwrap();
-        let txt_msg = match msg {
-            Message::Text(txt) => txt,
-            _ => panic!(),
-        };
+        self.send(tick_command).await.unwrap();
+        let txt_msg = self.websocket.read_text().await?;
 
         Ok(txt_msg)
     }
@@ -201,10 +203,12 @@ impl Broker for Xtb {
         .unwrap();
 
         loop {
-            let msg = self.websocket.read().await.unwrap();
-            let txt_msg = match msg {
-                Message::Text(txt) => txt,
-                _ => panic!(),
+            let txt_msg = match self.websocket.read_text().await {
+                Ok(txt) => txt,
+                Err(err) => {
+                    log::error!("Listen error {:?}", err);
+                    break;
+                }
             };
             let response = self.handle_response::<VEC_DOHLC>(&txt_msg).await.unwrap();
             //tokio::spawn(callback(response));
@@ -240,7 +244,10 @@ impl Broker for Xtb {
                     let high = open + data["high"].as_f64().unwrap();
                     let low = open + data["low"].as_f64().unwrap();
                     let close = open + data["close"].as_f64().unwrap();
-                    let volume = data["vol"].as_f64().unwrap() * 1000.;
+                    let symbol =
+                        SymbolMapper::from_env().from_broker(data["symbol"].as_str().unwrap_or(""));
+                    let volume = VolumeNormalizer::from_env()
+                        .normalize(&symbol, data["vol"].as_f64().unwrap());
 
                     let leches = (date, open, high, low, close, volume, 0.);
 
@@ -267,11 +274,7 @@ impl Broker for Xtb {
         };
 
         self.send(&ping_command).await.unwrap();
-        let msg = self.websocket.read().await.unwrap();
-        let txt_msg = match msg {
-            Message::Text(txt) => txt,
-            _ => panic!(),
-        };
+        let txt_msg = self.websocket.read_text().await?;
 
         Ok(txt_msg)
     }
@@ -287,6 +290,7 @@ impl Xtb {
     where
         for<'de> T: Serialize + Deserialize<'de> + Debug,
     {
+        self.rate_limiter.acquire().await;
         self.websocket
             .send(&serde_json::to_string(&command).unwrap())
             .await?;
@@ -295,11 +299,7 @@ impl Xtb {
     }
 
     async fn get_response(&mut self) -> Result<Response<VEC_DOHLC>> {
-        let msg = self.websocket.read().await.unwrap();
-        let txt_msg = match msg {
-            Message::Text(txt) => txt,
-            _ => panic!(),
-        };
+        let txt_msg = self.websocket.read_text().await?;
         let res = self.handle_response::<VEC_DOHLC>(&txt_msg).await.unwrap();
 
         Ok(res)
@@ -365,12 +365,14 @@ impl Xtb {
         let pow = x.powf(digits);
         for obj in data["returnData"]["rateInfos"].as_array().unwrap() {
             //FIXME!!
-            let date = parse_time(obj["ctm"].as_i64().unwrap() / 1000);
+            let date = from_ctm(obj["ctm"].as_i64().unwrap());
             let open = obj["open"].as_f64().unwrap() / pow;
             let high = open + obj["high"].as_f64().unwrap() / pow;
             let low = open + obj["low"].as_f64().unwrap() / pow;
             let close = open + obj["close"].as_f64().unwrap() / pow;
-            let volume = obj["vol"].as_f64().unwrap() * 1000.;
+            let volume = self
+                .volume_normalizer
+                .normalize(&self.symbol, obj["vol"].as_f64().unwrap());
             result.push((date, open, high, low, close, volume));
         }
 
@@ -410,11 +412,5 @@ impl Xtb {
     }
 }
 pub fn parse_symbol(symbol: &String) -> Result<String> {
-    if symbol.contains('_') {
-        let symbol_str: Vec<&str> = symbol.split('_').collect();
-        Ok(symbol_str[0].to_owned())
-    } else {
-        log::error!("Change fucking xtb");
-        Ok(symbol.to_owned())
-    }
+    Ok(SymbolMapper::from_env().from_broker(symbol))
 }
@@ -1,10 +1,12 @@
+use super::audit::AuditJournal;
+use super::rate_limiter::RateLimiter;
 use super::*;
-use crate::error::Result;
-use crate::helpers::calc;
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
 use crate::helpers::date;
 use crate::helpers::date::parse_time;
 use crate::helpers::date::*;
-use crate::helpers::uuid;
+use crate::helpers::uuid::IdGenerator;
+use crate::models::derivatives::{FundingRate, OpenInterest};
 use crate::models::market::*;
 use crate::models::order::*;
 use crate::models::pricing::Pricing;
@@ -17,9 +19,13 @@ use crate::ws::ws_client::WebSocket;
 use crate::ws::ws_stream_client::WebSocket as WebSocketClientStream;
 
 use chrono::{DateTime, Local};
-use futures_util::{stream::SplitStream, Future};
+use futures_util::{
+    stream::{self, BoxStream, SplitStream},
+    Future, StreamExt,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::env;
 use std::fmt::Debug;
 use tokio::net::TcpStream;
@@ -45,6 +51,13 @@ pub trait BrokerStream {
         period: usize,
         start: i64,
     ) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>>;
+    async fn get_instrument_history(
+        &mut self,
+        symbol: &str,
+        period: usize,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>>;
     async fn open_trade(
         &mut self,
         trade_in: TradeData<TradeIn>,
@@ -62,13 +75,50 @@ pub trait BrokerStream {
         trade: TradeData<TradeOut>,
         order: TradeData<Order>,
     ) -> Result<ResponseBody<TradeResponse<TradeOut>>>;
-    async fn get_market_hours(&mut self, symbol: &str) -> Result<ResponseBody<MarketHours>>;
-    async fn is_market_open(&mut self, symbol: &str) -> bool;
+    async fn cancel_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<Order>>>;
+    async fn modify_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<Order>>>;
+    async fn get_market_hours(
+        &mut self,
+        symbol: &MarketSymbol,
+    ) -> Result<ResponseBody<MarketHours>>;
+    async fn is_market_open(&mut self, symbol: &MarketSymbol) -> bool;
+    async fn get_calendar(
+        &mut self,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<CalendarEvent>>>;
     async fn get_instrument_pricing(&mut self, symbol: &str) -> Result<ResponseBody<Pricing>>;
+    async fn get_symbol_availability(
+        &mut self,
+        symbol: &str,
+    ) -> Result<ResponseBody<SymbolAvailability>>;
     async fn get_stream(&mut self) -> &mut SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+    async fn events(&mut self) -> BoxStream<'_, BrokerEvent>;
     async fn subscribe_stream(&mut self, symbol: &str) -> Result<()>;
     async fn subscribe_tick_prices(&mut self, symbol: &str) -> Result<()>;
-    async fn parse_stream_data(msg: Message) -> Option<String>;
+    async fn subscribe_trades(&mut self) -> Result<()>;
+    async fn subscribe_news(&mut self) -> Result<()>;
+    async fn subscribe_funding_rates(&mut self, symbol: &str) -> Result<()>;
+    async fn subscribe_open_interest(&mut self, symbol: &str) -> Result<()>;
+    async fn get_funding_rates(
+        &mut self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<FundingRate>>>;
+    async fn get_open_interest(
+        &mut self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<OpenInterest>>>;
+    async fn parse_stream_data(&self, msg: Message) -> Option<String>;
     async fn keepalive_ping(&mut self) -> Result<String>;
     async fn disconnect(&mut self) -> Result<()>;
 }
@@ -81,6 +131,13 @@ pub struct Xtb {
     streamSessionId: String,
     time_frame: usize,
     from_date: i64,
+    rate_limiter: RateLimiter,
+    symbol_mapper: SymbolMapper,
+    volume_normalizer: VolumeNormalizer,
+    audit_journal: AuditJournal,
+    subscriptions: HashMap<String, SubscriptionEvent>,
+    subscription_events: Vec<SubscriptionEvent>,
+    ids: IdGenerator,
 }
 
 #[async_trait::async_trait]
@@ -110,6 +167,13 @@ impl BrokerStream for Xtb {
             symbol: "".to_owned(),
             time_frame: 0,
             from_date: 0,
+            rate_limiter: RateLimiter::from_env(),
+            symbol_mapper: SymbolMapper::from_env(),
+            volume_normalizer: VolumeNormalizer::from_env(),
+            audit_journal: AuditJournal::from_env(),
+            subscriptions: HashMap::new(),
+            subscription_events: vec![],
+            ids: IdGenerator::from_env(),
         }
     }
 
@@ -137,12 +201,49 @@ impl BrokerStream for Xtb {
         &mut self.stream.read
     }
 
+    async fn events(&mut self) -> BoxStream<'_, BrokerEvent> {
+        let subscription_events = std::mem::take(&mut self.subscription_events)
+            .into_iter()
+            .map(BrokerEvent::Subscription);
+        let subscriptions = &mut self.subscriptions;
+        let symbol_mapper = &self.symbol_mapper;
+        let volume_normalizer = &self.volume_normalizer;
+
+        let stream_events = (&mut self.stream.read).filter_map(move |msg| async move {
+            match msg {
+                Ok(msg) => Xtb::parse_stream_event(msg, symbol_mapper, volume_normalizer),
+                Err(err) => {
+                    log::error!("Stream socket error {:?}", err);
+                    Some(BrokerEvent::Disconnect)
+                }
+            }
+        });
+
+        let socket_events = stream::unfold(Some(&mut self.socket), |socket| async move {
+            let socket = socket?;
+            match socket.read_msg().await {
+                Ok(Message::Text(txt)) => Some((Xtb::parse_command_event(&txt), Some(socket))),
+                Ok(Message::Close(_)) => Some((Some(BrokerEvent::Disconnect), None)),
+                Ok(_) => Some((None, Some(socket))),
+                Err(err) => {
+                    log::error!("Command socket error {:?}", err);
+                    Some((Some(BrokerEvent::Disconnect), None))
+                }
+            }
+        })
+        .filter_map(|event| async move { event });
+
+        stream::iter(subscription_events)
+            .chain(
+                stream::select(stream_events, socket_events).flat_map(move |event| {
+                    stream::iter(Xtb::track_subscriptions(subscriptions, event))
+                }),
+            )
+            .boxed()
+    }
+
     async fn read(&mut self) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>> {
-        let msg = self.socket.read().await.unwrap();
-        let txt_msg = match msg {
-            Message::Text(txt) => txt,
-            _ => panic!(),
-        };
+        let txt_msg = self.socket.read_text().await?;
         let response = self.handle_response::<VEC_DOHLC>(&txt_msg).await.unwrap();
         Ok(response)
     }
@@ -165,12 +266,13 @@ impl BrokerStream for Xtb {
     ) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>> {
         self.symbol = symbol.to_owned();
         self.time_frame = time_frame;
+        let custom_time_frame = TimeFrameType::from_number(time_frame);
         let instrument_command = Command {
             command: "getChartLastRequest".to_owned(),
             arguments: Instrument {
                 info: InstrumentCandles {
-                    symbol: symbol.to_owned(),
-                    period: time_frame,
+                    symbol: self.symbol_mapper.to_broker(symbol),
+                    period: custom_time_frame.broker_time_frame().to_number() as usize,
                     start: from_date * 1000,
                 },
             },
@@ -184,20 +286,105 @@ impl BrokerStream for Xtb {
 
         self.send(&instrument_command).await.unwrap();
 
-        let res = self.get_response().await?;
+        let mut res = self.get_response().await?;
+        if let Some(payload) = res.payload.as_mut() {
+            payload.data = from_broker_time_frame(&custom_time_frame, payload.data.clone());
+        }
         Ok(res)
     }
 
+    async fn get_instrument_history(
+        &mut self,
+        symbol: &str,
+        time_frame: usize,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>> {
+        let max_bars = env::var("MAX_BARS_PER_REQUEST")
+            .unwrap_or_else(|_| "1000".to_owned())
+            .parse::<i64>()
+            .unwrap();
+
+        let custom_time_frame = TimeFrameType::from_number(time_frame);
+        let broker_time_frame = custom_time_frame.broker_time_frame().to_number() as usize;
+        let step = broker_time_frame as i64 * 60 * max_bars;
+        let mut data: VEC_DOHLC = vec![];
+        let mut start = from;
+
+        self.symbol = symbol.to_owned();
+        self.time_frame = time_frame;
+
+        while start < to {
+            let end = (start + step).min(to);
+            let range_command = Command {
+                command: "getChartRangeRequest".to_owned(),
+                arguments: InstrumentRange {
+                    info: InstrumentRangeCandles {
+                        symbol: self.symbol_mapper.to_broker(symbol),
+                        period: broker_time_frame,
+                        start: start * 1000,
+                        end: end * 1000,
+                        ticks: 0,
+                    },
+                },
+            };
+
+            log::info!(
+                "Requesting {} history from {:?} to {:?}",
+                time_frame,
+                date::parse_time(start),
+                date::parse_time(end)
+            );
+
+            self.send(&range_command).await?;
+            let res = self.get_response().await?;
+
+            if let Some(chunk) = res.payload {
+                data.extend(chunk.data);
+            }
+
+            start = end;
+        }
+
+        data.sort_by(|a, b| a.0.cmp(&b.0));
+        data.dedup_by(|a, b| a.0 == b.0);
+        let data = from_broker_time_frame(&custom_time_frame, data);
+
+        let market_hours = self
+            .get_market_hours(&MarketSymbol::from_symbol(symbol))
+            .await?
+            .payload;
+
+        let gaps = match &market_hours {
+            Some(market_hours) => Xtb::find_gaps(&data, time_frame, market_hours),
+            None => vec![],
+        };
+
+        if !gaps.is_empty() {
+            log::warn!("{} {} history has {} gaps", symbol, time_frame, gaps.len());
+        }
+
+        Ok(ResponseBody {
+            response: ResponseType::GetInstrumentData,
+            payload: Some(InstrumentData {
+                symbol: symbol.to_owned(),
+                time_frame: TimeFrameType::from_number(time_frame),
+                data,
+                gaps,
+            }),
+        })
+    }
+
     async fn get_instrument_pricing(&mut self, symbol: &str) -> Result<ResponseBody<Pricing>> {
         let tick_command = Command {
             command: "getSymbol".to_owned(),
             arguments: SymbolArg {
-                symbol: symbol.to_owned(),
+                symbol: self.symbol_mapper.to_broker(symbol),
             },
         };
 
         self.send(&tick_command).await.unwrap();
-        let msg = self.socket.read().await.unwrap();
+        let msg = self.socket.read().await?;
         let txt_msg = match msg {
             Message::Text(txt) => {
                 let pricing = self
@@ -210,22 +397,76 @@ impl BrokerStream for Xtb {
                     payload: Some(pricing),
                 }
             }
-            _ => panic!(),
+            _ => {
+                return Err(RsAlgoError {
+                    err: RsAlgoErrorKind::WebSocketError,
+                })
+            }
+        };
+
+        Ok(txt_msg)
+    }
+
+    async fn get_symbol_availability(
+        &mut self,
+        symbol: &str,
+    ) -> Result<ResponseBody<SymbolAvailability>> {
+        let symbol_command = Command {
+            command: "getSymbol".to_owned(),
+            arguments: SymbolArg {
+                symbol: self.symbol_mapper.to_broker(symbol),
+            },
+        };
+
+        self.send(&symbol_command).await.unwrap();
+        let msg = self.socket.read().await?;
+        let txt_msg = match msg {
+            Message::Text(txt) => {
+                let availability = self
+                    .parse_symbol_availability(symbol.to_owned(), txt)
+                    .await
+                    .unwrap();
+
+                ResponseBody {
+                    response: ResponseType::GetSymbolAvailability,
+                    payload: Some(availability),
+                }
+            }
+            _ => {
+                return Err(RsAlgoError {
+                    err: RsAlgoErrorKind::WebSocketError,
+                })
+            }
         };
 
         Ok(txt_msg)
     }
 
-    async fn get_market_hours(&mut self, symbol: &str) -> Result<ResponseBody<MarketHours>> {
+    async fn get_market_hours(
+        &mut self,
+        market_symbol: &MarketSymbol,
+    ) -> Result<ResponseBody<MarketHours>> {
+        let symbol = market_symbol.name();
+
+        if !market_symbol.has_sessions() {
+            return Ok(ResponseBody {
+                response: ResponseType::GetMarketHours,
+                payload: Some(
+                    MarketHours::new(true, symbol.to_owned(), vec![])
+                        .set_market(market_symbol.market().clone()),
+                ),
+            });
+        }
+
         let trading_hours_command = Command {
             command: "getTradingHours".to_owned(),
             arguments: TradingHoursCommand {
-                symbols: vec![symbol.to_string()],
+                symbols: vec![self.symbol_mapper.to_broker(symbol)],
             },
         };
 
         self.send(&trading_hours_command).await.unwrap();
-        let msg = self.socket.read().await.unwrap();
+        let msg = self.socket.read().await?;
 
         let txt_msg = match msg {
             Message::Text(txt) => {
@@ -262,23 +503,35 @@ impl BrokerStream for Xtb {
                     result.push(market_hour);
                 }
 
-                match self.is_market_open(symbol).await {
+                match self.is_market_open(market_symbol).await {
                     true => open = true,
                     false => open = false,
                 };
 
                 ResponseBody {
                     response: ResponseType::GetMarketHours,
-                    payload: Some(MarketHours::new(open, symbol.to_owned(), result)),
+                    payload: Some(
+                        MarketHours::new(open, symbol.to_owned(), result)
+                            .set_market(market_symbol.market().clone()),
+                    ),
                 }
             }
-            _ => panic!(),
+            _ => {
+                return Err(RsAlgoError {
+                    err: RsAlgoErrorKind::WebSocketError,
+                })
+            }
         };
 
         Ok(txt_msg)
     }
 
-    async fn is_market_open(&mut self, symbol: &str) -> bool {
+    async fn is_market_open(&mut self, market_symbol: &MarketSymbol) -> bool {
+        if !market_symbol.has_sessions() {
+            return true;
+        }
+
+        let symbol = market_symbol.name();
         let minutes = 5;
         let from = (Local::now() - date::Duration::minutes(minutes)).timestamp();
         let res = self
@@ -303,6 +556,46 @@ impl BrokerStream for Xtb {
         }
     }
 
+    async fn get_calendar(
+        &mut self,
+        from: i64,
+        to: i64,
+    ) -> Result<ResponseBody<Vec<CalendarEvent>>> {
+        let calendar_command = CommandAllSymbols {
+            command: "getCalendar".to_owned(),
+        };
+
+        self.send(&calendar_command).await.unwrap();
+        let msg = self.socket.read().await?;
+
+        let txt_msg = match msg {
+            Message::Text(txt) => {
+                let data = self.parse_message(&txt).await.unwrap();
+                let events: Vec<CalendarEvent> = self
+                    .parse_calendar_data(&data)
+                    .unwrap()
+                    .into_iter()
+                    .filter(|event| {
+                        let timestamp = event.date.timestamp();
+                        timestamp >= from && timestamp <= to
+                    })
+                    .collect();
+
+                ResponseBody {
+                    response: ResponseType::GetCalendar,
+                    payload: Some(events),
+                }
+            }
+            _ => {
+                return Err(RsAlgoError {
+                    err: RsAlgoErrorKind::WebSocketError,
+                })
+            }
+        };
+
+        Ok(txt_msg)
+    }
+
     async fn open_trade(
         &mut self,
         trade: TradeData<TradeIn>,
@@ -310,9 +603,9 @@ impl BrokerStream for Xtb {
         let trade_command = Command {
             command: "tradeTransaction".to_owned(),
             arguments: Transaction {
-                cmd: "".to_owned(),
+                cmd: Transaction::BUY,
                 symbol: "".to_owned(),
-                customComment: "".to_owned(),
+                customComment: Transaction::custom_comment(&trade.data.tags),
                 expiration: 0,
                 order: 0,
                 price: 0.,
@@ -332,6 +625,9 @@ impl BrokerStream for Xtb {
         let mut data = trade.data;
         let trade_type = data.trade_type.clone();
 
+        let availability = self.get_symbol_availability(symbol).await?;
+        availability.payload.unwrap().check(&trade_type)?;
+
         let price_in = match trade_type.is_long() {
             true => ask,
             false => bid,
@@ -344,7 +640,7 @@ impl BrokerStream for Xtb {
             bid
         );
 
-        data.id = uuid::generate_ts_id(Local::now());
+        data.id = self.ids.next(Local::now());
         data.price_in = price_in;
         data.ask = ask;
         data.spread = spread;
@@ -359,6 +655,8 @@ impl BrokerStream for Xtb {
             }),
         };
 
+        self.audit("open_trade", &txt_msg)?;
+
         Ok(txt_msg)
     }
     async fn close_trade(
@@ -411,12 +709,15 @@ impl BrokerStream for Xtb {
             profit
         );
 
-        data.id = uuid::generate_ts_id(Local::now());
+        data.id = self.ids.next(Local::now());
         data.price_out = price_out;
-        data.date_out = to_dbtime(Local::now());
+        data.date_out = now_dbtime();
         data.bid = bid;
         data.ask = ask;
         data.spread_out = spread;
+        if data.close_reason == CloseReason::Unknown {
+            data.close_reason = CloseReason::Manual;
+        }
 
         let txt_msg = ResponseBody {
             response: ResponseType::TradeOutAccepted,
@@ -426,6 +727,8 @@ impl BrokerStream for Xtb {
                 data,
             }),
         };
+        self.audit("close_trade", &txt_msg)?;
+
         Ok(txt_msg)
     }
 
@@ -436,10 +739,10 @@ impl BrokerStream for Xtb {
         let trade_command = Command {
             command: "tradeTransaction".to_owned(),
             arguments: Transaction {
-                cmd: "".to_owned(),
+                cmd: Transaction::BUY,
                 symbol: "".to_owned(),
-                customComment: "".to_owned(),
-                expiration: 0,
+                customComment: Transaction::custom_comment(&order.data.tags),
+                expiration: order.data.expiration_ts() as isize,
                 order: 0,
                 price: 0.,
                 sl: 0.,
@@ -460,15 +763,19 @@ impl BrokerStream for Xtb {
             false => TradeType::OrderInShort,
         };
 
+        let availability = self.get_symbol_availability(symbol).await?;
+        availability.payload.unwrap().check(&trade_type)?;
+
         let price_in = match trade_type.is_long() {
             true => pricing.ask(),
             false => pricing.bid(),
         };
 
-        let quantity = calc::calculate_quantity(order.size(), price_in);
+        let (quantity, risk, risk_amount) =
+            calculate_trade_quantity(Some(&order), order.size(), price_in);
 
         let trade_in = TradeIn {
-            id: uuid::generate_ts_id(Local::now()),
+            id: self.ids.next(Local::now()),
             index_in: order.index_created,
             quantity,
             origin_price: order.origin_price,
@@ -476,7 +783,10 @@ impl BrokerStream for Xtb {
             ask: pricing.ask(),
             spread,
             trade_type,
-            date_in: to_dbtime(Local::now()),
+            date_in: now_dbtime(),
+            tags: order.tags().clone(),
+            risk,
+            risk_amount,
         };
 
         let txt_msg = ResponseBody {
@@ -488,6 +798,8 @@ impl BrokerStream for Xtb {
             }),
         };
 
+        self.audit("open_order", &txt_msg)?;
+
         Ok(txt_msg)
     }
 
@@ -507,7 +819,7 @@ impl BrokerStream for Xtb {
         let order_data = order.data;
 
         let trade_type = trade_data.trade_type.clone();
-        let order_type = order_data.order_type;
+        let order_type = order_data.order_type.clone();
 
         let non_profitable_outs = trade.options.non_profitable_out;
         let price_in = trade_data.price_in;
@@ -554,12 +866,13 @@ impl BrokerStream for Xtb {
             profit
         );
 
-        trade_data.id = uuid::generate_ts_id(Local::now());
+        trade_data.id = self.ids.next(Local::now());
         trade_data.price_out = price_out;
-        trade_data.date_out = to_dbtime(Local::now());
+        trade_data.date_out = now_dbtime();
         trade_data.bid = bid;
         trade_data.ask = ask;
         trade_data.spread_out = spread;
+        trade_data.close_reason = CloseReason::from_exit(&trade_type, Some(&order_data));
 
         let txt_msg = ResponseBody {
             response: ResponseType::TradeOutAccepted,
@@ -569,6 +882,143 @@ impl BrokerStream for Xtb {
                 data: trade_data,
             }),
         };
+        self.audit("close_order", &txt_msg)?;
+
+        Ok(txt_msg)
+    }
+
+    async fn cancel_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<Order>>> {
+        let order_engine = &env::var("ORDER_ENGINE").unwrap();
+        let symbol = order.symbol;
+        let mut data = order.data;
+
+        log::info!(
+            "{} {:?} order {} canceled",
+            symbol,
+            data.order_type,
+            data.id
+        );
+
+        // Broker held stops are the stop loss of the parent trade, so they are
+        // removed from the trade. Other broker orders are deleted.
+        let accepted = match (order_engine.as_ref(), data.broker_order) {
+            ("broker", Some(broker_order)) => {
+                let cmd = match data.order_type {
+                    OrderType::StopLossLong(_, _)
+                    | OrderType::BuyOrderLong(_, _, _)
+                    | OrderType::SellOrderShort(_, _, _)
+                    | OrderType::TakeProfitShort(_, _, _) => Transaction::BUY,
+                    _ => Transaction::SELL,
+                };
+
+                let trans_type = match data.order_type.is_stop() {
+                    true => Transaction::MODIFY,
+                    false => Transaction::DELETE,
+                };
+
+                self.send_transaction(Transaction {
+                    cmd,
+                    symbol: self.symbol_mapper.to_broker(&symbol),
+                    customComment: Transaction::custom_comment(&data.tags),
+                    expiration: 0,
+                    order: broker_order as isize,
+                    price: 0.,
+                    sl: 0.,
+                    tp: 0.,
+                    volume: data.remaining_size(),
+                    trans_type,
+                })
+                .await?
+            }
+            _ => true,
+        };
+
+        if accepted {
+            data.cancel_order(now_dbtime());
+        }
+
+        let txt_msg = ResponseBody {
+            response: ResponseType::CancelOrderAccepted,
+            payload: Some(TradeResponse {
+                symbol,
+                accepted,
+                data,
+            }),
+        };
+        self.audit("cancel_order", &txt_msg)?;
+
+        Ok(txt_msg)
+    }
+
+    async fn modify_order(
+        &mut self,
+        order: TradeData<Order>,
+    ) -> Result<ResponseBody<TradeResponse<Order>>> {
+        let order_engine = &env::var("ORDER_ENGINE").unwrap();
+        let symbol = order.symbol;
+        let data = order.data;
+
+        // Only stops live at the broker, as the stop loss of the parent trade.
+        // Other orders are kept by the bot.
+        let accepted = match (order_engine.as_ref(), data.order_type.is_stop()) {
+            ("broker", true) => {
+                let broker_order = match data.broker_order {
+                    Some(broker_order) => broker_order,
+                    None => {
+                        log::error!(
+                            "{} {:?} order {} has no broker trade to modify",
+                            symbol,
+                            data.order_type,
+                            data.id
+                        );
+                        return Err(RsAlgoError {
+                            err: RsAlgoErrorKind::RequestError,
+                        });
+                    }
+                };
+
+                let cmd = match data.order_type {
+                    OrderType::StopLossLong(_, _) => Transaction::BUY,
+                    _ => Transaction::SELL,
+                };
+
+                self.send_transaction(Transaction {
+                    cmd,
+                    symbol: self.symbol_mapper.to_broker(&symbol),
+                    customComment: Transaction::custom_comment(&data.tags),
+                    expiration: 0,
+                    order: broker_order as isize,
+                    price: 0.,
+                    sl: data.target_price,
+                    tp: 0.,
+                    volume: data.remaining_size(),
+                    trans_type: Transaction::MODIFY,
+                })
+                .await?
+            }
+            _ => true,
+        };
+
+        log::info!(
+            "{} {:?} order {} modified to {}",
+            symbol,
+            data.order_type,
+            data.id,
+            data.target_price
+        );
+
+        let txt_msg = ResponseBody {
+            response: ResponseType::ModifyOrderAccepted,
+            payload: Some(TradeResponse {
+                symbol,
+                accepted,
+                data,
+            }),
+        };
+
         Ok(txt_msg)
     }
 
@@ -583,10 +1033,11 @@ impl BrokerStream for Xtb {
         let command = CommandGetCandles {
             command: "getCandles".to_owned(),
             streamSessionId: self.streamSessionId.clone(),
-            symbol: symbol.to_owned(),
+            symbol: self.symbol_mapper.to_broker(symbol),
         };
 
         self.send_stream(&command).await.unwrap();
+        self.add_subscription("getCandles", symbol);
 
         Ok(())
     }
@@ -596,16 +1047,86 @@ impl BrokerStream for Xtb {
         let command = CommandTickStreamParams {
             command: "getTickPrices".to_owned(),
             streamSessionId: self.streamSessionId.clone(),
-            symbol: symbol.to_string(),
+            symbol: self.symbol_mapper.to_broker(symbol),
             minArrivalTime: 5000,
             maxLevel: 2,
         };
 
         self.send_stream(&command).await.unwrap();
+        self.add_subscription("getTickPrices", symbol);
+
+        Ok(())
+    }
+
+    async fn subscribe_trades(&mut self) -> Result<()> {
+        let command_trades = CommandStreaming {
+            command: "getTrades".to_owned(),
+            streamSessionId: self.streamSessionId.clone(),
+        };
+
+        self.send_stream(&command_trades).await.unwrap();
+
+        let command_status = CommandStreaming {
+            command: "getTradeStatus".to_owned(),
+            streamSessionId: self.streamSessionId.clone(),
+        };
+
+        self.send_stream(&command_status).await.unwrap();
+        self.add_subscription("getTrades", "");
 
         Ok(())
     }
 
+    async fn subscribe_news(&mut self) -> Result<()> {
+        let command = CommandStreaming {
+            command: "getNews".to_owned(),
+            streamSessionId: self.streamSessionId.clone(),
+        };
+
+        self.send_stream(&command).await.unwrap();
+        self.add_subscription("getNews", "");
+
+        Ok(())
+    }
+
+    async fn subscribe_funding_rates(&mut self, symbol: &str) -> Result<()> {
+        log::error!("{} funding rates not available on XTB", symbol);
+        Err(RsAlgoError {
+            err: RsAlgoErrorKind::NotSupported,
+        })
+    }
+
+    async fn subscribe_open_interest(&mut self, symbol: &str) -> Result<()> {
+        log::error!("{} open interest not available on XTB", symbol);
+        Err(RsAlgoError {
+            err: RsAlgoErrorKind::NotSupported,
+        })
+    }
+
+    async fn get_funding_rates(
+        &mut self,
+        symbol: &str,
+        _from: i64,
+        _to: i64,
+    ) -> Result<ResponseBody<Vec<FundingRate>>> {
+        log::error!("{} funding rates not available on XTB", symbol);
+        Err(RsAlgoError {
+            err: RsAlgoErrorKind::NotSupported,
+        })
+    }
+
+    async fn get_open_interest(
+        &mut self,
+        symbol: &str,
+        _from: i64,
+        _to: i64,
+    ) -> Result<ResponseBody<Vec<OpenInterest>>> {
+        log::error!("{} open interest not available on XTB", symbol);
+        Err(RsAlgoError {
+            err: RsAlgoErrorKind::NotSupported,
+        })
+    }
+
     async fn listen<F, T>(&mut self, symbol: &str, session_id: String, mut callback: F)
     where
         F: Send + FnMut(Message) -> T,
@@ -613,7 +1134,7 @@ impl BrokerStream for Xtb {
     {
     }
 
-    async fn parse_stream_data(msg: Message) -> Option<String> {
+    async fn parse_stream_data(&self, msg: Message) -> Option<String> {
         let txt = match msg {
             Message::Text(txt) => txt,
             _ => "".to_owned(),
@@ -626,12 +1147,16 @@ impl BrokerStream for Xtb {
                 let command = &obj["command"];
                 let data = &obj["data"];
                 if command == "candle" {
-                    let date = parse_time(data["ctm"].as_i64().unwrap() / 1000);
+                    let date = from_ctm(data["ctm"].as_i64().unwrap());
                     let open = data["open"].as_f64().unwrap();
                     let high = data["high"].as_f64().unwrap();
                     let low = data["low"].as_f64().unwrap();
                     let close = data["close"].as_f64().unwrap();
-                    let volume = data["vol"].as_f64().unwrap() * 1000.;
+                    let volume = Xtb::parse_stream_volume(
+                        data,
+                        &self.symbol_mapper,
+                        &self.volume_normalizer,
+                    );
 
                     let ohlc = (date, open, high, low, close, volume);
 
@@ -643,7 +1168,9 @@ impl BrokerStream for Xtb {
 
                     Some(serde_json::to_string(&msg).unwrap())
                 } else if command == "tickPrices" {
-                    let symbol = data["symbol"].as_str().unwrap().to_owned();
+                    let symbol = self
+                        .symbol_mapper
+                        .from_broker(data["symbol"].as_str().unwrap());
                     let ask = data["ask"].as_f64().unwrap();
                     let bid = data["bid"].as_f64().unwrap();
                     let spread = ask - bid;
@@ -653,6 +1180,20 @@ impl BrokerStream for Xtb {
                         payload: Some(pricing),
                     };
                     Some(serde_json::to_string(&msg).unwrap())
+                } else if command == "trade" || command == "tradeStatus" {
+                    let trade_update = Xtb::parse_trade_update(command, data, &self.symbol_mapper);
+                    log::info!("Trade update received {:?}", trade_update);
+                    let msg: ResponseBody<TradeUpdate> = ResponseBody {
+                        response: ResponseType::TradeUpdate,
+                        payload: Some(trade_update),
+                    };
+                    Some(serde_json::to_string(&msg).unwrap())
+                } else if command == "news" {
+                    let msg: ResponseBody<NewsItem> = ResponseBody {
+                        response: ResponseType::News,
+                        payload: Some(Xtb::parse_news_item(data)),
+                    };
+                    Some(serde_json::to_string(&msg).unwrap())
                 } else {
                     None
                 }
@@ -670,11 +1211,7 @@ impl BrokerStream for Xtb {
         };
 
         self.send(&ping_command).await.unwrap();
-        let msg = self.socket.read().await.unwrap();
-        let txt_msg = match msg {
-            Message::Text(txt) => txt,
-            _ => panic!(),
-        };
+        let txt_msg = self.socket.read_text().await?;
 
         Ok(txt_msg)
     }
@@ -687,10 +1224,86 @@ impl BrokerStream for Xtb {
 }
 
 impl Xtb {
+    pub fn symbol_mapper(&self) -> &SymbolMapper {
+        &self.symbol_mapper
+    }
+
+    pub fn volume_normalizer(&self) -> &VolumeNormalizer {
+        &self.volume_normalizer
+    }
+
+    fn add_subscription(&mut self, channel: &str, symbol: &str) {
+        let key = [channel, ":", symbol].concat();
+        let state = match self.subscriptions.contains_key(&key) {
+            true => SubscriptionState::Resubscribed,
+            false => SubscriptionState::Established,
+        };
+
+        log::info!("{} {} subscription {:?}", channel, symbol, state);
+
+        let event = SubscriptionEvent::new(channel, symbol, state);
+        self.subscriptions.insert(key, event.clone());
+        self.subscription_events.push(event);
+    }
+
+    pub fn track_subscriptions(
+        subscriptions: &mut HashMap<String, SubscriptionEvent>,
+        event: BrokerEvent,
+    ) -> Vec<BrokerEvent> {
+        let mut events = vec![];
+
+        match &event {
+            BrokerEvent::Disconnect => {
+                for subscription in subscriptions.values_mut().filter(|x| x.is_active()) {
+                    *subscription = SubscriptionEvent::new(
+                        &subscription.channel,
+                        &subscription.symbol,
+                        SubscriptionState::Dropped,
+                    );
+                    events.push(BrokerEvent::Subscription(subscription.clone()));
+                }
+            }
+            _ => {
+                if let Some(channel) = event.channel() {
+                    for subscription in subscriptions.values_mut().filter(|x| {
+                        x.channel == channel
+                            && (x.state == SubscriptionState::Established
+                                || x.state == SubscriptionState::Resubscribed)
+                    }) {
+                        *subscription = SubscriptionEvent::new(
+                            &subscription.channel,
+                            &subscription.symbol,
+                            SubscriptionState::Confirmed,
+                        );
+                        events.push(BrokerEvent::Subscription(subscription.clone()));
+                    }
+                }
+            }
+        };
+
+        events.push(event);
+        events
+    }
+
+    fn audit<T: Serialize>(
+        &self,
+        action: &str,
+        response: &ResponseBody<TradeResponse<T>>,
+    ) -> Result<()> {
+        if AuditJournal::is_dry_run() {
+            if let Some(payload) = &response.payload {
+                self.audit_journal
+                    .record(action, &payload.symbol, payload)?;
+            }
+        }
+        Ok(())
+    }
+
     async fn send<T>(&mut self, command: &T) -> Result<()>
     where
         for<'de> T: Serialize + Deserialize<'de> + Debug,
     {
+        self.rate_limiter.acquire().await;
         self.socket
             .send(&serde_json::to_string(&command).unwrap())
             .await?;
@@ -698,6 +1311,26 @@ impl Xtb {
         Ok(())
     }
 
+    /// Sends a trade transaction and returns the status the broker replied with
+    async fn send_transaction(&mut self, transaction: Transaction) -> Result<bool> {
+        let command = Command {
+            command: "tradeTransaction".to_owned(),
+            arguments: TradeTransactionInfo {
+                tradeTransInfo: transaction,
+            },
+        };
+
+        self.send(&command).await?;
+        let msg = self.socket.read().await?;
+        match msg {
+            Message::Text(txt) => {
+                let res = self.parse_message(&txt).await?;
+                Ok(res["status"].as_bool().unwrap_or(false))
+            }
+            _ => Ok(false),
+        }
+    }
+
     async fn send_stream<T>(&mut self, command: &T) -> Result<()>
     where
         for<'de> T: Serialize + Deserialize<'de> + Debug,
@@ -711,11 +1344,7 @@ impl Xtb {
     }
 
     async fn get_response(&mut self) -> Result<ResponseBody<InstrumentData<VEC_DOHLC>>> {
-        let msg = self.socket.read().await.unwrap();
-        let txt_msg = match msg {
-            Message::Text(txt) => txt,
-            _ => panic!(),
-        };
+        let txt_msg = self.socket.read_text().await?;
         let res = self.handle_response::<VEC_DOHLC>(&txt_msg).await.unwrap();
 
         Ok(res)
@@ -741,6 +1370,7 @@ impl Xtb {
                         symbol: "".to_owned(),
                         time_frame: TimeFrameType::from_number(self.time_frame),
                         data: vec![],
+                        gaps: vec![],
                     }),
                 }
             }
@@ -751,6 +1381,7 @@ impl Xtb {
                     symbol: self.symbol.clone(),
                     time_frame: TimeFrameType::from_number(self.time_frame),
                     data: self.parse_price_data(&data).await.unwrap(),
+                    gaps: vec![],
                 }),
             },
             _ => ResponseBody {
@@ -768,12 +1399,14 @@ impl Xtb {
         let pow = x.powf(digits);
         for obj in data["returnData"]["rateInfos"].as_array().unwrap() {
             //FIXME!!
-            let date = parse_time(obj["ctm"].as_i64().unwrap() / 1000);
+            let date = from_ctm(obj["ctm"].as_i64().unwrap());
             let open = obj["open"].as_f64().unwrap() / pow;
             let high = open + obj["high"].as_f64().unwrap() / pow;
             let low = open + obj["low"].as_f64().unwrap() / pow;
             let close = open + obj["close"].as_f64().unwrap() / pow;
-            let volume = obj["vol"].as_f64().unwrap() * 1000.;
+            let volume = self
+                .volume_normalizer
+                .normalize(&self.symbol, obj["vol"].as_f64().unwrap());
 
             result.push((date, open, high, low, close, volume));
         }
@@ -793,6 +1426,247 @@ impl Xtb {
         Ok(pricing)
     }
 
+    pub async fn parse_symbol_availability(
+        &mut self,
+        symbol: String,
+        txt: String,
+    ) -> Result<SymbolAvailability> {
+        let data = self.parse_message(&txt).await.unwrap();
+        let symbol_data = &data["returnData"];
+        let quote_id = symbol_data["quoteId"].as_i64().unwrap_or(0);
+        let has_quotes = symbol_data["ask"].as_f64().unwrap_or(0.) > 0.
+            && symbol_data["bid"].as_f64().unwrap_or(0.) > 0.;
+
+        //quoteId 4 (cross) instruments are quoted for reference only
+        let availability = SymbolAvailability {
+            symbol,
+            trailing_enabled: symbol_data["trailingEnabled"].as_bool().unwrap_or(false),
+            short_selling: symbol_data["shortSelling"].as_bool().unwrap_or(false),
+            long_only: symbol_data["longOnly"].as_bool().unwrap_or(false),
+            quote_id,
+            trading_enabled: has_quotes && quote_id != 4,
+        };
+
+        Ok(availability)
+    }
+
+    pub fn parse_stream_volume(
+        data: &Value,
+        symbol_mapper: &SymbolMapper,
+        volume_normalizer: &VolumeNormalizer,
+    ) -> f64 {
+        let symbol = symbol_mapper.from_broker(data["symbol"].as_str().unwrap_or(""));
+        volume_normalizer.normalize(&symbol, data["vol"].as_f64().unwrap_or(0.))
+    }
+
+    pub fn parse_stream_event(
+        msg: Message,
+        symbol_mapper: &SymbolMapper,
+        volume_normalizer: &VolumeNormalizer,
+    ) -> Option<BrokerEvent> {
+        let txt = match msg {
+            Message::Text(txt) => txt,
+            Message::Close(_) => return Some(BrokerEvent::Disconnect),
+            _ => return None,
+        };
+
+        let obj: Value = match serde_json::from_str(&txt) {
+            Ok(obj) => obj,
+            Err(err) => {
+                log::error!("Malformed stream frame {}: {}", txt, err);
+                return None;
+            }
+        };
+        let command = &obj["command"];
+        let data = &obj["data"];
+
+        if command == "candle" {
+            let candle = || {
+                let date = Local.timestamp_millis_opt(data["ctm"].as_i64()?).single()?;
+                let open = data["open"].as_f64()?;
+                let high = data["high"].as_f64()?;
+                let low = data["low"].as_f64()?;
+                let close = data["close"].as_f64()?;
+                let volume = Xtb::parse_stream_volume(data, symbol_mapper, volume_normalizer);
+                Some((date, open, high, low, close, volume))
+            };
+
+            match candle() {
+                Some(candle) => Some(BrokerEvent::Candle(candle)),
+                None => {
+                    log::error!("Malformed candle frame {}", txt);
+                    None
+                }
+            }
+        } else if command == "tickPrices" {
+            match (
+                data["symbol"].as_str(),
+                data["ask"].as_f64(),
+                data["bid"].as_f64(),
+            ) {
+                (Some(symbol), Some(ask), Some(bid)) => {
+                    let symbol = symbol_mapper.from_broker(symbol);
+                    let spread = ask - bid;
+                    let pricing = Pricing::new(symbol, ask, bid, spread, 0., 0.);
+                    Some(BrokerEvent::Tick(pricing))
+                }
+                _ => {
+                    log::error!("Malformed tick frame {}", txt);
+                    None
+                }
+            }
+        } else if command == "trade" || command == "tradeStatus" {
+            Some(BrokerEvent::TradeUpdate(Xtb::parse_trade_update(
+                command,
+                data,
+                symbol_mapper,
+            )))
+        } else if command == "news" {
+            Some(BrokerEvent::News(Xtb::parse_news_item(data)))
+        } else if command == "keepAlive" {
+            Some(BrokerEvent::KeepAlive)
+        } else {
+            None
+        }
+    }
+
+    pub fn parse_trade_update(
+        command: &Value,
+        data: &Value,
+        symbol_mapper: &SymbolMapper,
+    ) -> TradeUpdate {
+        let as_f64 = |key: &str| data[key].as_f64().unwrap_or(0.);
+        let as_string = |key: &str| data[key].as_str().unwrap_or("").to_owned();
+
+        match command == "tradeStatus" {
+            true => {
+                let status = match data["requestStatus"].as_i64() {
+                    Some(1) => TradeStatus::Pending,
+                    Some(3) => TradeStatus::Accepted,
+                    Some(4) => TradeStatus::Rejected,
+                    _ => TradeStatus::Error,
+                };
+
+                TradeUpdate {
+                    order: data["order"].as_i64().unwrap_or(0) as isize,
+                    symbol: "".to_owned(),
+                    status,
+                    price: as_f64("price"),
+                    sl: 0.,
+                    tp: 0.,
+                    volume: 0.,
+                    profit: 0.,
+                    comment: as_string("message"),
+                }
+            }
+            false => {
+                let closed = data["closed"].as_bool().unwrap_or(false);
+                let status = match (closed, data["state"].as_str(), data["type"].as_i64()) {
+                    (true, _, _) => TradeStatus::Closed,
+                    (_, Some("Deleted"), _) | (_, _, Some(4)) => TradeStatus::Deleted,
+                    (_, _, Some(1)) => TradeStatus::Pending,
+                    (_, _, Some(3)) => TradeStatus::Modified,
+                    _ => TradeStatus::Opened,
+                };
+
+                let price = match closed {
+                    true => as_f64("close_price"),
+                    false => as_f64("open_price"),
+                };
+
+                TradeUpdate {
+                    order: data["order"].as_i64().unwrap_or(0) as isize,
+                    symbol: symbol_mapper.from_broker(&as_string("symbol")),
+                    status,
+                    price,
+                    sl: as_f64("sl"),
+                    tp: as_f64("tp"),
+                    volume: as_f64("volume"),
+                    profit: as_f64("profit"),
+                    comment: as_string("customComment"),
+                }
+            }
+        }
+    }
+
+    /// Spacings longer than the time frame missing a bar inside market hours.
+    /// Weekends and session closes are not gaps.
+    pub fn find_gaps(
+        data: &VEC_DOHLC,
+        time_frame: usize,
+        market_hours: &MarketHours,
+    ) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+        let step = Duration::minutes(time_frame as i64);
+        let is_missing_bar = |from: DateTime<Local>, to: DateTime<Local>| {
+            let mut date = from + step;
+            while date < to {
+                if market_hours.is_open_at(date) {
+                    return true;
+                }
+                date = date + step;
+            }
+            false
+        };
+
+        data.windows(2)
+            .filter(|pair| is_missing_bar(pair[0].0, pair[1].0))
+            .map(|pair| (pair[0].0, pair[1].0))
+            .collect()
+    }
+
+    pub fn parse_news_item(data: &Value) -> NewsItem {
+        NewsItem {
+            key: data["key"].as_str().unwrap_or("").to_owned(),
+            date: parse_time(data["time"].as_i64().unwrap_or(0) / 1000),
+            title: data["title"].as_str().unwrap_or("").to_owned(),
+            body: data["body"].as_str().unwrap_or("").to_owned(),
+        }
+    }
+
+    pub fn parse_calendar_data(&mut self, data: &Value) -> Result<Vec<CalendarEvent>> {
+        let mut result: Vec<CalendarEvent> = vec![];
+        let as_string = |obj: &Value, key: &str| obj[key].as_str().unwrap_or("").to_owned();
+
+        for obj in data["returnData"].as_array().unwrap() {
+            let impact = match obj["impact"].as_str() {
+                Some("3") => CalendarImpact::High,
+                Some("2") => CalendarImpact::Medium,
+                _ => CalendarImpact::Low,
+            };
+
+            result.push(CalendarEvent {
+                date: parse_time(obj["time"].as_i64().unwrap() / 1000),
+                country: as_string(obj, "country"),
+                title: as_string(obj, "title"),
+                impact,
+                period: as_string(obj, "period"),
+                current: as_string(obj, "current"),
+                forecast: as_string(obj, "forecast"),
+                previous: as_string(obj, "previous"),
+            });
+        }
+
+        Ok(result)
+    }
+
+    pub fn parse_command_event(txt: &str) -> Option<BrokerEvent> {
+        let obj: Value = match serde_json::from_str(txt) {
+            Ok(obj) => obj,
+            Err(err) => {
+                log::error!("Malformed command frame {}: {}", txt, err);
+                return None;
+            }
+        };
+        match (&obj["status"], &obj["returnData"]) {
+            (Value::Bool(true), Value::Null) => Some(BrokerEvent::KeepAlive),
+            (Value::Bool(false), _) => {
+                log::error!("Command socket error response {:?}", obj);
+                None
+            }
+            _ => None,
+        }
+    }
+
     pub fn parse_market_hours(&mut self, data: &Value) -> Result<Vec<MarketHour>> {
         let mut result: Vec<MarketHour> = vec![];
         let current_date = Local::now();
@@ -814,13 +1688,7 @@ impl Xtb {
         Ok(result)
     }
 
-    pub fn parse_symbol(symbol: String) -> Result<String> {
-        if symbol.contains('_') {
-            let symbol_str: Vec<&str> = symbol.split('_').collect();
-            Ok(symbol_str[0].to_owned())
-        } else {
-            log::error!("Change fucking xtb");
-            Ok(symbol)
-        }
+    pub fn parse_symbol(&self, symbol: String) -> Result<String> {
+        Ok(self.symbol_mapper.from_broker(&symbol))
     }
 }
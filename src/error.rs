@@ -13,6 +13,48 @@ pub enum RsAlgoErrorKind {
     InvalidPeak,
     #[error("Error on Request!")]
     RequestError,
+    #[error("Invalid Config!")]
+    InvalidConfig,
+    #[error("Error on Audit Journal!")]
+    AuditError,
+    #[error("Short Selling Disabled!")]
+    ShortSellingDisabled,
+    #[error("Instrument Halted!")]
+    InstrumentHalted,
+    #[error("Not supported by broker!")]
+    NotSupported,
+    #[error("Error on Order Journal!")]
+    JournalError,
+    #[error("Invalid Indicator Params!")]
+    InvalidIndicatorParams,
+    #[error("Error on Instrument Snapshot!")]
+    SnapshotError,
+    #[error("Error on WebSocket!")]
+    WebSocketError,
+    #[error("Invalid Strategy!")]
+    InvalidStrategy,
+}
+
+#[derive(Clone, PartialEq, Debug, Error)]
+pub enum OrderError {
+    #[error("Target price {target} is lower than {price}")]
+    TargetBelowPrice { target: f64, price: f64 },
+    #[error("Target price {target} is higher than {price}")]
+    TargetAbovePrice { target: f64, price: f64 },
+    #[error("Stop loss {stop} can't be placed higher than buy level {buy}")]
+    StopLossAboveEntry { buy: f64, stop: f64 },
+    #[error("Stop loss {stop} can't be placed lower than buy level {buy}")]
+    StopLossBelowEntry { buy: f64, stop: f64 },
+    #[error("Pending order {id} not found")]
+    NotFound { id: usize },
+    #[error("Invalid order size {size}, already filled {filled}")]
+    InvalidSize { size: f64, filled: f64 },
+    #[error("Invalid target price {target}")]
+    InvalidTarget { target: f64 },
+    #[error("Can't size order at {target}")]
+    Unsized { target: f64 },
+    #[error("No order filter given")]
+    NoFilter,
 }
 
 #[derive(Debug, Error)]
@@ -31,10 +31,31 @@ pub fn calculate_profit(size: f64, price_in: f64, price_out: f64, trade_type: &T
     }
 }
 
+pub fn calculate_profit_in_account(
+    size: f64,
+    price_in: f64,
+    price_out: f64,
+    trade_type: &TradeType,
+    conversion_rate: f64,
+) -> f64 {
+    calculate_profit(size, price_in, price_out, trade_type) * conversion_rate
+}
+
 pub fn to_pips(pips: f64, pricing: &Pricing) -> f64 {
     pricing.pip_size() * pips
 }
 
+pub fn from_pips(distance: f64, pricing: &Pricing) -> f64 {
+    match pricing.pip_size() > 0. {
+        true => distance / pricing.pip_size(),
+        false => distance,
+    }
+}
+
+pub fn calculate_breakeven_pips(pricing: &Pricing, commission_pips: f64) -> f64 {
+    from_pips(pricing.spread(), pricing) + commission_pips
+}
+
 pub fn calculate_profit_per(price_in: f64, price_out: f64, trade_type: &TradeType) -> f64 {
     match trade_type.is_long() {
         true => ((price_out - price_in) / price_in) * 100.,
@@ -46,3 +46,45 @@ pub fn is_dst(datetime: &DateTime<Local>) -> bool {
     //UTC +1
     local_minus_utc == 3600
 }
+
+pub fn now_dbtime() -> DbDateTime {
+    to_dbtime(Local::now())
+}
+
+pub fn utc_to_dbtime(date: DateTime<Utc>) -> DbDateTime {
+    DbDateTime::from_millis(date.timestamp_millis())
+}
+
+pub fn dbtime_to_utc(date: &DbDateTime) -> DateTime<Utc> {
+    Utc.timestamp_millis(date.timestamp_millis())
+}
+
+pub fn to_millis<Tz: TimeZone>(date: &DateTime<Tz>) -> i64 {
+    date.timestamp_millis()
+}
+
+pub fn from_millis(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis(millis)
+}
+
+/// XTB `ctm` is epoch millis in UTC
+pub fn from_ctm(ctm: i64) -> DateTime<Local> {
+    Local.timestamp_millis(ctm)
+}
+
+pub fn to_ctm(date: DateTime<Local>) -> i64 {
+    date.timestamp_millis()
+}
+
+pub fn to_iso8601<Tz: TimeZone>(date: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    date.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+pub fn from_iso8601(date: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(date)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
@@ -14,6 +14,23 @@ pub fn maxima_minima(
         .unwrap()
         .parse::<bool>()
         .unwrap();
+    maxima_minima_scaled(
+        x_values,
+        y_values,
+        min_prominence,
+        min_distance,
+        logarithmic,
+    )
+}
+
+/// Peak values are exponentiated back when the prices are ln scaled.
+pub fn maxima_minima_scaled(
+    x_values: &Vec<f64>,
+    y_values: &Vec<f64>,
+    min_prominence: f64,
+    min_distance: usize,
+    logarithmic: bool,
+) -> Result<Vec<(usize, f64)>> {
     let result: Vec<(usize, f64)> = PeakFinder::new(x_values)
         .with_min_prominence(min_prominence)
         .with_min_distance(min_distance)
@@ -54,6 +71,61 @@ pub fn maxima_minima_exp(
     Ok(result)
 }
 
+/// Bill Williams fractals: points higher than the `bars` values at each side.
+/// On equal values only the first one is taken. The last `bars` values can't
+/// be confirmed yet.
+pub fn fractals_scaled(
+    x_values: &Vec<f64>,
+    y_values: &Vec<f64>,
+    bars: usize,
+    logarithmic: bool,
+) -> Result<Vec<(usize, f64)>> {
+    let bars = bars.max(1);
+    let len = x_values.len();
+    if len < bars * 2 + 1 {
+        return Ok(vec![]);
+    }
+
+    let result: Vec<(usize, f64)> = (bars..len - bars)
+        .filter(|x| {
+            let value = x_values[*x];
+            x_values[x - bars..*x].iter().all(|left| value > *left)
+                && x_values[x + 1..=x + bars]
+                    .iter()
+                    .all(|right| value >= *right)
+        })
+        .map(|x| {
+            let y = y_values[x];
+            let y = match logarithmic {
+                true => y.exp(),
+                false => y,
+            };
+            (x, y)
+        })
+        .collect();
+
+    Ok(result)
+}
+
+/// Height of the peak over the highest of the lowest values at each side,
+/// searched until a higher value or the end of the data.
+pub fn peak_prominence(values: &[f64], index: usize) -> f64 {
+    let value = match values.get(index) {
+        Some(value) => *value,
+        None => return 0.,
+    };
+
+    let left = side_base(values[..index].iter().rev(), value);
+    let right = side_base(values[index + 1..].iter(), value);
+
+    value - left.max(right)
+}
+
+fn side_base<'a>(side: impl Iterator<Item = &'a f64>, value: f64) -> f64 {
+    side.take_while(|x| **x <= value)
+        .fold(value, |acc, x| acc.min(*x))
+}
+
 pub fn peaks_are_sorted<T: IntoIterator>(t: T) -> Ordering
 where
     <T as IntoIterator>::Item: std::cmp::PartialOrd,
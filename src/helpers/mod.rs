@@ -4,6 +4,7 @@ pub mod date;
 pub mod http;
 pub mod maxima_minima;
 pub mod poly;
+pub mod random;
 pub mod regression;
 pub mod slope_intercept;
 pub mod status;
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::env;
 
 fn gauss_const(h: f64) -> f64 {
@@ -39,3 +40,126 @@ pub fn kernel_regression(bandwidth: f64, x: f64, data: &Vec<f64>) -> f64 {
     let w = weights(bandwidth, x, data, logarithmic);
     data.iter().zip(w.iter()).map(|(a, b)| (a * b)).sum()
 }
+
+/// Least squares fit of the points. Returns slope, intercept and r squared.
+pub fn linear_regression(points: &[(usize, f64)]) -> Option<(f64, f64, f64)> {
+    let num = points.len() as f64;
+    if points.len() < 2 {
+        return None;
+    }
+
+    let mean_x = points.iter().map(|(x, _)| *x as f64).sum::<f64>() / num;
+    let mean_y = points.iter().map(|(_, y)| *y).sum::<f64>() / num;
+
+    let (mut sxx, mut sxy, mut syy) = (0., 0., 0.);
+    for (x, y) in points {
+        let dx = *x as f64 - mean_x;
+        let dy = *y - mean_y;
+        sxx += dx * dx;
+        sxy += dx * dy;
+        syy += dy * dy;
+    }
+
+    if sxx == 0. {
+        return None;
+    }
+
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let r_squared = match syy == 0. {
+        true => 1.,
+        false => (sxy * sxy) / (sxx * syy),
+    };
+
+    Some((slope, intercept, r_squared))
+}
+
+/// Running `kernel_regression` of every point against all the values. Keeps
+/// the weighted sums per point, so pushing, updating or removing a value
+/// costs O(n) kernel evaluations instead of smoothing everything again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct KernelRegression {
+    bandwidth: f64,
+    logarithmic: bool,
+    x: Vec<f64>,
+    data: Vec<f64>,
+    weighted_sums: Vec<f64>,
+    kernel_sums: Vec<f64>,
+}
+
+impl KernelRegression {
+    pub fn new(bandwidth: f64, logarithmic: bool) -> Self {
+        Self {
+            bandwidth,
+            logarithmic,
+            ..Default::default()
+        }
+    }
+
+    pub fn bandwidth(&self) -> f64 {
+        self.bandwidth
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Adds a value, smoothed at `x`.
+    pub fn push(&mut self, x: f64, value: f64) {
+        self.add_contribution(value, 1.);
+        self.x.push(x);
+        self.data.push(value);
+
+        let (weighted_sum, kernel_sum) = self.sums_at(x);
+        self.weighted_sums.push(weighted_sum);
+        self.kernel_sums.push(kernel_sum);
+    }
+
+    pub fn update_last(&mut self, x: f64, value: f64) {
+        if let Some(last) = self.data.pop() {
+            self.x.pop();
+            self.weighted_sums.pop();
+            self.kernel_sums.pop();
+            self.add_contribution(last, -1.);
+        }
+        self.push(x, value);
+    }
+
+    pub fn remove_first(&mut self, num: usize) {
+        let num = num.min(self.data.len());
+        self.x.drain(..num);
+        self.weighted_sums.drain(..num);
+        self.kernel_sums.drain(..num);
+        let removed: Vec<f64> = self.data.drain(..num).collect();
+        for value in removed {
+            self.add_contribution(value, -1.);
+        }
+    }
+
+    pub fn value(&self, index: usize) -> f64 {
+        self.weighted_sums[index] / self.kernel_sums[index]
+    }
+
+    pub fn values(&self) -> Vec<f64> {
+        (0..self.data.len()).map(|index| self.value(index)).collect()
+    }
+
+    fn sums_at(&self, x: f64) -> (f64, f64) {
+        self.data.iter().fold((0., 0.), |(weighted_sum, kernel_sum), value| {
+            let k = kernel_function(self.bandwidth, x, *value, self.logarithmic);
+            (weighted_sum + value * k, kernel_sum + k)
+        })
+    }
+
+    fn add_contribution(&mut self, value: f64, sign: f64) {
+        for (index, x) in self.x.iter().enumerate() {
+            let k = kernel_function(self.bandwidth, *x, value, self.logarithmic);
+            self.weighted_sums[index] += sign * value * k;
+            self.kernel_sums[index] += sign * k;
+        }
+    }
+}
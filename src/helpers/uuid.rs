@@ -1,6 +1,7 @@
 pub use bson::Uuid;
 use chrono::{DateTime, Local};
 use std::collections::hash_map::DefaultHasher;
+use std::env;
 use std::hash::{Hash, Hasher};
 
 pub fn to_be_bytes(hash: &str) -> [u8; 16] {
@@ -26,3 +27,52 @@ pub fn from_str(uuid: String) -> Uuid {
 pub fn generate_ts_id(date: DateTime<Local>) -> usize {
     (date.timestamp_millis() / 1000) as usize
 }
+
+/// Order and trade ids. Owned by the bot or the backtest run and passed to
+/// the functions that create orders and trades.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdGenerator {
+    Timestamp,
+    Monotonic { last: usize },
+    Sequential { next: usize },
+}
+
+impl Default for IdGenerator {
+    fn default() -> Self {
+        IdGenerator::Monotonic { last: 0 }
+    }
+}
+
+impl IdGenerator {
+    /// ID_GENERATOR is "monotonic" (default), "timestamp" or "sequential"
+    /// starting at ID_SEED
+    pub fn from_env() -> Self {
+        let generator = env::var("ID_GENERATOR").unwrap_or("monotonic".to_string());
+        match generator.as_ref() {
+            "timestamp" => IdGenerator::Timestamp,
+            "sequential" => {
+                let seed = env::var("ID_SEED")
+                    .unwrap_or("1".to_string())
+                    .parse::<usize>()
+                    .unwrap();
+                IdGenerator::Sequential { next: seed }
+            }
+            _ => IdGenerator::Monotonic { last: 0 },
+        }
+    }
+
+    pub fn next(&mut self, date: DateTime<Local>) -> usize {
+        match self {
+            IdGenerator::Timestamp => generate_ts_id(date),
+            IdGenerator::Monotonic { last } => {
+                *last = generate_ts_id(date).max(*last + 1);
+                *last
+            }
+            IdGenerator::Sequential { next } => {
+                let id = *next;
+                *next += 1;
+                id
+            }
+        }
+    }
+}
@@ -1,60 +1,58 @@
-use super::Indicator;
-use crate::error::Result;
+use super::{new_outputs, push_outputs, update_outputs, Indicator, Outputs, StoredIndicator};
+use crate::error::{Result, RsAlgoError};
+use crate::indicators::params::IndicatorParams;
 
 use serde::{Deserialize, Serialize};
 use ta::indicators::AverageDirectionalIndex;
 use ta::{Next, Reset};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "StoredIndicator")]
 pub struct Adx {
     #[serde(skip_deserializing)]
     adx: AverageDirectionalIndex,
     #[serde(skip_deserializing)]
     adx_tmp: AverageDirectionalIndex,
-    data_a: Vec<f64>,
-    data_b: Vec<f64>,
-    data_c: Vec<f64>,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
 }
 
-impl Indicator for Adx {
-    fn new() -> Result<Self> {
-        Ok(Self {
-            adx: AverageDirectionalIndex::new(14).unwrap(),
-            adx_tmp: AverageDirectionalIndex::new(14).unwrap(),
-            data_a: vec![],
-            data_b: vec![],
-            data_c: vec![],
-        })
-    }
+impl TryFrom<StoredIndicator> for Adx {
+    type Error = RsAlgoError;
 
-    fn get_data_a(&self) -> &Vec<f64> {
-        &self.data_a
+    fn try_from(stored: StoredIndicator) -> Result<Self> {
+        stored.restore()
     }
+}
 
-    fn get_current_a(&self) -> &f64 {
-        &self.data_a.last().unwrap()
-    }
+impl Indicator for Adx {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        let period = params.period_or(14)?;
 
-    fn get_data_b(&self) -> &Vec<f64> {
-        &self.data_b
+        Ok(Self {
+            adx: AverageDirectionalIndex::new(period).unwrap(),
+            adx_tmp: AverageDirectionalIndex::new(period).unwrap(),
+            params: params.clone(),
+            outputs: new_outputs(&["adx"]),
+        })
     }
 
-    fn get_current_b(&self) -> &f64 {
-        let max = self.data_a.len() - 1;
-        &self.data_b[max]
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
     }
 
-    fn get_data_c(&self) -> &Vec<f64> {
-        &self.data_c
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
     }
 
-    fn get_current_c(&self) -> &f64 {
-        &self.data_c.last().unwrap()
+    fn params(&self) -> &IndicatorParams {
+        &self.params
     }
 
     fn next(&mut self, value: f64) -> Result<()> {
         let a = self.adx.next(value);
-        self.data_a.push(a);
+        push_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
 
@@ -63,14 +61,10 @@ impl Indicator for Adx {
     }
 
     //FIXME MONEKY PATCHING
-    fn next_OHLC(&mut self, _OHLC: (f64, f64, f64, f64)) -> Result<()> {
-        Ok(())
-    }
 
     fn update(&mut self, value: f64) -> Result<()> {
         let a = self.adx.next(value);
-        let last = self.data_a.last_mut().unwrap();
-        *last = a;
+        update_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
 
@@ -80,25 +74,7 @@ impl Indicator for Adx {
 
     fn update_tmp(&mut self, value: f64) -> Result<()> {
         let a = self.adx_tmp.next(value);
-        let last = self.data_a.last_mut().unwrap();
-        *last = a;
+        update_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
-
-    fn remove_a(&mut self, index: usize) -> f64 {
-        self.data_a.remove(index)
-    }
-
-    fn remove_b(&mut self, index: usize) -> f64 {
-        self.data_b.remove(index)
-    }
-
-    fn remove_c(&mut self, index: usize) -> f64 {
-        self.data_c.remove(index)
-    }
-
-    fn duplicate_last(&mut self) {
-        let a = self.data_a.last().unwrap();
-        self.data_a.insert(0, 0.);
-    }
 }
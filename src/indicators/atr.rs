@@ -1,5 +1,9 @@
-use super::Indicator;
-use crate::error::Result;
+use super::{
+    new_outputs, push_outputs, rehydrate, replay_OHLC, update_outputs, Indicator, Outputs,
+    StoredIndicator,
+};
+use crate::error::{Result, RsAlgoError};
+use crate::indicators::params::IndicatorParams;
 
 use serde::{Deserialize, Serialize};
 use ta::indicators::AverageTrueRange;
@@ -7,55 +11,82 @@ use ta::test_helper::Bar;
 use ta::{Next, Reset};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "StoredIndicator")]
 pub struct Atr {
     #[serde(skip_deserializing)]
     atr: AverageTrueRange,
     #[serde(skip_deserializing)]
     atr_tmp: AverageTrueRange,
-    data_a: Vec<f64>,
-    data_b: Vec<f64>,
-    data_c: Vec<f64>,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
+}
+
+impl TryFrom<StoredIndicator> for Atr {
+    type Error = RsAlgoError;
+
+    fn try_from(stored: StoredIndicator) -> Result<Self> {
+        stored.restore()
+    }
+}
+
+fn to_bar(OHLC: (f64, f64, f64, f64)) -> Bar {
+    Bar::new()
+        .open(OHLC.0)
+        .high(OHLC.1)
+        .low(OHLC.2)
+        .close(OHLC.3)
 }
 
 impl Indicator for Atr {
-    fn new() -> Result<Self> {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        let period = params.period_or(14)?;
+
         Ok(Self {
-            atr: AverageTrueRange::new(14).unwrap(),
-            atr_tmp: AverageTrueRange::new(14).unwrap(),
-            data_a: vec![],
-            data_b: vec![],
-            data_c: vec![],
+            atr: AverageTrueRange::new(period).unwrap(),
+            atr_tmp: AverageTrueRange::new(period).unwrap(),
+            params: params.clone(),
+            outputs: new_outputs(&["atr"]),
         })
     }
 
-    fn get_data_a(&self) -> &Vec<f64> {
-        &self.data_a
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
     }
 
-    fn get_current_a(&self) -> &f64 {
-        &self.data_a.last().unwrap()
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
     }
 
-    fn get_data_b(&self) -> &Vec<f64> {
-        &self.data_b
+    fn params(&self) -> &IndicatorParams {
+        &self.params
     }
 
-    fn get_current_b(&self) -> &f64 {
-        let max = self.data_a.len() - 1;
-        &self.data_b[max]
+    /// Feeds the closed bar into the slot created by duplicate_last.
+    fn update_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let a = self.atr.next(&to_bar(OHLC));
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
     }
 
-    fn get_data_c(&self) -> &Vec<f64> {
-        &self.data_c
+    fn next_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) {
+        self.atr_tmp.next(&to_bar(OHLC));
     }
 
-    fn get_current_c(&self) -> &f64 {
-        &self.data_c.last().unwrap()
+    fn update_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let a = self.atr_tmp.next(&to_bar(OHLC));
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn warm_up_OHLC(&mut self, data: &[(f64, f64, f64, f64)]) -> Result<()> {
+        let params = self.params.clone();
+        rehydrate(self, &params, |indicator| replay_OHLC(indicator, data))
     }
 
     fn next(&mut self, value: f64) -> Result<()> {
         let a = self.atr.next(value);
-        self.data_a.push(a);
+        push_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
 
@@ -63,46 +94,25 @@ impl Indicator for Atr {
         self.atr_tmp.next(value);
     }
 
-    //FIXME MONEKY PATCHING
     fn next_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
-        let bar = Bar::new().high(OHLC.1).low(OHLC.2).close(OHLC.3);
-        let a = self.atr.next(&bar);
-        self.data_a.push(a);
+        let a = self.atr.next(&to_bar(OHLC));
+        push_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
 
     fn update(&mut self, value: f64) -> Result<()> {
         let a = self.atr.next(value);
-        let last = self.data_a.last_mut().unwrap();
-        *last = a;
+        update_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
 
     fn update_tmp(&mut self, value: f64) -> Result<()> {
         let a = self.atr_tmp.next(value);
-        let last = self.data_a.last_mut().unwrap();
-        *last = a;
+        update_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
 
     fn reset_tmp(&mut self) {
         self.atr_tmp.reset();
     }
-
-    fn remove_a(&mut self, index: usize) -> f64 {
-        self.data_a.remove(index)
-    }
-
-    fn remove_b(&mut self, index: usize) -> f64 {
-        self.data_b.remove(index)
-    }
-
-    fn remove_c(&mut self, index: usize) -> f64 {
-        self.data_c.remove(index)
-    }
-
-    fn duplicate_last(&mut self) {
-        let a = self.data_a.last().unwrap();
-        self.data_a.push(*a);
-    }
 }
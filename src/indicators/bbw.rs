@@ -1,61 +1,61 @@
-use super::Indicator;
-use crate::error::Result;
+use super::{new_outputs, push_outputs, update_outputs, Indicator, Outputs, StoredIndicator};
+use crate::error::{Result, RsAlgoError};
+use crate::indicators::params::IndicatorParams;
 
 use serde::{Deserialize, Serialize};
 use ta::indicators::BollingerBands;
 use ta::{Next, Reset};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "StoredIndicator")]
 pub struct BollingerBW {
     #[serde(skip_deserializing)]
     bb: BollingerBands,
     #[serde(skip_deserializing)]
     bb_tmp: BollingerBands,
-    data_a: Vec<f64>,
-    data_b: Vec<f64>,
-    data_c: Vec<f64>,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
 }
 
-impl Indicator for BollingerBW {
-    fn new() -> Result<Self> {
-        Ok(Self {
-            bb: BollingerBands::new(20, 2.0).unwrap(),
-            bb_tmp: BollingerBands::new(20, 2.0).unwrap(),
-            data_a: vec![],
-            data_b: vec![],
-            data_c: vec![],
-        })
-    }
+impl TryFrom<StoredIndicator> for BollingerBW {
+    type Error = RsAlgoError;
 
-    fn get_data_a(&self) -> &Vec<f64> {
-        &self.data_a
+    fn try_from(stored: StoredIndicator) -> Result<Self> {
+        stored.restore()
     }
+}
 
-    fn get_current_a(&self) -> &f64 {
-        &self.data_a.last().unwrap()
-    }
+impl Indicator for BollingerBW {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        let period = params.period_or(20)?;
+        let multiplier = params.multiplier_or(2.0)?;
 
-    fn get_data_b(&self) -> &Vec<f64> {
-        &self.data_b
+        Ok(Self {
+            bb: BollingerBands::new(period, multiplier).unwrap(),
+            bb_tmp: BollingerBands::new(period, multiplier).unwrap(),
+            params: params.clone(),
+            outputs: new_outputs(&["width"]),
+        })
     }
 
-    fn get_current_b(&self) -> &f64 {
-        &self.data_b.last().unwrap()
+    //FIXME return self to get values from other indicators
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
     }
 
-    fn get_data_c(&self) -> &Vec<f64> {
-        &self.data_c
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
     }
 
-    fn get_current_c(&self) -> &f64 {
-        &self.data_c.last().unwrap()
+    fn params(&self) -> &IndicatorParams {
+        &self.params
     }
 
-    //FIXME return self to get values from other indicators
     fn next(&mut self, value: f64) -> Result<()> {
         let a = self.bb.next(value);
         let w = (a.upper - a.lower) / a.average;
-        self.data_a.push(w);
+        push_outputs(&mut self.outputs, &[w]);
         Ok(())
     }
 
@@ -66,45 +66,18 @@ impl Indicator for BollingerBW {
     fn update(&mut self, value: f64) -> Result<()> {
         let a = self.bb.next(value);
         let w = (a.upper - a.lower) / a.average;
-        let last_a = self.data_a.last_mut().unwrap();
-        *last_a = w;
+        update_outputs(&mut self.outputs, &[w]);
         Ok(())
     }
 
     fn update_tmp(&mut self, value: f64) -> Result<()> {
         let a = self.bb_tmp.next(value);
         let w = (a.upper - a.lower) / a.average;
-        let last_a = self.data_a.last_mut().unwrap();
-        *last_a = w;
+        update_outputs(&mut self.outputs, &[w]);
         Ok(())
     }
 
     fn reset_tmp(&mut self) {
         self.bb_tmp.reset();
     }
-
-    fn next_OHLC(&mut self, _OHLC: (f64, f64, f64, f64)) -> Result<()> {
-        Ok(())
-    }
-
-    fn remove_a(&mut self, index: usize) -> f64 {
-        self.data_a.remove(index)
-    }
-
-    fn remove_b(&mut self, index: usize) -> f64 {
-        self.data_b.remove(index)
-    }
-
-    fn remove_c(&mut self, index: usize) -> f64 {
-        self.data_c.remove(index)
-    }
-
-    fn duplicate_last(&mut self) {
-        let a = self.data_a.last().unwrap();
-        let b = self.data_b.last().unwrap();
-        let c = self.data_c.last().unwrap();
-        self.data_a.push(*a);
-        self.data_b.push(*b);
-        self.data_c.push(*c);
-    }
 }
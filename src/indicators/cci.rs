@@ -0,0 +1,138 @@
+use super::{
+    new_outputs, push_outputs, rehydrate, replay_OHLC, update_outputs, Indicator, Outputs,
+};
+use crate::error::Result;
+use crate::indicators::params::IndicatorParams;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default)]
+struct CciState {
+    period: usize,
+    typical_prices: Vec<f64>,
+}
+
+impl CciState {
+    fn new(period: usize) -> Self {
+        Self {
+            period,
+            typical_prices: vec![],
+        }
+    }
+
+    fn next(&mut self, high: f64, low: f64, close: f64) -> f64 {
+        let typical_price = (high + low + close) / 3.;
+        self.typical_prices.push(typical_price);
+        if self.typical_prices.len() > self.period {
+            self.typical_prices.remove(0);
+        }
+
+        let len = self.typical_prices.len() as f64;
+        let sma = self.typical_prices.iter().sum::<f64>() / len;
+        let mean_deviation = self
+            .typical_prices
+            .iter()
+            .map(|x| (x - sma).abs())
+            .sum::<f64>()
+            / len;
+
+        match mean_deviation > 0. {
+            true => (typical_price - sma) / (0.015 * mean_deviation),
+            false => 0.,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.typical_prices = vec![];
+    }
+}
+
+/// Commodity Channel Index over the typical price.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cci {
+    #[serde(skip)]
+    state: CciState,
+    #[serde(skip)]
+    state_tmp: CciState,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
+}
+
+impl Default for Cci {
+    fn default() -> Self {
+        Self::new().unwrap()
+    }
+}
+
+impl Indicator for Cci {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        let period = params.period_or(20)?;
+
+        Ok(Self {
+            state: CciState::new(period),
+            state_tmp: CciState::new(period),
+            params: params.clone(),
+            outputs: new_outputs(&["cci"]),
+        })
+    }
+
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
+    }
+
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
+    }
+
+    fn params(&self) -> &IndicatorParams {
+        &self.params
+    }
+
+    fn update_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let a = self.state.next(OHLC.1, OHLC.2, OHLC.3);
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn next_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) {
+        self.state_tmp.next(OHLC.1, OHLC.2, OHLC.3);
+    }
+
+    fn update_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let a = self.state_tmp.next(OHLC.1, OHLC.2, OHLC.3);
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn warm_up_OHLC(&mut self, data: &[(f64, f64, f64, f64)]) -> Result<()> {
+        let params = self.params.clone();
+        rehydrate(self, &params, |indicator| replay_OHLC(indicator, data))
+    }
+
+    fn next(&mut self, value: f64) -> Result<()> {
+        self.next_OHLC((value, value, value, value))
+    }
+
+    fn next_tmp(&mut self, value: f64) {
+        self.next_tmp_OHLC((value, value, value, value));
+    }
+
+    fn next_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let a = self.state.next(OHLC.1, OHLC.2, OHLC.3);
+        push_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn update(&mut self, value: f64) -> Result<()> {
+        self.update_OHLC((value, value, value, value))
+    }
+
+    fn update_tmp(&mut self, value: f64) -> Result<()> {
+        self.update_tmp_OHLC((value, value, value, value))
+    }
+
+    fn reset_tmp(&mut self) {
+        self.state_tmp.reset();
+    }
+}
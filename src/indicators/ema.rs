@@ -1,114 +1,84 @@
-use super::Indicator;
-use crate::error::Result;
+use super::{new_outputs, push_outputs, update_outputs, Indicator, Outputs, StoredIndicator};
+use crate::error::{Result, RsAlgoError};
+use crate::indicators::params::IndicatorParams;
 
 use serde::{Deserialize, Serialize};
 use ta::indicators::ExponentialMovingAverage;
 use ta::{Next, Reset};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "StoredIndicator")]
 pub struct Ema {
     #[serde(skip_deserializing)]
     ema: ExponentialMovingAverage,
     #[serde(skip_deserializing)]
     ema_tmp: ExponentialMovingAverage,
-    data_a: Vec<f64>,
-    data_b: Vec<f64>,
-    data_c: Vec<f64>,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
+}
+
+impl TryFrom<StoredIndicator> for Ema {
+    type Error = RsAlgoError;
+
+    fn try_from(stored: StoredIndicator) -> Result<Self> {
+        stored.restore()
+    }
 }
 
 impl Ema {
-    pub fn new_ema(index: usize) -> Result<Self> {
-        Ok(Self {
-            ema: ExponentialMovingAverage::new(index).unwrap(),
-            ema_tmp: ExponentialMovingAverage::new(index).unwrap(),
-            data_a: vec![],
-            data_b: vec![],
-            data_c: vec![],
-        })
+    pub fn new_ema(period: usize) -> Result<Self> {
+        Self::new_with_params(&IndicatorParams::with_period(period))
     }
 }
 
 impl Indicator for Ema {
-    fn new() -> Result<Self> {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        let period = params.period_or(20)?;
+
         Ok(Self {
-            ema: ExponentialMovingAverage::new(0).unwrap(),
-            ema_tmp: ExponentialMovingAverage::new(0).unwrap(),
-            data_a: vec![],
-            data_b: vec![],
-            data_c: vec![],
+            ema: ExponentialMovingAverage::new(period).unwrap(),
+            ema_tmp: ExponentialMovingAverage::new(period).unwrap(),
+            params: params.clone(),
+            outputs: new_outputs(&["ema"]),
         })
     }
-    fn get_data_a(&self) -> &Vec<f64> {
-        &self.data_a
-    }
-
-    fn get_current_a(&self) -> &f64 {
-        &self.data_a.last().unwrap()
-    }
-
-    fn get_data_b(&self) -> &Vec<f64> {
-        &self.data_b
-    }
 
-    fn get_current_b(&self) -> &f64 {
-        &self.data_b.last().unwrap()
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
     }
 
-    fn get_data_c(&self) -> &Vec<f64> {
-        &self.data_c
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
     }
 
-    fn get_current_c(&self) -> &f64 {
-        &self.data_c.last().unwrap()
+    fn params(&self) -> &IndicatorParams {
+        &self.params
     }
 
     fn next(&mut self, value: f64) -> Result<()> {
         let a = self.ema.next(value);
-        self.data_a.push(a);
+        push_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
 
     fn next_tmp(&mut self, value: f64) {
-        let leches = self.ema_tmp.next(value);
-    }
-
-    fn next_OHLC(&mut self, _OHLC: (f64, f64, f64, f64)) -> Result<()> {
-        Ok(())
+        self.ema_tmp.next(value);
     }
 
     fn update(&mut self, value: f64) -> Result<()> {
         let a = self.ema.next(value);
-        let last = self.data_a.last_mut().unwrap();
-        *last = a;
+        update_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
 
     fn update_tmp(&mut self, value: f64) -> Result<()> {
         let a = self.ema_tmp.next(value);
-        let last = self.data_a.last_mut().unwrap();
-        *last = a;
+        update_outputs(&mut self.outputs, &[a]);
         Ok(())
     }
 
     fn reset_tmp(&mut self) {
         self.ema_tmp.reset();
     }
-
-    fn remove_a(&mut self, index: usize) -> f64 {
-        self.data_a.remove(index)
-    }
-
-    fn remove_b(&mut self, index: usize) -> f64 {
-        self.data_b.remove(index)
-    }
-
-    fn remove_c(&mut self, index: usize) -> f64 {
-        self.data_c.remove(index)
-    }
-
-    fn duplicate_last(&mut self) {
-        let a = self.data_a.last().unwrap();
-        //log::info!("7777777777 {:?}", self.data_a.len());
-        self.data_a.push(*a);
-    }
 }
@@ -0,0 +1,153 @@
+use crate::helpers::date::*;
+use crate::scanner::candle::Candle;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(super) struct Bucket {
+    pub key: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+impl Bucket {
+    pub fn new(key: i64, candle: &Candle) -> Self {
+        Self {
+            key,
+            open: candle.open(),
+            high: candle.high(),
+            low: candle.low(),
+            close: candle.close(),
+        }
+    }
+
+    pub fn update(&mut self, candle: &Candle) {
+        self.high = self.high.max(candle.high());
+        self.low = self.low.min(candle.low());
+        self.close = candle.close();
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SessionLevels {
+    day: Option<Bucket>,
+    prev_day: Option<Bucket>,
+    week: Option<Bucket>,
+    prev_week: Option<Bucket>,
+    prev_day_high: Vec<f64>,
+    prev_day_low: Vec<f64>,
+    prev_day_close: Vec<f64>,
+    prev_week_high: Vec<f64>,
+    prev_week_low: Vec<f64>,
+    prev_week_close: Vec<f64>,
+    session_open: Vec<f64>,
+}
+
+impl SessionLevels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_candles(data: &Vec<Candle>) -> Self {
+        let mut levels = Self::new();
+        for candle in data {
+            levels.next(candle);
+        }
+        levels
+    }
+
+    pub fn next(&mut self, candle: &Candle) {
+        let date = candle.date();
+        let day_key = date.num_days_from_ce() as i64;
+        let week_key = date.iso_week().year() as i64 * 100 + date.iso_week().week() as i64;
+
+        roll_bucket(&mut self.day, &mut self.prev_day, day_key, candle);
+        roll_bucket(&mut self.week, &mut self.prev_week, week_key, candle);
+
+        let (day_high, day_low, day_close) = bucket_values(&self.prev_day);
+        let (week_high, week_low, week_close) = bucket_values(&self.prev_week);
+
+        self.prev_day_high.push(day_high);
+        self.prev_day_low.push(day_low);
+        self.prev_day_close.push(day_close);
+        self.prev_week_high.push(week_high);
+        self.prev_week_low.push(week_low);
+        self.prev_week_close.push(week_close);
+        self.session_open
+            .push(self.day.as_ref().map(|day| day.open).unwrap_or(0.));
+    }
+
+    pub fn update(&mut self, candle: &Candle) {
+        if let Some(day) = self.day.as_mut() {
+            day.update(candle);
+        }
+        if let Some(week) = self.week.as_mut() {
+            week.update(candle);
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index >= self.session_open.len() {
+            return;
+        }
+        self.prev_day_high.remove(index);
+        self.prev_day_low.remove(index);
+        self.prev_day_close.remove(index);
+        self.prev_week_high.remove(index);
+        self.prev_week_low.remove(index);
+        self.prev_week_close.remove(index);
+        self.session_open.remove(index);
+    }
+
+    pub fn prev_day_high(&self) -> &Vec<f64> {
+        &self.prev_day_high
+    }
+
+    pub fn prev_day_low(&self) -> &Vec<f64> {
+        &self.prev_day_low
+    }
+
+    pub fn prev_day_close(&self) -> &Vec<f64> {
+        &self.prev_day_close
+    }
+
+    pub fn prev_week_high(&self) -> &Vec<f64> {
+        &self.prev_week_high
+    }
+
+    pub fn prev_week_low(&self) -> &Vec<f64> {
+        &self.prev_week_low
+    }
+
+    pub fn prev_week_close(&self) -> &Vec<f64> {
+        &self.prev_week_close
+    }
+
+    pub fn session_open(&self) -> &Vec<f64> {
+        &self.session_open
+    }
+}
+
+pub(super) fn roll_bucket(
+    current: &mut Option<Bucket>,
+    prev: &mut Option<Bucket>,
+    key: i64,
+    candle: &Candle,
+) {
+    match current.as_mut() {
+        Some(bucket) if bucket.key == key => bucket.update(candle),
+        _ => {
+            *prev = current.take();
+            *current = Some(Bucket::new(key, candle));
+        }
+    }
+}
+
+fn bucket_values(bucket: &Option<Bucket>) -> (f64, f64, f64) {
+    match bucket {
+        Some(bucket) => (bucket.high, bucket.low, bucket.close),
+        None => (0., 0., 0.),
+    }
+}
@@ -1,12 +1,13 @@
-use super::Indicator;
-use crate::error::Result;
+use super::{new_outputs, push_outputs, update_outputs, Indicator, Outputs, StoredIndicator};
+use crate::error::{Result, RsAlgoError};
+use crate::indicators::params::IndicatorParams;
 
 use serde::{Deserialize, Serialize};
-use std::env;
 use ta::indicators::ExponentialMovingAverage;
 use ta::{Next, Reset};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "StoredIndicator")]
 pub struct Macd {
     #[serde(skip_deserializing)]
     ema_a: ExponentialMovingAverage,
@@ -20,16 +21,24 @@ pub struct Macd {
     ema_b_tmp: ExponentialMovingAverage,
     #[serde(skip_deserializing)]
     ema_c_tmp: ExponentialMovingAverage,
-    data_a: Vec<f64>,
-    data_b: Vec<f64>,
-    data_c: Vec<f64>,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
+}
+
+impl TryFrom<StoredIndicator> for Macd {
+    type Error = RsAlgoError;
+
+    fn try_from(stored: StoredIndicator) -> Result<Self> {
+        stored.restore()
+    }
 }
 
 impl Indicator for Macd {
-    fn new() -> Result<Self> {
-        let macd_a = env::var("MACD_A").unwrap().parse::<usize>().unwrap();
-        let macd_b = env::var("MACD_B").unwrap().parse::<usize>().unwrap();
-        let macd_c = env::var("MACD_C").unwrap().parse::<usize>().unwrap();
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        let macd_a = params.fast_period_or(12)?;
+        let macd_b = params.slow_period_or(26)?;
+        let macd_c = params.signal_period_or(9)?;
 
         Ok(Self {
             ema_a: ExponentialMovingAverage::new(macd_a).unwrap(),
@@ -38,40 +47,27 @@ impl Indicator for Macd {
             ema_a_tmp: ExponentialMovingAverage::new(macd_a).unwrap(),
             ema_b_tmp: ExponentialMovingAverage::new(macd_b).unwrap(),
             ema_c_tmp: ExponentialMovingAverage::new(macd_c).unwrap(),
-            data_a: vec![],
-            data_b: vec![],
-            data_c: vec![],
+            params: params.clone(),
+            outputs: new_outputs(&["macd", "signal"]),
         })
     }
-    fn get_data_a(&self) -> &Vec<f64> {
-        &self.data_a
-    }
 
-    fn get_current_a(&self) -> &f64 {
-        &self.data_a.last().unwrap()
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
     }
 
-    fn get_data_b(&self) -> &Vec<f64> {
-        &self.data_b
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
     }
 
-    fn get_current_b(&self) -> &f64 {
-        &self.data_b.last().unwrap()
-    }
-
-    fn get_data_c(&self) -> &Vec<f64> {
-        &self.data_c
-    }
-
-    fn get_current_c(&self) -> &f64 {
-        &self.data_c.last().unwrap()
+    fn params(&self) -> &IndicatorParams {
+        &self.params
     }
 
     fn next(&mut self, value: f64) -> Result<()> {
         let a = self.ema_a.next(value) - self.ema_b.next(value);
         let b = self.ema_c.next(a);
-        self.data_a.push(a);
-        self.data_b.push(b);
+        push_outputs(&mut self.outputs, &[a, b]);
         Ok(())
     }
 
@@ -80,27 +76,17 @@ impl Indicator for Macd {
         self.ema_c_tmp.next(a);
     }
 
-    fn next_OHLC(&mut self, _OHLC: (f64, f64, f64, f64)) -> Result<()> {
-        Ok(())
-    }
-
     fn update(&mut self, value: f64) -> Result<()> {
         let a = self.ema_a.next(value) - self.ema_b.next(value);
         let b = self.ema_c.next(a);
-        let last_a = self.data_a.last_mut().unwrap();
-        let last_b = self.data_b.last_mut().unwrap();
-        *last_a = a;
-        *last_b = b;
+        update_outputs(&mut self.outputs, &[a, b]);
         Ok(())
     }
 
     fn update_tmp(&mut self, value: f64) -> Result<()> {
         let a = self.ema_a_tmp.next(value) - self.ema_b_tmp.next(value);
         let b = self.ema_c_tmp.next(a);
-        let last_a = self.data_a.last_mut().unwrap();
-        let last_b = self.data_b.last_mut().unwrap();
-        *last_a = a;
-        *last_b = b;
+        update_outputs(&mut self.outputs, &[a, b]);
         Ok(())
     }
 
@@ -109,23 +95,4 @@ impl Indicator for Macd {
         self.ema_b_tmp.reset();
         self.ema_c_tmp.reset();
     }
-
-    fn remove_a(&mut self, index: usize) -> f64 {
-        self.data_a.remove(index)
-    }
-
-    fn remove_b(&mut self, index: usize) -> f64 {
-        self.data_b.remove(index)
-    }
-
-    fn remove_c(&mut self, index: usize) -> f64 {
-        self.data_c.remove(index)
-    }
-
-    fn duplicate_last(&mut self) {
-        let a = self.data_a.last().unwrap();
-        let b = self.data_b.last().unwrap();
-        self.data_a.push(*a);
-        self.data_b.push(*b);
-    }
 }
@@ -2,53 +2,295 @@ pub mod adx;
 pub mod atr;
 pub mod bb;
 pub mod bbw;
+pub mod cci;
 pub mod ema;
+pub mod levels;
 pub mod macd;
+pub mod obv;
+pub mod params;
+pub mod pivots;
+pub mod psar;
+pub mod registry;
 pub mod rsi;
 //pub mod sd;
 pub mod stoch;
+pub mod supertrend;
+pub mod volume_ma;
+pub mod williams_r;
 
 use crate::error::Result;
 use crate::indicators::atr::Atr;
 use crate::indicators::bb::BollingerB;
 use crate::indicators::bbw::BollingerBW;
+use crate::indicators::cci::Cci;
 use crate::indicators::ema::Ema;
 use crate::indicators::macd::Macd;
+use crate::indicators::obv::Obv;
+use crate::indicators::params::{IndicatorParams, IndicatorsParams};
+use crate::indicators::psar::ParabolicSar;
+use crate::indicators::registry::CustomIndicators;
 use crate::indicators::rsi::Rsi;
+use crate::indicators::supertrend::SuperTrend;
+use crate::indicators::volume_ma::VolumeMa;
+use crate::indicators::williams_r::WilliamsR;
 use crate::models::time_frame::TimeFrameType;
 use crate::scanner::candle::Candle;
 
 use chrono::{DateTime, Local};
+use dyn_clone::DynClone;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::marker::Sized;
 
-pub trait Indicator {
-    fn new() -> Result<Self>
+dyn_clone::clone_trait_object!(Indicator);
+
+/// Named output series of an indicator, in insertion order.
+pub type Outputs = IndexMap<String, Vec<f64>>;
+
+pub trait Indicator: DynClone + Send + Sync {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self>
     where
         Self: Sized;
+
+    fn new() -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::new_with_params(&IndicatorParams::default())
+    }
+
     fn next(&mut self, value: f64) -> Result<()>;
     fn next_tmp(&mut self, value: f64);
-    fn next_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()>;
     fn update(&mut self, value: f64) -> Result<()>;
     fn update_tmp(&mut self, value: f64) -> Result<()>;
     fn reset_tmp(&mut self);
-    // fn get_mut_data_a(&mut self) -> &Vec<f64>;
-    // fn get_mut_data_b(&mut self) -> &Vec<f64>;
-    fn get_data_a(&self) -> &Vec<f64>;
-    fn get_current_a(&self) -> &f64;
-    fn remove_a(&mut self, index: usize) -> f64;
-    //fn remove_a(&mut self, value: usize) -> &f64;
-    fn get_current_b(&self) -> &f64;
-    fn get_data_b(&self) -> &Vec<f64>;
-    fn remove_b(&mut self, index: usize) -> f64;
-    //fn remove_b(&mut self, data: &mut Vec<f64>, index: usize) -> f64;
-    //fn remove_b(&mut self, value: usize) -> &f64;
-    fn get_current_c(&self) -> &f64;
-    fn get_data_c(&self) -> &Vec<f64>;
-    fn duplicate_last(&mut self);
-    fn remove_c(&mut self, index: usize) -> f64;
-    //fn remove_c(&mut self, value: usize) -> &f64;
+    fn outputs(&self) -> &Outputs;
+    fn outputs_mut(&mut self) -> &mut Outputs;
+    fn params(&self) -> &IndicatorParams;
+
+    // OHLC counterparts, close based indicators only use the close.
+
+    fn next_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        self.next(OHLC.3)
+    }
+
+    fn update_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        self.update(OHLC.3)
+    }
+
+    fn next_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) {
+        self.next_tmp(OHLC.3)
+    }
+
+    fn update_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        self.update_tmp(OHLC.3)
+    }
+
+    /// Internal state is skipped on deserialization. Replays the history
+    /// through a fresh indicator to restore it, stored outputs are kept.
+    fn warm_up(&mut self, data: &[f64]) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let params = self.params().clone();
+        rehydrate(self, &params, |indicator| replay(indicator, data))
+    }
+
+    fn warm_up_OHLC(&mut self, data: &[(f64, f64, f64, f64)]) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let closes: Vec<f64> = data.iter().map(|OHLC| OHLC.3).collect();
+        self.warm_up(&closes)
+    }
+
+    fn output(&self, name: &str) -> Option<&Vec<f64>> {
+        self.outputs().get(name)
+    }
+
+    fn output_current(&self, name: &str) -> Option<&f64> {
+        self.output(name).and_then(|values| values.last())
+    }
+
+    fn output_names(&self) -> Vec<&str> {
+        self.outputs().keys().map(|name| name.as_str()).collect()
+    }
+
+    fn duplicate_last(&mut self) {
+        for values in self.outputs_mut().values_mut() {
+            if let Some(last) = values.last().copied() {
+                values.push(last);
+            }
+        }
+    }
+
+    /// Keeps the last `max_bars` values of every output.
+    fn trim(&mut self, max_bars: usize) {
+        for values in self.outputs_mut().values_mut() {
+            if values.len() > max_bars {
+                let excess = values.len() - max_bars;
+                values.drain(..excess);
+            }
+        }
+    }
+
+    // Positional accessors kept for existing callers. a, b and c map to the
+    // first, second and third registered output.
+
+    #[deprecated(note = "use output(name)")]
+    fn get_data_a(&self) -> &Vec<f64> {
+        output_at(self.outputs(), 0)
+    }
+
+    #[deprecated(note = "use output_current(name)")]
+    fn get_current_a(&self) -> &f64 {
+        output_at(self.outputs(), 0).last().unwrap()
+    }
+
+    #[deprecated(note = "use outputs_mut()")]
+    fn remove_a(&mut self, index: usize) -> f64 {
+        remove_at(self.outputs_mut(), 0, index)
+    }
+
+    #[deprecated(note = "use output(name)")]
+    fn get_data_b(&self) -> &Vec<f64> {
+        output_at(self.outputs(), 1)
+    }
+
+    #[deprecated(note = "use output_current(name)")]
+    fn get_current_b(&self) -> &f64 {
+        output_at(self.outputs(), 1).last().unwrap()
+    }
+
+    #[deprecated(note = "use outputs_mut()")]
+    fn remove_b(&mut self, index: usize) -> f64 {
+        remove_at(self.outputs_mut(), 1, index)
+    }
+
+    #[deprecated(note = "use output(name)")]
+    fn get_data_c(&self) -> &Vec<f64> {
+        output_at(self.outputs(), 2)
+    }
+
+    #[deprecated(note = "use output_current(name)")]
+    fn get_current_c(&self) -> &f64 {
+        output_at(self.outputs(), 2).last().unwrap()
+    }
+
+    #[deprecated(note = "use outputs_mut()")]
+    fn remove_c(&mut self, index: usize) -> f64 {
+        remove_at(self.outputs_mut(), 2, index)
+    }
+}
+
+fn is_indicator_enabled(key: &str) -> bool {
+    env::var(key)
+        .unwrap_or("false".to_string())
+        .parse::<bool>()
+        .unwrap()
+}
+
+static EMPTY_OUTPUT: Vec<f64> = Vec::new();
+
+pub fn new_outputs(names: &[&str]) -> Outputs {
+    names
+        .iter()
+        .map(|name| (name.to_string(), vec![]))
+        .collect()
+}
+
+/// Pushes one value per output, in registration order.
+pub fn push_outputs(outputs: &mut Outputs, values: &[f64]) {
+    for ((_, data), value) in outputs.iter_mut().zip(values) {
+        data.push(*value);
+    }
+}
+
+/// Replaces the last value of every output, in registration order.
+pub fn update_outputs(outputs: &mut Outputs, values: &[f64]) {
+    for ((_, data), value) in outputs.iter_mut().zip(values) {
+        match data.last_mut() {
+            Some(last) => *last = *value,
+            None => data.push(*value),
+        };
+    }
+}
+
+/// Rebuilds the indicator from params running `replay` on it, keeping its outputs.
+pub fn rehydrate<I, F>(indicator: &mut I, params: &IndicatorParams, replay: F) -> Result<()>
+where
+    I: Indicator,
+    F: FnOnce(&mut I) -> Result<()>,
+{
+    let outputs = indicator.outputs().clone();
+    *indicator = I::new_with_params(params)?;
+    replay(indicator)?;
+    *indicator.outputs_mut() = outputs;
+    Ok(())
+}
+
+pub fn replay<I: Indicator>(indicator: &mut I, data: &[f64]) -> Result<()> {
+    for value in data {
+        indicator.next(*value)?;
+    }
+    Ok(())
+}
+
+pub fn replay_OHLC<I: Indicator>(indicator: &mut I, data: &[(f64, f64, f64, f64)]) -> Result<()> {
+    for OHLC in data {
+        indicator.next_OHLC(*OHLC)?;
+    }
+    Ok(())
+}
+
+/// Serialized indicator with stored outputs. Records saved before the named
+/// outputs have `data_a`, `data_b` and `data_c` instead, read into the first
+/// three outputs.
+#[derive(Deserialize)]
+pub struct StoredIndicator {
+    #[serde(default)]
+    params: IndicatorParams,
+    #[serde(default)]
+    outputs: Option<Outputs>,
+    #[serde(default)]
+    data_a: Vec<f64>,
+    #[serde(default)]
+    data_b: Vec<f64>,
+    #[serde(default)]
+    data_c: Vec<f64>,
+}
+
+impl StoredIndicator {
+    /// Builds the indicator from its params with the stored outputs, the
+    /// internal state is restored by `warm_up`.
+    pub fn restore<I: Indicator>(self) -> Result<I> {
+        let mut indicator = I::new_with_params(&self.params)?;
+        match self.outputs {
+            Some(outputs) => *indicator.outputs_mut() = outputs,
+            None => {
+                let legacy = [self.data_a, self.data_b, self.data_c];
+                for ((_, values), data) in indicator.outputs_mut().iter_mut().zip(legacy) {
+                    *values = data;
+                }
+            }
+        };
+        Ok(indicator)
+    }
+}
+
+fn output_at(outputs: &Outputs, position: usize) -> &Vec<f64> {
+    match outputs.get_index(position) {
+        Some((_, values)) => values,
+        None => &EMPTY_OUTPUT,
+    }
+}
+
+fn remove_at(outputs: &mut Outputs, position: usize, index: usize) -> f64 {
+    match outputs.get_index_mut(position) {
+        Some((_, values)) => values.remove(index),
+        None => 0.,
+    }
 }
 
 //FIXME ARRAY OF TRAIT INDICATORS
@@ -64,28 +306,222 @@ pub struct Indicators {
     pub ema_a: Ema,
     pub ema_b: Ema,
     pub ema_c: Ema,
+    #[serde(default)]
+    pub supertrend: SuperTrend,
+    #[serde(default)]
+    pub obv: Obv,
+    #[serde(default)]
+    pub volume_ma: VolumeMa,
+    #[serde(default)]
+    pub psar: ParabolicSar,
+    #[serde(default)]
+    pub cci: Cci,
+    #[serde(default)]
+    pub williams_r: WilliamsR,
+    #[serde(skip)]
+    pub custom: CustomIndicators,
+    #[serde(default)]
+    pub params: IndicatorsParams,
 }
 
 impl Indicators {
     pub fn new() -> Result<Self> {
-        let ema_a = &env::var("EMA_A").unwrap().parse::<usize>().unwrap();
-        let ema_b = &env::var("EMA_B").unwrap().parse::<usize>().unwrap();
-        let ema_c = &env::var("EMA_C").unwrap().parse::<usize>().unwrap();
+        Self::new_with_params(&IndicatorsParams::from_env())
+    }
 
+    pub fn new_with_params(params: &IndicatorsParams) -> Result<Self> {
         Ok(Self {
-            macd: Macd::new().unwrap(),
-            rsi: Rsi::new().unwrap(),
+            macd: Macd::new_with_params(&params.macd)?,
+            rsi: Rsi::new_with_params(&params.rsi)?,
             //stoch: Stoch::new().unwrap(),
-            atr: Atr::new().unwrap(),
+            atr: Atr::new_with_params(&params.atr)?,
             //adx: Adx::new().unwrap(),
-            bb: BollingerB::new().unwrap(),
-            bbw: BollingerBW::new().unwrap(),
-            ema_a: Ema::new_ema(*ema_a).unwrap(),
-            ema_b: Ema::new_ema(*ema_b).unwrap(),
-            ema_c: Ema::new_ema(*ema_c).unwrap(),
+            bb: BollingerB::new_with_params(&params.bb)?,
+            bbw: BollingerBW::new_with_params(&params.bbw)?,
+            ema_a: Ema::new_with_params(&params.ema_a)?,
+            ema_b: Ema::new_with_params(&params.ema_b)?,
+            ema_c: Ema::new_with_params(&params.ema_c)?,
+            supertrend: SuperTrend::new_with_params(&params.supertrend)?,
+            obv: Obv::new_with_params(&params.obv)?,
+            volume_ma: VolumeMa::new_with_params(&params.volume_ma)?,
+            psar: ParabolicSar::new_with_params(&params.psar)?,
+            cci: Cci::new_with_params(&params.cci)?,
+            williams_r: WilliamsR::new_with_params(&params.williams_r)?,
+            custom: CustomIndicators::new(&params.custom)?,
+            params: params.clone(),
         })
     }
 
+    pub fn params(&self) -> &IndicatorsParams {
+        &self.params
+    }
+
+    /// Restores the internal state of every indicator after deserialization.
+    /// Records saved without params fall back to the env ones.
+    pub fn warm_up(&mut self, data: &[(f64, f64, f64, f64)]) -> Result<()> {
+        if self.params == IndicatorsParams::default() {
+            self.params = IndicatorsParams::from_env();
+        }
+
+        let params = self.params.clone();
+        let closes: Vec<f64> = data.iter().map(|OHLC| OHLC.3).collect();
+
+        rehydrate(&mut self.macd, &params.macd, |x| replay(x, &closes))?;
+        rehydrate(&mut self.rsi, &params.rsi, |x| replay(x, &closes))?;
+        rehydrate(&mut self.bb, &params.bb, |x| replay(x, &closes))?;
+        rehydrate(&mut self.bbw, &params.bbw, |x| replay(x, &closes))?;
+        rehydrate(&mut self.ema_a, &params.ema_a, |x| replay(x, &closes))?;
+        rehydrate(&mut self.ema_b, &params.ema_b, |x| replay(x, &closes))?;
+        rehydrate(&mut self.ema_c, &params.ema_c, |x| replay(x, &closes))?;
+        rehydrate(&mut self.atr, &params.atr, |x| replay_OHLC(x, data))?;
+        rehydrate(&mut self.supertrend, &params.supertrend, |x| {
+            replay_OHLC(x, data)
+        })?;
+        rehydrate(&mut self.psar, &params.psar, |x| replay_OHLC(x, data))?;
+        rehydrate(&mut self.cci, &params.cci, |x| replay_OHLC(x, data))?;
+        rehydrate(&mut self.williams_r, &params.williams_r, |x| {
+            replay_OHLC(x, data)
+        })?;
+        self.custom.warm_up(&params.custom, data)?;
+
+        Ok(())
+    }
+
+    /// Same as warm_up for the volume indicators, data is (close, volume).
+    pub fn warm_up_volume(&mut self, data: &[(f64, f64)]) -> Result<()> {
+        let params = self.params.clone();
+        let volumes: Vec<f64> = data.iter().map(|(_, volume)| *volume).collect();
+
+        self.obv.warm_up_volume(data)?;
+        rehydrate(&mut self.volume_ma, &params.volume_ma, |x| {
+            replay(x, &volumes)
+        })?;
+
+        Ok(())
+    }
+
+    /// Volume indicators are fed apart since OHLC doesn't carry the volume.
+    pub fn next_volume(
+        &mut self,
+        close: f64,
+        volume: f64,
+        delete: bool,
+        time_frame: &TimeFrameType,
+    ) -> Result<()> {
+        let num_bars = env::var("NUM_BARS").unwrap().parse::<usize>().unwrap();
+        let max_bars = num_bars / time_frame.clone().to_number() as usize;
+
+        if is_indicator_enabled("INDICATORS_OBV") {
+            self.obv.next_volume(close, volume).unwrap();
+
+            if delete && self.obv.get_data_a().len() > max_bars {
+                self.obv.remove_a(0);
+            }
+        }
+
+        if is_indicator_enabled("INDICATORS_VOLUME_MA") {
+            self.volume_ma.next(volume).unwrap();
+
+            if delete && self.volume_ma.get_data_a().len() > max_bars {
+                self.volume_ma.remove_a(0);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn next_close_delete_volume(
+        &mut self,
+        close: f64,
+        volume: f64,
+        time_frame: &TimeFrameType,
+    ) -> Result<()> {
+        let num_bars = env::var("NUM_BARS").unwrap().parse::<usize>().unwrap();
+        let max_bars = num_bars / time_frame.clone().to_number() as usize;
+
+        if is_indicator_enabled("INDICATORS_OBV") {
+            self.obv.update_volume(close, volume).unwrap();
+
+            if self.obv.get_data_a().len() > max_bars {
+                self.obv.remove_a(0);
+            }
+        }
+
+        if is_indicator_enabled("INDICATORS_VOLUME_MA") {
+            self.volume_ma.update(volume).unwrap();
+
+            if self.volume_ma.get_data_a().len() > max_bars {
+                self.volume_ma.remove_a(0);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn create_tmp_volume_indicators(
+        &mut self,
+        candle: &Candle,
+        prev_data: &Vec<Candle>,
+    ) -> Result<()> {
+        let is_obv = is_indicator_enabled("INDICATORS_OBV");
+        let is_volume_ma = is_indicator_enabled("INDICATORS_VOLUME_MA");
+
+        //WARMING
+        for prev_candle in prev_data
+            .iter()
+            .rev()
+            .take(50)
+            .rev()
+            .filter(|x| x.is_closed == true)
+        {
+            if is_obv {
+                self.obv
+                    .next_tmp_volume(prev_candle.close(), prev_candle.volume());
+            }
+            if is_volume_ma {
+                self.volume_ma.next_tmp(prev_candle.volume());
+            }
+        }
+
+        //UPDATING LAST VALUE & RESET
+        if is_obv {
+            self.obv
+                .update_tmp_volume(candle.close(), candle.volume())
+                .unwrap();
+            self.obv.reset_tmp();
+        }
+        if is_volume_ma {
+            self.volume_ma.update_tmp(candle.volume()).unwrap();
+            self.volume_ma.reset_tmp();
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds every indicator with the same params, dropping their data.
+    pub fn reset(&self) -> Result<Self> {
+        Self::new_with_params(&self.params)
+    }
+
+    /// Evicts the oldest values so no indicator keeps more than `max_bars`.
+    pub fn trim(&mut self, max_bars: usize) {
+        self.macd.trim(max_bars);
+        self.rsi.trim(max_bars);
+        self.atr.trim(max_bars);
+        self.bb.trim(max_bars);
+        self.bbw.trim(max_bars);
+        self.ema_a.trim(max_bars);
+        self.ema_b.trim(max_bars);
+        self.ema_c.trim(max_bars);
+        self.supertrend.trim(max_bars);
+        self.obv.trim(max_bars);
+        self.volume_ma.trim(max_bars);
+        self.psar.trim(max_bars);
+        self.cci.trim(max_bars);
+        self.williams_r.trim(max_bars);
+        self.custom.trim(max_bars);
+    }
+
     pub fn atr(&self) -> &Atr {
         &self.atr
     }
@@ -122,6 +558,34 @@ impl Indicators {
         &self.ema_c
     }
 
+    pub fn supertrend(&self) -> &SuperTrend {
+        &self.supertrend
+    }
+
+    pub fn psar(&self) -> &ParabolicSar {
+        &self.psar
+    }
+
+    pub fn cci(&self) -> &Cci {
+        &self.cci
+    }
+
+    pub fn williams_r(&self) -> &WilliamsR {
+        &self.williams_r
+    }
+
+    pub fn custom(&self) -> &CustomIndicators {
+        &self.custom
+    }
+
+    pub fn obv(&self) -> &Obv {
+        &self.obv
+    }
+
+    pub fn volume_ma(&self) -> &VolumeMa {
+        &self.volume_ma
+    }
+
     pub fn next(
         &mut self,
         OHLC: (f64, f64, f64, f64),
@@ -134,13 +598,63 @@ impl Indicators {
         //log::info!("INDICATORS  SIZE {:?}", self.ema_a().get_data_a().len());
 
         if env::var("INDICATORS_ATR").unwrap().parse::<bool>().unwrap() {
-            self.atr.next(close).unwrap();
+            self.atr.next_OHLC(OHLC).unwrap();
 
             if delete && self.atr.get_data_a().len() > max_bars {
                 self.atr.remove_a(0);
             }
         }
 
+        if env::var("INDICATORS_SUPERTREND")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.supertrend.next_OHLC(OHLC).unwrap();
+
+            if delete && self.supertrend.get_data_a().len() > max_bars {
+                self.supertrend.remove_a(0);
+                self.supertrend.remove_b(0);
+            }
+        }
+
+        if env::var("INDICATORS_PSAR")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.psar.next_OHLC(OHLC).unwrap();
+
+            if delete && self.psar.get_data_a().len() > max_bars {
+                self.psar.remove_a(0);
+                self.psar.remove_b(0);
+            }
+        }
+
+        if env::var("INDICATORS_CCI")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.cci.next_OHLC(OHLC).unwrap();
+
+            if delete && self.cci.get_data_a().len() > max_bars {
+                self.cci.remove_a(0);
+            }
+        }
+
+        if env::var("INDICATORS_WILLIAMS_R")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.williams_r.next_OHLC(OHLC).unwrap();
+
+            if delete && self.williams_r.get_data_a().len() > max_bars {
+                self.williams_r.remove_a(0);
+            }
+        }
+
         if env::var("INDICATORS_MACD")
             .unwrap()
             .parse::<bool>()
@@ -226,6 +740,13 @@ impl Indicators {
             }
         }
 
+        if !self.custom.is_empty() {
+            self.custom.next(OHLC)?;
+            if delete {
+                self.custom.trim(max_bars);
+            }
+        }
+
         Ok(())
     }
 
@@ -242,7 +763,39 @@ impl Indicators {
         // }
 
         if env::var("INDICATORS_ATR").unwrap().parse::<bool>().unwrap() {
-            self.atr.update(close).unwrap();
+            self.atr.update_OHLC(OHLC).unwrap();
+        }
+
+        if env::var("INDICATORS_SUPERTREND")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.supertrend.update_OHLC(OHLC).unwrap();
+        }
+
+        if env::var("INDICATORS_PSAR")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.psar.update_OHLC(OHLC).unwrap();
+        }
+
+        if env::var("INDICATORS_CCI")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.cci.update_OHLC(OHLC).unwrap();
+        }
+
+        if env::var("INDICATORS_WILLIAMS_R")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.williams_r.update_OHLC(OHLC).unwrap();
         }
 
         if env::var("INDICATORS_MACD")
@@ -297,6 +850,8 @@ impl Indicators {
             self.ema_c.update(close).unwrap();
         }
 
+        self.custom.update(OHLC)?;
+
         Ok(())
     }
 
@@ -321,6 +876,48 @@ impl Indicators {
             }
         }
 
+        if env::var("INDICATORS_SUPERTREND")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            if self.supertrend.get_data_a().len() > max_bars {
+                self.supertrend.remove_a(0);
+                self.supertrend.remove_b(0);
+            }
+        }
+
+        if env::var("INDICATORS_PSAR")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            if self.psar.get_data_a().len() > max_bars {
+                self.psar.remove_a(0);
+                self.psar.remove_b(0);
+            }
+        }
+
+        if env::var("INDICATORS_CCI")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            if self.cci.get_data_a().len() > max_bars {
+                self.cci.remove_a(0);
+            }
+        }
+
+        if env::var("INDICATORS_WILLIAMS_R")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            if self.williams_r.get_data_a().len() > max_bars {
+                self.williams_r.remove_a(0);
+            }
+        }
+
         if env::var("INDICATORS_MACD")
             .unwrap()
             .parse::<bool>()
@@ -392,6 +989,8 @@ impl Indicators {
             }
         }
 
+        self.custom.trim(max_bars);
+
         Ok(())
     }
 
@@ -404,7 +1003,39 @@ impl Indicators {
         // }
 
         if env::var("INDICATORS_ATR").unwrap().parse::<bool>().unwrap() {
-            self.atr.update_tmp(close).unwrap();
+            self.atr.update_tmp_OHLC(OHLC).unwrap();
+        }
+
+        if env::var("INDICATORS_SUPERTREND")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.supertrend.update_tmp_OHLC(OHLC).unwrap();
+        }
+
+        if env::var("INDICATORS_PSAR")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.psar.update_tmp_OHLC(OHLC).unwrap();
+        }
+
+        if env::var("INDICATORS_CCI")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.cci.update_tmp_OHLC(OHLC).unwrap();
+        }
+
+        if env::var("INDICATORS_WILLIAMS_R")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.williams_r.update_tmp_OHLC(OHLC).unwrap();
         }
 
         if env::var("INDICATORS_MACD")
@@ -459,6 +1090,8 @@ impl Indicators {
             self.ema_c.update_tmp(close).unwrap();
         }
 
+        self.custom.update_tmp(OHLC)?;
+
         Ok(())
     }
 
@@ -478,7 +1111,63 @@ impl Indicators {
             .filter(|x| x.is_closed == true)
         {
             if env::var("INDICATORS_ATR").unwrap().parse::<bool>().unwrap() {
-                self.atr.next_tmp(prev_candle.close());
+                self.atr.next_tmp_OHLC((
+                    prev_candle.open(),
+                    prev_candle.high(),
+                    prev_candle.low(),
+                    prev_candle.close(),
+                ));
+            }
+            if env::var("INDICATORS_SUPERTREND")
+                .unwrap_or("false".to_string())
+                .parse::<bool>()
+                .unwrap()
+            {
+                self.supertrend.next_tmp_OHLC((
+                    prev_candle.open(),
+                    prev_candle.high(),
+                    prev_candle.low(),
+                    prev_candle.close(),
+                ));
+            }
+
+            if env::var("INDICATORS_PSAR")
+                .unwrap_or("false".to_string())
+                .parse::<bool>()
+                .unwrap()
+            {
+                self.psar.next_tmp_OHLC((
+                    prev_candle.open(),
+                    prev_candle.high(),
+                    prev_candle.low(),
+                    prev_candle.close(),
+                ));
+            }
+
+            if env::var("INDICATORS_CCI")
+                .unwrap_or("false".to_string())
+                .parse::<bool>()
+                .unwrap()
+            {
+                self.cci.next_tmp_OHLC((
+                    prev_candle.open(),
+                    prev_candle.high(),
+                    prev_candle.low(),
+                    prev_candle.close(),
+                ));
+            }
+
+            if env::var("INDICATORS_WILLIAMS_R")
+                .unwrap_or("false".to_string())
+                .parse::<bool>()
+                .unwrap()
+            {
+                self.williams_r.next_tmp_OHLC((
+                    prev_candle.open(),
+                    prev_candle.high(),
+                    prev_candle.low(),
+                    prev_candle.close(),
+                ));
             }
             if env::var("INDICATORS_BB").unwrap().parse::<bool>().unwrap() {
                 self.bb.next_tmp(prev_candle.close());
@@ -522,9 +1211,54 @@ impl Indicators {
         //UPDATING LAST VALUE & RESET
 
         if env::var("INDICATORS_ATR").unwrap().parse::<bool>().unwrap() {
-            self.atr.update_tmp(close).unwrap();
+            self.atr
+                .update_tmp_OHLC((candle.open(), candle.high(), candle.low(), close))
+                .unwrap();
             self.atr.reset_tmp();
         }
+        if env::var("INDICATORS_SUPERTREND")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.supertrend
+                .update_tmp_OHLC((candle.open(), candle.high(), candle.low(), close))
+                .unwrap();
+            self.supertrend.reset_tmp();
+        }
+
+        if env::var("INDICATORS_PSAR")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.psar
+                .update_tmp_OHLC((candle.open(), candle.high(), candle.low(), close))
+                .unwrap();
+            self.psar.reset_tmp();
+        }
+
+        if env::var("INDICATORS_CCI")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.cci
+                .update_tmp_OHLC((candle.open(), candle.high(), candle.low(), close))
+                .unwrap();
+            self.cci.reset_tmp();
+        }
+
+        if env::var("INDICATORS_WILLIAMS_R")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.williams_r
+                .update_tmp_OHLC((candle.open(), candle.high(), candle.low(), close))
+                .unwrap();
+            self.williams_r.reset_tmp();
+        }
         if env::var("INDICATORS_BB").unwrap().parse::<bool>().unwrap() {
             self.bb.update_tmp(close).unwrap();
             self.bb.reset_tmp();
@@ -579,6 +1313,8 @@ impl Indicators {
             //self.stoch.reset_tmp();
         }
 
+        self.custom.create_tmp(candle, prev_data)?;
+
         Ok(())
     }
 
@@ -591,6 +1327,46 @@ impl Indicators {
             self.atr.duplicate_last();
         }
 
+        if env::var("INDICATORS_SUPERTREND")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.supertrend.duplicate_last();
+        }
+
+        if env::var("INDICATORS_PSAR")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.psar.duplicate_last();
+        }
+
+        if env::var("INDICATORS_CCI")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.cci.duplicate_last();
+        }
+
+        if env::var("INDICATORS_WILLIAMS_R")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .unwrap()
+        {
+            self.williams_r.duplicate_last();
+        }
+
+        if is_indicator_enabled("INDICATORS_OBV") {
+            self.obv.duplicate_last();
+        }
+
+        if is_indicator_enabled("INDICATORS_VOLUME_MA") {
+            self.volume_ma.duplicate_last();
+        }
+
         if env::var("INDICATORS_MACD")
             .unwrap()
             .parse::<bool>()
@@ -643,6 +1419,8 @@ impl Indicators {
             self.ema_c.duplicate_last();
         }
 
+        self.custom.duplicate_last();
+
         Ok(())
     }
 }
@@ -0,0 +1,124 @@
+use super::{new_outputs, push_outputs, update_outputs, Indicator, Outputs};
+use crate::error::Result;
+use crate::indicators::params::IndicatorParams;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ObvState {
+    obv: f64,
+    prev_close: Option<f64>,
+}
+
+impl ObvState {
+    fn next(&mut self, close: f64, volume: f64) -> f64 {
+        if let Some(prev_close) = self.prev_close {
+            if close > prev_close {
+                self.obv += volume;
+            } else if close < prev_close {
+                self.obv -= volume;
+            }
+        }
+        self.prev_close = Some(close);
+        self.obv
+    }
+}
+
+/// On-Balance Volume. It needs the bar volume so it is fed through
+/// next_volume, next(close) counts the bar with no volume.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Obv {
+    #[serde(skip)]
+    state: ObvState,
+    #[serde(skip)]
+    state_tmp: ObvState,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
+}
+
+impl Obv {
+    pub fn next_volume(&mut self, close: f64, volume: f64) -> Result<()> {
+        let a = self.state.next(close, volume);
+        push_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    pub fn update_volume(&mut self, close: f64, volume: f64) -> Result<()> {
+        let a = self.state.next(close, volume);
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    pub fn next_tmp_volume(&mut self, close: f64, volume: f64) {
+        self.state_tmp.next(close, volume);
+    }
+
+    pub fn update_tmp_volume(&mut self, close: f64, volume: f64) -> Result<()> {
+        let a = self.state_tmp.next(close, volume);
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    pub fn warm_up_volume(&mut self, data: &[(f64, f64)]) -> Result<()> {
+        self.state = ObvState::default();
+        self.state_tmp = ObvState::default();
+        for (close, volume) in data {
+            self.state.next(*close, *volume);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Obv {
+    fn default() -> Self {
+        Self::new().unwrap()
+    }
+}
+
+impl Indicator for Obv {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        Ok(Self {
+            state: ObvState::default(),
+            state_tmp: ObvState::default(),
+            params: params.clone(),
+            outputs: new_outputs(&["obv"]),
+        })
+    }
+
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
+    }
+
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
+    }
+
+    fn params(&self) -> &IndicatorParams {
+        &self.params
+    }
+
+    fn next(&mut self, value: f64) -> Result<()> {
+        self.next_volume(value, 0.)
+    }
+
+    fn next_tmp(&mut self, value: f64) {
+        self.next_tmp_volume(value, 0.);
+    }
+
+    fn next_OHLC(&mut self, _OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        Ok(())
+    }
+
+    fn update(&mut self, value: f64) -> Result<()> {
+        self.update_volume(value, 0.)
+    }
+
+    fn update_tmp(&mut self, value: f64) -> Result<()> {
+        self.update_tmp_volume(value, 0.)
+    }
+
+    fn reset_tmp(&mut self) {
+        self.state_tmp = ObvState::default();
+    }
+}
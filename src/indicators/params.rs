@@ -0,0 +1,203 @@
+use super::registry::custom_indicators_from_env;
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Indicator settings. Unset values fall back to each indicator defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct IndicatorParams {
+    pub period: Option<usize>,
+    pub multiplier: Option<f64>,
+    pub fast_period: Option<usize>,
+    pub slow_period: Option<usize>,
+    pub signal_period: Option<usize>,
+    #[serde(default)]
+    pub acceleration: Option<f64>,
+    #[serde(default)]
+    pub max_acceleration: Option<f64>,
+}
+
+impl IndicatorParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_period(period: usize) -> Self {
+        Self::new().period(period)
+    }
+
+    /// Reads {PREFIX}_PERIOD, {PREFIX}_MULTIPLIER, {PREFIX}_FAST, {PREFIX}_SLOW, {PREFIX}_SIGNAL,
+    /// {PREFIX}_ACCELERATION and {PREFIX}_MAX_ACCELERATION
+    pub fn from_env(prefix: &str) -> Self {
+        let parse_usize = |key: &str| {
+            env::var(format!("{}_{}", prefix, key))
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+        };
+
+        let parse_f64 = |key: &str| {
+            env::var(format!("{}_{}", prefix, key))
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+        };
+
+        Self {
+            period: parse_usize("PERIOD"),
+            multiplier: parse_f64("MULTIPLIER"),
+            fast_period: parse_usize("FAST"),
+            slow_period: parse_usize("SLOW"),
+            signal_period: parse_usize("SIGNAL"),
+            acceleration: parse_f64("ACCELERATION"),
+            max_acceleration: parse_f64("MAX_ACCELERATION"),
+        }
+    }
+
+    pub fn period(mut self, val: usize) -> Self {
+        self.period = Some(val);
+        self
+    }
+
+    pub fn multiplier(mut self, val: f64) -> Self {
+        self.multiplier = Some(val);
+        self
+    }
+
+    pub fn fast_period(mut self, val: usize) -> Self {
+        self.fast_period = Some(val);
+        self
+    }
+
+    pub fn slow_period(mut self, val: usize) -> Self {
+        self.slow_period = Some(val);
+        self
+    }
+
+    pub fn signal_period(mut self, val: usize) -> Self {
+        self.signal_period = Some(val);
+        self
+    }
+
+    pub fn acceleration(mut self, val: f64) -> Self {
+        self.acceleration = Some(val);
+        self
+    }
+
+    pub fn max_acceleration(mut self, val: f64) -> Self {
+        self.max_acceleration = Some(val);
+        self
+    }
+
+    pub fn period_or(&self, default: usize) -> Result<usize> {
+        non_zero(self.period.unwrap_or(default))
+    }
+
+    pub fn multiplier_or(&self, default: f64) -> Result<f64> {
+        positive(self.multiplier.unwrap_or(default))
+    }
+
+    pub fn acceleration_or(&self, default: f64) -> Result<f64> {
+        positive(self.acceleration.unwrap_or(default))
+    }
+
+    pub fn max_acceleration_or(&self, default: f64) -> Result<f64> {
+        positive(self.max_acceleration.unwrap_or(default))
+    }
+
+    pub fn fast_period_or(&self, default: usize) -> Result<usize> {
+        non_zero(self.fast_period.unwrap_or(default))
+    }
+
+    pub fn slow_period_or(&self, default: usize) -> Result<usize> {
+        non_zero(self.slow_period.unwrap_or(default))
+    }
+
+    pub fn signal_period_or(&self, default: usize) -> Result<usize> {
+        non_zero(self.signal_period.unwrap_or(default))
+    }
+}
+
+/// Per indicator settings used to build `Indicators`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct IndicatorsParams {
+    pub macd: IndicatorParams,
+    pub rsi: IndicatorParams,
+    pub atr: IndicatorParams,
+    pub bb: IndicatorParams,
+    pub bbw: IndicatorParams,
+    pub ema_a: IndicatorParams,
+    pub ema_b: IndicatorParams,
+    pub ema_c: IndicatorParams,
+    pub supertrend: IndicatorParams,
+    #[serde(default)]
+    pub obv: IndicatorParams,
+    #[serde(default)]
+    pub volume_ma: IndicatorParams,
+    #[serde(default)]
+    pub psar: IndicatorParams,
+    #[serde(default)]
+    pub cci: IndicatorParams,
+    #[serde(default)]
+    pub williams_r: IndicatorParams,
+    /// Registry names of the extra indicators to build.
+    #[serde(default)]
+    pub custom: Vec<String>,
+}
+
+impl IndicatorsParams {
+    /// EMA_A, EMA_B, EMA_C and MACD_A, MACD_B, MACD_C are still honoured
+    pub fn from_env() -> Self {
+        let legacy = |key: &str| env::var(key).ok().and_then(|val| val.parse::<usize>().ok());
+
+        let with_legacy_period = |prefix: &str| {
+            let mut params = IndicatorParams::from_env(prefix);
+            if params.period.is_none() {
+                params.period = legacy(prefix);
+            }
+            params
+        };
+
+        let mut macd = IndicatorParams::from_env("MACD");
+        macd.fast_period = macd.fast_period.or(legacy("MACD_A"));
+        macd.slow_period = macd.slow_period.or(legacy("MACD_B"));
+        macd.signal_period = macd.signal_period.or(legacy("MACD_C"));
+
+        Self {
+            macd,
+            rsi: IndicatorParams::from_env("RSI"),
+            atr: IndicatorParams::from_env("ATR"),
+            bb: IndicatorParams::from_env("BB"),
+            bbw: IndicatorParams::from_env("BBW"),
+            ema_a: with_legacy_period("EMA_A"),
+            ema_b: with_legacy_period("EMA_B"),
+            ema_c: with_legacy_period("EMA_C"),
+            supertrend: IndicatorParams::from_env("SUPERTREND"),
+            obv: IndicatorParams::from_env("OBV"),
+            volume_ma: IndicatorParams::from_env("VOLUME_MA"),
+            psar: IndicatorParams::from_env("PSAR"),
+            cci: IndicatorParams::from_env("CCI"),
+            williams_r: IndicatorParams::from_env("WILLIAMS_R"),
+            custom: custom_indicators_from_env(),
+        }
+    }
+}
+
+fn non_zero(val: usize) -> Result<usize> {
+    match val {
+        0 => Err(invalid_params()),
+        _ => Ok(val),
+    }
+}
+
+fn positive(val: f64) -> Result<f64> {
+    match val > 0. {
+        true => Ok(val),
+        false => Err(invalid_params()),
+    }
+}
+
+fn invalid_params() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::InvalidIndicatorParams,
+    }
+}
@@ -0,0 +1,198 @@
+use super::levels::{roll_bucket, Bucket};
+use super::{new_outputs, push_outputs, Outputs};
+use crate::models::time_frame::{period_key, TimeFrameType};
+use crate::scanner::candle::Candle;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PivotType {
+    Classic,
+    Fibonacci,
+    Camarilla,
+}
+
+impl PivotType {
+    pub fn from_str(pivot_type: &str) -> PivotType {
+        match pivot_type.to_lowercase().as_str() {
+            "fibonacci" => PivotType::Fibonacci,
+            "camarilla" => PivotType::Camarilla,
+            _ => PivotType::Classic,
+        }
+    }
+}
+
+impl Default for PivotType {
+    fn default() -> Self {
+        PivotType::Classic
+    }
+}
+
+/// Pivot and support/resistance levels of a closed period.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct PivotLevels {
+    pub pp: f64,
+    pub r1: f64,
+    pub r2: f64,
+    pub r3: f64,
+    pub s1: f64,
+    pub s2: f64,
+    pub s3: f64,
+}
+
+impl PivotLevels {
+    pub fn new(pivot_type: &PivotType, high: f64, low: f64, close: f64) -> Self {
+        let pp = (high + low + close) / 3.;
+        let range = high - low;
+
+        match pivot_type {
+            PivotType::Classic => Self {
+                pp,
+                r1: 2. * pp - low,
+                r2: pp + range,
+                r3: high + 2. * (pp - low),
+                s1: 2. * pp - high,
+                s2: pp - range,
+                s3: low - 2. * (high - pp),
+            },
+            PivotType::Fibonacci => Self {
+                pp,
+                r1: pp + 0.382 * range,
+                r2: pp + 0.618 * range,
+                r3: pp + range,
+                s1: pp - 0.382 * range,
+                s2: pp - 0.618 * range,
+                s3: pp - range,
+            },
+            PivotType::Camarilla => Self {
+                pp,
+                r1: close + range * 1.1 / 12.,
+                r2: close + range * 1.1 / 6.,
+                r3: close + range * 1.1 / 4.,
+                s1: close - range * 1.1 / 12.,
+                s2: close - range * 1.1 / 6.,
+                s3: close - range * 1.1 / 4.,
+            },
+        }
+    }
+
+    fn values(&self) -> [f64; 7] {
+        [
+            self.pp, self.r1, self.r2, self.r3, self.s1, self.s2, self.s3,
+        ]
+    }
+}
+
+/// Pivot levels of the previous higher time frame period (D, W or MN),
+/// one value per bar so intraday strategies can index them like any other
+/// indicator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pivots {
+    pivot_type: PivotType,
+    time_frame: TimeFrameType,
+    current: Option<Bucket>,
+    prev: Option<Bucket>,
+    outputs: Outputs,
+}
+
+impl Pivots {
+    pub fn new(pivot_type: PivotType, time_frame: TimeFrameType) -> Self {
+        Self {
+            pivot_type,
+            time_frame,
+            current: None,
+            prev: None,
+            outputs: new_outputs(&["pp", "r1", "r2", "r3", "s1", "s2", "s3"]),
+        }
+    }
+
+    /// Reads PIVOTS_TYPE (classic, fibonacci, camarilla) and PIVOTS_TIME_FRAME (D, W, MN)
+    pub fn from_env() -> Self {
+        let pivot_type =
+            PivotType::from_str(&env::var("PIVOTS_TYPE").unwrap_or("classic".to_string()));
+
+        let time_frame = match TimeFrameType::from_str(
+            &env::var("PIVOTS_TIME_FRAME").unwrap_or("D".to_string()),
+        ) {
+            TimeFrameType::ERR => TimeFrameType::D,
+            time_frame => time_frame,
+        };
+
+        Self::new(pivot_type, time_frame)
+    }
+
+    pub fn from_candles(&self, data: &Vec<Candle>) -> Self {
+        let mut pivots = Self::new(self.pivot_type.clone(), self.time_frame.clone());
+        for candle in data {
+            pivots.next(candle);
+        }
+        pivots
+    }
+
+    pub fn next(&mut self, candle: &Candle) {
+        let key = period_key(&candle.date(), &self.time_frame);
+        roll_bucket(&mut self.current, &mut self.prev, key, candle);
+        push_outputs(&mut self.outputs, &self.levels().values());
+    }
+
+    /// Levels come from the closed period, the open candle only feeds the running one.
+    pub fn update(&mut self, candle: &Candle) {
+        if let Some(bucket) = self.current.as_mut() {
+            bucket.update(candle);
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        for values in self.outputs.values_mut() {
+            if index < values.len() {
+                values.remove(index);
+            }
+        }
+    }
+
+    pub fn pivot_type(&self) -> &PivotType {
+        &self.pivot_type
+    }
+
+    pub fn time_frame(&self) -> &TimeFrameType {
+        &self.time_frame
+    }
+
+    /// Levels of the last closed period, zeroed until one closes.
+    pub fn levels(&self) -> PivotLevels {
+        match &self.prev {
+            Some(bucket) => {
+                PivotLevels::new(&self.pivot_type, bucket.high, bucket.low, bucket.close)
+            }
+            None => PivotLevels::default(),
+        }
+    }
+
+    pub fn outputs(&self) -> &Outputs {
+        &self.outputs
+    }
+
+    pub fn output(&self, name: &str) -> Option<&Vec<f64>> {
+        self.outputs.get(name)
+    }
+
+    pub fn get(&self, index: usize) -> Option<PivotLevels> {
+        let value = |name: &str| self.outputs.get(name).and_then(|values| values.get(index));
+        Some(PivotLevels {
+            pp: *value("pp")?,
+            r1: *value("r1")?,
+            r2: *value("r2")?,
+            r3: *value("r3")?,
+            s1: *value("s1")?,
+            s2: *value("s2")?,
+            s3: *value("s3")?,
+        })
+    }
+}
+
+impl Default for Pivots {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
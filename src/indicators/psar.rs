@@ -0,0 +1,227 @@
+use super::{
+    new_outputs, push_outputs, rehydrate, replay_OHLC, update_outputs, Indicator, Outputs,
+};
+use crate::error::Result;
+use crate::indicators::params::IndicatorParams;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default)]
+struct PsarState {
+    is_long: bool,
+    sar: f64,
+    extreme_point: f64,
+    acceleration: f64,
+    prev_bars: Vec<(f64, f64)>,
+}
+
+impl PsarState {
+    /// Returns (sar, direction) for the bar. Direction is 1 for long and -1 for short.
+    fn next(&mut self, high: f64, low: f64, start: f64, step: f64, max: f64) -> (f64, f64) {
+        if self.prev_bars.is_empty() {
+            self.is_long = true;
+            self.sar = low;
+            self.extreme_point = high;
+            self.acceleration = start;
+            self.prev_bars.push((high, low));
+            return (self.sar, 1.);
+        }
+
+        let mut sar = self.sar + self.acceleration * (self.extreme_point - self.sar);
+
+        match self.is_long {
+            true => {
+                for (_, prev_low) in self.prev_bars.iter() {
+                    sar = sar.min(*prev_low);
+                }
+
+                if low < sar {
+                    self.is_long = false;
+                    sar = self.extreme_point;
+                    self.extreme_point = low;
+                    self.acceleration = start;
+                } else if high > self.extreme_point {
+                    self.extreme_point = high;
+                    self.acceleration = (self.acceleration + step).min(max);
+                }
+            }
+            false => {
+                for (prev_high, _) in self.prev_bars.iter() {
+                    sar = sar.max(*prev_high);
+                }
+
+                if high > sar {
+                    self.is_long = true;
+                    sar = self.extreme_point;
+                    self.extreme_point = high;
+                    self.acceleration = start;
+                } else if low < self.extreme_point {
+                    self.extreme_point = low;
+                    self.acceleration = (self.acceleration + step).min(max);
+                }
+            }
+        };
+
+        self.sar = sar;
+        self.prev_bars.push((high, low));
+        if self.prev_bars.len() > 2 {
+            self.prev_bars.remove(0);
+        }
+
+        let direction = match self.is_long {
+            true => 1.,
+            false => -1.,
+        };
+
+        (sar, direction)
+    }
+}
+
+/// Parabolic SAR. Outputs the sar and the direction (1 long, -1 short).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParabolicSar {
+    acceleration: f64,
+    step: f64,
+    max_acceleration: f64,
+    #[serde(skip)]
+    state: PsarState,
+    #[serde(skip)]
+    state_tmp: PsarState,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
+}
+
+impl ParabolicSar {
+    pub fn new_psar(acceleration: f64, step: f64, max_acceleration: f64) -> Result<Self> {
+        Ok(Self {
+            acceleration,
+            step,
+            max_acceleration,
+            state: PsarState::default(),
+            state_tmp: PsarState::default(),
+            params: IndicatorParams::new()
+                .acceleration(acceleration)
+                .multiplier(step)
+                .max_acceleration(max_acceleration),
+            outputs: new_outputs(&["sar", "direction"]),
+        })
+    }
+
+    pub fn sar(&self) -> &Vec<f64> {
+        self.output("sar").unwrap()
+    }
+
+    pub fn direction(&self) -> &Vec<f64> {
+        self.output("direction").unwrap()
+    }
+
+    pub fn is_long(&self) -> bool {
+        matches!(self.direction().last(), Some(direction) if *direction > 0.)
+    }
+
+    pub fn is_short(&self) -> bool {
+        matches!(self.direction().last(), Some(direction) if *direction < 0.)
+    }
+}
+
+impl Default for ParabolicSar {
+    fn default() -> Self {
+        Self::new().unwrap()
+    }
+}
+
+impl Indicator for ParabolicSar {
+    /// Standard 0.02 start, 0.02 step and 0.2 max acceleration factors.
+    /// The step is taken from the params multiplier.
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        Self::new_psar(
+            params.acceleration_or(0.02)?,
+            params.multiplier_or(0.02)?,
+            params.max_acceleration_or(0.2)?,
+        )
+    }
+
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
+    }
+
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
+    }
+
+    fn params(&self) -> &IndicatorParams {
+        &self.params
+    }
+
+    fn update_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let (a, b) = self.state.next(
+            OHLC.1,
+            OHLC.2,
+            self.acceleration,
+            self.step,
+            self.max_acceleration,
+        );
+        update_outputs(&mut self.outputs, &[a, b]);
+        Ok(())
+    }
+
+    fn next_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) {
+        self.state_tmp.next(
+            OHLC.1,
+            OHLC.2,
+            self.acceleration,
+            self.step,
+            self.max_acceleration,
+        );
+    }
+
+    fn update_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let (a, b) = self.state_tmp.next(
+            OHLC.1,
+            OHLC.2,
+            self.acceleration,
+            self.step,
+            self.max_acceleration,
+        );
+        update_outputs(&mut self.outputs, &[a, b]);
+        Ok(())
+    }
+
+    fn warm_up_OHLC(&mut self, data: &[(f64, f64, f64, f64)]) -> Result<()> {
+        let params = self.params.clone();
+        rehydrate(self, &params, |indicator| replay_OHLC(indicator, data))
+    }
+
+    fn next(&mut self, value: f64) -> Result<()> {
+        self.next_OHLC((value, value, value, value))
+    }
+
+    fn next_tmp(&mut self, value: f64) {
+        self.next_tmp_OHLC((value, value, value, value));
+    }
+
+    fn next_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let (a, b) = self.state.next(
+            OHLC.1,
+            OHLC.2,
+            self.acceleration,
+            self.step,
+            self.max_acceleration,
+        );
+        push_outputs(&mut self.outputs, &[a, b]);
+        Ok(())
+    }
+
+    fn update(&mut self, value: f64) -> Result<()> {
+        self.update_OHLC((value, value, value, value))
+    }
+
+    fn update_tmp(&mut self, value: f64) -> Result<()> {
+        self.update_tmp_OHLC((value, value, value, value))
+    }
+
+    fn reset_tmp(&mut self) {
+        self.state_tmp = PsarState::default();
+    }
+}
@@ -0,0 +1,237 @@
+use super::adx::Adx;
+use super::atr::Atr;
+use super::bb::BollingerB;
+use super::bbw::BollingerBW;
+use super::cci::Cci;
+use super::ema::Ema;
+use super::macd::Macd;
+use super::obv::Obv;
+use super::params::IndicatorParams;
+use super::psar::ParabolicSar;
+use super::rsi::Rsi;
+use super::stoch::Stoch;
+use super::supertrend::SuperTrend;
+use super::volume_ma::VolumeMa;
+use super::williams_r::WilliamsR;
+use super::{Indicator, Outputs};
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::scanner::candle::Candle;
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::sync::Mutex;
+
+pub type BoxedIndicator = Box<dyn Indicator>;
+pub type IndicatorFactory = fn(&IndicatorParams) -> Result<BoxedIndicator>;
+
+static INDICATOR_REGISTRY: Mutex<Option<IndicatorRegistry>> = Mutex::new(None);
+
+fn boxed<I: Indicator + 'static>(params: &IndicatorParams) -> Result<BoxedIndicator> {
+    Ok(Box::new(I::new_with_params(params)?))
+}
+
+/// Indicator factories by name. Comes with the crate indicators, downstream
+/// crates add their own with register.
+#[derive(Clone)]
+pub struct IndicatorRegistry {
+    factories: HashMap<String, IndicatorFactory>,
+}
+
+impl IndicatorRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            factories: HashMap::new(),
+        };
+
+        registry.register("adx", boxed::<Adx>);
+        registry.register("atr", boxed::<Atr>);
+        registry.register("bb", boxed::<BollingerB>);
+        registry.register("bbw", boxed::<BollingerBW>);
+        registry.register("cci", boxed::<Cci>);
+        registry.register("ema", boxed::<Ema>);
+        registry.register("macd", boxed::<Macd>);
+        registry.register("obv", boxed::<Obv>);
+        registry.register("psar", boxed::<ParabolicSar>);
+        registry.register("rsi", boxed::<Rsi>);
+        registry.register("stoch", boxed::<Stoch>);
+        registry.register("supertrend", boxed::<SuperTrend>);
+        registry.register("volume_ma", boxed::<VolumeMa>);
+        registry.register("williams_r", boxed::<WilliamsR>);
+        registry
+    }
+
+    /// Registering an existing name replaces its factory.
+    pub fn register(&mut self, name: &str, factory: IndicatorFactory) {
+        self.factories.insert(name.to_lowercase(), factory);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(&name.to_lowercase())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.factories.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn create(&self, name: &str, params: &IndicatorParams) -> Result<BoxedIndicator> {
+        match self.factories.get(&name.to_lowercase()) {
+            Some(factory) => factory(params),
+            None => {
+                log::error!("Indicator {} not registered", name);
+                Err(RsAlgoError {
+                    err: RsAlgoErrorKind::InvalidIndicatorParams,
+                })
+            }
+        }
+    }
+}
+
+impl Default for IndicatorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn register_indicator(name: &str, factory: IndicatorFactory) {
+    let mut registry = INDICATOR_REGISTRY.lock().unwrap();
+    registry
+        .get_or_insert_with(IndicatorRegistry::new)
+        .register(name, factory);
+}
+
+pub fn create_indicator(name: &str, params: &IndicatorParams) -> Result<BoxedIndicator> {
+    let mut registry = INDICATOR_REGISTRY.lock().unwrap();
+    registry
+        .get_or_insert_with(IndicatorRegistry::new)
+        .create(name, params)
+}
+
+pub fn registered_indicators() -> Vec<String> {
+    let mut registry = INDICATOR_REGISTRY.lock().unwrap();
+    registry.get_or_insert_with(IndicatorRegistry::new).names()
+}
+
+/// Comma separated names from CUSTOM_INDICATORS.
+pub fn custom_indicators_from_env() -> Vec<String> {
+    env::var("CUSTOM_INDICATORS")
+        .unwrap_or("".to_string())
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Indicators built by name from the registry. They are fed OHLC bars and
+/// are not persisted, warm_up rebuilds them from the candles.
+#[derive(Clone, Default)]
+pub struct CustomIndicators {
+    indicators: Vec<(String, BoxedIndicator)>,
+}
+
+impl CustomIndicators {
+    /// Params of each name are read from {NAME}_PERIOD, {NAME}_MULTIPLIER...
+    pub fn new(names: &Vec<String>) -> Result<Self> {
+        let mut indicators = vec![];
+        for name in names {
+            let params = IndicatorParams::from_env(&name.to_uppercase());
+            indicators.push((name.clone(), create_indicator(name, &params)?));
+        }
+        Ok(Self { indicators })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indicators.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.indicators
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&BoxedIndicator> {
+        self.indicators
+            .iter()
+            .find(|(indicator_name, _)| indicator_name == name)
+            .map(|(_, indicator)| indicator)
+    }
+
+    pub fn outputs(&self, name: &str) -> Option<&Outputs> {
+        self.get(name).map(|indicator| indicator.outputs())
+    }
+
+    pub fn next(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        for (_, indicator) in self.indicators.iter_mut() {
+            indicator.next_OHLC(OHLC)?;
+        }
+        Ok(())
+    }
+
+    pub fn update(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        for (_, indicator) in self.indicators.iter_mut() {
+            indicator.update_OHLC(OHLC)?;
+        }
+        Ok(())
+    }
+
+    pub fn update_tmp(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        for (_, indicator) in self.indicators.iter_mut() {
+            indicator.update_tmp_OHLC(OHLC)?;
+        }
+        Ok(())
+    }
+
+    pub fn create_tmp(&mut self, candle: &Candle, prev_data: &Vec<Candle>) -> Result<()> {
+        for (_, indicator) in self.indicators.iter_mut() {
+            for prev_candle in prev_data
+                .iter()
+                .rev()
+                .take(50)
+                .rev()
+                .filter(|x| x.is_closed == true)
+            {
+                indicator.next_tmp_OHLC(ohlc(prev_candle));
+            }
+            indicator.update_tmp_OHLC(ohlc(candle))?;
+            indicator.reset_tmp();
+        }
+        Ok(())
+    }
+
+    pub fn trim(&mut self, max_bars: usize) {
+        for (_, indicator) in self.indicators.iter_mut() {
+            indicator.trim(max_bars);
+        }
+    }
+
+    pub fn duplicate_last(&mut self) {
+        for (_, indicator) in self.indicators.iter_mut() {
+            indicator.duplicate_last();
+        }
+    }
+
+    /// Rebuilds every indicator and replays the history through it.
+    pub fn warm_up(&mut self, names: &Vec<String>, data: &[(f64, f64, f64, f64)]) -> Result<()> {
+        *self = Self::new(names)?;
+        for OHLC in data {
+            self.next(*OHLC)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CustomIndicators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomIndicators")
+            .field("indicators", &self.names())
+            .finish()
+    }
+}
+
+fn ohlc(candle: &Candle) -> (f64, f64, f64, f64) {
+    (candle.open(), candle.high(), candle.low(), candle.close())
+}
@@ -1,6 +1,7 @@
-use super::Indicator;
+use super::{new_outputs, push_outputs, update_outputs, Indicator, Outputs, StoredIndicator};
+use crate::indicators::params::IndicatorParams;
 
-use crate::error::Result;
+use crate::error::{Result, RsAlgoError};
 
 use ta::indicators::SlowStochastic;
 
@@ -9,7 +10,7 @@ use ta::indicators::ExponentialMovingAverage;
 use ta::{Next, Reset};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-
+#[serde(try_from = "StoredIndicator")]
 pub struct Stoch {
     #[serde(skip_deserializing)]
     stoch: SlowStochastic,
@@ -19,53 +20,50 @@ pub struct Stoch {
     stoch_tmp: SlowStochastic,
     #[serde(skip_deserializing)]
     ema_tmp: ExponentialMovingAverage,
-    data_a: Vec<f64>,
-    data_b: Vec<f64>,
-    data_c: Vec<f64>,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
 }
 
-impl Indicator for Stoch {
-    fn new() -> Result<Self> {
-        Ok(Self {
-            stoch: SlowStochastic::new(10, 3).unwrap(),
-            ema: ExponentialMovingAverage::new(3).unwrap(),
-            stoch_tmp: SlowStochastic::new(10, 3).unwrap(),
-            ema_tmp: ExponentialMovingAverage::new(3).unwrap(),
-            data_a: vec![],
-            data_b: vec![],
-            data_c: vec![],
-        })
-    }
+impl TryFrom<StoredIndicator> for Stoch {
+    type Error = RsAlgoError;
 
-    fn get_data_a(&self) -> &Vec<f64> {
-        &self.data_a
+    fn try_from(stored: StoredIndicator) -> Result<Self> {
+        stored.restore()
     }
+}
 
-    fn get_current_a(&self) -> &f64 {
-        &self.data_a.last().unwrap()
-    }
+impl Indicator for Stoch {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        let period = params.period_or(10)?;
+        let signal = params.signal_period_or(3)?;
 
-    fn get_data_b(&self) -> &Vec<f64> {
-        &self.data_b
+        Ok(Self {
+            stoch: SlowStochastic::new(period, signal).unwrap(),
+            ema: ExponentialMovingAverage::new(signal).unwrap(),
+            stoch_tmp: SlowStochastic::new(period, signal).unwrap(),
+            ema_tmp: ExponentialMovingAverage::new(signal).unwrap(),
+            params: params.clone(),
+            outputs: new_outputs(&["k", "d"]),
+        })
     }
 
-    fn get_current_b(&self) -> &f64 {
-        &self.data_b.last().unwrap()
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
     }
 
-    fn get_data_c(&self) -> &Vec<f64> {
-        &self.data_c
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
     }
 
-    fn get_current_c(&self) -> &f64 {
-        &self.data_c.last().unwrap()
+    fn params(&self) -> &IndicatorParams {
+        &self.params
     }
 
     fn next(&mut self, value: f64) -> Result<()> {
         let a = self.stoch.next(value);
         let b = self.ema.next(a);
-        self.data_a.push(a);
-        self.data_b.push(b);
+        push_outputs(&mut self.outputs, &[a, b]);
         Ok(())
     }
 
@@ -76,20 +74,14 @@ impl Indicator for Stoch {
     fn update(&mut self, value: f64) -> Result<()> {
         let a = self.stoch.next(value);
         let b = self.ema.next(a);
-        let last_a = self.data_a.last_mut().unwrap();
-        let last_b = self.data_b.last_mut().unwrap();
-        *last_a = a;
-        *last_b = b;
+        update_outputs(&mut self.outputs, &[a, b]);
         Ok(())
     }
 
     fn update_tmp(&mut self, value: f64) -> Result<()> {
         let a = self.stoch.next(value);
         let b = self.ema.next(a);
-        let last_a = self.data_a.last_mut().unwrap();
-        let last_b = self.data_b.last_mut().unwrap();
-        *last_a = a;
-        *last_b = b;
+        update_outputs(&mut self.outputs, &[a, b]);
         Ok(())
     }
 
@@ -97,27 +89,4 @@ impl Indicator for Stoch {
         self.stoch_tmp.reset();
         self.ema_tmp.reset();
     }
-
-    fn next_OHLC(&mut self, _OHLC: (f64, f64, f64, f64)) -> Result<()> {
-        Ok(())
-    }
-
-    fn remove_a(&mut self, index: usize) -> f64 {
-        self.data_a.remove(index)
-    }
-
-    fn remove_b(&mut self, index: usize) -> f64 {
-        self.data_b.remove(index)
-    }
-
-    fn remove_c(&mut self, index: usize) -> f64 {
-        self.data_c.remove(index)
-    }
-
-    fn duplicate_last(&mut self) {
-        let a = self.data_a.last().unwrap();
-        let b = self.data_b.last().unwrap();
-        self.data_a.push(*a);
-        self.data_b.push(*b);
-    }
 }
@@ -0,0 +1,209 @@
+use super::{
+    new_outputs, push_outputs, rehydrate, replay_OHLC, update_outputs, Indicator, Outputs,
+};
+use crate::error::Result;
+use crate::indicators::params::IndicatorParams;
+
+use serde::{Deserialize, Serialize};
+use ta::indicators::AverageTrueRange;
+use ta::test_helper::Bar;
+use ta::{Next, Reset};
+
+#[derive(Debug, Clone, Default)]
+struct SuperTrendState {
+    atr: AverageTrueRange,
+    upper: f64,
+    lower: f64,
+    prev_close: f64,
+    direction: f64,
+    is_warm: bool,
+}
+
+impl SuperTrendState {
+    fn new(period: usize) -> Self {
+        Self {
+            atr: AverageTrueRange::new(period).unwrap(),
+            upper: 0.,
+            lower: 0.,
+            prev_close: 0.,
+            direction: 1.,
+            is_warm: false,
+        }
+    }
+
+    /// Returns (trend line, direction) for the bar. Direction is 1 for long and -1 for short.
+    fn next(&mut self, OHLC: (f64, f64, f64, f64), multiplier: f64) -> (f64, f64) {
+        let (_open, high, low, close) = OHLC;
+        let bar = Bar::new().high(high).low(low).close(close);
+        let atr = self.atr.next(&bar);
+        let hl2 = (high + low) / 2.;
+        let basic_upper = hl2 + multiplier * atr;
+        let basic_lower = hl2 - multiplier * atr;
+
+        match self.is_warm {
+            true => {
+                if basic_upper < self.upper || self.prev_close > self.upper {
+                    self.upper = basic_upper;
+                }
+                if basic_lower > self.lower || self.prev_close < self.lower {
+                    self.lower = basic_lower;
+                }
+
+                self.direction = match self.direction > 0. {
+                    true => match close < self.lower {
+                        true => -1.,
+                        false => 1.,
+                    },
+                    false => match close > self.upper {
+                        true => 1.,
+                        false => -1.,
+                    },
+                };
+            }
+            false => {
+                self.upper = basic_upper;
+                self.lower = basic_lower;
+                self.direction = 1.;
+                self.is_warm = true;
+            }
+        };
+
+        self.prev_close = close;
+
+        let line = match self.direction > 0. {
+            true => self.lower,
+            false => self.upper,
+        };
+
+        (line, self.direction)
+    }
+
+    fn reset(&mut self) {
+        self.atr.reset();
+        self.upper = 0.;
+        self.lower = 0.;
+        self.prev_close = 0.;
+        self.direction = 1.;
+        self.is_warm = false;
+    }
+}
+
+/// ATR band trailing line. Outputs the trend line and the direction
+/// (1 long, -1 short).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuperTrend {
+    multiplier: f64,
+    #[serde(skip)]
+    state: SuperTrendState,
+    #[serde(skip)]
+    state_tmp: SuperTrendState,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
+}
+
+impl SuperTrend {
+    pub fn new_supertrend(period: usize, multiplier: f64) -> Result<Self> {
+        Ok(Self {
+            multiplier,
+            state: SuperTrendState::new(period),
+            state_tmp: SuperTrendState::new(period),
+            params: IndicatorParams::with_period(period).multiplier(multiplier),
+            outputs: new_outputs(&["trend", "direction"]),
+        })
+    }
+
+    pub fn trend(&self) -> &Vec<f64> {
+        self.output("trend").unwrap()
+    }
+
+    pub fn direction(&self) -> &Vec<f64> {
+        self.output("direction").unwrap()
+    }
+
+    pub fn is_long(&self) -> bool {
+        matches!(self.direction().last(), Some(direction) if *direction > 0.)
+    }
+
+    pub fn is_short(&self) -> bool {
+        matches!(self.direction().last(), Some(direction) if *direction < 0.)
+    }
+
+    /// True when the direction changed on the last bar.
+    pub fn is_flip(&self) -> bool {
+        let direction = self.direction();
+        let len = direction.len();
+        len > 1 && direction[len - 1] != direction[len - 2]
+    }
+}
+
+impl Default for SuperTrend {
+    fn default() -> Self {
+        Self::new().unwrap()
+    }
+}
+
+impl Indicator for SuperTrend {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        Self::new_supertrend(params.period_or(10)?, params.multiplier_or(3.)?)
+    }
+
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
+    }
+
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
+    }
+
+    fn params(&self) -> &IndicatorParams {
+        &self.params
+    }
+
+    fn update_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let (a, b) = self.state.next(OHLC, self.multiplier);
+        update_outputs(&mut self.outputs, &[a, b]);
+        Ok(())
+    }
+
+    fn next_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) {
+        self.state_tmp.next(OHLC, self.multiplier);
+    }
+
+    fn update_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let (a, b) = self.state_tmp.next(OHLC, self.multiplier);
+        update_outputs(&mut self.outputs, &[a, b]);
+        Ok(())
+    }
+
+    fn warm_up_OHLC(&mut self, data: &[(f64, f64, f64, f64)]) -> Result<()> {
+        let params = self.params.clone();
+        rehydrate(self, &params, |indicator| replay_OHLC(indicator, data))
+    }
+
+    fn next(&mut self, value: f64) -> Result<()> {
+        self.next_OHLC((value, value, value, value))
+    }
+
+    fn next_tmp(&mut self, value: f64) {
+        self.next_tmp_OHLC((value, value, value, value));
+    }
+
+    fn next_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let (a, b) = self.state.next(OHLC, self.multiplier);
+        push_outputs(&mut self.outputs, &[a, b]);
+        Ok(())
+    }
+
+    fn update(&mut self, value: f64) -> Result<()> {
+        self.update_OHLC((value, value, value, value))
+    }
+
+    fn update_tmp(&mut self, value: f64) -> Result<()> {
+        self.update_tmp_OHLC((value, value, value, value))
+    }
+
+    fn reset_tmp(&mut self) {
+        self.state_tmp.reset();
+    }
+}
@@ -0,0 +1,91 @@
+use super::{new_outputs, push_outputs, update_outputs, Indicator, Outputs};
+use crate::error::Result;
+use crate::indicators::params::IndicatorParams;
+
+use serde::{Deserialize, Serialize};
+use ta::indicators::SimpleMovingAverage;
+use ta::{Next, Reset};
+
+/// Simple moving average of the bar volume. Values fed through the
+/// Indicator methods are volumes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeMa {
+    #[serde(skip_deserializing)]
+    sma: SimpleMovingAverage,
+    #[serde(skip_deserializing)]
+    sma_tmp: SimpleMovingAverage,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
+}
+
+impl VolumeMa {
+    /// Ratio between the volume and its average, above 1 on expanding volume.
+    pub fn relative_volume(&self, volume: f64) -> f64 {
+        match self.output_current("volume_ma") {
+            Some(avg) if *avg > 0. => volume / avg,
+            _ => 0.,
+        }
+    }
+}
+
+impl Default for VolumeMa {
+    fn default() -> Self {
+        Self::new().unwrap()
+    }
+}
+
+impl Indicator for VolumeMa {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        let period = params.period_or(20)?;
+
+        Ok(Self {
+            sma: SimpleMovingAverage::new(period).unwrap(),
+            sma_tmp: SimpleMovingAverage::new(period).unwrap(),
+            params: params.clone(),
+            outputs: new_outputs(&["volume_ma"]),
+        })
+    }
+
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
+    }
+
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
+    }
+
+    fn params(&self) -> &IndicatorParams {
+        &self.params
+    }
+
+    fn next(&mut self, value: f64) -> Result<()> {
+        let a = self.sma.next(value);
+        push_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn next_tmp(&mut self, value: f64) {
+        self.sma_tmp.next(value);
+    }
+
+    fn next_OHLC(&mut self, _OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        Ok(())
+    }
+
+    fn update(&mut self, value: f64) -> Result<()> {
+        let a = self.sma.next(value);
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn update_tmp(&mut self, value: f64) -> Result<()> {
+        let a = self.sma_tmp.next(value);
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn reset_tmp(&mut self) {
+        self.sma_tmp.reset();
+    }
+}
@@ -0,0 +1,139 @@
+use super::{
+    new_outputs, push_outputs, rehydrate, replay_OHLC, update_outputs, Indicator, Outputs,
+};
+use crate::error::Result;
+use crate::indicators::params::IndicatorParams;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default)]
+struct WilliamsRState {
+    period: usize,
+    bars: Vec<(f64, f64)>,
+}
+
+impl WilliamsRState {
+    fn new(period: usize) -> Self {
+        Self {
+            period,
+            bars: vec![],
+        }
+    }
+
+    fn next(&mut self, high: f64, low: f64, close: f64) -> f64 {
+        self.bars.push((high, low));
+        if self.bars.len() > self.period {
+            self.bars.remove(0);
+        }
+
+        let highest = self
+            .bars
+            .iter()
+            .map(|(high, _)| *high)
+            .fold(f64::MIN, f64::max);
+        let lowest = self
+            .bars
+            .iter()
+            .map(|(_, low)| *low)
+            .fold(f64::MAX, f64::min);
+
+        match highest > lowest {
+            true => (highest - close) / (highest - lowest) * -100.,
+            false => -50.,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.bars = vec![];
+    }
+}
+
+/// Williams %R, from 0 (close at the period high) to -100 (close at the period low).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WilliamsR {
+    #[serde(skip)]
+    state: WilliamsRState,
+    #[serde(skip)]
+    state_tmp: WilliamsRState,
+    #[serde(default)]
+    params: IndicatorParams,
+    outputs: Outputs,
+}
+
+impl Default for WilliamsR {
+    fn default() -> Self {
+        Self::new().unwrap()
+    }
+}
+
+impl Indicator for WilliamsR {
+    fn new_with_params(params: &IndicatorParams) -> Result<Self> {
+        let period = params.period_or(14)?;
+
+        Ok(Self {
+            state: WilliamsRState::new(period),
+            state_tmp: WilliamsRState::new(period),
+            params: params.clone(),
+            outputs: new_outputs(&["williams_r"]),
+        })
+    }
+
+    fn outputs(&self) -> &Outputs {
+        &self.outputs
+    }
+
+    fn outputs_mut(&mut self) -> &mut Outputs {
+        &mut self.outputs
+    }
+
+    fn params(&self) -> &IndicatorParams {
+        &self.params
+    }
+
+    fn update_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let a = self.state.next(OHLC.1, OHLC.2, OHLC.3);
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn next_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) {
+        self.state_tmp.next(OHLC.1, OHLC.2, OHLC.3);
+    }
+
+    fn update_tmp_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let a = self.state_tmp.next(OHLC.1, OHLC.2, OHLC.3);
+        update_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn warm_up_OHLC(&mut self, data: &[(f64, f64, f64, f64)]) -> Result<()> {
+        let params = self.params.clone();
+        rehydrate(self, &params, |indicator| replay_OHLC(indicator, data))
+    }
+
+    fn next(&mut self, value: f64) -> Result<()> {
+        self.next_OHLC((value, value, value, value))
+    }
+
+    fn next_tmp(&mut self, value: f64) {
+        self.next_tmp_OHLC((value, value, value, value));
+    }
+
+    fn next_OHLC(&mut self, OHLC: (f64, f64, f64, f64)) -> Result<()> {
+        let a = self.state.next(OHLC.1, OHLC.2, OHLC.3);
+        push_outputs(&mut self.outputs, &[a]);
+        Ok(())
+    }
+
+    fn update(&mut self, value: f64) -> Result<()> {
+        self.update_OHLC((value, value, value, value))
+    }
+
+    fn update_tmp(&mut self, value: f64) -> Result<()> {
+        self.update_tmp_OHLC((value, value, value, value))
+    }
+
+    fn reset_tmp(&mut self) {
+        self.state_tmp.reset();
+    }
+}
@@ -10,6 +10,8 @@ pub mod patterns;
 
 pub mod indicators;
 
+pub mod backtest;
+
 #[cfg(feature = "websocket")]
 pub mod ws;
 
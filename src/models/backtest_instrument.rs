@@ -1,11 +1,17 @@
+use crate::helpers::calc;
 use crate::helpers::date::*;
+use crate::helpers::uuid::IdGenerator;
 use crate::models::market::*;
+use crate::scanner::instrument::Instrument;
+use crate::scanner::regime::RegimeStats;
 
 use crate::models::strategy::*;
 use crate::models::trade::*;
 
 use serde::{Deserialize, Serialize};
+use std::env;
 
+use super::currency::CurrencyConverter;
 use super::order::Order;
 use super::time_frame::TimeFrameType;
 
@@ -36,6 +42,12 @@ pub struct BackTestInstrumentResult {
     pub max_drawdown: f64,
     pub buy_hold: f64,
     pub annual_return: f64,
+    #[serde(default)]
+    pub open_positions: Vec<OpenPosition>,
+    #[serde(default)]
+    pub unrealized_profit: f64,
+    #[serde(default)]
+    pub regime_stats: Vec<RegimeStats>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -46,6 +58,34 @@ pub struct BackTestInstrument {
     pub orders: Vec<Order>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum OpenPositionPolicy {
+    Liquidate,
+    Exclude,
+    MarkOpen,
+}
+
+impl OpenPositionPolicy {
+    pub fn from_env() -> Self {
+        let policy = env::var("BACKTEST_OPEN_POSITIONS").unwrap_or("liquidate".to_string());
+
+        match policy.as_ref() {
+            "exclude" => OpenPositionPolicy::Exclude,
+            "open" => OpenPositionPolicy::MarkOpen,
+            _ => OpenPositionPolicy::Liquidate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenPosition {
+    pub trade_in: TradeIn,
+    pub policy: OpenPositionPolicy,
+    pub last_price: f64,
+    pub unrealized_profit: f64,
+    pub unrealized_profit_per: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum BackTestResult {
     BackTestInstrumentResult(BackTestInstrumentResult),
@@ -63,3 +103,104 @@ impl std::fmt::Display for BackTestInstrument {
         write!(f, "{:?}", self)
     }
 }
+
+pub fn resolve_open_positions(
+    instrument: &Instrument,
+    trades_in: &mut Vec<TradeIn>,
+    trades_out: &mut Vec<TradeOut>,
+    policy: &OpenPositionPolicy,
+    ids: &mut IdGenerator,
+) -> Vec<OpenPosition> {
+    let data = instrument.data();
+    let last_candle = match data.last() {
+        Some(candle) => candle,
+        None => return vec![],
+    };
+    let last_index = data.len() - 1;
+    let last_price = last_candle.close();
+
+    let open_trades: Vec<TradeIn> = trades_in
+        .iter()
+        .filter(|trade_in| {
+            !trades_out
+                .iter()
+                .any(|trade_out| trade_out.index_in == trade_in.index_in)
+        })
+        .cloned()
+        .collect();
+
+    let mut open_positions = vec![];
+    let conversion_rate = CurrencyConverter::from_env().rate(instrument.symbol());
+
+    for trade_in in open_trades {
+        let trade_type = &trade_in.trade_type;
+        let price_in = trade_in.price_in;
+        let unrealized_profit = calc::calculate_profit_in_account(
+            trade_in.quantity,
+            price_in,
+            last_price,
+            trade_type,
+            conversion_rate,
+        );
+        let unrealized_profit_per = calc::calculate_profit_per(price_in, last_price, trade_type);
+
+        match policy {
+            OpenPositionPolicy::Liquidate => {
+                let index_in = trade_in.index_in;
+                let run_up =
+                    calc::calculate_runup(data, price_in, index_in, last_index, trade_type);
+                let draw_down =
+                    calc::calculate_drawdown(data, price_in, index_in, last_index, trade_type);
+
+                let trade_type_out = match trade_type.is_long() {
+                    true => TradeType::MarketOutLong,
+                    false => TradeType::MarketOutShort,
+                };
+
+                log::info!(
+                    "Liquidating open {:?} at last close {}",
+                    trade_type,
+                    last_price
+                );
+
+                trades_out.push(TradeOut {
+                    id: ids.next(last_candle.date()),
+                    trade_type: trade_type_out,
+                    index_in,
+                    price_in,
+                    ask: trade_in.ask,
+                    spread_in: trade_in.spread,
+                    date_in: trade_in.date_in,
+                    index_out: last_index,
+                    price_origin: price_in,
+                    price_out: last_price,
+                    bid: last_price,
+                    spread_out: trade_in.spread,
+                    date_out: to_dbtime(last_candle.date()),
+                    profit: unrealized_profit,
+                    profit_per: unrealized_profit_per,
+                    run_up,
+                    run_up_per: calc::calculate_runup_per(run_up, price_in, trade_type),
+                    draw_down,
+                    draw_down_per: calc::calculate_drawdown_per(draw_down, price_in, trade_type),
+                    tags: trade_in.tags.clone(),
+                    close_reason: CloseReason::EndOfData,
+                });
+            }
+            OpenPositionPolicy::Exclude => {
+                trades_in.retain(|x| x.index_in != trade_in.index_in);
+            }
+            OpenPositionPolicy::MarkOpen => (),
+        };
+
+        open_positions.push(OpenPosition {
+            trade_in,
+            policy: policy.clone(),
+            last_price,
+            unrealized_profit,
+            unrealized_profit_per,
+        });
+    }
+
+    open_positions
+}
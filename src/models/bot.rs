@@ -1,4 +1,6 @@
+use super::exposure::Exposure;
 use super::order::Order;
+use crate::error::Result;
 use crate::helpers::date::*;
 use crate::helpers::uuid::Uuid;
 use crate::models::market::*;
@@ -76,4 +78,20 @@ impl BotData {
     pub fn strategy_type(&self) -> &StrategyType {
         &self.strategy_type
     }
+    pub fn exposure(&self) -> Exposure {
+        Exposure::from_env(
+            &self.symbol,
+            &self.trades_in,
+            &self.trades_out,
+            &self.orders,
+        )
+    }
+    /// Restores the indicators state of a bot loaded from the db.
+    pub fn warm_up(&mut self) -> Result<()> {
+        self.instrument.warm_up_indicators()?;
+        if let HTFInstrument::HTFInstrument(htf_instrument) = &mut self.htf_instrument {
+            htf_instrument.warm_up_indicators()?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CommissionModel {
+    None,
+    /// Account currency per fill
+    PerTrade(f64),
+    /// Of the fill notional
+    Percentage(f64),
+    /// Account currency per unit
+    PerUnit(f64),
+}
+
+impl CommissionModel {
+    pub fn from_env() -> Self {
+        let model = env::var("COMMISSION_MODEL").unwrap_or("none".to_string());
+        let value = env::var("COMMISSION_VALUE")
+            .ok()
+            .and_then(|val| val.parse::<f64>().ok())
+            .unwrap_or(0.);
+
+        match model.as_ref() {
+            "trade" => CommissionModel::PerTrade(value),
+            "percentage" => CommissionModel::Percentage(value),
+            "unit" => CommissionModel::PerUnit(value),
+            _ => CommissionModel::None,
+        }
+    }
+
+    /// Commission of a single fill, entries and exits are charged apart.
+    pub fn commission(&self, quantity: f64, price: f64) -> f64 {
+        match self {
+            CommissionModel::None => 0.,
+            CommissionModel::PerTrade(amount) => *amount,
+            CommissionModel::Percentage(percentage) => quantity * price * percentage / 100.,
+            CommissionModel::PerUnit(amount) => quantity * amount,
+        }
+        .abs()
+    }
+}
+
+impl Default for CommissionModel {
+    fn default() -> Self {
+        CommissionModel::None
+    }
+}
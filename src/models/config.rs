@@ -0,0 +1,120 @@
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::helpers::date::*;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::{OnceLock, RwLock};
+
+/// Settings that can be tuned on a running bot: the risk percentage of new
+/// entries, the pending order limits, the max spread in pips of new entries
+/// and the dry run switch. Unset values keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ConfigUpdate {
+    pub risk_per_trade: Option<f64>,
+    pub max_buy_orders: Option<usize>,
+    pub max_sell_orders: Option<usize>,
+    pub max_stop_losses: Option<usize>,
+    pub max_pending_orders: Option<usize>,
+    pub max_spread: Option<f64>,
+    #[serde(default)]
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigAudit {
+    pub previous: ConfigUpdate,
+    pub current: ConfigUpdate,
+    pub date: DbDateTime,
+}
+
+/// Runtime config of the process. The env is read once, on the first use, and
+/// later env changes are ignored: the config only changes through `apply`,
+/// which swaps it as a whole.
+fn runtime_config() -> &'static RwLock<ConfigUpdate> {
+    static CONFIG: OnceLock<RwLock<ConfigUpdate>> = OnceLock::new();
+    CONFIG.get_or_init(|| RwLock::new(ConfigUpdate::from_env()))
+}
+
+impl ConfigUpdate {
+    /// Reads RISK_PER_TRADE, MAX_BUY_ORDERS, MAX_SELL_ORDERS,
+    /// MAX_STOP_LOSSES, MAX_PENDING_ORDERS, MAX_SPREAD and DRY_RUN
+    pub fn from_env() -> Self {
+        Self {
+            risk_per_trade: read_var("RISK_PER_TRADE"),
+            max_buy_orders: read_var("MAX_BUY_ORDERS"),
+            max_sell_orders: read_var("MAX_SELL_ORDERS"),
+            max_stop_losses: read_var("MAX_STOP_LOSSES"),
+            max_pending_orders: read_var("MAX_PENDING_ORDERS"),
+            max_spread: read_var("MAX_SPREAD"),
+            dry_run: read_var("DRY_RUN"),
+        }
+    }
+
+    /// The runtime config in use, a snapshot of the env taken on the first
+    /// call plus the updates applied since
+    pub fn current() -> Self {
+        runtime_config().read().unwrap().clone()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let valid_risk = match self.risk_per_trade {
+            Some(risk) => risk > 0. && risk <= 100.,
+            None => true,
+        };
+
+        let valid_spread = match self.max_spread {
+            Some(spread) => spread >= 0.,
+            None => true,
+        };
+
+        let valid_pending = match self.max_pending_orders {
+            Some(max_pending) => max_pending > 0,
+            None => true,
+        };
+
+        match valid_risk && valid_spread && valid_pending {
+            true => Ok(()),
+            false => Err(RsAlgoError {
+                err: RsAlgoErrorKind::InvalidConfig,
+            }),
+        }
+    }
+
+    /// The values set in the update replace the current ones
+    pub fn merge(&self, update: &ConfigUpdate) -> Self {
+        Self {
+            risk_per_trade: update.risk_per_trade.or(self.risk_per_trade),
+            max_buy_orders: update.max_buy_orders.or(self.max_buy_orders),
+            max_sell_orders: update.max_sell_orders.or(self.max_sell_orders),
+            max_stop_losses: update.max_stop_losses.or(self.max_stop_losses),
+            max_pending_orders: update.max_pending_orders.or(self.max_pending_orders),
+            max_spread: update.max_spread.or(self.max_spread),
+            dry_run: update.dry_run.or(self.dry_run),
+        }
+    }
+
+    /// Validates the update and merges it into the runtime config in one
+    /// step, readers see either the previous or the new config
+    pub fn apply(&self) -> Result<ConfigAudit> {
+        self.validate()?;
+
+        let mut config = runtime_config().write().unwrap();
+        let previous = config.clone();
+        *config = previous.merge(self);
+
+        let audit = ConfigAudit {
+            previous,
+            current: config.clone(),
+            date: now_dbtime(),
+        };
+        drop(config);
+
+        log::info!("Config updated {:?}", audit);
+
+        Ok(audit)
+    }
+}
+
+fn read_var<T: std::str::FromStr>(key: &str) -> Option<T> {
+    env::var(key).ok().and_then(|val| val.parse::<T>().ok())
+}
@@ -0,0 +1,152 @@
+use super::market::MarketSymbol;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+
+#[cfg(feature = "broker")]
+use crate::broker::BrokerStream;
+#[cfg(feature = "broker")]
+use crate::error::Result;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CurrencyConverter {
+    account_currency: String,
+    symbol_currencies: HashMap<String, String>,
+    rates: HashMap<String, f64>,
+}
+
+impl CurrencyConverter {
+    pub fn new(account_currency: &str) -> Self {
+        Self {
+            account_currency: account_currency.to_uppercase(),
+            symbol_currencies: HashMap::new(),
+            rates: HashMap::new(),
+        }
+    }
+
+    /// ACCOUNT_CURRENCY, SYMBOL_CURRENCIES ("US500:USD,DE30:EUR") and
+    /// CONVERSION_RATES ("EURUSD:1.08,USDJPY:150.2")
+    pub fn from_env() -> Self {
+        let account_currency = env::var("ACCOUNT_CURRENCY").unwrap_or("USD".to_string());
+        let mut converter = Self::new(&account_currency);
+
+        for (symbol, currency) in parse_pairs(&env::var("SYMBOL_CURRENCIES").unwrap_or_default()) {
+            converter.set_symbol_currency(&symbol, &currency);
+        }
+
+        for (pair, rate) in parse_pairs(&env::var("CONVERSION_RATES").unwrap_or_default()) {
+            match rate.parse::<f64>() {
+                Ok(rate) => converter.set_rate(&pair, rate),
+                Err(_) => log::error!("Invalid conversion rate {} for {}", rate, pair),
+            }
+        }
+
+        converter
+    }
+
+    pub fn account_currency(&self) -> &str {
+        &self.account_currency
+    }
+
+    pub fn set_symbol_currency(&mut self, symbol: &str, currency: &str) {
+        self.symbol_currencies
+            .insert(symbol.to_owned(), currency.to_uppercase());
+    }
+
+    pub fn set_rate(&mut self, pair: &str, rate: f64) {
+        self.rates.insert(pair.to_uppercase(), rate);
+    }
+
+    pub fn quote_currency(&self, symbol: &str) -> String {
+        if let Some(currency) = self.symbol_currencies.get(symbol) {
+            return currency.clone();
+        }
+
+        match MarketSymbol::from_symbol(symbol).currencies().last() {
+            Some(currency) => currency.clone(),
+            None => self.account_currency.clone(),
+        }
+    }
+
+    /// Pair quoting the currency against the account currency and whether
+    /// its price has to be inverted
+    pub fn conversion_pair(&self, currency: &str) -> (String, bool) {
+        let direct = [currency, &self.account_currency].concat();
+        let inverse = [self.account_currency.as_str(), currency].concat();
+
+        match self.rates.contains_key(&inverse) && !self.rates.contains_key(&direct) {
+            true => (inverse, true),
+            false => (direct, false),
+        }
+    }
+
+    /// Rate converting an amount in the symbol quote currency into the
+    /// account currency. Falls back to 1 when no rate is known.
+    pub fn rate(&self, symbol: &str) -> f64 {
+        let currency = self.quote_currency(symbol);
+        if currency == self.account_currency {
+            return 1.;
+        }
+
+        let (pair, inverted) = self.conversion_pair(&currency);
+        match self.rates.get(&pair) {
+            Some(rate) if *rate > 0. => match inverted {
+                true => 1. / rate,
+                false => *rate,
+            },
+            _ => {
+                log::warn!("No conversion rate for {}, using 1", pair);
+                1.
+            }
+        }
+    }
+
+    pub fn convert(&self, symbol: &str, amount: f64) -> f64 {
+        amount * self.rate(symbol)
+    }
+
+    #[cfg(feature = "broker")]
+    pub async fn load_rates<B: BrokerStream + Send>(
+        &mut self,
+        broker: &mut B,
+        symbols: &Vec<String>,
+    ) -> Result<()> {
+        for symbol in symbols {
+            let currency = self.quote_currency(symbol);
+            if currency == self.account_currency {
+                continue;
+            }
+
+            let direct = [currency.as_str(), &self.account_currency].concat();
+            let inverse = [self.account_currency.as_str(), &currency].concat();
+
+            for pair in [direct, inverse] {
+                let pricing = match broker.get_instrument_pricing(&pair).await {
+                    Ok(pricing) => pricing.payload,
+                    Err(_) => None,
+                };
+
+                if let Some(pricing) = pricing.filter(|x| x.ask() > 0.) {
+                    self.set_rate(&pair, (pricing.ask() + pricing.bid()) / 2.);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for CurrencyConverter {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+fn parse_pairs(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .filter_map(|item| item.split_once(':'))
+        .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+        .collect()
+}
@@ -0,0 +1,61 @@
+use crate::helpers::date::*;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FundingRate {
+    pub symbol: String,
+    pub date: DbDateTime,
+    pub rate: f64,
+    pub mark_price: f64,
+    pub next_funding: Option<DbDateTime>,
+}
+
+impl FundingRate {
+    pub fn is_positive(&self) -> bool {
+        self.rate > 0.
+    }
+
+    pub fn annualized(&self, fundings_per_day: f64) -> f64 {
+        self.rate * fundings_per_day * 365. * 100.
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenInterest {
+    pub symbol: String,
+    pub date: DbDateTime,
+    pub value: f64,
+    pub notional: f64,
+}
+
+pub trait DerivativeData {
+    fn get_date(&self) -> &DbDateTime;
+}
+
+impl DerivativeData for FundingRate {
+    fn get_date(&self) -> &DbDateTime {
+        &self.date
+    }
+}
+
+impl DerivativeData for OpenInterest {
+    fn get_date(&self) -> &DbDateTime {
+        &self.date
+    }
+}
+
+pub fn value_at<T: DerivativeData>(series: &Vec<T>, date: DateTime<Local>) -> Option<&T> {
+    let date = to_dbtime(date);
+    series.iter().rev().find(|x| x.get_date() <= &date)
+}
+
+pub fn align_to_candles<T: DerivativeData + Clone>(
+    series: &Vec<T>,
+    dates: &Vec<DateTime<Local>>,
+) -> Vec<Option<T>> {
+    dates
+        .iter()
+        .map(|date| value_at(series, *date).cloned())
+        .collect()
+}
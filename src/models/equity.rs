@@ -0,0 +1,147 @@
+use super::pricing::Pricing;
+use super::risk::account_equity;
+use super::trade::TradeIn;
+use crate::helpers::calc;
+
+use serde::{Deserialize, Serialize};
+
+/// Account equity with the open trades marked to market. Updated by the
+/// engines every bar and handed to the strategies.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Equity {
+    initial: f64,
+    realized: f64,
+    unrealized: f64,
+    commissions: f64,
+    peak: f64,
+    open_trades: usize,
+}
+
+impl Equity {
+    pub fn new(initial: f64) -> Self {
+        Self {
+            initial,
+            peak: initial,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(account_equity())
+    }
+
+    /// Marks the open trades at the price. Longs close at the price and
+    /// shorts at the price plus the spread.
+    pub fn mark<'a, I>(
+        &mut self,
+        open_trades: I,
+        price: f64,
+        pricing: &Pricing,
+        conversion_rate: f64,
+    ) where
+        I: IntoIterator<Item = &'a TradeIn>,
+    {
+        let mut unrealized = 0.;
+        let mut num_trades = 0;
+        for trade_in in open_trades {
+            let price_out = match trade_in.trade_type.is_long() {
+                true => price,
+                false => price + pricing.spread(),
+            };
+            unrealized += calc::calculate_profit_in_account(
+                trade_in.quantity,
+                trade_in.price_in,
+                price_out,
+                &trade_in.trade_type,
+                conversion_rate,
+            );
+            num_trades += 1;
+        }
+
+        self.unrealized = unrealized;
+        self.open_trades = num_trades;
+        self.update_peak();
+    }
+
+    /// The peak only moves on marks, the unrealized profit of a closed
+    /// trade is stale until then.
+    pub fn realize(&mut self, profit: f64) {
+        self.realized += profit;
+    }
+
+    pub fn charge(&mut self, commission: f64) {
+        self.commissions += commission;
+    }
+
+    /// Sums the profits of several accounts sharing this initial equity
+    pub fn aggregate<'a, I>(&mut self, equities: I)
+    where
+        I: IntoIterator<Item = &'a Equity>,
+    {
+        let (mut realized, mut unrealized, mut commissions, mut open_trades) = (0., 0., 0., 0);
+        for equity in equities {
+            realized += equity.realized;
+            unrealized += equity.unrealized;
+            commissions += equity.commissions;
+            open_trades += equity.open_trades;
+        }
+
+        self.realized = realized;
+        self.unrealized = unrealized;
+        self.commissions = commissions;
+        self.open_trades = open_trades;
+        self.update_peak();
+    }
+
+    pub fn initial(&self) -> f64 {
+        self.initial
+    }
+
+    pub fn realized(&self) -> f64 {
+        self.realized
+    }
+
+    pub fn unrealized(&self) -> f64 {
+        self.unrealized
+    }
+
+    pub fn commissions(&self) -> f64 {
+        self.commissions
+    }
+
+    pub fn open_trades(&self) -> usize {
+        self.open_trades
+    }
+
+    /// Equity without the open trades
+    pub fn balance(&self) -> f64 {
+        self.initial + self.realized - self.commissions
+    }
+
+    pub fn value(&self) -> f64 {
+        self.balance() + self.unrealized
+    }
+
+    pub fn profit(&self) -> f64 {
+        self.value() - self.initial
+    }
+
+    pub fn peak(&self) -> f64 {
+        self.peak
+    }
+
+    pub fn drawdown(&self) -> f64 {
+        (self.peak - self.value()).max(0.)
+    }
+
+    pub fn drawdown_per(&self) -> f64 {
+        match self.peak > 0. {
+            true => self.drawdown() / self.peak * 100.,
+            false => 0.,
+        }
+    }
+
+    fn update_peak(&mut self) {
+        self.peak = self.peak.max(self.value());
+    }
+}
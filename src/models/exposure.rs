@@ -0,0 +1,121 @@
+use super::order::Order;
+use super::trade::{TradeIn, TradeOut};
+use crate::helpers::calc;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Exposure {
+    pub symbol: String,
+    pub open_trades: usize,
+    pub long_quantity: f64,
+    pub short_quantity: f64,
+    pub net_quantity: f64,
+    pub average_price: f64,
+    pub notional: f64,
+    pub margin: f64,
+    pub pending_orders: usize,
+    pub pending_quantity: f64,
+}
+
+impl Exposure {
+    /// Aggregates the open trades (entries without exit) and the pending
+    /// entry orders of a symbol. Short quantities count negative.
+    pub fn new(
+        symbol: &str,
+        trades_in: &Vec<TradeIn>,
+        trades_out: &Vec<TradeOut>,
+        orders: &Vec<Order>,
+        margin_rate: f64,
+    ) -> Self {
+        let open_trades: Vec<&TradeIn> = open_trades(trades_in, trades_out);
+
+        let long_quantity: f64 = open_trades
+            .iter()
+            .filter(|x| x.trade_type.is_long())
+            .map(|x| x.quantity)
+            .sum();
+
+        let short_quantity: f64 = open_trades
+            .iter()
+            .filter(|x| !x.trade_type.is_long())
+            .map(|x| x.quantity)
+            .sum();
+
+        let notional: f64 = open_trades.iter().map(|x| x.quantity * x.price_in).sum();
+        let total_quantity = long_quantity + short_quantity;
+
+        let average_price = match total_quantity > 0. {
+            true => notional / total_quantity,
+            false => 0.,
+        };
+
+        let pending_entries: Vec<&Order> = orders
+            .iter()
+            .filter(|x| x.is_pending() && x.order_type.is_entry())
+            .collect();
+
+        let pending_quantity = pending_entries
+            .iter()
+            .map(|x| {
+                let quantity = calc::calculate_quantity(x.remaining_size(), x.target_price);
+                match x.order_type.is_long() {
+                    true => quantity,
+                    false => -quantity,
+                }
+            })
+            .sum();
+
+        Self {
+            symbol: symbol.to_owned(),
+            open_trades: open_trades.len(),
+            long_quantity,
+            short_quantity,
+            net_quantity: long_quantity - short_quantity,
+            average_price,
+            notional,
+            margin: notional * margin_rate,
+            pending_orders: pending_entries.len(),
+            pending_quantity,
+        }
+    }
+
+    pub fn from_env(
+        symbol: &str,
+        trades_in: &Vec<TradeIn>,
+        trades_out: &Vec<TradeOut>,
+        orders: &Vec<Order>,
+    ) -> Self {
+        Self::new(symbol, trades_in, trades_out, orders, margin_rate())
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.open_trades == 0
+    }
+}
+
+pub fn margin_rate() -> f64 {
+    env::var("MARGIN_RATE")
+        .unwrap_or("1".to_string())
+        .parse::<f64>()
+        .unwrap()
+}
+
+pub fn open_trades<'a>(
+    trades_in: &'a Vec<TradeIn>,
+    trades_out: &Vec<TradeOut>,
+) -> Vec<&'a TradeIn> {
+    trades_in
+        .iter()
+        .filter(|trade_in| {
+            !trades_out
+                .iter()
+                .any(|trade_out| trade_out.index_in == trade_in.index_in)
+        })
+        .collect()
+}
+
+pub fn total_margin(exposures: &Vec<Exposure>) -> f64 {
+    exposures.iter().map(|x| x.margin).sum()
+}
@@ -2,6 +2,7 @@ use chrono::{DateTime, Local, Timelike};
 use serde::{Deserialize, Serialize};
 
 use crate::helpers::date;
+use crate::helpers::symbols::{crypto, forex};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Market {
@@ -11,6 +12,104 @@ pub enum Market {
     Default,
 }
 
+impl Default for Market {
+    fn default() -> Self {
+        Market::Default
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MarketSymbol {
+    pub name: String,
+    pub market: Market,
+    pub venue: String,
+}
+
+impl MarketSymbol {
+    pub fn new(name: &str, market: Market, venue: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            market,
+            venue: venue.to_owned(),
+        }
+    }
+
+    pub fn from_symbol(name: &str) -> Self {
+        let symbol = name.to_owned();
+        let market = match symbol {
+            _ if crypto::get_symbols().contains(&symbol) => Market::Crypto,
+            _ if forex::get_symbols().contains(&symbol) => Market::Forex,
+            _ => Market::Stock,
+        };
+
+        Self::new(name, market, "")
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn market(&self) -> &Market {
+        &self.market
+    }
+
+    pub fn venue(&self) -> &str {
+        &self.venue
+    }
+
+    pub fn is_crypto(&self) -> bool {
+        self.market == Market::Crypto
+    }
+
+    pub fn has_sessions(&self) -> bool {
+        !self.is_crypto()
+    }
+
+    pub fn currencies(&self) -> Vec<String> {
+        match self.market {
+            Market::Forex if self.name.len() >= 6 => {
+                vec![self.name[0..3].to_owned(), self.name[3..6].to_owned()]
+            }
+            Market::Stock => vec!["USD".to_owned()],
+            _ => vec![],
+        }
+    }
+
+    pub fn countries(&self) -> Vec<&'static str> {
+        self.currencies()
+            .iter()
+            .flat_map(|currency| currency_countries(currency))
+            .collect()
+    }
+}
+
+impl From<&str> for MarketSymbol {
+    fn from(name: &str) -> Self {
+        MarketSymbol::from_symbol(name)
+    }
+}
+
+impl std::fmt::Display for MarketSymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub fn currency_countries(currency: &str) -> Vec<&'static str> {
+    match currency {
+        "USD" => vec!["US"],
+        "EUR" => vec!["EMU", "EU", "DE", "FR", "IT", "ES"],
+        "GBP" => vec!["GB", "UK"],
+        "JPY" => vec!["JP"],
+        "CHF" => vec!["CH"],
+        "CAD" => vec!["CA"],
+        "AUD" => vec!["AU"],
+        "NZD" => vec!["NZ"],
+        "CNH" | "CNY" => vec!["CN"],
+        _ => vec![],
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketHour {
     pub day: u32,
@@ -21,6 +120,8 @@ pub struct MarketHour {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketHours {
     open: bool,
+    #[serde(default)]
+    market: Market,
     retry_after: isize,
     symbol: String,
     data: Vec<MarketHour>,
@@ -32,10 +133,20 @@ impl MarketHours {
             open,
             symbol,
             data,
+            market: Market::Default,
             retry_after: 0,
         }
     }
 
+    pub fn set_market(mut self, market: Market) -> Self {
+        self.market = market;
+        self
+    }
+
+    pub fn market(&self) -> &Market {
+        &self.market
+    }
+
     pub fn open(&self) -> bool {
         self.open
     }
@@ -46,6 +157,10 @@ impl MarketHours {
         &self.data
     }
     pub fn is_open(&self) -> bool {
+        if self.market == Market::Crypto {
+            return true;
+        }
+
         let current_date = Local::now();
         let current_hours = current_date.hour();
         let week_day = date::get_week_day(current_date) as u32;
@@ -62,4 +177,35 @@ impl MarketHours {
         }
         open
     }
+
+    /// Whether the market trades at the date, 24h markets are always open.
+    pub fn is_open_at(&self, date: DateTime<Local>) -> bool {
+        if self.market == Market::Crypto {
+            return true;
+        }
+
+        let week_day = date::get_week_day(date);
+        let hour = date.hour();
+        self.data
+            .iter()
+            .any(|key| key.day == week_day && hour >= key.from && hour <= key.to)
+    }
+
+    /// Close of the session the date belongs to, None for 24h markets or
+    /// days without trading hours.
+    pub fn session_close(&self, date: DateTime<Local>) -> Option<DateTime<Local>> {
+        if self.market == Market::Crypto {
+            return None;
+        }
+
+        let week_day = date::get_week_day(date);
+        let close_hour = self
+            .data
+            .iter()
+            .filter(|key| key.day == week_day)
+            .map(|key| key.to)
+            .max()?;
+
+        Some(date.date().and_hms(0, 0, 0) + date::Duration::hours(close_hour as i64 + 1))
+    }
 }
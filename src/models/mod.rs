@@ -2,11 +2,24 @@ pub mod api;
 pub mod backtest_instrument;
 pub mod backtest_strategy;
 pub mod bot;
+pub mod commission;
+pub mod config;
+pub mod currency;
+pub mod derivatives;
+pub mod equity;
+pub mod exposure;
 pub mod indicator;
 pub mod market;
 pub mod mode;
 pub mod order;
+pub mod order_book;
+pub mod order_journal;
+pub mod position_sizer;
 pub mod pricing;
+pub mod risk;
+pub mod session;
+pub mod slippage;
+pub mod spread;
 pub mod status;
 pub mod stop_loss;
 pub mod strategy;
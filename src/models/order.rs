@@ -1,11 +1,18 @@
 use std::env;
 
+use super::market::MarketHours;
 use super::mode;
+use super::order_book::{self, OrderBook, OrderLimits};
+use super::position_sizer::Sizing;
 use super::pricing::Pricing;
-use super::trade::{Trade, TradeType};
+use super::risk::Risk;
+use super::session::{self, SessionGap};
+use super::time_frame::TimeFrameType;
+use super::trade::{tags_from_env, Trade, TradeType};
 
+use crate::error::OrderError;
 use crate::helpers::calc::*;
-use crate::helpers::uuid;
+use crate::helpers::uuid::{self, IdGenerator};
 use crate::helpers::{date, date::*};
 use crate::models::stop_loss::*;
 use crate::models::trade::Position;
@@ -42,10 +49,140 @@ pub enum OrderCondition {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum OrderStatus {
     Pending,
+    PartiallyFilled,
     Fulfilled,
     Canceled,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct OrderAmendment {
+    pub target_price: Option<f64>,
+    pub size: Option<f64>,
+    pub expiry: Option<ExpiryPolicy>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum OrderEvent {
+    Created(Order),
+    Amended {
+        id: usize,
+        trade_id: usize,
+        order_type: OrderType,
+        date: DbDateTime,
+        target_price: (f64, f64),
+        size: (f64, f64),
+        valid_until: (Option<DbDateTime>, Option<DbDateTime>),
+    },
+    Activated(Order),
+    Fulfilled(Order),
+    Cancelled(Order),
+    Expired(Order),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ExpiryPolicy {
+    GTC,
+    GTD(DbDateTime),
+    Bars(usize),
+    Session,
+}
+
+impl ExpiryPolicy {
+    pub fn from_env() -> Self {
+        let policy = env::var("ORDER_EXPIRY").unwrap_or_else(|_| "bars".to_owned());
+        match policy.as_ref() {
+            "gtc" => ExpiryPolicy::GTC,
+            "session" => ExpiryPolicy::Session,
+            _ => {
+                let valid_until_bars = env::var("VALID_UNTIL_BARS")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap();
+                ExpiryPolicy::Bars(valid_until_bars)
+            }
+        }
+    }
+
+    pub fn valid_until(
+        &self,
+        current_date: DateTime<Local>,
+        time_frame: &TimeFrameType,
+    ) -> Option<DateTime<Local>> {
+        match self {
+            ExpiryPolicy::GTC => None,
+            ExpiryPolicy::GTD(date) => Some(from_dbtime(date)),
+            ExpiryPolicy::Bars(bars) => {
+                let bars = *bars as i64;
+                match time_frame.is_minutely_time_frame() {
+                    true => {
+                        Some(current_date + date::Duration::minutes(bars * time_frame.to_minutes()))
+                    }
+                    false => {
+                        Some(current_date + date::Duration::hours(bars * time_frame.to_hours()))
+                    }
+                }
+            }
+            ExpiryPolicy::Session => {
+                let next_day = current_date + date::Duration::days(1);
+                Some(next_day.date().and_hms(0, 0, 0))
+            }
+        }
+    }
+}
+
+impl Default for ExpiryPolicy {
+    fn default() -> Self {
+        ExpiryPolicy::Bars(0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ActivationRule {
+    Wick,
+    Body,
+    Ticks(usize),
+}
+
+impl ActivationRule {
+    pub fn from_env() -> Self {
+        let rule = env::var("ENTRY_ACTIVATION").unwrap_or_else(|_| "wick".to_owned());
+        match rule.as_ref() {
+            "body" => ActivationRule::Body,
+            "ticks" => {
+                let ticks = env::var("ENTRY_ACTIVATION_TICKS")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap();
+                ActivationRule::Ticks(ticks)
+            }
+            _ => ActivationRule::Wick,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum OrderFilter {
+    All,
+    Symbol(String),
+    Strategy(String),
+    OrderType(OrderType),
+    OlderThan(DbDateTime),
+}
+
+impl OrderFilter {
+    pub fn matches(&self, order: &Order) -> bool {
+        match self {
+            OrderFilter::All => true,
+            OrderFilter::Symbol(val) => val == &order.symbol,
+            OrderFilter::Strategy(val) => val == &order.strategy,
+            OrderFilter::OrderType(order_type) => {
+                std::mem::discriminant(order_type) == std::mem::discriminant(&order.order_type)
+            }
+            OrderFilter::OlderThan(date) => from_dbtime(&order.created_at) < from_dbtime(date),
+        }
+    }
+}
+
 impl OrderType {
     pub fn is_long(&self) -> bool {
         match self {
@@ -79,6 +216,19 @@ impl OrderType {
             _ => false,
         }
     }
+
+    pub fn direction(&self) -> &OrderDirection {
+        match self {
+            OrderType::BuyOrderLong(direction, _, _)
+            | OrderType::BuyOrderShort(direction, _, _)
+            | OrderType::SellOrderLong(direction, _, _)
+            | OrderType::SellOrderShort(direction, _, _)
+            | OrderType::TakeProfitLong(direction, _, _)
+            | OrderType::TakeProfitShort(direction, _, _)
+            | OrderType::StopLossLong(direction, _)
+            | OrderType::StopLossShort(direction, _) => direction,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -96,6 +246,30 @@ pub struct Order {
     pub updated_at: Option<DbDateTime>,
     pub full_filled_at: Option<DbDateTime>,
     pub valid_until: Option<DbDateTime>,
+    #[serde(default)]
+    pub ticks_beyond: usize,
+    #[serde(default)]
+    pub breakeven: f64,
+    #[serde(default)]
+    pub oco_group: Option<usize>,
+    #[serde(default)]
+    pub expiry: ExpiryPolicy,
+    #[serde(default)]
+    pub filled_size: f64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub risk: Risk,
+    #[serde(default)]
+    pub stop_price: f64,
+    /// Number the broker gave to the order, or to the parent trade for exit
+    /// legs
+    #[serde(default)]
+    pub broker_order: Option<usize>,
+    #[serde(default)]
+    pub symbol: String,
+    #[serde(default)]
+    pub strategy: String,
 }
 
 impl Order {
@@ -103,6 +277,10 @@ impl Order {
         self.status = val
     }
 
+    pub fn set_broker_order(&mut self, val: usize) {
+        self.broker_order = Some(val)
+    }
+
     pub fn set_updated_at(&mut self, val: DbDateTime) {
         self.updated_at = Some(val)
     }
@@ -123,10 +301,83 @@ impl Order {
         self.valid_until = Some(val)
     }
 
+    pub fn set_expiry(&mut self, expiry: ExpiryPolicy, time_frame: &TimeFrameType) {
+        let created_at = from_dbtime(&self.created_at);
+        self.valid_until = expiry.valid_until(created_at, time_frame).map(to_dbtime);
+        self.expiry = expiry;
+    }
+
+    pub fn expiry(&self) -> &ExpiryPolicy {
+        &self.expiry
+    }
+
+    pub fn expiration_ts(&self) -> i64 {
+        match self.valid_until {
+            Some(valid_until) => valid_until.timestamp_millis(),
+            None => 0,
+        }
+    }
+
     pub fn size(&self) -> f64 {
         self.size
     }
 
+    pub fn set_breakeven(&mut self, val: f64) {
+        self.breakeven = val
+    }
+
+    pub fn breakeven(&self) -> f64 {
+        self.breakeven
+    }
+
+    pub fn set_oco_group(&mut self, val: usize) {
+        self.oco_group = Some(val)
+    }
+
+    pub fn oco_group(&self) -> Option<usize> {
+        self.oco_group
+    }
+
+    pub fn is_oco_sibling(&self, order: &Order) -> bool {
+        match (self.oco_group, order.oco_group) {
+            (Some(group), Some(other_group)) => {
+                group == other_group && self.order_type != order.order_type
+            }
+            _ => false,
+        }
+    }
+
+    pub fn created_ts(&self) -> usize {
+        uuid::generate_ts_id(from_dbtime(&self.created_at))
+    }
+
+    pub fn set_tags(&mut self, val: Vec<String>) {
+        self.tags = val
+    }
+
+    pub fn add_tag(&mut self, val: &str) {
+        if !self.has_tag(val) {
+            self.tags.push(val.to_owned())
+        }
+    }
+
+    pub fn tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, val: &str) -> bool {
+        self.tags.iter().any(|tag| tag == val)
+    }
+
+    pub fn set_risk(&mut self, risk: Risk, stop_price: f64) {
+        self.risk = risk;
+        self.stop_price = stop_price;
+    }
+
+    pub fn risk(&self) -> &Risk {
+        &self.risk
+    }
+
     pub fn update_pricing(&mut self, origin_price: f64, target_price: f64) {
         self.origin_price = origin_price;
         self.target_price = target_price;
@@ -153,11 +404,35 @@ impl Order {
 
     pub fn is_pending(&self) -> bool {
         match self.status {
-            OrderStatus::Pending => true,
+            OrderStatus::Pending | OrderStatus::PartiallyFilled => true,
             _ => false,
         }
     }
 
+    pub fn is_partially_filled(&self) -> bool {
+        self.status == OrderStatus::PartiallyFilled
+    }
+
+    pub fn filled_size(&self) -> f64 {
+        self.filled_size
+    }
+
+    pub fn remaining_size(&self) -> f64 {
+        (self.size - self.filled_size).max(0.)
+    }
+
+    pub fn fill(&mut self, fill_size: f64, index: usize, date: DateTime<Local>) {
+        self.filled_size = (self.filled_size + fill_size).min(self.size);
+        match self.remaining_size() > 0. {
+            true => {
+                self.set_status(OrderStatus::PartiallyFilled);
+                self.set_full_filled_index(index);
+                self.set_updated_at(to_dbtime(date));
+            }
+            false => self.fulfill_order(index, date),
+        }
+    }
+
     pub fn to_trade_type(&self) -> TradeType {
         match self.order_type {
             OrderType::BuyOrderLong(_, _, _) => TradeType::MarketInLong,
@@ -173,42 +448,74 @@ impl Order {
         }
     }
 
+    /// Entry orders expire at the close of the session they were placed in,
+    /// exit legs stay alive.
+    pub fn is_session_expired(&self, date: DateTime<Local>, market_hours: &MarketHours) -> bool {
+        if !self.order_type.is_entry() {
+            return false;
+        }
+
+        match market_hours.session_close(from_dbtime(&self.created_at)) {
+            Some(session_close) => date >= session_close,
+            None => false,
+        }
+    }
+
     pub fn is_still_valid(&self, date_compare: DateTime<Local>) -> bool {
-        let valid_until = from_dbtime(&self.valid_until.unwrap());
-        date_compare < valid_until && self.status == OrderStatus::Pending
+        let is_valid = match self.valid_until {
+            Some(valid_until) => date_compare < from_dbtime(&valid_until),
+            None => true,
+        };
+        is_valid && self.is_pending()
     }
 }
 
+/// Orders without a size get it from the sizer, entries at their target
+/// with the stop distance and exit legs the size of the entry
 pub fn prepare_orders(
     index: usize,
+    sizing: Sizing,
     instrument: &Instrument,
     pricing: &Pricing,
     trade_type: &TradeType,
     order_types: &Vec<OrderType>,
-) -> Vec<Order> {
+    ids: &mut IdGenerator,
+) -> std::result::Result<Vec<Order>, OrderError> {
     let execution_mode = mode::from_str(&env::var("EXECUTION_MODE").unwrap());
     let mut buy_order_target = 0.;
     let mut sell_order_target = 0.;
     let mut stop_order_target = 0.;
     let mut is_stop_loss = false;
-    let mut is_valid_buy_sell_order = true;
     let mut stop_loss_direction = OrderDirection::Up;
     let mut orders: Vec<Order> = vec![];
 
+    if trade_type.is_entry() && session::entries_suppressed(index, instrument) {
+        log::warn!("Entries suppressed after session open gap");
+        return Ok(orders);
+    }
+
     let current_candle = instrument.data().get(index).unwrap();
     let close_price = current_candle.close();
+    let gap_tolerance = session::stop_gap_tolerance(index, instrument);
 
     let next_candle = match execution_mode.is_back_test() {
         true => instrument.data().get(index).unwrap(),
         false => instrument.data.last().unwrap(),
     };
 
-    let trade_id = uuid::generate_ts_id(next_candle.date());
+    let trade_id = ids.next(next_candle.date());
     let order_with_spread = env::var("ORDER_WITH_SPREAD")
         .unwrap()
         .parse::<bool>()
         .unwrap();
 
+    let commission_pips = env::var("COMMISSION_PIPS")
+        .unwrap_or("0".to_string())
+        .parse::<f64>()
+        .unwrap();
+
+    let breakeven = calculate_breakeven_pips(pricing, commission_pips);
+
     for order_type in order_types {
         match order_type {
             OrderType::BuyOrderLong(direction, order_size, target_price)
@@ -217,43 +524,40 @@ pub fn prepare_orders(
             | OrderType::SellOrderShort(direction, order_size, target_price)
             | OrderType::TakeProfitLong(direction, order_size, target_price)
             | OrderType::TakeProfitShort(direction, order_size, target_price) => {
-                if validate_target_price(order_type, direction, &close_price, target_price) {
-                    //log::info!("{:?} validated", &order_type,);
-                    let order = create_order(
-                        index,
-                        trade_id,
-                        instrument,
-                        order_type,
-                        target_price,
-                        order_size,
-                    );
+                validate_target_price(order_type, direction, &close_price, target_price)?;
+                let mut order = create_order(
+                    index,
+                    trade_id,
+                    instrument,
+                    order_type,
+                    target_price,
+                    order_size,
+                    ids,
+                );
+                order.set_breakeven(breakeven);
 
-                    match order_type.is_entry() {
-                        true => {
-                            buy_order_target = match order_type.is_long() {
-                                true => match order_with_spread {
-                                    true => order.target_price,
-                                    false => order.target_price + pricing.spread(),
-                                },
-                                false => order.target_price,
-                            }
+                match order_type.is_entry() {
+                    true => {
+                        buy_order_target = match order_type.is_long() {
+                            true => match order_with_spread {
+                                true => order.target_price,
+                                false => order.target_price + pricing.spread(),
+                            },
+                            false => order.target_price,
                         }
-                        false => {
-                            sell_order_target = match order_type.is_long() {
+                    }
+                    false => {
+                        sell_order_target = match order_type.is_long() {
+                            true => order.target_price,
+                            false => match order_with_spread {
                                 true => order.target_price,
-                                false => match order_with_spread {
-                                    true => order.target_price,
-                                    false => order.target_price + pricing.spread(),
-                                },
-                            }
+                                false => order.target_price + pricing.spread(),
+                            },
                         }
-                    };
+                    }
+                };
 
-                    orders.push(order);
-                } else {
-                    is_valid_buy_sell_order = false;
-                    log::error!("{:?} not valid", &order_type,);
-                }
+                orders.push(order);
             }
             OrderType::StopLossLong(direction, stop_loss_type)
             | OrderType::StopLossShort(direction, stop_loss_type) => {
@@ -266,47 +570,104 @@ pub fn prepare_orders(
 
                 let order_size = match orders.first() {
                     Some(order) => order.size,
-                    None => std::env::var("ORDER_SIZE").unwrap().parse::<f64>().unwrap(),
+                    None => 0.,
                 };
 
-                if is_valid_buy_sell_order {
-                    let stop_loss = create_stop_loss_order(
-                        index,
-                        trade_id,
-                        instrument,
-                        pricing,
-                        direction,
-                        stop_loss_type,
-                        target_price,
-                        order_size,
-                    );
-                    stop_order_target = stop_loss.target_price;
-                    stop_loss_direction = direction.clone();
-                    orders.push(stop_loss);
-                }
+                let mut stop_loss = create_stop_loss_order(
+                    index,
+                    trade_id,
+                    instrument,
+                    pricing,
+                    direction,
+                    stop_loss_type,
+                    target_price,
+                    order_size,
+                    ids,
+                );
+                stop_loss.set_breakeven(breakeven);
+                stop_order_target = stop_loss.target_price;
+                stop_loss_direction = direction.clone();
+                orders.push(stop_loss);
             }
         }
     }
 
+    //LINK EXIT LEGS
+    for order in orders
+        .iter_mut()
+        .filter(|order| !order.order_type.is_entry())
+    {
+        order.set_oco_group(trade_id);
+    }
+
+    //LINK RISK TO STOP DISTANCE
+    if is_stop_loss {
+        let risk = Risk::from_env();
+        for order in orders
+            .iter_mut()
+            .filter(|order| order.order_type.is_entry())
+        {
+            order.set_risk(risk.clone(), stop_order_target);
+        }
+    }
+
+    //SIZE ORDERS
+    let stop_price = match is_stop_loss {
+        true => Some(stop_order_target),
+        false => None,
+    };
+
+    for order in orders
+        .iter_mut()
+        .filter(|order| order.order_type.is_entry() && order.size <= 0.)
+    {
+        match sizing.size(index, instrument, order.target_price, stop_price) {
+            Some(size) => order.size = size,
+            None => {
+                log::error!("Can't size order at {}", order.target_price);
+                return Err(OrderError::Unsized {
+                    target: order.target_price,
+                });
+            }
+        }
+    }
+
+    let entry_size = orders
+        .iter()
+        .find(|order| order.order_type.is_entry())
+        .map(|order| order.size)
+        .or_else(|| sizing.size(index, instrument, next_candle.open(), stop_price))
+        .unwrap_or(0.);
+
+    for order in orders.iter_mut().filter(|order| order.size <= 0.) {
+        order.size = entry_size;
+    }
+
     //CHECK STOP LOSS
     if is_stop_loss {
         match stop_loss_direction == OrderDirection::Down {
             true => {
-                if stop_order_target >= buy_order_target && buy_order_target > 0. {
+                if stop_order_target >= buy_order_target + gap_tolerance && buy_order_target > 0. {
                     log::error!(
                         "Stop loss can't be placed higher than buy level {:?}",
                         (buy_order_target, stop_order_target)
                     );
-                    panic!();
+                    return Err(OrderError::StopLossAboveEntry {
+                        buy: buy_order_target,
+                        stop: stop_order_target,
+                    });
                 }
             }
             false => {
-                if stop_order_target <= buy_order_target && buy_order_target > 0. {
+                if stop_order_target <= buy_order_target - gap_tolerance && buy_order_target > 0. {
                     log::error!(
                         "Stop loss can't be placed lower than buy level {:?}",
                         (buy_order_target, stop_order_target)
                     );
-                    panic!();
+                    return Err(OrderError::StopLossBelowEntry {
+                        buy: buy_order_target,
+                        stop: stop_order_target,
+                    });
                 }
             }
         }
@@ -356,7 +717,26 @@ pub fn prepare_orders(
         }
     };
 
-    orders
+    //CHECK BREAKEVEN DISTANCE
+    let breakeven_multiple = env::var("BREAKEVEN_MULTIPLE")
+        .unwrap_or("0".to_string())
+        .parse::<f64>()
+        .unwrap();
+
+    if breakeven_multiple > 0. && buy_order_target > 0. && sell_order_target > 0. {
+        let target_pips = from_pips((sell_order_target - buy_order_target).abs(), pricing);
+        if target_pips < breakeven * breakeven_multiple {
+            orders = vec![];
+            log::error!(
+                "Target distance {} pips under breakeven {} x {}",
+                target_pips,
+                breakeven,
+                breakeven_multiple
+            );
+        }
+    }
+
+    Ok(orders)
 }
 
 pub fn validate_target_price(
@@ -364,34 +744,38 @@ pub fn validate_target_price(
     direction: &OrderDirection,
     close_price: &f64,
     target_price: &f64,
-) -> bool {
+) -> std::result::Result<(), OrderError> {
     match direction {
         OrderDirection::Up => {
             if close_price >= target_price {
                 log::error!(
-                    "{:?} not valid. Target price {} is higher than {}",
+                    "{:?} not valid. Target price {} is lower than {}",
                     order_type,
                     target_price,
                     close_price,
                 );
-                panic!();
-                false
+                Err(OrderError::TargetBelowPrice {
+                    target: *target_price,
+                    price: *close_price,
+                })
             } else {
-                true
+                Ok(())
             }
         }
         OrderDirection::Down => {
             if close_price <= target_price {
                 log::error!(
-                    "{:?} not valid. Target price {} is lower than {}",
+                    "{:?} not valid. Target price {} is higher than {}",
                     order_type,
                     target_price,
                     close_price,
                 );
-                panic!();
-                false
+                Err(OrderError::TargetAbovePrice {
+                    target: *target_price,
+                    price: *close_price,
+                })
             } else {
-                true
+                Ok(())
             }
         }
     }
@@ -404,6 +788,7 @@ pub fn create_order(
     order_type: &OrderType,
     target_price: &f64,
     order_size: &f64,
+    ids: &mut IdGenerator,
 ) -> Order {
     let execution_mode = mode::from_str(&env::var("EXECUTION_MODE").unwrap());
 
@@ -416,18 +801,11 @@ pub fn create_order(
     let current_date = &current_candle.date();
     let origin_price = current_candle.close();
     let time_frame = instrument.time_frame();
-    let valid_until_bars = &env::var("VALID_UNTIL_BARS")
-        .unwrap()
-        .parse::<i64>()
-        .unwrap();
-
-    let valid_until = match instrument.time_frame().is_minutely_time_frame() {
-        true => *current_date + date::Duration::minutes(valid_until_bars * time_frame.to_minutes()),
-        false => *current_date + date::Duration::hours(valid_until_bars * time_frame.to_hours()),
-    };
+    let expiry = ExpiryPolicy::from_env();
+    let valid_until = expiry.valid_until(*current_date, time_frame);
 
     Order {
-        id: uuid::generate_ts_id(*current_date),
+        id: ids.next(*current_date),
         index_created: index,
         index_fulfilled: 0,
         trade_id,
@@ -439,7 +817,18 @@ pub fn create_order(
         created_at: to_dbtime(*current_date),
         updated_at: None,
         full_filled_at: None,
-        valid_until: Some(to_dbtime(valid_until)),
+        valid_until: valid_until.map(to_dbtime),
+        ticks_beyond: 0,
+        breakeven: 0.,
+        oco_group: None,
+        expiry,
+        filled_size: 0.,
+        tags: tags_from_env(),
+        risk: Risk::None,
+        stop_price: 0.,
+        broker_order: None,
+        symbol: instrument.symbol().to_owned(),
+        strategy: env::var("STRATEGY_NAME").unwrap_or_default(),
     }
 }
 
@@ -451,18 +840,44 @@ pub fn resolve_active_orders(
 ) -> Position {
     let mut order_position: Position = Position::None;
     let mut orders_activated = vec![];
+    let mut activated_groups: Vec<usize> = vec![];
 
     for (_id, order) in orders
         .iter()
         .enumerate()
-        .filter(|(_id, order)| order.status == OrderStatus::Pending)
+        .filter(|(_id, order)| order.is_pending())
     {
-        match order_activated(index, order, instrument) {
+        let is_sibling_activated = match order.oco_group {
+            Some(group) => activated_groups.contains(&group),
+            None => false,
+        };
+
+        match !is_sibling_activated && order_activated(index, order, instrument) {
             true => {
+                if let Some(group) = order.oco_group {
+                    activated_groups.push(group);
+                }
                 match order.order_type {
                     OrderType::BuyOrderLong(_, _, _) | OrderType::BuyOrderShort(_, _, _) => {
-                        order_position = Position::MarketInOrder(order.clone());
-                        orders_activated.push(order_position.clone());
+                        let current_candle = instrument.data().get(index).unwrap();
+                        let mut fill_order = order.clone();
+                        let fill_volume_ratio = env::var("FILL_VOLUME_RATIO")
+                            .ok()
+                            .and_then(|val| val.parse::<f64>().ok())
+                            .unwrap_or(0.);
+                        fill_order.size = get_fill_size(order, current_candle, fill_volume_ratio);
+                        if fill_order.size < order.remaining_size() {
+                            log::info!(
+                                "Partial fill {} of {} for order {}",
+                                fill_order.size,
+                                order.remaining_size(),
+                                order.id
+                            );
+                        }
+                        if fill_order.size > 0. {
+                            order_position = Position::MarketInOrder(fill_order);
+                            orders_activated.push(order_position.clone());
+                        }
                     }
                     OrderType::SellOrderLong(_, _, _)
                     | OrderType::SellOrderShort(_, _, _)
@@ -489,15 +904,17 @@ pub fn resolve_active_orders(
 }
 
 fn order_activated(index: usize, order: &Order, instrument: &Instrument) -> bool {
+    let execution_mode = mode::from_str(&env::var("EXECUTION_MODE").unwrap());
     let order_engine = &env::var("ORDER_ENGINE").unwrap();
     let activation_source = &env::var("ORDER_ACTIVATION_SOURCE").unwrap();
+    let activation_rule = ActivationRule::from_env();
 
     let data = &instrument.data;
     let prev_index = get_prev_index(index);
     let current_candle = data.get(index).unwrap();
     let candle_ts = uuid::generate_ts_id(current_candle.date());
     let prev_candle = data.get(prev_index).unwrap();
-    let is_next_bar = candle_ts > order.id;
+    let is_next_bar = candle_ts > order.created_ts();
 
     let (current_price_over, current_price_bellow, _, _) =
         get_order_activation_price(current_candle, prev_candle, activation_source);
@@ -511,12 +928,21 @@ fn order_activated(index: usize, order: &Order, instrument: &Instrument) -> bool
     let cross_bellow = current_price_bellow <= order.target_price && is_next_bar && is_closed;
     let stop_cross_over = current_candle.high() >= order.target_price && is_next_bar;
     let stop_cross_bellow = current_candle.low() <= order.target_price && is_next_bar;
+    let body_cross_over =
+        current_candle.close() >= order.target_price && is_next_bar && current_candle.is_closed();
+    let body_cross_bellow =
+        current_candle.close() <= order.target_price && is_next_bar && current_candle.is_closed();
 
     let activated = match &order.order_type {
         OrderType::BuyOrderLong(direction, _, _) | OrderType::BuyOrderShort(direction, _, _) => {
-            match direction {
-                OrderDirection::Up => cross_over,
-                OrderDirection::Down => cross_bellow,
+            match (&activation_rule, direction) {
+                (ActivationRule::Body, OrderDirection::Up) => body_cross_over,
+                (ActivationRule::Body, OrderDirection::Down) => body_cross_bellow,
+                (ActivationRule::Ticks(min_ticks), _) if !execution_mode.is_back_test() => {
+                    order.ticks_beyond >= *min_ticks && is_next_bar
+                }
+                (_, OrderDirection::Up) => cross_over,
+                (_, OrderDirection::Down) => cross_bellow,
             }
         }
         OrderType::SellOrderLong(direction, _, _)
@@ -534,86 +960,56 @@ fn order_activated(index: usize, order: &Order, instrument: &Instrument) -> bool
     activated
 }
 
-pub fn add_pending(orders: Vec<Order>, new_orders: Vec<Order>) -> Vec<Order> {
-    let max_buy_orders = env::var("MAX_BUY_ORDERS")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap();
-    let max_sell_orders = env::var("MAX_SELL_ORDERS")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap();
-    let max_stop_losses = env::var("MAX_STOP_LOSSES")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap();
+pub fn register_tick(orders: &mut Vec<Order>, pricing: &Pricing) {
+    for order in orders
+        .iter_mut()
+        .filter(|order| order.is_pending() && order.order_type.is_entry())
+    {
+        let price = match order.order_type.is_long() {
+            true => pricing.ask(),
+            false => pricing.bid(),
+        };
 
-    let max_pending_orders = env::var("MAX_PENDING_ORDERS")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap();
+        let is_beyond = match order.order_type.direction() {
+            OrderDirection::Up => price >= order.target_price,
+            OrderDirection::Down => price <= order.target_price,
+        };
+
+        if is_beyond {
+            order.ticks_beyond += 1;
+        }
+    }
+}
 
+pub fn add_pending(orders: Vec<Order>, new_orders: Vec<Order>) -> Vec<Order> {
     let _overwrite_orders = env::var("OVERWRITE_ORDERS")
         .unwrap()
         .parse::<bool>()
         .unwrap();
 
-    let (buy_orders, sell_orders, stop_losses) = get_num_pending_orders(&orders);
-    let result: Vec<Order> = new_orders
-        .iter()
-        .filter(|order| order.status == OrderStatus::Pending)
-        .filter(|order| match order.order_type {
-            OrderType::BuyOrderLong(_, _, _) | OrderType::BuyOrderShort(_, _, _) => {
-                buy_orders < max_buy_orders && stop_losses < max_stop_losses
-            }
-            OrderType::SellOrderLong(_, _, _)
-            | OrderType::SellOrderShort(_, _, _)
-            | OrderType::TakeProfitLong(_, _, _)
-            | OrderType::TakeProfitShort(_, _, _) => sell_orders < max_sell_orders,
-            OrderType::StopLossLong(_, _) | OrderType::StopLossShort(_, _) => {
-                stop_losses < max_stop_losses
-            }
-        })
-        .cloned()
-        .collect();
-
-    if result.len() <= max_pending_orders {
-        [orders, result].concat()
-    } else {
-        orders
+    let mut orders = orders;
+    let rejected = with_order_book(&mut orders, |book| book.add(new_orders));
+    for order in rejected {
+        log::warn!("Rejected {:?} order {}", order.order_type, order.id);
     }
+
+    orders
 }
 
 pub fn get_pending(orders: &Vec<Order>) -> Vec<Order> {
-    let max_pending_orders = env::var("MAX_PENDING_ORDERS")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap();
-
-    let len = orders.len();
-
-    let pending_orders = match len > 0 {
-        true => {
-            let pending_orders: Vec<Order> = orders
-                .iter()
-                .skip(len.saturating_sub(max_pending_orders))
-                .filter(|x| x.status == OrderStatus::Pending)
-                .take(max_pending_orders)
-                .cloned()
-                .collect();
-            pending_orders
-        }
-        false => vec![],
-    };
+    let max_pending_orders = OrderLimits::from_env().max_pending_orders;
 
-    pending_orders
+    orders
+        .iter()
+        .skip(orders.len().saturating_sub(max_pending_orders))
+        .filter(|order| order.is_pending())
+        .take(max_pending_orders)
+        .cloned()
+        .collect()
 }
 
 pub fn has_executed_buy_order(orders: &Vec<Order>, operation: &Position) -> bool {
-    let max_buy_orders = env::var("MAX_BUY_ORDERS")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap();
+    let max_buy_orders = OrderLimits::from_env().max_buy_orders;
 
     let (pending_buy_orders, _sell_orders, _stop_losses) = get_num_pending_orders(orders);
 
@@ -629,101 +1025,143 @@ pub fn has_executed_buy_order(orders: &Vec<Order>, operation: &Position) -> bool
 }
 
 pub fn get_num_pending_orders(orders: &Vec<Order>) -> (usize, usize, usize) {
-    let max_pending_orders = env::var("MAX_PENDING_ORDERS")
-        .unwrap()
-        .parse::<usize>()
-        .unwrap();
-    let mut buy_orders = 0;
-    let mut sell_orders = 0;
-    let mut stop_losses = 0;
+    order_book::num_pending(orders)
+}
 
-    for order in orders
-        .iter()
-        .rev()
-        .take(max_pending_orders)
-        .filter(|x| x.status == OrderStatus::Pending)
-    {
-        match order.order_type {
-            OrderType::BuyOrderLong(_, _, _) | OrderType::BuyOrderShort(_, _, _) => buy_orders += 1,
-            OrderType::SellOrderLong(_, _, _)
-            | OrderType::SellOrderShort(_, _, _)
-            | OrderType::TakeProfitLong(_, _, _)
-            | OrderType::TakeProfitShort(_, _, _) => sell_orders += 1,
-            OrderType::StopLossLong(_, _) | OrderType::StopLossShort(_, _) => stop_losses += 1,
-        };
+/// Applies `f` to the orders as an `OrderBook`, so pending limits, fills and
+/// cancellations follow the book rules. Backtests drop the cancelled orders.
+fn with_order_book<F, R>(orders: &mut Vec<Order>, f: F) -> R
+where
+    F: FnOnce(&mut OrderBook) -> R,
+{
+    let execution_mode = mode::from_str(&env::var("EXECUTION_MODE").unwrap());
+    let mut book = OrderBook::from_orders(OrderLimits::from_env(), std::mem::take(orders));
+    let result = f(&mut book);
+
+    if execution_mode.is_back_test() {
+        book.drain_cancelled();
     }
-    (buy_orders, sell_orders, stop_losses)
+
+    *orders = book.into_orders();
+    result
 }
 
 pub fn cancel_pending_expired_orders(
     index: usize,
     instrument: &Instrument,
+    market_hours: Option<&MarketHours>,
     orders: &mut Vec<Order>,
 ) -> Vec<Order> {
     let execution_mode = mode::from_str(&env::var("EXECUTION_MODE").unwrap());
-    match execution_mode.is_back_test() {
-        true => {
-            let current_date = instrument.data.get(index).unwrap().date();
-            let mut i = 0;
-            while i < orders.len() {
-                let order = &mut orders[i];
-                if order.status == OrderStatus::Pending && !order.is_still_valid(current_date) {
-                    orders.remove(i);
-                } else {
-                    i += 1;
-                }
-            }
-            orders.clone()
-        }
-        false => {
-            let current_date = Local::now();
-            orders
-                .iter_mut()
-                .map(|x| {
-                    if x.status == OrderStatus::Pending && !x.is_still_valid(current_date) {
-                        x.cancel_order(to_dbtime(Local::now()));
-                    }
-                    x.clone()
-                })
-                .collect()
-        }
+    let session_close = env::var("CANCEL_ENTRIES_AT_SESSION_CLOSE")
+        .unwrap_or("false".to_string())
+        .parse::<bool>()
+        .unwrap();
+
+    let current_date = match execution_mode.is_back_test() {
+        true => instrument.data.get(index).unwrap().date(),
+        false => Local::now(),
+    };
+
+    let is_expired = |order: &Order| {
+        let is_session_expired = match (session_close, market_hours) {
+            (true, Some(market_hours)) => order.is_session_expired(current_date, market_hours),
+            _ => false,
+        };
+        !order.is_still_valid(current_date) || is_session_expired
+    };
+
+    with_order_book(orders, |book| {
+        book.cancel_where(is_expired, to_dbtime(current_date))
+    });
+
+    orders.clone()
+}
+
+/// Cancels the pending entries jumped by the session gap. Jumped exits are
+/// moved to the gap open, so a stop fills where the market opened instead of
+/// at a price it never traded.
+pub fn cancel_gapped_orders(
+    instrument: &Instrument,
+    gap: &SessionGap,
+    orders: &mut Vec<Order>,
+) -> Vec<Order> {
+    let date = to_dbtime(instrument.data().get(gap.index).unwrap().date());
+
+    for order in orders.iter_mut().filter(|order| {
+        order.is_pending() && !order.order_type.is_entry() && gap.is_jumped(order.target_price)
+    }) {
+        log::warn!(
+            "Filling {:?} order {} at session open {}",
+            order.order_type,
+            order.id,
+            gap.open
+        );
+        order.target_price = gap.open;
+    }
+
+    let canceled_orders = with_order_book(orders, |book| {
+        book.cancel_where(
+            |order| order.order_type.is_entry() && gap.is_jumped(order.target_price),
+            date,
+        )
+    });
+
+    for order in canceled_orders.iter() {
+        log::warn!(
+            "Canceled {:?} order {} jumped by session gap {:?}",
+            order.order_type,
+            order.id,
+            (gap.prev_close, gap.open)
+        );
     }
+
+    canceled_orders
 }
 
 pub fn extend_all_pending_orders(orders: &mut Vec<Order>) {
     for order in orders {
-        if order.status == OrderStatus::Pending {
-            let current_valid = from_dbtime(&order.valid_until.unwrap());
-            let new_valid_date = current_valid + date::Duration::days(365);
-            log::info!("Extending StopLoss order to {:?}", new_valid_date);
-            order.set_valid_until(to_dbtime(new_valid_date));
+        if order.is_pending() {
+            if let Some(valid_until) = order.valid_until {
+                let new_valid_date = from_dbtime(&valid_until) + date::Duration::days(365);
+                log::info!("Extending StopLoss order to {:?}", new_valid_date);
+                order.set_valid_until(to_dbtime(new_valid_date));
+            }
         }
     }
 }
 
 pub fn cancel_trade_pending_orders<T: Trade>(trade: &T, orders: &mut Vec<Order>) {
-    let execution_mode = mode::from_str(&env::var("EXECUTION_MODE").unwrap());
-    match execution_mode.is_back_test() {
-        true => {
-            let mut i = 0;
-            while i < orders.len() {
-                let order = &mut orders[i];
-                if order.status == OrderStatus::Pending {
-                    orders.remove(i);
-                } else {
-                    i += 1;
-                }
-            }
-        }
-        false => {
-            for order in orders {
-                if order.status == OrderStatus::Pending {
-                    log::info!("Canceling Pending order to {:?}", order.id);
-                    order.cancel_order(*trade.get_date());
-                }
-            }
-        }
+    let canceled_orders = with_order_book(orders, |book| book.cancel_all(*trade.get_date()));
+
+    for order in canceled_orders.iter() {
+        log::info!("Canceling Pending order to {:?}", order.id);
+    }
+}
+
+/// Cancels the pending orders matching every filter. An empty filter list is
+/// an error, use `OrderFilter::All` to cancel everything.
+pub fn cancel_orders_where(
+    orders: &mut Vec<Order>,
+    filters: &[OrderFilter],
+    date: DbDateTime,
+) -> std::result::Result<Vec<Order>, OrderError> {
+    if filters.is_empty() {
+        return Err(OrderError::NoFilter);
     }
+
+    let canceled_orders = with_order_book(orders, |book| {
+        book.cancel_where(
+            |order| filters.iter().all(|filter| filter.matches(order)),
+            date,
+        )
+    });
+
+    for order in canceled_orders.iter() {
+        log::info!("Canceling {:?} order {}", order.order_type, order.id);
+    }
+
+    Ok(canceled_orders)
 }
 
 pub fn fulfill_trade_order<T: Trade>(
@@ -733,15 +1171,189 @@ pub fn fulfill_trade_order<T: Trade>(
     orders: &mut Vec<Order>,
 ) {
     let date = trade.get_chrono_date();
-    let order_position = orders
+    with_order_book(orders, |book| book.fill(order, index, date));
+}
+
+/// Size of the entry that can be filled on `candle`. Order sizes are in account
+/// currency while volume is in units, so the cap of `fill_volume_ratio` of the
+/// bar volume is applied to the order quantity at its target price.
+pub fn get_fill_size(order: &Order, candle: &Candle, fill_volume_ratio: f64) -> f64 {
+    let remaining_size = order.remaining_size();
+
+    match fill_volume_ratio > 0. && order.target_price > 0. {
+        true => {
+            let remaining_quantity = calculate_quantity(remaining_size, order.target_price);
+            let max_quantity = candle.volume() * fill_volume_ratio;
+            match remaining_quantity > max_quantity {
+                true => max_quantity * order.target_price,
+                false => remaining_size,
+            }
+        }
+        false => remaining_size,
+    }
+}
+
+/// Reprices a pending order in place. Orders created on the same bar share the
+/// id, so the order type disambiguates entry and exit legs. Targets are checked
+/// against the other legs of the trade, and resizing an entry resizes its
+/// pending exit legs. Returns one event per amended order, the requested one first.
+pub fn amend_order(
+    id: usize,
+    order_type: &OrderType,
+    amendment: OrderAmendment,
+    orders: &mut Vec<Order>,
+    time_frame: &TimeFrameType,
+    date: DbDateTime,
+) -> std::result::Result<Vec<OrderEvent>, OrderError> {
+    let position = orders
         .iter()
-        .position(|x| x.status == OrderStatus::Pending && x.order_type == order.order_type);
+        .position(|x| {
+            x.id == id
+                && x.is_pending()
+                && std::mem::discriminant(&x.order_type) == std::mem::discriminant(order_type)
+        })
+        .ok_or(OrderError::NotFound { id })?;
+
+    let order = &orders[position];
+    let is_leg = |x: &Order| {
+        x.trade_id == order.trade_id
+            && x.order_type != order.order_type
+            && x.status != OrderStatus::Canceled
+    };
+
+    if let Some(target) = amendment.target_price {
+        if target <= 0. {
+            return Err(OrderError::InvalidTarget { target });
+        }
+
+        match order.order_type.is_entry() {
+            true => {
+                for leg in orders.iter().filter(|x| is_leg(x) && x.is_pending()) {
+                    validate_leg_target(&leg.order_type, leg.target_price, target)?;
+                }
+            }
+            false => {
+                if let Some(entry) = orders.iter().find(|x| is_leg(x) && x.order_type.is_entry()) {
+                    validate_leg_target(&order.order_type, target, entry.target_price)?;
+                }
+            }
+        }
+    }
+
+    let resized_legs: Vec<usize> = match (amendment.size, order.order_type.is_entry()) {
+        (Some(_), true) => orders
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| is_leg(x) && x.is_pending() && !x.order_type.is_entry())
+            .map(|(index, _)| index)
+            .collect(),
+        _ => vec![],
+    };
+
+    if let Some(size) = amendment.size {
+        for index in std::iter::once(position).chain(resized_legs.iter().cloned()) {
+            let filled = orders[index].filled_size;
+            if size <= 0. || size < filled {
+                return Err(OrderError::InvalidSize { size, filled });
+            }
+        }
+    }
+
+    let order = &mut orders[position];
+    let previous = (order.target_price, order.size, order.valid_until);
+
+    if let Some(target) = amendment.target_price {
+        order.target_price = target;
+        order.ticks_beyond = 0;
+    }
+
+    if let Some(size) = amendment.size {
+        order.size = size;
+    }
+
+    if let Some(expiry) = amendment.expiry {
+        order.set_expiry(expiry, time_frame);
+    }
+
+    order.set_updated_at(date);
+
+    let mut events = vec![amended_event(order, previous, date)];
+
+    for index in resized_legs {
+        let leg = &mut orders[index];
+        let previous = (leg.target_price, leg.size, leg.valid_until);
+        leg.size = amendment.size.unwrap_or(leg.size);
+        leg.set_updated_at(date);
+        events.push(amended_event(leg, previous, date));
+    }
+
+    Ok(events)
+}
+
+fn amended_event(
+    order: &Order,
+    previous: (f64, f64, Option<DbDateTime>),
+    date: DbDateTime,
+) -> OrderEvent {
+    log::info!(
+        "Amended {:?} order {} to {} size {}",
+        order.order_type,
+        order.id,
+        order.target_price,
+        order.size
+    );
+
+    OrderEvent::Amended {
+        id: order.id,
+        trade_id: order.trade_id,
+        order_type: order.order_type.clone(),
+        date,
+        target_price: (previous.0, order.target_price),
+        size: (previous.1, order.size),
+        valid_until: (previous.2, order.valid_until),
+    }
+}
 
-    match order_position {
-        Some(x) => {
-            orders.get_mut(x).unwrap().fulfill_order(index, date);
+/// Stops and take profits have to stay on their side of the entry target.
+fn validate_leg_target(
+    leg_type: &OrderType,
+    leg_target: f64,
+    entry_target: f64,
+) -> std::result::Result<(), OrderError> {
+    match leg_type {
+        OrderType::StopLossLong(_, _) if leg_target >= entry_target => {
+            Err(OrderError::StopLossAboveEntry {
+                buy: entry_target,
+                stop: leg_target,
+            })
+        }
+        OrderType::StopLossShort(_, _) if leg_target <= entry_target => {
+            Err(OrderError::StopLossBelowEntry {
+                buy: entry_target,
+                stop: leg_target,
+            })
+        }
+        OrderType::TakeProfitLong(_, _, _) if leg_target <= entry_target => {
+            Err(OrderError::TargetBelowPrice {
+                target: leg_target,
+                price: entry_target,
+            })
         }
-        None => {}
+        OrderType::TakeProfitShort(_, _, _) if leg_target >= entry_target => {
+            Err(OrderError::TargetAbovePrice {
+                target: leg_target,
+                price: entry_target,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn cancel_oco_siblings(order: &Order, orders: &mut Vec<Order>, date: DbDateTime) {
+    let canceled_orders = with_order_book(orders, |book| book.cancel_oco_siblings(order, date));
+
+    for sibling in canceled_orders.iter() {
+        log::info!("Canceling OCO sibling order {:?}", sibling.id);
     }
 }
 
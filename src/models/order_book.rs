@@ -0,0 +1,304 @@
+use super::config::ConfigUpdate;
+use super::order::{amend_order, Order, OrderAmendment, OrderEvent, OrderStatus, OrderType};
+use super::time_frame::TimeFrameType;
+use super::trade::Trade;
+use crate::error::{OrderError, Result, RsAlgoError, RsAlgoErrorKind};
+
+use crate::helpers::date::*;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderLimits {
+    pub max_buy_orders: usize,
+    pub max_sell_orders: usize,
+    pub max_stop_losses: usize,
+    pub max_pending_orders: usize,
+}
+
+impl OrderLimits {
+    /// Limits of the runtime config, see `ConfigUpdate`. Panics when a limit
+    /// is not set.
+    pub fn from_env() -> Self {
+        Self::from_config(&ConfigUpdate::current()).unwrap()
+    }
+
+    /// Errors when MAX_BUY_ORDERS, MAX_SELL_ORDERS, MAX_STOP_LOSSES or
+    /// MAX_PENDING_ORDERS is missing from the config
+    pub fn from_config(config: &ConfigUpdate) -> Result<Self> {
+        let required = |name: &str, val: Option<usize>| {
+            val.ok_or_else(|| {
+                log::error!("{} is not set", name);
+                RsAlgoError {
+                    err: RsAlgoErrorKind::InvalidConfig,
+                }
+            })
+        };
+
+        Ok(Self {
+            max_buy_orders: required("MAX_BUY_ORDERS", config.max_buy_orders)?,
+            max_sell_orders: required("MAX_SELL_ORDERS", config.max_sell_orders)?,
+            max_stop_losses: required("MAX_STOP_LOSSES", config.max_stop_losses)?,
+            max_pending_orders: required("MAX_PENDING_ORDERS", config.max_pending_orders)?,
+        })
+    }
+}
+
+impl Default for OrderLimits {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// Owns the orders of a symbol in insertion order. Orders only move forward
+/// (pending -> fulfilled | cancelled) and pending limits are checked on insertion.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct OrderBook {
+    limits: OrderLimits,
+    orders: Vec<Order>,
+}
+
+impl OrderBook {
+    pub fn new(limits: OrderLimits) -> Self {
+        Self {
+            limits,
+            orders: vec![],
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(OrderLimits::from_env())
+    }
+
+    pub fn from_orders(limits: OrderLimits, orders: Vec<Order>) -> Self {
+        Self { limits, orders }
+    }
+
+    pub fn limits(&self) -> &OrderLimits {
+        &self.limits
+    }
+
+    pub fn orders(&self) -> &Vec<Order> {
+        &self.orders
+    }
+
+    pub fn into_orders(self) -> Vec<Order> {
+        self.orders
+    }
+
+    pub fn pending(&self) -> Vec<&Order> {
+        self.orders
+            .iter()
+            .filter(|order| order.is_pending())
+            .collect()
+    }
+
+    pub fn fulfilled(&self) -> Vec<&Order> {
+        self.with_status(OrderStatus::Fulfilled)
+    }
+
+    pub fn cancelled(&self) -> Vec<&Order> {
+        self.with_status(OrderStatus::Canceled)
+    }
+
+    fn with_status(&self, status: OrderStatus) -> Vec<&Order> {
+        self.orders
+            .iter()
+            .filter(|order| order.status == status)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.orders.iter().any(|order| order.is_pending())
+    }
+
+    pub fn num_pending(&self) -> (usize, usize, usize) {
+        num_pending(&self.orders)
+    }
+
+    /// Adds the new pending orders the limits allow and returns the rejected ones.
+    /// Orders of the same trade are admitted or rejected together, so an entry is
+    /// never accepted without its stop. Entries are rejected while the stops of
+    /// open trades are pending.
+    pub fn add(&mut self, orders: Vec<Order>) -> Vec<Order> {
+        let mut rejected = vec![];
+        let (_, _, open_stops) = self.num_pending();
+        let mut groups: Vec<Vec<Order>> = vec![];
+
+        for order in orders {
+            match groups
+                .iter_mut()
+                .find(|group| group[0].trade_id == order.trade_id)
+            {
+                Some(group) => group.push(order),
+                None => groups.push(vec![order]),
+            }
+        }
+
+        for group in groups {
+            match self.has_room_for(&group, open_stops) {
+                true => self.orders.extend(group),
+                false => rejected.extend(group),
+            }
+        }
+
+        rejected
+    }
+
+    fn has_room_for(&self, group: &[Order], open_stops: usize) -> bool {
+        let (mut buy_orders, mut sell_orders, mut stop_losses) = self.num_pending();
+        let mut num_pending = self
+            .orders
+            .iter()
+            .filter(|order| order.is_pending())
+            .count();
+
+        for (index, order) in group.iter().enumerate() {
+            let is_allowed = match order.order_type {
+                OrderType::BuyOrderLong(_, _, _) | OrderType::BuyOrderShort(_, _, _) => {
+                    buy_orders += 1;
+                    buy_orders <= self.limits.max_buy_orders
+                        && open_stops < self.limits.max_stop_losses
+                }
+                OrderType::SellOrderLong(_, _, _)
+                | OrderType::SellOrderShort(_, _, _)
+                | OrderType::TakeProfitLong(_, _, _)
+                | OrderType::TakeProfitShort(_, _, _) => {
+                    sell_orders += 1;
+                    sell_orders <= self.limits.max_sell_orders
+                }
+                OrderType::StopLossLong(_, _) | OrderType::StopLossShort(_, _) => {
+                    stop_losses += 1;
+                    stop_losses <= self.limits.max_stop_losses
+                }
+            };
+
+            num_pending += 1;
+            let is_duplicated = self
+                .orders
+                .iter()
+                .filter(|pending| pending.is_pending())
+                .chain(group[..index].iter())
+                .any(|pending| pending == order);
+
+            if !order.is_pending()
+                || !is_allowed
+                || is_duplicated
+                || num_pending > self.limits.max_pending_orders
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn get(&self, id: usize, trade_id: usize) -> Option<&Order> {
+        self.orders
+            .iter()
+            .find(|order| order.id == id && order.trade_id == trade_id)
+    }
+
+    pub fn amend(
+        &mut self,
+        id: usize,
+        order_type: &OrderType,
+        amendment: OrderAmendment,
+        time_frame: &TimeFrameType,
+        date: DbDateTime,
+    ) -> std::result::Result<Vec<OrderEvent>, OrderError> {
+        amend_order(
+            id,
+            order_type,
+            amendment,
+            &mut self.orders,
+            time_frame,
+            date,
+        )
+    }
+
+    /// Cancels every pending order of the trade and returns them.
+    pub fn cancel_for_trade<T: Trade>(&mut self, trade_id: usize, trade: &T) -> Vec<Order> {
+        self.cancel_where(|order| order.trade_id == trade_id, *trade.get_date())
+    }
+
+    pub fn cancel_all(&mut self, date: DbDateTime) -> Vec<Order> {
+        self.cancel_where(|_| true, date)
+    }
+
+    pub fn cancel_expired(&mut self, date: DateTime<Local>) -> Vec<Order> {
+        self.cancel_where(|order| !order.is_still_valid(date), to_dbtime(date))
+    }
+
+    pub fn cancel_oco_siblings(&mut self, order: &Order, date: DbDateTime) -> Vec<Order> {
+        self.cancel_where(|x| order.is_oco_sibling(x), date)
+    }
+
+    pub fn cancel_where<F>(&mut self, predicate: F, date: DbDateTime) -> Vec<Order>
+    where
+        F: Fn(&Order) -> bool,
+    {
+        let mut canceled = vec![];
+
+        for order in self
+            .orders
+            .iter_mut()
+            .filter(|order| order.is_pending() && predicate(order))
+        {
+            order.cancel_order(date);
+            canceled.push(order.clone());
+        }
+
+        canceled
+    }
+
+    /// Drops the cancelled orders, backtests don't keep them.
+    pub fn drain_cancelled(&mut self) -> Vec<Order> {
+        let (cancelled, orders): (Vec<Order>, Vec<Order>) = std::mem::take(&mut self.orders)
+            .into_iter()
+            .partition(|order| order.status == OrderStatus::Canceled);
+
+        self.orders = orders;
+        cancelled
+    }
+
+    /// Fills the pending order of the same type as `order`. Entries can be
+    /// filled partially, once fulfilled the order's OCO siblings are cancelled.
+    pub fn fill(&mut self, order: &Order, index: usize, date: DateTime<Local>) -> Option<Order> {
+        let pending_order = self
+            .orders
+            .iter_mut()
+            .find(|x| x.is_pending() && x.order_type == order.order_type)?;
+
+        match order.order_type.is_entry() {
+            true => pending_order.fill(order.size(), index, date),
+            false => pending_order.fulfill_order(index, date),
+        };
+
+        let filled = pending_order.clone();
+        if filled.status == OrderStatus::Fulfilled {
+            self.cancel_oco_siblings(&filled, to_dbtime(date));
+        }
+
+        Some(filled)
+    }
+}
+
+/// Number of pending buy orders, sell orders and stop losses.
+pub fn num_pending(orders: &[Order]) -> (usize, usize, usize) {
+    let mut buy_orders = 0;
+    let mut sell_orders = 0;
+    let mut stop_losses = 0;
+
+    for order in orders.iter().filter(|order| order.is_pending()) {
+        match order.order_type {
+            OrderType::BuyOrderLong(_, _, _) | OrderType::BuyOrderShort(_, _, _) => buy_orders += 1,
+            OrderType::SellOrderLong(_, _, _)
+            | OrderType::SellOrderShort(_, _, _)
+            | OrderType::TakeProfitLong(_, _, _)
+            | OrderType::TakeProfitShort(_, _, _) => sell_orders += 1,
+            OrderType::StopLossLong(_, _) | OrderType::StopLossShort(_, _) => stop_losses += 1,
+        };
+    }
+    (buy_orders, sell_orders, stop_losses)
+}
@@ -0,0 +1,173 @@
+use super::order::{Order, OrderEvent, OrderType};
+use super::order_book::{OrderBook, OrderLimits};
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::helpers::date::*;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    pub seq: usize,
+    pub date: DbDateTime,
+    pub symbol: String,
+    pub event: OrderEvent,
+}
+
+/// Append-only record of order state transitions. Entries are kept in memory
+/// and, when a path is set, appended as JSON lines.
+#[derive(Debug, Clone, Default)]
+pub struct OrderJournal {
+    path: Option<String>,
+    entries: Vec<JournalEntry>,
+}
+
+impl OrderJournal {
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            entries: vec![],
+        }
+    }
+
+    pub fn with_file(path: &str) -> Self {
+        Self {
+            path: Some(path.to_owned()),
+            entries: vec![],
+        }
+    }
+
+    pub fn from_env() -> Self {
+        match env::var("ORDER_JOURNAL") {
+            Ok(path) => Self::with_file(&path),
+            Err(_) => Self::new(),
+        }
+    }
+
+    /// Loads an existing journal, new entries are appended to the same file.
+    pub fn load(path: &str) -> Result<Self> {
+        let file = File::open(path).map_err(|_| journal_error())?;
+        let mut entries = vec![];
+
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|_| journal_error())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: JournalEntry = serde_json::from_str(&line).map_err(|_| journal_error())?;
+            entries.push(entry);
+        }
+
+        Ok(Self {
+            path: Some(path.to_owned()),
+            entries,
+        })
+    }
+
+    pub fn entries(&self) -> &Vec<JournalEntry> {
+        &self.entries
+    }
+
+    pub fn entries_for(&self, symbol: &str) -> Vec<&JournalEntry> {
+        self.entries.iter().filter(|x| x.symbol == symbol).collect()
+    }
+
+    pub fn record(&mut self, symbol: &str, event: OrderEvent) -> Result<()> {
+        let date = match &event {
+            OrderEvent::Amended { date, .. } => *date,
+            _ => now_dbtime(),
+        };
+
+        let entry = JournalEntry {
+            seq: self.entries.len(),
+            date,
+            symbol: symbol.to_owned(),
+            event,
+        };
+
+        if let Some(path) = &self.path {
+            let line = serde_json::to_string(&entry).map_err(|_| journal_error())?;
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|_| journal_error())?;
+            writeln!(file, "{}", line).map_err(|_| journal_error())?;
+        }
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub fn record_all<F>(&mut self, symbol: &str, orders: &Vec<Order>, event: F) -> Result<()>
+    where
+        F: Fn(Order) -> OrderEvent,
+    {
+        for order in orders {
+            self.record(symbol, event(order.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// Rebuilds the orders of a symbol applying the events in order.
+    pub fn replay_orders(&self, symbol: &str) -> Vec<Order> {
+        let mut orders: Vec<Order> = vec![];
+
+        for entry in self.entries_for(symbol) {
+            match &entry.event {
+                OrderEvent::Amended {
+                    id,
+                    trade_id,
+                    order_type,
+                    date,
+                    target_price,
+                    size,
+                    valid_until,
+                } => {
+                    if let Some(order) = orders
+                        .iter_mut()
+                        .find(|x| is_same_order(x, *id, *trade_id, order_type))
+                    {
+                        order.target_price = target_price.1;
+                        order.size = size.1;
+                        order.valid_until = valid_until.1;
+                        order.set_updated_at(*date);
+                    }
+                }
+                OrderEvent::Created(order)
+                | OrderEvent::Activated(order)
+                | OrderEvent::Fulfilled(order)
+                | OrderEvent::Cancelled(order)
+                | OrderEvent::Expired(order) => {
+                    match orders
+                        .iter_mut()
+                        .find(|x| is_same_order(x, order.id, order.trade_id, &order.order_type))
+                    {
+                        Some(existing) => *existing = order.clone(),
+                        None => orders.push(order.clone()),
+                    }
+                }
+            }
+        }
+
+        orders
+    }
+
+    pub fn replay(&self, symbol: &str, limits: OrderLimits) -> OrderBook {
+        OrderBook::from_orders(limits, self.replay_orders(symbol))
+    }
+}
+
+fn is_same_order(order: &Order, id: usize, trade_id: usize, order_type: &OrderType) -> bool {
+    order.id == id
+        && order.trade_id == trade_id
+        && std::mem::discriminant(&order.order_type) == std::mem::discriminant(order_type)
+}
+
+fn journal_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::JournalError,
+    }
+}
@@ -0,0 +1,247 @@
+use super::risk::{account_equity, Risk};
+use crate::scanner::instrument::Instrument;
+
+use dyn_clone::DynClone;
+use std::env;
+use std::fmt::Debug;
+
+/// What a sizer knows about the entry
+#[derive(Debug, Clone, Copy)]
+pub struct SizingContext<'a> {
+    pub index: usize,
+    pub instrument: &'a Instrument,
+    pub equity: f64,
+    pub price: f64,
+    pub stop_price: Option<f64>,
+}
+
+impl<'a> SizingContext<'a> {
+    pub fn stop_distance(&self) -> Option<f64> {
+        match self.stop_price {
+            Some(stop_price) if stop_price > 0. && stop_price != self.price => {
+                Some((self.price - stop_price).abs())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Order size of new entries in account currency. None when the entry
+/// can't be sized.
+pub trait PositionSizer: DynClone + Debug + Send + Sync {
+    fn size(&self, ctx: &SizingContext) -> Option<f64>;
+}
+
+dyn_clone::clone_trait_object!(PositionSizer);
+
+pub type BoxedSizer = Box<dyn PositionSizer>;
+
+/// The sizer and the account equity an entry is sized with
+#[derive(Debug, Clone, Copy)]
+pub struct Sizing<'a> {
+    pub sizer: &'a dyn PositionSizer,
+    pub equity: f64,
+}
+
+impl<'a> Sizing<'a> {
+    pub fn new(sizer: &'a dyn PositionSizer, equity: f64) -> Self {
+        Self { sizer, equity }
+    }
+
+    pub fn size(
+        &self,
+        index: usize,
+        instrument: &Instrument,
+        price: f64,
+        stop_price: Option<f64>,
+    ) -> Option<f64> {
+        self.sizer
+            .size(&SizingContext {
+                index,
+                instrument,
+                equity: self.equity,
+                price,
+                stop_price,
+            })
+            .filter(|size| *size > 0.)
+    }
+}
+
+/// Same order size for every entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedSize(pub f64);
+
+impl PositionSizer for FixedSize {
+    fn size(&self, _ctx: &SizingContext) -> Option<f64> {
+        Some(self.0)
+    }
+}
+
+/// Same quantity for every entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedLots(pub f64);
+
+impl PositionSizer for FixedLots {
+    fn size(&self, ctx: &SizingContext) -> Option<f64> {
+        Some(self.0 * ctx.price)
+    }
+}
+
+/// Risks a percentage of the equity to the stop. Without a stop the
+/// percentage is the order size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedFractional(pub f64);
+
+impl PositionSizer for FixedFractional {
+    fn size(&self, ctx: &SizingContext) -> Option<f64> {
+        fractional_size(ctx, self.0 / 100.)
+    }
+}
+
+/// Risks a percentage of the equity to a stop `atr_multiple` ATRs away, so
+/// quieter markets get bigger positions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolatilityTarget {
+    pub risk_per: f64,
+    pub atr_period: usize,
+    pub atr_multiple: f64,
+}
+
+impl PositionSizer for VolatilityTarget {
+    fn size(&self, ctx: &SizingContext) -> Option<f64> {
+        let atr = average_true_range(ctx.instrument, ctx.index, self.atr_period)?;
+        let stop_distance = atr * self.atr_multiple;
+        match stop_distance > 0. {
+            true => Some(ctx.equity * self.risk_per / 100. / stop_distance * ctx.price),
+            false => None,
+        }
+    }
+}
+
+/// Kelly fraction of the equity from the win rate and the average win to
+/// average loss ratio, capped. Negative edges don't trade.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kelly {
+    pub win_rate: f64,
+    pub payoff_ratio: f64,
+    pub cap: f64,
+}
+
+impl Kelly {
+    pub fn fraction(&self) -> f64 {
+        match self.payoff_ratio > 0. {
+            true => (self.win_rate - (1. - self.win_rate) / self.payoff_ratio).clamp(0., self.cap),
+            false => 0.,
+        }
+    }
+}
+
+impl PositionSizer for Kelly {
+    fn size(&self, ctx: &SizingContext) -> Option<f64> {
+        fractional_size(ctx, self.fraction())
+    }
+}
+
+/// Risks the amount of the order risk to the stop. Entries without a stop
+/// can't be sized.
+impl PositionSizer for Risk {
+    fn size(&self, ctx: &SizingContext) -> Option<f64> {
+        let amount = self.amount(ctx.equity);
+        match amount > 0. {
+            true => ctx
+                .stop_distance()
+                .map(|stop_distance| amount / stop_distance * ctx.price),
+            false => None,
+        }
+    }
+}
+
+/// Risks the runtime risk per trade of `Risk::from_env` to the stop, so
+/// config updates apply to the next entry. Entries without a stop or a risk
+/// per trade get `order_size`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskPerTrade {
+    pub order_size: f64,
+}
+
+impl PositionSizer for RiskPerTrade {
+    fn size(&self, ctx: &SizingContext) -> Option<f64> {
+        let risk = Risk::from_env();
+        match risk.is_none() || ctx.stop_distance().is_none() {
+            true => Some(self.order_size),
+            false => risk.size(ctx),
+        }
+    }
+}
+
+fn fractional_size(ctx: &SizingContext, fraction: f64) -> Option<f64> {
+    let amount = ctx.equity * fraction;
+    match ctx.stop_distance() {
+        Some(stop_distance) => Some(amount / stop_distance * ctx.price),
+        None => Some(amount),
+    }
+}
+
+/// Average true range of the `period` bars up to `index`
+pub fn average_true_range(instrument: &Instrument, index: usize, period: usize) -> Option<f64> {
+    let data = instrument.data();
+    if period == 0 || index >= data.len() || index < period {
+        return None;
+    }
+
+    let ranges: Vec<f64> = (index + 1 - period..=index)
+        .map(|i| {
+            let (candle, prev_close) = (&data[i], data[i - 1].close());
+            (candle.high() - candle.low())
+                .max((candle.high() - prev_close).abs())
+                .max((candle.low() - prev_close).abs())
+        })
+        .collect();
+
+    Some(ranges.iter().sum::<f64>() / period as f64)
+}
+
+/// POSITION_SIZER is "size" (ORDER_SIZE), "lots" (POSITION_LOTS),
+/// "fractional" (POSITION_RISK), "volatility" (POSITION_RISK,
+/// POSITION_ATR_PERIOD, POSITION_ATR_MULTIPLE), "kelly" (KELLY_WIN_RATE,
+/// KELLY_PAYOFF_RATIO, KELLY_CAP) or "risk" (the risk per trade and
+/// ORDER_SIZE). Unset it is "risk" when a risk per trade is configured and
+/// "size" otherwise.
+pub fn position_sizer_from_env() -> BoxedSizer {
+    let parse = |key: &str, default: f64| {
+        env::var(key)
+            .ok()
+            .and_then(|val| val.parse::<f64>().ok())
+            .unwrap_or(default)
+    };
+
+    match env::var("POSITION_SIZER").unwrap_or_default().as_ref() {
+        "lots" => Box::new(FixedLots(parse("POSITION_LOTS", 1.))),
+        "fractional" => Box::new(FixedFractional(parse("POSITION_RISK", 1.))),
+        "volatility" => Box::new(VolatilityTarget {
+            risk_per: parse("POSITION_RISK", 1.),
+            atr_period: parse("POSITION_ATR_PERIOD", 14.) as usize,
+            atr_multiple: parse("POSITION_ATR_MULTIPLE", 2.),
+        }),
+        "kelly" => Box::new(Kelly {
+            win_rate: parse("KELLY_WIN_RATE", 0.5),
+            payoff_ratio: parse("KELLY_PAYOFF_RATIO", 1.),
+            cap: parse("KELLY_CAP", 0.25),
+        }),
+        "size" => Box::new(FixedSize(parse("ORDER_SIZE", 0.))),
+        "risk" => Box::new(RiskPerTrade {
+            order_size: parse("ORDER_SIZE", 0.),
+        }),
+        _ => match Risk::from_env().is_none() {
+            true => Box::new(FixedSize(parse("ORDER_SIZE", 0.))),
+            false => Box::new(RiskPerTrade {
+                order_size: parse("ORDER_SIZE", 0.),
+            }),
+        },
+    }
+}
+
+/// Sizer from the env with the ACCOUNT_EQUITY
+pub fn sizing_from_env(sizer: &dyn PositionSizer) -> Sizing {
+    Sizing::new(sizer, account_equity())
+}
@@ -0,0 +1,328 @@
+use super::config::ConfigUpdate;
+use super::equity::Equity;
+use super::exposure::Exposure;
+use crate::helpers::date::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Risk {
+    None,
+    Amount(f64),
+    Percentage(f64),
+}
+
+impl Default for Risk {
+    fn default() -> Self {
+        Risk::None
+    }
+}
+
+impl Risk {
+    /// The risk per trade of the runtime config, or RISK_TYPE and RISK_VALUE
+    pub fn from_env() -> Self {
+        if let Some(per) = ConfigUpdate::current().risk_per_trade {
+            return Risk::Percentage(per);
+        }
+
+        let risk_type = env::var("RISK_TYPE").unwrap_or_else(|_| "none".to_owned());
+        let value = env::var("RISK_VALUE")
+            .unwrap_or("0".to_string())
+            .parse::<f64>()
+            .unwrap();
+
+        match risk_type.as_ref() {
+            "amount" => Risk::Amount(value),
+            "percentage" => Risk::Percentage(value),
+            _ => Risk::None,
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, Risk::None)
+    }
+
+    /// Money at risk for the given account equity
+    pub fn amount(&self, equity: f64) -> f64 {
+        match self {
+            Risk::None => 0.,
+            Risk::Amount(amount) => *amount,
+            Risk::Percentage(per) => equity * per / 100.,
+        }
+    }
+}
+
+pub fn account_equity() -> f64 {
+    env::var("ACCOUNT_EQUITY")
+        .unwrap_or("0".to_string())
+        .parse::<f64>()
+        .unwrap()
+}
+
+/// Limits checked before new entries. Losses and exposures are amounts in
+/// the account currency, the drawdown a percentage of the equity peak. 0
+/// disables a limit.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct RiskLimits {
+    pub max_daily_loss: f64,
+    pub max_weekly_loss: f64,
+    pub max_open_trades: usize,
+    pub max_symbol_exposure: f64,
+    pub max_sector_exposure: f64,
+    pub max_drawdown_per: f64,
+    /// Sector by symbol
+    #[serde(default)]
+    pub sectors: HashMap<String, String>,
+}
+
+impl RiskLimits {
+    /// Reads RISK_MAX_DAILY_LOSS, RISK_MAX_WEEKLY_LOSS, RISK_MAX_OPEN_TRADES,
+    /// RISK_MAX_SYMBOL_EXPOSURE, RISK_MAX_SECTOR_EXPOSURE, RISK_MAX_DRAWDOWN
+    /// and RISK_SECTORS as "EURUSD:forex,GBPUSD:forex,AAPL:tech"
+    pub fn from_env() -> Self {
+        let parse = |key: &str| {
+            env::var(key)
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(0.)
+        };
+
+        let sectors = env::var("RISK_SECTORS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|pair| pair.split_once(':'))
+            .map(|(symbol, sector)| (symbol.trim().to_owned(), sector.trim().to_owned()))
+            .collect();
+
+        Self {
+            max_daily_loss: parse("RISK_MAX_DAILY_LOSS"),
+            max_weekly_loss: parse("RISK_MAX_WEEKLY_LOSS"),
+            max_open_trades: parse("RISK_MAX_OPEN_TRADES") as usize,
+            max_symbol_exposure: parse("RISK_MAX_SYMBOL_EXPOSURE"),
+            max_sector_exposure: parse("RISK_MAX_SECTOR_EXPOSURE"),
+            max_drawdown_per: parse("RISK_MAX_DRAWDOWN"),
+            sectors,
+        }
+    }
+
+    pub fn max_daily_loss(mut self, val: f64) -> Self {
+        self.max_daily_loss = val;
+        self
+    }
+
+    pub fn max_weekly_loss(mut self, val: f64) -> Self {
+        self.max_weekly_loss = val;
+        self
+    }
+
+    pub fn max_open_trades(mut self, val: usize) -> Self {
+        self.max_open_trades = val;
+        self
+    }
+
+    pub fn max_symbol_exposure(mut self, val: f64) -> Self {
+        self.max_symbol_exposure = val;
+        self
+    }
+
+    pub fn max_sector_exposure(mut self, val: f64) -> Self {
+        self.max_sector_exposure = val;
+        self
+    }
+
+    pub fn max_drawdown_per(mut self, val: f64) -> Self {
+        self.max_drawdown_per = val;
+        self
+    }
+
+    pub fn sector(mut self, symbol: &str, sector: &str) -> Self {
+        self.sectors.insert(symbol.to_owned(), sector.to_owned());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RiskBreach {
+    DailyLoss {
+        loss: f64,
+        limit: f64,
+    },
+    WeeklyLoss {
+        loss: f64,
+        limit: f64,
+    },
+    OpenTrades {
+        open_trades: usize,
+        limit: usize,
+    },
+    SymbolExposure {
+        exposure: f64,
+        limit: f64,
+    },
+    SectorExposure {
+        sector: String,
+        exposure: f64,
+        limit: f64,
+    },
+    Drawdown {
+        drawdown_per: f64,
+        limit: f64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RiskEvent {
+    pub date: DbDateTime,
+    pub symbol: String,
+    pub breach: RiskBreach,
+}
+
+/// Vetoes new entries while a risk limit is breached and records every veto
+/// as a `RiskEvent`. Exits are never blocked.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RiskManager {
+    limits: RiskLimits,
+    day: Option<((i32, u32), f64)>,
+    week: Option<((i32, u32), f64)>,
+    events: Vec<RiskEvent>,
+}
+
+impl RiskManager {
+    pub fn new(limits: RiskLimits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(RiskLimits::from_env())
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    pub fn events(&self) -> &Vec<RiskEvent> {
+        &self.events
+    }
+
+    pub fn take_events(&mut self) -> Vec<RiskEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Called every bar. The equity of the first update of a day or ISO week
+    /// is the reference of its loss limit.
+    pub fn update(&mut self, date: DateTime<Local>, equity: &Equity) {
+        let day = (date.year(), date.ordinal());
+        let week = (date.iso_week().year(), date.iso_week().week());
+
+        if self.day.map(|(key, _)| key) != Some(day) {
+            self.day = Some((day, equity.value()));
+        }
+        if self.week.map(|(key, _)| key) != Some(week) {
+            self.week = Some((week, equity.value()));
+        }
+    }
+
+    /// Whether a new entry of `notional` is allowed. `exposures` are the
+    /// current exposures of every traded symbol.
+    pub fn check_entry(
+        &mut self,
+        date: DateTime<Local>,
+        symbol: &str,
+        equity: &Equity,
+        exposures: &[Exposure],
+        notional: f64,
+    ) -> bool {
+        match self.breach(symbol, equity, exposures, notional) {
+            Some(breach) => {
+                log::warn!("Risk limit breached on {} {:?}", symbol, breach);
+                self.events.push(RiskEvent {
+                    date: to_dbtime(date),
+                    symbol: symbol.to_owned(),
+                    breach,
+                });
+                false
+            }
+            None => true,
+        }
+    }
+
+    pub fn breach(
+        &self,
+        symbol: &str,
+        equity: &Equity,
+        exposures: &[Exposure],
+        notional: f64,
+    ) -> Option<RiskBreach> {
+        let limits = &self.limits;
+        let value = equity.value();
+
+        let daily_loss = self.day.map(|(_, start)| start - value).unwrap_or(0.);
+        if limits.max_daily_loss > 0. && daily_loss >= limits.max_daily_loss {
+            return Some(RiskBreach::DailyLoss {
+                loss: daily_loss,
+                limit: limits.max_daily_loss,
+            });
+        }
+
+        let weekly_loss = self.week.map(|(_, start)| start - value).unwrap_or(0.);
+        if limits.max_weekly_loss > 0. && weekly_loss >= limits.max_weekly_loss {
+            return Some(RiskBreach::WeeklyLoss {
+                loss: weekly_loss,
+                limit: limits.max_weekly_loss,
+            });
+        }
+
+        if limits.max_drawdown_per > 0. && equity.drawdown_per() >= limits.max_drawdown_per {
+            return Some(RiskBreach::Drawdown {
+                drawdown_per: equity.drawdown_per(),
+                limit: limits.max_drawdown_per,
+            });
+        }
+
+        let open_trades: usize = exposures.iter().map(|x| x.open_trades).sum();
+        if limits.max_open_trades > 0 && open_trades >= limits.max_open_trades {
+            return Some(RiskBreach::OpenTrades {
+                open_trades,
+                limit: limits.max_open_trades,
+            });
+        }
+
+        let symbol_exposure = notional
+            + exposures
+                .iter()
+                .filter(|x| x.symbol == symbol)
+                .map(|x| x.notional)
+                .sum::<f64>();
+        if limits.max_symbol_exposure > 0. && symbol_exposure > limits.max_symbol_exposure {
+            return Some(RiskBreach::SymbolExposure {
+                exposure: symbol_exposure,
+                limit: limits.max_symbol_exposure,
+            });
+        }
+
+        match limits.sectors.get(symbol) {
+            Some(sector) if limits.max_sector_exposure > 0. => {
+                let sector_exposure = notional
+                    + exposures
+                        .iter()
+                        .filter(|x| limits.sectors.get(&x.symbol) == Some(sector))
+                        .map(|x| x.notional)
+                        .sum::<f64>();
+                match sector_exposure > limits.max_sector_exposure {
+                    true => Some(RiskBreach::SectorExposure {
+                        sector: sector.clone(),
+                        exposure: sector_exposure,
+                        limit: limits.max_sector_exposure,
+                    }),
+                    false => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::helpers::calc::get_prev_index;
+use crate::helpers::date::*;
+use crate::scanner::instrument::Instrument;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionGap {
+    pub index: usize,
+    pub prev_close: f64,
+    pub open: f64,
+    pub bars_since_open: usize,
+}
+
+impl SessionGap {
+    pub fn size(&self) -> f64 {
+        (self.open - self.prev_close).abs()
+    }
+
+    pub fn is_gap_up(&self) -> bool {
+        self.open > self.prev_close
+    }
+
+    pub fn is_jumped(&self, price: f64) -> bool {
+        let (low, high) = match self.is_gap_up() {
+            true => (self.prev_close, self.open),
+            false => (self.open, self.prev_close),
+        };
+        price >= low && price <= high
+    }
+}
+
+pub fn is_session_open(index: usize, instrument: &Instrument) -> bool {
+    let data = instrument.data();
+    let prev_index = get_prev_index(index);
+
+    match (data.get(index), data.get(prev_index)) {
+        (Some(candle), Some(prev_candle)) if index > 0 => {
+            let max_distance = Duration::minutes(instrument.time_frame().to_minutes());
+            candle.date() - prev_candle.date() > max_distance
+        }
+        _ => false,
+    }
+}
+
+/// Gap of the bar when it opens a session
+pub fn session_open_gap(index: usize, instrument: &Instrument) -> Option<SessionGap> {
+    match is_session_open(index, instrument) {
+        true => get_session_gap(index, instrument, 0),
+        false => None,
+    }
+}
+
+/// Gap of the last session open within `max_bars` bars before the index
+pub fn get_session_gap(
+    index: usize,
+    instrument: &Instrument,
+    max_bars: usize,
+) -> Option<SessionGap> {
+    let data = instrument.data();
+    let open_index = (index.saturating_sub(max_bars)..=index)
+        .rev()
+        .find(|idx| is_session_open(*idx, instrument))?;
+
+    let candle = data.get(open_index)?;
+    let prev_candle = data.get(get_prev_index(open_index))?;
+
+    Some(SessionGap {
+        index: open_index,
+        prev_close: prev_candle.close(),
+        open: candle.open(),
+        bars_since_open: index - open_index,
+    })
+}
+
+pub fn entries_suppressed(index: usize, instrument: &Instrument) -> bool {
+    let suppress_bars = env::var("GAP_SUPPRESS_BARS")
+        .unwrap_or_else(|_| "0".to_owned())
+        .parse::<usize>()
+        .unwrap();
+
+    match suppress_bars > 0 {
+        true => get_session_gap(index, instrument, suppress_bars - 1).is_some(),
+        false => false,
+    }
+}
+
+pub fn stop_gap_tolerance(index: usize, instrument: &Instrument) -> f64 {
+    let widen_stops = env::var("GAP_WIDEN_STOPS")
+        .unwrap_or_else(|_| "false".to_owned())
+        .parse::<bool>()
+        .unwrap();
+
+    match widen_stops {
+        true => session_open_gap(index, instrument)
+            .map(|gap| gap.size())
+            .unwrap_or(0.),
+        false => 0.,
+    }
+}
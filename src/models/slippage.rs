@@ -0,0 +1,105 @@
+use super::pricing::Pricing;
+use crate::helpers::calc;
+use crate::indicators::Indicator;
+use crate::scanner::instrument::Instrument;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum SlippageModel {
+    None,
+    FixedPips(f64),
+    Percentage(f64),
+    Volatility(f64),
+    Random(f64, f64),
+}
+
+impl SlippageModel {
+    pub fn from_env() -> Self {
+        let model = env::var("SLIPPAGE_MODEL").unwrap_or("none".to_string());
+        let value = || {
+            env::var("SLIPPAGE_VALUE")
+                .unwrap_or("0".to_string())
+                .parse::<f64>()
+                .unwrap()
+        };
+
+        match model.as_ref() {
+            "pips" => SlippageModel::FixedPips(value()),
+            "percentage" => SlippageModel::Percentage(value()),
+            "volatility" => SlippageModel::Volatility(value()),
+            "random" => {
+                let min = env::var("SLIPPAGE_MIN")
+                    .unwrap_or("0".to_string())
+                    .parse::<f64>()
+                    .unwrap();
+                let max = env::var("SLIPPAGE_MAX")
+                    .unwrap_or("0".to_string())
+                    .parse::<f64>()
+                    .unwrap();
+                SlippageModel::Random(min, max)
+            }
+            _ => SlippageModel::None,
+        }
+    }
+
+    #[allow(deprecated)]
+    pub fn slippage(
+        &self,
+        index: usize,
+        instrument: &Instrument,
+        pricing: &Pricing,
+        price: f64,
+    ) -> f64 {
+        match self {
+            SlippageModel::None => 0.,
+            SlippageModel::FixedPips(pips) => calc::to_pips(*pips, pricing),
+            SlippageModel::Percentage(percentage) => price * percentage / 100.,
+            SlippageModel::Volatility(multiplier) => {
+                match instrument.indicators.atr.get_data_a().get(index) {
+                    Some(atr) => atr * multiplier,
+                    None => 0.,
+                }
+            }
+            SlippageModel::Random(min, max) => {
+                let seed = match instrument.data().get(index) {
+                    Some(candle) => candle.date().timestamp() as u64 ^ index as u64,
+                    None => index as u64,
+                };
+                let pips = min + (max - min) * pseudo_random(seed);
+                calc::to_pips(pips, pricing)
+            }
+        }
+    }
+
+    /// Moves the price against the trade: buys fill higher, sells fill lower.
+    pub fn apply(
+        &self,
+        index: usize,
+        instrument: &Instrument,
+        pricing: &Pricing,
+        price: f64,
+        is_buy: bool,
+    ) -> f64 {
+        let slippage = self.slippage(index, instrument, pricing, price).abs();
+        match is_buy {
+            true => price + slippage,
+            false => price - slippage,
+        }
+    }
+}
+
+impl Default for SlippageModel {
+    fn default() -> Self {
+        SlippageModel::None
+    }
+}
+
+fn pseudo_random(seed: u64) -> f64 {
+    let mut x = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
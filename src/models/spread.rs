@@ -0,0 +1,172 @@
+use super::pricing::Pricing;
+use super::session;
+use crate::helpers::date::*;
+use crate::scanner::instrument::Instrument;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SpreadWindow {
+    pub from_minute: u32,
+    pub to_minute: u32,
+    pub multiplier: f64,
+}
+
+impl SpreadWindow {
+    pub fn new(from: (u32, u32), to: (u32, u32), multiplier: f64) -> Self {
+        Self {
+            from_minute: from.0 * 60 + from.1,
+            to_minute: to.0 * 60 + to.1,
+            multiplier,
+        }
+    }
+
+    /// Windows crossing midnight (from > to) are supported.
+    pub fn contains(&self, minute: u32) -> bool {
+        match self.from_minute <= self.to_minute {
+            true => minute >= self.from_minute && minute < self.to_minute,
+            false => minute >= self.from_minute || minute < self.to_minute,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct SpreadSchedule {
+    windows: Vec<SpreadWindow>,
+    session_open_multiplier: f64,
+}
+
+impl SpreadSchedule {
+    pub fn new(windows: Vec<SpreadWindow>, session_open_multiplier: f64) -> Self {
+        Self {
+            windows,
+            session_open_multiplier,
+        }
+    }
+
+    /// Rollover widening between 21:00 and 22:00 UTC
+    pub fn rollover() -> Self {
+        Self::new(vec![SpreadWindow::new((21, 0), (22, 0), 3.)], 2.)
+    }
+
+    /// SPREAD_SCHEDULE is either "rollover" or a list of "HH:MM-HH:MM:multiplier" in UTC
+    pub fn from_env() -> Self {
+        let schedule = env::var("SPREAD_SCHEDULE").unwrap_or("".to_string());
+        let session_open_multiplier = env::var("SPREAD_SESSION_OPEN_MULTIPLIER")
+            .unwrap_or("0".to_string())
+            .parse::<f64>()
+            .unwrap();
+
+        let mut spread_schedule = match schedule.as_ref() {
+            "rollover" => Self::rollover(),
+            _ => Self::new(parse_windows(&schedule), 0.),
+        };
+
+        if session_open_multiplier > 0. {
+            spread_schedule.session_open_multiplier = session_open_multiplier;
+        }
+
+        spread_schedule
+    }
+
+    /// Builds hourly windows from observed (date, spread) samples, widening
+    /// the hours whose average spread is above the overall average.
+    pub fn learn(samples: &Vec<(DateTime<Local>, f64)>) -> Self {
+        let mut sums = [0.; 24];
+        let mut counts = [0usize; 24];
+
+        for (date, spread) in samples {
+            let hour = date.with_timezone(&Utc).hour() as usize;
+            sums[hour] += spread;
+            counts[hour] += 1;
+        }
+
+        let total: f64 = sums.iter().sum();
+        let num: usize = counts.iter().sum();
+        if num == 0 || total <= 0. {
+            return Self::default();
+        }
+        let avg = total / num as f64;
+
+        let windows = (0..24)
+            .filter(|hour| counts[*hour] > 0)
+            .filter_map(|hour| {
+                let multiplier = sums[hour] / counts[hour] as f64 / avg;
+                match multiplier > 1. {
+                    true => Some(SpreadWindow::new(
+                        (hour as u32, 0),
+                        ((hour as u32 + 1) % 24, 0),
+                        multiplier,
+                    )),
+                    false => None,
+                }
+            })
+            .collect();
+
+        Self::new(windows, 0.)
+    }
+
+    pub fn windows(&self) -> &Vec<SpreadWindow> {
+        &self.windows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty() && self.session_open_multiplier <= 0.
+    }
+
+    pub fn multiplier(&self, date: DateTime<Local>) -> f64 {
+        let utc = date.with_timezone(&Utc);
+        let minute = utc.hour() * 60 + utc.minute();
+
+        self.windows
+            .iter()
+            .filter(|window| window.contains(minute))
+            .map(|window| window.multiplier)
+            .fold(1., f64::max)
+    }
+
+    pub fn spread(&self, index: usize, instrument: &Instrument, pricing: &Pricing) -> f64 {
+        if self.is_empty() {
+            return pricing.spread();
+        }
+
+        let date = match instrument.data().get(index) {
+            Some(candle) => candle.date(),
+            None => return pricing.spread(),
+        };
+
+        let mut multiplier = self.multiplier(date);
+        if self.session_open_multiplier > 0. && session::is_session_open(index, instrument) {
+            multiplier = multiplier.max(self.session_open_multiplier);
+        }
+
+        pricing.spread() * multiplier
+    }
+}
+
+fn parse_windows(schedule: &str) -> Vec<SpreadWindow> {
+    let parse_time = |time: &str| -> Option<(u32, u32)> {
+        let mut parts = time.trim().split(':');
+        let hour = parts.next()?.parse::<u32>().ok()?;
+        let minute = parts.next().unwrap_or("0").parse::<u32>().ok()?;
+        Some((hour, minute))
+    };
+
+    schedule
+        .split(',')
+        .filter(|x| !x.trim().is_empty())
+        .filter_map(|window| {
+            let (range, multiplier) = window.rsplit_once(':')?;
+            let (from, to) = range.split_once('-')?;
+            let multiplier = multiplier.trim().parse::<f64>().ok()?;
+            match (parse_time(from), parse_time(to)) {
+                (Some(from), Some(to)) => Some(SpreadWindow::new(from, to, multiplier)),
+                _ => {
+                    log::error!("Invalid spread window {}", window);
+                    None
+                }
+            }
+        })
+        .collect()
+}
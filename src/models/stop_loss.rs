@@ -1,17 +1,23 @@
+use super::mode;
 use super::order::{self, Order, OrderDirection, OrderType};
 use super::pricing::Pricing;
 
+use crate::helpers::uuid::IdGenerator;
 use crate::helpers::{calc, date::*};
 use crate::indicators::Indicator;
+use crate::scanner::candle::Candle;
 use crate::scanner::instrument::Instrument;
 
 use serde::{Deserialize, Serialize};
+use std::env;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum StopLossType {
     Atr(f64),
     Price(f64),
     Pips(f64),
+    Trailing(f64),
+    Psar(f64),
     None,
 }
 
@@ -30,8 +36,8 @@ pub fn init_stop_loss(stop_type: StopLossType, value: f64) -> StopLoss {
         price: 0.,
         value,
         stop_type,
-        created_at: to_dbtime(Local::now()),
-        updated_at: to_dbtime(Local::now()),
+        created_at: now_dbtime(),
+        updated_at: now_dbtime(),
         valid_until: to_dbtime(Local::now() + Duration::days(1000)),
     }
 }
@@ -45,6 +51,7 @@ pub fn create_stop_loss_order(
     stop_loss_type: &StopLossType,
     target_price: f64,
     order_size: f64,
+    ids: &mut IdGenerator,
 ) -> Order {
     let spread = pricing.spread();
 
@@ -63,6 +70,7 @@ pub fn create_stop_loss_order(
         false => 0.,
     };
 
+    #[allow(deprecated)]
     let current_atr_value =
         instrument.indicators.atr.get_data_a().get(index).unwrap() * atr_multiplier;
     let _current_close = instrument.data().get(index).unwrap().close();
@@ -90,6 +98,17 @@ pub fn create_stop_loss_order(
             OrderDirection::Up => (target_price + spread) + calc::to_pips(*pips, pricing),
             OrderDirection::Down => (target_price + spread) - calc::to_pips(*pips, pricing),
         },
+        StopLossType::Trailing(distance) => {
+            let distance = trailing_distance(index, instrument, pricing, *distance);
+            match order_direction {
+                OrderDirection::Up => (target_price + spread) + distance,
+                OrderDirection::Down => (target_price + spread) - distance,
+            }
+        }
+        StopLossType::Psar(sar) => match order_direction {
+            OrderDirection::Up => sar + spread,
+            OrderDirection::Down => *sar,
+        },
         StopLossType::None => todo!(),
     };
 
@@ -109,5 +128,136 @@ pub fn create_stop_loss_order(
         &stop_loss,
         &target_price,
         &order_size,
+        ids,
     )
 }
+
+/// Stop at the parabolic SAR of the bar. None while the SAR is on the
+/// wrong side of the trade.
+pub fn psar_stop_loss(
+    index: usize,
+    instrument: &Instrument,
+    order_direction: &OrderDirection,
+) -> Option<StopLossType> {
+    let psar = instrument.indicators.psar();
+    let sar = psar.sar().get(index)?;
+    let direction = psar.direction().get(index)?;
+
+    match (order_direction, *direction > 0.) {
+        (OrderDirection::Down, true) | (OrderDirection::Up, false) => {
+            Some(StopLossType::Psar(*sar))
+        }
+        _ => None,
+    }
+}
+
+pub fn trailing_distance(
+    index: usize,
+    instrument: &Instrument,
+    pricing: &Pricing,
+    distance: f64,
+) -> f64 {
+    let trailing_type = env::var("TRAILING_STOP_TYPE").unwrap_or("pips".to_string());
+
+    match trailing_type.as_ref() {
+        "atr" => {
+            #[allow(deprecated)]
+            let current_atr_value = instrument.indicators.atr.get_data_a().get(index).unwrap();
+            distance * current_atr_value
+        }
+        _ => calc::to_pips(distance, pricing),
+    }
+}
+
+pub fn update_trailing_stop_loss(
+    index: usize,
+    instrument: &Instrument,
+    pricing: &Pricing,
+    orders: &mut Vec<Order>,
+) -> Vec<Order> {
+    let execution_mode = mode::from_str(&env::var("EXECUTION_MODE").unwrap());
+    let current_candle = match execution_mode.is_back_test() {
+        true => instrument.data().get(index).unwrap(),
+        false => instrument.data().last().unwrap(),
+    };
+
+    let mut updated_orders = vec![];
+
+    for order in orders.iter_mut().filter(|order| order.is_pending()) {
+        let (direction, distance) = match &order.order_type {
+            OrderType::StopLossLong(direction, StopLossType::Trailing(distance))
+            | OrderType::StopLossShort(direction, StopLossType::Trailing(distance)) => {
+                (direction.clone(), Some(*distance))
+            }
+            OrderType::StopLossLong(direction, StopLossType::Psar(_))
+            | OrderType::StopLossShort(direction, StopLossType::Psar(_)) => {
+                (direction.clone(), None)
+            }
+            _ => continue,
+        };
+
+        let new_target = match distance {
+            Some(distance) => {
+                let distance = trailing_distance(index, instrument, pricing, distance);
+                trailing_target(
+                    &direction,
+                    distance,
+                    current_candle,
+                    pricing,
+                    &execution_mode,
+                )
+            }
+            None => match psar_stop_loss(index, instrument, &direction) {
+                Some(StopLossType::Psar(sar)) => match direction {
+                    OrderDirection::Up => sar + pricing.spread(),
+                    OrderDirection::Down => sar,
+                },
+                _ => continue,
+            },
+        };
+
+        let has_advanced = match direction {
+            OrderDirection::Down => new_target > order.target_price,
+            OrderDirection::Up => new_target < order.target_price,
+        };
+
+        if has_advanced {
+            log::info!(
+                "Trailing stop {} moved from {} to {}",
+                order.id,
+                order.target_price,
+                new_target
+            );
+            order.update_pricing(order.origin_price, new_target);
+            order.set_updated_at(to_dbtime(current_candle.date()));
+            updated_orders.push(order.clone());
+        }
+    }
+
+    updated_orders
+}
+
+fn trailing_target(
+    direction: &OrderDirection,
+    distance: f64,
+    current_candle: &Candle,
+    pricing: &Pricing,
+    execution_mode: &mode::ExecutionMode,
+) -> f64 {
+    match direction {
+        OrderDirection::Down => {
+            let price = match execution_mode.is_back_test() {
+                true => current_candle.close(),
+                false => pricing.bid(),
+            };
+            price - distance
+        }
+        OrderDirection::Up => {
+            let price = match execution_mode.is_back_test() {
+                true => current_candle.close() + pricing.spread(),
+                false => pricing.ask(),
+            };
+            price + distance
+        }
+    }
+}
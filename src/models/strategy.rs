@@ -1,4 +1,15 @@
+use super::equity::Equity;
+use super::pricing::Pricing;
+use super::time_frame::TimeFrameType;
+use super::trade::Position;
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::scanner::instrument::{HTFInstrument, Instrument};
+
+use dyn_clone::DynClone;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum StrategyType {
@@ -19,6 +30,14 @@ impl StrategyType {
         }
     }
 
+    pub fn is_short_only(&self) -> bool {
+        match *self {
+            StrategyType::OnlyShort => true,
+            StrategyType::OnlyShortMTF => true,
+            _ => false,
+        }
+    }
+
     pub fn is_multi_timeframe(&self) -> bool {
         match *self {
             StrategyType::OnlyLongMTF => true,
@@ -100,3 +119,216 @@ impl StrategyStats {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Signal {
+    EntryLong,
+    ExitLong,
+    EntryShort,
+    ExitShort,
+}
+
+impl Signal {
+    pub fn is_entry(&self) -> bool {
+        match *self {
+            Signal::EntryLong => true,
+            Signal::EntryShort => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_long(&self) -> bool {
+        match *self {
+            Signal::EntryLong => true,
+            Signal::ExitLong => true,
+            _ => false,
+        }
+    }
+}
+
+/// Interface implemented by the strategy crates. Positions are evaluated on
+/// the candle at `index` of the base instrument, `htf_instrument` holds the
+/// higher time frame of MTF strategies.
+pub trait Strategy: DynClone + Send + Sync {
+    fn name(&self) -> &str;
+    fn strategy_type(&self) -> &StrategyType;
+    fn time_frame(&self) -> &TimeFrameType;
+
+    fn higher_time_frame(&self) -> Option<&TimeFrameType> {
+        None
+    }
+
+    /// Base time frame first
+    fn time_frames(&self) -> Vec<TimeFrameType> {
+        let mut time_frames = vec![self.time_frame().clone()];
+        if let Some(higher_time_frame) = self.higher_time_frame() {
+            time_frames.push(higher_time_frame.clone());
+        }
+        time_frames
+    }
+
+    /// Account equity at the close of the bar, before its signals are
+    /// evaluated. Strategies sizing or halting on equity keep a copy.
+    fn update_equity(&mut self, _equity: &Equity) {}
+
+    fn entry_long(
+        &self,
+        index: usize,
+        instrument: &Instrument,
+        htf_instrument: &HTFInstrument,
+        pricing: &Pricing,
+    ) -> Position;
+
+    fn exit_long(
+        &self,
+        index: usize,
+        instrument: &Instrument,
+        htf_instrument: &HTFInstrument,
+        pricing: &Pricing,
+    ) -> Position;
+
+    fn entry_short(
+        &self,
+        _index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        Position::None
+    }
+
+    fn exit_short(
+        &self,
+        _index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        Position::None
+    }
+
+    /// Position of the signal, `Position::None` for the direction the
+    /// strategy type doesn't trade.
+    fn signal(
+        &self,
+        signal: Signal,
+        index: usize,
+        instrument: &Instrument,
+        htf_instrument: &HTFInstrument,
+        pricing: &Pricing,
+    ) -> Position {
+        let strategy_type = self.strategy_type();
+        match signal {
+            Signal::EntryLong | Signal::ExitLong if strategy_type.is_short_only() => Position::None,
+            Signal::EntryShort | Signal::ExitShort if strategy_type.is_long_only() => {
+                Position::None
+            }
+            Signal::EntryLong => self.entry_long(index, instrument, htf_instrument, pricing),
+            Signal::ExitLong => self.exit_long(index, instrument, htf_instrument, pricing),
+            Signal::EntryShort => self.entry_short(index, instrument, htf_instrument, pricing),
+            Signal::ExitShort => self.exit_short(index, instrument, htf_instrument, pricing),
+        }
+    }
+}
+
+dyn_clone::clone_trait_object!(Strategy);
+
+pub type BoxedStrategy = Box<dyn Strategy>;
+pub type StrategyFactory = fn(&StrategyConfig) -> Result<BoxedStrategy>;
+
+static STRATEGY_REGISTRY: Mutex<Option<StrategyRegistry>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StrategyConfig {
+    pub name: String,
+    pub strategy_type: StrategyType,
+    pub time_frame: TimeFrameType,
+    pub higher_time_frame: Option<TimeFrameType>,
+}
+
+impl StrategyConfig {
+    pub fn new(name: &str, strategy_type: StrategyType, time_frame: TimeFrameType) -> Self {
+        Self {
+            name: name.to_owned(),
+            strategy_type,
+            time_frame,
+            higher_time_frame: None,
+        }
+    }
+
+    pub fn higher_time_frame(mut self, val: TimeFrameType) -> Self {
+        self.higher_time_frame = Some(val);
+        self
+    }
+
+    /// Reads STRATEGY_NAME, STRATEGY_TYPE, TIME_FRAME and HIGHER_TIME_FRAME
+    pub fn from_env() -> Self {
+        Self {
+            name: env::var("STRATEGY_NAME").unwrap_or_default(),
+            strategy_type: from_str(&env::var("STRATEGY_TYPE").unwrap_or_default()),
+            time_frame: TimeFrameType::from_str(&env::var("TIME_FRAME").unwrap_or("D".to_string())),
+            higher_time_frame: env::var("HIGHER_TIME_FRAME")
+                .ok()
+                .filter(|val| !val.is_empty())
+                .map(|val| TimeFrameType::from_str(&val)),
+        }
+    }
+}
+
+/// Strategy factories by name, registered by the strategy crates.
+#[derive(Clone, Default)]
+pub struct StrategyRegistry {
+    factories: HashMap<String, StrategyFactory>,
+}
+
+impl StrategyRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registering an existing name replaces its factory.
+    pub fn register(&mut self, name: &str, factory: StrategyFactory) {
+        self.factories.insert(name.to_lowercase(), factory);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(&name.to_lowercase())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.factories.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn create(&self, config: &StrategyConfig) -> Result<BoxedStrategy> {
+        match self.factories.get(&config.name.to_lowercase()) {
+            Some(factory) => factory(config),
+            None => {
+                log::error!("Strategy {} not registered", config.name);
+                Err(RsAlgoError {
+                    err: RsAlgoErrorKind::InvalidStrategy,
+                })
+            }
+        }
+    }
+}
+
+pub fn register_strategy(name: &str, factory: StrategyFactory) {
+    let mut registry = STRATEGY_REGISTRY.lock().unwrap();
+    registry
+        .get_or_insert_with(StrategyRegistry::new)
+        .register(name, factory);
+}
+
+pub fn create_strategy(config: &StrategyConfig) -> Result<BoxedStrategy> {
+    let mut registry = STRATEGY_REGISTRY.lock().unwrap();
+    registry
+        .get_or_insert_with(StrategyRegistry::new)
+        .create(config)
+}
+
+pub fn registered_strategies() -> Vec<String> {
+    let mut registry = STRATEGY_REGISTRY.lock().unwrap();
+    registry.get_or_insert_with(StrategyRegistry::new).names()
+}
@@ -5,12 +5,12 @@ use super::{
 use crate::{
     helpers::{
         calc::get_prev_index,
-        date::{DateTime, Duration, Local},
+        date::{DateTime, Duration, Local, TimeZone},
     },
     scanner::instrument::{HTFInstrument, Instrument},
 };
 
-use chrono::Timelike;
+use chrono::{Datelike, LocalResult, NaiveDate, NaiveDateTime, Timelike};
 use serde::{Deserialize, Serialize};
 use std::env;
 
@@ -18,7 +18,7 @@ type DOHLC = (DateTime<Local>, f64, f64, f64, f64, f64);
 type DOHLCC = (DateTime<Local>, f64, f64, f64, f64, f64, bool);
 type VEC_DOHLC = Vec<DOHLC>;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum TimeFrameType {
     MN,
     W,
@@ -29,9 +29,44 @@ pub enum TimeFrameType {
     M15,
     M5,
     M1,
+    /// Non standard period in minutes (M2, M10, H2, H8...)
+    Custom(i64),
     ERR,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum WeekStart {
+    Monday,
+    Sunday,
+}
+
+impl WeekStart {
+    pub fn from_env() -> Self {
+        match env::var("WEEK_START")
+            .unwrap_or("MONDAY".to_string())
+            .to_uppercase()
+            .as_ref()
+        {
+            "SUNDAY" => WeekStart::Sunday,
+            _ => WeekStart::Monday,
+        }
+    }
+
+    /// Days elapsed since the start of the week.
+    pub fn num_days_from(&self, date: &DateTime<Local>) -> i64 {
+        match self {
+            WeekStart::Monday => date.weekday().num_days_from_monday() as i64,
+            WeekStart::Sunday => date.weekday().num_days_from_sunday() as i64,
+        }
+    }
+}
+
+impl Default for WeekStart {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TimeFrame {}
 
@@ -47,7 +82,7 @@ impl TimeFrame {
             "D" => TimeFrameType::D,
             "W" => TimeFrameType::W,
             "MN" => TimeFrameType::MN,
-            &_ => TimeFrameType::ERR,
+            &_ => TimeFrameType::from_str(time_frame),
         }
     }
 
@@ -86,7 +121,8 @@ impl TimeFrameType {
             1440 => TimeFrameType::D,
             10080 => TimeFrameType::W,
             43200 => TimeFrameType::MN,
-            _ => TimeFrameType::ERR,
+            0 => TimeFrameType::ERR,
+            _ => TimeFrameType::Custom(time_frame as i64),
         }
     }
 
@@ -101,10 +137,46 @@ impl TimeFrameType {
             "D" => TimeFrameType::D,
             "W" => TimeFrameType::W,
             "MN" => TimeFrameType::MN,
+            _ => TimeFrameType::parse_custom(time_frame),
+        }
+    }
+
+    /// "M10" or "H2" like strings
+    fn parse_custom(time_frame: &str) -> TimeFrameType {
+        let minutes = match (time_frame.strip_prefix('M'), time_frame.strip_prefix('H')) {
+            (Some(num), _) => num.parse::<i64>().ok(),
+            (_, Some(num)) => num.parse::<i64>().ok().map(|hours| hours * 60),
+            _ => None,
+        };
+
+        match minutes {
+            Some(minutes) if minutes > 0 => TimeFrameType::from_number(minutes as usize),
             _ => TimeFrameType::ERR,
         }
     }
 
+    pub fn is_custom(&self) -> bool {
+        matches!(self, TimeFrameType::Custom(_))
+    }
+
+    /// Largest standard time frame the broker can serve and that builds this one.
+    pub fn broker_time_frame(&self) -> TimeFrameType {
+        match *self {
+            TimeFrameType::Custom(minutes) => [
+                TimeFrameType::D,
+                TimeFrameType::H4,
+                TimeFrameType::H1,
+                TimeFrameType::M30,
+                TimeFrameType::M15,
+                TimeFrameType::M5,
+            ]
+            .into_iter()
+            .find(|time_frame| minutes % time_frame.to_minutes() == 0)
+            .unwrap_or(TimeFrameType::M1),
+            _ => self.clone(),
+        }
+    }
+
     pub fn to_number(&self) -> i64 {
         match *self {
             TimeFrameType::ERR => 0,
@@ -117,6 +189,7 @@ impl TimeFrameType {
             TimeFrameType::D => 1440,
             TimeFrameType::W => 10080,
             TimeFrameType::MN => 43200,
+            TimeFrameType::Custom(minutes) => minutes,
         }
     }
 
@@ -129,10 +202,13 @@ impl TimeFrameType {
             || self == &TimeFrameType::M5
             || self == &TimeFrameType::M15
             || self == &TimeFrameType::M30
+            || matches!(self, TimeFrameType::Custom(minutes) if *minutes < 60)
     }
 
     pub fn is_hourly_time_frame(&self) -> bool {
-        self == &TimeFrameType::H1 || self == &TimeFrameType::H4
+        self == &TimeFrameType::H1
+            || self == &TimeFrameType::H4
+            || matches!(self, TimeFrameType::Custom(minutes) if *minutes >= 60 && *minutes < 1440)
     }
 
     pub fn is_daily_time_frame(&self) -> bool {
@@ -151,6 +227,7 @@ impl TimeFrameType {
             TimeFrameType::D => 1440,
             TimeFrameType::W => 10080,
             TimeFrameType::MN => 43200,
+            TimeFrameType::Custom(minutes) => minutes,
         }
     }
 
@@ -162,6 +239,7 @@ impl TimeFrameType {
             TimeFrameType::D => 24,
             TimeFrameType::W => 168,
             TimeFrameType::MN => 672,
+            TimeFrameType::Custom(minutes) => minutes / 60,
             _ => 0,
         }
     }
@@ -187,6 +265,10 @@ impl TimeFrameType {
             TimeFrameType::D => vec![0],
             TimeFrameType::W => vec![0],
             TimeFrameType::MN => vec![0],
+            TimeFrameType::Custom(minutes) => match minutes < 60 {
+                true => (0..60).step_by(minutes as usize).collect(),
+                false => vec![0],
+            },
         }
     }
 
@@ -203,8 +285,12 @@ impl TimeFrameType {
             ],
             TimeFrameType::H4 => vec![0, 4, 8, 12, 16, 20],
             TimeFrameType::D => vec![0],
-            TimeFrameType::W => vec![1],
-            TimeFrameType::MN => vec![1],
+            TimeFrameType::W => vec![0],
+            TimeFrameType::MN => vec![0],
+            TimeFrameType::Custom(minutes) => match minutes >= 60 {
+                true => (0..24).step_by((minutes / 60) as usize).collect(),
+                false => vec![],
+            },
         }
     }
 }
@@ -217,12 +303,22 @@ impl std::fmt::Display for TimeFrame {
 
 impl std::fmt::Display for TimeFrameType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            TimeFrameType::Custom(minutes) if minutes % 60 == 0 => write!(f, "H{}", minutes / 60),
+            TimeFrameType::Custom(minutes) => write!(f, "M{}", minutes),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
 pub fn get_open_until(data: DOHLC, time_frame: &TimeFrameType, next: bool) -> DateTime<Local> {
     let date = data.0;
+
+    // Weeks and months don't have a fixed length
+    if matches!(time_frame, TimeFrameType::W | TimeFrameType::MN) {
+        return next_close(date, time_frame);
+    }
+
     let candle_minute = date.minute() as i64 + 1;
     let candle_hour = date.hour() as i64 + 1;
     let num_minutes = time_frame.to_minutes();
@@ -280,8 +376,13 @@ pub fn get_open_until(data: DOHLC, time_frame: &TimeFrameType, next: bool) -> Da
 }
 
 pub fn get_open_from(data: DOHLC, time_frame: &TimeFrameType, next: bool) -> DateTime<Local> {
-    let minutes_interval = time_frame.to_minutes();
-    get_open_until(data, time_frame, next) - Duration::minutes(minutes_interval)
+    let open_until = get_open_until(data, time_frame, next);
+    match time_frame {
+        TimeFrameType::W | TimeFrameType::MN => {
+            period_start(&(open_until - Duration::minutes(1)), time_frame)
+        }
+        _ => open_until - Duration::minutes(time_frame.to_minutes()),
+    }
 }
 
 pub fn adapt_to_timeframe(data: DOHLC, time_frame: &TimeFrameType, next: bool) -> DOHLCC {
@@ -313,9 +414,12 @@ pub fn adapt_to_timeframe(data: DOHLC, time_frame: &TimeFrameType, next: bool) -
         false => get_open_until(data, time_frame, next),
     };
 
-    let open_from = match time_frame.is_minutely_time_frame() {
-        true => open_until - Duration::minutes(num_minutes),
-        false => open_until - Duration::hours(num_hours),
+    let open_from = match time_frame {
+        TimeFrameType::W | TimeFrameType::MN => {
+            period_start(&(open_until - Duration::minutes(1)), time_frame)
+        }
+        _ if time_frame.is_minutely_time_frame() => open_until - Duration::minutes(num_minutes),
+        _ => open_until - Duration::hours(num_hours),
     };
 
     let is_closed = match next {
@@ -331,6 +435,327 @@ pub fn adapt_to_timeframe(data: DOHLC, time_frame: &TimeFrameType, next: bool) -
     adapted
 }
 
+/// Key of the time frame period containing the date. Candles sharing a key
+/// aggregate into the same higher time frame bar.
+pub fn period_key(date: &DateTime<Local>, time_frame: &TimeFrameType) -> i64 {
+    period_key_with(date, time_frame, WeekStart::from_env())
+}
+
+/// `period_key` with weeks starting on `week_start` instead of WEEK_START.
+pub fn period_key_with(
+    date: &DateTime<Local>,
+    time_frame: &TimeFrameType,
+    week_start: WeekStart,
+) -> i64 {
+    match time_frame {
+        TimeFrameType::MN => date.year() as i64 * 100 + date.month() as i64,
+        TimeFrameType::W => {
+            period_start_with(date, time_frame, week_start).num_days_from_ce() as i64
+        }
+        TimeFrameType::D => date.num_days_from_ce() as i64,
+        TimeFrameType::ERR => 0,
+        _ => date.timestamp() / 60 / time_frame.to_minutes(),
+    }
+}
+
+/// Opening date of the time frame period containing the date.
+pub fn period_start(date: &DateTime<Local>, time_frame: &TimeFrameType) -> DateTime<Local> {
+    period_start_with(date, time_frame, WeekStart::from_env())
+}
+
+/// `period_start` with weeks starting on `week_start` instead of WEEK_START.
+pub fn period_start_with(
+    date: &DateTime<Local>,
+    time_frame: &TimeFrameType,
+    week_start: WeekStart,
+) -> DateTime<Local> {
+    let midnight = |date: DateTime<Local>| date.date().and_hms(0, 0, 0);
+
+    match time_frame {
+        TimeFrameType::MN => midnight(*date - Duration::days(date.day() as i64 - 1)),
+        TimeFrameType::W => midnight(*date - Duration::days(week_start.num_days_from(date))),
+        TimeFrameType::D => midnight(*date),
+        TimeFrameType::ERR => *date,
+        _ => {
+            let seconds = time_frame.to_minutes() * 60;
+            Local.timestamp(date.timestamp() - date.timestamp().rem_euclid(seconds), 0)
+        }
+    }
+}
+
+/// Builds higher time frame bars from lower time frame ones (M1 by default).
+/// A bar is emitted as soon as its period is complete, either because the
+/// last constituent bar reached the boundary or because a bar of the next
+/// period arrived.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimeFrameAggregator {
+    time_frame: TimeFrameType,
+    base_time_frame: TimeFrameType,
+    key: i64,
+    current: Option<DOHLC>,
+    #[serde(default)]
+    week_start: WeekStart,
+}
+
+impl TimeFrameAggregator {
+    pub fn new(time_frame: TimeFrameType) -> Self {
+        Self::with_base(time_frame, TimeFrameType::M1)
+    }
+
+    pub fn with_base(time_frame: TimeFrameType, base_time_frame: TimeFrameType) -> Self {
+        Self {
+            time_frame,
+            base_time_frame,
+            key: 0,
+            current: None,
+            week_start: WeekStart::from_env(),
+        }
+    }
+
+    pub fn week_start(mut self, val: WeekStart) -> Self {
+        self.week_start = val;
+        self
+    }
+
+    pub fn time_frame(&self) -> &TimeFrameType {
+        &self.time_frame
+    }
+
+    /// Bar being built, not closed yet.
+    pub fn current(&self) -> Option<DOHLC> {
+        self.current
+    }
+
+    /// Adds a base bar and returns the closed higher time frame bars, at
+    /// most two when a bar of a new period also completes it.
+    pub fn next(&mut self, bar: DOHLC) -> Vec<DOHLC> {
+        let (date, open, high, low, close, volume) = bar;
+        let key = period_key_with(&date, &self.time_frame, self.week_start);
+        let mut closed = vec![];
+
+        match self.current.as_mut() {
+            Some(current) if self.key == key => {
+                current.2 = current.2.max(high);
+                current.3 = current.3.min(low);
+                current.4 = close;
+                current.5 += volume;
+            }
+            _ => {
+                if let Some(current) = self.current.take() {
+                    closed.push(current);
+                }
+                self.key = key;
+                self.current = Some((
+                    period_start_with(&date, &self.time_frame, self.week_start),
+                    open,
+                    high,
+                    low,
+                    close,
+                    volume,
+                ));
+            }
+        };
+
+        let bar_end = next_close_with(date, &self.base_time_frame, self.week_start);
+        if period_key_with(&bar_end, &self.time_frame, self.week_start) != key {
+            if let Some(current) = self.current.take() {
+                closed.push(current);
+            }
+        }
+
+        closed
+    }
+
+    /// Returns the open bar and starts over.
+    pub fn flush(&mut self) -> Option<DOHLC> {
+        self.current.take()
+    }
+
+    /// Aggregates a whole series. The last bar is flagged as not closed when
+    /// its period is incomplete.
+    pub fn aggregate(time_frame: &TimeFrameType, data: &[DOHLC]) -> Vec<DOHLCC> {
+        Self::aggregate_from(time_frame, &TimeFrameType::M1, data)
+    }
+
+    pub fn aggregate_from(
+        time_frame: &TimeFrameType,
+        base_time_frame: &TimeFrameType,
+        data: &[DOHLC],
+    ) -> Vec<DOHLCC> {
+        Self::with_base(time_frame.clone(), base_time_frame.clone()).aggregate_series(data)
+    }
+
+    /// Aggregates a whole series with this aggregator settings.
+    pub fn aggregate_series(mut self, data: &[DOHLC]) -> Vec<DOHLCC> {
+        let mut bars: Vec<DOHLCC> = vec![];
+
+        for bar in data {
+            for (date, open, high, low, close, volume) in self.next(*bar) {
+                bars.push((date, open, high, low, close, volume, true));
+            }
+        }
+
+        if let Some((date, open, high, low, close, volume)) = self.flush() {
+            bars.push((date, open, high, low, close, volume, false));
+        }
+
+        bars
+    }
+}
+
+/// Builds custom time frame bars from the broker time frame data. Standard
+/// time frames are returned untouched.
+pub fn from_broker_time_frame(time_frame: &TimeFrameType, data: VEC_DOHLC) -> VEC_DOHLC {
+    match time_frame.is_custom() {
+        true => {
+            TimeFrameAggregator::aggregate_from(time_frame, &time_frame.broker_time_frame(), &data)
+                .into_iter()
+                .map(|(date, open, high, low, close, volume, _)| {
+                    (date, open, high, low, close, volume)
+                })
+                .collect()
+        }
+        false => data,
+    }
+}
+
+/// Closing date of the time frame period the date belongs to. Dates on a
+/// boundary return the next one.
+///
+/// Intraday periods align to the local wall clock, as `closing_minutes` and
+/// `closing_hours` do for live candles, so H4 closes at 0, 4, 8, 12, 16 and
+/// 20h local time. On DST changes the affected period is one hour shorter or
+/// longer, boundaries falling in the skipped hour close when it ends.
+pub fn next_close(date: DateTime<Local>, time_frame: &TimeFrameType) -> DateTime<Local> {
+    next_close_with(date, time_frame, WeekStart::from_env())
+}
+
+/// `next_close` with weeks starting on `week_start` instead of WEEK_START.
+pub fn next_close_with(
+    date: DateTime<Local>,
+    time_frame: &TimeFrameType,
+    week_start: WeekStart,
+) -> DateTime<Local> {
+    let minutes = time_frame.to_minutes();
+    let naive = date.naive_local();
+    let midnight = naive.date().and_hms(0, 0, 0);
+
+    let candidates: Vec<NaiveDateTime> = match time_frame {
+        TimeFrameType::ERR => return date,
+        TimeFrameType::MN => {
+            let (year, month) = match naive.month() {
+                12 => (naive.year() + 1, 1),
+                month => (naive.year(), month + 1),
+            };
+            vec![NaiveDate::from_ymd(year, month, 1).and_hms(0, 0, 0)]
+        }
+        TimeFrameType::W => {
+            let days = 7 - week_start.num_days_from(&date);
+            vec![midnight + Duration::days(days)]
+        }
+        TimeFrameType::D => vec![midnight + Duration::days(1)],
+        _ if minutes >= 1440 => {
+            return period_start_with(&date, time_frame, week_start) + Duration::minutes(minutes)
+        }
+        _ => {
+            let elapsed = naive.hour() as i64 * 60 + naive.minute() as i64;
+            let start = elapsed - elapsed % minutes;
+            // Ambiguous wall times can repeat the current period start later on
+            (0..3)
+                .map(|i| start + i * minutes)
+                .map(|val| midnight + Duration::minutes(val.min(1440)))
+                .collect()
+        }
+    };
+
+    candidates
+        .iter()
+        .flat_map(|naive| from_wall_clock(naive))
+        .filter(|close| close > &date)
+        .min()
+        .unwrap_or_else(|| date + Duration::minutes(minutes))
+}
+
+/// Every instant showing the wall clock time. Times skipped by DST resolve to
+/// the end of the gap.
+fn from_wall_clock(naive: &NaiveDateTime) -> Vec<DateTime<Local>> {
+    match Local.from_local_datetime(naive) {
+        LocalResult::Single(date) => vec![date],
+        LocalResult::Ambiguous(first, second) => vec![first, second],
+        LocalResult::None => Local
+            .from_local_datetime(&(*naive + Duration::hours(1)))
+            .earliest()
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Fires at every candle close of the time frame, `delay` after the boundary
+/// so the broker has time to publish the closed bar.
+#[cfg(feature = "broker")]
+#[derive(Debug, Clone)]
+pub struct CandleClock {
+    time_frame: TimeFrameType,
+    delay: Duration,
+    last_close: Option<DateTime<Local>>,
+}
+
+#[cfg(feature = "broker")]
+impl CandleClock {
+    pub fn new(time_frame: TimeFrameType) -> Self {
+        Self {
+            time_frame,
+            delay: Duration::milliseconds(
+                env::var("CANDLE_CLOCK_DELAY")
+                    .ok()
+                    .and_then(|val| val.parse::<i64>().ok())
+                    .unwrap_or(0),
+            ),
+            last_close: None,
+        }
+    }
+
+    pub fn delay(mut self, val: Duration) -> Self {
+        self.delay = val;
+        self
+    }
+
+    pub fn time_frame(&self) -> &TimeFrameType {
+        &self.time_frame
+    }
+
+    /// Waits for the next close and returns its date. A boundary is never
+    /// returned twice, even if the system clock goes back.
+    pub async fn tick(&mut self) -> DateTime<Local> {
+        let from = match self.last_close {
+            Some(last_close) if last_close > Local::now() => last_close,
+            _ => Local::now(),
+        };
+        let close = next_close(from, &self.time_frame);
+
+        loop {
+            let wait = close + self.delay - Local::now();
+            match wait.to_std() {
+                Ok(wait) if !wait.is_zero() => tokio::time::sleep(wait).await,
+                _ => break,
+            }
+        }
+
+        self.last_close = Some(close);
+        close
+    }
+
+    pub fn stream(self) -> futures_util::stream::BoxStream<'static, DateTime<Local>> {
+        use futures_util::StreamExt;
+
+        futures_util::stream::unfold(self, |mut clock| async move {
+            let close = clock.tick().await;
+            Some((close, clock))
+        })
+        .boxed()
+    }
+}
+
 fn get_htf_indexes<'a>(
     index: usize,
     instrument: &'a Instrument,
@@ -1,11 +1,17 @@
 use std::env;
 
+use super::config::ConfigUpdate;
+use super::currency::CurrencyConverter;
 use super::mode::{self, ExecutionMode};
 use super::order::{Order, OrderType};
+use super::position_sizer::Sizing;
 use super::pricing::Pricing;
+use super::risk::Risk;
+use super::slippage::SlippageModel;
+use super::spread::SpreadSchedule;
 use crate::helpers::calc;
 use crate::helpers::date::*;
-use crate::helpers::uuid;
+use crate::helpers::uuid::IdGenerator;
 use crate::scanner::instrument::*;
 
 use serde::{Deserialize, Serialize};
@@ -71,9 +77,38 @@ pub enum PositionResult {
     PendingOrder(Vec<Order>),
     MarketInOrder(TradeResult, Order),
     MarketOutOrder(TradeResult, Order),
+    ScaleIn(TradeResult, Option<Vec<Order>>),
+    MarketOutEntry(TradeResult, ExitTarget),
+    MarketFlip(TradeResult, TradeResult),
     None,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PositionMode {
+    Hedging,
+    Netting,
+}
+
+impl PositionMode {
+    pub fn from_env() -> Self {
+        let mode = env::var("POSITION_MODE").unwrap_or("netting".to_string());
+        match mode.as_ref() {
+            "hedging" => PositionMode::Hedging,
+            _ => PositionMode::Netting,
+        }
+    }
+
+    pub fn is_hedging(&self) -> bool {
+        *self == PositionMode::Hedging
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ExitTarget {
+    Entry(usize),
+    Aggregate,
+}
+
 impl TradeType {
     pub fn is_entry(&self) -> bool {
         match *self {
@@ -168,6 +203,79 @@ pub fn type_from_str(trade_type: &str) -> TradeType {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CloseReason {
+    Signal,
+    StopLoss,
+    TakeProfit,
+    ExitOrder,
+    Expiry,
+    Manual,
+    EndOfData,
+    Unknown,
+}
+
+impl Default for CloseReason {
+    fn default() -> Self {
+        CloseReason::Unknown
+    }
+}
+
+impl CloseReason {
+    pub fn from_exit(trade_type: &TradeType, order: Option<&Order>) -> Self {
+        if trade_type.is_stop() {
+            return CloseReason::StopLoss;
+        }
+
+        match order {
+            Some(order) => match order.order_type {
+                OrderType::StopLossLong(_, _) | OrderType::StopLossShort(_, _) => {
+                    CloseReason::StopLoss
+                }
+                OrderType::TakeProfitLong(_, _, _) | OrderType::TakeProfitShort(_, _, _) => {
+                    CloseReason::TakeProfit
+                }
+                _ => CloseReason::ExitOrder,
+            },
+            None => CloseReason::Signal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CloseReasonStats {
+    pub close_reason: CloseReason,
+    pub trades: usize,
+    pub wining_trades: usize,
+    pub profit: f64,
+}
+
+pub fn close_reason_breakdown(trades_out: &Vec<TradeOut>) -> Vec<CloseReasonStats> {
+    let mut stats: Vec<CloseReasonStats> = vec![];
+
+    for trade_out in trades_out {
+        let wining_trade = (trade_out.profit > 0.) as usize;
+        match stats
+            .iter_mut()
+            .find(|x| x.close_reason == trade_out.close_reason)
+        {
+            Some(stat) => {
+                stat.trades += 1;
+                stat.wining_trades += wining_trade;
+                stat.profit += trade_out.profit;
+            }
+            None => stats.push(CloseReasonStats {
+                close_reason: trade_out.close_reason.clone(),
+                trades: 1,
+                wining_trades: wining_trade,
+                profit: trade_out.profit,
+            }),
+        }
+    }
+
+    stats
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TradeResult {
     TradeIn(TradeIn),
@@ -186,6 +294,12 @@ pub struct TradeIn {
     pub spread: f64,
     pub date_in: DbDateTime,
     pub trade_type: TradeType,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub risk: Risk,
+    #[serde(default)]
+    pub risk_amount: f64,
 }
 
 impl Trade for TradeIn {
@@ -224,6 +338,10 @@ pub struct TradeOut {
     pub run_up_per: f64,
     pub draw_down: f64,
     pub draw_down_per: f64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub close_reason: CloseReason,
 }
 
 impl Trade for TradeOut {
@@ -241,6 +359,185 @@ impl Trade for TradeOut {
     }
 }
 
+impl TradeIn {
+    pub fn has_tag(&self, val: &str) -> bool {
+        self.tags.iter().any(|tag| tag == val)
+    }
+}
+
+impl TradeOut {
+    pub fn has_tag(&self, val: &str) -> bool {
+        self.tags.iter().any(|tag| tag == val)
+    }
+}
+
+/// Quantity of the trade size and the money at risk to the order stop
+pub fn calculate_trade_quantity(
+    order: Option<&Order>,
+    trade_size: f64,
+    price_in: f64,
+) -> (f64, Risk, f64) {
+    let quantity = calc::calculate_quantity(trade_size, price_in);
+    match order {
+        Some(order) if order.stop_price > 0. => {
+            let risk_amount = quantity * (price_in - order.stop_price).abs();
+            (quantity, order.risk().clone(), risk_amount)
+        }
+        _ => (quantity, Risk::None, 0.),
+    }
+}
+
+pub fn tags_from_env() -> Vec<String> {
+    match env::var("TRADE_TAGS") {
+        Ok(tags) => tags
+            .split(',')
+            .map(|tag| tag.trim().to_owned())
+            .filter(|tag| !tag.is_empty())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+pub fn filter_trades_by_tag(trades: &Vec<TradeOut>, tag: &str) -> Vec<TradeOut> {
+    trades.iter().filter(|x| x.has_tag(tag)).cloned().collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PositionManager {
+    max_entries: usize,
+    entries: Vec<TradeIn>,
+}
+
+impl PositionManager {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            entries: vec![],
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let max_entries = env::var("MAX_POSITION_ENTRIES")
+            .unwrap_or("1".to_string())
+            .parse::<usize>()
+            .unwrap();
+        Self::new(max_entries)
+    }
+
+    pub fn entries(&self) -> &Vec<TradeIn> {
+        &self.entries
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    pub fn is_long(&self) -> bool {
+        match self.entries.first() {
+            Some(entry) => entry.trade_type.is_long(),
+            None => false,
+        }
+    }
+
+    pub fn can_scale_in(&self, trade_type: &TradeType) -> bool {
+        match self.entries.first() {
+            Some(entry) => {
+                self.entries.len() < self.max_entries
+                    && entry.trade_type.is_long() == trade_type.is_long()
+            }
+            None => true,
+        }
+    }
+
+    /// Adds a new entry when it has the direction of the open position and
+    /// the max number of entries is not reached.
+    pub fn add(&mut self, trade_in: TradeIn) -> bool {
+        match self.can_scale_in(&trade_in.trade_type) {
+            true => {
+                self.entries.push(trade_in);
+                true
+            }
+            false => false,
+        }
+    }
+
+    pub fn total_quantity(&self) -> f64 {
+        self.entries.iter().map(|x| x.quantity).sum()
+    }
+
+    pub fn average_price(&self) -> f64 {
+        let quantity = self.total_quantity();
+        match quantity > 0. {
+            true => {
+                self.entries
+                    .iter()
+                    .map(|x| x.price_in * x.quantity)
+                    .sum::<f64>()
+                    / quantity
+            }
+            false => 0.,
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&TradeIn> {
+        self.entries.iter().find(|x| x.id == id)
+    }
+
+    /// Synthetic entry holding the aggregated quantity at the average price,
+    /// anchored on the first entry.
+    pub fn aggregate(&self) -> Option<TradeIn> {
+        let first = self.entries.first()?;
+        let quantity = self.total_quantity();
+        let price_in = self.average_price();
+        let mut tags: Vec<String> = vec![];
+        for tag in self.entries.iter().flat_map(|x| x.tags.iter()) {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
+        Some(TradeIn {
+            quantity,
+            price_in,
+            origin_price: price_in,
+            ask: match first.trade_type.is_long() {
+                true => price_in,
+                false => first.ask,
+            },
+            risk_amount: self.entries.iter().map(|x| x.risk_amount).sum(),
+            tags,
+            ..first.clone()
+        })
+    }
+
+    /// Entry an exit has to be resolved against.
+    pub fn resolve_exit(&self, target: &ExitTarget) -> Option<TradeIn> {
+        match target {
+            ExitTarget::Entry(id) => self.get(*id).cloned(),
+            ExitTarget::Aggregate => self.aggregate(),
+        }
+    }
+
+    /// Removes the entries closed by the exit and returns them.
+    pub fn close(&mut self, target: &ExitTarget) -> Vec<TradeIn> {
+        match target {
+            ExitTarget::Entry(id) => {
+                let (closed, open): (Vec<TradeIn>, Vec<TradeIn>) =
+                    self.entries.drain(..).partition(|x| x.id == *id);
+                self.entries = open;
+                closed
+            }
+            ExitTarget::Aggregate => self.entries.drain(..).collect(),
+        }
+    }
+}
+
+impl Default for PositionManager {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
 impl std::fmt::Display for TradeIn {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{:?}", self)
@@ -253,23 +550,38 @@ impl std::fmt::Display for TradeOut {
     }
 }
 
+/// The size of the entry comes from the sizer at the entry price and the
+/// stop of the order, capped by the order size
 pub fn resolve_trade_in(
     index: usize,
-    trade_size: f64,
+    sizing: Sizing,
     instrument: &Instrument,
     pricing: &Pricing,
     trade_type: &TradeType,
     order: Option<&Order>,
+    ids: &mut IdGenerator,
 ) -> TradeResult {
     let execution_mode = mode::from_str(&env::var("EXECUTION_MODE").unwrap());
     let order_engine = &env::var("ORDER_ENGINE").unwrap();
     let index = calculate_trade_index(index, order, &execution_mode);
 
     if trade_type.is_entry() {
-        let spread = pricing.spread();
+        let spread = match execution_mode.is_back_test() {
+            true => SpreadSchedule::from_env().spread(index, instrument, pricing),
+            false => pricing.spread(),
+        };
+
+        if let Some(max_spread) = ConfigUpdate::current().max_spread {
+            let spread_pips = calc::from_pips(spread, pricing);
+            if spread_pips > max_spread {
+                log::warn!("Spread {} over max spread {}", spread_pips, max_spread);
+                return TradeResult::None;
+            }
+        }
+
         let current_candle = instrument.data.get(index).unwrap();
         let current_date = current_candle.date();
-        let id = uuid::generate_ts_id(current_date);
+        let id = ids.next(current_date);
 
         let price = match order_engine.as_ref() {
             "broker" => match order {
@@ -279,6 +591,17 @@ pub fn resolve_trade_in(
             _ => current_candle.open(),
         };
 
+        let price = match execution_mode.is_back_test() {
+            true => SlippageModel::from_env().apply(
+                index,
+                instrument,
+                pricing,
+                price,
+                trade_type.is_long(),
+            ),
+            false => price,
+        };
+
         let ask = match trade_type.is_long() {
             true => price + spread,
             false => price,
@@ -289,13 +612,35 @@ pub fn resolve_trade_in(
             false => price,
         };
 
-        let quantity = calc::calculate_quantity(trade_size, price_in);
+        let stop_price = order
+            .map(|order| order.stop_price)
+            .filter(|stop_price| *stop_price > 0.);
+
+        let trade_size = match sizing.size(index, instrument, price_in, stop_price) {
+            Some(trade_size) => trade_size,
+            None => {
+                log::warn!("Can't size {:?} at {}", trade_type, price_in);
+                return TradeResult::None;
+            }
+        };
+
+        let trade_size = match order {
+            Some(order) => trade_size.min(order.size()),
+            None => trade_size,
+        };
+
+        let (quantity, risk, risk_amount) = calculate_trade_quantity(order, trade_size, price_in);
 
         let index_in = match execution_mode.is_back_test() {
             true => index,
             false => id,
         };
 
+        let tags = match order {
+            Some(order) => order.tags().clone(),
+            None => tags_from_env(),
+        };
+
         TradeResult::TradeIn(TradeIn {
             id,
             index_in,
@@ -306,6 +651,9 @@ pub fn resolve_trade_in(
             quantity,
             date_in: to_dbtime(current_date),
             trade_type: trade_type.clone(),
+            tags,
+            risk,
+            risk_amount,
         })
     } else {
         TradeResult::None
@@ -319,10 +667,10 @@ pub fn resolve_trade_out(
     trade_in: &TradeIn,
     trade_type: &TradeType,
     order: Option<&Order>,
+    ids: &mut IdGenerator,
 ) -> TradeResult {
     let quantity = trade_in.quantity;
     let data = &instrument.data;
-    let spread = pricing.spread();
     let trade_in_type = &trade_in.trade_type;
     let index_in = trade_in.index_in;
     let spread_in = trade_in.spread;
@@ -334,6 +682,10 @@ pub fn resolve_trade_out(
     let order_engine = &env::var("ORDER_ENGINE").unwrap();
 
     let index = calculate_trade_index(index, order, &execution_mode);
+    let spread = match execution_mode.is_back_test() {
+        true => SpreadSchedule::from_env().spread(index, instrument, pricing),
+        false => pricing.spread(),
+    };
     let current_candle = instrument.data.get(index).unwrap();
     let current_date = current_candle.date();
     let price_origin = *trade_in.get_price_in();
@@ -351,6 +703,17 @@ pub fn resolve_trade_out(
         _ => close_trade_price,
     };
 
+    let price_out = match execution_mode.is_back_test() {
+        true => SlippageModel::from_env().apply(
+            index,
+            instrument,
+            pricing,
+            price_out,
+            !trade_in_type.is_long(),
+        ),
+        false => price_out,
+    };
+
     let (price_in, price_out) = match execution_mode.is_back_test() {
         true => match trade_in_type.is_long() {
             true => (trade_in.price_in, price_out),
@@ -398,7 +761,13 @@ pub fn resolve_trade_out(
         };
 
         let profit = match execution_mode.is_back_test() {
-            true => calc::calculate_profit(quantity, price_in, price_out, trade_in_type),
+            true => calc::calculate_profit_in_account(
+                quantity,
+                price_in,
+                price_out,
+                trade_in_type,
+                CurrencyConverter::from_env().rate(instrument.symbol()),
+            ),
             false => 0.,
         };
 
@@ -428,7 +797,7 @@ pub fn resolve_trade_out(
         };
 
         TradeResult::TradeOut(TradeOut {
-            id: uuid::generate_ts_id(current_date),
+            id: ids.next(current_date),
             index_in,
             price_in,
             trade_type: trade_type.clone(),
@@ -439,7 +808,7 @@ pub fn resolve_trade_out(
             price_origin,
             price_out,
             bid,
-            spread_out: pricing.spread(),
+            spread_out: spread,
             date_out,
             profit,
             profit_per,
@@ -447,6 +816,8 @@ pub fn resolve_trade_out(
             run_up_per,
             draw_down,
             draw_down_per,
+            tags: trade_in.tags.clone(),
+            close_reason: CloseReason::from_exit(trade_type, order),
         })
     } else {
         log::warn!("Non profitable {:?} exit", trade_type);
@@ -454,6 +825,82 @@ pub fn resolve_trade_out(
     }
 }
 
+/// Closes the open trade and opens the opposite one on the same bar. Both
+/// legs are returned or none of them.
+pub fn resolve_flip(
+    index: usize,
+    sizing: Sizing,
+    instrument: &Instrument,
+    pricing: &Pricing,
+    trade_in: &TradeIn,
+    trade_type: &TradeType,
+    ids: &mut IdGenerator,
+) -> Option<(TradeOut, TradeIn)> {
+    let exit_type = match trade_in.trade_type.is_long() {
+        true => TradeType::MarketOutLong,
+        false => TradeType::MarketOutShort,
+    };
+
+    let trade_out =
+        match resolve_trade_out(index, instrument, pricing, trade_in, &exit_type, None, ids) {
+            TradeResult::TradeOut(trade_out) => trade_out,
+            _ => {
+                log::warn!(
+                    "Can't flip {:?}, exit was not resolved",
+                    trade_in.trade_type
+                );
+                return None;
+            }
+        };
+
+    match resolve_trade_in(index, sizing, instrument, pricing, trade_type, None, ids) {
+        TradeResult::TradeIn(new_trade_in) => Some((trade_out, new_trade_in)),
+        _ => None,
+    }
+}
+
+/// Resolves an entry signal against the open trade. In netting mode an
+/// opposite signal flips the position, in hedging mode it opens a new one.
+pub fn resolve_position_mode(
+    index: usize,
+    sizing: Sizing,
+    instrument: &Instrument,
+    pricing: &Pricing,
+    mode: &PositionMode,
+    open_trade: Option<&TradeIn>,
+    trade_type: &TradeType,
+    ids: &mut IdGenerator,
+) -> PositionResult {
+    let is_opposite = match open_trade {
+        Some(trade_in) => trade_in.trade_type.is_long() != trade_type.is_long(),
+        None => false,
+    };
+
+    match (is_opposite, mode) {
+        (true, PositionMode::Netting) => {
+            match resolve_flip(
+                index,
+                sizing,
+                instrument,
+                pricing,
+                open_trade.unwrap(),
+                trade_type,
+                ids,
+            ) {
+                Some((trade_out, trade_in)) => PositionResult::MarketFlip(
+                    TradeResult::TradeOut(trade_out),
+                    TradeResult::TradeIn(trade_in),
+                ),
+                None => PositionResult::None,
+            }
+        }
+        _ => PositionResult::MarketIn(
+            resolve_trade_in(index, sizing, instrument, pricing, trade_type, None, ids),
+            None,
+        ),
+    }
+}
+
 pub fn calculate_trade_index(
     index: usize,
     order: Option<&Order>,
@@ -0,0 +1,191 @@
+use crate::helpers::maxima_minima::maxima_minima_exp;
+use crate::scanner::pattern::{lookback_from, lookback_from_env, lookback_points};
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum DivergenceKind {
+    /// Price lower low, indicator higher low
+    RegularBullish,
+    /// Price higher high, indicator lower high
+    RegularBearish,
+    /// Price higher low, indicator lower low
+    HiddenBullish,
+    /// Price lower high, indicator higher high
+    HiddenBearish,
+}
+
+impl DivergenceKind {
+    pub fn is_bullish(&self) -> bool {
+        matches!(self, Self::RegularBullish | Self::HiddenBullish)
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        matches!(self, Self::HiddenBullish | Self::HiddenBearish)
+    }
+}
+
+impl std::fmt::Display for DivergenceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Two consecutive price peaks and the indicator peaks matched to them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DivergenceEvent {
+    pub kind: DivergenceKind,
+    pub price_from: (usize, f64),
+    pub price_to: (usize, f64),
+    pub indicator_from: (usize, f64),
+    pub indicator_to: (usize, f64),
+}
+
+impl DivergenceEvent {
+    pub fn from(&self) -> usize {
+        self.price_from.0
+    }
+
+    pub fn to(&self) -> usize {
+        self.price_to.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DivergenceConfig {
+    /// Min prominence of the indicator peaks
+    pub prominence: f64,
+    pub min_distance: usize,
+    /// Max bars between a price peak and its indicator peak
+    pub max_lag: usize,
+    /// Max bars between the two anchors
+    pub max_bars: usize,
+    /// Only price peaks in the last bars, the whole data when not set
+    pub lookback: Option<usize>,
+}
+
+impl DivergenceConfig {
+    pub fn from_env() -> Self {
+        Self {
+            prominence: env::var("DIVERGENCE_MIN_PROMINENCE")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(5.),
+            min_distance: env::var("DIVERGENCE_PROMINENCE_MIN_DISTANCE")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(3),
+            max_lag: env::var("DIVERGENCE_MAX_LAG")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(3),
+            max_bars: env::var("DIVERGENCE_MAX_BARS")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(60),
+            lookback: lookback_from_env("DIVERGENCE_LOOKBACK"),
+        }
+    }
+}
+
+impl Default for DivergenceConfig {
+    fn default() -> Self {
+        Self {
+            prominence: 5.,
+            min_distance: 3,
+            max_lag: 3,
+            max_bars: 60,
+            lookback: None,
+        }
+    }
+}
+
+/// Maxima and minima of an indicator series.
+pub fn indicator_peaks(
+    values: &[f64],
+    config: &DivergenceConfig,
+) -> (Vec<(usize, f64)>, Vec<(usize, f64)>) {
+    let values = values.to_vec();
+    let inverted: Vec<f64> = values.iter().map(|x| -x).collect();
+
+    let maxima = maxima_minima_exp(&values, &values, config.prominence, config.min_distance)
+        .unwrap_or_default();
+    let minima = maxima_minima_exp(&inverted, &values, config.prominence, config.min_distance)
+        .unwrap_or_default();
+
+    (maxima, minima)
+}
+
+/// Compares every pair of consecutive price peaks with the indicator peaks
+/// closest to them. Events are sorted by the second anchor.
+pub fn detect_divergences(
+    price_maxima: &[(usize, f64)],
+    price_minima: &[(usize, f64)],
+    indicator: &[f64],
+    config: &DivergenceConfig,
+) -> Vec<DivergenceEvent> {
+    let (indicator_maxima, indicator_minima) = indicator_peaks(indicator, config);
+    let from = lookback_from(indicator.len(), config.lookback);
+    let price_maxima = &lookback_points(price_maxima, from);
+    let price_minima = &lookback_points(price_minima, from);
+
+    let mut events = [
+        compare(price_maxima, &indicator_maxima, false, config),
+        compare(price_minima, &indicator_minima, true, config),
+    ]
+    .concat();
+
+    events.sort_by(|a, b| a.to().cmp(&b.to()));
+    events
+}
+
+fn compare(
+    price_peaks: &[(usize, f64)],
+    indicator_peaks: &[(usize, f64)],
+    lows: bool,
+    config: &DivergenceConfig,
+) -> Vec<DivergenceEvent> {
+    price_peaks
+        .windows(2)
+        .filter(|pair| pair[1].0 - pair[0].0 <= config.max_bars)
+        .filter_map(|pair| {
+            let (price_from, price_to) = (pair[0], pair[1]);
+            let indicator_from = nearest_peak(indicator_peaks, price_from.0, config.max_lag)?;
+            let indicator_to = nearest_peak(indicator_peaks, price_to.0, config.max_lag)?;
+
+            if indicator_from.0 >= indicator_to.0 {
+                return None;
+            }
+
+            let price_higher = price_to.1 > price_from.1;
+            let price_lower = price_to.1 < price_from.1;
+            let indicator_higher = indicator_to.1 > indicator_from.1;
+            let indicator_lower = indicator_to.1 < indicator_from.1;
+
+            let kind = match lows {
+                true if price_lower && indicator_higher => DivergenceKind::RegularBullish,
+                true if price_higher && indicator_lower => DivergenceKind::HiddenBullish,
+                false if price_higher && indicator_lower => DivergenceKind::RegularBearish,
+                false if price_lower && indicator_higher => DivergenceKind::HiddenBearish,
+                _ => return None,
+            };
+
+            Some(DivergenceEvent {
+                kind,
+                price_from,
+                price_to,
+                indicator_from,
+                indicator_to,
+            })
+        })
+        .collect()
+}
+
+fn nearest_peak(peaks: &[(usize, f64)], index: usize, max_lag: usize) -> Option<(usize, f64)> {
+    peaks
+        .iter()
+        .filter(|(peak_index, _)| peak_index.abs_diff(index) <= max_lag)
+        .min_by_key(|(peak_index, _)| peak_index.abs_diff(index))
+        .copied()
+}
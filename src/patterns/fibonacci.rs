@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+pub const RETRACEMENTS: [f64; 3] = [0.382, 0.5, 0.618];
+pub const EXTENSIONS: [f64; 2] = [1.272, 1.618];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum FibonacciLevelType {
+    Retracement,
+    Extension,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FibonacciLevel {
+    /// Ratio as percentage, like `61.8%` or `50.0%`
+    pub name: String,
+    pub level_type: FibonacciLevelType,
+    pub ratio: f64,
+    pub price: f64,
+}
+
+/// Move between the last peak of each side, `from` being the oldest one.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Swing {
+    pub from: (usize, f64),
+    pub to: (usize, f64),
+}
+
+impl Swing {
+    pub fn is_up(&self) -> bool {
+        self.to.1 > self.from.1
+    }
+
+    pub fn range(&self) -> f64 {
+        (self.to.1 - self.from.1).abs()
+    }
+
+    /// Retracements go back from the swing end, extensions project from
+    /// its start beyond the end.
+    pub fn price_at(&self, level_type: FibonacciLevelType, ratio: f64) -> f64 {
+        let direction = match self.is_up() {
+            true => 1.,
+            false => -1.,
+        };
+        match level_type {
+            FibonacciLevelType::Retracement => self.to.1 - direction * ratio * self.range(),
+            FibonacciLevelType::Extension => self.from.1 + direction * ratio * self.range(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct Fibonacci {
+    swing: Option<Swing>,
+    levels: Vec<FibonacciLevel>,
+}
+
+impl Fibonacci {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Levels of the latest swing. Extrema peaks are used when available,
+    /// falling back to the local ones.
+    pub fn from_peaks(
+        extrema_maxima: &[(usize, f64)],
+        extrema_minima: &[(usize, f64)],
+        local_maxima: &[(usize, f64)],
+        local_minima: &[(usize, f64)],
+    ) -> Self {
+        let (maxima, minima) = match extrema_maxima.is_empty() || extrema_minima.is_empty() {
+            true => (local_maxima, local_minima),
+            false => (extrema_maxima, extrema_minima),
+        };
+
+        match (maxima.last(), minima.last()) {
+            (Some(max), Some(min)) if max.1 != min.1 => {
+                let swing = match max.0 > min.0 {
+                    true => Swing {
+                        from: *min,
+                        to: *max,
+                    },
+                    false => Swing {
+                        from: *max,
+                        to: *min,
+                    },
+                };
+                Self::from_swing(swing)
+            }
+            _ => Self::new(),
+        }
+    }
+
+    pub fn from_swing(swing: Swing) -> Self {
+        let levels = RETRACEMENTS
+            .iter()
+            .map(|ratio| (FibonacciLevelType::Retracement, *ratio))
+            .chain(
+                EXTENSIONS
+                    .iter()
+                    .map(|ratio| (FibonacciLevelType::Extension, *ratio)),
+            )
+            .map(|(level_type, ratio)| FibonacciLevel {
+                name: format!("{:.1}%", ratio * 100.),
+                level_type,
+                ratio,
+                price: swing.price_at(level_type, ratio),
+            })
+            .collect();
+
+        Self {
+            swing: Some(swing),
+            levels,
+        }
+    }
+
+    pub fn swing(&self) -> Option<&Swing> {
+        self.swing.as_ref()
+    }
+
+    pub fn levels(&self) -> &Vec<FibonacciLevel> {
+        &self.levels
+    }
+
+    pub fn level(&self, name: &str) -> Option<&FibonacciLevel> {
+        self.levels.iter().find(|level| level.name == name)
+    }
+
+    pub fn retracements(&self) -> Vec<&FibonacciLevel> {
+        self.levels_of(FibonacciLevelType::Retracement)
+    }
+
+    pub fn extensions(&self) -> Vec<&FibonacciLevel> {
+        self.levels_of(FibonacciLevelType::Extension)
+    }
+
+    /// Recomputes the levels when the peaks form a new swing. Returns true
+    /// if they changed.
+    pub fn next(
+        &mut self,
+        extrema_maxima: &[(usize, f64)],
+        extrema_minima: &[(usize, f64)],
+        local_maxima: &[(usize, f64)],
+        local_minima: &[(usize, f64)],
+    ) -> bool {
+        let fibonacci =
+            Self::from_peaks(extrema_maxima, extrema_minima, local_maxima, local_minima);
+        match fibonacci.swing == self.swing {
+            true => false,
+            false => {
+                *self = fibonacci;
+                true
+            }
+        }
+    }
+
+    /// Shifts the swing indexes once the first `num_bars` candles are evicted.
+    pub fn evict(&mut self, num_bars: usize) {
+        if let Some(swing) = self.swing.as_mut() {
+            swing.from.0 = swing.from.0.saturating_sub(num_bars);
+            swing.to.0 = swing.to.0.saturating_sub(num_bars);
+        }
+    }
+
+    fn levels_of(&self, level_type: FibonacciLevelType) -> Vec<&FibonacciLevel> {
+        self.levels
+            .iter()
+            .filter(|level| level.level_type == level_type)
+            .collect()
+    }
+}
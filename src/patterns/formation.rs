@@ -0,0 +1,262 @@
+use crate::helpers::regression::linear_regression;
+use crate::scanner::candle::Candle;
+use crate::scanner::pattern::{lookback_from, lookback_from_env, lookback_points};
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum FormationType {
+    AscendingTriangle,
+    DescendingTriangle,
+    SymmetricalTriangle,
+    RisingWedge,
+    FallingWedge,
+    BullFlag,
+    BearFlag,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Breakout {
+    Up,
+    Down,
+    None,
+}
+
+/// Regression line over peak points, `price = slope * index + intercept`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Trendline {
+    pub slope: f64,
+    pub intercept: f64,
+    pub r_squared: f64,
+    pub from: usize,
+    pub to: usize,
+}
+
+impl Trendline {
+    pub fn fit(points: &[(usize, f64)]) -> Option<Self> {
+        let (slope, intercept, r_squared) = linear_regression(points)?;
+        Some(Self {
+            slope,
+            intercept,
+            r_squared,
+            from: points.first()?.0,
+            to: points.last()?.0,
+        })
+    }
+
+    pub fn value_at(&self, index: usize) -> f64 {
+        self.slope * index as f64 + self.intercept
+    }
+
+    /// Slope as a percentage of the line price, per bar.
+    pub fn slope_percentage(&self) -> f64 {
+        let price = self.value_at((self.from + self.to) / 2);
+        match price != 0. {
+            true => self.slope / price.abs() * 100.,
+            false => 0.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Formation {
+    pub formation_type: FormationType,
+    pub upper: Trendline,
+    pub lower: Trendline,
+    pub breakout: Breakout,
+    pub breakout_index: Option<usize>,
+}
+
+impl Formation {
+    pub fn start(&self) -> usize {
+        self.upper.from.min(self.lower.from)
+    }
+
+    pub fn end(&self) -> usize {
+        self.upper.to.max(self.lower.to)
+    }
+
+    pub fn width_at(&self, index: usize) -> f64 {
+        self.upper.value_at(index) - self.lower.value_at(index)
+    }
+
+    /// Index where the trendlines cross, if they converge.
+    pub fn apex(&self) -> Option<f64> {
+        let slopes = self.lower.slope - self.upper.slope;
+        match slopes > 0. {
+            true => Some((self.upper.intercept - self.lower.intercept) / slopes),
+            false => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FormationConfig {
+    /// Peaks of each side used for the trendlines
+    pub num_points: usize,
+    /// Slopes under this percentage per bar are flat
+    pub flat_slope: f64,
+    pub min_r_squared: f64,
+    /// Bars before the flag where the pole is measured
+    pub pole_bars: usize,
+    pub min_pole_percentage: f64,
+    /// Only peaks in the last bars, the whole data when not set
+    pub lookback: Option<usize>,
+}
+
+impl FormationConfig {
+    pub fn from_env() -> Self {
+        let var = |key: &str, default: f64| {
+            env::var(key)
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            num_points: var("FORMATIONS_NUM_POINTS", 3.) as usize,
+            flat_slope: var("FORMATIONS_FLAT_SLOPE", 0.02),
+            min_r_squared: var("FORMATIONS_MIN_R_SQUARED", 0.7),
+            pole_bars: var("FORMATIONS_POLE_BARS", 10.) as usize,
+            min_pole_percentage: var("FORMATIONS_MIN_POLE", 5.),
+            lookback: lookback_from_env("FORMATIONS_LOOKBACK"),
+        }
+    }
+}
+
+impl Default for FormationConfig {
+    fn default() -> Self {
+        Self {
+            num_points: 3,
+            flat_slope: 0.02,
+            min_r_squared: 0.7,
+            pole_bars: 10,
+            min_pole_percentage: 5.,
+            lookback: None,
+        }
+    }
+}
+
+/// Fits the last `num_points` maxima and minima and classifies the shape
+/// they draw. Breakouts are looked for in the candles after the last peak.
+pub fn detect_formation(
+    maxima: &[(usize, f64)],
+    minima: &[(usize, f64)],
+    candles: &[Candle],
+    config: &FormationConfig,
+) -> Option<Formation> {
+    let from = lookback_from(candles.len(), config.lookback);
+    let maxima = &lookback_points(maxima, from);
+    let minima = &lookback_points(minima, from);
+
+    let num_points = config.num_points.max(2);
+    if maxima.len() < num_points || minima.len() < num_points {
+        return None;
+    }
+
+    let upper = Trendline::fit(&maxima[maxima.len() - num_points..])?;
+    let lower = Trendline::fit(&minima[minima.len() - num_points..])?;
+    if upper.r_squared < config.min_r_squared || lower.r_squared < config.min_r_squared {
+        return None;
+    }
+
+    let start = upper.from.min(lower.from);
+    let end = upper.to.max(lower.to);
+    let start_width = upper.value_at(start) - lower.value_at(start);
+    let end_width = upper.value_at(end) - lower.value_at(end);
+    if start_width <= 0. || end_width <= 0. {
+        return None;
+    }
+
+    let formation_type = classify(&upper, &lower, start_width, end_width, candles, config)?;
+    let (breakout, breakout_index) = find_breakout(&upper, &lower, end, candles);
+
+    Some(Formation {
+        formation_type,
+        upper,
+        lower,
+        breakout,
+        breakout_index,
+    })
+}
+
+fn classify(
+    upper: &Trendline,
+    lower: &Trendline,
+    start_width: f64,
+    end_width: f64,
+    candles: &[Candle],
+    config: &FormationConfig,
+) -> Option<FormationType> {
+    let flat = config.flat_slope;
+    let upper_slope = upper.slope_percentage();
+    let lower_slope = lower.slope_percentage();
+    let is_converging = end_width < start_width;
+
+    let direction = |slope: f64| match slope {
+        _ if slope > flat => 1,
+        _ if slope < -flat => -1,
+        _ => 0,
+    };
+
+    match (direction(upper_slope), direction(lower_slope)) {
+        (0, 1) => Some(FormationType::AscendingTriangle),
+        (-1, 0) => Some(FormationType::DescendingTriangle),
+        (-1, 1) => Some(FormationType::SymmetricalTriangle),
+        (1, 1) if is_converging => Some(FormationType::RisingWedge),
+        (-1, -1) if is_converging => Some(FormationType::FallingWedge),
+        (upper_direction, lower_direction) if upper_direction == lower_direction => flag(
+            upper_direction,
+            start_width,
+            end_width,
+            candles,
+            upper.from.min(lower.from),
+            config,
+        ),
+        _ => None,
+    }
+}
+
+/// Parallel channel against a strong move right before it.
+fn flag(
+    direction: i32,
+    start_width: f64,
+    end_width: f64,
+    candles: &[Candle],
+    start: usize,
+    config: &FormationConfig,
+) -> Option<FormationType> {
+    let is_parallel = (end_width - start_width).abs() / start_width <= 0.25;
+    let pole_start = start.checked_sub(config.pole_bars)?;
+    let from = candles.get(pole_start)?.close();
+    let to = candles.get(start)?.close();
+    let pole = (to - from) / from * 100.;
+
+    match (is_parallel, direction) {
+        (true, direction) if direction <= 0 && pole >= config.min_pole_percentage => {
+            Some(FormationType::BullFlag)
+        }
+        (true, direction) if direction >= 0 && pole <= -config.min_pole_percentage => {
+            Some(FormationType::BearFlag)
+        }
+        _ => None,
+    }
+}
+
+fn find_breakout(
+    upper: &Trendline,
+    lower: &Trendline,
+    from: usize,
+    candles: &[Candle],
+) -> (Breakout, Option<usize>) {
+    for (index, candle) in candles.iter().enumerate().skip(from + 1) {
+        if candle.close() > upper.value_at(index) {
+            return (Breakout::Up, Some(index));
+        }
+        if candle.close() < lower.value_at(index) {
+            return (Breakout::Down, Some(index));
+        }
+    }
+    (Breakout::None, None)
+}
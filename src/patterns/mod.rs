@@ -1,11 +1,16 @@
 pub mod broadening;
 pub mod channel;
+pub mod divergence;
 //pub mod divergences;
 pub mod double;
+pub mod fibonacci;
+pub mod formation;
 pub mod head_shoulders;
 pub mod highs_lows;
 pub mod horizontal_levels;
 pub mod pattern;
 pub mod peaks;
 pub mod rectangle;
+pub mod trendlines;
 pub mod triangle;
+pub mod zones;
@@ -0,0 +1,277 @@
+use crate::helpers::regression::linear_regression;
+use crate::scanner::candle::Candle;
+use crate::scanner::pattern::{lookback_from, lookback_from_env, lookback_points};
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TrendlineType {
+    Support,
+    Resistance,
+}
+
+/// Line fitted through the peaks touching it. Broken once a candle closes
+/// beyond it by more than the tolerance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TouchedTrendline {
+    pub trendline_type: TrendlineType,
+    pub slope: f64,
+    pub intercept: f64,
+    /// Indexes of the touching peaks
+    pub touches: Vec<usize>,
+    pub broken_at: Option<usize>,
+}
+
+impl TouchedTrendline {
+    pub fn value_at(&self, index: usize) -> f64 {
+        self.slope * index as f64 + self.intercept
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.broken_at.is_none()
+    }
+
+    pub fn from(&self) -> usize {
+        *self.touches.first().unwrap()
+    }
+
+    pub fn to(&self) -> usize {
+        *self.touches.last().unwrap()
+    }
+
+    fn is_broken_by(&self, index: usize, close: f64, tolerance: f64) -> bool {
+        let value = self.value_at(index);
+        match self.trendline_type {
+            TrendlineType::Support => close < value * (1. - tolerance),
+            TrendlineType::Resistance => close > value * (1. + tolerance),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrendlinesConfig {
+    pub min_touches: usize,
+    /// Max distance, in percentage, from a peak to the line to touch it
+    pub tolerance_percentage: f64,
+    /// Last peaks of each side tried as line anchors
+    pub max_points: usize,
+    /// Only peaks in the last bars, the whole data when not set
+    pub lookback: Option<usize>,
+}
+
+impl TrendlinesConfig {
+    pub fn from_env() -> Self {
+        Self {
+            min_touches: env::var("TRENDLINES_MIN_TOUCHES")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(3),
+            tolerance_percentage: env::var("TRENDLINES_TOLERANCE")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(0.2),
+            max_points: env::var("TRENDLINES_MAX_POINTS")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(10),
+            lookback: lookback_from_env("TRENDLINES_LOOKBACK"),
+        }
+    }
+}
+
+impl Default for TrendlinesConfig {
+    fn default() -> Self {
+        Self {
+            min_touches: 3,
+            tolerance_percentage: 0.2,
+            max_points: 10,
+            lookback: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Trendlines {
+    config: TrendlinesConfig,
+    data: Vec<TouchedTrendline>,
+}
+
+impl Trendlines {
+    pub fn new() -> Self {
+        Self::with_config(TrendlinesConfig::from_env())
+    }
+
+    pub fn with_config(config: TrendlinesConfig) -> Self {
+        Self {
+            config,
+            data: vec![],
+        }
+    }
+
+    /// Tries every pair of the last peaks as anchors and keeps the lines
+    /// touched by at least `min_touches` peaks with no close through them in
+    /// between. Candles after the last touch decide if they are broken.
+    pub fn from_peaks(
+        &self,
+        maxima: &[(usize, f64)],
+        minima: &[(usize, f64)],
+        candles: &[Candle],
+    ) -> Self {
+        let mut trendlines = Self::with_config(self.config.clone());
+        let from = lookback_from(candles.len(), self.config.lookback);
+
+        for (trendline_type, peaks) in [
+            (TrendlineType::Support, minima),
+            (TrendlineType::Resistance, maxima),
+        ] {
+            let peaks = lookback_points(peaks, from);
+            let first = peaks.len().saturating_sub(self.config.max_points);
+            trendlines
+                .data
+                .extend(self.fit(trendline_type, &peaks[first..], candles));
+        }
+
+        trendlines
+            .data
+            .sort_by(|a, b| b.touches.len().cmp(&a.touches.len()));
+        trendlines
+    }
+
+    pub fn data(&self) -> &Vec<TouchedTrendline> {
+        &self.data
+    }
+
+    pub fn active(&self) -> Vec<&TouchedTrendline> {
+        self.data.iter().filter(|line| line.is_active()).collect()
+    }
+
+    pub fn broken(&self) -> Vec<&TouchedTrendline> {
+        self.data.iter().filter(|line| !line.is_active()).collect()
+    }
+
+    /// Closest active line value under the price at the index.
+    pub fn support_at(&self, index: usize, price: f64) -> Option<f64> {
+        self.active_values(index, TrendlineType::Support)
+            .filter(|value| *value <= price)
+            .fold(None, |acc: Option<f64>, value| {
+                Some(acc.map_or(value, |acc| acc.max(value)))
+            })
+    }
+
+    /// Closest active line value over the price at the index.
+    pub fn resistance_at(&self, index: usize, price: f64) -> Option<f64> {
+        self.active_values(index, TrendlineType::Resistance)
+            .filter(|value| *value >= price)
+            .fold(None, |acc: Option<f64>, value| {
+                Some(acc.map_or(value, |acc| acc.min(value)))
+            })
+    }
+
+    /// Checks the active lines against a closed candle.
+    pub fn next(&mut self, candle: &Candle, index: usize) {
+        let tolerance = self.config.tolerance_percentage / 100.;
+        for line in self.data.iter_mut().filter(|line| line.is_active()) {
+            if index > line.to() && line.is_broken_by(index, candle.close(), tolerance) {
+                line.broken_at = Some(index);
+            }
+        }
+    }
+
+    /// Shifts the indexes once the first `num_bars` candles are evicted,
+    /// moving the intercept so lines keep their prices.
+    pub fn evict(&mut self, num_bars: usize) {
+        for line in self.data.iter_mut() {
+            line.intercept += line.slope * num_bars as f64;
+            line.touches = line
+                .touches
+                .iter()
+                .filter(|touch| **touch >= num_bars)
+                .map(|touch| touch - num_bars)
+                .collect();
+            line.broken_at = line.broken_at.map(|index| index.saturating_sub(num_bars));
+        }
+        self.data.retain(|line| !line.touches.is_empty());
+    }
+
+    fn active_values(
+        &self,
+        index: usize,
+        trendline_type: TrendlineType,
+    ) -> impl Iterator<Item = f64> + '_ {
+        self.data
+            .iter()
+            .filter(move |line| line.is_active() && line.trendline_type == trendline_type)
+            .map(move |line| line.value_at(index))
+    }
+
+    fn fit(
+        &self,
+        trendline_type: TrendlineType,
+        peaks: &[(usize, f64)],
+        candles: &[Candle],
+    ) -> Vec<TouchedTrendline> {
+        let tolerance = self.config.tolerance_percentage / 100.;
+        let mut lines: Vec<TouchedTrendline> = vec![];
+
+        for (i, a) in peaks.iter().enumerate() {
+            for b in peaks.iter().skip(i + 1) {
+                let slope = (b.1 - a.1) / (b.0 as f64 - a.0 as f64);
+                let intercept = a.1 - slope * a.0 as f64;
+
+                let touching: Vec<(usize, f64)> = peaks
+                    .iter()
+                    .filter(|(index, price)| {
+                        let value = slope * *index as f64 + intercept;
+                        (price - value).abs() <= value.abs() * tolerance
+                    })
+                    .copied()
+                    .collect();
+
+                if touching.len() < self.config.min_touches {
+                    continue;
+                }
+
+                let (slope, intercept, _) = match linear_regression(&touching) {
+                    Some(fit) => fit,
+                    None => continue,
+                };
+
+                let mut line = TouchedTrendline {
+                    trendline_type,
+                    slope,
+                    intercept,
+                    touches: touching.iter().map(|(index, _)| *index).collect(),
+                    broken_at: None,
+                };
+
+                let is_duplicated = lines.iter().any(|x| x.touches == line.touches);
+                let is_crossed = (line.from()..=line.to()).any(|index| match candles.get(index) {
+                    Some(candle) => line.is_broken_by(index, candle.close(), tolerance),
+                    None => false,
+                });
+
+                if is_duplicated || is_crossed {
+                    continue;
+                }
+
+                line.broken_at = candles
+                    .iter()
+                    .enumerate()
+                    .skip(line.to() + 1)
+                    .find(|(index, candle)| line.is_broken_by(*index, candle.close(), tolerance))
+                    .map(|(index, _)| index);
+
+                lines.push(line);
+            }
+        }
+
+        lines
+    }
+}
+
+impl Default for Trendlines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,275 @@
+use crate::scanner::candle::Candle;
+use crate::scanner::pattern::{lookback_from, lookback_from_env};
+use crate::scanner::volume_profile::VolumeProfile;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Horizontal price band where peaks cluster.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Zone {
+    pub low: f64,
+    pub high: f64,
+    /// Peaks plus live candle visits into the zone
+    pub touches: usize,
+    pub last_index: usize,
+    /// Volume profile share of the zone, from 0 to 1
+    pub volume: f64,
+    pub strength: f64,
+    #[serde(default)]
+    touching: bool,
+}
+
+impl Zone {
+    fn new(index: usize, price: f64) -> Self {
+        Self {
+            low: price,
+            high: price,
+            touches: 1,
+            last_index: index,
+            volume: 0.,
+            strength: 0.,
+            touching: false,
+        }
+    }
+
+    pub fn mid(&self) -> f64 {
+        (self.low + self.high) / 2.
+    }
+
+    pub fn contains(&self, price: f64) -> bool {
+        price >= self.low && price <= self.high
+    }
+
+    fn overlaps(&self, low: f64, high: f64) -> bool {
+        high >= self.low && low <= self.high
+    }
+
+    fn merge(&mut self, other: &Zone) {
+        self.low = self.low.min(other.low);
+        self.high = self.high.max(other.high);
+        self.touches += other.touches;
+        self.last_index = self.last_index.max(other.last_index);
+        self.volume = self.volume.max(other.volume);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ZonesConfig {
+    /// Max distance, in percentage, between a peak and a zone to join it
+    pub width_percentage: f64,
+    pub min_touches: usize,
+    /// Bars for the recency score to halve
+    pub recency_bars: f64,
+    /// Only peaks in the last bars, the whole data when not set
+    pub lookback: Option<usize>,
+}
+
+impl ZonesConfig {
+    pub fn from_env() -> Self {
+        Self {
+            width_percentage: env::var("ZONES_WIDTH")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(0.5),
+            min_touches: env::var("ZONES_MIN_TOUCHES")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(2),
+            recency_bars: env::var("ZONES_RECENCY_BARS")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(100.),
+            lookback: lookback_from_env("ZONES_LOOKBACK"),
+        }
+    }
+}
+
+impl Default for ZonesConfig {
+    fn default() -> Self {
+        Self {
+            width_percentage: 0.5,
+            min_touches: 2,
+            recency_bars: 100.,
+            lookback: None,
+        }
+    }
+}
+
+/// Support and resistance zones built from the local peaks. Strength is
+/// `touches * (1 + recency) * (1 + volume)`, recency decaying from 1 with
+/// the bars since the last touch.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Zones {
+    config: ZonesConfig,
+    data: Vec<Zone>,
+    index: usize,
+}
+
+impl Zones {
+    pub fn new() -> Self {
+        Self::with_config(ZonesConfig::from_env())
+    }
+
+    pub fn with_config(config: ZonesConfig) -> Self {
+        Self {
+            config,
+            data: vec![],
+            index: 0,
+        }
+    }
+
+    pub fn from_peaks(
+        &self,
+        maxima: &[(usize, f64)],
+        minima: &[(usize, f64)],
+        data: &[Candle],
+        volume_profile: Option<&VolumeProfile>,
+    ) -> Self {
+        let mut zones = Self::with_config(self.config.clone());
+        let from = lookback_from(data.len(), self.config.lookback);
+        let mut peaks = [maxima, minima].concat();
+        peaks.retain(|(index, _)| *index >= from);
+        peaks.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (index, price) in peaks {
+            zones.add_peak(index, price);
+        }
+
+        zones.index = zones.index.max(data.len().saturating_sub(1));
+        if let Some(volume_profile) = volume_profile {
+            zones.set_volume(volume_profile);
+        }
+        zones.calculate_strength();
+        zones
+    }
+
+    /// Zones with enough touches, strongest first.
+    pub fn zones(&self) -> Vec<&Zone> {
+        let mut zones: Vec<&Zone> = self
+            .data
+            .iter()
+            .filter(|zone| zone.touches >= self.config.min_touches)
+            .collect();
+        zones.sort_by(|a, b| b.strength.partial_cmp(&a.strength).unwrap());
+        zones
+    }
+
+    pub fn nearest_zone_above(&self, price: f64) -> Option<&Zone> {
+        self.zones()
+            .into_iter()
+            .filter(|zone| zone.low > price)
+            .min_by(|a, b| a.low.partial_cmp(&b.low).unwrap())
+    }
+
+    pub fn nearest_zone_below(&self, price: f64) -> Option<&Zone> {
+        self.zones()
+            .into_iter()
+            .filter(|zone| zone.high < price)
+            .max_by(|a, b| a.high.partial_cmp(&b.high).unwrap())
+    }
+
+    pub fn zone_at(&self, price: f64) -> Option<&Zone> {
+        self.zones().into_iter().find(|zone| zone.contains(price))
+    }
+
+    /// Joins the peak to the closest zone within the width, or opens a new one.
+    pub fn add_peak(&mut self, index: usize, price: f64) {
+        let width = self.config.width_percentage / 100.;
+        let closest = self
+            .data
+            .iter_mut()
+            .filter(|zone| (zone.mid() - price).abs() <= zone.mid().abs() * width)
+            .min_by(|a, b| {
+                (a.mid() - price)
+                    .abs()
+                    .partial_cmp(&(b.mid() - price).abs())
+                    .unwrap()
+            });
+
+        match closest {
+            Some(zone) => {
+                zone.low = zone.low.min(price);
+                zone.high = zone.high.max(price);
+                zone.touches += 1;
+                zone.last_index = zone.last_index.max(index);
+            }
+            None => self.data.push(Zone::new(index, price)),
+        }
+
+        self.merge_overlapping();
+        self.index = self.index.max(index);
+        self.calculate_strength();
+    }
+
+    /// Counts a touch every time a candle enters a zone.
+    pub fn next(&mut self, candle: &Candle, index: usize) {
+        for zone in self.data.iter_mut() {
+            let is_touching = zone.overlaps(candle.low(), candle.high());
+            if is_touching && !zone.touching {
+                zone.touches += 1;
+                zone.last_index = index;
+            }
+            zone.touching = is_touching;
+        }
+        self.index = index;
+        self.calculate_strength();
+    }
+
+    pub fn set_volume(&mut self, volume_profile: &VolumeProfile) {
+        let total = volume_profile.total_volume();
+        if total <= 0. {
+            return;
+        }
+
+        let nodes = volume_profile.nodes();
+        let bucket_size = volume_profile.bucket_size();
+        for zone in self.data.iter_mut() {
+            let volume: f64 = nodes
+                .iter()
+                .filter(|node| zone.overlaps(node.price, node.price + bucket_size))
+                .map(|node| node.volume)
+                .sum();
+            zone.volume = volume / total;
+        }
+        self.calculate_strength();
+    }
+
+    /// Shifts the indexes once the first `num_bars` candles are evicted.
+    pub fn evict(&mut self, num_bars: usize) {
+        for zone in self.data.iter_mut() {
+            zone.last_index = zone.last_index.saturating_sub(num_bars);
+        }
+        self.index = self.index.saturating_sub(num_bars);
+    }
+
+    fn merge_overlapping(&mut self) {
+        self.data.sort_by(|a, b| a.low.partial_cmp(&b.low).unwrap());
+
+        let mut merged: Vec<Zone> = vec![];
+        for zone in self.data.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.overlaps(zone.low, zone.high) => last.merge(&zone),
+                _ => merged.push(zone),
+            }
+        }
+        self.data = merged;
+    }
+
+    fn calculate_strength(&mut self) {
+        let index = self.index;
+        let recency_bars = self.config.recency_bars.max(1.);
+
+        for zone in self.data.iter_mut() {
+            let bars = index.saturating_sub(zone.last_index) as f64;
+            let recency = 0.5_f64.powf(bars / recency_bars);
+            zone.strength = zone.touches as f64 * (1. + recency) * (1. + zone.volume);
+        }
+    }
+}
+
+impl Default for Zones {
+    fn default() -> Self {
+        Self::new()
+    }
+}
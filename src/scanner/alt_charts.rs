@@ -0,0 +1,280 @@
+use super::candle::{Candle, DOHLCV};
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::helpers::date::*;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum AltChartType {
+    Renko(f64),
+    Range(f64),
+}
+
+impl AltChartType {
+    pub fn size(&self) -> f64 {
+        match self {
+            AltChartType::Renko(size) | AltChartType::Range(size) => *size,
+        }
+    }
+}
+
+/// Builds Renko bricks or range bars as `Candle`s, either from a batch of
+/// DOHLCV bars or tick by tick. Only completed bars are returned, the one
+/// being formed is available through `current`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AltChart {
+    chart_type: AltChartType,
+    data: Vec<Candle>,
+    current: Option<Candle>,
+}
+
+impl AltChart {
+    pub fn new(chart_type: AltChartType) -> Result<Self> {
+        match chart_type.size() > 0. {
+            true => Ok(Self {
+                chart_type,
+                data: vec![],
+                current: None,
+            }),
+            false => Err(RsAlgoError {
+                err: RsAlgoErrorKind::InvalidCandle,
+            }),
+        }
+    }
+
+    pub fn renko(brick_size: f64) -> Result<Self> {
+        Self::new(AltChartType::Renko(brick_size))
+    }
+
+    pub fn range(range: f64) -> Result<Self> {
+        Self::new(AltChartType::Range(range))
+    }
+
+    /// Every bar is walked open, low, high, close for bullish bars and
+    /// open, high, low, close for bearish ones.
+    pub fn from_dohlc(chart_type: AltChartType, data: &[DOHLCV]) -> Result<Vec<Candle>> {
+        let mut chart = Self::new(chart_type)?;
+        for bar in data {
+            chart.next_bar(*bar)?;
+        }
+        Ok(chart.data)
+    }
+
+    pub fn chart_type(&self) -> &AltChartType {
+        &self.chart_type
+    }
+
+    pub fn data(&self) -> &Vec<Candle> {
+        &self.data
+    }
+
+    pub fn current(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+
+    pub fn next_bar(&mut self, bar: DOHLCV) -> Result<Vec<Candle>> {
+        let (date, open, high, low, close, volume) = bar;
+        let path = match close >= open {
+            true => [open, low, high, close],
+            false => [open, high, low, close],
+        };
+
+        let mut closed = vec![];
+        for (i, price) in path.iter().enumerate() {
+            let tick_volume = match i {
+                3 => volume,
+                _ => 0.,
+            };
+            closed.extend(self.next_tick(date, *price, tick_volume)?);
+        }
+        Ok(closed)
+    }
+
+    /// Returns the bars closed by the tick.
+    pub fn next_tick(
+        &mut self,
+        date: DateTime<Local>,
+        price: f64,
+        volume: f64,
+    ) -> Result<Vec<Candle>> {
+        match self.chart_type {
+            AltChartType::Renko(size) => self.next_renko(date, price, volume, size),
+            AltChartType::Range(size) => self.next_range(date, price, volume, size),
+        }
+    }
+
+    fn next_renko(
+        &mut self,
+        date: DateTime<Local>,
+        price: f64,
+        volume: f64,
+        size: f64,
+    ) -> Result<Vec<Candle>> {
+        let mut closed = vec![];
+        let mut current = match self.current.take() {
+            Some(current) => current,
+            None => {
+                self.current = Some(self.build(date, price, price, price, price, volume, false)?);
+                return Ok(closed);
+            }
+        };
+
+        current.high = current.high.max(price);
+        current.low = current.low.min(price);
+        current.close = price;
+        current.volume += volume;
+
+        // Reversals need two bricks from the last brick open
+        let (up_level, down_level) = match self.data.last() {
+            Some(last) if last.close > last.open => (last.close + size, last.close - 2. * size),
+            Some(last) if last.close < last.open => (last.close + 2. * size, last.close - size),
+            _ => (current.open + size, current.open - size),
+        };
+
+        let mut up_level = up_level;
+        let mut down_level = down_level;
+        let mut volume = current.volume;
+
+        while price >= up_level || price <= down_level {
+            let (brick_open, brick_close) = match price >= up_level {
+                true => (up_level - size, up_level),
+                false => (down_level + size, down_level),
+            };
+
+            let brick = self.build(
+                date,
+                brick_open,
+                brick_open.max(brick_close),
+                brick_open.min(brick_close),
+                brick_close,
+                volume,
+                true,
+            )?;
+            self.data.push(brick.clone());
+            closed.push(brick);
+            volume = 0.;
+
+            match brick_close > brick_open {
+                true => {
+                    up_level = brick_close + size;
+                    down_level = brick_close - 2. * size;
+                }
+                false => {
+                    up_level = brick_close + 2. * size;
+                    down_level = brick_close - size;
+                }
+            };
+        }
+
+        self.current = match closed.last() {
+            Some(last) => Some(self.build(
+                date,
+                last.close,
+                price.max(last.close),
+                price.min(last.close),
+                price,
+                0.,
+                false,
+            )?),
+            None => Some(current),
+        };
+
+        Ok(closed)
+    }
+
+    fn next_range(
+        &mut self,
+        date: DateTime<Local>,
+        price: f64,
+        volume: f64,
+        size: f64,
+    ) -> Result<Vec<Candle>> {
+        let mut closed = vec![];
+        let mut current = match self.current.take() {
+            Some(current) => current,
+            None => self.build(date, price, price, price, price, 0., false)?,
+        };
+        current.volume += volume;
+
+        loop {
+            let high = current.high.max(price);
+            let low = current.low.min(price);
+
+            match high - low >= size {
+                true => {
+                    // Closes at the range boundary, the rest of the move opens the next bar
+                    let close = match price > current.high {
+                        true => current.low + size,
+                        false => current.high - size,
+                    };
+                    let bar = self.build(
+                        date,
+                        current.open,
+                        current.high.max(close),
+                        current.low.min(close),
+                        close,
+                        current.volume,
+                        true,
+                    )?;
+                    self.data.push(bar.clone());
+                    closed.push(bar);
+                    current = self.build(date, close, close, close, close, 0., false)?;
+
+                    if close == price {
+                        break;
+                    }
+                }
+                false => {
+                    current.high = high;
+                    current.low = low;
+                    current.close = price;
+                    break;
+                }
+            }
+        }
+
+        self.current = Some(current);
+        Ok(closed)
+    }
+
+    fn build(
+        &self,
+        date: DateTime<Local>,
+        open: f64,
+        high: f64,
+        low: f64,
+        close: f64,
+        volume: f64,
+        is_closed: bool,
+    ) -> Result<Candle> {
+        let previous_candles: Vec<DOHLCV> = self
+            .data
+            .iter()
+            .rev()
+            .take(2)
+            .rev()
+            .map(|candle| {
+                (
+                    candle.date(),
+                    candle.open(),
+                    candle.high(),
+                    candle.low(),
+                    candle.close(),
+                    candle.volume(),
+                )
+            })
+            .collect();
+
+        Candle::new()
+            .date(date)
+            .open(open)
+            .high(high)
+            .low(low)
+            .close(close)
+            .volume(volume)
+            .is_closed(is_closed)
+            .previous_candles(previous_candles)
+            .logarithmic(false)
+            .build()
+    }
+}
@@ -4,6 +4,7 @@ use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
 use crate::helpers::comp::percentage_change;
 use crate::helpers::date::*;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 
 pub type OHLCV = (f64, f64, f64, f64);
 pub type DOHLCV = (DateTime<Local>, f64, f64, f64, f64, f64);
@@ -39,6 +40,8 @@ pub struct Candle {
     pub close: f64,
     pub volume: f64,
     pub is_closed: bool,
+    #[serde(default)]
+    pub patterns: Vec<CandlePattern>,
 }
 
 impl Candle {
@@ -107,6 +110,18 @@ impl Candle {
         &self.candle_type
     }
 
+    /// Every matched pattern, best score first.
+    pub fn patterns(&self) -> &Vec<CandlePattern> {
+        &self.patterns
+    }
+
+    pub fn pattern_score(&self, candle_type: &CandleType) -> Option<f64> {
+        self.patterns
+            .iter()
+            .find(|pattern| &pattern.candle_type == candle_type)
+            .map(|pattern| pattern.score)
+    }
+
     pub fn is_bullish(&self) -> bool {
         self.candle_type == CandleType::Engulfing
             || self.candle_type == CandleType::Karakasa
@@ -119,6 +134,20 @@ impl Candle {
             || self.candle_type == CandleType::BearishStar
     }
 
+    pub fn to_logarithmic_values(&self) -> Self {
+        Self {
+            date: self.date,
+            open: self.open.ln(),
+            high: self.high.ln(),
+            low: self.low.ln(),
+            close: self.close.ln(),
+            volume: self.volume,
+            is_closed: self.is_closed(),
+            candle_type: self.candle_type.clone(),
+            patterns: self.patterns.clone(),
+        }
+    }
+
     pub fn from_logarithmic_values(&self) -> Self {
         Self {
             date: self.date,
@@ -129,10 +158,114 @@ impl Candle {
             volume: self.volume,
             is_closed: self.is_closed(),
             candle_type: self.candle_type.clone(),
+            patterns: self.patterns.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CandlePattern {
+    pub candle_type: CandleType,
+    /// From 0 (barely matched) to 1
+    pub score: f64,
+}
+
+/// Candle pattern thresholds, read from CANDLE_* env vars.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CandlePatternConfig {
+    /// Max body to range ratio of a doji
+    pub doji_body: f64,
+    /// Min shadow to range ratio of karakasas and stars
+    pub shadow: f64,
+    /// Range to body multiplier of karakasas and stars
+    pub range_body: f64,
+    pub hanging_man_shadow: f64,
+    pub hanging_man_range_body: f64,
+    /// Max shadow, relative to price, of a marubozu
+    pub marubozu_shadow: f64,
+    /// Min gap in percentage
+    pub gap_percentage: f64,
+    /// Min body, relative to price, of every crow
+    pub crows_body: f64,
+    /// Max close shadow to range ratio of every crow
+    pub crows_shadow: f64,
+    /// Patterns scoring below are discarded
+    pub min_score: f64,
+}
+
+impl CandlePatternConfig {
+    pub fn from_env() -> Self {
+        let parse = |key: &str, default: f64| {
+            env::var(key)
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            doji_body: parse("CANDLE_DOJI_BODY", 0.1),
+            shadow: parse("CANDLE_SHADOW", 0.6),
+            range_body: parse("CANDLE_RANGE_BODY", 3.),
+            hanging_man_shadow: parse("CANDLE_HANGING_MAN_SHADOW", 0.75),
+            hanging_man_range_body: parse("CANDLE_HANGING_MAN_RANGE_BODY", 4.),
+            marubozu_shadow: parse("CANDLE_MARUBOZU_SHADOW", 0.1),
+            gap_percentage: parse("CANDLE_GAP_PERCENTAGE", 3.),
+            crows_body: parse("CANDLE_CROWS_BODY", 0.01),
+            crows_shadow: parse("CANDLE_CROWS_SHADOW", 0.2),
+            min_score: parse("CANDLE_MIN_SCORE", 0.),
+        }
+    }
+}
+
+impl Default for CandlePatternConfig {
+    fn default() -> Self {
+        Self {
+            doji_body: 0.1,
+            shadow: 0.6,
+            range_body: 3.,
+            hanging_man_shadow: 0.75,
+            hanging_man_range_body: 4.,
+            marubozu_shadow: 0.1,
+            gap_percentage: 3.,
+            crows_body: 0.01,
+            crows_shadow: 0.2,
+            min_score: 0.,
         }
     }
 }
 
+/// Score of a pattern that matched right on its thresholds
+const MIN_SCORE: f64 = 0.01;
+
+fn matches(conditions: &[bool]) -> Option<()> {
+    match conditions.iter().all(|condition| *condition) {
+        true => Some(()),
+        false => None,
+    }
+}
+
+/// Average of the margins, each clamped between MIN_SCORE and 1
+fn score(margins: &[f64]) -> Option<f64> {
+    let total: f64 = margins
+        .iter()
+        .map(|margin| match margin.is_finite() {
+            true => margin.clamp(MIN_SCORE, 1.),
+            false => MIN_SCORE,
+        })
+        .sum();
+    Some(total / margins.len().max(1) as f64)
+}
+
+/// How far a ratio is over its minimum, relative to the room left up to 1
+fn above(value: f64, threshold: f64) -> f64 {
+    (value - threshold) / (1. - threshold).max(f64::EPSILON)
+}
+
+/// How far a value is under its maximum
+fn below(value: f64, threshold: f64) -> f64 {
+    1. - value / threshold.max(f64::EPSILON)
+}
+
 pub struct CandleBuilder {
     date: Option<DateTime<Local>>,
     open: Option<f64>,
@@ -143,6 +276,7 @@ pub struct CandleBuilder {
     is_closed: Option<bool>,
     previous_candles: Option<Vec<DOHLCV>>,
     logarithmic: Option<bool>,
+    config: Option<CandlePatternConfig>,
 }
 
 impl CandleBuilder {
@@ -157,6 +291,7 @@ impl CandleBuilder {
             is_closed: None,
             previous_candles: None,
             logarithmic: None,
+            config: None,
         }
     }
 
@@ -205,6 +340,11 @@ impl CandleBuilder {
         self
     }
 
+    pub fn pattern_config(mut self, val: CandlePatternConfig) -> Self {
+        self.config = Some(val);
+        self
+    }
+
     fn get_current_ohlc(&self) -> OHLCV {
         match self.logarithmic.unwrap() {
             true => (
@@ -222,239 +362,361 @@ impl CandleBuilder {
         }
     }
 
+    /// Previous candles come in the same scale as the current one.
     fn get_previous_ohlc(&self, index: usize) -> OHLCV {
         match self.logarithmic.unwrap() {
             true => (
-                self.previous_candles.as_ref().unwrap()[index].1,
-                self.previous_candles.as_ref().unwrap()[index].2,
-                self.previous_candles.as_ref().unwrap()[index].3,
-                self.previous_candles.as_ref().unwrap()[index].4,
-            ),
-            false => (
                 self.previous_candles.as_ref().unwrap()[index].1.exp(),
                 self.previous_candles.as_ref().unwrap()[index].2.exp(),
                 self.previous_candles.as_ref().unwrap()[index].3.exp(),
                 self.previous_candles.as_ref().unwrap()[index].4.exp(),
             ),
+            false => (
+                self.previous_candles.as_ref().unwrap()[index].1,
+                self.previous_candles.as_ref().unwrap()[index].2,
+                self.previous_candles.as_ref().unwrap()[index].3,
+                self.previous_candles.as_ref().unwrap()[index].4,
+            ),
+        }
+    }
+
+    fn has_previous(&self, num: usize) -> bool {
+        match &self.previous_candles {
+            Some(previous_candles) => previous_candles.len() >= num,
+            None => false,
         }
     }
 
-    fn is_doji(&self) -> bool {
+    fn is_doji(&self, config: &CandlePatternConfig) -> Option<f64> {
         // (O = C ) || (ABS(O – C ) <= ((H – L ) * 0.1))
         let (open, high, low, close) = &self.get_current_ohlc();
-        (open.floor() == close.floor()) || (open - close).abs() <= ((high - low) * 0.1)
+        let body = (open - close).abs() / (high - low).max(f64::EPSILON);
+        match (open.floor() == close.floor(), body <= config.doji_body) {
+            (_, true) => score(&[below(body, config.doji_body)]),
+            (true, false) => Some(MIN_SCORE),
+            (false, false) => None,
+        }
     }
 
-    fn is_karakasa(&self) -> bool {
+    fn is_karakasa(&self, config: &CandlePatternConfig) -> Option<f64> {
         // ((H-L)>3*(O-C)AND((C-L)/(.001+H-L)>0.6)AND((O-L)/(.001+H-L)>0.6))
         let (open, high, low, close) = &self.get_current_ohlc();
-        (high - low) > 3. * (open - close)
-            && ((close - low) / (0.001 + high - low) > 0.6)
-            && ((open - low) / (0.001 + high - low) > 0.6)
-    }
-
-    fn is_bearish_karakasa(&self) -> bool {
+        let close_shadow = (close - low) / (0.001 + high - low);
+        let open_shadow = (open - low) / (0.001 + high - low);
+        matches(&[
+            (high - low) > config.range_body * (open - close),
+            close_shadow > config.shadow,
+            open_shadow > config.shadow,
+        ])?;
+        score(&[
+            above(close_shadow, config.shadow),
+            above(open_shadow, config.shadow),
+        ])
+    }
+
+    fn is_bearish_karakasa(&self, config: &CandlePatternConfig) -> Option<f64> {
         // (((H – L) > 3 * (O – C)) AND ((H – C) / (.001 + H – L) > 0.6) AND ((H – O) / (.001 + H – L) > 0.6))
         let (open, high, low, close) = &self.get_current_ohlc();
-        ((high - low) > 3. * (open - close))
-            && ((high - close) / (0.001 + high - low) > 0.6)
-            && ((high - open) / (0.001 + high - low) > 0.6)
-    }
-
-    fn is_bullish_star(&self) -> bool {
+        let close_shadow = (high - close) / (0.001 + high - low);
+        let open_shadow = (high - open) / (0.001 + high - low);
+        matches(&[
+            (high - low) > config.range_body * (open - close),
+            close_shadow > config.shadow,
+            open_shadow > config.shadow,
+        ])?;
+        score(&[
+            above(close_shadow, config.shadow),
+            above(open_shadow, config.shadow),
+        ])
+    }
+
+    fn is_bullish_star(&self, config: &CandlePatternConfig) -> Option<f64> {
         // ((O2>C2)AND((O2-C2)/(.001+H2-L2)>.6)AND(C2>O1) AND(O1>C1)AND((H1-L1)>(3*(C1-O1))) AND(C>O)AND(O>O1))
+        if !self.has_previous(2) {
+            return None;
+        }
         let (open, _high, _low, close) = &self.get_current_ohlc();
         let (prev_open, prev_high, prev_low, prev_close) = &self.get_previous_ohlc(0);
         let (prev_open1, prev_high1, prev_low1, prev_close1) = &self.get_previous_ohlc(1);
-        (prev_open1 > prev_close1)
-            && ((prev_open1 - prev_close1) / (0.001 + prev_high1 - prev_low1) > 0.6)
-            && (prev_close1 > prev_open)
-            && (prev_open > prev_close)
-            && ((prev_high - prev_low) > (3. * (prev_close - prev_open)))
-            && (close > open)
-            && (open > prev_open)
-    }
-
-    fn is_bearish_star(&self) -> bool {
+        let first_body = (prev_open1 - prev_close1) / (0.001 + prev_high1 - prev_low1);
+        matches(&[
+            prev_open1 > prev_close1,
+            first_body > config.shadow,
+            prev_close1 > prev_open,
+            prev_open > prev_close,
+            (prev_high - prev_low) > (config.range_body * (prev_close - prev_open)),
+            close > open,
+            open > prev_open,
+        ])?;
+        score(&[above(first_body, config.shadow)])
+    }
+
+    fn is_bearish_star(&self, config: &CandlePatternConfig) -> Option<f64> {
         // ((O2>C2)AND((O2-C2)/(.001+H2-L2)>.6)AND(C2>O1) AND(O1>C1)AND((H1-L1)>(3*(C1-O1))) AND(C>O)AND(O>O1))
+        if !self.has_previous(2) {
+            return None;
+        }
         let (open, _high, _low, close) = &self.get_current_ohlc();
         let (prev_open, prev_high, prev_low, prev_close) = &self.get_previous_ohlc(0);
         let (prev_open1, prev_high1, prev_low1, prev_close1) = &self.get_previous_ohlc(1);
-        (prev_open1 > prev_close1)
-            && ((prev_open1 - prev_close1) / (0.001 + prev_high1 - prev_low1) > 0.6)
-            && (prev_close1 < prev_open)
-            && (prev_open > prev_close)
-            && ((prev_high - prev_low) > (3. * (prev_close - prev_open)))
-            && (close > open)
-            && (open < prev_open)
-    }
-
-    fn is_marubozu(&self) -> bool {
+        let first_body = (prev_open1 - prev_close1) / (0.001 + prev_high1 - prev_low1);
+        matches(&[
+            prev_open1 > prev_close1,
+            first_body > config.shadow,
+            prev_close1 < prev_open,
+            prev_open > prev_close,
+            (prev_high - prev_low) > (config.range_body * (prev_close - prev_open)),
+            close > open,
+            open < prev_open,
+        ])?;
+        score(&[above(first_body, config.shadow)])
+    }
+
+    fn is_marubozu(&self, config: &CandlePatternConfig) -> Option<f64> {
         //O = L AND H = C.
         let (open, high, low, close) = &self.get_current_ohlc();
         let high_shadow = (high - close) / close;
         let low_shadow = (low - open) / open;
-        (open <= low && low_shadow < 0.1) && (high >= close && high_shadow < 0.1)
+        matches(&[
+            open <= low && low_shadow < config.marubozu_shadow,
+            high >= close && high_shadow < config.marubozu_shadow,
+        ])?;
+        score(&[
+            below(low_shadow.abs(), config.marubozu_shadow),
+            below(high_shadow.abs(), config.marubozu_shadow),
+        ])
     }
 
-    fn is_bearish_marubozu(&self) -> bool {
+    fn is_bearish_marubozu(&self, config: &CandlePatternConfig) -> Option<f64> {
         //O = H AND C = L.
         let (open, high, low, close) = &self.get_current_ohlc();
         let high_shadow = (high - open) / open;
         let _low_shadow = (low - close) / close;
-        (open >= high && high_shadow < 0.1) && (low <= close && high_shadow < 0.1)
+        matches(&[
+            open >= high && high_shadow < config.marubozu_shadow,
+            low <= close && high_shadow < config.marubozu_shadow,
+        ])?;
+        score(&[below(high_shadow.abs(), config.marubozu_shadow)])
     }
 
-    fn is_hanging_man(&self) -> bool {
+    fn is_hanging_man(&self, config: &CandlePatternConfig) -> Option<f64> {
         // (((H – L) > 4 * (O – C)) AND ((C – L) / (.001 + H – L) >= 0.75) AND ((O – L) / (.001 + H – L) >= .075)))
         let (open, high, low, close) = &self.get_current_ohlc();
-        ((high - low) > 4. * (open - close))
-            && ((close - low) / (0.001 + high - low) > 0.75)
-            && ((open - low) / (0.001 + high - low) > 0.75)
-    }
-
-    fn is_engulfing(&self) -> bool {
+        let close_shadow = (close - low) / (0.001 + high - low);
+        let open_shadow = (open - low) / (0.001 + high - low);
+        matches(&[
+            (high - low) > config.hanging_man_range_body * (open - close),
+            close_shadow > config.hanging_man_shadow,
+            open_shadow > config.hanging_man_shadow,
+        ])?;
+        score(&[
+            above(close_shadow, config.hanging_man_shadow),
+            above(open_shadow, config.hanging_man_shadow),
+        ])
+    }
+
+    fn is_engulfing(&self) -> Option<f64> {
         //(O1 > C1) AND (C > O) AND (C >= O1) AND (C1 >= O) AND ((C – O) > (O1 – C1))
+        if !self.has_previous(1) {
+            return None;
+        }
         let (open, _high, _low, close) = &self.get_current_ohlc();
         let (prev_open, _prev_high, _prev_low, prev_close) = &self.get_previous_ohlc(0);
-        (prev_open > prev_close)
-            && (close > open)
-            && (close >= prev_open)
-            && (prev_close >= open)
-            && ((close - open) > (prev_open - prev_close))
+        matches(&[
+            prev_open > prev_close,
+            close > open,
+            close >= prev_open,
+            prev_close >= open,
+            (close - open) > (prev_open - prev_close),
+        ])?;
+        score(&[1. - (prev_open - prev_close) / (close - open)])
     }
 
-    fn is_bearish_engulfing(&self) -> bool {
+    fn is_bearish_engulfing(&self) -> Option<f64> {
         //(C1 > O1) AND (O > C) AND (O >= C1) AND (O1 >= C) AND ((O – C) > (C1 – O1))
+        if !self.has_previous(1) {
+            return None;
+        }
         let (open, _high, _low, close) = &self.get_current_ohlc();
         let (prev_open, _prev_high, _prev_low, prev_close) = &self.get_previous_ohlc(0);
-        (prev_close > prev_open)
-            && (open > close)
-            && (open >= prev_close)
-            && (prev_open >= close)
-            && ((open - close) > (prev_close - prev_open))
+        matches(&[
+            prev_close > prev_open,
+            open > close,
+            open >= prev_close,
+            prev_open >= close,
+            (open - close) > (prev_close - prev_open),
+        ])?;
+        score(&[1. - (prev_close - prev_open) / (open - close)])
     }
 
-    fn is_harami(&self) -> bool {
+    fn is_harami(&self) -> Option<f64> {
         //((O1 > C1) AND (C > O) AND (C <= O1) AND (C1 <= O) AND ((C – O) < (O1 – C1)))
+        if !self.has_previous(1) {
+            return None;
+        }
         let (open, _high, _low, close) = &self.get_current_ohlc();
         let (prev_open, _prev_high, _prev_low, prev_close) = &self.get_previous_ohlc(0);
-        (prev_open > prev_close)
-            && (close > open)
-            && (close <= prev_open)
-            && (prev_close <= open)
-            && ((close - open) < (prev_open - prev_close))
+        matches(&[
+            prev_open > prev_close,
+            close > open,
+            close <= prev_open,
+            prev_close <= open,
+            (close - open) < (prev_open - prev_close),
+        ])?;
+        score(&[1. - (close - open) / (prev_open - prev_close)])
     }
 
-    fn is_bearish_harami(&self) -> bool {
+    fn is_bearish_harami(&self) -> Option<f64> {
         //((C1 > O1) AND (O > C) AND (O <= C1) AND (O1 <= C) AND ((O – C) < (C1 – O1)))
+        if !self.has_previous(1) {
+            return None;
+        }
         let (open, _high, _low, close) = &self.get_current_ohlc();
         let (prev_open, _prev_high, _prev_low, prev_close) = &self.get_previous_ohlc(0);
-        (prev_close > prev_open)
-            && (open > close)
-            && (open <= prev_close)
-            && (prev_open <= close)
-            && ((open - close) < (prev_close - prev_open))
-    }
-
-    fn is_bullish_gap(&self) -> bool {
-        //((C1 > O1) AND (O > C) AND (O <= C1) AND (O1 <= C) AND ((O – C) < (C1 – O1)))
+        matches(&[
+            prev_close > prev_open,
+            open > close,
+            open <= prev_close,
+            prev_open <= close,
+            (open - close) < (prev_close - prev_open),
+        ])?;
+        score(&[1. - (open - close) / (prev_close - prev_open)])
+    }
+
+    fn is_bullish_gap(&self, config: &CandlePatternConfig) -> Option<f64> {
+        if !self.has_previous(1) {
+            return None;
+        }
         let (open, _high, _low, close) = &self.get_current_ohlc();
         let (_prev_open, prev_high, _prev_low, _prev_close) = &self.get_previous_ohlc(0);
         let percentage_diff = percentage_change(*prev_high, *open);
-        open > prev_high && percentage_diff > 3. && close > prev_high
+        matches(&[
+            open > prev_high,
+            percentage_diff > config.gap_percentage,
+            close > prev_high,
+        ])?;
+        score(&[(percentage_diff - config.gap_percentage) / config.gap_percentage])
     }
 
-    fn is_bearish_gap(&self) -> bool {
+    fn is_bearish_gap(&self, config: &CandlePatternConfig) -> Option<f64> {
         //FIXME
-        //((C1 > O1) AND (O > C) AND (O <= C1) AND (O1 <= C) AND ((O – C) < (C1 – O1)))
+        if !self.has_previous(1) {
+            return None;
+        }
         let (open, _high, _low, close) = &self.get_current_ohlc();
         let (_a, _prev_high, prev_low, _prev_close) = &self.get_previous_ohlc(0);
         let percentage_diff = percentage_change(*prev_low, *open);
-        open < prev_low && percentage_diff > 3. && close < prev_low
+        matches(&[
+            open < prev_low,
+            percentage_diff > config.gap_percentage,
+            close < prev_low,
+        ])?;
+        score(&[(percentage_diff - config.gap_percentage) / config.gap_percentage])
     }
 
-    fn is_bullish_crows(&self) -> bool {
+    fn is_bullish_crows(&self, config: &CandlePatternConfig) -> Option<f64> {
         //(C>O*1.01) AND(C1>O1*1.01) AND(C2>O2*1.01) AND(C>C1) AND
         // (C1>C2) AND(OO1) AND(O1O2) AND (((H-C)/(H-L))<.2) AND(((H1-C1)/(H1-L1))<.2)AND(((H2-C2)/(H2-L2))<.2)
+        if !self.has_previous(2) {
+            return None;
+        }
         let (open, high, low, close) = &self.get_current_ohlc();
         let (prev_open, prev_high, prev_low, prev_close) = &self.get_previous_ohlc(0);
         let (prev_open1, prev_high1, prev_low1, prev_close1) = &self.get_previous_ohlc(1);
-
-        (close > &(open * 1.01))
-            && (prev_close > &(prev_open * 1.01))
-            && (prev_close1 > &(prev_open1 * 1.01))
-            && (close > prev_close)
-            && (prev_close > prev_close1)
-            && (((high - close) / (high - low) < 0.2)
-                && ((prev_high - prev_close) / (prev_high - prev_low) < 0.2)
-                && ((prev_high1 - prev_close1) / (prev_high1 - prev_low1) < 0.2))
-    }
-
-    fn is_bearish_crows(&self) -> bool {
+        let body = 1. + config.crows_body;
+        let shadows = [
+            (high - close) / (high - low),
+            (prev_high - prev_close) / (prev_high - prev_low),
+            (prev_high1 - prev_close1) / (prev_high1 - prev_low1),
+        ];
+
+        matches(&[
+            close > &(open * body),
+            prev_close > &(prev_open * body),
+            prev_close1 > &(prev_open1 * body),
+            close > prev_close,
+            prev_close > prev_close1,
+        ])?;
+        matches(&shadows.map(|shadow| shadow < config.crows_shadow))?;
+        score(&shadows.map(|shadow| below(shadow, config.crows_shadow)))
+    }
+
+    fn is_bearish_crows(&self, config: &CandlePatternConfig) -> Option<f64> {
         //(C>O*1.01) AND(C1>O1*1.01) AND(C2>O2*1.01) AND(C>C1) AND
         // (C1>C2) AND(OO1) AND(O1O2) AND (((H-C)/(H-L))<.2) AND(((H1-C1)/(H1-L1))<.2)AND(((H2-C2)/(H2-L2))<.2)
+        if !self.has_previous(2) {
+            return None;
+        }
         let (open, high, low, close) = &self.get_current_ohlc();
         let (prev_open, prev_high, prev_low, prev_close) = &self.get_previous_ohlc(0);
         let (prev_open1, prev_high1, prev_low1, prev_close1) = &self.get_previous_ohlc(1);
-
-        (open > &(close * 1.01))
-            && (prev_open > &(prev_close * 1.01))
-            && (prev_open1 > &(prev_close1 * 1.01))
-            && (close < prev_close)
-            && (prev_close < prev_close1)
-            && (open > prev_close)
-            && (open < prev_open)
-            && (prev_open > prev_close1)
-            && (prev_open < prev_open1)
-            && (((close - low) / (high - low) < 0.2)
-                && ((prev_close - prev_low) / (prev_high - prev_low) < 0.2)
-                && ((prev_close1 - prev_low1) / (prev_high1 - prev_low1) < 0.2))
-    }
-
-    fn identify_candle_type(&self) -> CandleType {
+        let body = 1. + config.crows_body;
+        let shadows = [
+            (close - low) / (high - low),
+            (prev_close - prev_low) / (prev_high - prev_low),
+            (prev_close1 - prev_low1) / (prev_high1 - prev_low1),
+        ];
+
+        matches(&[
+            open > &(close * body),
+            prev_open > &(prev_close * body),
+            prev_open1 > &(prev_close1 * body),
+            close < prev_close,
+            prev_close < prev_close1,
+            open > prev_close,
+            open < prev_open,
+            prev_open > prev_close1,
+            prev_open < prev_open1,
+        ])?;
+        matches(&shadows.map(|shadow| shadow < config.crows_shadow))?;
+        score(&shadows.map(|shadow| below(shadow, config.crows_shadow)))
+    }
+
+    /// Every matching pattern with its score, in the historical priority order.
+    fn identify_candle_patterns(&self) -> Vec<CandlePattern> {
         let candle_types = env::var("CANDLE_TYPES").unwrap().parse::<bool>().unwrap();
 
-        match candle_types {
-            true => {
-                if self.is_bullish_gap() {
-                    CandleType::BullishGap
-                } else if self.is_karakasa() {
-                    CandleType::Karakasa
-                } else if self.is_bullish_star() {
-                    CandleType::MorningStar
-                } else if self.is_bullish_crows() {
-                    CandleType::BullishCrows
-                } else if self.is_marubozu() {
-                    CandleType::Marubozu
-                } else if self.is_engulfing() {
-                    CandleType::Engulfing
-                } else if self.is_bearish_karakasa() {
-                    CandleType::BearishKarakasa
-                } else if self.is_bearish_star() {
-                    CandleType::BearishStar
-                } else if self.is_hanging_man() {
-                    CandleType::HangingMan
-                } else if self.is_bearish_gap() {
-                    CandleType::BearishGap
-                } else if self.is_bearish_crows() {
-                    CandleType::BearishCrows
-                } else if self.is_bearish_marubozu() {
-                    CandleType::BearishMarubozu
-                } else if self.is_bearish_engulfing() {
-                    CandleType::BearishEngulfing
-                } else if self.is_harami() {
-                    CandleType::Harami
-                } else if self.is_bearish_harami() {
-                    CandleType::BearishHarami
-                } else if self.is_doji() {
-                    CandleType::Doji
-                } else {
-                    CandleType::Default
-                }
-            }
-            false => CandleType::Default,
+        if !candle_types {
+            return vec![];
         }
+
+        let config = self
+            .config
+            .clone()
+            .unwrap_or_else(CandlePatternConfig::from_env);
+
+        let patterns: Vec<CandlePattern> = [
+            (CandleType::BullishGap, self.is_bullish_gap(&config)),
+            (CandleType::Karakasa, self.is_karakasa(&config)),
+            (CandleType::MorningStar, self.is_bullish_star(&config)),
+            (CandleType::BullishCrows, self.is_bullish_crows(&config)),
+            (CandleType::Marubozu, self.is_marubozu(&config)),
+            (CandleType::Engulfing, self.is_engulfing()),
+            (
+                CandleType::BearishKarakasa,
+                self.is_bearish_karakasa(&config),
+            ),
+            (CandleType::BearishStar, self.is_bearish_star(&config)),
+            (CandleType::HangingMan, self.is_hanging_man(&config)),
+            (CandleType::BearishGap, self.is_bearish_gap(&config)),
+            (CandleType::BearishCrows, self.is_bearish_crows(&config)),
+            (
+                CandleType::BearishMarubozu,
+                self.is_bearish_marubozu(&config),
+            ),
+            (CandleType::BearishEngulfing, self.is_bearish_engulfing()),
+            (CandleType::Harami, self.is_harami()),
+            (CandleType::BearishHarami, self.is_bearish_harami()),
+            (CandleType::Doji, self.is_doji(&config)),
+        ]
+        .into_iter()
+        .filter_map(|(candle_type, score)| match score {
+            Some(score) if score >= config.min_score => Some(CandlePattern { candle_type, score }),
+            _ => None,
+        })
+        .collect();
+
+        patterns
     }
 
     pub fn build(self) -> Result<Candle> {
@@ -466,7 +728,6 @@ impl CandleBuilder {
             Some(close),
             Some(volume),
             Some(is_closed),
-            Some(_previous_candles),
             Some(_logarithmic),
         ) = (
             self.date,
@@ -476,11 +737,19 @@ impl CandleBuilder {
             self.close,
             self.volume,
             self.is_closed,
-            self.previous_candles.as_ref(),
             self.logarithmic,
         ) {
+            let mut patterns = self.identify_candle_patterns();
+            // The priority winner stays the candle type, scores only rank patterns()
+            let candle_type = patterns
+                .first()
+                .map(|pattern| pattern.candle_type.clone())
+                .unwrap_or(CandleType::Default);
+            patterns.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+
             Ok(Candle {
-                candle_type: self.identify_candle_type(),
+                candle_type,
+                patterns,
                 date,
                 open,
                 close,
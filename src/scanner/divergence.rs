@@ -3,7 +3,7 @@ use crate::helpers::date::*;
 use crate::helpers::maxima_minima::*;
 use crate::indicators::Indicator;
 use crate::models::indicator::IndicatorType;
-use crate::scanner::pattern::{DataPoints, Pattern};
+use crate::scanner::pattern::{evict_points, DataPoints, Pattern};
 use std::cmp::Ordering;
 use std::env;
 
@@ -54,6 +54,16 @@ impl Divergences {
     pub fn new() -> Result<Self> {
         Ok(Self { data: vec![] })
     }
+
+    /// Drops the divergences drawn over the first `num_bars` candles.
+    pub fn evict(&mut self, num_bars: usize) {
+        self.data
+            .retain(|divergence| divergence.data.iter().all(|(index, _)| *index >= num_bars));
+
+        for divergence in self.data.iter_mut() {
+            evict_points(&mut divergence.data, num_bars);
+        }
+    }
     // CONTINUE HERE
     pub fn detect_divergences(
         &mut self,
@@ -72,6 +82,7 @@ impl Divergences {
             .parse::<usize>()
             .unwrap();
 
+        #[allow(deprecated)]
         let data_indicators: [(IndicatorType, &Vec<f64>); 2] = [
             (IndicatorType::Rsi, indicators.rsi().get_data_a()),
             (IndicatorType::Rsi, indicators.rsi().get_data_a()),
@@ -0,0 +1,214 @@
+use super::candle::Candle;
+use crate::helpers::calc::from_pips;
+use crate::helpers::comp::percentage_change;
+use crate::helpers::date::*;
+use crate::models::pricing::Pricing;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum GapDirection {
+    Up,
+    Down,
+}
+
+/// Price void between the previous candle range and the open of the next one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Gap {
+    pub date: DateTime<Local>,
+    pub direction: GapDirection,
+    /// Previous candle high for up gaps, low for down gaps
+    pub from: f64,
+    /// Open of the gap candle
+    pub to: f64,
+    pub size_percentage: f64,
+    /// Gap opened with a new session
+    pub session_open: bool,
+    /// Furthest price reached back into the gap
+    pub fill_price: f64,
+    pub filled_at: Option<DateTime<Local>>,
+}
+
+impl Gap {
+    pub fn size(&self) -> f64 {
+        (self.to - self.from).abs()
+    }
+
+    pub fn size_pips(&self, pricing: &Pricing) -> f64 {
+        from_pips(self.size(), pricing)
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.filled_at.is_some()
+    }
+
+    /// Share of the gap already retraced, from 0 to 100
+    pub fn filled_percentage(&self) -> f64 {
+        match self.size() > 0. {
+            true => ((self.to - self.fill_price).abs() / self.size() * 100.).min(100.),
+            false => 100.,
+        }
+    }
+
+    fn fill(&mut self, candle: &Candle) {
+        if self.is_filled() {
+            return;
+        }
+
+        self.fill_price = match self.direction {
+            GapDirection::Up => self.fill_price.min(candle.low()),
+            GapDirection::Down => self.fill_price.max(candle.high()),
+        };
+
+        let is_filled = match self.direction {
+            GapDirection::Up => self.fill_price <= self.from,
+            GapDirection::Down => self.fill_price >= self.from,
+        };
+
+        if is_filled {
+            self.filled_at = Some(candle.date());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct GapStats {
+    pub total: usize,
+    pub filled: usize,
+    pub fill_rate: f64,
+    pub avg_size_percentage: f64,
+    /// Candles it took, on average, to fill the filled gaps
+    pub avg_bars_to_fill: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Gaps {
+    min_percentage: f64,
+    data: Vec<Gap>,
+}
+
+impl Gaps {
+    pub fn new() -> Self {
+        Self {
+            min_percentage: env::var("GAPS_MIN_PERCENTAGE")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(0.),
+            data: vec![],
+        }
+    }
+
+    pub fn from_candles(data: &Vec<Candle>) -> Self {
+        let mut gaps = Self::new();
+        for (prev, candle) in data.iter().zip(data.iter().skip(1)) {
+            gaps.next(prev, candle);
+        }
+        gaps
+    }
+
+    pub fn data(&self) -> &Vec<Gap> {
+        &self.data
+    }
+
+    pub fn open_gaps(&self) -> Vec<&Gap> {
+        self.data.iter().filter(|gap| !gap.is_filled()).collect()
+    }
+
+    pub fn filled_gaps(&self) -> Vec<&Gap> {
+        self.data.iter().filter(|gap| gap.is_filled()).collect()
+    }
+
+    pub fn last_open_gap(&self) -> Option<&Gap> {
+        self.data.iter().rev().find(|gap| !gap.is_filled())
+    }
+
+    /// Fills the open gaps with the candle and records the one it opens, if any.
+    pub fn next(&mut self, prev: &Candle, candle: &Candle) {
+        self.update(candle);
+
+        let direction = match candle.open() {
+            open if open > prev.high() => Some((GapDirection::Up, prev.high())),
+            open if open < prev.low() => Some((GapDirection::Down, prev.low())),
+            _ => None,
+        };
+
+        if let Some((direction, from)) = direction {
+            let size_percentage = percentage_change(from, candle.open());
+            if size_percentage >= self.min_percentage {
+                let mut gap = Gap {
+                    date: candle.date(),
+                    direction,
+                    from,
+                    to: candle.open(),
+                    size_percentage,
+                    session_open: prev.date().date() != candle.date().date(),
+                    fill_price: candle.open(),
+                    filled_at: None,
+                };
+                gap.fill(candle);
+                self.data.push(gap);
+            }
+        }
+    }
+
+    /// Checks the open gaps against the last, still forming, candle.
+    pub fn update(&mut self, candle: &Candle) {
+        for gap in self.data.iter_mut() {
+            gap.fill(candle);
+        }
+    }
+
+    /// Drops the gaps opened before the date.
+    pub fn remove_before(&mut self, date: DateTime<Local>) {
+        self.data.retain(|gap| gap.date >= date);
+    }
+
+    pub fn stats(&self, data: &Vec<Candle>) -> GapStats {
+        Self::calculate_stats(self.data.iter().collect(), data)
+    }
+
+    pub fn session_stats(&self, data: &Vec<Candle>) -> GapStats {
+        Self::calculate_stats(
+            self.data.iter().filter(|gap| gap.session_open).collect(),
+            data,
+        )
+    }
+
+    fn calculate_stats(gaps: Vec<&Gap>, data: &Vec<Candle>) -> GapStats {
+        let total = gaps.len();
+        if total == 0 {
+            return GapStats::default();
+        }
+
+        let position = |date: &DateTime<Local>| data.iter().position(|x| &x.date() == date);
+        let bars_to_fill: Vec<f64> = gaps
+            .iter()
+            .filter_map(|gap| {
+                let opened = position(&gap.date)?;
+                let filled = position(gap.filled_at.as_ref()?)?;
+                Some((filled - opened) as f64)
+            })
+            .collect();
+
+        let filled = gaps.iter().filter(|gap| gap.is_filled()).count();
+
+        GapStats {
+            total,
+            filled,
+            fill_rate: filled as f64 / total as f64 * 100.,
+            avg_size_percentage: gaps.iter().map(|gap| gap.size_percentage).sum::<f64>()
+                / total as f64,
+            avg_bars_to_fill: match bars_to_fill.is_empty() {
+                true => 0.,
+                false => bars_to_fill.iter().sum::<f64>() / bars_to_fill.len() as f64,
+            },
+        }
+    }
+}
+
+impl Default for Gaps {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -83,7 +83,7 @@ impl HorizontalLevels {
                         HorizontalLevel {
                             price,
                             occurrences,
-                            date: to_dbtime(Local::now()),
+                            date: now_dbtime(),
                             level_type,
                         },
                     );
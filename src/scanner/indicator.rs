@@ -16,12 +16,12 @@ use crate::indicators::bb::BollingerB;
 use crate::indicators::bbw::BollingerBW;
 use crate::indicators::ema::Ema;
 use crate::indicators::macd::Macd;
+use crate::indicators::params::IndicatorsParams;
 use crate::indicators::rsi::Rsi;
 
 //use crate::models::indicator::Indicator;
 use crate::indicators::Indicator;
 use serde::{Deserialize, Serialize};
-use std::env;
 
 // pub trait Indicator {
 //     fn new() -> Result<Self>
@@ -54,21 +54,19 @@ pub struct Indicators {
 
 impl Indicators {
     pub fn new() -> Result<Self> {
-        let ema_a = &env::var("EMA_A").unwrap().parse::<usize>().unwrap();
-        let ema_b = &env::var("EMA_B").unwrap().parse::<usize>().unwrap();
-        let ema_c = &env::var("EMA_C").unwrap().parse::<usize>().unwrap();
+        let params = IndicatorsParams::from_env();
 
         Ok(Self {
-            macd: Macd::new().unwrap(),
-            rsi: Rsi::new().unwrap(),
+            macd: Macd::new_with_params(&params.macd)?,
+            rsi: Rsi::new_with_params(&params.rsi)?,
             //stoch: Stoch::new().unwrap(),
-            atr: Atr::new().unwrap(),
+            atr: Atr::new_with_params(&params.atr)?,
             //adx: Adx::new().unwrap(),
-            bb: BollingerB::new().unwrap(),
-            bbw: BollingerBW::new().unwrap(),
-            ema_a: Ema::new_ema(*ema_a).unwrap(),
-            ema_b: Ema::new_ema(*ema_b).unwrap(),
-            ema_c: Ema::new_ema(*ema_c).unwrap(),
+            bb: BollingerB::new_with_params(&params.bb)?,
+            bbw: BollingerBW::new_with_params(&params.bbw)?,
+            ema_a: Ema::new_with_params(&params.ema_a)?,
+            ema_b: Ema::new_with_params(&params.ema_b)?,
+            ema_c: Ema::new_with_params(&params.ema_c)?,
         })
     }
 
@@ -1,22 +1,35 @@
 use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
 use crate::helpers::comp::*;
 use crate::helpers::date::*;
+use crate::indicators::levels::SessionLevels;
+use crate::indicators::params::IndicatorsParams;
+use crate::indicators::pivots::Pivots;
 use crate::indicators::{Indicator, Indicators};
 use crate::models::indicator::CompactIndicators;
 use crate::models::mode::ExecutionMode;
 use crate::models::pricing::Pricing;
 use crate::models::time_frame::*;
 use crate::models::{market::*, mode};
-use crate::scanner::candle::{Candle, CandleType};
+use crate::patterns::divergence::{detect_divergences, DivergenceConfig, DivergenceEvent};
+use crate::patterns::fibonacci::Fibonacci;
+use crate::patterns::formation::{detect_formation, Formation, FormationConfig};
+use crate::patterns::trendlines::Trendlines;
+use crate::patterns::zones::Zones;
+use crate::scanner::candle::{Candle, CandlePatternConfig, CandleType};
 use crate::scanner::divergence::{CompactDivergences, Divergences};
+use crate::scanner::gap::Gaps;
 use crate::scanner::horizontal_level::HorizontalLevels;
 use crate::scanner::pattern::PatternSize;
+use crate::scanner::pattern_event::{pattern_events, PatternEvent, PatternSubscribers};
 use crate::scanner::pattern::Patterns;
 use crate::scanner::peak::Peaks;
+use crate::scanner::volume_profile::VolumeProfile;
 
 use serde::{Deserialize, Serialize};
 use std::env;
 
+const SNAPSHOT_VERSION: u8 = 1;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CompactInstrument {
     pub symbol: String,
@@ -51,6 +64,31 @@ pub struct Instrument {
     pub horizontal_levels: HorizontalLevels,
     pub indicators: Indicators,
     pub divergences: Divergences,
+    #[serde(default)]
+    pub levels: SessionLevels,
+    #[serde(default)]
+    pub pivots: Pivots,
+    #[serde(default)]
+    pub gaps: Gaps,
+    #[serde(default)]
+    pub volume_profile: VolumeProfile,
+    #[serde(default)]
+    pub zones: Zones,
+    #[serde(default)]
+    pub trendlines: Trendlines,
+    #[serde(default)]
+    pub fibonacci: Fibonacci,
+    #[serde(skip)]
+    pub pattern_subscribers: PatternSubscribers,
+    /// Candles, peaks and patterns work on ln prices, indicators on linear ones.
+    #[serde(default = "logarithmic_from_env")]
+    pub logarithmic: bool,
+    /// Ring buffer size, NUM_BARS scaled to the time frame when not set.
+    #[serde(default)]
+    pub max_bars: Option<usize>,
+    /// Resolved once, every candle is built with it.
+    #[serde(default = "CandlePatternConfig::from_env")]
+    pub candle_pattern_config: CandlePatternConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,6 +120,10 @@ impl Instrument {
         &self.time_frame
     }
 
+    pub fn candle_pattern_config(&self) -> &CandlePatternConfig {
+        &self.candle_pattern_config
+    }
+
     pub fn indicators(&self) -> &Indicators {
         &self.indicators
     }
@@ -122,6 +164,11 @@ impl Instrument {
     pub fn patterns(&self) -> &Patterns {
         &self.patterns
     }
+
+    /// Receives the patterns detected, confirmed or invalidated from now on.
+    pub fn subscribe_patterns(&mut self) -> std::sync::mpsc::Receiver<PatternEvent> {
+        self.pattern_subscribers.subscribe()
+    }
     pub fn horizontal_levels(&self) -> &HorizontalLevels {
         &self.horizontal_levels
     }
@@ -130,6 +177,63 @@ impl Instrument {
         &self.divergences
     }
 
+    pub fn levels(&self) -> &SessionLevels {
+        &self.levels
+    }
+
+    pub fn pivots(&self) -> &Pivots {
+        &self.pivots
+    }
+
+    /// Triangle, wedge or flag drawn by the last local peaks.
+    pub fn formation(&self, config: &FormationConfig) -> Option<Formation> {
+        detect_formation(
+            self.peaks.local_maxima(),
+            self.peaks.local_minima(),
+            &self.data,
+            config,
+        )
+    }
+
+    /// Divergences between the price peaks and an indicator series, like
+    /// `indicators().rsi().output("rsi")`.
+    pub fn indicator_divergences(
+        &self,
+        indicator: &[f64],
+        config: &DivergenceConfig,
+    ) -> Vec<DivergenceEvent> {
+        detect_divergences(
+            self.peaks.local_maxima(),
+            self.peaks.local_minima(),
+            indicator,
+            config,
+        )
+    }
+
+    pub fn trendlines(&self) -> &Trendlines {
+        &self.trendlines
+    }
+
+    pub fn fibonacci(&self) -> &Fibonacci {
+        &self.fibonacci
+    }
+
+    pub fn zones(&self) -> &Zones {
+        &self.zones
+    }
+
+    pub fn volume_profile(&self) -> &VolumeProfile {
+        &self.volume_profile
+    }
+
+    pub fn gaps(&self) -> &Gaps {
+        &self.gaps
+    }
+
+    pub fn is_logarithmic(&self) -> bool {
+        self.logarithmic
+    }
+
     pub fn get_scale_ohlc(
         &self,
         x: (DateTime<Local>, f64, f64, f64, f64, f64, bool),
@@ -166,6 +270,16 @@ impl Instrument {
         (open, high, low, close)
     }
 
+    fn scale_dohlc(
+        &self,
+        x: (DateTime<Local>, f64, f64, f64, f64, f64),
+        logarithmic_scanner: bool,
+    ) -> (DateTime<Local>, f64, f64, f64, f64, f64) {
+        let (open, high, low, close) =
+            self.get_scale_ohlc((x.0, x.1, x.2, x.3, x.4, x.5, true), logarithmic_scanner);
+        (x.0, open, high, low, close, x.5)
+    }
+
     pub fn get_scale_ohlc_indicators(
         &mut self,
         candle: &Candle,
@@ -217,8 +331,12 @@ impl Instrument {
             .close(close)
             .volume(volume)
             .is_closed(is_closed)
-            .previous_candles(vec![data[pre_0], data[prev_1]])
+            .previous_candles(vec![
+                self.scale_dohlc(data[pre_0], logarithmic_scanner),
+                self.scale_dohlc(data[prev_1], logarithmic_scanner),
+            ])
             .logarithmic(logarithmic_scanner)
+            .pattern_config(self.candle_pattern_config.clone())
             .build()
             .unwrap()
     }
@@ -276,6 +394,7 @@ impl Instrument {
             .is_closed(is_closed)
             .previous_candles(vec![last_candle, second_last_candle])
             .logarithmic(logarithmic_scanner)
+            .pattern_config(self.candle_pattern_config.clone())
             .build()
             .unwrap()
     }
@@ -285,10 +404,7 @@ impl Instrument {
         data: Vec<(DateTime<Local>, f64, f64, f64, f64, f64)>,
     ) -> Result<()> {
         let mut avg_volume = vec![];
-        let logarithmic_scanner = env::var("LOGARITHMIC_SCANNER")
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
+        let logarithmic_scanner = self.logarithmic;
 
         let process_indicators = env::var("INDICATORS").unwrap().parse::<bool>().unwrap();
         let process_patterns = env::var("PATTERNS").unwrap().parse::<bool>().unwrap();
@@ -350,6 +466,14 @@ impl Instrument {
                         self.indicators
                             .next(ohlc_indicators, delete_previous, &self.time_frame().clone())
                             .unwrap();
+                        self.indicators
+                            .next_volume(
+                                ohlc_indicators.3,
+                                candle.volume(),
+                                delete_previous,
+                                &self.time_frame().clone(),
+                            )
+                            .unwrap();
                     } else {
                         self.indicators.duplicate_last().unwrap();
                     }
@@ -370,12 +494,14 @@ impl Instrument {
                 // let extrema_maxima = self.peaks.extrema_maxima();
                 // let extrema_minima = self.peaks.extrema_minima();
 
+                let previous_patterns = self.patterns.clone();
                 self.patterns.detect_pattern(
                     PatternSize::Local,
                     local_maxima,
                     local_minima,
                     &candles,
                 );
+                self.emit_pattern_events(&previous_patterns);
 
                 // self.patterns.process_pattern(
                 //     PatternSize::Extrema,
@@ -430,6 +556,26 @@ impl Instrument {
                 .collect();
 
             //self.data = candles;
+            self.levels = SessionLevels::from_candles(&self.data);
+            self.pivots = self.pivots.from_candles(&self.data);
+            self.gaps = Gaps::from_candles(&self.data);
+            self.volume_profile = self.volume_profile.from_candles(&self.data);
+            self.zones = self.zones.from_peaks(
+                self.peaks.local_maxima(),
+                self.peaks.local_minima(),
+                &self.data,
+                Some(&self.volume_profile),
+            );
+            self.trendlines = self.trendlines.from_peaks(
+                self.peaks.local_maxima(),
+                self.peaks.local_minima(),
+                &self.data,
+            );
+            self.refresh_fibonacci();
+
+            if let Some(max_bars) = self.max_bars {
+                self.evict(max_bars);
+            }
 
             self.set_current_price(self.data.last().unwrap().close());
 
@@ -441,10 +587,7 @@ impl Instrument {
     }
 
     pub fn next(&mut self, data: (DateTime<Local>, f64, f64, f64, f64, f64)) -> Result<Candle> {
-        let logarithmic_scanner = env::var("LOGARITHMIC_SCANNER")
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
+        let logarithmic_scanner = self.logarithmic;
 
         let next_id = self.data.len();
         let last_candle = &self.data().last().unwrap().clone();
@@ -456,10 +599,16 @@ impl Instrument {
         if candle.is_closed() {
             self.close_last_candle();
             self.close_indicators(&last_candle);
+            self.trendlines.next(&last_candle, self.data.len() - 1);
             //self.next_peaks(&last_candle);
         } else {
             self.adapt_last_candle_tf(candle.clone(), &last_candle, time_frame);
             let updated_candle = &self.data.last().unwrap().clone();
+            self.levels.update(updated_candle);
+            self.pivots.update(updated_candle);
+            self.gaps.update(updated_candle);
+            self.volume_profile.update(updated_candle);
+            self.zones.next(updated_candle, self.data.len() - 1);
             self.update_indicators(&updated_candle);
         }
 
@@ -467,10 +616,7 @@ impl Instrument {
     }
 
     pub fn close_indicators(&mut self, candle: &Candle) {
-        let logarithmic_scanner = env::var("LOGARITHMIC_SCANNER")
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
+        let logarithmic_scanner = self.logarithmic;
 
         let process_indicators = env::var("INDICATORS").unwrap().parse::<bool>().unwrap();
         if process_indicators {
@@ -479,10 +625,60 @@ impl Instrument {
             self.indicators
                 .next_close_delete(ohlc_indicators, &self.time_frame().clone())
                 .unwrap();
+            self.indicators
+                .next_close_delete_volume(
+                    ohlc_indicators.3,
+                    candle.volume(),
+                    &self.time_frame().clone(),
+                )
+                .unwrap();
             self.indicators.duplicate_last().unwrap();
         }
     }
 
+    /// CBOR encoded snapshot, prefixed by the snapshot format version.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![SNAPSHOT_VERSION];
+        ciborium::ser::into_writer(self, &mut bytes).map_err(|_| snapshot_error())?;
+        Ok(bytes)
+    }
+
+    /// Indicators internal state is not part of the snapshot, it is replayed
+    /// from the candles.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        match bytes.split_first() {
+            Some((&SNAPSHOT_VERSION, payload)) => {
+                let mut instrument: Instrument =
+                    ciborium::de::from_reader(payload).map_err(|_| snapshot_error())?;
+                instrument.warm_up_indicators()?;
+                Ok(instrument)
+            }
+            _ => Err(snapshot_error()),
+        }
+    }
+
+    /// Restores the indicators internal state after loading the instrument from the db.
+    pub fn warm_up_indicators(&mut self) -> Result<()> {
+        let logarithmic_scanner = self.logarithmic;
+
+        let data: Vec<(f64, f64, f64, f64)> = self
+            .data
+            .clone()
+            .iter()
+            .map(|candle| self.get_scale_ohlc_indicators(candle, logarithmic_scanner))
+            .collect();
+
+        let volume_data: Vec<(f64, f64)> = self
+            .data
+            .iter()
+            .zip(data.iter())
+            .map(|(candle, OHLC)| (OHLC.3, candle.volume()))
+            .collect();
+
+        self.indicators.warm_up(&data)?;
+        self.indicators.warm_up_volume(&volume_data)
+    }
+
     pub fn update_indicators(&mut self, candle: &Candle) {
         let process_indicators = env::var("INDICATORS").unwrap().parse::<bool>().unwrap();
 
@@ -490,29 +686,54 @@ impl Instrument {
             self.indicators
                 .create_tmp_indicators(candle, &self.data)
                 .unwrap();
+            self.indicators
+                .create_tmp_volume_indicators(candle, &self.data)
+                .unwrap();
         }
     }
 
     pub fn next_peaks(&mut self, candle: &Candle) {
-        let _logarithmic_scanner = env::var("LOGARITHMIC_SCANNER")
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
         let process_patterns = env::var("PATTERNS").unwrap().parse::<bool>().unwrap();
         if process_patterns {
-            //FIXME peaks next detection iterates the whole list
             self.peaks.update(candle);
             self.peaks
-                .calculate_peaks(&self.max_price, &self.min_price, &0)
+                .calculate_peaks_incremental(&self.max_price, &self.min_price)
                 .unwrap();
             let local_maxima = self.peaks.local_maxima();
             let local_minima = self.peaks.local_minima();
             //Fixme CALCULATE ONLY LAST CHANGES clean first pattern
+            let previous_patterns = self.patterns.clone();
             self.patterns
                 .update(PatternSize::Local, local_maxima, local_minima, &self.data);
+            self.emit_pattern_events(&previous_patterns);
+            self.refresh_fibonacci();
         }
     }
 
+    fn emit_pattern_events(&mut self, previous_patterns: &Patterns) {
+        if self.pattern_subscribers.is_empty() {
+            return;
+        }
+
+        let events = pattern_events(
+            &self.symbol,
+            &self.time_frame,
+            previous_patterns,
+            &self.patterns,
+        );
+        self.pattern_subscribers.send(&events);
+    }
+
+    /// Recomputes the fibonacci levels if the peaks formed a new swing.
+    pub fn refresh_fibonacci(&mut self) -> bool {
+        self.fibonacci.next(
+            self.peaks.extrema_maxima(),
+            self.peaks.extrema_minima(),
+            self.peaks.local_maxima(),
+            self.peaks.local_minima(),
+        )
+    }
+
     pub fn close_last_candle(&mut self) {
         let last_candle = self.data.last_mut().unwrap();
         last_candle.set_is_closed(true);
@@ -554,17 +775,55 @@ impl Instrument {
         *self.data.last_mut().unwrap() = candle.clone();
     }
 
+    pub fn max_bars(&self) -> usize {
+        match self.max_bars {
+            Some(max_bars) => max_bars,
+            None => {
+                let num_bars = env::var("NUM_BARS").unwrap().parse::<usize>().unwrap();
+                num_bars / self.time_frame.to_number() as usize
+            }
+        }
+    }
+
+    pub fn set_max_bars(&mut self, max_bars: Option<usize>) {
+        self.max_bars = max_bars;
+    }
+
+    /// Evicts the oldest candles above `max_bars` together with everything
+    /// computed from them, shifting indexes so they keep pointing to the same
+    /// candles. Returns the number of evicted candles.
+    pub fn evict(&mut self, max_bars: usize) -> usize {
+        let len = self.data.len();
+        if len <= max_bars {
+            return 0;
+        }
+
+        let num_bars = len - max_bars;
+        self.data.drain(..num_bars);
+        for _ in 0..num_bars {
+            self.levels.remove(0);
+            self.pivots.remove(0);
+        }
+        if let Some(first) = self.data.first() {
+            self.gaps.remove_before(first.date());
+        }
+        self.peaks.evict(num_bars);
+        self.patterns.evict(num_bars);
+        self.divergences.evict(num_bars);
+        self.zones.evict(num_bars);
+        self.trendlines.evict(num_bars);
+        self.fibonacci.evict(num_bars);
+        self.indicators.trim(max_bars);
+
+        num_bars
+    }
+
     pub fn init_candle(
         &mut self,
         data: (DateTime<Local>, f64, f64, f64, f64, f64),
         time_frame: &Option<TimeFrameType>,
     ) {
-        let logarithmic_scanner = env::var("LOGARITHMIC_SCANNER")
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
-
-        let num_bars = env::var("NUM_BARS").unwrap().parse::<usize>().unwrap();
+        let logarithmic_scanner = self.logarithmic;
 
         let adapted = adapt_to_timeframe(data, &self.time_frame, true);
         let open_from = get_open_from(data, &self.time_frame, true);
@@ -576,22 +835,44 @@ impl Instrument {
         candle.set_is_closed(false);
         candle.set_date(open_from);
 
-        let max_bars = num_bars / time_frame.clone().unwrap().to_number() as usize;
+        let max_bars = match self.max_bars {
+            Some(max_bars) => max_bars,
+            None => {
+                let num_bars = env::var("NUM_BARS").unwrap().parse::<usize>().unwrap();
+                num_bars / time_frame.clone().unwrap().to_number() as usize
+            }
+        };
+
+        self.evict(max_bars);
 
-        if len > max_bars {
-            self.data.remove(0);
+        if let Some(last_candle) = self.data.last() {
+            self.gaps.next(last_candle, &candle);
         }
-
+        self.levels.next(&candle);
+        self.pivots.next(&candle);
+        self.volume_profile.next(&candle);
+        self.zones.next(&candle, self.data.len());
+        self.zones.set_volume(&self.volume_profile);
         self.data.push(candle);
     }
 
     pub fn init(&mut self) {
         self.data = vec![];
-        self.peaks = Peaks::new();
+        self.peaks = Peaks::new().logarithmic(self.logarithmic);
         self.horizontal_levels = HorizontalLevels::new();
         self.patterns = Patterns::new();
-        self.indicators = Indicators::new().unwrap();
+        self.indicators = self.indicators.reset().unwrap();
         self.divergences = Divergences::new().unwrap();
+        self.levels = SessionLevels::new();
+        self.gaps = Gaps::new();
+        self.volume_profile = self.volume_profile.from_candles(&vec![]);
+        self.zones = self.zones.from_peaks(&[], &[], &[], None);
+        self.trendlines = self.trendlines.from_peaks(&[], &[], &[]);
+        self.fibonacci = Fibonacci::new();
+        self.pivots = Pivots::new(
+            self.pivots.pivot_type().clone(),
+            self.pivots.time_frame().clone(),
+        );
         //self.set_data(data).unwrap();
     }
 }
@@ -600,7 +881,10 @@ pub struct InstrumentBuilder {
     symbol: Option<String>,
     market: Option<Market>,
     time_frame: Option<TimeFrameType>,
-    //indicators: Option<Indicators>,
+    indicator_params: Option<IndicatorsParams>,
+    logarithmic: Option<bool>,
+    max_bars: Option<usize>,
+    candle_pattern_config: Option<CandlePatternConfig>,
 }
 
 impl InstrumentBuilder {
@@ -609,6 +893,10 @@ impl InstrumentBuilder {
             symbol: None,
             market: None,
             time_frame: None,
+            indicator_params: None,
+            logarithmic: None,
+            max_bars: None,
+            candle_pattern_config: None,
         }
     }
     pub fn symbol(mut self, val: &str) -> Self {
@@ -626,26 +914,65 @@ impl InstrumentBuilder {
         self
     }
 
+    pub fn logarithmic(mut self, val: bool) -> Self {
+        self.logarithmic = Some(val);
+        self
+    }
+
+    pub fn max_bars(mut self, val: usize) -> Self {
+        self.max_bars = Some(val);
+        self
+    }
+
+    pub fn indicator_params(mut self, val: IndicatorsParams) -> Self {
+        self.indicator_params = Some(val);
+        self
+    }
+
+    pub fn candle_pattern_config(mut self, val: CandlePatternConfig) -> Self {
+        self.candle_pattern_config = Some(val);
+        self
+    }
+
     pub fn build(self) -> Result<Instrument> {
         if let (Some(symbol), Some(market), Some(time_frame)) =
             (self.symbol, self.market, self.time_frame)
         {
+            let indicator_params = self
+                .indicator_params
+                .unwrap_or_else(IndicatorsParams::from_env);
+            let logarithmic = self.logarithmic.unwrap_or_else(logarithmic_from_env);
+            let candle_pattern_config = self
+                .candle_pattern_config
+                .unwrap_or_else(CandlePatternConfig::from_env);
+
             Ok(Instrument {
                 symbol,
                 market,
                 time_frame,
                 current_price: 0.,
-                date: to_dbtime(Local::now()), //FIXME
+                date: now_dbtime(), //FIXME
                 current_candle: CandleType::Default,
                 min_price: env::var("MIN_PRICE").unwrap().parse::<f64>().unwrap(),
                 max_price: env::var("MIN_PRICE").unwrap().parse::<f64>().unwrap(),
                 avg_volume: 0.,
                 data: vec![],
-                peaks: Peaks::new(),
+                peaks: Peaks::new().logarithmic(logarithmic),
                 horizontal_levels: HorizontalLevels::new(),
                 patterns: Patterns::new(),
-                indicators: Indicators::new().unwrap(),
+                indicators: Indicators::new_with_params(&indicator_params)?,
                 divergences: Divergences::new().unwrap(),
+                levels: SessionLevels::new(),
+                pivots: Pivots::from_env(),
+                gaps: Gaps::new(),
+                volume_profile: VolumeProfile::new(),
+                zones: Zones::new(),
+                trendlines: Trendlines::new(),
+                fibonacci: Fibonacci::new(),
+                pattern_subscribers: PatternSubscribers::new(),
+                logarithmic,
+                max_bars: self.max_bars,
+                candle_pattern_config,
             })
         } else {
             Err(RsAlgoError {
@@ -654,3 +981,16 @@ impl InstrumentBuilder {
         }
     }
 }
+
+fn snapshot_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::SnapshotError,
+    }
+}
+
+fn logarithmic_from_env() -> bool {
+    env::var("LOGARITHMIC_SCANNER")
+        .ok()
+        .and_then(|val| val.parse::<bool>().ok())
+        .unwrap_or(false)
+}
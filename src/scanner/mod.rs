@@ -1,8 +1,17 @@
+pub mod alt_charts;
 pub mod candle;
 pub mod divergence;
+pub mod gap;
 pub mod horizontal_level;
 pub mod indicator;
 pub mod instrument;
+pub mod mtf;
 pub mod pattern;
+pub mod pattern_event;
 pub mod peak;
 pub mod prices;
+pub mod regime;
+pub mod snapshot;
+pub mod tick_aggregator;
+pub mod validate;
+pub mod volume_profile;
@@ -0,0 +1,147 @@
+use super::candle::Candle;
+use super::instrument::{HTFInstrument, Instrument};
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::helpers::date::*;
+use crate::models::market::Market;
+use crate::models::time_frame::{TimeFrameAggregator, TimeFrameType};
+
+use serde::{Deserialize, Serialize};
+
+type DOHLC = (DateTime<Local>, f64, f64, f64, f64, f64);
+
+/// Base instrument plus the higher time frame instruments derived from it.
+/// Every feed goes through `next` so all of them stay in sync.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiTimeFrameInstrument {
+    base: Instrument,
+    higher: Vec<Instrument>,
+}
+
+impl MultiTimeFrameInstrument {
+    pub fn new(
+        symbol: &str,
+        market: Market,
+        base_time_frame: TimeFrameType,
+        higher_time_frames: &[TimeFrameType],
+    ) -> Result<Self> {
+        let build = |time_frame: &TimeFrameType| {
+            Instrument::new()
+                .symbol(symbol)
+                .market(market.clone())
+                .time_frame(time_frame.clone())
+                .build()
+        };
+
+        let mut higher = vec![];
+        for time_frame in higher_time_frames {
+            if time_frame.to_minutes() <= base_time_frame.to_minutes() {
+                log::error!(
+                    "{} is not higher than the base time frame {}",
+                    time_frame,
+                    base_time_frame
+                );
+                return Err(RsAlgoError {
+                    err: RsAlgoErrorKind::WrongInstrumentConf,
+                });
+            }
+            higher.push(build(time_frame)?);
+        }
+
+        Ok(Self {
+            base: build(&base_time_frame)?,
+            higher,
+        })
+    }
+
+    pub fn base(&self) -> &Instrument {
+        &self.base
+    }
+
+    pub fn higher(&self) -> &Vec<Instrument> {
+        &self.higher
+    }
+
+    pub fn get(&self, time_frame: &TimeFrameType) -> Option<&Instrument> {
+        match &self.base.time_frame == time_frame {
+            true => Some(&self.base),
+            false => self
+                .higher
+                .iter()
+                .find(|instrument| &instrument.time_frame == time_frame),
+        }
+    }
+
+    /// Wraps a higher time frame for the `get_htf_data` style helpers.
+    pub fn htf_instrument(&self, time_frame: &TimeFrameType) -> HTFInstrument {
+        match self
+            .higher
+            .iter()
+            .find(|instrument| &instrument.time_frame == time_frame)
+        {
+            Some(instrument) => HTFInstrument::HTFInstrument(instrument.clone()),
+            None => HTFInstrument::None,
+        }
+    }
+
+    /// Higher time frames are resampled from the base data.
+    pub fn set_data(&mut self, data: Vec<DOHLC>) -> Result<()> {
+        let base_time_frame = self.base.time_frame.clone();
+
+        for instrument in self.higher.iter_mut() {
+            let htf_data: Vec<DOHLC> = TimeFrameAggregator::aggregate_from(
+                &instrument.time_frame.clone(),
+                &base_time_frame,
+                &data,
+            )
+            .into_iter()
+            .map(|(date, open, high, low, close, volume, _)| (date, open, high, low, close, volume))
+            .collect();
+
+            instrument.set_data(htf_data)?;
+        }
+
+        self.base.set_data(data)
+    }
+
+    /// Feeds the same bar or tick to every time frame. Returns the base candle.
+    pub fn next(&mut self, data: DOHLC) -> Result<Candle> {
+        for instrument in self.higher.iter_mut() {
+            instrument.next(data)?;
+        }
+        self.base.next(data)
+    }
+
+    /// Index of the higher time frame candle containing the base candle.
+    pub fn htf_index(&self, base_index: usize, time_frame: &TimeFrameType) -> Option<usize> {
+        let date = self.base.data.get(base_index)?.date();
+        let instrument = self.get(time_frame)?;
+
+        match instrument
+            .data
+            .partition_point(|candle| candle.date() <= date)
+        {
+            0 => None,
+            idx => Some(idx - 1),
+        }
+    }
+
+    /// Index of the last higher time frame candle already closed at the base
+    /// candle close. Use it in backtests to avoid looking into the future.
+    pub fn closed_htf_index(&self, base_index: usize, time_frame: &TimeFrameType) -> Option<usize> {
+        let idx = self.htf_index(base_index, time_frame)?;
+        let base_close = self.base.data.get(base_index)?.date()
+            + Duration::minutes(self.base.time_frame.to_minutes());
+        let htf_close = self.get(time_frame)?.data.get(idx)?.date()
+            + Duration::minutes(time_frame.to_minutes());
+
+        match base_close >= htf_close {
+            true => Some(idx),
+            false => idx.checked_sub(1),
+        }
+    }
+
+    pub fn htf_candle(&self, base_index: usize, time_frame: &TimeFrameType) -> Option<&Candle> {
+        let idx = self.htf_index(base_index, time_frame)?;
+        self.get(time_frame)?.data.get(idx)
+    }
+}
@@ -138,6 +138,25 @@ impl Patterns {
         self.detect_pattern(pattern_size, maxima, minima, candles);
     }
 
+    /// Drops the patterns drawn over the first `num_bars` candles and shifts
+    /// the indexes of the rest once those candles are evicted.
+    pub fn evict(&mut self, num_bars: usize) {
+        for patterns in [&mut self.local_patterns, &mut self.extrema_patterns] {
+            patterns.retain(|pattern| {
+                pattern
+                    .data_points
+                    .iter()
+                    .all(|(index, _)| *index >= num_bars)
+            });
+
+            for pattern in patterns.iter_mut() {
+                pattern.index = pattern.index.saturating_sub(num_bars);
+                pattern.active.index = pattern.active.index.saturating_sub(num_bars);
+                evict_points(&mut pattern.data_points, num_bars);
+            }
+        }
+    }
+
     pub fn update(
         &mut self,
         pattern_size: PatternSize,
@@ -170,6 +189,10 @@ impl Patterns {
             .parse::<usize>()
             .unwrap();
 
+        let from = lookback_from(candles.len(), lookback_from_env("PATTERNS_LOOKBACK"));
+        let maxima = &lookback_points(maxima, from);
+        let minima = &lookback_points(minima, from);
+
         let mut max_start = 0;
         let mut max_end = 0;
         let mut min_start = 0;
@@ -762,6 +785,36 @@ impl Patterns {
     }
 }
 
+/// Index of the first of the last `lookback` bars, 0 when the whole data is used.
+pub fn lookback_from(len: usize, lookback: Option<usize>) -> usize {
+    lookback.map_or(0, |lookback| len.saturating_sub(lookback))
+}
+
+/// Points from the index on. Indexes are kept, relative to the whole data.
+pub fn lookback_points(points: &[(usize, f64)], from: usize) -> Vec<(usize, f64)> {
+    points
+        .iter()
+        .filter(|(index, _)| *index >= from)
+        .copied()
+        .collect()
+}
+
+/// Lookback bars of a detector, unset or 0 for the whole data.
+pub fn lookback_from_env(key: &str) -> Option<usize> {
+    env::var(key)
+        .ok()
+        .and_then(|val| val.parse::<usize>().ok())
+        .filter(|val| *val > 0)
+}
+
+/// Removes the points of the first `num_bars` candles and shifts the rest.
+pub fn evict_points(points: &mut Vec<(usize, f64)>, num_bars: usize) {
+    points.retain(|(index, _)| *index >= num_bars);
+    for point in points.iter_mut() {
+        point.0 -= num_bars;
+    }
+}
+
 pub fn pattern_active_result(
     data: &DataPoints,
     top: PatternActiveResult,
@@ -0,0 +1,157 @@
+use crate::helpers::date::DbDateTime;
+use crate::models::time_frame::TimeFrameType;
+use crate::scanner::pattern::{Pattern, PatternDirection, PatternSize, PatternType, Patterns};
+
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PatternEventType {
+    Detected,
+    /// Price broke out of the pattern
+    Confirmed,
+    /// Pattern no longer detected before being confirmed
+    Invalidated,
+}
+
+impl std::fmt::Display for PatternEventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PatternEvent {
+    pub event_type: PatternEventType,
+    pub symbol: String,
+    pub time_frame: TimeFrameType,
+    /// Candle index of the pattern, or of the breakout when confirmed
+    pub index: usize,
+    pub date: DbDateTime,
+    pub pattern_type: PatternType,
+    pub pattern_size: PatternSize,
+    pub direction: PatternDirection,
+}
+
+impl PatternEvent {
+    fn new(
+        event_type: PatternEventType,
+        symbol: &str,
+        time_frame: &TimeFrameType,
+        pattern: &Pattern,
+    ) -> Self {
+        let (index, date) = match event_type {
+            PatternEventType::Confirmed => (pattern.active.index, pattern.active.date),
+            _ => (pattern.index, pattern.date),
+        };
+
+        Self {
+            event_type,
+            symbol: symbol.to_owned(),
+            time_frame: time_frame.clone(),
+            index,
+            date,
+            pattern_type: pattern.pattern_type.clone(),
+            pattern_size: pattern.pattern_size.clone(),
+            direction: pattern.direction.clone(),
+        }
+    }
+}
+
+/// Same pattern in two detections: type, direction and first point.
+fn is_same(a: &Pattern, b: &Pattern) -> bool {
+    a.pattern_type == b.pattern_type
+        && a.direction == b.direction
+        && a.pattern_size == b.pattern_size
+        && a.data_points.first().map(|(index, _)| index)
+            == b.data_points.first().map(|(index, _)| index)
+}
+
+/// Events between two detections of the instrument patterns.
+pub fn pattern_events(
+    symbol: &str,
+    time_frame: &TimeFrameType,
+    previous: &Patterns,
+    current: &Patterns,
+) -> Vec<PatternEvent> {
+    let mut events = vec![];
+
+    for (previous, current) in [
+        (&previous.local_patterns, &current.local_patterns),
+        (&previous.extrema_patterns, &current.extrema_patterns),
+    ] {
+        for pattern in current {
+            match previous.iter().find(|x| is_same(x, pattern)) {
+                None => {
+                    events.push(PatternEvent::new(
+                        PatternEventType::Detected,
+                        symbol,
+                        time_frame,
+                        pattern,
+                    ));
+                    if pattern.active.active {
+                        events.push(PatternEvent::new(
+                            PatternEventType::Confirmed,
+                            symbol,
+                            time_frame,
+                            pattern,
+                        ));
+                    }
+                }
+                Some(old) if !old.active.active && pattern.active.active => {
+                    events.push(PatternEvent::new(
+                        PatternEventType::Confirmed,
+                        symbol,
+                        time_frame,
+                        pattern,
+                    ));
+                }
+                _ => (),
+            }
+        }
+
+        for pattern in previous {
+            let is_gone = !current.iter().any(|x| is_same(x, pattern));
+            if is_gone && !pattern.active.active {
+                events.push(PatternEvent::new(
+                    PatternEventType::Invalidated,
+                    symbol,
+                    time_frame,
+                    pattern,
+                ));
+            }
+        }
+    }
+
+    events
+}
+
+/// Senders of the pattern event subscribers. Closed channels are dropped on send.
+#[derive(Debug, Clone, Default)]
+pub struct PatternSubscribers {
+    senders: Vec<Sender<PatternEvent>>,
+}
+
+impl PatternSubscribers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<PatternEvent> {
+        let (sender, receiver) = channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    pub fn send(&mut self, events: &[PatternEvent]) {
+        self.senders.retain(|sender| {
+            events
+                .iter()
+                .all(|event| sender.send(event.clone()).is_ok())
+        });
+    }
+}
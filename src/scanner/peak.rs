@@ -1,10 +1,39 @@
 use crate::error::Result;
-use crate::helpers::maxima_minima::maxima_minima;
-use crate::helpers::regression::kernel_regression;
+use crate::helpers::date::{to_dbtime, DbDateTime};
+use crate::helpers::maxima_minima::{fractals_scaled, maxima_minima_scaled, peak_prominence};
+use crate::helpers::regression::{kernel_regression, KernelRegression};
 use serde::{Deserialize, Serialize};
 use std::env;
 
 use super::candle::Candle;
+use super::pattern::{evict_points, lookback_from, lookback_from_env};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SwingKind {
+    High,
+    Low,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SwingPoint {
+    pub index: usize,
+    pub price: f64,
+    pub kind: SwingKind,
+    /// Candle date, None when the peaks were built without dates
+    pub date: Option<DbDateTime>,
+    /// Height over the surrounding prices, in linear price units
+    pub prominence: f64,
+}
+
+impl SwingPoint {
+    pub fn is_high(&self) -> bool {
+        self.kind == SwingKind::High
+    }
+
+    pub fn point(&self) -> (usize, f64) {
+        (self.index, self.price)
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Peaks {
@@ -18,6 +47,25 @@ pub struct Peaks {
     pub smooth_close: Vec<(usize, f64)>,
     pub extrema_maxima: Vec<(usize, f64)>,
     pub extrema_minima: Vec<(usize, f64)>,
+    /// Prices are ln scaled, peak values are returned linear.
+    #[serde(default)]
+    pub logarithmic: bool,
+    #[serde(default)]
+    pub dates: Vec<DbDateTime>,
+    /// Local maxima and minima as swing points, sorted by index
+    #[serde(default)]
+    pub local_swings: Vec<SwingPoint>,
+    #[serde(default)]
+    pub extrema_swings: Vec<SwingPoint>,
+    #[serde(default)]
+    pub method: PeaksMethod,
+    /// Smoothing state of the incremental mode, rebuilt when missing.
+    #[serde(skip)]
+    kernel_highs: KernelRegression,
+    #[serde(skip)]
+    kernel_lows: KernelRegression,
+    #[serde(skip)]
+    kernel_close: KernelRegression,
 }
 
 impl Peaks {
@@ -33,9 +81,30 @@ impl Peaks {
             smooth_close: vec![],
             extrema_maxima: vec![],
             extrema_minima: vec![],
+            logarithmic: env::var("LOGARITHMIC_SCANNER")
+                .ok()
+                .and_then(|val| val.parse::<bool>().ok())
+                .unwrap_or(false),
+            dates: vec![],
+            local_swings: vec![],
+            extrema_swings: vec![],
+            method: PeaksMethod::from_env(),
+            kernel_highs: KernelRegression::default(),
+            kernel_lows: KernelRegression::default(),
+            kernel_close: KernelRegression::default(),
         }
     }
 
+    pub fn logarithmic(mut self, val: bool) -> Self {
+        self.logarithmic = val;
+        self
+    }
+
+    pub fn method(mut self, val: PeaksMethod) -> Self {
+        self.method = val;
+        self
+    }
+
     pub fn highs(&self) -> &Vec<f64> {
         &self.highs
     }
@@ -68,10 +137,19 @@ impl Peaks {
         &self.extrema_minima
     }
 
+    pub fn local_swings(&self) -> &Vec<SwingPoint> {
+        &self.local_swings
+    }
+
+    pub fn extrema_swings(&self) -> &Vec<SwingPoint> {
+        &self.extrema_swings
+    }
+
     pub fn next(&mut self, candle: &Candle) {
         self.highs.push(candle.high());
         self.lows.push(candle.low());
         self.close.push(candle.close());
+        self.dates.push(to_dbtime(candle.date()));
     }
 
     pub fn next_delete(&mut self, candle: &Candle) {
@@ -82,10 +160,51 @@ impl Peaks {
             self.highs.remove(0);
             self.lows.remove(0);
             self.close.remove(0);
+            if !self.dates.is_empty() {
+                self.dates.remove(0);
+            }
         }
         self.highs.push(candle.high());
         self.lows.push(candle.low());
         self.close.push(candle.close());
+        self.dates.push(to_dbtime(candle.date()));
+    }
+
+    /// Forgets the first `num_bars` candles, keeping peak indexes aligned
+    /// with the instrument data.
+    pub fn evict(&mut self, num_bars: usize) {
+        for values in [&mut self.highs, &mut self.lows, &mut self.close] {
+            let num_bars = num_bars.min(values.len());
+            values.drain(..num_bars);
+        }
+        self.dates.drain(..num_bars.min(self.dates.len()));
+
+        for swings in [&mut self.local_swings, &mut self.extrema_swings] {
+            swings.retain(|swing| swing.index >= num_bars);
+            for swing in swings.iter_mut() {
+                swing.index -= num_bars;
+            }
+        }
+
+        for points in [
+            &mut self.local_maxima,
+            &mut self.local_minima,
+            &mut self.smooth_highs,
+            &mut self.smooth_lows,
+            &mut self.smooth_close,
+            &mut self.extrema_maxima,
+            &mut self.extrema_minima,
+        ] {
+            evict_points(points, num_bars);
+        }
+
+        for kernel in [
+            &mut self.kernel_highs,
+            &mut self.kernel_lows,
+            &mut self.kernel_close,
+        ] {
+            kernel.remove_first(num_bars);
+        }
     }
 
     pub fn update(&mut self, candle: &Candle) {
@@ -96,6 +215,9 @@ impl Peaks {
         *highs = candle.high();
         *lows = candle.low();
         *close = candle.close();
+        if let Some(date) = self.dates.get_mut(last_index) {
+            *date = to_dbtime(candle.date());
+        }
     }
 
     pub fn calculate_peaks(
@@ -114,61 +236,34 @@ impl Peaks {
         //     _ => self.lows[self.lows.len() - start_index..self.lows.len() - 1].to_vec(),
         // };
 
-        let mut local_prominence = env::var("LOCAL_MIN_PROMINENCE")
-            .unwrap()
-            .parse::<f64>()
-            .unwrap();
-
         let _extrema_prominence = env::var("EXTREMA_MIN_PROMINENCE")
             .unwrap()
             .parse::<f64>()
             .unwrap();
 
-        let local_min_distance = env::var("LOCAL_PROMINENCE_MIN_DISTANCE")
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-
         let _extrema_min_distance = env::var("EXTREMA_PROMINENCE_MIN_DISTANCE")
             .unwrap()
             .parse::<usize>()
             .unwrap();
 
-        let price_smoothing = env::var("KERNEL_PRICE_SMOOTHING")
-            .unwrap()
-            .parse::<bool>()
-            .unwrap();
-
-        let mut kernel_bandwidth = env::var("KERNEL_REGRESSION_BANDWIDTH")
-            .unwrap()
-            .parse::<f64>()
-            .unwrap();
-
-        let price_source = env::var("PRICE_SOURCE").unwrap();
+        let config = PeaksConfig::from_env(max_price, min_price, self.method);
 
-        let mut smooth_highs: Vec<f64> = vec![];
-        let mut smooth_lows: Vec<f64> = vec![];
-        let mut smooth_close: Vec<f64> = vec![];
-
-        let price_diff = max_price - min_price;
-        local_prominence *= price_diff;
-
-        if price_smoothing {
-            kernel_bandwidth *= price_diff;
+        self.smooth_highs = vec![];
+        self.smooth_lows = vec![];
+        self.smooth_close = vec![];
 
+        if config.price_smoothing {
             let mut candle_id = 0;
 
             for x in &self.close {
-                if price_source == "highs_lows" {
-                    let smoothed_high = kernel_regression(kernel_bandwidth, *x, &self.highs);
-                    let smoothed_low = kernel_regression(kernel_bandwidth, *x, &self.lows);
-                    smooth_highs.push(smoothed_high.abs());
-                    smooth_lows.push(smoothed_low.abs());
+                if config.price_source == "highs_lows" {
+                    let smoothed_high = kernel_regression(config.kernel_bandwidth, *x, &self.highs);
+                    let smoothed_low = kernel_regression(config.kernel_bandwidth, *x, &self.lows);
                     self.smooth_highs.push((candle_id, smoothed_high.abs()));
                     self.smooth_lows.push((candle_id, smoothed_low.abs()));
                 } else {
-                    let smoothed_close = kernel_regression(kernel_bandwidth, *x, &self.close);
-                    smooth_close.push(smoothed_close.abs());
+                    let smoothed_close =
+                        kernel_regression(config.kernel_bandwidth, *x, &self.close);
                     self.smooth_close.push((candle_id, smoothed_close.abs()));
                 }
 
@@ -176,36 +271,299 @@ impl Peaks {
             }
         }
 
-        let source = match price_smoothing {
-            true => match price_source.as_ref() {
-                "highs_lows" => (&smooth_highs, &self.highs, &smooth_lows, &self.lows),
-                "close" => (&smooth_close, &self.close, &smooth_close, &self.close),
-                &_ => (&smooth_close, &smooth_close, &self.close, &self.close),
+        let from = lookback_from(self.close.len(), config.lookback);
+        let (local_maxima, local_minima) = self.local_peaks(from, &config)?;
+        self.local_maxima = local_maxima;
+        self.local_minima = local_minima;
+        self.set_swings(&config);
+
+        Ok(())
+    }
+
+    /// Live alternative to `calculate_peaks`. Smoothing reuses the kernel sums
+    /// of the previous calls, and maxima/minima are only searched again in the
+    /// last PEAKS_INCREMENTAL_WINDOW bars. Peaks before the second half of the
+    /// window, away from its edge, are kept as they were.
+    pub fn calculate_peaks_incremental(&mut self, max_price: &f64, min_price: &f64) -> Result<()> {
+        let window = env::var("PEAKS_INCREMENTAL_WINDOW")
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok())
+            .unwrap_or(200);
+
+        let len = self.close.len();
+        let has_peaks = !self.local_maxima.is_empty() || !self.local_minima.is_empty();
+        let config = PeaksConfig::from_env(max_price, min_price, self.method);
+        let lookback_start = lookback_from(len, config.lookback);
+
+        if config.price_smoothing {
+            self.sync_kernels(&config);
+        }
+
+        if len - lookback_start <= window || !has_peaks {
+            let (local_maxima, local_minima) = self.local_peaks(lookback_start, &config)?;
+            self.local_maxima = local_maxima;
+            self.local_minima = local_minima;
+            self.set_swings(&config);
+            return Ok(());
+        }
+
+        let from = len - window;
+        let keep_until = from + window / 2;
+        let (local_maxima, local_minima) = self.local_peaks(from, &config)?;
+
+        for (peaks, window_peaks) in [
+            (&mut self.local_maxima, local_maxima),
+            (&mut self.local_minima, local_minima),
+        ] {
+            peaks.retain(|(index, _)| *index >= lookback_start && *index < keep_until);
+            peaks.extend(
+                window_peaks
+                    .into_iter()
+                    .filter(|(index, _)| *index >= keep_until),
+            );
+        }
+        self.set_swings(&config);
+
+        Ok(())
+    }
+
+    fn set_swings(&mut self, config: &PeaksConfig) {
+        self.local_swings = self.swing_points(&self.local_maxima, &self.local_minima, config);
+        self.extrema_swings = self.swing_points(&self.extrema_maxima, &self.extrema_minima, config);
+    }
+
+    /// Prominence is measured on the raw prices of the price source.
+    fn swing_points(
+        &self,
+        maxima: &[(usize, f64)],
+        minima: &[(usize, f64)],
+        config: &PeaksConfig,
+    ) -> Vec<SwingPoint> {
+        let (highs, lows) = match config.price_source.as_ref() {
+            "highs_lows" => (&self.highs, &self.lows),
+            _ => (&self.close, &self.close),
+        };
+        let linear = |values: &Vec<f64>, sign: f64| -> Vec<f64> {
+            values
+                .iter()
+                .map(|x| match self.logarithmic {
+                    true => sign * x.exp(),
+                    false => sign * x,
+                })
+                .collect()
+        };
+        let highs = linear(highs, 1.);
+        let lows = linear(lows, -1.);
+
+        let swing =
+            |kind: SwingKind, (index, price): &(usize, f64), values: &Vec<f64>| SwingPoint {
+                index: *index,
+                price: *price,
+                kind,
+                date: self.dates.get(*index).copied(),
+                prominence: peak_prominence(values, *index),
+            };
+
+        let mut swings: Vec<SwingPoint> = maxima
+            .iter()
+            .map(|point| swing(SwingKind::High, point, &highs))
+            .chain(
+                minima
+                    .iter()
+                    .map(|point| swing(SwingKind::Low, point, &lows)),
+            )
+            .collect();
+        swings.sort_by_key(|swing| swing.index);
+        swings
+    }
+
+    /// Brings the kernel sums up to date with the prices, rebuilding them when
+    /// the bandwidth changed.
+    fn sync_kernels(&mut self, config: &PeaksConfig) {
+        let logarithmic = env::var("LOGARITHMIC_SCANNER")
+            .ok()
+            .and_then(|val| val.parse::<bool>().ok())
+            .unwrap_or(false);
+
+        let len = self.close.len();
+        for (kernel, values) in [
+            (&mut self.kernel_highs, &self.highs),
+            (&mut self.kernel_lows, &self.lows),
+            (&mut self.kernel_close, &self.close),
+        ] {
+            if kernel.bandwidth() != config.kernel_bandwidth || kernel.len() > len {
+                *kernel = KernelRegression::new(config.kernel_bandwidth, logarithmic);
+            }
+
+            // Last candle may have been updated since the previous call
+            if !kernel.is_empty() {
+                let last = kernel.len() - 1;
+                kernel.update_last(self.close[last], values[last]);
+            }
+
+            for index in kernel.len()..len {
+                kernel.push(self.close[index], values[index]);
+            }
+        }
+
+        let smoothed = |kernel: &KernelRegression| -> Vec<(usize, f64)> {
+            kernel
+                .values()
+                .into_iter()
+                .map(|value| value.abs())
+                .enumerate()
+                .collect()
+        };
+
+        match config.price_source == "highs_lows" {
+            true => {
+                self.smooth_highs = smoothed(&self.kernel_highs);
+                self.smooth_lows = smoothed(&self.kernel_lows);
+                self.smooth_close = vec![];
+            }
+            false => {
+                self.smooth_highs = vec![];
+                self.smooth_lows = vec![];
+                self.smooth_close = smoothed(&self.kernel_close);
+            }
+        }
+    }
+
+    /// Maxima and minima from the index on, with indexes of the whole series.
+    fn local_peaks(
+        &self,
+        from: usize,
+        config: &PeaksConfig,
+    ) -> Result<(Vec<(usize, f64)>, Vec<(usize, f64)>)> {
+        let values = |points: &Vec<(usize, f64)>| -> Vec<f64> {
+            points.iter().skip(from).map(|(_, value)| *value).collect()
+        };
+        let smooth_highs = values(&self.smooth_highs);
+        let smooth_lows = values(&self.smooth_lows);
+        let smooth_close = values(&self.smooth_close);
+        let highs = self.highs[from..].to_vec();
+        let lows = self.lows[from..].to_vec();
+        let close = self.close[from..].to_vec();
+
+        let source = match config.price_smoothing {
+            true => match config.price_source.as_ref() {
+                "highs_lows" => (&smooth_highs, &highs, &smooth_lows, &lows),
+                "close" => (&smooth_close, &close, &smooth_close, &close),
+                &_ => (&smooth_close, &smooth_close, &close, &close),
             },
-            false => match price_source.as_ref() {
-                "highs_lows" => (&self.highs, &self.highs, &self.lows, &self.lows),
-                "close" => (&self.close, &self.close, &self.close, &self.close),
-                &_ => (&self.close, &self.close, &self.close, &self.close),
+            false => match config.price_source.as_ref() {
+                "highs_lows" => (&highs, &highs, &lows, &lows),
+                "close" => (&close, &close, &close, &close),
+                &_ => (&close, &close, &close, &close),
             },
         };
 
-        self.local_maxima =
-            maxima_minima(source.0, source.1, local_prominence, local_min_distance)?;
+        let offset = |points: Vec<(usize, f64)>| -> Vec<(usize, f64)> {
+            let mut points: Vec<(usize, f64)> = points
+                .into_iter()
+                .map(|(index, value)| (index + from, value))
+                .collect();
+            points.sort_by(|(id_a, _), (id_b, _)| id_a.cmp(id_b));
+            points
+        };
+
+        let detect = |x_values: &Vec<f64>, y_values: &Vec<f64>| match config.method {
+            PeaksMethod::Prominence => maxima_minima_scaled(
+                x_values,
+                y_values,
+                config.local_prominence,
+                config.local_min_distance,
+                self.logarithmic,
+            ),
+            PeaksMethod::Fractal => {
+                fractals_scaled(x_values, y_values, config.fractal_bars, self.logarithmic)
+            }
+        };
 
-        self.local_maxima
-            .sort_by(|(id_a, _indicator_value_a), (id_b, _indicator_value_b)| id_a.cmp(id_b));
+        let local_maxima = detect(source.0, source.1)?;
+        let local_minima = detect(&source.2.iter().map(|x| -x).collect(), source.3)?;
 
-        self.local_minima = maxima_minima(
-            &source.2.iter().map(|x| -x).collect(),
-            source.3,
-            local_prominence,
-            local_min_distance,
-        )?;
+        Ok((offset(local_maxima), offset(local_minima)))
+    }
+}
 
-        self.local_minima
-            .sort_by(|(id_a, _indicator_value_a), (id_b, _indicator_value_b)| id_a.cmp(id_b));
+/// How local maxima and minima are found. Defaults to PEAKS_METHOD.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PeaksMethod {
+    /// Kernel regression smoothing, when enabled, and prominence
+    Prominence,
+    /// N bar pivot highs and lows, FRACTAL_BARS at each side
+    Fractal,
+}
 
-        Ok(())
+impl PeaksMethod {
+    pub fn from_env() -> Self {
+        match env::var("PEAKS_METHOD") {
+            Ok(val) if val == "fractal" => PeaksMethod::Fractal,
+            _ => PeaksMethod::Prominence,
+        }
+    }
+}
+
+impl Default for PeaksMethod {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+struct PeaksConfig {
+    method: PeaksMethod,
+    fractal_bars: usize,
+    local_prominence: f64,
+    local_min_distance: usize,
+    price_smoothing: bool,
+    kernel_bandwidth: f64,
+    price_source: String,
+    /// Last bars searched for peaks, PEAKS_LOOKBACK
+    lookback: Option<usize>,
+}
+
+impl PeaksConfig {
+    /// Prominence and bandwidth are scaled to the price range.
+    fn from_env(max_price: &f64, min_price: &f64, method: PeaksMethod) -> Self {
+        let price_diff = max_price - min_price;
+
+        let local_prominence = env::var("LOCAL_MIN_PROMINENCE")
+            .unwrap()
+            .parse::<f64>()
+            .unwrap();
+
+        let local_min_distance = env::var("LOCAL_PROMINENCE_MIN_DISTANCE")
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+
+        let price_smoothing = env::var("KERNEL_PRICE_SMOOTHING")
+            .unwrap()
+            .parse::<bool>()
+            .unwrap();
+
+        let kernel_bandwidth = env::var("KERNEL_REGRESSION_BANDWIDTH")
+            .unwrap()
+            .parse::<f64>()
+            .unwrap();
+
+        let fractal_bars = env::var("FRACTAL_BARS")
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok())
+            .unwrap_or(2);
+
+        Self {
+            method,
+            fractal_bars,
+            local_prominence: local_prominence * price_diff,
+            local_min_distance,
+            // Fractals work on the raw prices
+            price_smoothing: price_smoothing && method == PeaksMethod::Prominence,
+            kernel_bandwidth: kernel_bandwidth * price_diff,
+            price_source: env::var("PRICE_SOURCE").unwrap(),
+            lookback: lookback_from_env("PEAKS_LOOKBACK"),
+        }
     }
 }
 
@@ -0,0 +1,289 @@
+use super::candle::Candle;
+use super::instrument::Instrument;
+use crate::helpers::calc;
+use crate::models::trade::TradeOut;
+
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Regime {
+    TrendingUp,
+    TrendingDown,
+    Ranging,
+    Volatile,
+    Undefined,
+}
+
+impl Regime {
+    pub fn is_trending(&self) -> bool {
+        matches!(self, Regime::TrendingUp | Regime::TrendingDown)
+    }
+
+    pub fn is_ranging(&self) -> bool {
+        *self == Regime::Ranging
+    }
+
+    pub fn is_volatile(&self) -> bool {
+        *self == Regime::Volatile
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegimeClassifier {
+    pub adx_period: usize,
+    pub adx_threshold: f64,
+    pub atr_period: usize,
+    pub atr_window: usize,
+    pub volatile_percentile: f64,
+}
+
+impl RegimeClassifier {
+    pub fn from_env() -> Self {
+        let parse = |key: &str, default: &str| {
+            env::var(key)
+                .unwrap_or(default.to_string())
+                .parse::<f64>()
+                .unwrap()
+        };
+
+        Self {
+            adx_period: parse("REGIME_ADX_PERIOD", "14") as usize,
+            adx_threshold: parse("REGIME_ADX_THRESHOLD", "25"),
+            atr_period: parse("REGIME_ATR_PERIOD", "14") as usize,
+            atr_window: parse("REGIME_ATR_WINDOW", "100") as usize,
+            volatile_percentile: parse("REGIME_VOLATILE_PERCENTILE", "90"),
+        }
+    }
+
+    /// Classifies every bar. Volatility takes precedence over trend, bars
+    /// without enough history are Undefined.
+    pub fn classify(&self, data: &Vec<Candle>) -> Regimes {
+        let (adx, plus_di, minus_di) = calculate_adx(data, self.adx_period);
+        let atr = calculate_atr(data, self.atr_period);
+        let warm_up = self.adx_period * 2;
+
+        let regimes = (0..data.len())
+            .map(|index| {
+                if index < warm_up {
+                    return Regime::Undefined;
+                }
+
+                let from = index.saturating_sub(self.atr_window);
+                let atr_rank = percentile_rank(&atr[from..=index], atr[index]);
+
+                match atr_rank >= self.volatile_percentile {
+                    true => Regime::Volatile,
+                    false => match adx[index] >= self.adx_threshold {
+                        true => match plus_di[index] >= minus_di[index] {
+                            true => Regime::TrendingUp,
+                            false => Regime::TrendingDown,
+                        },
+                        false => Regime::Ranging,
+                    },
+                }
+            })
+            .collect();
+
+        Regimes { regimes, adx, atr }
+    }
+}
+
+impl Default for RegimeClassifier {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Regimes {
+    regimes: Vec<Regime>,
+    adx: Vec<f64>,
+    atr: Vec<f64>,
+}
+
+impl Regimes {
+    pub fn from_instrument(instrument: &Instrument) -> Self {
+        RegimeClassifier::from_env().classify(instrument.data())
+    }
+
+    pub fn get(&self, index: usize) -> Regime {
+        match self.regimes.get(index) {
+            Some(regime) => *regime,
+            None => Regime::Undefined,
+        }
+    }
+
+    pub fn current(&self) -> Regime {
+        match self.regimes.last() {
+            Some(regime) => *regime,
+            None => Regime::Undefined,
+        }
+    }
+
+    pub fn regimes(&self) -> &Vec<Regime> {
+        &self.regimes
+    }
+
+    pub fn adx(&self) -> &Vec<f64> {
+        &self.adx
+    }
+
+    pub fn atr(&self) -> &Vec<f64> {
+        &self.atr
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegimeStats {
+    pub regime: Regime,
+    pub trades: usize,
+    pub wining_trades: usize,
+    pub losing_trades: usize,
+    pub gross_profit: f64,
+    pub gross_loss: f64,
+    pub net_profit: f64,
+    pub profitable_trades: f64,
+    pub profit_factor: f64,
+}
+
+/// Groups closed trades by the regime of their entry bar.
+pub fn regime_performance(trades_out: &Vec<TradeOut>, regimes: &Regimes) -> Vec<RegimeStats> {
+    let all = [
+        Regime::TrendingUp,
+        Regime::TrendingDown,
+        Regime::Ranging,
+        Regime::Volatile,
+        Regime::Undefined,
+    ];
+
+    all.iter()
+        .filter_map(|regime| {
+            let trades: Vec<&TradeOut> = trades_out
+                .iter()
+                .filter(|trade| regimes.get(trade.index_in) == *regime)
+                .collect();
+
+            if trades.is_empty() {
+                return None;
+            }
+
+            let wining_trades = trades.iter().filter(|x| x.profit > 0.).count();
+            let gross_profit: f64 = trades
+                .iter()
+                .filter(|x| x.profit > 0.)
+                .map(|x| x.profit)
+                .sum();
+            let gross_loss: f64 = trades
+                .iter()
+                .filter(|x| x.profit <= 0.)
+                .map(|x| x.profit)
+                .sum();
+
+            Some(RegimeStats {
+                regime: *regime,
+                trades: trades.len(),
+                wining_trades,
+                losing_trades: trades.len() - wining_trades,
+                gross_profit,
+                gross_loss,
+                net_profit: gross_profit + gross_loss,
+                profitable_trades: calc::total_profitable_trades(wining_trades, trades.len()),
+                profit_factor: calc::total_profit_factor(gross_profit, gross_loss),
+            })
+        })
+        .collect()
+}
+
+fn true_range(candle: &Candle, prev_close: f64) -> f64 {
+    (candle.high() - candle.low())
+        .max((candle.high() - prev_close).abs())
+        .max((candle.low() - prev_close).abs())
+}
+
+pub fn calculate_atr(data: &Vec<Candle>, period: usize) -> Vec<f64> {
+    let mut atr = Vec::with_capacity(data.len());
+    let mut prev: Option<f64> = None;
+
+    for (index, candle) in data.iter().enumerate() {
+        let prev_close = match index {
+            0 => candle.close(),
+            _ => data[index - 1].close(),
+        };
+        let tr = true_range(candle, prev_close);
+        let value = match prev {
+            Some(prev) => (prev * (period as f64 - 1.) + tr) / period as f64,
+            None => tr,
+        };
+        prev = Some(value);
+        atr.push(value);
+    }
+
+    atr
+}
+
+/// Wilder's ADX. Returns (adx, +di, -di) aligned with data.
+pub fn calculate_adx(data: &Vec<Candle>, period: usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let len = data.len();
+    let period_f = period as f64;
+    let mut adx = vec![0.; len];
+    let mut plus_di = vec![0.; len];
+    let mut minus_di = vec![0.; len];
+
+    let mut tr_smooth = 0.;
+    let mut plus_smooth = 0.;
+    let mut minus_smooth = 0.;
+    let mut adx_value = 0.;
+
+    for index in 1..len {
+        let candle = &data[index];
+        let prev = &data[index - 1];
+        let up_move = candle.high() - prev.high();
+        let down_move = prev.low() - candle.low();
+
+        let plus_dm = match up_move > down_move && up_move > 0. {
+            true => up_move,
+            false => 0.,
+        };
+        let minus_dm = match down_move > up_move && down_move > 0. {
+            true => down_move,
+            false => 0.,
+        };
+        let tr = true_range(candle, prev.close());
+
+        tr_smooth = tr_smooth - tr_smooth / period_f + tr;
+        plus_smooth = plus_smooth - plus_smooth / period_f + plus_dm;
+        minus_smooth = minus_smooth - minus_smooth / period_f + minus_dm;
+
+        let (pdi, mdi) = match tr_smooth > 0. {
+            true => (
+                100. * plus_smooth / tr_smooth,
+                100. * minus_smooth / tr_smooth,
+            ),
+            false => (0., 0.),
+        };
+
+        let dx = match pdi + mdi > 0. {
+            true => 100. * (pdi - mdi).abs() / (pdi + mdi),
+            false => 0.,
+        };
+
+        adx_value = match index < period {
+            true => dx,
+            false => (adx_value * (period_f - 1.) + dx) / period_f,
+        };
+
+        plus_di[index] = pdi;
+        minus_di[index] = mdi;
+        adx[index] = adx_value;
+    }
+
+    (adx, plus_di, minus_di)
+}
+
+fn percentile_rank(window: &[f64], value: f64) -> f64 {
+    match window.len() {
+        0 => 0.,
+        len => window.iter().filter(|x| **x <= value).count() as f64 / len as f64 * 100.,
+    }
+}
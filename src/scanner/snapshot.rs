@@ -0,0 +1,231 @@
+use super::instrument::Instrument;
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::models::time_frame::TimeFrameType;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SECTIONS: [&str; 6] = [
+    "indicators",
+    "peaks",
+    "patterns",
+    "horizontal_levels",
+    "divergences",
+    "levels",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SnapshotDiff {
+    pub section: String,
+    pub path: String,
+    pub index: Option<usize>,
+    pub before: Value,
+    pub after: Value,
+}
+
+/// File name used to checkpoint an instrument inside `dir`.
+pub fn snapshot_path(dir: &str, symbol: &str, time_frame: &TimeFrameType) -> PathBuf {
+    Path::new(dir).join(format!("{}_{}.snapshot", symbol, time_frame))
+}
+
+/// Writes to a temporary file first so a crash never leaves a truncated
+/// snapshot behind.
+pub fn save_instrument<P: AsRef<Path>>(instrument: &Instrument, path: P) -> Result<()> {
+    let path = path.as_ref();
+    let tmp_path = path.with_extension("tmp");
+    let bytes = instrument.to_bytes()?;
+
+    fs::write(&tmp_path, bytes).map_err(|_| snapshot_error())?;
+    fs::rename(&tmp_path, path).map_err(|_| snapshot_error())
+}
+
+pub fn load_instrument<P: AsRef<Path>>(path: P) -> Result<Instrument> {
+    let bytes = fs::read(path).map_err(|_| snapshot_error())?;
+    Instrument::from_bytes(&bytes)
+}
+
+/// Loads the checkpoint if there is a valid one, so callers can fall back to
+/// downloading the data.
+pub fn try_load_instrument(
+    dir: &str,
+    symbol: &str,
+    time_frame: &TimeFrameType,
+) -> Option<Instrument> {
+    let path = snapshot_path(dir, symbol, time_frame);
+    match path.exists() {
+        true => match load_instrument(&path) {
+            Ok(instrument) => Some(instrument),
+            Err(_) => {
+                log::warn!("Invalid snapshot {:?}, ignoring it", path);
+                None
+            }
+        },
+        false => None,
+    }
+}
+
+pub fn diff_instruments(
+    before: &Instrument,
+    after: &Instrument,
+    tolerance: f64,
+) -> Result<Vec<SnapshotDiff>> {
+    let before = serde_json::to_value(before).map_err(|_| parse_error())?;
+    let after = serde_json::to_value(after).map_err(|_| parse_error())?;
+    Ok(diff_values(&before, &after, tolerance))
+}
+
+pub fn diff_snapshots(before: &str, after: &str, tolerance: f64) -> Result<Vec<SnapshotDiff>> {
+    let before: Value = serde_json::from_str(before).map_err(|_| parse_error())?;
+    let after: Value = serde_json::from_str(after).map_err(|_| parse_error())?;
+    Ok(diff_values(&before, &after, tolerance))
+}
+
+pub fn diff_values(before: &Value, after: &Value, tolerance: f64) -> Vec<SnapshotDiff> {
+    let mut diffs = vec![];
+
+    for section in SECTIONS {
+        walk(
+            section,
+            section,
+            None,
+            &before[section],
+            &after[section],
+            tolerance,
+            &mut diffs,
+        );
+    }
+
+    diffs
+}
+
+pub fn group_by_index(diffs: &Vec<SnapshotDiff>) -> BTreeMap<usize, Vec<&SnapshotDiff>> {
+    let mut grouped: BTreeMap<usize, Vec<&SnapshotDiff>> = BTreeMap::new();
+
+    for diff in diffs {
+        if let Some(index) = diff.index {
+            grouped.entry(index).or_default().push(diff);
+        }
+    }
+
+    grouped
+}
+
+pub fn summarize(diffs: &Vec<SnapshotDiff>) -> BTreeMap<String, usize> {
+    let mut summary: BTreeMap<String, usize> = BTreeMap::new();
+
+    for diff in diffs {
+        *summary.entry(diff.section.clone()).or_default() += 1;
+    }
+
+    summary
+}
+
+fn walk(
+    section: &str,
+    path: &str,
+    index: Option<usize>,
+    before: &Value,
+    after: &Value,
+    tolerance: f64,
+    diffs: &mut Vec<SnapshotDiff>,
+) {
+    match (before, after) {
+        (Value::Object(before_obj), Value::Object(after_obj)) => {
+            let mut keys: Vec<&String> = before_obj.keys().chain(after_obj.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                walk(
+                    section,
+                    &[path, ".", key].concat(),
+                    index,
+                    before_obj.get(key).unwrap_or(&Value::Null),
+                    after_obj.get(key).unwrap_or(&Value::Null),
+                    tolerance,
+                    diffs,
+                );
+            }
+        }
+        (Value::Array(before_arr), Value::Array(after_arr)) => {
+            let len = before_arr.len().max(after_arr.len());
+            for i in 0..len {
+                let before_item = before_arr.get(i).unwrap_or(&Value::Null);
+                let after_item = after_arr.get(i).unwrap_or(&Value::Null);
+                let index = item_index(before_item)
+                    .or_else(|| item_index(after_item))
+                    .or(index)
+                    .or_else(|| match before_item.is_number() || after_item.is_number() {
+                        true => Some(i),
+                        false => None,
+                    });
+
+                walk(
+                    section,
+                    &[path, "[", &i.to_string(), "]"].concat(),
+                    index,
+                    before_item,
+                    after_item,
+                    tolerance,
+                    diffs,
+                );
+            }
+        }
+        (Value::Number(before_num), Value::Number(after_num)) => {
+            let before_f64 = before_num.as_f64().unwrap_or(0.);
+            let after_f64 = after_num.as_f64().unwrap_or(0.);
+            if (before_f64 - after_f64).abs() > tolerance {
+                diffs.push(new_diff(section, path, index, before, after));
+            }
+        }
+        _ => {
+            if before != after {
+                diffs.push(new_diff(section, path, index, before, after));
+            }
+        }
+    }
+}
+
+/// Candle index an item carries itself, an `index` field or the first
+/// element of an `(index, value)` point. Only per bar arrays of numbers are
+/// indexed by position.
+fn item_index(item: &Value) -> Option<usize> {
+    let index = match item {
+        Value::Object(obj) => obj.get("index").and_then(|index| index.as_u64()),
+        Value::Array(point) if point.len() == 2 => point[0].as_u64(),
+        _ => None,
+    };
+
+    index.map(|index| index as usize)
+}
+
+fn new_diff(
+    section: &str,
+    path: &str,
+    index: Option<usize>,
+    before: &Value,
+    after: &Value,
+) -> SnapshotDiff {
+    SnapshotDiff {
+        section: section.to_owned(),
+        path: path.to_owned(),
+        index,
+        before: before.clone(),
+        after: after.clone(),
+    }
+}
+
+fn parse_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::WrongInstrumentConf,
+    }
+}
+
+fn snapshot_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::SnapshotError,
+    }
+}
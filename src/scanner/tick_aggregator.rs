@@ -0,0 +1,150 @@
+use super::candle::{Candle, DOHLCV};
+use crate::error::Result;
+use crate::helpers::date::*;
+use crate::models::pricing::Pricing;
+use crate::models::time_frame::{period_key, period_start, TimeFrameType};
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FormingCandle {
+    key: i64,
+    time_frame: TimeFrameType,
+    candle: Candle,
+    previous: Vec<DOHLCV>,
+}
+
+/// Builds candles from live ticks, one forming candle per symbol and time
+/// frame. Candles carry the same open date and `is_closed` semantics as the
+/// backtest ones.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TickAggregator {
+    candles: HashMap<String, FormingCandle>,
+}
+
+impl TickAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(symbol: &str, time_frame: &TimeFrameType) -> String {
+        format!("{}_{}", symbol, time_frame)
+    }
+
+    pub fn current(&self, symbol: &str, time_frame: &TimeFrameType) -> Option<&Candle> {
+        self.candles
+            .get(&Self::key(symbol, time_frame))
+            .map(|forming| &forming.candle)
+    }
+
+    /// Returns the closed candle when the tick belongs to a new period, unless
+    /// `close_expired` already returned it.
+    pub fn next_tick(
+        &mut self,
+        symbol: &str,
+        time_frame: &TimeFrameType,
+        date: DateTime<Local>,
+        price: f64,
+        volume: f64,
+    ) -> Result<Option<Candle>> {
+        let key = period_key(&date, time_frame);
+
+        match self.candles.get_mut(&Self::key(symbol, time_frame)) {
+            Some(forming) if forming.key == key => {
+                let candle = &mut forming.candle;
+                candle.set_high(candle.high().max(price));
+                candle.set_low(candle.low().min(price));
+                candle.set_close(price);
+                candle.volume += volume;
+                Ok(None)
+            }
+            Some(forming) => {
+                let was_closed = forming.candle.is_closed();
+                let mut closed = forming.candle.clone();
+                closed.set_is_closed(true);
+
+                forming.previous.push(to_dohlcv(&closed));
+                if forming.previous.len() > 2 {
+                    forming.previous.remove(0);
+                }
+                forming.key = key;
+                forming.candle = build(
+                    period_start(&date, time_frame),
+                    price,
+                    volume,
+                    forming.previous.clone(),
+                )?;
+
+                match was_closed {
+                    true => Ok(None),
+                    false => Ok(Some(closed)),
+                }
+            }
+            None => {
+                let forming = FormingCandle {
+                    key,
+                    time_frame: time_frame.clone(),
+                    candle: build(period_start(&date, time_frame), price, volume, vec![])?,
+                    previous: vec![],
+                };
+                self.candles.insert(Self::key(symbol, time_frame), forming);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Ticks are priced at the bid, as broker candles are.
+    pub fn next_pricing(
+        &mut self,
+        pricing: &Pricing,
+        time_frame: &TimeFrameType,
+        date: DateTime<Local>,
+    ) -> Result<Option<Candle>> {
+        self.next_tick(&pricing.symbol(), time_frame, date, pricing.bid(), 0.)
+    }
+
+    /// Marks the forming candles whose period ended before `now` as closed,
+    /// for quiet markets where no tick opens the next period.
+    pub fn close_expired(&mut self, now: DateTime<Local>) -> Vec<(String, Candle)> {
+        let mut closed = vec![];
+        for (key, forming) in self.candles.iter_mut() {
+            let is_expired = period_key(&now, &forming.time_frame) != forming.key;
+            if is_expired && !forming.candle.is_closed() {
+                forming.candle.set_is_closed(true);
+                closed.push((key.clone(), forming.candle.clone()));
+            }
+        }
+        closed
+    }
+}
+
+fn build(
+    date: DateTime<Local>,
+    price: f64,
+    volume: f64,
+    previous_candles: Vec<DOHLCV>,
+) -> Result<Candle> {
+    Candle::new()
+        .date(date)
+        .open(price)
+        .high(price)
+        .low(price)
+        .close(price)
+        .volume(volume)
+        .is_closed(false)
+        .previous_candles(previous_candles)
+        .logarithmic(false)
+        .build()
+}
+
+fn to_dohlcv(candle: &Candle) -> DOHLCV {
+    (
+        candle.date(),
+        candle.open(),
+        candle.high(),
+        candle.low(),
+        candle.close(),
+        candle.volume(),
+    )
+}
@@ -0,0 +1,231 @@
+use super::instrument::Instrument;
+use crate::helpers::date::*;
+use crate::models::market::MarketHours;
+use crate::models::time_frame::TimeFrameType;
+
+use serde::{Deserialize, Serialize};
+
+type DOHLC = (DateTime<Local>, f64, f64, f64, f64, f64);
+type VEC_DOHLC = Vec<DOHLC>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum DataIssueType {
+    Duplicate,
+    OutOfOrder,
+    Gap,
+    InvalidPrice,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DataIssue {
+    pub issue_type: DataIssueType,
+    pub index: usize,
+    pub date: DateTime<Local>,
+    /// Number of missing bars for gaps, 1 otherwise.
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ValidationReport {
+    pub num_bars: usize,
+    pub issues: Vec<DataIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn issues_of(&self, issue_type: DataIssueType) -> Vec<&DataIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.issue_type == issue_type)
+            .collect()
+    }
+
+    pub fn duplicates(&self) -> Vec<&DataIssue> {
+        self.issues_of(DataIssueType::Duplicate)
+    }
+
+    pub fn out_of_order(&self) -> Vec<&DataIssue> {
+        self.issues_of(DataIssueType::OutOfOrder)
+    }
+
+    pub fn gaps(&self) -> Vec<&DataIssue> {
+        self.issues_of(DataIssueType::Gap)
+    }
+
+    pub fn invalid_prices(&self) -> Vec<&DataIssue> {
+        self.issues_of(DataIssueType::InvalidPrice)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum RepairStrategy {
+    /// Keeps the last of the duplicated bars and drops invalid ones.
+    Drop,
+    /// Like Drop, but invalid bars and gaps become flat bars at the previous close.
+    ForwardFill,
+    /// Duplicated bars are merged into one and invalid ones dropped.
+    Merge,
+}
+
+pub fn is_valid_price(bar: &DOHLC) -> bool {
+    let (_, open, high, low, close, _) = *bar;
+    [open, high, low, close]
+        .iter()
+        .all(|price| price.is_finite() && *price > 0.)
+        && high >= low
+}
+
+/// Bars expected between two dates while the market is open. Without
+/// market hours every bar is expected.
+fn missing_bars(
+    from: DateTime<Local>,
+    to: DateTime<Local>,
+    time_frame: &TimeFrameType,
+    market_hours: Option<&MarketHours>,
+) -> Vec<DateTime<Local>> {
+    let step = Duration::minutes(time_frame.to_minutes());
+    let mut missing = vec![];
+
+    if time_frame.to_minutes() <= 0 {
+        return missing;
+    }
+
+    let mut date = from + step;
+    while date < to {
+        let is_open = match market_hours {
+            Some(market_hours) => market_hours.is_open_at(date),
+            None => true,
+        };
+        if is_open {
+            missing.push(date);
+        }
+        date = date + step;
+    }
+
+    missing
+}
+
+pub fn validate(
+    data: &VEC_DOHLC,
+    time_frame: &TimeFrameType,
+    market_hours: Option<&MarketHours>,
+) -> ValidationReport {
+    let mut issues = vec![];
+
+    for (index, bar) in data.iter().enumerate() {
+        if !is_valid_price(bar) {
+            issues.push(DataIssue {
+                issue_type: DataIssueType::InvalidPrice,
+                index,
+                date: bar.0,
+                size: 1,
+            });
+        }
+
+        if index == 0 {
+            continue;
+        }
+
+        let prev_date = data[index - 1].0;
+        let issue = match bar.0 {
+            date if date == prev_date => Some((DataIssueType::Duplicate, 1)),
+            date if date < prev_date => Some((DataIssueType::OutOfOrder, 1)),
+            date => match missing_bars(prev_date, date, time_frame, market_hours).len() {
+                0 => None,
+                size => Some((DataIssueType::Gap, size)),
+            },
+        };
+
+        if let Some((issue_type, size)) = issue {
+            issues.push(DataIssue {
+                issue_type,
+                index,
+                date: bar.0,
+                size,
+            });
+        }
+    }
+
+    ValidationReport {
+        num_bars: data.len(),
+        issues,
+    }
+}
+
+pub fn validate_instrument(
+    instrument: &Instrument,
+    market_hours: Option<&MarketHours>,
+) -> ValidationReport {
+    let data: VEC_DOHLC = instrument
+        .data()
+        .iter()
+        .map(|candle| {
+            (
+                candle.date(),
+                candle.open(),
+                candle.high(),
+                candle.low(),
+                candle.close(),
+                candle.volume(),
+            )
+        })
+        .collect();
+
+    validate(&data, instrument.time_frame(), market_hours)
+}
+
+/// Sorts the bars and fixes duplicates, invalid prices and, when forward
+/// filling, gaps.
+pub fn repair(
+    data: &VEC_DOHLC,
+    time_frame: &TimeFrameType,
+    market_hours: Option<&MarketHours>,
+    strategy: RepairStrategy,
+) -> VEC_DOHLC {
+    let mut sorted = data.clone();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut repaired: VEC_DOHLC = vec![];
+    for bar in sorted {
+        let prev = repaired.last().copied();
+
+        let bar = match (is_valid_price(&bar), strategy, prev) {
+            (true, _, _) => bar,
+            (false, RepairStrategy::ForwardFill, Some(prev)) => flat_bar(bar.0, prev.4),
+            (false, _, _) => continue,
+        };
+
+        match prev {
+            Some(prev) if prev.0 == bar.0 => {
+                let last = repaired.last_mut().unwrap();
+                *last = match strategy {
+                    RepairStrategy::Merge => (
+                        prev.0,
+                        prev.1,
+                        prev.2.max(bar.2),
+                        prev.3.min(bar.3),
+                        bar.4,
+                        prev.5 + bar.5,
+                    ),
+                    _ => bar,
+                };
+            }
+            Some(prev) if strategy == RepairStrategy::ForwardFill => {
+                for date in missing_bars(prev.0, bar.0, time_frame, market_hours) {
+                    repaired.push(flat_bar(date, prev.4));
+                }
+                repaired.push(bar);
+            }
+            _ => repaired.push(bar),
+        }
+    }
+
+    repaired
+}
+
+fn flat_bar(date: DateTime<Local>, price: f64) -> DOHLC {
+    (date, price, price, price, price, 0.)
+}
@@ -0,0 +1,277 @@
+use super::candle::Candle;
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::env;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct VolumeNode {
+    /// Bucket lower bound
+    pub price: f64,
+    pub volume: f64,
+}
+
+/// Volume traded per price bucket over the last `window` candles. Every
+/// candle volume is spread evenly over the buckets its range covers, so
+/// candles can be added and evicted without rebuilding the histogram.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VolumeProfile {
+    window: usize,
+    num_buckets: usize,
+    value_area_percentage: f64,
+    bucket_size: f64,
+    /// Bucket key of volumes[0]
+    origin: i64,
+    volumes: Vec<f64>,
+    /// (high, low, volume) of the candles in the window
+    bars: VecDeque<(f64, f64, f64)>,
+    poc: f64,
+    value_area_high: f64,
+    value_area_low: f64,
+}
+
+impl VolumeProfile {
+    pub fn new() -> Self {
+        Self {
+            window: env::var("VOLUME_PROFILE_WINDOW")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(100),
+            num_buckets: env::var("VOLUME_PROFILE_BUCKETS")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(50),
+            value_area_percentage: env::var("VOLUME_PROFILE_VALUE_AREA")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(70.),
+            bucket_size: 0.,
+            origin: 0,
+            volumes: vec![],
+            bars: VecDeque::new(),
+            poc: 0.,
+            value_area_high: 0.,
+            value_area_low: 0.,
+        }
+    }
+
+    pub fn window(mut self, val: usize) -> Self {
+        self.window = val;
+        self
+    }
+
+    pub fn num_buckets(mut self, val: usize) -> Self {
+        self.num_buckets = val;
+        self
+    }
+
+    pub fn value_area_percentage(mut self, val: f64) -> Self {
+        self.value_area_percentage = val;
+        self
+    }
+
+    /// Bucket size is fixed from the price range of the first window.
+    pub fn from_candles(&self, data: &Vec<Candle>) -> Self {
+        let mut profile = Self::new()
+            .window(self.window)
+            .num_buckets(self.num_buckets)
+            .value_area_percentage(self.value_area_percentage);
+
+        let from = data.len().saturating_sub(profile.window);
+        let candles = &data[from..];
+
+        let high = candles.iter().map(|x| x.high()).fold(f64::MIN, f64::max);
+        let low = candles.iter().map(|x| x.low()).fold(f64::MAX, f64::min);
+        if high > low {
+            profile.bucket_size = (high - low) / profile.num_buckets.max(1) as f64;
+        }
+
+        for candle in candles {
+            profile.push(candle);
+        }
+        profile.calculate();
+        profile
+    }
+
+    pub fn poc(&self) -> f64 {
+        self.poc
+    }
+
+    pub fn value_area_high(&self) -> f64 {
+        self.value_area_high
+    }
+
+    pub fn value_area_low(&self) -> f64 {
+        self.value_area_low
+    }
+
+    pub fn bucket_size(&self) -> f64 {
+        self.bucket_size
+    }
+
+    pub fn total_volume(&self) -> f64 {
+        self.volumes.iter().sum()
+    }
+
+    pub fn nodes(&self) -> Vec<VolumeNode> {
+        self.volumes
+            .iter()
+            .enumerate()
+            .map(|(idx, volume)| VolumeNode {
+                price: self.price_of(idx),
+                volume: *volume,
+            })
+            .collect()
+    }
+
+    pub fn volume_at(&self, price: f64) -> f64 {
+        match self.index_of(self.key(price)) {
+            Some(idx) => self.volumes[idx],
+            None => 0.,
+        }
+    }
+
+    pub fn is_in_value_area(&self, price: f64) -> bool {
+        price >= self.value_area_low && price <= self.value_area_high
+    }
+
+    /// Adds a new candle, evicting the oldest one out of the window.
+    pub fn next(&mut self, candle: &Candle) {
+        if self.bucket_size <= 0. && candle.high() > candle.low() {
+            self.bucket_size = (candle.high() - candle.low()) / self.num_buckets.max(1) as f64;
+            self.rebuild();
+        }
+
+        self.push(candle);
+        while self.bars.len() > self.window {
+            if let Some(bar) = self.bars.pop_front() {
+                self.distribute(bar, -1.);
+            }
+        }
+        self.calculate();
+    }
+
+    /// Replaces the last, still forming, candle.
+    pub fn update(&mut self, candle: &Candle) {
+        match self.bars.pop_back() {
+            Some(bar) => {
+                self.distribute(bar, -1.);
+                self.push(candle);
+                self.calculate();
+            }
+            None => self.next(candle),
+        }
+    }
+
+    fn push(&mut self, candle: &Candle) {
+        let bar = (candle.high(), candle.low(), candle.volume());
+        self.bars.push_back(bar);
+        self.distribute(bar, 1.);
+    }
+
+    /// Flat windows have no bucket size yet, redistributes them once it is set.
+    fn rebuild(&mut self) {
+        self.volumes.clear();
+        for bar in self.bars.clone() {
+            self.distribute(bar, 1.);
+        }
+    }
+
+    fn key(&self, price: f64) -> i64 {
+        match self.bucket_size > 0. {
+            true => (price / self.bucket_size).floor() as i64,
+            false => 0,
+        }
+    }
+
+    fn index_of(&self, key: i64) -> Option<usize> {
+        let idx = key - self.origin;
+        match idx >= 0 && (idx as usize) < self.volumes.len() {
+            true => Some(idx as usize),
+            false => None,
+        }
+    }
+
+    fn price_of(&self, idx: usize) -> f64 {
+        (self.origin + idx as i64) as f64 * self.bucket_size
+    }
+
+    fn distribute(&mut self, bar: (f64, f64, f64), sign: f64) {
+        let (high, low, volume) = bar;
+        let from = self.key(low);
+        let to = self.key(high).max(from);
+
+        if self.volumes.is_empty() {
+            self.origin = from;
+        }
+        if from < self.origin {
+            let missing = (self.origin - from) as usize;
+            self.volumes.splice(0..0, vec![0.; missing]);
+            self.origin = from;
+        }
+        let len = (to - self.origin + 1) as usize;
+        if len > self.volumes.len() {
+            self.volumes.resize(len, 0.);
+        }
+
+        let share = volume / (to - from + 1) as f64;
+        for key in from..=to {
+            let idx = (key - self.origin) as usize;
+            self.volumes[idx] = (self.volumes[idx] + sign * share).max(0.);
+        }
+    }
+
+    /// POC is the bucket with the highest volume. The value area grows from
+    /// it towards the side with more volume until it holds
+    /// `value_area_percentage` of the total.
+    fn calculate(&mut self) {
+        let total = self.total_volume();
+        let poc_idx = match self
+            .volumes
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+        {
+            Some((idx, _)) if total > 0. => idx,
+            _ => return,
+        };
+
+        let target = total * self.value_area_percentage / 100.;
+        let (mut low_idx, mut high_idx) = (poc_idx, poc_idx);
+        let mut volume = self.volumes[poc_idx];
+
+        while volume < target {
+            let below = match low_idx > 0 {
+                true => Some(self.volumes[low_idx - 1]),
+                false => None,
+            };
+            let above = self.volumes.get(high_idx + 1).copied();
+
+            match (below, above) {
+                (Some(below), Some(above)) if above >= below => {
+                    high_idx += 1;
+                    volume += above;
+                }
+                (Some(below), _) => {
+                    low_idx -= 1;
+                    volume += below;
+                }
+                (None, Some(above)) => {
+                    high_idx += 1;
+                    volume += above;
+                }
+                (None, None) => break,
+            }
+        }
+
+        self.poc = self.price_of(poc_idx) + self.bucket_size / 2.;
+        self.value_area_low = self.price_of(low_idx);
+        self.value_area_high = self.price_of(high_idx) + self.bucket_size;
+    }
+}
+
+impl Default for VolumeProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
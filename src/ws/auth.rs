@@ -0,0 +1,113 @@
+use crate::ws::message::Credentials;
+
+use bson::Uuid;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Validates the `InitSession` credentials: api keys or tokens signed by a
+/// trusted issuer.
+pub trait Authenticator: Send + Sync {
+    fn authenticate(&self, credentials: &Credentials) -> bool;
+}
+
+impl<F> Authenticator for F
+where
+    F: Fn(&Credentials) -> bool + Send + Sync,
+{
+    fn authenticate(&self, credentials: &Credentials) -> bool {
+        self(credentials)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    keys: HashSet<String>,
+}
+
+impl ApiKeys {
+    pub fn new(keys: &[&str]) -> Self {
+        Self {
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        }
+    }
+
+    /// WS_API_KEYS: comma separated keys.
+    pub fn from_env() -> Self {
+        Self {
+            keys: env::var("WS_API_KEYS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|key| key.trim().to_owned())
+                .filter(|key| !key.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl Authenticator for ApiKeys {
+    fn authenticate(&self, credentials: &Credentials) -> bool {
+        match credentials {
+            Credentials::ApiKey(key) => self.keys.contains(key),
+            _ => false,
+        }
+    }
+}
+
+/// Session tokens issued after authenticating. They stay valid across
+/// reconnections until they expire.
+#[derive(Debug, Clone)]
+pub struct SessionTokens {
+    ttl: Duration,
+    tokens: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl SessionTokens {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            tokens: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// WS_SESSION_TOKEN_TTL: seconds, one day by default.
+    pub fn from_env() -> Self {
+        Self::new(Duration::from_secs(
+            env::var("WS_SESSION_TOKEN_TTL")
+                .ok()
+                .and_then(|val| val.parse::<u64>().ok())
+                .unwrap_or(86_400),
+        ))
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn issue(&self) -> String {
+        let token = [Uuid::new().to_string(), Uuid::new().to_string()].concat();
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(token.clone(), Instant::now() + self.ttl);
+        token
+    }
+
+    pub fn is_valid(&self, token: &str) -> bool {
+        let now = Instant::now();
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.retain(|_, expires_at| *expires_at > now);
+        tokens.contains_key(token)
+    }
+
+    pub fn revoke(&self, token: &str) {
+        self.tokens.lock().unwrap().remove(token);
+    }
+}
+
+impl Default for SessionTokens {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
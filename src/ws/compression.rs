@@ -0,0 +1,138 @@
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use tungstenite::http::HeaderMap;
+use tungstenite::Message;
+
+#[cfg(feature = "compression")]
+use flate2::{read::DeflateDecoder, write::DeflateEncoder};
+#[cfg(feature = "compression")]
+use std::io::{Read, Write};
+
+/// tungstenite has no RFC 7692 support, so messages are deflated whole and
+/// sent as binary frames. The extension token is private so servers not
+/// knowing it just ignore the offer.
+pub const EXTENSION: &str = "x-rs-algo-deflate";
+pub const EXTENSIONS_HEADER: &str = "Sec-WebSocket-Extensions";
+
+#[cfg(feature = "compression")]
+const DEFLATE: Option<Compression> = Some(Compression::Deflate);
+#[cfg(not(feature = "compression"))]
+const DEFLATE: Option<Compression> = None;
+
+#[cfg(feature = "compression")]
+const TEXT: u8 = 0;
+#[cfg(feature = "compression")]
+const BINARY: u8 = 1;
+
+/// Message compression, negotiated per connection in the handshake.
+/// Deflate needs the `compression` feature.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    #[cfg(feature = "compression")]
+    Deflate,
+}
+
+fn compression_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::WebSocketError,
+    }
+}
+
+impl Compression {
+    /// WS_COMPRESSION: deflate or none. None when not set or not supported.
+    pub fn from_env() -> Self {
+        match env::var("WS_COMPRESSION").ok().as_deref() {
+            Some("deflate") => DEFLATE.unwrap_or_default(),
+            _ => Compression::None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self != Compression::None
+    }
+
+    /// Extension offered by the client or accepted by the server.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            #[cfg(feature = "compression")]
+            Compression::Deflate => Some(EXTENSION),
+        }
+    }
+
+    /// Compression of the `Sec-WebSocket-Extensions` header, as offered by
+    /// a client or accepted by a server.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let offered = headers
+            .get_all(EXTENSIONS_HEADER)
+            .iter()
+            .filter_map(|val| val.to_str().ok())
+            .flat_map(|val| val.split(','))
+            .any(|val| val.split(';').next().unwrap_or("").trim() == EXTENSION);
+
+        match offered {
+            true => DEFLATE.unwrap_or_default(),
+            false => Compression::None,
+        }
+    }
+
+    /// Compression of the connection, enabled only when both sides support it.
+    pub fn negotiate(&self, headers: &HeaderMap) -> Self {
+        match self.is_enabled() {
+            true => Self::from_headers(headers),
+            false => Compression::None,
+        }
+    }
+
+    pub fn compress(&self, msg: Message) -> Result<Message> {
+        match self {
+            Compression::None => Ok(msg),
+            #[cfg(feature = "compression")]
+            Compression::Deflate => {
+                let (kind, bytes) = match msg {
+                    Message::Text(text) => (TEXT, text.into_bytes()),
+                    Message::Binary(bytes) => (BINARY, bytes),
+                    msg => return Ok(msg),
+                };
+
+                let mut encoder = DeflateEncoder::new(vec![kind], flate2::Compression::fast());
+                encoder.write_all(&bytes).map_err(|_| compression_error())?;
+                encoder
+                    .finish()
+                    .map(Message::Binary)
+                    .map_err(|_| compression_error())
+            }
+        }
+    }
+
+    pub fn decompress(&self, msg: Message) -> Result<Message> {
+        match self {
+            Compression::None => Ok(msg),
+            #[cfg(feature = "compression")]
+            Compression::Deflate => {
+                let bytes = match msg {
+                    Message::Binary(bytes) => bytes,
+                    msg => return Ok(msg),
+                };
+                let (kind, compressed) = bytes.split_first().ok_or_else(compression_error)?;
+
+                let mut decoded = vec![];
+                DeflateDecoder::new(compressed)
+                    .read_to_end(&mut decoded)
+                    .map_err(|_| compression_error())?;
+
+                match *kind {
+                    TEXT => String::from_utf8(decoded)
+                        .map(Message::Text)
+                        .map_err(|_| compression_error()),
+                    BINARY => Ok(Message::Binary(decoded)),
+                    _ => Err(compression_error()),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tungstenite::http::HeaderMap;
+use tungstenite::Message;
+
+/// Wire format of the ws messages, negotiated with the `Sec-WebSocket-Protocol`
+/// header. Binary formats need the `binary` feature.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Json,
+    #[cfg(feature = "binary")]
+    Cbor,
+    #[cfg(feature = "binary")]
+    MessagePack,
+}
+
+fn encoding_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::WebSocketError,
+    }
+}
+
+impl Encoding {
+    /// WS_ENCODING: json, cbor or msgpack. Json when not set or not supported.
+    pub fn from_env() -> Self {
+        env::var("WS_ENCODING")
+            .ok()
+            .and_then(|val| Self::from_name(&val))
+            .unwrap_or(Encoding::Json)
+    }
+
+    pub fn all() -> Vec<Encoding> {
+        vec![
+            #[cfg(feature = "binary")]
+            Encoding::MessagePack,
+            #[cfg(feature = "binary")]
+            Encoding::Cbor,
+            Encoding::Json,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Json => "json",
+            #[cfg(feature = "binary")]
+            Encoding::Cbor => "cbor",
+            #[cfg(feature = "binary")]
+            Encoding::MessagePack => "msgpack",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|encoding| encoding.name() == name.trim().to_lowercase())
+    }
+
+    /// Handshake subprotocol tag, like `rs-algo.msgpack`.
+    pub fn subprotocol(&self) -> String {
+        ["rs-algo.", self.name()].concat()
+    }
+
+    pub fn from_subprotocol(subprotocol: &str) -> Option<Self> {
+        subprotocol
+            .trim()
+            .strip_prefix("rs-algo.")
+            .and_then(Self::from_name)
+    }
+
+    /// First supported encoding of the client `Sec-WebSocket-Protocol` list,
+    /// in the client preference order.
+    pub fn negotiate(header: &str) -> Self {
+        header
+            .split(',')
+            .find_map(Self::from_subprotocol)
+            .unwrap_or(Encoding::Json)
+    }
+
+    /// Encoding accepted by the server in the handshake response.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|val| val.to_str().ok())
+            .and_then(Self::from_subprotocol)
+            .unwrap_or(Encoding::Json)
+    }
+
+    pub fn is_binary(&self) -> bool {
+        *self != Encoding::Json
+    }
+
+    pub fn encode<T: Serialize>(&self, msg: &T) -> Result<Message> {
+        match self {
+            Encoding::Json => serde_json::to_string(msg)
+                .map(Message::Text)
+                .map_err(|_| encoding_error()),
+            #[cfg(feature = "binary")]
+            Encoding::Cbor => {
+                let mut bytes = vec![];
+                ciborium::ser::into_writer(msg, &mut bytes).map_err(|_| encoding_error())?;
+                Ok(Message::Binary(bytes))
+            }
+            #[cfg(feature = "binary")]
+            Encoding::MessagePack => rmp_serde::to_vec_named(msg)
+                .map(Message::Binary)
+                .map_err(|_| encoding_error()),
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, msg: &Message) -> Result<T> {
+        let bytes: &[u8] = match msg {
+            Message::Text(text) => text.as_bytes(),
+            Message::Binary(bytes) => bytes,
+            _ => return Err(encoding_error()),
+        };
+
+        match self {
+            Encoding::Json => serde_json::from_slice(bytes).map_err(|_| encoding_error()),
+            #[cfg(feature = "binary")]
+            Encoding::Cbor => ciborium::de::from_reader(bytes).map_err(|_| encoding_error()),
+            #[cfg(feature = "binary")]
+            Encoding::MessagePack => rmp_serde::from_slice(bytes).map_err(|_| encoding_error()),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::ws::encoding::Encoding;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tungstenite::Message;
+
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Messages sent before the envelope, with the bare payload
+pub const LEGACY_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Envelope<T> {
+    pub version: u32,
+    /// Session token of authenticated commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    pub payload: T,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(payload: T) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            token: None,
+            payload,
+        }
+    }
+
+    pub fn token(mut self, val: Option<&str>) -> Self {
+        self.token = val.map(|token| token.to_owned());
+        self
+    }
+}
+
+/// Variant renamed in the `since` version. Messages of older versions are
+/// read with the new name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rename {
+    pub since: u32,
+    pub from: &'static str,
+    pub to: &'static str,
+}
+
+pub const RENAMES: &[Rename] = &[];
+
+fn envelope_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::WebSocketError,
+    }
+}
+
+pub fn encode_envelope<T: Serialize>(encoding: &Encoding, payload: &T) -> Result<Message> {
+    encoding.encode(&Envelope::new(payload))
+}
+
+pub fn encode_envelope_with_token<T: Serialize>(
+    encoding: &Encoding,
+    payload: &T,
+    token: Option<&str>,
+) -> Result<Message> {
+    encoding.encode(&Envelope::new(payload).token(token))
+}
+
+/// Reads messages of any version: bare legacy payloads, older envelopes with
+/// renamed variants and newer ones with unknown fields, which are ignored.
+pub fn decode_envelope<T: DeserializeOwned>(
+    encoding: &Encoding,
+    msg: &Message,
+) -> Result<Envelope<T>> {
+    decode_envelope_with(encoding, msg, RENAMES)
+}
+
+pub fn decode_envelope_with<T: DeserializeOwned>(
+    encoding: &Encoding,
+    msg: &Message,
+    renames: &[Rename],
+) -> Result<Envelope<T>> {
+    let value: Value = encoding.decode(msg)?;
+    let (version, token, mut payload) = split_envelope(value);
+
+    for rename in renames.iter().filter(|rename| version < rename.since) {
+        rename_variant(&mut payload, rename.from, rename.to);
+    }
+
+    let payload = serde_json::from_value(payload).map_err(|_| envelope_error())?;
+    Ok(Envelope {
+        version,
+        token,
+        payload,
+    })
+}
+
+fn split_envelope(value: Value) -> (u32, Option<String>, Value) {
+    match value {
+        Value::Object(mut map) if map.contains_key("payload") => {
+            match map.get("version").and_then(|version| version.as_u64()) {
+                Some(version) => {
+                    let token = map
+                        .get("token")
+                        .and_then(|token| token.as_str())
+                        .map(|token| token.to_owned());
+                    (version as u32, token, map.remove("payload").unwrap())
+                }
+                None => (LEGACY_VERSION, None, Value::Object(map)),
+            }
+        }
+        value => (LEGACY_VERSION, None, value),
+    }
+}
+
+/// Unit variants are strings, the rest single key objects.
+fn rename_variant(value: &mut Value, from: &str, to: &str) {
+    match value {
+        Value::String(name) if name == from => *name = to.to_owned(),
+        Value::Array(values) => {
+            for value in values.iter_mut() {
+                rename_variant(value, from, to);
+            }
+        }
+        Value::Object(map) => {
+            if map.len() == 1 {
+                if let Some(inner) = map.remove(from) {
+                    map.insert(to.to_owned(), inner);
+                }
+            }
+            for value in map.values_mut() {
+                rename_variant(value, from, to);
+            }
+        }
+        _ => (),
+    }
+}
@@ -0,0 +1,98 @@
+use std::env;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeartbeatConfig {
+    pub interval: Duration,
+    /// Connection considered lost without any frame for this long
+    pub timeout: Duration,
+}
+
+impl HeartbeatConfig {
+    pub fn from_env() -> Self {
+        let millis = |key: &str, default: u64| {
+            Duration::from_millis(
+                env::var(key)
+                    .ok()
+                    .and_then(|val| val.parse::<u64>().ok())
+                    .unwrap_or(default),
+            )
+        };
+
+        Self {
+            interval: millis("WS_PING_INTERVAL", 15_000),
+            timeout: millis("WS_PONG_TIMEOUT", 45_000),
+        }
+    }
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(15),
+            timeout: Duration::from_secs(45),
+        }
+    }
+}
+
+/// Ping schedule and liveness watchdog. Any received frame counts as a pong.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    config: HeartbeatConfig,
+    last_ping: Instant,
+    last_pong: Instant,
+}
+
+impl Heartbeat {
+    pub fn new(config: HeartbeatConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            config,
+            last_ping: now,
+            last_pong: now,
+        }
+    }
+
+    pub fn config(&self) -> &HeartbeatConfig {
+        &self.config
+    }
+
+    /// Disabled with a zero interval
+    pub fn is_enabled(&self) -> bool {
+        !self.config.interval.is_zero()
+    }
+
+    pub fn should_ping(&self, now: Instant) -> bool {
+        self.is_enabled() && now.saturating_duration_since(self.last_ping) >= self.config.interval
+    }
+
+    pub fn ping_sent(&mut self, now: Instant) {
+        self.last_ping = now;
+    }
+
+    pub fn pong_received(&mut self, now: Instant) {
+        self.last_pong = now;
+    }
+
+    pub fn is_alive(&self, now: Instant) -> bool {
+        !self.is_enabled() || now.saturating_duration_since(self.last_pong) < self.config.timeout
+    }
+
+    /// Time left until the next ping is due.
+    pub fn next_ping(&self, now: Instant) -> Duration {
+        self.config
+            .interval
+            .saturating_sub(now.saturating_duration_since(self.last_ping))
+    }
+
+    pub fn reset(&mut self, now: Instant) {
+        self.last_ping = now;
+        self.last_pong = now;
+    }
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new(HeartbeatConfig::from_env())
+    }
+}
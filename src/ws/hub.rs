@@ -0,0 +1,134 @@
+use crate::models::time_frame::TimeFrameType;
+use crate::ws::message::ResponseBody;
+use crate::ws::ws_server::Sessions;
+
+use bson::Uuid;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Topic {
+    pub symbol: String,
+    pub time_frame: TimeFrameType,
+}
+
+impl Topic {
+    pub fn new(symbol: &str, time_frame: TimeFrameType) -> Self {
+        Self {
+            symbol: symbol.to_owned(),
+            time_frame,
+        }
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.symbol, self.time_frame)
+    }
+}
+
+/// Fans out the stream responses of one upstream broker subscription per
+/// topic to every session subscribed to it. The subscribe and unsubscribe
+/// calls return true when the upstream subscription has to be opened or
+/// closed, that is for the first and the last subscriber.
+#[derive(Debug, Clone)]
+pub struct StreamHub {
+    sessions: Sessions,
+    topics: Arc<Mutex<HashMap<Topic, HashSet<Uuid>>>>,
+}
+
+impl StreamHub {
+    pub fn new(sessions: Sessions) -> Self {
+        Self {
+            sessions,
+            topics: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn subscribe(&self, session_id: &Uuid, topic: &Topic) -> bool {
+        let mut topics = self.topics.lock().unwrap();
+        let subscribers = topics.entry(topic.clone()).or_default();
+        subscribers.insert(*session_id) && subscribers.len() == 1
+    }
+
+    pub fn unsubscribe(&self, session_id: &Uuid, topic: &Topic) -> bool {
+        let mut topics = self.topics.lock().unwrap();
+        match topics.get_mut(topic) {
+            Some(subscribers) if subscribers.remove(session_id) => match subscribers.is_empty() {
+                true => {
+                    topics.remove(topic);
+                    true
+                }
+                false => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Drops the session subscriptions, returning the topics left without
+    /// subscribers.
+    pub fn unsubscribe_session(&self, session_id: &Uuid) -> Vec<Topic> {
+        self.release(|id| id == session_id)
+    }
+
+    /// Drops the subscriptions of disconnected sessions, returning the topics
+    /// left without subscribers.
+    pub fn prune(&self) -> Vec<Topic> {
+        let connected: HashSet<Uuid> = self.sessions.ids().into_iter().collect();
+        self.release(|id| !connected.contains(id))
+    }
+
+    pub fn topics(&self) -> Vec<Topic> {
+        self.topics.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn subscribers(&self, topic: &Topic) -> usize {
+        self.topics
+            .lock()
+            .unwrap()
+            .get(topic)
+            .map(|subscribers| subscribers.len())
+            .unwrap_or(0)
+    }
+
+    pub fn is_subscribed(&self, topic: &Topic) -> bool {
+        self.subscribers(topic) > 0
+    }
+
+    /// Sends the stream response or candle to the topic subscribers,
+    /// returning how many got it.
+    pub fn publish<T: Serialize>(&self, topic: &Topic, msg: &ResponseBody<T>) -> usize {
+        let subscribers: Vec<Uuid> = match self.topics.lock().unwrap().get(topic) {
+            Some(subscribers) => subscribers.iter().cloned().collect(),
+            None => vec![],
+        };
+
+        subscribers
+            .iter()
+            .filter(|session_id| self.sessions.send(session_id, msg).is_ok())
+            .count()
+    }
+
+    fn release<F>(&self, released: F) -> Vec<Topic>
+    where
+        F: Fn(&Uuid) -> bool,
+    {
+        let mut topics = self.topics.lock().unwrap();
+        let mut empty = vec![];
+
+        topics.retain(|topic, subscribers| {
+            subscribers.retain(|id| !released(id));
+            match subscribers.is_empty() {
+                true => {
+                    empty.push(topic.clone());
+                    false
+                }
+                false => true,
+            }
+        });
+
+        empty
+    }
+}
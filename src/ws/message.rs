@@ -2,28 +2,42 @@ use bson::Uuid;
 #[cfg(feature = "websocket")]
 pub use tungstenite::Message;
 
-use crate::broker::{DOHLC, VEC_DOHLC};
+use crate::broker::{CalendarEvent, NewsItem, TradeUpdate, DOHLC, VEC_DOHLC};
+use crate::helpers::date::{DateTime, Local};
 use crate::models::bot::BotData;
+use crate::models::config::ConfigAudit;
+use crate::models::derivatives::{FundingRate, OpenInterest};
 use crate::models::market::MarketHours;
-use crate::models::order::Order;
+use crate::models::order::{Order, OrderEvent};
 use crate::models::pricing::Pricing;
 use crate::models::strategy::StrategyType;
 use crate::models::time_frame::TimeFrameType;
 use crate::models::trade::{TradeIn, TradeOut};
+use crate::scanner::pattern_event::PatternEvent;
+use crate::ws::encoding::Encoding;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CommandType {
     InitSession,
     GetCurrentState,
     GetInstrumentData,
     GetInstrumentPricing,
     GetMarketHours,
+    GetCalendar,
     UpdateBotData,
     ExecuteTrade,
     ExecutePosition,
+    ExecuteOrder,
+    CancelOrders,
+    ModifyOrder,
+    UpdateConfig,
     SubscribeStream,
+    SubscribePatterns,
+    /// Sent by a newer peer
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,12 +53,35 @@ pub enum ResponseType {
     Reconnect,
     GetInstrumentData,
     GetInstrumentPricing,
+    GetSymbolAvailability,
     GetMarketHours,
+    GetCalendar,
+    GetFundingRates,
+    GetOpenInterest,
     TradeInAccepted,
     TradeOutAccepted,
+    CancelOrderAccepted,
+    ModifyOrderAccepted,
+    ExecuteOrder,
+    OrderFulfilled,
+    OrderCancelled,
+    OrderAmended,
+    ConfigUpdated,
     InitSession,
     SubscribeStream,
     SubscribeTickPrices,
+    SubscribeTrades,
+    SubscribeNews,
+    SubscribeFundingRates,
+    SubscribeOpenInterest,
+    TradeUpdate,
+    News,
+    PatternEvent,
+    Authenticated,
+    Unauthorized,
+    /// Sent by a newer peer
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -75,6 +112,9 @@ pub struct InstrumentData<T> {
     pub symbol: String,
     pub time_frame: TimeFrameType,
     pub data: T,
+    /// Bars missing inside market hours, as (last bar, next bar)
+    #[serde(default)]
+    pub gaps: Vec<(DateTime<Local>, DateTime<Local>)>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -107,6 +147,17 @@ impl<T> TradeData<T> {
     }
 }
 
+/// Order lifecycle data, correlated to the trade owning the order.
+impl TradeData<Order> {
+    pub fn trade_id(&self) -> usize {
+        self.data.trade_id
+    }
+
+    pub fn order_id(&self) -> usize {
+        self.data.id
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TradeResponse<T> {
     pub symbol: String,
@@ -123,6 +174,26 @@ pub struct PricingData {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConnectedData {
     pub session_id: Uuid,
+    /// Encoding accepted by the server in the handshake
+    #[serde(default)]
+    pub encoding: Encoding,
+}
+
+/// `InitSession` command data when the server requires authentication.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Credentials {
+    ApiKey(String),
+    /// Token signed by a trusted issuer, like a JWT
+    Token(String),
+}
+
+/// Token to send in the envelope of the following commands.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionToken {
+    pub session_id: Uuid,
+    pub token: String,
+    /// Seconds
+    pub expires_in: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -149,11 +220,50 @@ pub enum MessageType {
     InstrumentData(ResponseBody<InstrumentData<VEC_DOHLC>>),
     PricingData(ResponseBody<Pricing>),
     MarketHours(ResponseBody<MarketHours>),
+    Calendar(ResponseBody<Vec<CalendarEvent>>),
+    FundingRates(ResponseBody<Vec<FundingRate>>),
+    OpenInterest(ResponseBody<Vec<OpenInterest>>),
+    News(ResponseBody<NewsItem>),
     InitSession(ResponseBody<BotData>),
     TradeInAccepted(ResponseBody<TradeResponse<TradeIn>>),
     TradeOutAccepted(ResponseBody<TradeResponse<TradeOut>>),
     ExecuteOrder(ResponseBody<TradeResponse<Order>>),
+    CancelOrderAccepted(ResponseBody<TradeResponse<Order>>),
+    ModifyOrderAccepted(ResponseBody<TradeResponse<Order>>),
+    OrderFulfilled(ResponseBody<TradeData<Order>>),
+    OrderCancelled(ResponseBody<TradeData<Order>>),
+    OrderAmended(ResponseBody<TradeData<Order>>),
+    ConfigUpdated(ResponseBody<ConfigAudit>),
+    TradeUpdate(ResponseBody<TradeUpdate>),
+    PatternEvent(ResponseBody<PatternEvent>),
+    Authenticated(ResponseBody<SessionToken>),
     Connected(ResponseBody<Uuid>),
     Reconnect(ResponseBody<ReconnectOptions>),
     Error(ResponseBody<bool>),
 }
+
+impl MessageType {
+    /// Lifecycle message of a fulfilled, cancelled or expired order. Expired
+    /// orders are sent as cancelled.
+    pub fn from_order_event(
+        symbol: &str,
+        options: TradeOptions,
+        event: OrderEvent,
+    ) -> Option<MessageType> {
+        let body = |response: ResponseType, order: Order| ResponseBody {
+            response,
+            payload: Some(TradeData::new(symbol, order, options.clone())),
+        };
+
+        match event {
+            OrderEvent::Fulfilled(order) => Some(MessageType::OrderFulfilled(body(
+                ResponseType::OrderFulfilled,
+                order,
+            ))),
+            OrderEvent::Cancelled(order) | OrderEvent::Expired(order) => Some(
+                MessageType::OrderCancelled(body(ResponseType::OrderCancelled, order)),
+            ),
+            _ => None,
+        }
+    }
+}
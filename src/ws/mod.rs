@@ -1,5 +1,18 @@
+pub mod auth;
+pub mod compression;
+pub mod encoding;
+pub mod envelope;
+pub mod heartbeat;
+pub mod hub;
 pub mod message;
+pub mod outbound;
+pub mod reconnect;
+pub mod ws_builder;
 
 #[cfg(feature = "broker")]
 pub mod ws_client;
+pub mod ws_server;
 pub mod ws_stream_client;
+
+pub use ws_builder::WebSocketBuilder;
+pub use ws_server::WebSocketServer;
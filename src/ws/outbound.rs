@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::env;
+use tungstenite::Message;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboundConfig {
+    pub capacity: usize,
+    /// Max messages written per flush
+    pub batch_size: usize,
+}
+
+impl OutboundConfig {
+    pub fn from_env() -> Self {
+        Self {
+            capacity: env::var("WS_OUTBOUND_CAPACITY")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(1024),
+            batch_size: env::var("WS_OUTBOUND_BATCH")
+                .ok()
+                .and_then(|val| val.parse::<usize>().ok())
+                .unwrap_or(64),
+        }
+    }
+}
+
+impl Default for OutboundConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            batch_size: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutboundMetrics {
+    pub depth: usize,
+    pub max_depth: usize,
+    pub queued: usize,
+    pub sent: usize,
+    /// Replaced by a newer message with the same key
+    pub coalesced: usize,
+    /// Rejected or evicted with the queue full
+    pub dropped: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Outbound {
+    key: Option<String>,
+    msg: Message,
+}
+
+/// Bounded queue of the messages waiting to be written, so sending never
+/// waits on a slow connection. Tick frequency messages are queued with a
+/// key and only the last one per key is kept. With the queue full the
+/// oldest keyed message is evicted and, if there is none, the new message
+/// is rejected.
+#[derive(Debug, Clone)]
+pub struct OutboundQueue {
+    config: OutboundConfig,
+    queue: VecDeque<Outbound>,
+    metrics: OutboundMetrics,
+}
+
+impl OutboundQueue {
+    pub fn new(config: OutboundConfig) -> Self {
+        Self {
+            config,
+            queue: VecDeque::new(),
+            metrics: OutboundMetrics::default(),
+        }
+    }
+
+    pub fn push(&mut self, msg: Message) -> bool {
+        self.enqueue(Outbound { key: None, msg })
+    }
+
+    /// Replaces the queued message with the same key, keeping its position.
+    pub fn push_coalesced(&mut self, key: &str, msg: Message) -> bool {
+        match self
+            .queue
+            .iter_mut()
+            .find(|item| item.key.as_deref() == Some(key))
+        {
+            Some(item) => {
+                item.msg = msg;
+                self.metrics.coalesced += 1;
+                true
+            }
+            None => self.enqueue(Outbound {
+                key: Some(key.to_owned()),
+                msg,
+            }),
+        }
+    }
+
+    /// Next messages to write, up to the batch size.
+    pub fn next_batch(&mut self) -> Vec<Message> {
+        let size = self.config.batch_size.max(1).min(self.queue.len());
+        let batch: Vec<Message> = self.queue.drain(..size).map(|item| item.msg).collect();
+        self.metrics.sent += batch.len();
+        self.metrics.depth = self.queue.len();
+        batch
+    }
+
+    /// Puts back the messages of a failed write, ahead of the queued ones.
+    pub fn requeue(&mut self, batch: Vec<Message>) {
+        self.metrics.sent -= batch.len().min(self.metrics.sent);
+        for msg in batch.into_iter().rev() {
+            self.queue.push_front(Outbound { key: None, msg });
+        }
+        self.metrics.depth = self.queue.len();
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.queue.len() >= self.config.capacity
+    }
+
+    pub fn metrics(&self) -> &OutboundMetrics {
+        &self.metrics
+    }
+
+    fn enqueue(&mut self, item: Outbound) -> bool {
+        if self.is_full() {
+            match self.queue.iter().position(|item| item.key.is_some()) {
+                Some(index) => {
+                    self.queue.remove(index);
+                    self.metrics.dropped += 1;
+                }
+                None => {
+                    self.metrics.dropped += 1;
+                    return false;
+                }
+            }
+        }
+
+        self.queue.push_back(item);
+        self.metrics.queued += 1;
+        self.metrics.depth = self.queue.len();
+        self.metrics.max_depth = self.metrics.max_depth.max(self.metrics.depth);
+        true
+    }
+}
+
+impl Default for OutboundQueue {
+    fn default() -> Self {
+        Self::new(OutboundConfig::from_env())
+    }
+}
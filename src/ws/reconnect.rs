@@ -0,0 +1,170 @@
+use std::env;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    /// Heartbeat pongs stopped arriving
+    ConnectionLost,
+    Reconnecting {
+        attempt: u32,
+    },
+    /// Max reconnect attempts reached
+    Disconnected,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    /// Unlimited when not set
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectPolicy {
+    pub fn from_env() -> Self {
+        let millis = |key: &str, default: u64| {
+            Duration::from_millis(
+                env::var(key)
+                    .ok()
+                    .and_then(|val| val.parse::<u64>().ok())
+                    .unwrap_or(default),
+            )
+        };
+
+        Self {
+            initial_delay: millis("WS_RECONNECT_INITIAL_DELAY", 500),
+            max_delay: millis("WS_RECONNECT_MAX_DELAY", 30_000),
+            multiplier: env::var("WS_RECONNECT_MULTIPLIER")
+                .ok()
+                .and_then(|val| val.parse::<f64>().ok())
+                .unwrap_or(2.),
+            max_attempts: env::var("WS_RECONNECT_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|val| val.parse::<u32>().ok()),
+        }
+    }
+
+    /// Wait before the attempt, starting from 1, capped to the max delay.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .max(1.)
+            .powi(attempt.saturating_sub(1) as i32);
+        let delay = self.initial_delay.as_secs_f64() * factor;
+        Duration::from_secs_f64(delay.min(self.max_delay.as_secs_f64()))
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.,
+            max_attempts: None,
+        }
+    }
+}
+
+type StateCallback = Box<dyn Fn(&ConnectionState) + Send + Sync>;
+
+/// Reconnection state shared by the websocket clients: backoff attempts,
+/// state callbacks and the subscription commands replayed once connected again.
+pub struct Reconnect {
+    policy: ReconnectPolicy,
+    attempt: u32,
+    state: ConnectionState,
+    subscriptions: Vec<String>,
+    callbacks: Vec<StateCallback>,
+}
+
+impl Reconnect {
+    pub fn new(policy: ReconnectPolicy) -> Self {
+        Self {
+            policy,
+            attempt: 0,
+            state: ConnectionState::Connected,
+            subscriptions: vec![],
+            callbacks: vec![],
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    pub fn on_state_change<F>(&mut self, callback: F)
+    where
+        F: Fn(&ConnectionState) + Send + Sync + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Registers a command to be sent again after every reconnection.
+    pub fn add_subscription(&mut self, msg: &str) {
+        if !self.subscriptions.iter().any(|x| x == msg) {
+            self.subscriptions.push(msg.to_owned());
+        }
+    }
+
+    pub fn remove_subscription(&mut self, msg: &str) {
+        self.subscriptions.retain(|x| x != msg);
+    }
+
+    pub fn subscriptions(&self) -> &Vec<String> {
+        &self.subscriptions
+    }
+
+    /// Delay before the next attempt, None once the attempts are exhausted.
+    pub fn next_attempt(&mut self) -> Option<Duration> {
+        self.attempt += 1;
+        match self.policy.max_attempts {
+            Some(max_attempts) if self.attempt > max_attempts => {
+                self.set_state(ConnectionState::Disconnected);
+                None
+            }
+            _ => {
+                self.set_state(ConnectionState::Reconnecting {
+                    attempt: self.attempt,
+                });
+                Some(self.policy.delay(self.attempt))
+            }
+        }
+    }
+
+    pub fn connection_lost(&mut self) {
+        self.set_state(ConnectionState::ConnectionLost);
+    }
+
+    pub fn connected(&mut self) {
+        self.attempt = 0;
+        self.set_state(ConnectionState::Connected);
+    }
+
+    fn set_state(&mut self, state: ConnectionState) {
+        self.state = state;
+        for callback in &self.callbacks {
+            callback(&state);
+        }
+    }
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self::new(ReconnectPolicy::from_env())
+    }
+}
+
+impl fmt::Debug for Reconnect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Reconnect")
+            .field("policy", &self.policy)
+            .field("attempt", &self.attempt)
+            .field("state", &self.state)
+            .field("subscriptions", &self.subscriptions)
+            .finish()
+    }
+}
@@ -0,0 +1,140 @@
+use crate::ws::compression::{Compression, EXTENSIONS_HEADER};
+use crate::ws::encoding::Encoding;
+
+use socket2::{SockRef, TcpKeepalive};
+use std::net::TcpStream;
+use std::time::Duration;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::handshake::client::Request;
+use tungstenite::http::{HeaderName, HeaderValue};
+
+#[derive(Debug, Clone)]
+pub struct WebSocketBuilder {
+    url: String,
+    headers: Vec<(String, String)>,
+    subprotocols: Vec<String>,
+    keepalive: Option<Duration>,
+    nodelay: bool,
+    encoding: Encoding,
+    compression: Compression,
+}
+
+impl WebSocketBuilder {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_owned(),
+            headers: vec![],
+            subprotocols: vec![],
+            keepalive: None,
+            nodelay: false,
+            encoding: Encoding::Json,
+            compression: Compression::None,
+        }
+    }
+
+    pub fn header(mut self, key: &str, val: &str) -> Self {
+        self.headers.push((key.to_owned(), val.to_owned()));
+        self
+    }
+
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.header("Authorization", &["Bearer ", token].concat())
+    }
+
+    pub fn subprotocol(mut self, val: &str) -> Self {
+        self.subprotocols.push(val.to_owned());
+        self
+    }
+
+    pub fn keepalive(mut self, val: Duration) -> Self {
+        self.keepalive = Some(val);
+        self
+    }
+
+    pub fn nodelay(mut self, val: bool) -> Self {
+        self.nodelay = val;
+        self
+    }
+
+    /// Offers the encoding in the handshake, falling back to json.
+    pub fn encoding(mut self, val: Encoding) -> Self {
+        self.encoding = val;
+        self.subprotocols
+            .retain(|x| Encoding::from_subprotocol(x).is_none());
+        if val != Encoding::Json {
+            self.subprotocols.push(val.subprotocol());
+        }
+        self.subprotocols.push(Encoding::Json.subprotocol());
+        self
+    }
+
+    pub fn preferred_encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Offers the compression in the handshake, used only if the server
+    /// accepts it.
+    pub fn compression(mut self, val: Compression) -> Self {
+        self.compression = val;
+        self
+    }
+
+    pub fn preferred_compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn url(&self) -> &String {
+        &self.url
+    }
+
+    pub fn request(&self) -> Request {
+        let mut request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .expect("Invalid websocket url");
+
+        let headers = request.headers_mut();
+        for (key, val) in &self.headers {
+            headers.insert(
+                HeaderName::from_bytes(key.as_bytes()).expect("Invalid header name"),
+                HeaderValue::from_str(val).expect("Invalid header value"),
+            );
+        }
+
+        if !self.subprotocols.is_empty() {
+            headers.insert(
+                "Sec-WebSocket-Protocol",
+                HeaderValue::from_str(&self.subprotocols.join(", ")).expect("Invalid subprotocol"),
+            );
+        }
+
+        if let Some(extension) = self.compression.extension() {
+            headers.insert(EXTENSIONS_HEADER, HeaderValue::from_static(extension));
+        }
+
+        request
+    }
+
+    pub fn tcp_stream(&self, request: &Request) -> TcpStream {
+        self.try_tcp_stream(request).expect("Can't connect")
+    }
+
+    pub fn try_tcp_stream(&self, request: &Request) -> std::io::Result<TcpStream> {
+        let uri = request.uri();
+        let host = uri.host().expect("Missing websocket host");
+        let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+            Some("wss") => 443,
+            _ => 80,
+        });
+
+        let stream = TcpStream::connect((host, port))?;
+        stream.set_nodelay(self.nodelay)?;
+
+        if let Some(keepalive) = self.keepalive {
+            SockRef::from(&stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(keepalive))?;
+        }
+
+        Ok(stream)
+    }
+}
@@ -1,67 +1,458 @@
-use crate::error::Result;
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::ws::compression::Compression;
+use crate::ws::encoding::Encoding;
+use crate::ws::envelope::{decode_envelope, encode_envelope_with_token};
+use crate::ws::heartbeat::Heartbeat;
 use crate::ws::message::*;
+use crate::ws::outbound::{OutboundMetrics, OutboundQueue};
+use crate::ws::reconnect::{ConnectionState, Reconnect};
+use crate::ws::ws_builder::WebSocketBuilder;
 
-use std::net::TcpStream;
-use tungstenite::stream::MaybeTlsStream;
-use tungstenite::{connect, WebSocket as Ws};
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{self, ErrorKind};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::TcpStream;
+use tokio::sync::Notify;
+use tokio_tungstenite::{client_async_tls, connect_async, MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 #[derive(Debug)]
 pub struct WebSocket {
     url: String,
-    socket: Ws<MaybeTlsStream<TcpStream>>,
+    builder: Option<WebSocketBuilder>,
+    socket: Socket,
+    connection: Reconnect,
+    heartbeat: Heartbeat,
+    encoding: Encoding,
+    compression: Compression,
+    outbound: OutboundQueue,
+    token: Option<String>,
+    cancel: Arc<Notify>,
+}
+
+/// Cancels the pending read of a client from another task.
+#[derive(Debug, Clone)]
+pub struct CancelHandle {
+    notify: Arc<Notify>,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.notify.notify_waiters();
+    }
+}
+
+enum Frame {
+    Message(Message),
+    /// Ping interval elapsed without frames
+    Tick,
+    Cancelled,
+}
+
+fn ws_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::WebSocketError,
+    }
 }
 
 impl WebSocket {
     pub async fn connect(url: &str) -> Self {
-        let (mut socket, response) = connect(url).expect("Can't connect");
+        let (socket, response) = connect_async(url).await.expect("Can't connect");
 
         log::info!("Connected to the server");
         //log::info!("Response HTTP code: {}", response.status());
 
         Self {
             url: url.to_string(),
+            builder: None,
+            socket,
+            connection: Reconnect::default(),
+            heartbeat: Heartbeat::default(),
+            encoding: Encoding::from_headers(response.headers()),
+            compression: Compression::None,
+            outbound: OutboundQueue::default(),
+            token: None,
+            cancel: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn builder(url: &str) -> WebSocketBuilder {
+        WebSocketBuilder::new(url)
+    }
+
+    pub async fn connect_with(builder: &WebSocketBuilder) -> Self {
+        let (socket, encoding, compression) = Self::open_socket_with(builder)
+            .await
+            .expect("Can't connect");
+
+        log::info!("Connected to the server");
+
+        Self {
+            url: builder.url().to_owned(),
+            builder: Some(builder.clone()),
             socket,
+            connection: Reconnect::default(),
+            heartbeat: Heartbeat::default(),
+            encoding,
+            compression,
+            outbound: OutboundQueue::default(),
+            token: None,
+            cancel: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.connection.state()
+    }
+
+    pub fn on_state_change<F>(&mut self, callback: F)
+    where
+        F: Fn(&ConnectionState) + Send + Sync + 'static,
+    {
+        self.connection.on_state_change(callback);
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Handle to cancel the pending `read` from another task. Reads are
+    /// also cancel safe, so they can be dropped in a `select!`.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle {
+            notify: self.cancel.clone(),
         }
     }
 
     pub async fn send(&mut self, msg: &str) -> Result<()> {
-        self.socket.write_message(Message::text(msg)).unwrap();
-        Ok(())
+        let msg = self.compression.compress(self.outgoing(msg)?)?;
+        self.socket.send(msg).await.map_err(|_| ws_error())
+    }
+
+    /// Queues the message without waiting on the connection. Fails with the
+    /// queue full.
+    pub fn queue(&mut self, msg: &str) -> Result<()> {
+        match self.outbound.push(self.outgoing(msg)?) {
+            true => Ok(()),
+            false => Err(ws_error()),
+        }
+    }
+
+    /// Queues a tick frequency message, replacing the queued one with the
+    /// same key.
+    pub fn queue_coalesced(&mut self, key: &str, msg: &str) -> Result<()> {
+        match self.outbound.push_coalesced(key, self.outgoing(msg)?) {
+            true => Ok(()),
+            false => Err(ws_error()),
+        }
+    }
+
+    pub fn outbound_metrics(&self) -> &OutboundMetrics {
+        self.outbound.metrics()
+    }
+
+    /// Writes the next batch of queued messages with a single flush. The
+    /// queue is also flushed on every `read`.
+    pub async fn flush(&mut self) -> Result<usize> {
+        let batch = self.outbound.next_batch();
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        let frames = batch
+            .iter()
+            .map(|msg| self.compression.compress(msg.clone()))
+            .collect::<Result<Vec<Message>>>();
+        let frames = match frames {
+            Ok(frames) => frames,
+            Err(err) => {
+                self.outbound.requeue(batch);
+                return Err(err);
+            }
+        };
+
+        let mut result = Ok(());
+        for msg in frames {
+            result = self.socket.feed(msg).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        if result.is_ok() {
+            result = self.socket.flush().await;
+        }
+
+        match result {
+            Ok(_) => Ok(batch.len()),
+            Err(err) => {
+                log::error!("WebSocket write error: {}", err);
+                self.outbound.requeue(batch);
+                Err(ws_error())
+            }
+        }
+    }
+
+    /// Sends the command and sends it again after every reconnection.
+    pub async fn subscribe(&mut self, msg: &str) -> Result<()> {
+        self.connection.add_subscription(msg);
+        self.send(msg).await
+    }
+
+    pub fn unsubscribe(&mut self, msg: &str) {
+        self.connection.remove_subscription(msg);
     }
 
     pub async fn re_connect(&mut self) {
-        log::info!("Reconnecting to the server...");
-        let url = self.url.to_owned();
-        let (socket, _response) = connect(url).expect("Can't connect");
-        self.socket = socket;
+        if self.reconnect().await.is_err() {
+            log::error!("Can't reconnect to the server");
+        }
+    }
+
+    /// Reconnects with exponential backoff and replays the subscriptions.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        while let Some(delay) = self.connection.next_attempt() {
+            log::info!("Reconnecting to the server in {:?}...", delay);
+            tokio::time::sleep(delay).await;
+
+            if let Ok((socket, encoding, compression)) = self.open_socket().await {
+                self.socket = socket;
+                self.encoding = encoding;
+                self.compression = compression;
+                self.heartbeat.reset(Instant::now());
+                self.connection.connected();
+                log::info!("Reconnected to the server");
+
+                for msg in self.connection.subscriptions().clone() {
+                    let msg = self.compression.compress(self.outgoing(&msg)?)?;
+                    self.socket.send(msg).await.map_err(|_| ws_error())?;
+                }
+                return Ok(());
+            }
+        }
+
+        Err(ws_error())
     }
 
     pub async fn ping(&mut self, msg: &[u8]) {
-        self.socket
-            .write_message(Message::Ping(msg.to_vec()))
-            .unwrap();
+        self.socket.send(Message::Ping(msg.to_vec())).await.unwrap();
     }
 
     pub async fn pong(&mut self, msg: &[u8]) {
-        self.socket
-            .write_message(Message::Pong(msg.to_vec()))
-            .unwrap();
+        self.socket.send(Message::Pong(msg.to_vec())).await.unwrap();
     }
 
+    /// Next data message, pinging the server on schedule and reconnecting
+    /// when the connection fails or the pongs stop.
     pub async fn read(&mut self) -> Result<Message> {
-        let msg = self.socket.read_message().unwrap();
+        loop {
+            if !self.outbound.is_empty() && self.flush().await.is_err() {
+                self.reconnect().await?;
+                continue;
+            }
+
+            match self.read_frame().await {
+                Ok(Frame::Message(Message::Ping(_))) | Ok(Frame::Message(Message::Pong(_))) => (),
+                Ok(Frame::Message(msg)) => return Ok(msg),
+                Ok(Frame::Tick) => (),
+                Ok(Frame::Cancelled) => return Err(ws_error()),
+                Err(err) => {
+                    log::error!("WebSocket read error: {}", err);
+                    self.reconnect().await?;
+                }
+            }
+        }
+    }
+
+    /// Next text message. Close and binary frames are errors.
+    pub async fn read_text(&mut self) -> Result<String> {
+        match self.read().await? {
+            Message::Text(txt) => Ok(txt),
+            Message::Close(_) => {
+                log::error!("WebSocket closed by the server");
+                Err(ws_error())
+            }
+            _ => Err(ws_error()),
+        }
+    }
+
+    /// Sends the credentials with `InitSession` and keeps the issued session
+    /// token for the following commands.
+    pub async fn authenticate(&mut self, credentials: Credentials) -> Result<SessionToken> {
+        self.send_typed(&Command {
+            command: CommandType::InitSession,
+            data: Some(credentials),
+        })
+        .await?;
+
+        loop {
+            let msg = self.recv_typed::<Value>().await?;
+            match msg.response {
+                ResponseType::Authenticated => {
+                    let session: SessionToken = msg
+                        .payload
+                        .and_then(|payload| serde_json::from_value(payload).ok())
+                        .ok_or_else(ws_error)?;
+                    self.token = Some(session.token.clone());
+                    return Ok(session);
+                }
+                ResponseType::Unauthorized | ResponseType::Error => {
+                    log::error!("WebSocket authentication failed");
+                    return Err(ws_error());
+                }
+                _ => (),
+            }
+        }
+    }
 
-        Ok(msg)
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
     }
 
+    /// Raw commands go in an envelope with the session token once
+    /// authenticated.
+    fn outgoing(&self, msg: &str) -> Result<Message> {
+        match &self.token {
+            Some(token) => {
+                let payload: Value = serde_json::from_str(msg).map_err(|_| ws_error())?;
+                encode_envelope_with_token(&self.encoding, &payload, Some(token))
+            }
+            None => Ok(Message::text(msg)),
+        }
+    }
+
+    pub async fn send_typed<T: Serialize>(&mut self, msg: &Command<T>) -> Result<()> {
+        let msg = self.compression.compress(encode_envelope_with_token(
+            &self.encoding,
+            msg,
+            self.token.as_deref(),
+        )?)?;
+        self.socket.send(msg).await.map_err(|_| ws_error())
+    }
+
+    /// Next response in the negotiated encoding, of any protocol version.
+    pub async fn recv_typed<T: DeserializeOwned>(&mut self) -> Result<ResponseBody<T>> {
+        match self.read().await? {
+            Message::Close(_) => {
+                log::error!("WebSocket closed by the server");
+                Err(ws_error())
+            }
+            msg => decode_envelope(&self.encoding, &msg).map(|envelope| envelope.payload),
+        }
+    }
+
+    /// Next frame, without reconnecting. Fails with `ConnectionClosed` once
+    /// the pongs stop and with an interrupted io error when cancelled.
     pub async fn read_msg(
         &mut self,
     ) -> std::result::Result<tungstenite::Message, tungstenite::Error> {
-        self.socket.read_message()
+        loop {
+            match self.read_frame().await? {
+                Frame::Message(msg) => return Ok(msg),
+                Frame::Tick => (),
+                Frame::Cancelled => {
+                    return Err(tungstenite::Error::Io(io::Error::new(
+                        ErrorKind::Interrupted,
+                        "read cancelled",
+                    )))
+                }
+            }
+        }
+    }
+
+    async fn read_frame(&mut self) -> std::result::Result<Frame, tungstenite::Error> {
+        let now = Instant::now();
+        if !self.heartbeat.is_alive(now) {
+            log::error!("WebSocket connection lost, no pong received");
+            self.connection.connection_lost();
+            return Err(tungstenite::Error::ConnectionClosed);
+        }
+
+        if self.heartbeat.should_ping(now) {
+            self.heartbeat.ping_sent(now);
+            self.socket.send(Message::Ping(vec![])).await?;
+        }
+
+        let enabled = self.heartbeat.is_enabled();
+        let wait = self.heartbeat.next_ping(now);
+        let cancel = self.cancel.clone();
+        let socket = &mut self.socket;
+        let next = async {
+            match enabled {
+                true => tokio::time::timeout(wait, socket.next()).await.ok(),
+                false => Some(socket.next().await),
+            }
+        };
+
+        let next = tokio::select! {
+            next = next => next,
+            _ = cancel.notified() => return Ok(Frame::Cancelled),
+        };
+
+        match next {
+            None => Ok(Frame::Tick),
+            Some(Some(Ok(msg))) => {
+                self.heartbeat.pong_received(Instant::now());
+                self.compression
+                    .decompress(msg)
+                    .map(Frame::Message)
+                    .map_err(|_| {
+                        tungstenite::Error::Io(io::Error::new(
+                            ErrorKind::InvalidData,
+                            "invalid compressed message",
+                        ))
+                    })
+            }
+            Some(Some(Err(err))) => Err(err),
+            Some(None) => Err(tungstenite::Error::ConnectionClosed),
+        }
     }
 
     pub async fn disconnect(&mut self) -> Result<()> {
-        self.socket.close(None).unwrap();
+        self.socket.close(None).await.unwrap();
         Ok(())
     }
+
+    async fn open_socket(&self) -> Result<(Socket, Encoding, Compression)> {
+        match &self.builder {
+            Some(builder) => Self::open_socket_with(builder).await,
+            None => {
+                let (socket, response) = connect_async(self.url.as_str())
+                    .await
+                    .map_err(|_| ws_error())?;
+                Ok((
+                    socket,
+                    Encoding::from_headers(response.headers()),
+                    Compression::None,
+                ))
+            }
+        }
+    }
+
+    async fn open_socket_with(
+        builder: &WebSocketBuilder,
+    ) -> Result<(Socket, Encoding, Compression)> {
+        let request = builder.request();
+        let stream = builder.try_tcp_stream(&request).map_err(|_| ws_error())?;
+        stream.set_nonblocking(true).map_err(|_| ws_error())?;
+        let stream = TcpStream::from_std(stream).map_err(|_| ws_error())?;
+        let (socket, response) = client_async_tls(request, stream)
+            .await
+            .map_err(|_| ws_error())?;
+        Ok((
+            socket,
+            Encoding::from_headers(response.headers()),
+            builder
+                .preferred_compression()
+                .negotiate(response.headers()),
+        ))
+    }
 }
@@ -0,0 +1,429 @@
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::models::config::ConfigUpdate;
+use crate::ws::auth::{Authenticator, SessionTokens};
+use crate::ws::compression::{Compression, EXTENSIONS_HEADER};
+use crate::ws::encoding::Encoding;
+use crate::ws::envelope::{decode_envelope, encode_envelope};
+use crate::ws::message::*;
+
+use bson::Uuid;
+use futures_util::future::BoxFuture;
+use futures_util::{Future, SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_tungstenite::accept_hdr_async;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tungstenite::http::HeaderValue;
+
+type Handler = Arc<dyn Fn(Uuid, Value, Sessions) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+fn ws_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::WebSocketError,
+    }
+}
+
+#[derive(Debug)]
+struct Session {
+    encoding: Encoding,
+    compression: Compression,
+    sender: UnboundedSender<Message>,
+    subscriptions: HashSet<String>,
+}
+
+impl Session {
+    fn encode<T: Serialize>(&self, msg: &ResponseBody<T>) -> Result<Message> {
+        self.compression
+            .compress(encode_envelope(&self.encoding, msg)?)
+    }
+}
+
+/// Connected sessions, shared by the server and the command handlers.
+#[derive(Debug, Clone, Default)]
+pub struct Sessions {
+    sessions: Arc<Mutex<HashMap<Uuid, Session>>>,
+}
+
+impl Sessions {
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn ids(&self) -> Vec<Uuid> {
+        self.sessions.lock().unwrap().keys().cloned().collect()
+    }
+
+    pub fn contains(&self, session_id: &Uuid) -> bool {
+        self.sessions.lock().unwrap().contains_key(session_id)
+    }
+
+    pub fn subscribe(&self, session_id: &Uuid, topic: &str) {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(session_id) {
+            session.subscriptions.insert(topic.to_owned());
+        }
+    }
+
+    pub fn unsubscribe(&self, session_id: &Uuid, topic: &str) {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(session_id) {
+            session.subscriptions.remove(topic);
+        }
+    }
+
+    pub fn subscribers(&self, topic: &str) -> Vec<Uuid> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, session)| session.subscriptions.contains(topic))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    pub fn send<T: Serialize>(&self, session_id: &Uuid, msg: &ResponseBody<T>) -> Result<()> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(session_id).ok_or_else(ws_error)?;
+        let msg = session.encode(msg)?;
+        session.sender.send(msg).map_err(|_| ws_error())
+    }
+
+    /// Sends the response to the sessions subscribed to the topic, returning
+    /// how many got it.
+    pub fn broadcast<T: Serialize>(&self, topic: &str, msg: &ResponseBody<T>) -> usize {
+        self.sessions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|session| session.subscriptions.contains(topic))
+            .filter_map(|session| session.encode(msg).ok().map(|x| (session, x)))
+            .filter(|(session, msg)| session.sender.send(msg.clone()).is_ok())
+            .count()
+    }
+
+    fn insert(&self, session_id: Uuid, session: Session) {
+        self.sessions.lock().unwrap().insert(session_id, session);
+    }
+
+    fn remove(&self, session_id: &Uuid) {
+        self.sessions.lock().unwrap().remove(session_id);
+    }
+}
+
+struct Auth {
+    authenticator: Arc<dyn Authenticator>,
+    tokens: SessionTokens,
+}
+
+struct Router {
+    handlers: HashMap<CommandType, Handler>,
+    auth: Option<Auth>,
+}
+
+/// Server side of the protocol: accepts connections, opens a session for
+/// each one and routes the commands to the handler of its `CommandType`.
+/// With an authenticator, sessions must send their credentials with
+/// `InitSession` and the following commands the issued session token.
+pub struct WebSocketServer {
+    handlers: HashMap<CommandType, Handler>,
+    sessions: Sessions,
+    compression: Compression,
+    auth: Option<Auth>,
+}
+
+impl WebSocketServer {
+    pub fn new() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            sessions: Sessions::default(),
+            compression: Compression::None,
+            auth: None,
+        }
+    }
+
+    pub fn authenticator<A: Authenticator + 'static>(mut self, val: A) -> Self {
+        self.auth = Some(Auth {
+            authenticator: Arc::new(val),
+            tokens: SessionTokens::from_env(),
+        });
+        self
+    }
+
+    pub fn session_tokens(mut self, val: SessionTokens) -> Self {
+        if let Some(auth) = &mut self.auth {
+            auth.tokens = val;
+        }
+        self
+    }
+
+    /// Accepts the compression for the clients offering it.
+    pub fn compression(mut self, val: Compression) -> Self {
+        self.compression = val;
+        self
+    }
+
+    pub fn sessions(&self) -> Sessions {
+        self.sessions.clone()
+    }
+
+    /// Handler of the command, with its data decoded as `T`.
+    pub fn on<T, F, Fut>(mut self, command: CommandType, handler: F) -> Self
+    where
+        T: DeserializeOwned + Send + 'static,
+        F: Fn(Uuid, Command<T>, Sessions) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.handlers.insert(
+            command,
+            Arc::new(move |session_id, msg, sessions| {
+                let handler = handler.clone();
+                Box::pin(async move {
+                    let msg: Command<T> = serde_json::from_value(msg).map_err(|_| ws_error())?;
+                    handler(session_id, msg, sessions).await
+                })
+            }),
+        );
+        self
+    }
+
+    /// Applies the `UpdateConfig` commands to the runtime config and answers
+    /// with the audit. Invalid updates get an error and change nothing.
+    pub fn config_updates(self) -> Self {
+        self.on(
+            CommandType::UpdateConfig,
+            |session_id, msg: Command<ConfigUpdate>, sessions: Sessions| async move {
+                let audit = msg.data.ok_or_else(ws_error)?.apply()?;
+                sessions.send(
+                    &session_id,
+                    &ResponseBody {
+                        response: ResponseType::ConfigUpdated,
+                        payload: Some(audit),
+                    },
+                )
+            },
+        )
+    }
+
+    pub async fn run(self, addr: &str) -> Result<()> {
+        let listener = TcpListener::bind(addr).await.map_err(|_| ws_error())?;
+        log::info!("WebSocket server listening on {}", addr);
+        self.serve(listener).await
+    }
+
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        let router = Arc::new(Router {
+            handlers: self.handlers,
+            auth: self.auth,
+        });
+        loop {
+            let (stream, addr) = listener.accept().await.map_err(|_| ws_error())?;
+            let router = router.clone();
+            let sessions = self.sessions.clone();
+            let compression = self.compression;
+
+            tokio::spawn(async move {
+                if let Err(err) =
+                    Self::handle_connection(stream, router, sessions, compression).await
+                {
+                    log::error!("Connection {} closed with error {:?}", addr, err);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(
+        stream: TcpStream,
+        router: Arc<Router>,
+        sessions: Sessions,
+        accepted: Compression,
+    ) -> Result<()> {
+        let mut encoding = Encoding::Json;
+        let mut compression = Compression::None;
+        let callback = |request: &Request, mut response: Response| {
+            let offered = request
+                .headers()
+                .get(SEC_WEBSOCKET_PROTOCOL)
+                .and_then(|val| val.to_str().ok())
+                .filter(|val| {
+                    val.split(',')
+                        .any(|x| Encoding::from_subprotocol(x).is_some())
+                });
+
+            if let Some(offered) = offered {
+                encoding = Encoding::negotiate(offered);
+                if let Ok(val) = HeaderValue::from_str(&encoding.subprotocol()) {
+                    response.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, val);
+                }
+            }
+
+            compression = accepted.negotiate(request.headers());
+            if let Some(extension) = compression.extension() {
+                response
+                    .headers_mut()
+                    .insert(EXTENSIONS_HEADER, HeaderValue::from_static(extension));
+            }
+            Ok::<Response, ErrorResponse>(response)
+        };
+        let socket = accept_hdr_async(stream, callback)
+            .await
+            .map_err(|_| ws_error())?;
+
+        let (mut write, mut read) = socket.split();
+        let (sender, mut receiver) = unbounded_channel::<Message>();
+        let session_id = Uuid::new();
+
+        sessions.insert(
+            session_id,
+            Session {
+                encoding,
+                compression,
+                sender,
+                subscriptions: HashSet::new(),
+            },
+        );
+        log::info!("Session {} connected", session_id);
+
+        let writer = tokio::spawn(async move {
+            while let Some(msg) = receiver.recv().await {
+                if write.send(msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        sessions.send(
+            &session_id,
+            &ResponseBody {
+                response: ResponseType::Connected,
+                payload: Some(ConnectedData {
+                    session_id,
+                    encoding,
+                }),
+            },
+        )?;
+
+        while let Some(Ok(msg)) = read.next().await {
+            match msg {
+                Message::Text(_) | Message::Binary(_) => {
+                    let msg = compression.decompress(msg);
+                    Self::route(&session_id, &encoding, msg, &router, &sessions).await
+                }
+                Message::Close(_) => break,
+                _ => (),
+            }
+        }
+
+        sessions.remove(&session_id);
+        writer.abort();
+        log::info!("Session {} disconnected", session_id);
+        Ok(())
+    }
+
+    async fn route(
+        session_id: &Uuid,
+        encoding: &Encoding,
+        msg: Result<Message>,
+        router: &Router,
+        sessions: &Sessions,
+    ) {
+        let envelope = match msg.and_then(|msg| decode_envelope::<Value>(encoding, &msg)) {
+            Ok(envelope) => envelope,
+            Err(_) => return Self::respond(session_id, sessions, ResponseType::Error),
+        };
+
+        let command = envelope
+            .payload
+            .get("command")
+            .cloned()
+            .and_then(|command| serde_json::from_value::<CommandType>(command).ok());
+
+        if let Some(auth) = &router.auth {
+            let authorized = envelope
+                .token
+                .as_deref()
+                .map(|token| auth.tokens.is_valid(token))
+                .unwrap_or(false);
+
+            match command {
+                Some(CommandType::InitSession) => {
+                    return Self::authenticate(session_id, &envelope.payload, auth, sessions)
+                }
+                _ if !authorized => {
+                    log::warn!("Session {} command not authorized", session_id);
+                    return Self::respond(session_id, sessions, ResponseType::Unauthorized);
+                }
+                _ => (),
+            }
+        }
+
+        let handled = match command.and_then(|command| router.handlers.get(&command)) {
+            Some(handler) => handler(*session_id, envelope.payload, sessions.clone())
+                .await
+                .is_ok(),
+            None => false,
+        };
+
+        if !handled {
+            log::error!("Session {} command not handled", session_id);
+            Self::respond(session_id, sessions, ResponseType::Error);
+        }
+    }
+
+    fn authenticate(session_id: &Uuid, msg: &Value, auth: &Auth, sessions: &Sessions) {
+        let credentials = msg
+            .get("data")
+            .cloned()
+            .and_then(|data| serde_json::from_value::<Credentials>(data).ok())
+            .filter(|credentials| auth.authenticator.authenticate(credentials));
+
+        match credentials {
+            Some(_) => {
+                log::info!("Session {} authenticated", session_id);
+                sessions
+                    .send(
+                        session_id,
+                        &ResponseBody {
+                            response: ResponseType::Authenticated,
+                            payload: Some(SessionToken {
+                                session_id: *session_id,
+                                token: auth.tokens.issue(),
+                                expires_in: auth.tokens.ttl().as_secs(),
+                            }),
+                        },
+                    )
+                    .ok();
+            }
+            None => {
+                log::warn!("Session {} authentication failed", session_id);
+                Self::respond(session_id, sessions, ResponseType::Unauthorized);
+            }
+        }
+    }
+
+    fn respond(session_id: &Uuid, sessions: &Sessions, response: ResponseType) {
+        sessions
+            .send(
+                session_id,
+                &ResponseBody {
+                    response,
+                    payload: Some(false),
+                },
+            )
+            .ok();
+    }
+}
+
+impl Default for WebSocketServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,19 +1,49 @@
-use crate::error::Result;
+use crate::error::{Result, RsAlgoError, RsAlgoErrorKind};
+use crate::ws::compression::Compression;
+use crate::ws::encoding::Encoding;
+use crate::ws::envelope::{decode_envelope, encode_envelope_with_token};
+use crate::ws::heartbeat::Heartbeat;
+use crate::ws::message::{
+    Command, CommandType, Credentials, ResponseBody, ResponseType, SessionToken,
+};
+use crate::ws::outbound::{OutboundMetrics, OutboundQueue};
+use crate::ws::reconnect::{ConnectionState, Reconnect};
+use crate::ws::ws_builder::WebSocketBuilder;
 
 use futures_util::{
     stream::{SplitSink, SplitStream},
     Future, SinkExt, StreamExt,
 };
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{client_async_tls, connect_async, MaybeTlsStream, WebSocketStream};
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
 use std::env;
+use std::time::Instant;
 use tokio::net::TcpStream;
 use tungstenite::Message;
 
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
 #[derive(Debug)]
 pub struct WebSocket {
-    pub write: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
-    pub read: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+    pub write: SplitSink<Socket, Message>,
+    pub read: SplitStream<Socket>,
+    url: String,
+    builder: Option<WebSocketBuilder>,
+    connection: Reconnect,
+    heartbeat: Heartbeat,
+    encoding: Encoding,
+    compression: Compression,
+    outbound: OutboundQueue,
+    token: Option<String>,
+}
+
+fn ws_error() -> RsAlgoError {
+    RsAlgoError {
+        err: RsAlgoErrorKind::WebSocketError,
+    }
 }
 
 impl WebSocket {
@@ -24,14 +54,302 @@ impl WebSocket {
         //log::info!("[STREAM] Response HTTP code: {}", response.status());
 
         let (write, read) = socket.split();
-        Self { write, read }
+        Self {
+            write,
+            read,
+            url: url.to_owned(),
+            builder: None,
+            connection: Reconnect::default(),
+            heartbeat: Heartbeat::default(),
+            encoding: Encoding::from_headers(response.headers()),
+            compression: Compression::None,
+            outbound: OutboundQueue::default(),
+            token: None,
+        }
+    }
+
+    pub fn builder(url: &str) -> WebSocketBuilder {
+        WebSocketBuilder::new(url)
+    }
+
+    pub async fn connect_with(builder: &WebSocketBuilder) -> Self {
+        let (socket, encoding, compression) = Self::open_socket_with(builder)
+            .await
+            .expect("Can't connect");
+
+        log::info!("Connected to the stream server");
+
+        let (write, read) = socket.split();
+        Self {
+            write,
+            read,
+            url: builder.url().to_owned(),
+            builder: Some(builder.clone()),
+            connection: Reconnect::default(),
+            heartbeat: Heartbeat::default(),
+            encoding,
+            compression,
+            outbound: OutboundQueue::default(),
+            token: None,
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.connection.state()
+    }
+
+    pub fn on_state_change<F>(&mut self, callback: F)
+    where
+        F: Fn(&ConnectionState) + Send + Sync + 'static,
+    {
+        self.connection.on_state_change(callback);
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
     }
 
     pub async fn send(&mut self, msg: &str) -> Result<()> {
-        self.write.send(Message::text(msg)).await.unwrap();
+        let msg = self.compression.compress(self.outgoing(msg)?)?;
+        self.write.send(msg).await.unwrap();
         Ok(())
     }
 
+    /// Queues the message without waiting on the connection. Fails with the
+    /// queue full.
+    pub fn queue(&mut self, msg: &str) -> Result<()> {
+        match self.outbound.push(self.outgoing(msg)?) {
+            true => Ok(()),
+            false => Err(ws_error()),
+        }
+    }
+
+    /// Queues a tick frequency message, replacing the queued one with the
+    /// same key.
+    pub fn queue_coalesced(&mut self, key: &str, msg: &str) -> Result<()> {
+        match self.outbound.push_coalesced(key, self.outgoing(msg)?) {
+            true => Ok(()),
+            false => Err(ws_error()),
+        }
+    }
+
+    pub fn outbound_metrics(&self) -> &OutboundMetrics {
+        self.outbound.metrics()
+    }
+
+    /// Writes the next batch of queued messages with a single flush. The
+    /// queue is also flushed on every `next_message`.
+    pub async fn flush(&mut self) -> Result<usize> {
+        let batch = self.outbound.next_batch();
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        let frames = batch
+            .iter()
+            .map(|msg| self.compression.compress(msg.clone()))
+            .collect::<Result<Vec<Message>>>();
+        let frames = match frames {
+            Ok(frames) => frames,
+            Err(err) => {
+                self.outbound.requeue(batch);
+                return Err(err);
+            }
+        };
+
+        let mut result = Ok(());
+        for msg in frames {
+            result = self.write.feed(msg).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        if result.is_ok() {
+            result = self.write.flush().await;
+        }
+
+        match result {
+            Ok(_) => Ok(batch.len()),
+            Err(err) => {
+                log::error!("WebSocket stream write error: {}", err);
+                self.outbound.requeue(batch);
+                Err(ws_error())
+            }
+        }
+    }
+
+    /// Sends the command and sends it again after every reconnection.
+    pub async fn subscribe(&mut self, msg: &str) -> Result<()> {
+        self.connection.add_subscription(msg);
+        self.send(msg).await
+    }
+
+    pub fn unsubscribe(&mut self, msg: &str) {
+        self.connection.remove_subscription(msg);
+    }
+
+    /// Reconnects with exponential backoff and replays the subscriptions.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        while let Some(delay) = self.connection.next_attempt() {
+            log::info!("Reconnecting to the stream server in {:?}...", delay);
+            tokio::time::sleep(delay).await;
+
+            if let Ok((socket, encoding, compression)) = self.open_socket().await {
+                let (write, read) = socket.split();
+                self.encoding = encoding;
+                self.compression = compression;
+                self.write = write;
+                self.read = read;
+                self.heartbeat.reset(Instant::now());
+                self.connection.connected();
+                log::info!("Reconnected to the stream server");
+
+                for msg in self.connection.subscriptions().clone() {
+                    let msg = self.compression.compress(self.outgoing(&msg)?)?;
+                    self.write.send(msg).await.map_err(|_| ws_error())?;
+                }
+                return Ok(());
+            }
+        }
+
+        Err(ws_error())
+    }
+
+    /// Next data message, pinging the server on schedule and reconnecting
+    /// when the stream fails, ends or the pongs stop.
+    pub async fn next_message(&mut self) -> Result<Message> {
+        loop {
+            let now = Instant::now();
+            if !self.heartbeat.is_alive(now) {
+                log::error!("WebSocket stream lost, no pong received");
+                self.connection.connection_lost();
+                self.reconnect().await?;
+                continue;
+            }
+
+            if !self.outbound.is_empty() && self.flush().await.is_err() {
+                self.reconnect().await?;
+                continue;
+            }
+
+            if self.heartbeat.should_ping(now) {
+                self.heartbeat.ping_sent(now);
+                if let Err(err) = self.write.send(Message::Ping(vec![])).await {
+                    log::error!("WebSocket stream ping error: {}", err);
+                    self.reconnect().await?;
+                    continue;
+                }
+            }
+
+            let next = match self.heartbeat.is_enabled() {
+                true => tokio::time::timeout(self.heartbeat.next_ping(now), self.read.next())
+                    .await
+                    .ok(),
+                false => Some(self.read.next().await),
+            };
+
+            match next {
+                None => (),
+                Some(Some(Ok(msg))) => {
+                    self.heartbeat.pong_received(Instant::now());
+                    match msg {
+                        Message::Ping(_) | Message::Pong(_) => (),
+                        msg => return self.compression.decompress(msg),
+                    }
+                }
+                Some(Some(Err(err))) => {
+                    log::error!("WebSocket stream error: {}", err);
+                    self.reconnect().await?;
+                }
+                Some(None) => {
+                    log::error!("WebSocket stream closed");
+                    self.reconnect().await?;
+                }
+            }
+        }
+    }
+
+    /// Next text message. Close and binary frames are errors.
+    pub async fn read_text(&mut self) -> Result<String> {
+        match self.next_message().await? {
+            Message::Text(txt) => Ok(txt),
+            Message::Close(_) => {
+                log::error!("WebSocket stream closed by the server");
+                Err(ws_error())
+            }
+            _ => Err(ws_error()),
+        }
+    }
+
+    /// Sends the credentials with `InitSession` and keeps the issued session
+    /// token for the following commands.
+    pub async fn authenticate(&mut self, credentials: Credentials) -> Result<SessionToken> {
+        self.send_typed(&Command {
+            command: CommandType::InitSession,
+            data: Some(credentials),
+        })
+        .await?;
+
+        loop {
+            let msg = self.recv_typed::<Value>().await?;
+            match msg.response {
+                ResponseType::Authenticated => {
+                    let session: SessionToken = msg
+                        .payload
+                        .and_then(|payload| serde_json::from_value(payload).ok())
+                        .ok_or_else(ws_error)?;
+                    self.token = Some(session.token.clone());
+                    return Ok(session);
+                }
+                ResponseType::Unauthorized | ResponseType::Error => {
+                    log::error!("WebSocket authentication failed");
+                    return Err(ws_error());
+                }
+                _ => (),
+            }
+        }
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Raw commands go in an envelope with the session token once
+    /// authenticated.
+    fn outgoing(&self, msg: &str) -> Result<Message> {
+        match &self.token {
+            Some(token) => {
+                let payload: Value = serde_json::from_str(msg).map_err(|_| ws_error())?;
+                encode_envelope_with_token(&self.encoding, &payload, Some(token))
+            }
+            None => Ok(Message::text(msg)),
+        }
+    }
+
+    pub async fn send_typed<T: Serialize>(&mut self, msg: &Command<T>) -> Result<()> {
+        let msg = self.compression.compress(encode_envelope_with_token(
+            &self.encoding,
+            msg,
+            self.token.as_deref(),
+        )?)?;
+        self.write.send(msg).await.map_err(|_| ws_error())
+    }
+
+    /// Next response in the negotiated encoding, of any protocol version.
+    pub async fn recv_typed<T: DeserializeOwned>(&mut self) -> Result<ResponseBody<T>> {
+        match self.next_message().await? {
+            Message::Close(_) => {
+                log::error!("WebSocket stream closed by the server");
+                Err(ws_error())
+            }
+            msg => decode_envelope(&self.encoding, &msg).map(|envelope| envelope.payload),
+        }
+    }
+
     pub async fn ping(&mut self, msg: &[u8]) {
         self.write.send(Message::Ping(msg.to_vec())).await.unwrap();
     }
@@ -40,4 +358,39 @@ impl WebSocket {
         self.write.close().await.unwrap();
         Ok(())
     }
+
+    async fn open_socket(&self) -> Result<(Socket, Encoding, Compression)> {
+        match &self.builder {
+            Some(builder) => Self::open_socket_with(builder).await,
+            None => {
+                let (socket, response) = connect_async(self.url.as_str())
+                    .await
+                    .map_err(|_| ws_error())?;
+                Ok((
+                    socket,
+                    Encoding::from_headers(response.headers()),
+                    Compression::None,
+                ))
+            }
+        }
+    }
+
+    async fn open_socket_with(
+        builder: &WebSocketBuilder,
+    ) -> Result<(Socket, Encoding, Compression)> {
+        let request = builder.request();
+        let stream = builder.try_tcp_stream(&request).map_err(|_| ws_error())?;
+        stream.set_nonblocking(true).map_err(|_| ws_error())?;
+        let stream = TcpStream::from_std(stream).map_err(|_| ws_error())?;
+        let (socket, response) = client_async_tls(request, stream)
+            .await
+            .map_err(|_| ws_error())?;
+        Ok((
+            socket,
+            Encoding::from_headers(response.headers()),
+            builder
+                .preferred_compression()
+                .negotiate(response.headers()),
+        ))
+    }
 }
@@ -0,0 +1,94 @@
+mod common;
+
+use rs_algo_shared::backtest::engine::*;
+use rs_algo_shared::helpers::uuid::IdGenerator;
+use rs_algo_shared::models::backtest_instrument::{resolve_open_positions, OpenPositionPolicy};
+use rs_algo_shared::models::commission::CommissionModel;
+use rs_algo_shared::models::pricing::Pricing;
+use rs_algo_shared::models::strategy::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::models::trade::TradeType;
+use rs_algo_shared::scanner::instrument::HTFInstrument;
+
+use common::*;
+
+#[test]
+fn trades_on_next_bar_open_with_commissions() {
+    set_env();
+    let instrument = instrument(&[100., 101., 102., 103., 104., 105.]);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let strategy = Scheduled {
+        strategy_type: StrategyType::OnlyLong,
+        time_frame: TimeFrameType::H1,
+        entry: 1,
+        exit: 3,
+    };
+
+    let engine = BackTestEngine::new(
+        BackTestConfig::from_env()
+            .equity(10_000.)
+            .order_size(1_000.)
+            .warm_up(0)
+            .commission(CommissionModel::PerTrade(1.))
+            .open_positions(OpenPositionPolicy::Liquidate),
+    );
+    let result = engine.run(&strategy, &instrument, &HTFInstrument::None, &pricing);
+
+    assert_eq!(result.trades_in.len(), 1);
+    assert_eq!(result.trades_out.len(), 1);
+    assert_eq!(result.trades_in[0].index_in, 2);
+    assert_eq!(result.trades_in[0].trade_type, TradeType::MarketInLong);
+    assert_eq!(result.trades_out[0].index_out, 4);
+    assert_eq!(result.trades_out[0].price_out, 104.);
+    assert!(result.open_positions.is_empty());
+    assert_eq!(result.commissions, 2.);
+
+    let profit = result.trades_out[0].profit;
+    assert!((profit - 9.804 * 2.).abs() < 1e-9);
+    assert_eq!(result.equity_curve.len(), 6);
+    assert_eq!(result.equity_curve[2].equity, 9_999.);
+    assert!((result.final_equity() - (10_000. + profit - 2.)).abs() < 1e-9);
+}
+
+#[test]
+fn liquidates_open_positions_at_the_end() {
+    set_env();
+    let instrument = instrument(&[100., 101., 102., 103.]);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let strategy = Scheduled {
+        strategy_type: StrategyType::OnlyLong,
+        time_frame: TimeFrameType::H1,
+        entry: 0,
+        exit: usize::MAX,
+    };
+
+    let engine = BackTestEngine::new(
+        BackTestConfig::from_env()
+            .equity(10_000.)
+            .order_size(1_010.)
+            .commission(CommissionModel::None)
+            .open_positions(OpenPositionPolicy::Liquidate),
+    );
+    let result = engine.run(&strategy, &instrument, &HTFInstrument::None, &pricing);
+
+    assert_eq!(result.trades_in.len(), 1);
+    assert_eq!(result.trades_out.len(), 1);
+    assert_eq!(result.open_positions.len(), 1);
+    assert!((result.net_profit() - 10. * 2.).abs() < 1e-9);
+}
+
+#[test]
+fn resolves_no_open_positions_without_data() {
+    set_env();
+    let instrument = instrument(&[]);
+
+    let open_positions = resolve_open_positions(
+        &instrument,
+        &mut vec![],
+        &mut vec![],
+        &OpenPositionPolicy::Liquidate,
+        &mut IdGenerator::default(),
+    );
+
+    assert!(open_positions.is_empty());
+}
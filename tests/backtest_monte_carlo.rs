@@ -0,0 +1,72 @@
+use rs_algo_shared::backtest::monte_carlo::*;
+
+const PROFITS: [f64; 6] = [100., -50., 200., -150., 80., -30.];
+
+fn config(resampling: Resampling) -> MonteCarloConfig {
+    MonteCarloConfig::from_env()
+        .runs(200)
+        .resampling(resampling)
+        .seed(42)
+}
+
+#[test]
+fn shuffle_keeps_final_equity() {
+    let result = MonteCarlo::new(config(Resampling::Shuffle)).run(10_000., &PROFITS);
+
+    assert_eq!(result.runs, 200);
+    assert_eq!(result.final_equity.values.len(), 200);
+    assert!((result.final_equity.min - 10_150.).abs() < 1e-9);
+    assert!((result.final_equity.max - 10_150.).abs() < 1e-9);
+    assert_eq!(result.loss_probability, 0.);
+
+    // Worst order puts every loss in a row
+    assert!(result.max_drawdown.max <= 230. + 1e-9);
+    assert!(result.max_drawdown.min >= 150. - 1e-9);
+    assert!(result.max_drawdown.max > result.max_drawdown.min);
+}
+
+#[test]
+fn bootstrap_spreads_final_equity() {
+    let result = MonteCarlo::new(config(Resampling::Bootstrap)).run(10_000., &PROFITS);
+
+    assert!(result.final_equity.min < 10_150.);
+    assert!(result.final_equity.max > 10_150.);
+    assert!(result.final_equity.std_dev > 0.);
+    assert!(result.loss_probability > 0.);
+    assert!(result.final_equity.percentile(5.) <= result.final_equity.median());
+    assert!(result.final_equity.median() <= result.final_equity.percentile(95.));
+}
+
+#[test]
+fn skip_leaves_trades_out() {
+    let result = MonteCarlo::new(config(Resampling::Skip(2))).run(10_000., &PROFITS);
+
+    // Bounded by skipping the two largest winners or losers
+    assert!(result.final_equity.min >= 10_150. - 300. - 1e-9);
+    assert!(result.final_equity.max <= 10_150. + 200. + 1e-9);
+    assert!(result.final_equity.min < result.final_equity.max);
+}
+
+#[test]
+fn runs_are_reproducible() {
+    let monte_carlo = MonteCarlo::new(config(Resampling::Bootstrap));
+    let result = monte_carlo.run(10_000., &PROFITS);
+
+    assert_eq!(result, monte_carlo.run(10_000., &PROFITS));
+    assert_ne!(
+        result,
+        MonteCarlo::new(config(Resampling::Bootstrap).seed(43)).run(10_000., &PROFITS)
+    );
+}
+
+#[test]
+fn distribution_percentiles() {
+    let distribution = Distribution::new(vec![5., 1., 3., 2., 4.]);
+
+    assert_eq!(distribution.values, vec![1., 2., 3., 4., 5.]);
+    assert_eq!(distribution.mean, 3.);
+    assert_eq!(distribution.median(), 3.);
+    assert_eq!(distribution.percentile(0.), 1.);
+    assert_eq!(distribution.percentile(100.), 5.);
+    assert_eq!(Distribution::new(vec![]), Distribution::default());
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "optimize")]
+
+mod common;
+
+use rs_algo_shared::backtest::engine::*;
+use rs_algo_shared::backtest::optimize::*;
+use rs_algo_shared::error::{RsAlgoError, RsAlgoErrorKind};
+use rs_algo_shared::models::backtest_instrument::OpenPositionPolicy;
+use rs_algo_shared::models::commission::CommissionModel;
+use rs_algo_shared::models::pricing::Pricing;
+use rs_algo_shared::models::strategy::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::scanner::instrument::HTFInstrument;
+
+use common::*;
+
+fn ranges() -> Vec<ParamRange> {
+    vec![
+        ParamRange::new("entry", 0., 2., 1.),
+        ParamRange::new("exit", 3., 5., 1.),
+    ]
+}
+
+#[test]
+fn grid_covers_every_combination() {
+    let samples = Optimizer::new(ranges()).samples();
+
+    assert_eq!(samples.len(), 9);
+    assert_eq!(samples[0].get("entry"), Some(&0.));
+    assert_eq!(samples[0].get("exit"), Some(&3.));
+    assert_eq!(samples[8].get("entry"), Some(&2.));
+    assert_eq!(samples[8].get("exit"), Some(&5.));
+    assert_eq!(
+        ParamRange::new("size", 0.1, 0.3, 0.1).values(),
+        vec![0.1, 0.2, 0.3]
+    );
+}
+
+#[test]
+fn random_samples_are_reproducible_and_deduplicated() {
+    let optimizer = Optimizer::new(ranges()).search(Search::Random(50)).seed(7);
+    let samples = optimizer.samples();
+
+    assert_eq!(samples, optimizer.samples());
+    assert!(samples.len() <= 9);
+    for (i, params) in samples.iter().enumerate() {
+        assert!(!samples[i + 1..].contains(params));
+        assert!(ranges()[0].values().contains(&params["entry"]));
+    }
+    assert_ne!(samples, optimizer.clone().seed(8).samples());
+}
+
+#[test]
+fn latin_hypercube_spreads_every_dimension() {
+    let ranges = vec![
+        ParamRange::new("fast", 0., 9., 1.),
+        ParamRange::new("slow", 10., 19., 1.),
+    ];
+    let samples = Optimizer::new(ranges)
+        .search(Search::LatinHypercube(10))
+        .seed(3)
+        .samples();
+
+    assert_eq!(samples.len(), 10);
+    let mut fast: Vec<f64> = samples.iter().map(|x| x["fast"]).collect();
+    fast.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    fast.dedup();
+    assert!(fast.len() >= 8);
+}
+
+#[test]
+fn ranks_results_by_objective() {
+    set_env();
+    let instrument = instrument(&[100., 101., 102., 103., 104., 105., 106., 107.]);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let engine = BackTestEngine::new(
+        BackTestConfig::from_env()
+            .equity(10_000.)
+            .order_size(1_000.)
+            .warm_up(0)
+            .commission(CommissionModel::None)
+            .open_positions(OpenPositionPolicy::Liquidate),
+    );
+
+    let ranges = vec![
+        ParamRange::new("entry", 0., 2., 1.),
+        ParamRange::new("exit", 3., 5., 1.),
+        ParamRange::new("invalid", 0., 1., 1.),
+    ];
+    let results = Optimizer::new(ranges).objective(Objective::NetProfit).run(
+        &engine,
+        &instrument,
+        &HTFInstrument::None,
+        &pricing,
+        |params| match params["invalid"] > 0. {
+            true => Err(RsAlgoError {
+                err: RsAlgoErrorKind::InvalidStrategy,
+            }),
+            false => Ok(Box::new(Scheduled {
+                strategy_type: StrategyType::OnlyLong,
+                time_frame: TimeFrameType::H1,
+                entry: params["entry"] as usize,
+                exit: params["exit"] as usize,
+            }) as BoxedStrategy),
+        },
+    );
+
+    assert_eq!(results.len(), 9);
+    assert!(results.windows(2).all(|x| x[0].score >= x[1].score));
+    assert_eq!(results[0].params["entry"], 0.);
+    assert_eq!(results[0].params["exit"], 5.);
+    assert_eq!(results[0].score, results[0].stats.net_profit);
+}
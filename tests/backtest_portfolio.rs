@@ -0,0 +1,113 @@
+mod common;
+
+use rs_algo_shared::backtest::engine::*;
+use rs_algo_shared::backtest::portfolio::*;
+use rs_algo_shared::models::backtest_instrument::OpenPositionPolicy;
+use rs_algo_shared::models::commission::CommissionModel;
+use rs_algo_shared::models::pricing::Pricing;
+use rs_algo_shared::models::strategy::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::scanner::instrument::HTFInstrument;
+
+use common::*;
+
+fn strategy(entry: usize, exit: usize) -> Scheduled {
+    Scheduled {
+        strategy_type: StrategyType::OnlyLong,
+        time_frame: TimeFrameType::H1,
+        entry,
+        exit,
+    }
+}
+
+fn engine(equity: f64) -> BackTestEngine {
+    BackTestEngine::new(
+        BackTestConfig::from_env()
+            .equity(equity)
+            .order_size(1_000.)
+            .warm_up(0)
+            .commission(CommissionModel::PerTrade(1.))
+            .open_positions(OpenPositionPolicy::Liquidate),
+    )
+}
+
+fn limits() -> PortfolioLimits {
+    PortfolioLimits::from_env()
+        .margin_rate(1.)
+        .max_exposure_per(0.)
+        .max_symbol_exposure_per(0.)
+        .max_open_trades(0)
+}
+
+#[test]
+fn combines_legs_on_one_timeline() {
+    set_env();
+    let eurusd = instrument_at("EURUSD", &[100., 101., 102., 103., 104.], 0);
+    let gbpusd = instrument_at("GBPUSD", &[200., 198., 196., 194.], 1);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let (long_eurusd, long_gbpusd) = (strategy(0, 2), strategy(0, 2));
+
+    let portfolio = PortfolioBackTest::new(engine(10_000.), limits());
+    let result = portfolio.run(&[
+        PortfolioLeg::new(&long_eurusd, &eurusd, &HTFInstrument::None, &pricing),
+        PortfolioLeg::new(&long_gbpusd, &gbpusd, &HTFInstrument::None, &pricing),
+    ]);
+
+    assert_eq!(result.equity_curve.len(), 5);
+    assert_eq!(result.results.len(), 2);
+    assert_eq!(result.results[0].trades_out.len(), 1);
+    assert_eq!(result.results[1].trades_out.len(), 1);
+    assert_eq!(result.results[1].trades_in[0].index_in, 1);
+
+    let eurusd_profit = result.results[0].net_profit();
+    let gbpusd_profit = result.results[1].net_profit();
+    assert!(eurusd_profit > 0.);
+    assert!(gbpusd_profit < 0.);
+    assert!((result.net_profit() - (eurusd_profit + gbpusd_profit)).abs() < 1e-9);
+
+    assert_eq!(result.attribution[0].symbol, "EURUSD");
+    assert_eq!(result.attribution[1].symbol, "GBPUSD");
+    assert_eq!(result.attribution[0].commissions, 2.);
+    assert_eq!(result.attribution[0].rejected_entries, 0);
+    assert!((result.attribution[0].net_profit - eurusd_profit).abs() < 1e-9);
+}
+
+#[test]
+fn shared_margin_rejects_entries() {
+    set_env();
+    let eurusd = instrument_at("EURUSD", &[100., 101., 102., 103., 104.], 0);
+    let gbpusd = instrument_at("GBPUSD", &[100., 101., 102., 103., 104.], 0);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let (long_eurusd, long_gbpusd) = (strategy(0, 3), strategy(1, 3));
+
+    let portfolio = PortfolioBackTest::new(engine(1_500.), limits());
+    let result = portfolio.run(&[
+        PortfolioLeg::new(&long_eurusd, &eurusd, &HTFInstrument::None, &pricing),
+        PortfolioLeg::new(&long_gbpusd, &gbpusd, &HTFInstrument::None, &pricing),
+    ]);
+
+    assert_eq!(result.results[0].trades_in.len(), 1);
+    assert!(result.results[1].trades_in.is_empty());
+    assert_eq!(result.attribution[1].rejected_entries, 1);
+    assert_eq!(result.attribution[1].net_profit, 0.);
+}
+
+#[test]
+fn open_trades_limit() {
+    set_env();
+    let eurusd = instrument_at("EURUSD", &[100., 101., 102., 103., 104.], 0);
+    let gbpusd = instrument_at("GBPUSD", &[100., 101., 102., 103., 104.], 0);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let (long_eurusd, long_gbpusd) = (strategy(0, 1), strategy(2, 3));
+
+    let portfolio = PortfolioBackTest::new(engine(10_000.), limits().max_open_trades(1));
+    let result = portfolio.run(&[
+        PortfolioLeg::new(&long_eurusd, &eurusd, &HTFInstrument::None, &pricing),
+        PortfolioLeg::new(&long_gbpusd, &gbpusd, &HTFInstrument::None, &pricing),
+    ]);
+
+    // The second leg enters once the first one is out
+    assert_eq!(result.results[0].trades_out.len(), 1);
+    assert_eq!(result.results[1].trades_out.len(), 1);
+    assert_eq!(result.attribution[1].rejected_entries, 0);
+}
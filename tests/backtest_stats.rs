@@ -0,0 +1,115 @@
+use rs_algo_shared::backtest::engine::EquityPoint;
+use rs_algo_shared::backtest::stats::*;
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::trade::{CloseReason, TradeOut, TradeType};
+
+// 2023-01-30 12:00 UTC
+const START: i64 = 1_675_080_000;
+const DAY: i64 = 86_400;
+
+fn date(day: usize) -> DbDateTime {
+    to_dbtime(Local.timestamp(START + day as i64 * DAY, 0))
+}
+
+fn trade_out(index_in: usize, index_out: usize, profit: f64) -> TradeOut {
+    TradeOut {
+        id: index_out,
+        trade_type: TradeType::MarketOutLong,
+        index_in,
+        price_in: 100.,
+        ask: 100.,
+        spread_in: 0.,
+        date_in: date(index_in),
+        index_out,
+        price_origin: 100.,
+        price_out: 100. + profit / 10.,
+        bid: 100. + profit / 10.,
+        spread_out: 0.,
+        date_out: date(index_out),
+        profit,
+        profit_per: profit / 10.,
+        run_up: 0.,
+        run_up_per: 0.,
+        draw_down: 0.,
+        draw_down_per: 0.,
+        tags: vec![],
+        close_reason: CloseReason::Signal,
+    }
+}
+
+fn equity_curve(equities: &[f64]) -> Vec<EquityPoint> {
+    equities
+        .iter()
+        .enumerate()
+        .map(|(index, equity)| EquityPoint {
+            index,
+            date: date(index),
+            equity: *equity,
+        })
+        .collect()
+}
+
+#[test]
+fn trade_and_equity_metrics() {
+    let trades_out = vec![
+        trade_out(0, 1, 100.),
+        trade_out(1, 3, -150.),
+        trade_out(3, 4, 250.),
+    ];
+    let equity_curve = equity_curve(&[10_000., 10_100., 10_050., 9_950., 10_200.]);
+    let stats = BackTestStats::new(&trades_out, &equity_curve);
+
+    assert_eq!(stats.trades, 3);
+    assert_eq!(stats.winning_trades, 2);
+    assert_eq!(stats.losing_trades, 1);
+    assert_eq!(stats.gross_profit, 350.);
+    assert_eq!(stats.gross_loss, 150.);
+    assert_eq!(stats.net_profit, 200.);
+    assert_eq!(stats.net_profit_per, 2.);
+    assert!((stats.profit_factor - 350. / 150.).abs() < 1e-9);
+    assert!((stats.expectancy - 200. / 3.).abs() < 1e-9);
+    assert_eq!(stats.avg_win, 175.);
+    assert_eq!(stats.avg_loss, -150.);
+    assert_eq!(stats.largest_win, 250.);
+    assert_eq!(stats.largest_loss, -150.);
+    assert_eq!(stats.max_consecutive_wins, 1);
+    assert_eq!(stats.max_consecutive_losses, 1);
+    assert_eq!(stats.max_drawdown, 150.);
+    assert!((stats.max_drawdown_per - 150. / 10_100. * 100.).abs() < 1e-9);
+    assert!(stats.sharpe_ratio > 0.);
+    assert!(stats.sortino_ratio > stats.sharpe_ratio);
+    assert_eq!(stats.exposure, 100.);
+    assert!((stats.avg_bars_in_trade - 4. / 3.).abs() < 1e-9);
+}
+
+#[test]
+fn monthly_breakdown() {
+    let trades_out = vec![
+        trade_out(0, 1, 100.),
+        trade_out(1, 3, -150.),
+        trade_out(3, 4, 250.),
+    ];
+    let equity_curve = equity_curve(&[10_000., 10_100., 10_050., 9_950., 10_200.]);
+    let stats = BackTestStats::new(&trades_out, &equity_curve);
+
+    assert_eq!(stats.monthly.len(), 2);
+    let (january, february) = (&stats.monthly[0], &stats.monthly[1]);
+    assert_eq!((january.year, january.month), (2023, 1));
+    assert_eq!(january.trades, 1);
+    assert_eq!(january.net_profit, 100.);
+    assert!((january.return_per - 1.).abs() < 1e-9);
+    assert_eq!((february.year, february.month), (2023, 2));
+    assert_eq!(february.trades, 2);
+    assert_eq!(february.net_profit, 100.);
+    assert!((february.return_per - 100. / 10_100. * 100.).abs() < 1e-9);
+
+    let serialized = serde_json::to_string(&stats).unwrap();
+    let deserialized: BackTestStats = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, stats);
+}
+
+#[test]
+fn empty_backtest() {
+    let stats = BackTestStats::new(&vec![], &vec![]);
+    assert_eq!(stats, BackTestStats::default());
+}
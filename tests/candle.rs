@@ -0,0 +1,189 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::scanner::candle::*;
+
+// 2023-01-02 10:00:00 UTC
+const START: i64 = 1_672_653_600;
+
+fn ln(bar: DOHLCV) -> DOHLCV {
+    (bar.0, bar.1.ln(), bar.2.ln(), bar.3.ln(), bar.4.ln(), bar.5)
+}
+
+fn build(bar: DOHLCV, previous: Vec<DOHLCV>, logarithmic: bool) -> Candle {
+    build_with(bar, previous, logarithmic, CandlePatternConfig::default())
+}
+
+fn build_with(
+    bar: DOHLCV,
+    previous: Vec<DOHLCV>,
+    logarithmic: bool,
+    config: CandlePatternConfig,
+) -> Candle {
+    std::env::set_var("CANDLE_TYPES", "true");
+
+    Candle::new()
+        .date(bar.0)
+        .open(bar.1)
+        .high(bar.2)
+        .low(bar.3)
+        .close(bar.4)
+        .volume(bar.5)
+        .is_closed(true)
+        .previous_candles(previous)
+        .logarithmic(logarithmic)
+        .pattern_config(config)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn linear_and_log_candles_match() {
+    let prev = (Local.timestamp(START, 0), 10., 10.5, 8.9, 9., 100.);
+    let bar = (Local.timestamp(START + 60, 0), 8.8, 10.6, 8.7, 10.5, 100.);
+
+    let linear = build(bar, vec![prev, prev], false);
+    let log = build(ln(bar), vec![ln(prev), ln(prev)], true);
+
+    assert_eq!(linear.candle_type(), &CandleType::Engulfing);
+    assert_eq!(log.candle_type(), linear.candle_type());
+    assert_eq!(
+        log.patterns()
+            .iter()
+            .map(|pattern| pattern.candle_type.clone())
+            .collect::<Vec<CandleType>>(),
+        linear
+            .patterns()
+            .iter()
+            .map(|pattern| pattern.candle_type.clone())
+            .collect::<Vec<CandleType>>()
+    );
+}
+
+#[test]
+fn logarithmic_values_round_trip() {
+    let bar = (
+        Local.timestamp(START, 0),
+        1.0845,
+        1.0912,
+        1.0801,
+        1.0876,
+        250.,
+    );
+    let candle = build(bar, vec![], false);
+    let round_trip = candle.to_logarithmic_values().from_logarithmic_values();
+
+    for (a, b) in [
+        (candle.open(), round_trip.open()),
+        (candle.high(), round_trip.high()),
+        (candle.low(), round_trip.low()),
+        (candle.close(), round_trip.close()),
+    ] {
+        assert!((a - b).abs() < 1e-12);
+    }
+    assert_eq!(candle.volume(), round_trip.volume());
+    assert_eq!(candle.date(), round_trip.date());
+}
+
+fn bar(secs: i64, open: f64, high: f64, low: f64, close: f64) -> DOHLCV {
+    (
+        Local.timestamp(START + secs, 0),
+        open,
+        high,
+        low,
+        close,
+        100.,
+    )
+}
+
+fn score(candle: &Candle, candle_type: CandleType) -> f64 {
+    candle.pattern_score(&candle_type).unwrap()
+}
+
+#[test]
+fn doji_scores_by_body_and_respects_threshold() {
+    let doji = bar(0, 9.99, 10.5, 9.5, 10.01);
+
+    let candle = build(doji, vec![], false);
+    assert_eq!(candle.candle_type(), &CandleType::Doji);
+    assert!((score(&candle, CandleType::Doji) - 0.8).abs() < 1e-9);
+
+    let strict = CandlePatternConfig {
+        doji_body: 0.01,
+        ..CandlePatternConfig::default()
+    };
+    let candle = build_with(doji, vec![], false, strict);
+    assert_eq!(candle.candle_type(), &CandleType::Default);
+    assert!(candle.patterns().is_empty());
+}
+
+#[test]
+fn candle_type_keeps_priority_over_best_score() {
+    let karakasa = bar(0, 10.999, 11.2, 10., 11.);
+
+    let candle = build(karakasa, vec![], false);
+
+    assert_eq!(candle.candle_type(), &CandleType::Karakasa);
+    assert!(candle.is_bullish());
+    assert_eq!(candle.patterns()[0].candle_type, CandleType::Doji);
+    assert!(score(&candle, CandleType::Doji) > score(&candle, CandleType::Karakasa));
+    assert!(score(&candle, CandleType::Karakasa) > score(&candle, CandleType::HangingMan));
+}
+
+#[test]
+fn min_score_discards_weak_patterns() {
+    let karakasa = bar(0, 10.999, 11.2, 10., 11.);
+    let config = CandlePatternConfig {
+        min_score: 0.6,
+        ..CandlePatternConfig::default()
+    };
+
+    let candle = build_with(karakasa, vec![], false, config);
+
+    assert_eq!(candle.patterns().len(), 1);
+    assert_eq!(candle.candle_type(), &CandleType::Doji);
+    assert_eq!(candle.pattern_score(&CandleType::Karakasa), None);
+}
+
+#[test]
+fn engulfing_scores_by_body_ratio() {
+    let prev = bar(0, 10., 10.5, 8.9, 9.);
+    let engulfing = bar(60, 8.8, 10.6, 8.7, 10.5);
+
+    let candle = build(engulfing, vec![prev, prev], false);
+
+    assert!((score(&candle, CandleType::Engulfing) - (1. - 1. / 1.7)).abs() < 1e-9);
+}
+
+#[test]
+fn marubozu_scores_by_shadows_and_respects_threshold() {
+    let marubozu = bar(0, 10., 11., 10., 10.95);
+
+    let candle = build(marubozu, vec![], false);
+    assert_eq!(candle.candle_type(), &CandleType::Marubozu);
+    let high_shadow = 0.05 / 10.95;
+    let expected = (1. + 1. - high_shadow / 0.1) / 2.;
+    assert!((score(&candle, CandleType::Marubozu) - expected).abs() < 1e-9);
+
+    let strict = CandlePatternConfig {
+        marubozu_shadow: 0.004,
+        ..CandlePatternConfig::default()
+    };
+    let candle = build_with(marubozu, vec![], false, strict);
+    assert_eq!(candle.pattern_score(&CandleType::Marubozu), None);
+}
+
+#[test]
+fn gap_scores_over_threshold() {
+    let prev = bar(0, 95., 100., 94., 99.);
+    let gap = bar(60, 105., 107., 104., 106.);
+
+    let candle = build(gap, vec![prev], false);
+    assert_eq!(candle.candle_type(), &CandleType::BullishGap);
+    assert!((score(&candle, CandleType::BullishGap) - 2. / 3.).abs() < 1e-9);
+
+    let strict = CandlePatternConfig {
+        gap_percentage: 6.,
+        ..CandlePatternConfig::default()
+    };
+    let candle = build_with(gap, vec![prev], false, strict);
+    assert_eq!(candle.pattern_score(&CandleType::BullishGap), None);
+}
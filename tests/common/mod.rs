@@ -0,0 +1,164 @@
+#![allow(dead_code)]
+
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::indicators::params::IndicatorsParams;
+use rs_algo_shared::models::config::ConfigUpdate;
+use rs_algo_shared::models::market::Market;
+use rs_algo_shared::models::pricing::Pricing;
+use rs_algo_shared::models::strategy::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::models::trade::Position;
+use rs_algo_shared::scanner::candle::Candle;
+use rs_algo_shared::scanner::instrument::{HTFInstrument, Instrument};
+
+// 2023-01-02 10:00 UTC, a monday
+pub const START: i64 = 1_672_653_600;
+
+/// Enters and exits long on fixed bars
+#[derive(Clone)]
+pub struct Scheduled {
+    pub strategy_type: StrategyType,
+    pub time_frame: TimeFrameType,
+    pub entry: usize,
+    pub exit: usize,
+}
+
+impl Strategy for Scheduled {
+    fn name(&self) -> &str {
+        "scheduled"
+    }
+
+    fn strategy_type(&self) -> &StrategyType {
+        &self.strategy_type
+    }
+
+    fn time_frame(&self) -> &TimeFrameType {
+        &self.time_frame
+    }
+
+    fn entry_long(
+        &self,
+        index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        match index == self.entry {
+            true => Position::MarketIn(None),
+            false => Position::None,
+        }
+    }
+
+    fn exit_long(
+        &self,
+        index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        match index == self.exit {
+            true => Position::MarketOut(None),
+            false => Position::None,
+        }
+    }
+}
+
+/// Enters whenever it is flat and exits on the next bar
+#[derive(Clone)]
+pub struct AlwaysIn {
+    pub strategy_type: StrategyType,
+    pub time_frame: TimeFrameType,
+}
+
+impl Strategy for AlwaysIn {
+    fn name(&self) -> &str {
+        "always_in"
+    }
+
+    fn strategy_type(&self) -> &StrategyType {
+        &self.strategy_type
+    }
+
+    fn time_frame(&self) -> &TimeFrameType {
+        &self.time_frame
+    }
+
+    fn entry_long(
+        &self,
+        _index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        Position::MarketIn(None)
+    }
+
+    fn exit_long(
+        &self,
+        _index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        Position::MarketOut(None)
+    }
+}
+
+pub fn set_env() {
+    for (key, val) in [
+        ("EXECUTION_MODE", "BackTest"),
+        ("ORDER_ENGINE", "backtest"),
+        ("NON_PROFITABLE_OUTS", "true"),
+        ("CANDLE_TYPES", "false"),
+        ("MIN_PRICE", "0"),
+    ] {
+        std::env::set_var(key, val);
+    }
+
+    ConfigUpdate {
+        max_buy_orders: Some(1),
+        max_sell_orders: Some(1),
+        max_stop_losses: Some(1),
+        max_pending_orders: Some(3),
+        ..ConfigUpdate::default()
+    }
+    .apply()
+    .unwrap();
+}
+
+/// EURUSD H1 bars from START with a one point range around each price
+pub fn instrument(prices: &[f64]) -> Instrument {
+    instrument_at("EURUSD", prices, 0)
+}
+
+/// H1 bars starting `offset` bars after START
+pub fn instrument_at(symbol: &str, prices: &[f64], offset: usize) -> Instrument {
+    let mut instrument = Instrument::new()
+        .symbol(symbol)
+        .market(Market::Forex)
+        .time_frame(TimeFrameType::H1)
+        .indicator_params(IndicatorsParams::default())
+        .logarithmic(false)
+        .build()
+        .unwrap();
+
+    instrument.data = prices
+        .iter()
+        .enumerate()
+        .map(|(idx, price)| {
+            Candle::new()
+                .date(Local.timestamp(START + (offset + idx) as i64 * 3600, 0))
+                .open(*price)
+                .high(price + 0.5)
+                .low(price - 0.5)
+                .close(*price)
+                .volume(100.)
+                .is_closed(true)
+                .previous_candles(vec![])
+                .logarithmic(false)
+                .build()
+                .unwrap()
+        })
+        .collect();
+    instrument
+}
@@ -0,0 +1,90 @@
+use rs_algo_shared::models::config::*;
+use rs_algo_shared::models::order_book::OrderLimits;
+use rs_algo_shared::models::risk::Risk;
+
+fn update() -> ConfigUpdate {
+    ConfigUpdate {
+        risk_per_trade: None,
+        max_buy_orders: None,
+        max_sell_orders: None,
+        max_stop_losses: None,
+        max_pending_orders: None,
+        max_spread: None,
+        dry_run: None,
+    }
+}
+
+#[test]
+fn validation_rejects_out_of_range_values() {
+    assert!(update().validate().is_ok());
+
+    for invalid in [
+        ConfigUpdate {
+            risk_per_trade: Some(0.),
+            ..update()
+        },
+        ConfigUpdate {
+            risk_per_trade: Some(150.),
+            ..update()
+        },
+        ConfigUpdate {
+            max_spread: Some(-1.),
+            ..update()
+        },
+        ConfigUpdate {
+            max_pending_orders: Some(0),
+            ..update()
+        },
+    ] {
+        assert!(invalid.validate().is_err());
+    }
+}
+
+#[test]
+fn updates_are_merged_and_audited() {
+    let before = ConfigUpdate::current();
+
+    let invalid = ConfigUpdate {
+        risk_per_trade: Some(2.),
+        max_pending_orders: Some(0),
+        ..update()
+    };
+    assert!(invalid.apply().is_err());
+    assert_eq!(ConfigUpdate::current(), before);
+
+    let audit = ConfigUpdate {
+        risk_per_trade: Some(2.),
+        max_buy_orders: Some(4),
+        max_sell_orders: Some(1),
+        max_stop_losses: Some(1),
+        max_pending_orders: Some(3),
+        ..update()
+    }
+    .apply()
+    .unwrap();
+
+    assert_eq!(audit.previous, before);
+    assert_eq!(audit.current.risk_per_trade, Some(2.));
+    assert_eq!(audit.current.max_buy_orders, Some(4));
+    assert_eq!(audit.current.max_spread, before.max_spread);
+    assert_eq!(ConfigUpdate::current(), audit.current);
+
+    // Sizing and the order limits read the new values
+    assert_eq!(Risk::from_env(), Risk::Percentage(2.));
+    assert_eq!(OrderLimits::from_env().max_buy_orders, 4);
+}
+
+#[test]
+fn missing_limits_are_an_error() {
+    assert!(OrderLimits::from_config(&update()).is_err());
+
+    let limits = OrderLimits::from_config(&ConfigUpdate {
+        max_buy_orders: Some(1),
+        max_sell_orders: Some(1),
+        max_stop_losses: Some(1),
+        max_pending_orders: Some(3),
+        ..update()
+    })
+    .unwrap();
+    assert_eq!(limits.max_pending_orders, 3);
+}
@@ -0,0 +1,46 @@
+use rs_algo_shared::helpers::date::*;
+
+const MILLIS: i64 = 1_672_574_400_123;
+
+#[test]
+fn utc_dbtime_round_trip() {
+    let date = from_millis(MILLIS);
+    let db_date = utc_to_dbtime(date);
+
+    assert_eq!(db_date.timestamp_millis(), MILLIS);
+    assert_eq!(dbtime_to_utc(&db_date), date);
+}
+
+#[test]
+fn millis_round_trip() {
+    let date = from_millis(MILLIS);
+
+    assert_eq!(to_millis(&date), MILLIS);
+    assert_eq!(to_millis(&date.with_timezone(&Local)), MILLIS);
+}
+
+#[test]
+fn ctm_round_trip() {
+    let date = from_ctm(MILLIS);
+
+    assert_eq!(to_ctm(date), MILLIS);
+    assert_eq!(date.with_timezone(&Utc), from_millis(MILLIS));
+}
+
+#[test]
+fn iso8601_round_trip() {
+    let date = from_millis(MILLIS);
+    let iso = to_iso8601(&date);
+
+    assert_eq!(iso, "2023-01-01T12:00:00.123Z");
+    assert_eq!(from_iso8601(&iso), Some(date));
+    assert_eq!(
+        from_iso8601(&to_iso8601(&date.with_timezone(&Local))),
+        Some(date)
+    );
+}
+
+#[test]
+fn iso8601_rejects_invalid_dates() {
+    assert_eq!(from_iso8601("2023-13-01"), None);
+}
@@ -0,0 +1,57 @@
+use rs_algo_shared::patterns::divergence::*;
+
+// Flat series at `base` plus a triangular bump of `height` at each center
+fn series(base: f64, bumps: &[(usize, f64)]) -> Vec<f64> {
+    (0..=40)
+        .map(|idx| {
+            bumps.iter().fold(base, |acc, (center, height)| {
+                let distance = idx.abs_diff(*center) as f64;
+                acc + height.signum() * (height.abs() - 3. * distance).max(0.)
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn regular_bearish_on_lower_indicator_high() {
+    // Indicator peaks one bar after the price
+    let indicator = series(40., &[(11, 30.), (31, 20.)]);
+    let events = detect_divergences(
+        &[(10, 110.), (30, 115.)],
+        &[],
+        &indicator,
+        &DivergenceConfig::default(),
+    );
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, DivergenceKind::RegularBearish);
+    assert_eq!(events[0].from(), 10);
+    assert_eq!(events[0].to(), 30);
+    assert_eq!(events[0].indicator_from, (11, 70.));
+    assert_eq!(events[0].indicator_to, (31, 60.));
+}
+
+#[test]
+fn hidden_bullish_on_lower_indicator_low() {
+    let indicator = series(60., &[(10, -30.), (30, -40.)]);
+    let events = detect_divergences(
+        &[],
+        &[(10, 100.), (30, 104.)],
+        &indicator,
+        &DivergenceConfig::default(),
+    );
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, DivergenceKind::HiddenBullish);
+    assert!(events[0].kind.is_bullish() && events[0].kind.is_hidden());
+    assert_eq!(events[0].indicator_to, (30, 20.));
+}
+
+#[test]
+fn no_divergence_when_peaks_agree_or_are_too_far() {
+    let indicator = series(40., &[(10, 20.), (30, 30.)]);
+    let config = DivergenceConfig::default();
+
+    assert!(detect_divergences(&[(10, 110.), (30, 115.)], &[], &indicator, &config).is_empty());
+    assert!(detect_divergences(&[(5, 110.), (30, 105.)], &[], &indicator, &config).is_empty());
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use rs_algo_shared::backtest::engine::*;
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::backtest_instrument::OpenPositionPolicy;
+use rs_algo_shared::models::commission::CommissionModel;
+use rs_algo_shared::models::equity::Equity;
+use rs_algo_shared::models::pricing::Pricing;
+use rs_algo_shared::models::risk::Risk;
+use rs_algo_shared::models::strategy::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::models::trade::{Position, TradeIn, TradeType};
+use rs_algo_shared::scanner::instrument::{HTFInstrument, Instrument};
+
+use common::*;
+
+fn trade_in(trade_type: TradeType, quantity: f64, price_in: f64) -> TradeIn {
+    TradeIn {
+        id: 0,
+        index_in: 0,
+        quantity,
+        origin_price: price_in,
+        price_in,
+        ask: price_in,
+        spread: 0.,
+        date_in: to_dbtime(Local.timestamp(START, 0)),
+        trade_type,
+        tags: vec![],
+        risk: Risk::None,
+        risk_amount: 0.,
+    }
+}
+
+/// Enters when flat until the drawdown reaches the limit, exits on the
+/// next bar
+#[derive(Clone)]
+struct KillSwitch {
+    strategy_type: StrategyType,
+    time_frame: TimeFrameType,
+    max_drawdown: f64,
+    equity: Equity,
+}
+
+impl Strategy for KillSwitch {
+    fn name(&self) -> &str {
+        "kill_switch"
+    }
+
+    fn strategy_type(&self) -> &StrategyType {
+        &self.strategy_type
+    }
+
+    fn time_frame(&self) -> &TimeFrameType {
+        &self.time_frame
+    }
+
+    fn update_equity(&mut self, equity: &Equity) {
+        self.equity = equity.clone();
+    }
+
+    fn entry_long(
+        &self,
+        _index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        match self.equity.drawdown() < self.max_drawdown {
+            true => Position::MarketIn(None),
+            false => Position::None,
+        }
+    }
+
+    fn exit_long(
+        &self,
+        _index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        Position::MarketOut(None)
+    }
+}
+
+#[test]
+fn marks_open_trades_with_spread() {
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0.5, 0.01, 0.);
+    let long = trade_in(TradeType::MarketInLong, 10., 100.);
+    let short = trade_in(TradeType::MarketInShort, 10., 100.);
+    let mut equity = Equity::new(10_000.);
+
+    equity.mark(vec![&long, &short], 102., &pricing, 1.);
+    assert_eq!(equity.open_trades(), 2);
+    assert_eq!(equity.unrealized(), 20. - 25.);
+    assert_eq!(equity.value(), 9_995.);
+    assert_eq!(equity.peak(), 10_000.);
+    assert_eq!(equity.drawdown(), 5.);
+
+    equity.mark(Some(&long), 105., &pricing, 1.);
+    assert_eq!(equity.value(), 10_050.);
+    assert_eq!(equity.peak(), 10_050.);
+
+    equity.realize(50.);
+    equity.charge(2.);
+    equity.mark(None::<&TradeIn>, 105., &pricing, 1.);
+    assert_eq!(equity.balance(), 10_048.);
+    assert_eq!(equity.value(), 10_048.);
+    assert_eq!(equity.profit(), 48.);
+    assert_eq!(equity.drawdown(), 2.);
+    assert!((equity.drawdown_per() - 2. / 10_050. * 100.).abs() < 1e-9);
+}
+
+#[test]
+fn aggregates_accounts() {
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let long = trade_in(TradeType::MarketInLong, 10., 100.);
+    let mut first = Equity::new(10_000.);
+    let mut second = Equity::new(10_000.);
+    first.realize(100.);
+    second.charge(5.);
+    second.mark(Some(&long), 101., &pricing, 1.);
+
+    let mut portfolio = Equity::new(10_000.);
+    portfolio.aggregate(vec![&first, &second]);
+    assert_eq!(portfolio.value(), 10_105.);
+    assert_eq!(portfolio.open_trades(), 1);
+    assert_eq!(portfolio.peak(), 10_105.);
+}
+
+#[test]
+fn strategies_get_the_equity() {
+    set_env();
+    let instrument = instrument(&[100., 99., 98., 97., 96., 95., 94.]);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let strategy = |max_drawdown: f64| KillSwitch {
+        strategy_type: StrategyType::OnlyLong,
+        time_frame: TimeFrameType::H1,
+        max_drawdown,
+        equity: Equity::default(),
+    };
+
+    let engine = BackTestEngine::new(
+        BackTestConfig::from_env()
+            .equity(10_000.)
+            .order_size(1_000.)
+            .warm_up(0)
+            .commission(CommissionModel::None)
+            .open_positions(OpenPositionPolicy::Liquidate),
+    );
+
+    let halted = engine.run(&strategy(5.), &instrument, &HTFInstrument::None, &pricing);
+    assert_eq!(halted.trades_in.len(), 1);
+
+    let unbounded = engine.run(
+        &strategy(f64::MAX),
+        &instrument,
+        &HTFInstrument::None,
+        &pricing,
+    );
+    assert!(unbounded.trades_in.len() > 1);
+}
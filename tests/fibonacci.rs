@@ -0,0 +1,44 @@
+use rs_algo_shared::patterns::fibonacci::*;
+
+fn assert_price(fibonacci: &Fibonacci, name: &str, price: f64) {
+    let level = fibonacci.level(name).unwrap();
+    assert!(
+        (level.price - price).abs() < 1e-9,
+        "{} {}",
+        name,
+        level.price
+    );
+}
+
+#[test]
+fn levels_of_the_last_up_swing() {
+    let maxima = vec![(2, 105.), (20, 200.)];
+    let minima = vec![(10, 100.)];
+    let fibonacci = Fibonacci::from_peaks(&[], &[], &maxima, &minima);
+
+    let swing = fibonacci.swing().unwrap();
+    assert!(swing.is_up());
+    assert_eq!(swing.from, (10, 100.));
+    assert_eq!(fibonacci.retracements().len(), 3);
+    assert_eq!(fibonacci.extensions().len(), 2);
+    assert_price(&fibonacci, "38.2%", 161.8);
+    assert_price(&fibonacci, "50.0%", 150.);
+    assert_price(&fibonacci, "61.8%", 138.2);
+    assert_price(&fibonacci, "161.8%", 261.8);
+}
+
+#[test]
+fn extrema_take_precedence_and_refresh_on_new_swing() {
+    let local_maxima = vec![(20, 200.)];
+    let local_minima = vec![(10, 100.)];
+    let mut fibonacci =
+        Fibonacci::from_peaks(&[(5, 300.)], &[(15, 100.)], &local_maxima, &local_minima);
+
+    assert!(!fibonacci.swing().unwrap().is_up());
+    assert_price(&fibonacci, "50.0%", 200.);
+    assert_price(&fibonacci, "127.2%", 45.6);
+
+    assert!(!fibonacci.next(&[(5, 300.)], &[(15, 100.)], &local_maxima, &local_minima));
+    assert!(fibonacci.next(&[], &[], &local_maxima, &local_minima));
+    assert_eq!(fibonacci.swing().unwrap().to, (20, 200.));
+}
@@ -0,0 +1,111 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::patterns::formation::*;
+use rs_algo_shared::scanner::candle::Candle;
+
+// 2023-01-02 10:00:00 UTC
+const START: i64 = 1_672_653_600;
+
+fn candles(closes: &[f64]) -> Vec<Candle> {
+    std::env::set_var("CANDLE_TYPES", "false");
+
+    closes
+        .iter()
+        .enumerate()
+        .map(|(idx, close)| {
+            Candle::new()
+                .date(Local.timestamp(START + idx as i64 * 60, 0))
+                .open(*close)
+                .high(*close)
+                .low(*close)
+                .close(*close)
+                .volume(1.)
+                .is_closed(true)
+                .previous_candles(vec![])
+                .logarithmic(false)
+                .build()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn ascending_triangle_with_breakout() {
+    let maxima = vec![(2, 110.), (6, 110.), (10, 110.)];
+    let minima = vec![(4, 100.), (8, 104.), (12, 108.)];
+    let mut closes = vec![105.; 13];
+    closes.push(109.5);
+    closes.push(112.);
+
+    let formation = detect_formation(
+        &maxima,
+        &minima,
+        &candles(&closes),
+        &FormationConfig::default(),
+    )
+    .unwrap();
+
+    assert_eq!(formation.formation_type, FormationType::AscendingTriangle);
+    assert_eq!(formation.breakout, Breakout::Up);
+    assert_eq!(formation.breakout_index, Some(14));
+    assert!(formation.apex().unwrap() > 12.);
+
+    // Peaks before the last 10 bars are left out
+    let config = FormationConfig {
+        lookback: Some(10),
+        ..FormationConfig::default()
+    };
+    assert!(detect_formation(&maxima, &minima, &candles(&closes), &config).is_none());
+}
+
+#[test]
+fn wedges_and_symmetrical_triangles() {
+    let config = FormationConfig::default();
+
+    let rising = detect_formation(
+        &[(2, 110.), (6, 114.), (10, 118.)],
+        &[(4, 100.), (8, 108.), (12, 116.)],
+        &[],
+        &config,
+    )
+    .unwrap();
+    assert_eq!(rising.formation_type, FormationType::RisingWedge);
+    assert_eq!(rising.breakout, Breakout::None);
+
+    let falling = detect_formation(
+        &[(2, 118.), (6, 110.), (10, 102.)],
+        &[(4, 100.), (8, 98.), (12, 96.)],
+        &[],
+        &config,
+    )
+    .unwrap();
+    assert_eq!(falling.formation_type, FormationType::FallingWedge);
+
+    let symmetrical = detect_formation(
+        &[(2, 120.), (6, 116.), (10, 112.)],
+        &[(4, 100.), (8, 104.), (12, 108.)],
+        &[],
+        &config,
+    )
+    .unwrap();
+    assert_eq!(
+        symmetrical.formation_type,
+        FormationType::SymmetricalTriangle
+    );
+}
+
+#[test]
+fn bull_flag_after_pole() {
+    // Pole from 100 to 120 over the first ten bars
+    let mut closes: Vec<f64> = (0..=10).map(|idx| 100. + idx as f64 * 2.).collect();
+    closes.extend(vec![118.; 12]);
+
+    let formation = detect_formation(
+        &[(10, 120.), (14, 119.), (18, 118.)],
+        &[(12, 116.), (16, 114.9), (20, 113.8)],
+        &candles(&closes),
+        &FormationConfig::default(),
+    )
+    .unwrap();
+
+    assert_eq!(formation.formation_type, FormationType::BullFlag);
+}
@@ -0,0 +1,40 @@
+use rs_algo_shared::indicators::Indicator;
+use rs_algo_shared::scanner::instrument::Instrument;
+
+// Instrument as stored before the named indicator outputs
+const BASELINE_INSTRUMENT: &str = include_str!("fixtures/baseline_instrument.json");
+
+#[test]
+fn legacy_outputs_are_read_into_named_outputs() {
+    let instrument: Instrument = serde_json::from_str(BASELINE_INSTRUMENT).unwrap();
+    let indicators = &instrument.indicators;
+
+    assert_eq!(indicators.rsi.output("rsi"), Some(&vec![50., 62.5]));
+    assert_eq!(indicators.bb.output("upper"), Some(&vec![1.075, 1.078]));
+    assert_eq!(indicators.bb.output("lower"), Some(&vec![1.065, 1.066]));
+    assert_eq!(indicators.bb.output("average"), Some(&vec![1.07, 1.072]));
+    assert_eq!(indicators.macd.output("signal"), Some(&vec![0.0005, 0.001]));
+    assert_eq!(indicators.ema_b.output("ema"), Some(&vec![1.07, 1.0705]));
+    assert_eq!(indicators.ema_b.output_names(), vec!["ema"]);
+}
+
+#[test]
+fn instruments_round_trip() {
+    let instrument: Instrument = serde_json::from_str(BASELINE_INSTRUMENT).unwrap();
+    let json = serde_json::to_string(&instrument).unwrap();
+    let restored: Instrument = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(restored.data, instrument.data);
+    assert_eq!(
+        restored.indicators.bb.outputs(),
+        instrument.indicators.bb.outputs()
+    );
+    assert_eq!(
+        restored.indicators.macd.outputs(),
+        instrument.indicators.macd.outputs()
+    );
+    assert_eq!(
+        restored.indicators.rsi.params(),
+        instrument.indicators.rsi.params()
+    );
+}
@@ -0,0 +1,151 @@
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::thread;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+pub const SESSION_ID: &str = "mock-session";
+pub const CANDLE_TS: i64 = 1_672_531_200_000;
+
+pub struct MockXtbServer {
+    pub url: String,
+    pub stream_url: String,
+}
+
+impl MockXtbServer {
+    /// Starts a single shared server for the whole test binary, since the
+    /// broker reads its urls from process wide env vars.
+    pub fn shared() -> &'static MockXtbServer {
+        static SERVER: OnceLock<MockXtbServer> = OnceLock::new();
+        SERVER.get_or_init(|| {
+            let server = MockXtbServer::start();
+            std::env::set_var("BROKER_URL", &server.url);
+            std::env::set_var("BROKER_STREAM_URL", &server.stream_url);
+            std::env::set_var("STREAM_SUBSCRIBE", "true");
+            std::env::set_var("BROKER_REQUESTS_PER_SECOND", "1000");
+            std::env::set_var("BROKER_REQUESTS_BURST", "1000");
+            server
+        })
+    }
+
+    pub fn start() -> Self {
+        let command_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let command_addr = command_listener.local_addr().unwrap();
+        let stream_addr = stream_listener.local_addr().unwrap();
+
+        command_listener.set_nonblocking(true).unwrap();
+        stream_listener.set_nonblocking(true).unwrap();
+
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            runtime.block_on(async move {
+                let command_listener = TcpListener::from_std(command_listener).unwrap();
+                let stream_listener = TcpListener::from_std(stream_listener).unwrap();
+                tokio::spawn(serve(command_listener, handle_command));
+                serve(stream_listener, handle_stream).await;
+            });
+        });
+
+        Self {
+            url: ws_url(command_addr),
+            stream_url: ws_url(stream_addr),
+        }
+    }
+}
+
+fn ws_url(addr: SocketAddr) -> String {
+    ["ws://", &addr.to_string()].concat()
+}
+
+async fn serve(listener: TcpListener, handler: fn(&Value) -> Vec<Value>) {
+    loop {
+        let (tcp, _) = listener.accept().await.unwrap();
+        tokio::spawn(handle_connection(tcp, handler));
+    }
+}
+
+async fn handle_connection(tcp: TcpStream, handler: fn(&Value) -> Vec<Value>) {
+    let mut socket = accept_async(tcp).await.unwrap();
+
+    while let Some(Ok(msg)) = socket.next().await {
+        let request: Value = match msg {
+            Message::Text(txt) => serde_json::from_str(&txt).unwrap(),
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        for response in handler(&request) {
+            socket
+                .send(Message::text(response.to_string()))
+                .await
+                .unwrap();
+        }
+    }
+}
+
+fn handle_command(request: &Value) -> Vec<Value> {
+    let arguments = &request["arguments"];
+
+    let response = match request["command"].as_str().unwrap_or("") {
+        "login" => json!({ "status": true, "streamSessionId": SESSION_ID }),
+        "ping" => json!({ "status": true }),
+        "getChartLastRequest" | "getChartRangeRequest" => json!({
+            "status": true,
+            "returnData": {
+                "digits": 5,
+                "rateInfos": [
+                    { "ctm": CANDLE_TS, "open": 110000., "high": 20., "low": -10., "close": 5., "vol": 1. },
+                    { "ctm": CANDLE_TS + 60_000, "open": 110005., "high": 15., "low": -5., "close": 10., "vol": 2. }
+                ]
+            }
+        }),
+        "getSymbol" => json!({
+            "status": true,
+            "returnData": {
+                "symbol": arguments["symbol"],
+                "ask": 1.1002,
+                "bid": 1.1,
+                "tickSize": 0.00001,
+                "trailingEnabled": true,
+                "shortSelling": true,
+                "longOnly": false,
+                "quoteId": 2
+            }
+        }),
+        _ => json!({ "status": false, "errorCode": "MOCK", "errorDescr": "Unknown command" }),
+    };
+
+    vec![response]
+}
+
+fn handle_stream(request: &Value) -> Vec<Value> {
+    match request["command"].as_str().unwrap_or("") {
+        "getKeepAlive" => {
+            vec![json!({ "command": "keepAlive", "data": { "timestamp": CANDLE_TS } })]
+        }
+        "getCandles" => vec![json!({
+            "command": "candle",
+            "data": {
+                "symbol": request["symbol"],
+                "ctm": CANDLE_TS,
+                "open": 1.1,
+                "high": 1.1002,
+                "low": 1.0999,
+                "close": 1.1001,
+                "vol": 3.
+            }
+        })],
+        "getTickPrices" => vec![json!({
+            "command": "tickPrices",
+            "data": { "symbol": request["symbol"], "ask": 1.1002, "bid": 1.1 }
+        })],
+        _ => vec![],
+    }
+}
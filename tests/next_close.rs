@@ -0,0 +1,104 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::time_frame::*;
+
+// 2023-03-26 00:00:00 UTC, Europe/Madrid moves from CET to CEST at 01:00 UTC
+const SPRING_FORWARD: i64 = 1_679_788_800;
+// 2023-10-29 00:00:00 UTC, Europe/Madrid moves from CEST to CET at 01:00 UTC
+const FALL_BACK: i64 = 1_698_537_600;
+
+fn madrid() {
+    std::env::set_var("TZ", "Europe/Madrid");
+}
+
+fn utc(timestamp: i64) -> DateTime<Local> {
+    Local.timestamp(timestamp, 0)
+}
+
+#[test]
+fn intraday_closes() {
+    madrid();
+    let date = Local.ymd(2023, 1, 2).and_hms(10, 7, 30);
+
+    assert_eq!(
+        next_close(date, &TimeFrameType::M15),
+        Local.ymd(2023, 1, 2).and_hms(10, 15, 0)
+    );
+    assert_eq!(
+        next_close(date, &TimeFrameType::H1),
+        Local.ymd(2023, 1, 2).and_hms(11, 0, 0)
+    );
+    assert_eq!(
+        next_close(Local.ymd(2023, 1, 2).and_hms(11, 0, 0), &TimeFrameType::H1),
+        Local.ymd(2023, 1, 2).and_hms(12, 0, 0)
+    );
+    assert_eq!(
+        next_close(Local.ymd(2023, 1, 2).and_hms(22, 30, 0), &TimeFrameType::H4),
+        Local.ymd(2023, 1, 3).and_hms(0, 0, 0)
+    );
+}
+
+#[test]
+fn daily_weekly_and_monthly_closes() {
+    madrid();
+    // Wednesday
+    let date = Local.ymd(2023, 12, 27).and_hms(15, 0, 0);
+
+    assert_eq!(
+        next_close(date, &TimeFrameType::D),
+        Local.ymd(2023, 12, 28).and_hms(0, 0, 0)
+    );
+    assert_eq!(
+        next_close(date, &TimeFrameType::W),
+        Local.ymd(2024, 1, 1).and_hms(0, 0, 0)
+    );
+    assert_eq!(
+        next_close(date, &TimeFrameType::MN),
+        Local.ymd(2024, 1, 1).and_hms(0, 0, 0)
+    );
+}
+
+#[test]
+fn spring_forward() {
+    madrid();
+    // 01:30 CET
+    let date = utc(SPRING_FORWARD + 1800);
+
+    // 02:00 doesn't exist, the candle closes at 03:00 CEST
+    assert_eq!(
+        next_close(date, &TimeFrameType::H1),
+        utc(SPRING_FORWARD + 3600)
+    );
+    // The 00:00 - 04:00 period lasts three hours
+    assert_eq!(
+        next_close(date, &TimeFrameType::H4),
+        Local.ymd(2023, 3, 26).and_hms(4, 0, 0)
+    );
+    assert_eq!(
+        next_close(date, &TimeFrameType::H4),
+        utc(SPRING_FORWARD + 2 * 3600)
+    );
+    assert_eq!(
+        next_close(date, &TimeFrameType::D),
+        Local.ymd(2023, 3, 27).and_hms(0, 0, 0)
+    );
+}
+
+#[test]
+fn fall_back() {
+    madrid();
+    // 02:30 CEST, the first one
+    let date = utc(FALL_BACK + 1800);
+
+    // 02:00 CET, one real hour after 02:00 CEST
+    let close = next_close(date, &TimeFrameType::H1);
+    assert_eq!(close, utc(FALL_BACK + 3600));
+    assert_eq!(
+        next_close(close, &TimeFrameType::H1),
+        utc(FALL_BACK + 2 * 3600)
+    );
+    // The 00:00 - 04:00 period lasts five hours
+    assert_eq!(
+        next_close(date, &TimeFrameType::H4),
+        utc(FALL_BACK + 3 * 3600)
+    );
+}
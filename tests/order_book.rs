@@ -0,0 +1,527 @@
+use rs_algo_shared::error::OrderError;
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::order::*;
+use rs_algo_shared::models::order_book::{OrderBook, OrderLimits};
+use rs_algo_shared::models::order_journal::OrderJournal;
+use rs_algo_shared::models::risk::Risk;
+use rs_algo_shared::models::stop_loss::StopLossType;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::models::trade::Trade;
+use rs_algo_shared::scanner::candle::Candle;
+
+const ORDER_TS: i64 = 1_672_531_200;
+
+struct ClosedTrade {
+    date: DbDateTime,
+}
+
+impl Trade for ClosedTrade {
+    fn get_date(&self) -> &DbDateTime {
+        &self.date
+    }
+    fn get_chrono_date(&self) -> DateTime<Local> {
+        from_dbtime(&self.date)
+    }
+    fn get_price_in(&self) -> &f64 {
+        &0.
+    }
+    fn get_price_out(&self) -> &f64 {
+        &0.
+    }
+}
+
+fn limits() -> OrderLimits {
+    OrderLimits {
+        max_buy_orders: 1,
+        max_sell_orders: 1,
+        max_stop_losses: 1,
+        max_pending_orders: 3,
+    }
+}
+
+fn order(trade_id: usize, order_type: OrderType, target_price: f64) -> Order {
+    let created_at = Local.timestamp_opt(ORDER_TS, 0).unwrap();
+    Order {
+        id: ORDER_TS as usize,
+        trade_id,
+        index_created: 0,
+        index_fulfilled: 0,
+        size: 1.,
+        order_type,
+        status: OrderStatus::Pending,
+        origin_price: 100.,
+        target_price,
+        created_at: to_dbtime(created_at),
+        updated_at: None,
+        full_filled_at: None,
+        valid_until: None,
+        ticks_beyond: 0,
+        breakeven: 0.,
+        oco_group: None,
+        expiry: ExpiryPolicy::GTC,
+        filled_size: 0.,
+        tags: vec![],
+        risk: Risk::None,
+        stop_price: 0.,
+        broker_order: None,
+        symbol: "EURUSD".to_owned(),
+        strategy: String::new(),
+    }
+}
+
+fn exit_legs(trade_id: usize) -> Vec<Order> {
+    let mut take_profit = order(
+        trade_id,
+        OrderType::TakeProfitLong(OrderDirection::Up, 100., 110.),
+        110.,
+    );
+    let mut stop_loss = order(
+        trade_id,
+        OrderType::StopLossLong(OrderDirection::Down, StopLossType::Price(90.)),
+        90.,
+    );
+    take_profit.set_oco_group(trade_id);
+    stop_loss.set_oco_group(trade_id);
+    vec![take_profit, stop_loss]
+}
+
+#[test]
+fn add_enforces_limits() {
+    let mut book = OrderBook::new(limits());
+    let buy = |trade_id| {
+        order(
+            trade_id,
+            OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.),
+            105.,
+        )
+    };
+
+    let rejected = book.add(vec![buy(1), buy(2)]);
+
+    assert_eq!(book.pending().len(), 1);
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(book.num_pending(), (1, 0, 0));
+}
+
+#[test]
+fn add_rejects_non_pending_orders() {
+    let mut book = OrderBook::new(limits());
+    let mut buy = order(
+        1,
+        OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.),
+        105.,
+    );
+    buy.set_status(OrderStatus::Canceled);
+
+    let rejected = book.add(vec![buy]);
+
+    assert!(book.is_empty());
+    assert_eq!(rejected.len(), 1);
+}
+
+#[test]
+fn add_rejects_brackets_that_do_not_fit() {
+    let mut book = OrderBook::new(OrderLimits {
+        max_pending_orders: 2,
+        ..limits()
+    });
+    let buy = order(
+        1,
+        OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.),
+        105.,
+    );
+
+    let rejected = book.add([vec![buy], exit_legs(1)].concat());
+
+    assert!(book.is_empty());
+    assert_eq!(rejected.len(), 3);
+}
+
+#[test]
+fn add_admits_each_trade_on_its_own() {
+    let mut book = OrderBook::new(OrderLimits {
+        max_pending_orders: 3,
+        ..limits()
+    });
+    let buy = order(
+        1,
+        OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.),
+        105.,
+    );
+
+    let rejected = book.add([vec![buy], exit_legs(1), exit_legs(2)].concat());
+
+    assert_eq!(book.pending().len(), 3);
+    assert_eq!(rejected.len(), 2);
+    assert!(rejected.iter().all(|x| x.trade_id == 2));
+}
+
+#[test]
+fn cancel_for_trade_only_touches_trade_orders() {
+    let mut book = OrderBook::new(OrderLimits {
+        max_sell_orders: 2,
+        max_pending_orders: 4,
+        ..limits()
+    });
+    let other = order(
+        2,
+        OrderType::TakeProfitLong(OrderDirection::Up, 1., 120.),
+        120.,
+    );
+    book.add([exit_legs(1), vec![other]].concat());
+
+    let trade = ClosedTrade {
+        date: to_dbtime(Local.timestamp_opt(ORDER_TS + 60, 0).unwrap()),
+    };
+    let canceled = book.cancel_for_trade(1, &trade);
+
+    assert_eq!(canceled.len(), 2);
+    assert!(canceled.iter().all(|x| x.status == OrderStatus::Canceled));
+    assert_eq!(book.cancelled().len(), 2);
+    assert_eq!(book.pending().len(), 1);
+    assert_eq!(book.pending()[0].trade_id, 2);
+}
+
+#[test]
+fn from_orders_splits_by_status() {
+    let mut fulfilled = order(
+        1,
+        OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.),
+        105.,
+    );
+    fulfilled.fulfill_order(1, Local.timestamp_opt(ORDER_TS + 60, 0).unwrap());
+
+    let book = OrderBook::from_orders(limits(), [vec![fulfilled], exit_legs(1)].concat());
+
+    assert_eq!(book.fulfilled().len(), 1);
+    assert_eq!(book.pending().len(), 2);
+    assert_eq!(book.orders().len(), 3);
+}
+
+#[test]
+fn amend_reprices_pending_order() {
+    let mut book = OrderBook::new(limits());
+    book.add(exit_legs(1));
+    let take_profit = OrderType::TakeProfitLong(OrderDirection::Up, 100., 110.);
+    let amendment = OrderAmendment {
+        target_price: Some(108.),
+        size: Some(2.),
+        expiry: None,
+    };
+    let date = to_dbtime(Local.timestamp_opt(ORDER_TS + 60, 0).unwrap());
+
+    let events = book
+        .amend(
+            ORDER_TS as usize,
+            &take_profit,
+            amendment,
+            &TimeFrameType::M1,
+            date,
+        )
+        .unwrap();
+
+    assert_eq!(events.len(), 1);
+    match &events[0] {
+        OrderEvent::Amended {
+            target_price, size, ..
+        } => {
+            assert_eq!(*target_price, (110., 108.));
+            assert_eq!(*size, (1., 2.));
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+    let amended = book
+        .pending()
+        .iter()
+        .find(|x| x.order_type.is_exit())
+        .unwrap();
+    assert_eq!(amended.target_price, 108.);
+    assert!(book
+        .pending()
+        .iter()
+        .any(|x| x.order_type.is_stop() && x.target_price == 90.));
+}
+
+#[test]
+fn amend_rejects_unknown_order() {
+    let mut book = OrderBook::new(limits());
+    book.add(exit_legs(1));
+    let take_profit = OrderType::TakeProfitLong(OrderDirection::Up, 100., 110.);
+    let date = to_dbtime(Local.timestamp_opt(ORDER_TS, 0).unwrap());
+
+    let result = book.amend(
+        0,
+        &take_profit,
+        OrderAmendment::default(),
+        &TimeFrameType::M1,
+        date,
+    );
+
+    assert!(result.is_err());
+}
+
+fn bracket(trade_id: usize) -> Vec<Order> {
+    let buy = order(
+        trade_id,
+        OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.),
+        100.,
+    );
+    [vec![buy], exit_legs(trade_id)].concat()
+}
+
+#[test]
+fn amend_keeps_legs_on_their_side_of_the_entry() {
+    let mut book = OrderBook::new(limits());
+    book.add(bracket(1));
+    let stop_loss = OrderType::StopLossLong(OrderDirection::Down, StopLossType::Price(90.));
+    let buy = OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.);
+    let date = to_dbtime(Local.timestamp_opt(ORDER_TS + 60, 0).unwrap());
+    let amend = |book: &mut OrderBook, order_type: &OrderType, target_price: f64| {
+        let amendment = OrderAmendment {
+            target_price: Some(target_price),
+            ..OrderAmendment::default()
+        };
+        book.amend(
+            ORDER_TS as usize,
+            order_type,
+            amendment,
+            &TimeFrameType::M1,
+            date,
+        )
+    };
+
+    assert!(matches!(
+        amend(&mut book, &stop_loss, 101.),
+        Err(OrderError::StopLossAboveEntry { .. })
+    ));
+    assert!(matches!(
+        amend(&mut book, &buy, 112.),
+        Err(OrderError::TargetBelowPrice { .. })
+    ));
+    assert!(amend(&mut book, &stop_loss, 95.).is_ok());
+    assert!(amend(&mut book, &buy, 98.).is_ok());
+}
+
+#[test]
+fn amend_resizes_the_exit_legs_of_an_entry() {
+    let mut book = OrderBook::new(limits());
+    book.add(bracket(1));
+    let buy = OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.);
+    let amendment = OrderAmendment {
+        size: Some(2.),
+        ..OrderAmendment::default()
+    };
+    let date = to_dbtime(Local.timestamp_opt(ORDER_TS + 60, 0).unwrap());
+
+    let events = book
+        .amend(ORDER_TS as usize, &buy, amendment, &TimeFrameType::M1, date)
+        .unwrap();
+
+    assert_eq!(events.len(), 3);
+    assert!(book.pending().iter().all(|x| x.size == 2.));
+}
+
+#[test]
+fn journal_replay_rebuilds_order_book() {
+    let mut book = OrderBook::new(limits());
+    let mut journal = OrderJournal::new();
+    let legs = exit_legs(1);
+    book.add(legs.clone());
+    journal
+        .record_all("EURUSD", &legs, OrderEvent::Created)
+        .unwrap();
+
+    let take_profit = OrderType::TakeProfitLong(OrderDirection::Up, 100., 110.);
+    let amendment = OrderAmendment {
+        target_price: Some(111.),
+        ..OrderAmendment::default()
+    };
+    let date = to_dbtime(Local.timestamp_opt(ORDER_TS + 30, 0).unwrap());
+    let amended = book
+        .amend(
+            ORDER_TS as usize,
+            &take_profit,
+            amendment,
+            &TimeFrameType::M1,
+            date,
+        )
+        .unwrap();
+    for event in amended {
+        journal.record("EURUSD", event).unwrap();
+    }
+
+    let date = Local.timestamp_opt(ORDER_TS + 60, 0).unwrap();
+    let filled = book.fill(&legs[0], 1, date).unwrap();
+    journal
+        .record("EURUSD", OrderEvent::Fulfilled(filled))
+        .unwrap();
+    let cancelled: Vec<Order> = book.cancelled().into_iter().cloned().collect();
+    journal
+        .record_all("EURUSD", &cancelled, OrderEvent::Cancelled)
+        .unwrap();
+
+    let replayed = journal.replay("EURUSD", limits());
+
+    assert_eq!(replayed.orders(), book.orders());
+    assert_eq!(replayed.fulfilled()[0].target_price, 111.);
+    assert!(journal.replay("GBPUSD", limits()).orders().is_empty());
+}
+
+#[test]
+fn closing_a_trade_cancels_partially_filled_remainder() {
+    let mut orders = exit_legs(1);
+    orders[0].fill(0.4, 1, Local.timestamp_opt(ORDER_TS + 60, 0).unwrap());
+    assert!(orders[0].is_partially_filled());
+    let mut book = OrderBook::from_orders(limits(), orders);
+
+    let trade = ClosedTrade {
+        date: to_dbtime(Local.timestamp_opt(ORDER_TS + 120, 0).unwrap()),
+    };
+    book.cancel_for_trade(1, &trade);
+
+    assert!(book.is_empty());
+    assert_eq!(book.cancelled().len(), 2);
+    assert_eq!(book.cancelled()[0].remaining_size(), 0.6);
+}
+
+#[test]
+fn add_accepts_partially_filled_orders() {
+    let mut book = OrderBook::new(limits());
+    let mut take_profit = exit_legs(1).remove(0);
+    take_profit.fill(0.4, 1, Local.timestamp_opt(ORDER_TS + 60, 0).unwrap());
+
+    let rejected = book.add(vec![take_profit]);
+
+    assert!(rejected.is_empty());
+    assert_eq!(book.num_pending(), (0, 1, 0));
+}
+
+#[test]
+fn add_rejects_entries_while_stops_are_pending() {
+    let mut book = OrderBook::new(limits());
+    book.add(exit_legs(1));
+    let buy = order(
+        2,
+        OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.),
+        105.,
+    );
+
+    let rejected = book.add(vec![buy]);
+
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(book.num_pending(), (0, 1, 1));
+}
+
+#[test]
+fn fill_keeps_partial_entries_pending() {
+    let mut book = OrderBook::new(limits());
+    let buy = order(
+        1,
+        OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.),
+        105.,
+    );
+    book.add(vec![buy.clone()]);
+    let mut partial = buy.clone();
+    partial.size = 0.4;
+    let date = Local.timestamp_opt(ORDER_TS + 60, 0).unwrap();
+
+    let filled = book.fill(&partial, 1, date).unwrap();
+    assert!(filled.is_partially_filled());
+    assert_eq!(book.pending().len(), 1);
+
+    partial.size = 0.6;
+    let filled = book.fill(&partial, 2, date).unwrap();
+    assert_eq!(filled.status, OrderStatus::Fulfilled);
+    assert!(book.is_empty());
+    assert_eq!(book.fulfilled().len(), 1);
+}
+
+#[test]
+fn fill_cancels_oco_siblings() {
+    let mut book = OrderBook::new(limits());
+    let legs = exit_legs(1);
+    book.add(legs.clone());
+    let date = Local.timestamp_opt(ORDER_TS + 60, 0).unwrap();
+
+    let filled = book.fill(&legs[0], 1, date).unwrap();
+
+    assert_eq!(filled.status, OrderStatus::Fulfilled);
+    assert!(book.is_empty());
+    assert_eq!(book.cancelled().len(), 1);
+    assert!(book.cancelled()[0].order_type.is_stop());
+}
+
+#[test]
+fn orders_keep_insertion_order() {
+    let mut book = OrderBook::new(OrderLimits {
+        max_sell_orders: 2,
+        max_pending_orders: 4,
+        ..limits()
+    });
+    let mut take_profit = order(
+        2,
+        OrderType::TakeProfitLong(OrderDirection::Up, 1., 120.),
+        120.,
+    );
+    take_profit.id = 1;
+    book.add([vec![take_profit], exit_legs(1)].concat());
+    book.fill(
+        &exit_legs(1)[0],
+        1,
+        Local.timestamp_opt(ORDER_TS + 60, 0).unwrap(),
+    );
+
+    let trade_ids: Vec<usize> = book.orders().iter().map(|x| x.trade_id).collect();
+
+    assert_eq!(trade_ids, vec![2, 1, 1]);
+    assert_eq!(get_num_pending_orders(book.orders()), (0, 1, 0));
+}
+
+#[test]
+fn filters_match_the_order_symbol_and_strategy() {
+    let mut take_profit = exit_legs(1).remove(0);
+    take_profit.strategy = "breakout".to_owned();
+
+    assert!(OrderFilter::Symbol("EURUSD".to_owned()).matches(&take_profit));
+    assert!(!OrderFilter::Symbol("GBPUSD".to_owned()).matches(&take_profit));
+    assert!(OrderFilter::Strategy("breakout".to_owned()).matches(&take_profit));
+    assert!(!OrderFilter::Strategy("reversal".to_owned()).matches(&take_profit));
+}
+
+#[test]
+fn cancel_orders_where_requires_a_filter() {
+    let mut orders = exit_legs(1);
+    let date = to_dbtime(Local.timestamp_opt(ORDER_TS + 60, 0).unwrap());
+
+    let result = cancel_orders_where(&mut orders, &[], date);
+
+    assert!(result.is_err());
+    assert!(orders.iter().all(|x| x.is_pending()));
+}
+
+#[test]
+fn fill_size_is_capped_by_the_bar_volume() {
+    let candle = Candle::new()
+        .date(Local.timestamp_opt(ORDER_TS, 0).unwrap())
+        .open(2.)
+        .high(2.)
+        .low(2.)
+        .close(2.)
+        .volume(100.)
+        .is_closed(true)
+        .previous_candles(vec![])
+        .logarithmic(false)
+        .build()
+        .unwrap();
+    let mut buy = order(
+        1,
+        OrderType::BuyOrderLong(OrderDirection::Up, 1000., 2.),
+        2.,
+    );
+    buy.size = 1000.;
+
+    // 500 units to buy, half of the 100 units traded on the bar fit
+    assert_eq!(get_fill_size(&buy, &candle, 0.5), 100.);
+    assert_eq!(get_fill_size(&buy, &candle, 10.), 1000.);
+    assert_eq!(get_fill_size(&buy, &candle, 0.), 1000.);
+}
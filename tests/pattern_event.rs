@@ -0,0 +1,87 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::status::Status;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::scanner::pattern::*;
+use rs_algo_shared::scanner::pattern_event::*;
+
+// 2023-01-02 10:00:00 UTC
+const START: i64 = 1_672_653_600;
+
+fn pattern(pattern_type: PatternType, first_index: usize, active_at: Option<usize>) -> Pattern {
+    let date = to_dbtime(Local.timestamp(START, 0));
+    Pattern {
+        index: first_index + 4,
+        date,
+        pattern_type,
+        pattern_size: PatternSize::Local,
+        data_points: (first_index..first_index + 5).map(|x| (x, 100.)).collect(),
+        direction: PatternDirection::Top,
+        active: PatternActive {
+            active: active_at.is_some(),
+            completed: false,
+            index: active_at.unwrap_or(0),
+            date,
+            price: 100.,
+            status: Status::Default,
+            break_direction: PatternDirection::Top,
+            target: 110.,
+        },
+        target: 110.,
+    }
+}
+
+fn patterns(local_patterns: Vec<Pattern>) -> Patterns {
+    Patterns {
+        local_patterns,
+        extrema_patterns: vec![],
+    }
+}
+
+#[test]
+fn detected_confirmed_and_invalidated() {
+    let time_frame = TimeFrameType::H1;
+    let previous = patterns(vec![
+        pattern(PatternType::Rectangle, 0, None),
+        pattern(PatternType::DoubleTop, 10, None),
+    ]);
+    let current = patterns(vec![
+        pattern(PatternType::Rectangle, 0, Some(12)),
+        pattern(PatternType::ChannelUp, 20, None),
+    ]);
+
+    let events = pattern_events("EURUSD", &time_frame, &previous, &current);
+    let summary: Vec<(PatternEventType, PatternType, usize)> = events
+        .iter()
+        .map(|event| (event.event_type, event.pattern_type.clone(), event.index))
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            (PatternEventType::Confirmed, PatternType::Rectangle, 12),
+            (PatternEventType::Detected, PatternType::ChannelUp, 24),
+            (PatternEventType::Invalidated, PatternType::DoubleTop, 14),
+        ]
+    );
+    assert_eq!(events[0].symbol, "EURUSD");
+    assert!(pattern_events("EURUSD", &time_frame, &current, &current).is_empty());
+}
+
+#[test]
+fn subscribers_receive_events() {
+    let mut subscribers = PatternSubscribers::new();
+    let receiver = subscribers.subscribe();
+    let closed = subscribers.subscribe();
+    drop(closed);
+
+    let events = pattern_events(
+        "EURUSD",
+        &TimeFrameType::H1,
+        &patterns(vec![]),
+        &patterns(vec![pattern(PatternType::Rectangle, 0, None)]),
+    );
+    subscribers.send(&events);
+
+    assert_eq!(receiver.try_recv().unwrap(), events[0]);
+    assert!(!subscribers.is_empty());
+}
@@ -0,0 +1,132 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::helpers::maxima_minima::{fractals_scaled, peak_prominence};
+use rs_algo_shared::helpers::regression::{kernel_regression, KernelRegression};
+use rs_algo_shared::scanner::candle::Candle;
+use rs_algo_shared::scanner::pattern::{evict_points, lookback_from, lookback_points};
+use rs_algo_shared::scanner::peak::{Peaks, PeaksMethod, SwingKind};
+
+#[test]
+fn evicted_peaks_keep_indexes_aligned() {
+    let mut peaks = Peaks::new().logarithmic(false);
+    peaks.highs = vec![1.2, 1.5, 1.3, 1.6, 1.4];
+    peaks.lows = vec![1.0, 1.1, 1.0, 1.2, 1.1];
+    peaks.close = vec![1.1, 1.4, 1.2, 1.5, 1.3];
+    peaks.local_maxima = vec![(1, 1.5), (3, 1.6)];
+    peaks.local_minima = vec![(0, 1.0), (2, 1.0)];
+
+    peaks.evict(2);
+
+    assert_eq!(peaks.highs(), &vec![1.3, 1.6, 1.4]);
+    assert_eq!(peaks.local_maxima(), &vec![(1, 1.6)]);
+    assert_eq!(peaks.local_minima(), &vec![(0, 1.0)]);
+    assert_eq!(peaks.highs()[peaks.local_maxima()[0].0], 1.6);
+}
+
+#[test]
+fn evict_points_drops_and_shifts() {
+    let mut points = vec![(0, 1.), (4, 2.), (9, 3.)];
+    evict_points(&mut points, 5);
+    assert_eq!(points, vec![(4, 3.)]);
+}
+
+#[test]
+fn lookback_keeps_whole_data_indexes() {
+    let points = vec![(0, 1.), (4, 2.), (9, 3.)];
+    assert_eq!(lookback_from(10, None), 0);
+    assert_eq!(lookback_from(10, Some(20)), 0);
+
+    let from = lookback_from(10, Some(6));
+    assert_eq!(from, 4);
+    assert_eq!(lookback_points(&points, from), vec![(4, 2.), (9, 3.)]);
+}
+
+#[test]
+fn kernel_sums_match_full_regression() {
+    std::env::set_var("LOGARITHMIC_SCANNER", "false");
+    let bandwidth = 0.8;
+    let mut values = vec![10., 11.5, 10.8, 12.2, 11.1, 12.9];
+    let mut kernel = KernelRegression::new(bandwidth, false);
+    for value in &values {
+        kernel.push(*value, *value);
+    }
+
+    values[5] = 13.4;
+    kernel.update_last(13.4, 13.4);
+    values.drain(..2);
+    kernel.remove_first(2);
+
+    assert_eq!(kernel.len(), values.len());
+    for (index, value) in values.iter().enumerate() {
+        let expected = kernel_regression(bandwidth, *value, &values);
+        assert!((kernel.value(index) - expected).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn fractal_pivots() {
+    let highs = vec![1., 2., 3., 2., 1., 2., 2.5, 2.5, 1., 0.5, 4.];
+    let lows: Vec<f64> = highs.iter().map(|x| -x).collect();
+
+    let maxima = fractals_scaled(&highs, &highs, 2, false).unwrap();
+    let minima = fractals_scaled(&lows, &highs, 2, false).unwrap();
+
+    // Equal highs at 6 and 7 pivot on the first one, 10 is not confirmed
+    assert_eq!(maxima, vec![(2, 3.), (6, 2.5)]);
+    assert_eq!(minima, vec![(4, 1.)]);
+    assert!(fractals_scaled(&highs[..4].to_vec(), &highs, 2, false)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn swing_points_with_dates_and_prominence() {
+    for (key, val) in [
+        ("CANDLE_TYPES", "false"),
+        ("LOGARITHMIC_SCANNER", "false"),
+        ("LOCAL_MIN_PROMINENCE", "0.01"),
+        ("LOCAL_PROMINENCE_MIN_DISTANCE", "1"),
+        ("EXTREMA_MIN_PROMINENCE", "0.1"),
+        ("EXTREMA_PROMINENCE_MIN_DISTANCE", "1"),
+        ("KERNEL_PRICE_SMOOTHING", "false"),
+        ("KERNEL_REGRESSION_BANDWIDTH", "0.05"),
+        ("PRICE_SOURCE", "close"),
+    ] {
+        std::env::set_var(key, val);
+    }
+
+    let closes = [10., 11., 14., 12., 9., 10., 12., 11., 10.];
+    let mut peaks = Peaks::new().logarithmic(false).method(PeaksMethod::Fractal);
+    for (idx, close) in closes.iter().enumerate() {
+        let candle = Candle::new()
+            .date(Local.timestamp(1_672_653_600 + idx as i64 * 60, 0))
+            .open(*close)
+            .high(*close)
+            .low(*close)
+            .close(*close)
+            .volume(1.)
+            .is_closed(true)
+            .previous_candles(vec![])
+            .logarithmic(false)
+            .build()
+            .unwrap();
+        peaks.next(&candle);
+    }
+    peaks.calculate_peaks(&14., &9., &0).unwrap();
+
+    let swings = peaks.local_swings();
+    assert_eq!(swings.len(), 3);
+    assert_eq!(
+        (swings[0].kind, swings[0].point()),
+        (SwingKind::High, (2, 14.))
+    );
+    assert_eq!(swings[0].prominence, 4.);
+    assert_eq!((swings[1].kind, swings[1].index), (SwingKind::Low, 4));
+    assert_eq!(swings[1].prominence, 3.);
+    assert_eq!(swings[2].prominence, 2.);
+    assert_eq!(
+        swings[2].date,
+        Some(to_dbtime(Local.timestamp(1_672_653_960, 0)))
+    );
+
+    assert_eq!(peak_prominence(&[1., 3., 2., 4.], 1), 1.);
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use rs_algo_shared::backtest::engine::*;
+use rs_algo_shared::error::OrderError;
+use rs_algo_shared::helpers::uuid::IdGenerator;
+use rs_algo_shared::models::backtest_instrument::OpenPositionPolicy;
+use rs_algo_shared::models::commission::CommissionModel;
+use rs_algo_shared::models::order::*;
+use rs_algo_shared::models::position_sizer::*;
+use rs_algo_shared::models::pricing::Pricing;
+use rs_algo_shared::models::risk::Risk;
+use rs_algo_shared::models::stop_loss::StopLossType;
+use rs_algo_shared::models::strategy::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::models::trade::TradeType;
+use rs_algo_shared::scanner::instrument::HTFInstrument;
+
+use common::*;
+
+fn engine(order_size: f64) -> BackTestEngine {
+    BackTestEngine::new(
+        BackTestConfig::from_env()
+            .equity(10_000.)
+            .order_size(order_size)
+            .warm_up(0)
+            .commission(CommissionModel::None)
+            .open_positions(OpenPositionPolicy::Liquidate),
+    )
+}
+
+#[test]
+fn fixed_and_fractional_sizes() {
+    let instrument = instrument(&[100.; 5]);
+    let size = |sizer: &dyn PositionSizer, stop_price: Option<f64>| {
+        Sizing::new(sizer, 10_000.).size(2, &instrument, 100., stop_price)
+    };
+
+    assert_eq!(size(&FixedSize(1_000.), None), Some(1_000.));
+    assert_eq!(size(&FixedLots(3.), None), Some(300.));
+
+    // 1% of the equity is 100, 50 units with a 2 points stop
+    assert_eq!(size(&FixedFractional(1.), Some(98.)), Some(5_000.));
+    assert_eq!(size(&FixedFractional(1.), None), Some(100.));
+    assert_eq!(size(&FixedSize(0.), None), None);
+}
+
+#[test]
+fn risk_sizes_from_the_sizing_equity() {
+    let instrument = instrument(&[100.; 5]);
+    let size = |sizer: &dyn PositionSizer, stop_price: Option<f64>| {
+        Sizing::new(sizer, 10_000.).size(2, &instrument, 100., stop_price)
+    };
+
+    // 1% of the equity is 100, 50 units with a 2 points stop
+    assert_eq!(size(&Risk::Percentage(1.), Some(98.)), Some(5_000.));
+    assert_eq!(size(&Risk::Amount(50.), Some(98.)), Some(2_500.));
+    assert_eq!(size(&Risk::Percentage(1.), None), None);
+    assert_eq!(size(&Risk::None, Some(98.)), None);
+    assert_eq!(
+        size(&RiskPerTrade { order_size: 1_000. }, None),
+        Some(1_000.)
+    );
+}
+
+#[test]
+fn kelly_fraction_is_capped() {
+    let kelly = |win_rate: f64, payoff_ratio: f64| Kelly {
+        win_rate,
+        payoff_ratio,
+        cap: 0.25,
+    };
+
+    assert!((kelly(0.5, 1.5).fraction() - 0.5 / 3.).abs() < 1e-9);
+    assert_eq!(kelly(0.6, 2.).fraction(), 0.25);
+    assert_eq!(kelly(0.4, 1.).fraction(), 0.);
+
+    let instrument = instrument(&[100.; 5]);
+    let sizer = kelly(0.6, 2.);
+    let sizing = Sizing::new(&sizer, 10_000.);
+    assert_eq!(sizing.size(2, &instrument, 100., None), Some(2_500.));
+    assert_eq!(sizing.size(2, &instrument, 100., Some(95.)), Some(50_000.));
+
+    let sizer = kelly(0.4, 1.);
+    assert_eq!(
+        Sizing::new(&sizer, 10_000.).size(2, &instrument, 100., None),
+        None
+    );
+}
+
+#[test]
+fn volatility_targets_the_atr() {
+    let instrument = instrument(&[100., 100., 101., 101., 101.]);
+    assert_eq!(average_true_range(&instrument, 2, 2), Some(1.25));
+    assert_eq!(average_true_range(&instrument, 4, 2), Some(1.));
+    assert_eq!(average_true_range(&instrument, 2, 3), None);
+
+    // 1% of the equity over a 2 ATR stop
+    let sizer = VolatilityTarget {
+        risk_per: 1.,
+        atr_period: 2,
+        atr_multiple: 2.,
+    };
+    let sizing = Sizing::new(&sizer, 10_000.);
+    assert_eq!(sizing.size(4, &instrument, 100., None), Some(5_000.));
+    assert_eq!(sizing.size(1, &instrument, 100., None), None);
+}
+
+#[test]
+fn engine_sizes_entries_with_the_sizer() {
+    set_env();
+    let instrument = instrument(&[100.; 6]);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let strategy = AlwaysIn {
+        strategy_type: StrategyType::OnlyLong,
+        time_frame: TimeFrameType::H1,
+    };
+
+    let fixed = engine(500.).run(&strategy, &instrument, &HTFInstrument::None, &pricing);
+    assert_eq!(fixed.trades_in[0].quantity, 5.);
+
+    let fractional = engine(500.).sizer(Box::new(FixedFractional(10.))).run(
+        &strategy,
+        &instrument,
+        &HTFInstrument::None,
+        &pricing,
+    );
+    assert_eq!(fractional.trades_in[0].quantity, 10.);
+
+    // No ATR before the third bar, the first entry waits for it
+    let volatility = engine(500.)
+        .sizer(Box::new(VolatilityTarget {
+            risk_per: 1.,
+            atr_period: 3,
+            atr_multiple: 1.,
+        }))
+        .run(&strategy, &instrument, &HTFInstrument::None, &pricing);
+    assert_eq!(volatility.trades_in[0].index_in, 3);
+    assert_eq!(volatility.trades_in[0].quantity, 100.);
+}
+
+#[test]
+fn unsized_entries_are_an_error() {
+    set_env();
+    std::env::set_var("ORDER_WITH_SPREAD", "false");
+    let instrument = instrument(&[100.; 5]);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let order_types = vec![
+        OrderType::BuyOrderLong(OrderDirection::Up, 0., 101.),
+        OrderType::StopLossLong(OrderDirection::Down, StopLossType::Price(99.)),
+    ];
+    let prepare = |sizer: &dyn PositionSizer| {
+        prepare_orders(
+            2,
+            Sizing::new(sizer, 10_000.),
+            &instrument,
+            &pricing,
+            &TradeType::OrderInLong,
+            &order_types,
+            &mut IdGenerator::default(),
+        )
+    };
+
+    // 1% of the equity over the 2 points stop, the stop gets the entry size
+    let orders = prepare(&Risk::Percentage(1.)).unwrap();
+    assert_eq!(orders.len(), 2);
+    assert!(orders.iter().all(|order| order.size() == 5_050.));
+
+    assert!(matches!(
+        prepare(&FixedSize(0.)),
+        Err(OrderError::Unsized { .. })
+    ));
+}
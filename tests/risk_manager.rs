@@ -0,0 +1,134 @@
+mod common;
+
+use rs_algo_shared::backtest::engine::*;
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::backtest_instrument::OpenPositionPolicy;
+use rs_algo_shared::models::commission::CommissionModel;
+use rs_algo_shared::models::equity::Equity;
+use rs_algo_shared::models::exposure::Exposure;
+use rs_algo_shared::models::pricing::Pricing;
+use rs_algo_shared::models::risk::*;
+use rs_algo_shared::models::strategy::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::scanner::instrument::HTFInstrument;
+
+use common::*;
+
+const DAY: i64 = 86_400;
+
+fn date(seconds: i64) -> DateTime<Local> {
+    Local.timestamp(START + seconds, 0)
+}
+
+fn exposure(symbol: &str, open_trades: usize, notional: f64) -> Exposure {
+    Exposure {
+        symbol: symbol.to_owned(),
+        open_trades,
+        notional,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn daily_and_weekly_losses() {
+    let limits = RiskLimits::default()
+        .max_daily_loss(100.)
+        .max_weekly_loss(150.);
+    let mut risk = RiskManager::new(limits);
+    let mut equity = Equity::new(10_000.);
+
+    risk.update(date(0), &equity);
+    equity.realize(-100.);
+    assert!(!risk.check_entry(date(3600), "EURUSD", &equity, &[], 10.));
+    assert!(matches!(
+        risk.events()[0].breach,
+        RiskBreach::DailyLoss { loss, .. } if loss == 100.
+    ));
+
+    // A new day resets the daily loss but not the weekly one
+    risk.update(date(DAY), &equity);
+    assert!(risk.check_entry(date(DAY), "EURUSD", &equity, &[], 10.));
+    equity.realize(-60.);
+    assert!(!risk.check_entry(date(DAY), "EURUSD", &equity, &[], 10.));
+    assert!(matches!(
+        risk.events()[1].breach,
+        RiskBreach::WeeklyLoss { .. }
+    ));
+
+    // Next monday
+    risk.update(date(7 * DAY), &equity);
+    assert!(risk.check_entry(date(7 * DAY), "EURUSD", &equity, &[], 10.));
+    assert_eq!(risk.take_events().len(), 2);
+    assert!(risk.events().is_empty());
+}
+
+#[test]
+fn open_trades_and_exposures() {
+    let limits = RiskLimits::default()
+        .max_open_trades(2)
+        .max_symbol_exposure(1_500.)
+        .max_sector_exposure(2_200.)
+        .sector("EURUSD", "forex")
+        .sector("GBPUSD", "forex");
+    let mut risk = RiskManager::new(limits);
+    let equity = Equity::new(10_000.);
+
+    let exposures = vec![exposure("EURUSD", 1, 1_000.), exposure("AAPL", 1, 1_000.)];
+    assert!(!risk.check_entry(date(0), "GBPUSD", &equity, &exposures, 100.));
+    assert!(matches!(
+        risk.events()[0].breach,
+        RiskBreach::OpenTrades {
+            open_trades: 2,
+            limit: 2
+        }
+    ));
+
+    let exposures = vec![exposure("EURUSD", 1, 1_000.)];
+    assert!(!risk.check_entry(date(0), "EURUSD", &equity, &exposures, 1_000.));
+    assert!(matches!(
+        risk.events()[1].breach,
+        RiskBreach::SymbolExposure { exposure, .. } if exposure == 2_000.
+    ));
+
+    assert!(risk.check_entry(date(0), "GBPUSD", &equity, &exposures, 1_000.));
+    assert!(!risk.check_entry(date(0), "GBPUSD", &equity, &exposures, 1_300.));
+    assert!(matches!(
+        &risk.events()[2].breach,
+        RiskBreach::SectorExposure { sector, .. } if sector == "forex"
+    ));
+    assert_eq!(risk.events()[2].symbol, "GBPUSD");
+
+    // No sector, no sector limit
+    assert!(risk.check_entry(date(0), "AAPL", &equity, &exposures, 1_500.));
+}
+
+#[test]
+fn drawdown_kill_switch_in_backtests() {
+    set_env();
+    let instrument = instrument(&[100., 99., 98., 97., 96., 95., 94.]);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let strategy = AlwaysIn {
+        strategy_type: StrategyType::OnlyLong,
+        time_frame: TimeFrameType::H1,
+    };
+
+    let engine = BackTestEngine::new(
+        BackTestConfig::from_env()
+            .equity(10_000.)
+            .order_size(1_000.)
+            .warm_up(0)
+            .commission(CommissionModel::None)
+            .open_positions(OpenPositionPolicy::Liquidate)
+            .risk(RiskLimits::default().max_drawdown_per(0.05)),
+    );
+    let result = engine.run(&strategy, &instrument, &HTFInstrument::None, &pricing);
+
+    assert_eq!(result.trades_in.len(), 1);
+    assert_eq!(result.trades_out.len(), 1);
+    assert_eq!(result.rejected_entries, 4);
+    assert_eq!(result.risk_events.len(), 4);
+    assert!(matches!(
+        result.risk_events[0].breach,
+        RiskBreach::Drawdown { .. }
+    ));
+}
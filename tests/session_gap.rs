@@ -0,0 +1,125 @@
+mod common;
+
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::indicators::params::IndicatorsParams;
+use rs_algo_shared::models::market::Market;
+use rs_algo_shared::models::order::*;
+use rs_algo_shared::models::risk::Risk;
+use rs_algo_shared::models::session::*;
+use rs_algo_shared::models::stop_loss::StopLossType;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::scanner::candle::Candle;
+use rs_algo_shared::scanner::instrument::Instrument;
+
+const START: i64 = 1_672_653_600;
+
+fn candle(seconds: i64, open: f64, close: f64) -> Candle {
+    Candle::new()
+        .date(Local.timestamp(START + seconds, 0))
+        .open(open)
+        .high(open.max(close) + 0.5)
+        .low(open.min(close) - 0.5)
+        .close(close)
+        .volume(100.)
+        .is_closed(true)
+        .previous_candles(vec![])
+        .logarithmic(false)
+        .build()
+        .unwrap()
+}
+
+/// Two hourly bars and a session opening 3 days later 10 points lower
+fn instrument() -> Instrument {
+    let mut instrument = Instrument::new()
+        .symbol("EURUSD")
+        .market(Market::Forex)
+        .time_frame(TimeFrameType::H1)
+        .indicator_params(IndicatorsParams::default())
+        .logarithmic(false)
+        .build()
+        .unwrap();
+
+    instrument.data = vec![
+        candle(0, 100., 100.),
+        candle(3_600, 100., 100.),
+        candle(3 * 86_400, 90., 91.),
+        candle(3 * 86_400 + 3_600, 91., 92.),
+    ];
+    instrument
+}
+
+fn order(order_type: OrderType, target_price: f64) -> Order {
+    Order {
+        id: START as usize,
+        trade_id: 1,
+        index_created: 0,
+        index_fulfilled: 0,
+        size: 1.,
+        order_type,
+        status: OrderStatus::Pending,
+        origin_price: 100.,
+        target_price,
+        created_at: to_dbtime(Local.timestamp(START, 0)),
+        updated_at: None,
+        full_filled_at: None,
+        valid_until: None,
+        ticks_beyond: 0,
+        breakeven: 0.,
+        oco_group: None,
+        expiry: ExpiryPolicy::GTC,
+        filled_size: 0.,
+        tags: vec![],
+        risk: Risk::None,
+        stop_price: 0.,
+        broker_order: None,
+        symbol: "EURUSD".to_owned(),
+        strategy: String::new(),
+    }
+}
+
+#[test]
+fn session_open_gap_is_only_on_the_open_bar() {
+    let instrument = instrument();
+    assert_eq!(session_open_gap(1, &instrument), None);
+    assert_eq!(session_open_gap(3, &instrument), None);
+
+    let gap = session_open_gap(2, &instrument).unwrap();
+    assert_eq!((gap.index, gap.prev_close, gap.open), (2, 100., 90.));
+    assert_eq!(
+        get_session_gap(3, &instrument, 1),
+        Some(SessionGap {
+            bars_since_open: 1,
+            ..gap
+        })
+    );
+    assert_eq!(get_session_gap(3, &instrument, 0), None);
+}
+
+#[test]
+fn gapped_entries_are_cancelled_and_stops_fill_at_the_open() {
+    common::set_env();
+    let instrument = instrument();
+    let gap = session_open_gap(2, &instrument).unwrap();
+
+    let mut orders = vec![
+        order(OrderType::BuyOrderLong(OrderDirection::Down, 1., 95.), 95.),
+        order(
+            OrderType::StopLossLong(OrderDirection::Down, StopLossType::Price(95.)),
+            95.,
+        ),
+        order(
+            OrderType::TakeProfitLong(OrderDirection::Up, 1., 110.),
+            110.,
+        ),
+    ];
+
+    let canceled = cancel_gapped_orders(&instrument, &gap, &mut orders);
+    assert_eq!(canceled.len(), 1);
+    assert!(canceled[0].order_type.is_entry());
+    assert_eq!(canceled[0].status, OrderStatus::Canceled);
+
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[0].target_price, 90.);
+    assert!(orders[0].is_pending());
+    assert_eq!(orders[1].target_price, 110.);
+}
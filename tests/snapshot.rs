@@ -0,0 +1,98 @@
+use rs_algo_shared::scanner::snapshot::*;
+use serde_json::{json, Value};
+
+fn snapshot(rsi: Vec<f64>, maxima: Vec<(usize, f64)>, pattern_points: Vec<(usize, f64)>) -> Value {
+    json!({
+        "indicators": {
+            "rsi": { "data_a": rsi }
+        },
+        "peaks": {
+            "highs": [1.5, 2.5, 3.5],
+            "local_maxima": maxima
+        },
+        "patterns": {
+            "local_patterns": [
+                { "index": 2, "pattern_type": "Rectangle", "data_points": [[0, 1.5], [1, 2.5]] },
+                { "index": 40, "pattern_type": "ChannelUp", "data_points": pattern_points }
+            ],
+            "extrema_patterns": []
+        }
+    })
+}
+
+fn base() -> Value {
+    snapshot(
+        vec![50.5, 60.5, 70.5],
+        vec![(12, 1.5), (30, 2.5)],
+        vec![(35, 1.5), (38, 2.5)],
+    )
+}
+
+#[test]
+fn identical_snapshots_have_no_diffs() {
+    assert!(diff_values(&base(), &base(), 1e-9).is_empty());
+}
+
+#[test]
+fn indicator_diffs_are_indexed_by_bar() {
+    let after = snapshot(
+        vec![50.5, 61.5, 70.5],
+        vec![(12, 1.5), (30, 2.5)],
+        vec![(35, 1.5), (38, 2.5)],
+    );
+
+    let diffs = diff_values(&base(), &after, 1e-9);
+
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].section, "indicators");
+    assert_eq!(diffs[0].path, "indicators.rsi.data_a[1]");
+    assert_eq!(diffs[0].index, Some(1));
+    assert!(diff_values(&base(), &after, 2.).is_empty());
+}
+
+#[test]
+fn peak_diffs_are_indexed_by_the_peak_candle() {
+    let after = snapshot(
+        vec![50.5, 60.5, 70.5],
+        vec![(12, 1.5), (30, 2.75)],
+        vec![(35, 1.5), (38, 2.5)],
+    );
+
+    let diffs = diff_values(&base(), &after, 1e-9);
+
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].path, "peaks.local_maxima[1][1]");
+    assert_eq!(diffs[0].index, Some(30));
+}
+
+#[test]
+fn pattern_diffs_are_indexed_by_the_data_point_candle() {
+    let after = snapshot(
+        vec![50.5, 60.5, 70.5],
+        vec![(12, 1.5), (30, 2.5)],
+        vec![(35, 1.5), (39, 2.5)],
+    );
+
+    let diffs = diff_values(&base(), &after, 1e-9);
+
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(
+        diffs[0].path,
+        "patterns.local_patterns[1].data_points[1][0]"
+    );
+    assert_eq!(diffs[0].index, Some(38));
+}
+
+#[test]
+fn pattern_field_diffs_are_indexed_by_the_pattern() {
+    let mut after = base();
+    after["patterns"]["local_patterns"][1]["pattern_type"] = json!("ChannelDown");
+
+    let diffs = diff_values(&base(), &after, 1e-9);
+    let grouped = group_by_index(&diffs);
+
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].index, Some(40));
+    assert_eq!(grouped.keys().collect::<Vec<_>>(), vec![&40]);
+    assert_eq!(summarize(&diffs).get("patterns"), Some(&1));
+}
@@ -0,0 +1,133 @@
+use rs_algo_shared::error::Result;
+use rs_algo_shared::indicators::params::IndicatorsParams;
+use rs_algo_shared::models::market::Market;
+use rs_algo_shared::models::pricing::Pricing;
+use rs_algo_shared::models::strategy::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::models::trade::Position;
+use rs_algo_shared::scanner::instrument::{HTFInstrument, Instrument};
+
+#[derive(Clone)]
+struct Crossover {
+    name: String,
+    strategy_type: StrategyType,
+    time_frame: TimeFrameType,
+    higher_time_frame: Option<TimeFrameType>,
+}
+
+impl Crossover {
+    fn boxed(config: &StrategyConfig) -> Result<BoxedStrategy> {
+        Ok(Box::new(Self {
+            name: config.name.clone(),
+            strategy_type: config.strategy_type.clone(),
+            time_frame: config.time_frame.clone(),
+            higher_time_frame: config.higher_time_frame.clone(),
+        }))
+    }
+}
+
+impl Strategy for Crossover {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn strategy_type(&self) -> &StrategyType {
+        &self.strategy_type
+    }
+
+    fn time_frame(&self) -> &TimeFrameType {
+        &self.time_frame
+    }
+
+    fn higher_time_frame(&self) -> Option<&TimeFrameType> {
+        self.higher_time_frame.as_ref()
+    }
+
+    fn entry_long(
+        &self,
+        _index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        Position::MarketIn(None)
+    }
+
+    fn exit_long(
+        &self,
+        _index: usize,
+        _instrument: &Instrument,
+        _htf_instrument: &HTFInstrument,
+        _pricing: &Pricing,
+    ) -> Position {
+        Position::MarketOut(None)
+    }
+}
+
+fn instrument() -> Instrument {
+    std::env::set_var("MIN_PRICE", "0");
+    Instrument::new()
+        .symbol("EURUSD")
+        .market(Market::Forex)
+        .time_frame(TimeFrameType::H1)
+        .indicator_params(IndicatorsParams::default())
+        .logarithmic(false)
+        .build()
+        .unwrap()
+}
+
+fn pricing() -> Pricing {
+    Pricing::new("EURUSD".to_owned(), 1.1, 1.1, 0., 0.0001, 0.)
+}
+
+#[test]
+fn registry_creates_strategies_by_name() {
+    register_strategy("Crossover", Crossover::boxed);
+    assert!(registered_strategies().contains(&"crossover".to_owned()));
+
+    let config = StrategyConfig::new("crossover", StrategyType::LongShortMTF, TimeFrameType::H1)
+        .higher_time_frame(TimeFrameType::H4);
+    let strategy = create_strategy(&config).unwrap();
+    assert_eq!(strategy.name(), "crossover");
+    assert_eq!(
+        strategy.time_frames(),
+        vec![TimeFrameType::H1, TimeFrameType::H4]
+    );
+
+    let missing = StrategyConfig::new("missing", StrategyType::OnlyLong, TimeFrameType::H1);
+    assert!(create_strategy(&missing).is_err());
+}
+
+#[test]
+fn signals_follow_the_strategy_type() {
+    let instrument = instrument();
+    let pricing = pricing();
+    let config = StrategyConfig::new("crossover", StrategyType::OnlyLong, TimeFrameType::H1);
+    let strategy = Crossover::boxed(&config).unwrap();
+
+    let signal = |signal| strategy.signal(signal, 0, &instrument, &HTFInstrument::None, &pricing);
+    assert!(matches!(
+        signal(Signal::EntryLong),
+        Position::MarketIn(None)
+    ));
+    assert!(matches!(
+        signal(Signal::ExitLong),
+        Position::MarketOut(None)
+    ));
+    assert!(matches!(signal(Signal::EntryShort), Position::None));
+
+    let config = StrategyConfig::new("crossover", StrategyType::OnlyShort, TimeFrameType::H1);
+    let strategy = Crossover::boxed(&config).unwrap();
+    assert!(matches!(
+        strategy.signal(
+            Signal::EntryLong,
+            0,
+            &instrument,
+            &HTFInstrument::None,
+            &pricing
+        ),
+        Position::None
+    ));
+    assert!(Signal::EntryShort.is_entry());
+    assert!(!Signal::ExitShort.is_long());
+}
@@ -0,0 +1,222 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::time_frame::*;
+
+// 2023-01-02 10:00:00 UTC
+const START: i64 = 1_672_653_600;
+
+fn m1_bars(from: i64, num: i64) -> Vec<(DateTime<Local>, f64, f64, f64, f64, f64)> {
+    (from..from + num)
+        .map(|i| {
+            let price = i as f64;
+            (
+                Local.timestamp(START + i * 60, 0),
+                price,
+                price + 2.,
+                price - 1.,
+                price + 1.,
+                1.,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn m1_to_h1_closes_on_boundary() {
+    let mut aggregator = TimeFrameAggregator::new(TimeFrameType::H1);
+    let mut closed = vec![];
+
+    for bar in m1_bars(0, 60) {
+        closed.extend(aggregator.next(bar));
+    }
+
+    assert_eq!(
+        closed,
+        vec![(Local.timestamp(START, 0), 0., 61., -1., 60., 60.)]
+    );
+    assert_eq!(aggregator.current(), None);
+}
+
+#[test]
+fn m1_to_h1_aggregate() {
+    let bars = TimeFrameAggregator::aggregate(&TimeFrameType::H1, &m1_bars(0, 150));
+
+    assert_eq!(
+        bars,
+        vec![
+            (Local.timestamp(START, 0), 0., 61., -1., 60., 60., true),
+            (
+                Local.timestamp(START + 3600, 0),
+                60.,
+                121.,
+                59.,
+                120.,
+                60.,
+                true
+            ),
+            (
+                Local.timestamp(START + 7200, 0),
+                120.,
+                151.,
+                119.,
+                150.,
+                30.,
+                false
+            ),
+        ]
+    );
+}
+
+#[test]
+fn m1_to_h1_closes_on_next_period() {
+    let mut aggregator = TimeFrameAggregator::new(TimeFrameType::H1);
+    let mut data = m1_bars(0, 30);
+    data.extend(m1_bars(65, 1));
+
+    let mut closed = vec![];
+    for bar in data {
+        closed.extend(aggregator.next(bar));
+    }
+
+    assert_eq!(
+        closed,
+        vec![(Local.timestamp(START, 0), 0., 31., -1., 30., 30.)]
+    );
+    assert_eq!(
+        aggregator.current(),
+        Some((Local.timestamp(START + 3600, 0), 65., 67., 64., 66., 1.))
+    );
+}
+
+#[test]
+fn period_start_of_h4() {
+    let date = Local.timestamp(START + 3 * 3600 + 120, 0);
+
+    assert_eq!(
+        period_start(&date, &TimeFrameType::H4),
+        Local.timestamp(START + 2 * 3600, 0)
+    );
+}
+
+#[test]
+fn custom_time_frames() {
+    assert_eq!(TimeFrameType::from_str("M10"), TimeFrameType::Custom(10));
+    assert_eq!(TimeFrameType::from_str("H2"), TimeFrameType::Custom(120));
+    assert_eq!(TimeFrameType::from_str("H4"), TimeFrameType::H4);
+    assert_eq!(TimeFrameType::from_str("X2"), TimeFrameType::ERR);
+    assert_eq!(TimeFrameType::from_number(120).to_string(), "H2");
+    assert_eq!(TimeFrameType::from_number(10).to_string(), "M10");
+    assert_eq!(
+        TimeFrameType::Custom(120).broker_time_frame(),
+        TimeFrameType::H1
+    );
+    assert_eq!(
+        TimeFrameType::Custom(10).broker_time_frame(),
+        TimeFrameType::M5
+    );
+}
+
+#[test]
+fn m1_to_m10_aggregate() {
+    let bars = TimeFrameAggregator::aggregate(&TimeFrameType::Custom(10), &m1_bars(0, 20));
+
+    assert_eq!(
+        bars,
+        vec![
+            (Local.timestamp(START, 0), 0., 11., -1., 10., 10., true),
+            (
+                Local.timestamp(START + 600, 0),
+                10.,
+                21.,
+                9.,
+                20.,
+                10.,
+                true
+            ),
+        ]
+    );
+}
+
+fn daily_bars(from: DateTime<Local>, num: i64) -> Vec<(DateTime<Local>, f64, f64, f64, f64, f64)> {
+    (0..num)
+        .map(|i| {
+            let price = i as f64;
+            (
+                (from + Duration::days(i)).date().and_hms(0, 0, 0),
+                price,
+                price + 2.,
+                price - 1.,
+                price + 1.,
+                1.,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn daily_to_weekly_and_monthly() {
+    // Sunday 2023-01-01 to Tuesday 2023-02-14
+    let data = daily_bars(Local.ymd(2023, 1, 1).and_hms(0, 0, 0), 45);
+
+    let weeks = TimeFrameAggregator::with_base(TimeFrameType::W, TimeFrameType::D)
+        .week_start(WeekStart::Monday)
+        .aggregate_series(&data);
+    assert_eq!(weeks.len(), 8);
+    assert_eq!(
+        weeks[0],
+        (
+            Local.ymd(2022, 12, 26).and_hms(0, 0, 0),
+            0.,
+            2.,
+            -1.,
+            1.,
+            1.,
+            true
+        )
+    );
+    assert_eq!(weeks[1].0, Local.ymd(2023, 1, 2).and_hms(0, 0, 0));
+    assert_eq!(weeks[1].5, 7.);
+    assert!(!weeks.last().unwrap().6);
+
+    let weeks = TimeFrameAggregator::with_base(TimeFrameType::W, TimeFrameType::D)
+        .week_start(WeekStart::Sunday)
+        .aggregate_series(&data);
+    assert_eq!(weeks.len(), 7);
+    assert_eq!(
+        weeks[0],
+        (
+            Local.ymd(2023, 1, 1).and_hms(0, 0, 0),
+            0.,
+            8.,
+            -1.,
+            7.,
+            7.,
+            true
+        )
+    );
+    assert_eq!(
+        next_close_with(
+            Local.ymd(2023, 1, 4).and_hms(12, 0, 0),
+            &TimeFrameType::W,
+            WeekStart::Sunday
+        ),
+        Local.ymd(2023, 1, 8).and_hms(0, 0, 0)
+    );
+
+    let months = TimeFrameAggregator::aggregate_from(&TimeFrameType::MN, &TimeFrameType::D, &data);
+    assert_eq!(months.len(), 2);
+    assert_eq!(
+        months[0],
+        (
+            Local.ymd(2023, 1, 1).and_hms(0, 0, 0),
+            0.,
+            32.,
+            -1.,
+            31.,
+            31.,
+            true
+        )
+    );
+    assert_eq!(months[1].0, Local.ymd(2023, 2, 1).and_hms(0, 0, 0));
+    assert!(!months[1].6);
+    assert_eq!(TimeFrameType::MN.closing_hours(), vec![0]);
+}
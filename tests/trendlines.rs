@@ -0,0 +1,62 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::patterns::trendlines::*;
+use rs_algo_shared::scanner::candle::Candle;
+
+// 2023-01-02 10:00:00 UTC
+const START: i64 = 1_672_653_600;
+
+fn candle(idx: usize, close: f64) -> Candle {
+    std::env::set_var("CANDLE_TYPES", "false");
+
+    Candle::new()
+        .date(Local.timestamp(START + idx as i64 * 60, 0))
+        .open(close)
+        .high(close + 0.5)
+        .low(close - 0.5)
+        .close(close)
+        .volume(1.)
+        .is_closed(true)
+        .previous_candles(vec![])
+        .logarithmic(false)
+        .build()
+        .unwrap()
+}
+
+// Rising support at 100 + index, minima every 5 bars
+fn setup() -> (Vec<(usize, f64)>, Vec<Candle>) {
+    let minima = vec![(0, 100.), (5, 105.), (10, 110.), (15, 115.)];
+    let candles = (0..=20).map(|idx| candle(idx, 102. + idx as f64)).collect();
+    (minima, candles)
+}
+
+#[test]
+fn support_through_touching_peaks() {
+    let (minima, candles) = setup();
+    let trendlines =
+        Trendlines::with_config(TrendlinesConfig::default()).from_peaks(&[], &minima, &candles);
+
+    let support = trendlines.active()[0];
+    assert_eq!(support.trendline_type, TrendlineType::Support);
+    assert_eq!(support.touches, vec![0, 5, 10, 15]);
+    assert!((support.value_at(20) - 120.).abs() < 1e-9);
+    assert!((trendlines.support_at(20, 122.).unwrap() - 120.).abs() < 1e-9);
+    assert_eq!(trendlines.resistance_at(20, 122.), None);
+}
+
+#[test]
+fn broken_on_close_through_the_line() {
+    let (minima, candles) = setup();
+    let mut trendlines =
+        Trendlines::with_config(TrendlinesConfig::default()).from_peaks(&[], &minima, &candles);
+
+    trendlines.next(&candle(21, 121.5), 21);
+    assert_eq!(trendlines.active().len(), 1);
+
+    trendlines.next(&candle(22, 118.), 22);
+    assert!(trendlines.active().is_empty());
+    assert_eq!(trendlines.broken()[0].broken_at, Some(22));
+
+    trendlines.evict(5);
+    assert_eq!(trendlines.broken()[0].touches, vec![0, 5, 10]);
+    assert_eq!(trendlines.broken()[0].broken_at, Some(17));
+}
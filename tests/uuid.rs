@@ -0,0 +1,67 @@
+mod common;
+
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::helpers::uuid::IdGenerator;
+use rs_algo_shared::models::order::*;
+use rs_algo_shared::models::position_sizer::*;
+use rs_algo_shared::models::pricing::Pricing;
+use rs_algo_shared::models::stop_loss::StopLossType;
+use rs_algo_shared::models::trade::TradeType;
+
+use common::*;
+
+#[test]
+fn monotonic_ids_never_collide() {
+    let date = Local.timestamp_opt(1_672_531_200, 0).unwrap();
+    let mut generator = IdGenerator::Monotonic { last: 0 };
+
+    let ids: Vec<usize> = (0..3).map(|_| generator.next(date)).collect();
+
+    assert_eq!(ids, vec![1_672_531_200, 1_672_531_201, 1_672_531_202]);
+    assert_eq!(IdGenerator::default(), IdGenerator::Monotonic { last: 0 });
+}
+
+#[test]
+fn sequential_ids_are_deterministic() {
+    let date = Local::now();
+    let mut generator = IdGenerator::Sequential { next: 10 };
+    let mut other = generator.clone();
+
+    let ids: Vec<usize> = (0..3).map(|_| generator.next(date)).collect();
+    let other_ids: Vec<usize> = (0..3).map(|_| other.next(date)).collect();
+
+    assert_eq!(ids, vec![10, 11, 12]);
+    assert_eq!(ids, other_ids);
+}
+
+#[test]
+fn orders_of_a_bar_get_their_own_ids() {
+    set_env();
+    std::env::set_var("ORDER_WITH_SPREAD", "false");
+    let instrument = instrument(&[100.; 5]);
+    let pricing = Pricing::new("EURUSD".to_owned(), 100., 100., 0., 0.01, 0.);
+    let order_types = vec![
+        OrderType::BuyOrderLong(OrderDirection::Up, 1_000., 101.),
+        OrderType::StopLossLong(OrderDirection::Down, StopLossType::Price(99.)),
+        OrderType::TakeProfitLong(OrderDirection::Up, 1_000., 105.),
+    ];
+    let mut ids = IdGenerator::Sequential { next: 1 };
+
+    let orders = prepare_orders(
+        2,
+        Sizing::new(&FixedSize(1_000.), 10_000.),
+        &instrument,
+        &pricing,
+        &TradeType::OrderInLong,
+        &order_types,
+        &mut ids,
+    )
+    .unwrap();
+
+    // The trade takes the first id, its orders the next ones
+    assert!(orders.iter().all(|order| order.trade_id == 1));
+    assert_eq!(
+        orders.iter().map(|order| order.id).collect::<Vec<_>>(),
+        vec![2, 3, 4]
+    );
+}
@@ -0,0 +1,68 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::scanner::validate::*;
+
+// 2023-01-02 10:00:00 UTC
+const START: i64 = 1_672_653_600;
+
+fn bar(minute: i64, close: f64, volume: f64) -> (DateTime<Local>, f64, f64, f64, f64, f64) {
+    (
+        Local.timestamp(START + minute * 60, 0),
+        close,
+        close + 1.,
+        close - 1.,
+        close,
+        volume,
+    )
+}
+
+#[test]
+fn reports_every_issue() {
+    let data = vec![
+        bar(0, 10., 1.),
+        bar(1, 11., 1.),
+        bar(1, 12., 1.),
+        bar(0, 13., 1.),
+        bar(5, f64::NAN, 1.),
+    ];
+
+    let report = validate(&data, &TimeFrameType::M1, None);
+
+    assert!(!report.is_valid());
+    assert_eq!(report.duplicates().len(), 1);
+    assert_eq!(report.out_of_order().len(), 1);
+    assert_eq!(report.invalid_prices().len(), 1);
+    assert_eq!(report.gaps().len(), 1);
+    assert_eq!(report.gaps()[0].size, 4);
+}
+
+#[test]
+fn repair_strategies() {
+    let data = vec![
+        bar(0, 10., 1.),
+        bar(1, 11., 1.),
+        bar(1, 12., 2.),
+        bar(3, 0., 1.),
+        bar(4, 14., 1.),
+    ];
+
+    let dropped = repair(&data, &TimeFrameType::M1, None, RepairStrategy::Drop);
+    assert_eq!(
+        dropped,
+        vec![bar(0, 10., 1.), bar(1, 12., 2.), bar(4, 14., 1.)]
+    );
+
+    let merged = repair(&data, &TimeFrameType::M1, None, RepairStrategy::Merge);
+    assert_eq!(
+        merged[1],
+        (Local.timestamp(START + 60, 0), 11., 13., 10., 12., 3.)
+    );
+
+    let filled = repair(&data, &TimeFrameType::M1, None, RepairStrategy::ForwardFill);
+    assert_eq!(filled.len(), 5);
+    assert_eq!(
+        filled[2],
+        (Local.timestamp(START + 120, 0), 12., 12., 12., 12., 0.)
+    );
+    assert!(validate(&filled, &TimeFrameType::M1, None).is_valid());
+}
@@ -0,0 +1,64 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::scanner::candle::Candle;
+use rs_algo_shared::scanner::volume_profile::VolumeProfile;
+
+// 2023-01-02 10:00:00 UTC
+const START: i64 = 1_672_653_600;
+
+fn candle(idx: i64, high: f64, low: f64, volume: f64) -> Candle {
+    std::env::set_var("CANDLE_TYPES", "false");
+
+    Candle::new()
+        .date(Local.timestamp(START + idx * 60, 0))
+        .open(low)
+        .high(high)
+        .low(low)
+        .close(high)
+        .volume(volume)
+        .is_closed(true)
+        .previous_candles(vec![])
+        .logarithmic(false)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn poc_and_value_area() {
+    let data = vec![
+        candle(0, 110., 100., 100.),
+        candle(1, 103.5, 102., 900.),
+        candle(2, 109., 108., 50.),
+    ];
+
+    let profile = VolumeProfile::new()
+        .window(10)
+        .num_buckets(10)
+        .value_area_percentage(70.)
+        .from_candles(&data);
+
+    assert!(profile.poc() > 102. && profile.poc() < 104.);
+    assert!(profile.is_in_value_area(103.));
+    assert!(!profile.is_in_value_area(109.5));
+    assert!((profile.total_volume() - 1050.).abs() < 1e-9);
+}
+
+#[test]
+fn incremental_matches_full_window() {
+    let data: Vec<Candle> = (0..20)
+        .map(|idx| {
+            let base = 100. + (idx % 7) as f64;
+            candle(idx, base + 2., base, 10. + idx as f64)
+        })
+        .collect();
+
+    let mut incremental = VolumeProfile::new()
+        .window(5)
+        .num_buckets(8)
+        .from_candles(&data[..5].to_vec());
+    for candle in &data[5..] {
+        incremental.next(candle);
+    }
+
+    let window_volume: f64 = data[15..].iter().map(|x| x.volume()).sum();
+    assert!((incremental.total_volume() - window_volume).abs() < 1e-6);
+}
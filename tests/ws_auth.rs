@@ -0,0 +1,125 @@
+#![cfg(feature = "websocket")]
+
+use futures_util::{SinkExt, StreamExt};
+use rs_algo_shared::ws::auth::*;
+use rs_algo_shared::ws::encoding::Encoding;
+use rs_algo_shared::ws::envelope::*;
+use rs_algo_shared::ws::message::*;
+use rs_algo_shared::ws::ws_server::{Sessions, WebSocketServer};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::connect_async;
+
+async fn next_response<S>(read: &mut S) -> Envelope<Value>
+where
+    S: StreamExt<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    let msg = read.next().await.unwrap().unwrap();
+    decode_envelope(&Encoding::Json, &msg).unwrap()
+}
+
+fn command<T>(command: CommandType, data: Option<T>) -> Command<T> {
+    Command { command, data }
+}
+
+#[test]
+fn api_keys_accept_known_keys_only() {
+    let keys = ApiKeys::new(&["key"]);
+
+    assert!(keys.authenticate(&Credentials::ApiKey("key".to_owned())));
+    assert!(!keys.authenticate(&Credentials::ApiKey("other".to_owned())));
+    assert!(!keys.authenticate(&Credentials::Token("key".to_owned())));
+}
+
+#[test]
+fn session_tokens_expire_and_revoke() {
+    let tokens = SessionTokens::new(Duration::from_secs(60));
+    let token = tokens.issue();
+
+    assert!(tokens.is_valid(&token));
+    assert!(!tokens.is_valid("other"));
+
+    tokens.revoke(&token);
+    assert!(!tokens.is_valid(&token));
+
+    let tokens = SessionTokens::new(Duration::ZERO);
+    let token = tokens.issue();
+    assert!(!tokens.is_valid(&token));
+}
+
+#[tokio::test]
+async fn commands_require_the_session_token() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = WebSocketServer::new()
+        .authenticator(ApiKeys::new(&["key"]))
+        .session_tokens(SessionTokens::new(Duration::from_secs(60)))
+        .on(
+            CommandType::GetCurrentState,
+            |session_id, _msg: Command<bool>, sessions: Sessions| async move {
+                sessions.send(
+                    &session_id,
+                    &ResponseBody {
+                        response: ResponseType::InitSession,
+                        payload: Some(true),
+                    },
+                )
+            },
+        );
+    tokio::spawn(server.serve(listener));
+
+    let (socket, _) = connect_async(format!("ws://{}", addr)).await.unwrap();
+    let (mut write, mut read) = socket.split();
+    let connected = next_response(&mut read).await;
+    assert_eq!(connected.payload["response"], "Connected");
+
+    let state = command::<bool>(CommandType::GetCurrentState, None);
+    write
+        .send(encode_envelope(&Encoding::Json, &state).unwrap())
+        .await
+        .unwrap();
+    let response = next_response(&mut read).await;
+    assert_eq!(response.payload["response"], "Unauthorized");
+
+    let init = command(
+        CommandType::InitSession,
+        Some(Credentials::ApiKey("other".to_owned())),
+    );
+    write
+        .send(encode_envelope(&Encoding::Json, &init).unwrap())
+        .await
+        .unwrap();
+    let response = next_response(&mut read).await;
+    assert_eq!(response.payload["response"], "Unauthorized");
+
+    let init = command(
+        CommandType::InitSession,
+        Some(Credentials::ApiKey("key".to_owned())),
+    );
+    write
+        .send(encode_envelope(&Encoding::Json, &init).unwrap())
+        .await
+        .unwrap();
+    let response = next_response(&mut read).await;
+    assert_eq!(response.payload["response"], "Authenticated");
+    let session: SessionToken =
+        serde_json::from_value(response.payload["payload"].clone()).unwrap();
+    assert_eq!(session.expires_in, 60);
+
+    write
+        .send(encode_envelope_with_token(&Encoding::Json, &state, Some("other")).unwrap())
+        .await
+        .unwrap();
+    let response = next_response(&mut read).await;
+    assert_eq!(response.payload["response"], "Unauthorized");
+
+    write
+        .send(encode_envelope_with_token(&Encoding::Json, &state, Some(&session.token)).unwrap())
+        .await
+        .unwrap();
+    let response = next_response(&mut read).await;
+    assert_eq!(response.payload["response"], "InitSession");
+    assert_eq!(response.payload["payload"], true);
+}
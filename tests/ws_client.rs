@@ -0,0 +1,51 @@
+#![cfg(all(feature = "broker", feature = "websocket"))]
+
+use rs_algo_shared::ws::message::*;
+use rs_algo_shared::ws::ws_client::WebSocket;
+use rs_algo_shared::ws::ws_server::{Sessions, WebSocketServer};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn reads_without_blocking_and_cancels() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = WebSocketServer::new().on(
+        CommandType::GetInstrumentPricing,
+        |session_id, msg: Command<Symbol>, sessions: Sessions| async move {
+            sessions.send(
+                &session_id,
+                &ResponseBody {
+                    response: ResponseType::GetInstrumentPricing,
+                    payload: msg.data,
+                },
+            )
+        },
+    );
+    tokio::spawn(server.serve(listener));
+
+    let mut client = WebSocket::connect(&format!("ws://{}", addr)).await;
+    let connected: ResponseBody<ConnectedData> = client.recv_typed().await.unwrap();
+    assert!(matches!(connected.response, ResponseType::Connected));
+
+    let cancel = client.cancel_handle();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+    });
+    assert!(client.read().await.is_err());
+
+    client
+        .send_typed(&Command {
+            command: CommandType::GetInstrumentPricing,
+            data: Some(Symbol {
+                symbol: "EURUSD".to_owned(),
+            }),
+        })
+        .await
+        .unwrap();
+
+    let response: ResponseBody<Symbol> = client.recv_typed().await.unwrap();
+    assert_eq!(response.payload.unwrap().symbol, "EURUSD");
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "compression")]
+
+use rs_algo_shared::ws::compression::*;
+use rs_algo_shared::ws::message::*;
+use rs_algo_shared::ws::ws_server::{Sessions, WebSocketServer};
+use rs_algo_shared::ws::ws_stream_client::WebSocket;
+use rs_algo_shared::ws::WebSocketBuilder;
+use tokio::net::TcpListener;
+use tungstenite::http::{HeaderMap, HeaderValue};
+
+#[test]
+fn deflate_round_trip() {
+    let text = Message::text("{\"symbol\":\"EURUSD\"}".repeat(100));
+    let compressed = Compression::Deflate.compress(text.clone()).unwrap();
+    assert!(compressed.is_binary());
+    assert!(compressed.len() < text.len());
+    assert_eq!(Compression::Deflate.decompress(compressed).unwrap(), text);
+
+    let binary = Message::Binary(vec![1, 2, 3]);
+    let compressed = Compression::Deflate.compress(binary.clone()).unwrap();
+    assert_eq!(Compression::Deflate.decompress(compressed).unwrap(), binary);
+
+    let ping = Message::Ping(vec![]);
+    assert_eq!(Compression::Deflate.compress(ping.clone()).unwrap(), ping);
+    assert_eq!(Compression::None.compress(text.clone()).unwrap(), text);
+}
+
+#[test]
+fn negotiates_the_extension() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(Compression::Deflate.negotiate(&headers), Compression::None);
+
+    headers.insert(
+        EXTENSIONS_HEADER,
+        HeaderValue::from_static("permessage-deflate; client_max_window_bits, x-rs-algo-deflate"),
+    );
+    assert_eq!(Compression::from_headers(&headers), Compression::Deflate);
+    assert_eq!(
+        Compression::Deflate.negotiate(&headers),
+        Compression::Deflate
+    );
+    assert_eq!(Compression::None.negotiate(&headers), Compression::None);
+}
+
+#[tokio::test]
+async fn compressed_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = WebSocketServer::new().compression(Compression::Deflate).on(
+        CommandType::GetInstrumentData,
+        |session_id, msg: Command<Symbol>, sessions: Sessions| async move {
+            sessions.send(
+                &session_id,
+                &ResponseBody {
+                    response: ResponseType::GetInstrumentData,
+                    payload: msg.data,
+                },
+            )
+        },
+    );
+    tokio::spawn(server.serve(listener));
+
+    let builder =
+        WebSocketBuilder::new(&format!("ws://{}", addr)).compression(Compression::Deflate);
+    let mut client = WebSocket::connect_with(&builder).await;
+    assert_eq!(client.compression(), Compression::Deflate);
+
+    let connected: ResponseBody<ConnectedData> = client.recv_typed().await.unwrap();
+    assert!(matches!(connected.response, ResponseType::Connected));
+
+    client
+        .send_typed(&Command {
+            command: CommandType::GetInstrumentData,
+            data: Some(Symbol {
+                symbol: "EURUSD".to_owned(),
+            }),
+        })
+        .await
+        .unwrap();
+
+    let response: ResponseBody<Symbol> = client.recv_typed().await.unwrap();
+    assert_eq!(response.payload.unwrap().symbol, "EURUSD");
+}
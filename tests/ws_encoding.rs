@@ -0,0 +1,47 @@
+#![cfg(feature = "websocket")]
+
+use rs_algo_shared::ws::encoding::Encoding;
+use rs_algo_shared::ws::message::*;
+
+fn command() -> Command<Symbol> {
+    Command {
+        command: CommandType::SubscribeStream,
+        data: Some(Symbol {
+            symbol: "EURUSD".to_owned(),
+        }),
+    }
+}
+
+fn round_trip(encoding: Encoding) {
+    let msg = encoding.encode(&command()).unwrap();
+    assert_eq!(msg.is_binary(), encoding.is_binary());
+
+    let decoded: Command<Symbol> = encoding.decode(&msg).unwrap();
+    assert!(matches!(decoded.command, CommandType::SubscribeStream));
+    assert_eq!(decoded.data.unwrap().symbol, "EURUSD");
+}
+
+#[test]
+fn json_round_trip_and_negotiation() {
+    round_trip(Encoding::Json);
+
+    assert_eq!(Encoding::negotiate("other, rs-algo.json"), Encoding::Json);
+    assert_eq!(Encoding::negotiate(""), Encoding::Json);
+    assert_eq!(
+        Encoding::from_subprotocol(&Encoding::Json.subprotocol()),
+        Some(Encoding::Json)
+    );
+}
+
+#[cfg(feature = "binary")]
+#[test]
+fn binary_round_trip_and_negotiation() {
+    round_trip(Encoding::Cbor);
+    round_trip(Encoding::MessagePack);
+
+    assert_eq!(
+        Encoding::negotiate("rs-algo.msgpack, rs-algo.json"),
+        Encoding::MessagePack
+    );
+    assert_eq!(Encoding::from_name("CBOR"), Some(Encoding::Cbor));
+}
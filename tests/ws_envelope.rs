@@ -0,0 +1,72 @@
+#![cfg(feature = "websocket")]
+
+use rs_algo_shared::ws::encoding::Encoding;
+use rs_algo_shared::ws::envelope::*;
+use rs_algo_shared::ws::message::*;
+
+fn command() -> Command<Symbol> {
+    Command {
+        command: CommandType::SubscribeStream,
+        data: Some(Symbol {
+            symbol: "EURUSD".to_owned(),
+        }),
+    }
+}
+
+#[test]
+fn envelope_round_trip() {
+    let msg = encode_envelope(&Encoding::Json, &command()).unwrap();
+    let envelope: Envelope<Command<Symbol>> = decode_envelope(&Encoding::Json, &msg).unwrap();
+
+    assert_eq!(envelope.version, PROTOCOL_VERSION);
+    assert!(matches!(
+        envelope.payload.command,
+        CommandType::SubscribeStream
+    ));
+    assert_eq!(envelope.payload.data.unwrap().symbol, "EURUSD");
+}
+
+#[test]
+fn legacy_bare_payload() {
+    let msg = Encoding::Json.encode(&command()).unwrap();
+    let envelope: Envelope<Command<Symbol>> = decode_envelope(&Encoding::Json, &msg).unwrap();
+
+    assert_eq!(envelope.version, LEGACY_VERSION);
+    assert_eq!(envelope.payload.data.unwrap().symbol, "EURUSD");
+}
+
+#[test]
+fn newer_messages_are_tolerated() {
+    let msg = Message::text(
+        r#"{"version":9,"trace":"abc","payload":{"command":"SubscribeOrderFlow","data":{"symbol":"EURUSD","venue":"x"},"priority":1}}"#,
+    );
+    let envelope: Envelope<Command<Symbol>> = decode_envelope(&Encoding::Json, &msg).unwrap();
+
+    assert_eq!(envelope.version, 9);
+    assert!(matches!(envelope.payload.command, CommandType::Unknown));
+    assert_eq!(envelope.payload.data.unwrap().symbol, "EURUSD");
+}
+
+#[test]
+fn renamed_variants_of_older_versions() {
+    let renames = [Rename {
+        since: 2,
+        from: "SubscribeTicks",
+        to: "SubscribeStream",
+    }];
+
+    let msg = Message::text(r#"{"command":"SubscribeTicks","data":{"symbol":"EURUSD"}}"#);
+    let envelope: Envelope<Command<Symbol>> =
+        decode_envelope_with(&Encoding::Json, &msg, &renames).unwrap();
+    assert!(matches!(
+        envelope.payload.command,
+        CommandType::SubscribeStream
+    ));
+
+    let msg = Message::text(
+        r#"{"version":2,"payload":{"command":"SubscribeTicks","data":{"symbol":"EURUSD"}}}"#,
+    );
+    let envelope: Envelope<Command<Symbol>> =
+        decode_envelope_with(&Encoding::Json, &msg, &renames).unwrap();
+    assert!(matches!(envelope.payload.command, CommandType::Unknown));
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "websocket")]
+
+use rs_algo_shared::ws::heartbeat::*;
+use rs_algo_shared::ws::reconnect::*;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+fn heartbeat() -> Heartbeat {
+    Heartbeat::new(HeartbeatConfig {
+        interval: Duration::from_secs(10),
+        timeout: Duration::from_secs(30),
+    })
+}
+
+#[test]
+fn pings_on_schedule() {
+    let mut heartbeat = heartbeat();
+    let start = Instant::now();
+
+    assert!(!heartbeat.should_ping(start));
+    assert!(heartbeat.should_ping(start + Duration::from_secs(10)));
+
+    heartbeat.ping_sent(start + Duration::from_secs(10));
+    assert!(!heartbeat.should_ping(start + Duration::from_secs(15)));
+    assert_eq!(
+        heartbeat.next_ping(start + Duration::from_secs(15)),
+        Duration::from_secs(5)
+    );
+}
+
+#[test]
+fn detects_lost_connections() {
+    let mut heartbeat = heartbeat();
+    let start = Instant::now();
+
+    assert!(heartbeat.is_alive(start + Duration::from_secs(29)));
+    heartbeat.pong_received(start + Duration::from_secs(20));
+    assert!(heartbeat.is_alive(start + Duration::from_secs(45)));
+    assert!(!heartbeat.is_alive(start + Duration::from_secs(50)));
+
+    let disabled = Heartbeat::new(HeartbeatConfig {
+        interval: Duration::ZERO,
+        timeout: Duration::from_secs(30),
+    });
+    assert!(!disabled.should_ping(start + Duration::from_secs(60)));
+    assert!(disabled.is_alive(start + Duration::from_secs(60)));
+}
+
+#[test]
+fn surfaces_connection_lost() {
+    let states = Arc::new(Mutex::new(vec![]));
+    let mut reconnect = Reconnect::default();
+    let recorded = states.clone();
+    reconnect.on_state_change(move |state| recorded.lock().unwrap().push(*state));
+
+    reconnect.connection_lost();
+    assert_eq!(reconnect.state(), ConnectionState::ConnectionLost);
+    assert_eq!(
+        *states.lock().unwrap(),
+        vec![ConnectionState::ConnectionLost]
+    );
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "websocket")]
+
+use bson::Uuid;
+use rs_algo_shared::models::time_frame::TimeFrameType;
+use rs_algo_shared::ws::hub::*;
+use rs_algo_shared::ws::message::*;
+use rs_algo_shared::ws::ws_server::Sessions;
+
+#[test]
+fn reference_counts_upstream_subscriptions() {
+    let hub = StreamHub::new(Sessions::default());
+    let topic = Topic::new("EURUSD", TimeFrameType::M5);
+    let (first, second) = (Uuid::new(), Uuid::new());
+
+    assert!(hub.subscribe(&first, &topic));
+    assert!(!hub.subscribe(&second, &topic));
+    assert!(!hub.subscribe(&second, &topic));
+    assert_eq!(hub.subscribers(&topic), 2);
+    assert_eq!(topic.to_string(), "EURUSD@M5");
+
+    assert!(!hub.unsubscribe(&first, &topic));
+    assert!(!hub.unsubscribe(&first, &topic));
+    assert!(hub.unsubscribe(&second, &topic));
+    assert!(!hub.is_subscribed(&topic));
+}
+
+#[test]
+fn releases_topics_of_gone_sessions() {
+    let hub = StreamHub::new(Sessions::default());
+    let eurusd = Topic::new("EURUSD", TimeFrameType::M5);
+    let gbpusd = Topic::new("GBPUSD", TimeFrameType::H1);
+    let (first, second) = (Uuid::new(), Uuid::new());
+
+    hub.subscribe(&first, &eurusd);
+    hub.subscribe(&first, &gbpusd);
+    hub.subscribe(&second, &gbpusd);
+
+    assert_eq!(hub.unsubscribe_session(&first), vec![eurusd.clone()]);
+    assert_eq!(hub.topics(), vec![gbpusd.clone()]);
+
+    let msg = ResponseBody {
+        response: ResponseType::SubscribeStream,
+        payload: Some(1.1),
+    };
+    assert_eq!(hub.publish(&gbpusd, &msg), 0);
+
+    assert_eq!(hub.prune(), vec![gbpusd]);
+    assert!(hub.topics().is_empty());
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "websocket")]
+
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::order::*;
+use rs_algo_shared::models::risk::Risk;
+use rs_algo_shared::ws::message::*;
+
+fn order(status: OrderStatus) -> Order {
+    Order {
+        id: 7,
+        trade_id: 3,
+        index_created: 0,
+        index_fulfilled: 0,
+        size: 1.,
+        order_type: OrderType::BuyOrderLong(OrderDirection::Up, 1., 100.),
+        status,
+        origin_price: 100.,
+        target_price: 101.,
+        created_at: to_dbtime(Local.timestamp_opt(1_672_531_200, 0).unwrap()),
+        updated_at: None,
+        full_filled_at: None,
+        valid_until: None,
+        ticks_beyond: 0,
+        breakeven: 0.,
+        oco_group: None,
+        expiry: ExpiryPolicy::GTC,
+        filled_size: 0.,
+        tags: vec![],
+        risk: Risk::None,
+        stop_price: 0.,
+        broker_order: None,
+        symbol: "EURUSD".to_owned(),
+        strategy: String::new(),
+    }
+}
+
+fn options() -> TradeOptions {
+    TradeOptions {
+        non_profitable_out: false,
+    }
+}
+
+#[test]
+fn order_lifecycle_messages() {
+    let msg = MessageType::from_order_event(
+        "EURUSD",
+        options(),
+        OrderEvent::Fulfilled(order(OrderStatus::Fulfilled)),
+    );
+    match msg {
+        Some(MessageType::OrderFulfilled(body)) => {
+            assert!(matches!(body.response, ResponseType::OrderFulfilled));
+            let data = body.payload.unwrap();
+            assert_eq!(data.symbol, "EURUSD");
+            assert_eq!(data.trade_id(), 3);
+            assert_eq!(data.order_id(), 7);
+        }
+        _ => panic!("expected an order fulfilled message"),
+    }
+
+    let msg = MessageType::from_order_event(
+        "EURUSD",
+        options(),
+        OrderEvent::Expired(order(OrderStatus::Canceled)),
+    );
+    assert!(matches!(msg, Some(MessageType::OrderCancelled(_))));
+
+    let msg = MessageType::from_order_event(
+        "EURUSD",
+        options(),
+        OrderEvent::Activated(order(OrderStatus::Pending)),
+    );
+    assert!(msg.is_none());
+}
+
+#[test]
+fn order_amended_round_trip() {
+    let msg = MessageType::OrderAmended(ResponseBody {
+        response: ResponseType::OrderAmended,
+        payload: Some(TradeData::new(
+            "EURUSD",
+            order(OrderStatus::Pending),
+            options(),
+        )),
+    });
+
+    let json = serde_json::to_string(&msg).unwrap();
+    let decoded: MessageType = serde_json::from_str(&json).unwrap();
+    match decoded {
+        MessageType::OrderAmended(body) => {
+            assert_eq!(body.payload.unwrap().data, order(OrderStatus::Pending))
+        }
+        _ => panic!("expected an order amended message"),
+    }
+
+    let command: Command<TradeData<Order>> =
+        serde_json::from_str(r#"{"command":"ExecuteOrder","data":null}"#).unwrap();
+    assert!(matches!(command.command, CommandType::ExecuteOrder));
+}
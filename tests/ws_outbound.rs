@@ -0,0 +1,56 @@
+#![cfg(feature = "websocket")]
+
+use rs_algo_shared::ws::message::Message;
+use rs_algo_shared::ws::outbound::*;
+
+fn queue(capacity: usize, batch_size: usize) -> OutboundQueue {
+    OutboundQueue::new(OutboundConfig {
+        capacity,
+        batch_size,
+    })
+}
+
+#[test]
+fn coalesces_ticks_and_batches() {
+    let mut queue = queue(10, 2);
+
+    assert!(queue.push(Message::text("order")));
+    assert!(queue.push_coalesced("EURUSD", Message::text("1.10")));
+    assert!(queue.push_coalesced("EURUSD", Message::text("1.11")));
+    assert!(queue.push(Message::text("cancel")));
+
+    assert_eq!(queue.len(), 3);
+    assert_eq!(
+        queue.next_batch(),
+        vec![Message::text("order"), Message::text("1.11")]
+    );
+    assert_eq!(queue.next_batch(), vec![Message::text("cancel")]);
+    assert!(queue.next_batch().is_empty());
+
+    let metrics = queue.metrics();
+    assert_eq!(metrics.queued, 3);
+    assert_eq!(metrics.coalesced, 1);
+    assert_eq!(metrics.sent, 3);
+    assert_eq!(metrics.max_depth, 3);
+    assert_eq!(metrics.depth, 0);
+}
+
+#[test]
+fn applies_backpressure_when_full() {
+    let mut queue = queue(2, 10);
+
+    assert!(queue.push_coalesced("EURUSD", Message::text("1.10")));
+    assert!(queue.push(Message::text("order")));
+    assert!(queue.is_full());
+
+    assert!(queue.push(Message::text("cancel")));
+    assert!(!queue.push(Message::text("modify")));
+    assert_eq!(queue.metrics().dropped, 2);
+
+    let batch = queue.next_batch();
+    assert_eq!(batch, vec![Message::text("order"), Message::text("cancel")]);
+
+    queue.requeue(batch);
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.metrics().sent, 0);
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "websocket")]
+
+use rs_algo_shared::ws::reconnect::*;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn exponential_backoff_is_capped() {
+    let policy = ReconnectPolicy {
+        initial_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(1000),
+        multiplier: 2.,
+        max_attempts: None,
+    };
+
+    assert_eq!(policy.delay(1), Duration::from_millis(100));
+    assert_eq!(policy.delay(3), Duration::from_millis(400));
+    assert_eq!(policy.delay(10), Duration::from_millis(1000));
+}
+
+#[test]
+fn state_callbacks_and_subscriptions() {
+    let states = Arc::new(Mutex::new(vec![]));
+    let mut reconnect = Reconnect::new(ReconnectPolicy {
+        max_attempts: Some(2),
+        ..ReconnectPolicy::default()
+    });
+
+    let received = states.clone();
+    reconnect.on_state_change(move |state| received.lock().unwrap().push(*state));
+    reconnect.add_subscription("subscribe EURUSD");
+    reconnect.add_subscription("subscribe EURUSD");
+    reconnect.add_subscription("subscribe GBPUSD");
+    reconnect.remove_subscription("subscribe GBPUSD");
+
+    assert!(reconnect.next_attempt().is_some());
+    reconnect.connected();
+    assert!(reconnect.next_attempt().is_some());
+    assert!(reconnect.next_attempt().is_some());
+    assert!(reconnect.next_attempt().is_none());
+
+    assert_eq!(
+        reconnect.subscriptions(),
+        &vec!["subscribe EURUSD".to_owned()]
+    );
+    assert_eq!(
+        *states.lock().unwrap(),
+        vec![
+            ConnectionState::Reconnecting { attempt: 1 },
+            ConnectionState::Connected,
+            ConnectionState::Reconnecting { attempt: 1 },
+            ConnectionState::Reconnecting { attempt: 2 },
+            ConnectionState::Disconnected,
+        ]
+    );
+}
@@ -0,0 +1,134 @@
+#![cfg(feature = "websocket")]
+
+use futures_util::{SinkExt, StreamExt};
+use rs_algo_shared::models::config::ConfigUpdate;
+use rs_algo_shared::ws::encoding::Encoding;
+use rs_algo_shared::ws::envelope::*;
+use rs_algo_shared::ws::message::*;
+use rs_algo_shared::ws::ws_server::{Sessions, WebSocketServer};
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio_tungstenite::connect_async;
+
+async fn next_response<S>(read: &mut S) -> Envelope<Value>
+where
+    S: StreamExt<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    let msg = read.next().await.unwrap().unwrap();
+    decode_envelope(&Encoding::Json, &msg).unwrap()
+}
+
+#[tokio::test]
+async fn routes_commands_and_broadcasts_to_subscribers() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = WebSocketServer::new().on(
+        CommandType::SubscribeStream,
+        |session_id, msg: Command<Symbol>, sessions: Sessions| async move {
+            sessions.subscribe(&session_id, &msg.data.unwrap().symbol);
+            Ok(())
+        },
+    );
+    let sessions = server.sessions();
+    tokio::spawn(server.serve(listener));
+
+    let (socket, _) = connect_async(format!("ws://{}", addr)).await.unwrap();
+    let (mut write, mut read) = socket.split();
+
+    let connected = next_response(&mut read).await;
+    assert_eq!(connected.version, PROTOCOL_VERSION);
+    assert_eq!(connected.payload["response"], "Connected");
+    assert_eq!(sessions.len(), 1);
+
+    let command = Command {
+        command: CommandType::SubscribeStream,
+        data: Some(Symbol {
+            symbol: "EURUSD".to_owned(),
+        }),
+    };
+    write
+        .send(encode_envelope(&Encoding::Json, &command).unwrap())
+        .await
+        .unwrap();
+
+    while sessions.subscribers("EURUSD").is_empty() {
+        tokio::task::yield_now().await;
+    }
+
+    let sent = sessions.broadcast(
+        "EURUSD",
+        &ResponseBody {
+            response: ResponseType::SubscribeStream,
+            payload: Some(1.1),
+        },
+    );
+    assert_eq!(sent, 1);
+    assert_eq!(
+        sessions.broadcast(
+            "GBPUSD",
+            &ResponseBody::<f64> {
+                response: ResponseType::SubscribeStream,
+                payload: None,
+            }
+        ),
+        0
+    );
+
+    let response = next_response(&mut read).await;
+    assert_eq!(response.payload["response"], "SubscribeStream");
+    assert_eq!(response.payload["payload"], 1.1);
+}
+
+#[tokio::test]
+async fn unhandled_commands_get_an_error() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(WebSocketServer::new().serve(listener));
+
+    let (socket, _) = connect_async(format!("ws://{}", addr)).await.unwrap();
+    let (mut write, mut read) = socket.split();
+    next_response(&mut read).await;
+
+    write
+        .send(Message::text(r#"{"command":"GetMarketHours","data":null}"#))
+        .await
+        .unwrap();
+
+    let response = next_response(&mut read).await;
+    assert_eq!(response.payload["response"], "Error");
+}
+
+#[tokio::test]
+async fn config_updates_are_applied_and_audited() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(WebSocketServer::new().config_updates().serve(listener));
+
+    let (socket, _) = connect_async(format!("ws://{}", addr)).await.unwrap();
+    let (mut write, mut read) = socket.split();
+    next_response(&mut read).await;
+
+    write
+        .send(Message::text(
+            r#"{"command":"UpdateConfig","data":{"max_spread":2.5}}"#,
+        ))
+        .await
+        .unwrap();
+
+    let response = next_response(&mut read).await;
+    assert_eq!(response.payload["response"], "ConfigUpdated");
+    assert_eq!(response.payload["payload"]["current"]["max_spread"], 2.5);
+    assert_eq!(ConfigUpdate::current().max_spread, Some(2.5));
+
+    write
+        .send(Message::text(
+            r#"{"command":"UpdateConfig","data":{"risk_per_trade":150.0}}"#,
+        ))
+        .await
+        .unwrap();
+
+    let response = next_response(&mut read).await;
+    assert_eq!(response.payload["response"], "Error");
+    assert_eq!(ConfigUpdate::current().risk_per_trade, None);
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "websocket")]
+
+use rs_algo_shared::ws::message::*;
+use rs_algo_shared::ws::ws_server::{Sessions, WebSocketServer};
+use rs_algo_shared::ws::ws_stream_client::WebSocket;
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn typed_commands_and_responses() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = WebSocketServer::new().on(
+        CommandType::GetInstrumentPricing,
+        |session_id, msg: Command<Symbol>, sessions: Sessions| async move {
+            sessions.send(
+                &session_id,
+                &ResponseBody {
+                    response: ResponseType::GetInstrumentPricing,
+                    payload: msg.data,
+                },
+            )
+        },
+    );
+    tokio::spawn(server.serve(listener));
+
+    let mut client = WebSocket::connect(&format!("ws://{}", addr)).await;
+    let connected: ResponseBody<ConnectedData> = client.recv_typed().await.unwrap();
+    assert!(matches!(connected.response, ResponseType::Connected));
+
+    client
+        .send_typed(&Command {
+            command: CommandType::GetInstrumentPricing,
+            data: Some(Symbol {
+                symbol: "EURUSD".to_owned(),
+            }),
+        })
+        .await
+        .unwrap();
+
+    let response: ResponseBody<Symbol> = client.recv_typed().await.unwrap();
+    assert!(matches!(
+        response.response,
+        ResponseType::GetInstrumentPricing
+    ));
+    assert_eq!(response.payload.unwrap().symbol, "EURUSD");
+
+    client
+        .send_typed(&Command::<Symbol> {
+            command: CommandType::GetCalendar,
+            data: None,
+        })
+        .await
+        .unwrap();
+    let error: ResponseBody<bool> = client.recv_typed().await.unwrap();
+    assert!(matches!(error.response, ResponseType::Error));
+}
@@ -0,0 +1,152 @@
+#![cfg(feature = "broker")]
+
+mod mock_xtb;
+
+use futures_util::StreamExt;
+use mock_xtb::{MockXtbServer, SESSION_ID};
+use rs_algo_shared::broker::xtb_stream::Xtb;
+use rs_algo_shared::broker::{BrokerEvent, BrokerStream, Message};
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::models::market::{MarketHour, MarketHours};
+
+async fn connect() -> Xtb {
+    MockXtbServer::shared();
+    let mut xtb = Xtb::new().await;
+    xtb.login("user", "password").await.unwrap();
+    xtb
+}
+
+async fn next_stream_event(xtb: &mut Xtb) -> Option<BrokerEvent> {
+    let msg = xtb.get_stream().await.next().await.unwrap().unwrap();
+    Xtb::parse_stream_event(msg, xtb.symbol_mapper(), xtb.volume_normalizer())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn login_stores_stream_session() {
+    let mut xtb = connect().await;
+    assert_eq!(xtb.get_session_id(), SESSION_ID);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn instrument_data_is_parsed() {
+    let mut xtb = connect().await;
+    let res = xtb.get_instrument_data("EURUSD", 1, 0).await.unwrap();
+    let data = res.payload.unwrap().data;
+
+    assert_eq!(data.len(), 2);
+    let (_date, open, high, low, close, _volume) = data[0];
+    assert!((open - 1.1).abs() < 1e-9);
+    assert!((high - 1.1002).abs() < 1e-9);
+    assert!((low - 1.0999).abs() < 1e-9);
+    assert!((close - 1.10005).abs() < 1e-9);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn instrument_pricing_is_parsed() {
+    let mut xtb = connect().await;
+    let pricing = xtb
+        .get_instrument_pricing("EURUSD")
+        .await
+        .unwrap()
+        .payload
+        .unwrap();
+
+    assert!((pricing.ask() - 1.1002).abs() < 1e-9);
+    assert!((pricing.bid() - 1.1).abs() < 1e-9);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn symbol_availability_is_parsed() {
+    let mut xtb = connect().await;
+    let availability = xtb
+        .get_symbol_availability("EURUSD")
+        .await
+        .unwrap()
+        .payload
+        .unwrap();
+
+    assert!(availability.trading_enabled);
+    assert!(availability.can_short());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn streamed_candles_and_ticks_are_parsed() {
+    let mut xtb = connect().await;
+
+    xtb.subscribe_stream("EURUSD").await.unwrap();
+    assert!(matches!(
+        next_stream_event(&mut xtb).await,
+        Some(BrokerEvent::KeepAlive)
+    ));
+    assert!(matches!(
+        next_stream_event(&mut xtb).await,
+        Some(BrokerEvent::Candle(_))
+    ));
+
+    xtb.subscribe_tick_prices("EURUSD").await.unwrap();
+    match next_stream_event(&mut xtb).await {
+        Some(BrokerEvent::Tick(pricing)) => assert_eq!(pricing.symbol(), "EURUSD"),
+        event => panic!("Unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn streamed_and_historic_volume_share_the_scale() {
+    let mut xtb = connect().await;
+    let scale = xtb.volume_normalizer().scale("EURUSD");
+
+    let res = xtb.get_instrument_data("EURUSD", 1, 0).await.unwrap();
+    let (_date, _open, _high, _low, _close, volume) = res.payload.unwrap().data[0];
+    assert!((volume - scale).abs() < 1e-9);
+
+    xtb.subscribe_stream("EURUSD").await.unwrap();
+    next_stream_event(&mut xtb).await;
+    match next_stream_event(&mut xtb).await {
+        Some(BrokerEvent::Candle((_date, _open, _high, _low, _close, volume))) => {
+            assert!((volume - 3. * scale).abs() < 1e-9)
+        }
+        event => panic!("Unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn malformed_frames_are_skipped() {
+    let xtb = connect().await;
+    let frames = [
+        "not json",
+        r#"{"command":"candle","data":{"ctm":"soon","open":1.1}}"#,
+        r#"{"command":"tickPrices","data":{"symbol":"EURUSD"}}"#,
+    ];
+
+    for frame in frames {
+        let msg = Message::Text(frame.to_owned());
+        assert!(
+            Xtb::parse_stream_event(msg, xtb.symbol_mapper(), xtb.volume_normalizer()).is_none()
+        );
+    }
+    assert!(Xtb::parse_command_event("not json").is_none());
+}
+
+#[test]
+fn gaps_outside_market_hours_are_ignored() {
+    let hours = (1..=5)
+        .map(|day| MarketHour {
+            day,
+            from: 0,
+            to: 23,
+        })
+        .collect();
+    let market_hours = MarketHours::new(true, "EURUSD".to_owned(), hours);
+    let bar = |date: DateTime<Local>| (date, 1., 1., 1., 1., 1.);
+    let monday = Local.ymd(2023, 1, 9).and_hms(0, 0, 0);
+    let data = vec![
+        bar(Local.ymd(2023, 1, 6).and_hms(22, 0, 0)),
+        bar(Local.ymd(2023, 1, 6).and_hms(23, 0, 0)),
+        bar(monday),
+        bar(monday + Duration::hours(3)),
+    ];
+
+    let gaps = Xtb::find_gaps(&data, 60, &market_hours);
+
+    assert_eq!(gaps, vec![(monday, monday + Duration::hours(3))]);
+}
@@ -0,0 +1,58 @@
+use rs_algo_shared::helpers::date::*;
+use rs_algo_shared::patterns::zones::*;
+use rs_algo_shared::scanner::candle::Candle;
+
+// 2023-01-02 10:00:00 UTC
+const START: i64 = 1_672_653_600;
+
+fn candle(idx: i64, high: f64, low: f64) -> Candle {
+    std::env::set_var("CANDLE_TYPES", "false");
+
+    Candle::new()
+        .date(Local.timestamp(START + idx * 60, 0))
+        .open(low)
+        .high(high)
+        .low(low)
+        .close(high)
+        .volume(1.)
+        .is_closed(true)
+        .previous_candles(vec![])
+        .logarithmic(false)
+        .build()
+        .unwrap()
+}
+
+fn zones() -> Zones {
+    let maxima = vec![(2, 110.), (10, 110.2), (20, 109.9), (30, 120.)];
+    let minima = vec![(5, 100.), (15, 100.3), (25, 105.)];
+
+    Zones::with_config(ZonesConfig::default()).from_peaks(&maxima, &minima, &[], None)
+}
+
+#[test]
+fn clusters_peaks_into_zones() {
+    let zones = zones();
+
+    // Single touch zones at 105 and 120 are left out
+    assert_eq!(zones.zones().len(), 2);
+    assert_eq!(zones.nearest_zone_above(105.).unwrap().touches, 3);
+    assert_eq!(zones.nearest_zone_above(105.).unwrap().low, 109.9);
+    assert_eq!(zones.nearest_zone_below(105.).unwrap().high, 100.3);
+    assert!(zones.nearest_zone_above(111.).is_none());
+    assert!(zones.zone_at(110.).is_some());
+}
+
+#[test]
+fn candle_visits_add_touches() {
+    let mut zones = zones();
+
+    zones.next(&candle(31, 101., 99.), 31);
+    zones.next(&candle(32, 101.5, 99.5), 32);
+    zones.next(&candle(33, 104., 102.), 33);
+    zones.next(&candle(34, 100.5, 98.), 34);
+
+    let support = zones.nearest_zone_below(105.).unwrap();
+    assert_eq!(support.touches, 4);
+    assert_eq!(support.last_index, 34);
+    assert!(support.strength > zones.nearest_zone_above(105.).unwrap().strength);
+}